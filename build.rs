@@ -11,6 +11,7 @@ use minify_js::{minify, Session, TopLevelMode};
 use std::{
     fs::{read_dir, read_to_string, File, OpenOptions},
     io::{Read, Write},
+    process::Command,
 };
 
 // ------- Constants -------
@@ -23,6 +24,9 @@ const STYLE_FOLDERS: [&str; 2] = ["themes", "colorschemes"];
 const PACKAGE_ENVIRONMENT_VARIABLE: &str = "PKG_ENV";
 /// A constant for the `prod` value of the `pkg_env` environment variable.
 const PRODUCTION_PKG_ENV_VARIABLE_VALUE: &str = "prod";
+/// A constant for the name of the environment variable holding the git commit from which the
+/// server is built.
+const GIT_COMMIT_ENVIRONMENT_VARIABLE: &str = "WEBSURFX_GIT_COMMIT";
 
 /// A main function which minifies both css and js files using `lightningcss` and `minify_js` when
 /// the `PKG_ENV` environment and it is set to the value of `prod`.
@@ -32,6 +36,24 @@ const PRODUCTION_PKG_ENV_VARIABLE_VALUE: &str = "prod";
 /// This function returns the unit type when the minification process runs successfully otherwise
 /// it returns a standard error.
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The git commit is exposed through the `/api/v1/info` route, it can be provided through the
+    // environment when the server is not built from a git checkout (like in a docker build).
+    let git_commit = std::env::var(GIT_COMMIT_ENVIRONMENT_VARIABLE)
+        .ok()
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "--short", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+        })
+        .unwrap_or_default();
+    println!(
+        "cargo:rustc-env={GIT_COMMIT_ENVIRONMENT_VARIABLE}={}",
+        git_commit.trim()
+    );
+
     if let Ok(pkg_env_var) = std::env::var(PACKAGE_ENVIRONMENT_VARIABLE) {
         if pkg_env_var.to_lowercase() == PRODUCTION_PKG_ENV_VARIABLE_VALUE {
             // A for loop that loops over each file name containing in the `colorschemes` and `themes` folders
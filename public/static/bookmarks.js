@@ -0,0 +1,68 @@
+/**
+ * Selects the container of the search results of the search page
+ * @type {HTMLElement}
+ */
+const bookmarkedResults = document.querySelector('.results_aggregated')
+
+/**
+ * Checks whether the user has a server-side profile, in which the starred results are kept, from
+ * the `profile` flag of the settings cookie
+ * @returns {boolean} Whether the user has a server-side profile
+ */
+function hasProfile() {
+  const settings = document.cookie
+    .split(';')
+    .map((item) => item.trim().split('='))
+    .find(([name]) => name === 'appCookie')
+  try {
+    return settings !== undefined && JSON.parse(decodeURIComponent(settings[1])).profile === true
+  } catch {
+    return false
+  }
+}
+
+/**
+ * Stars a search result, which is added to the bookmarks of the user
+ * @param {HTMLElement} result - The search result to be starred
+ * @param {HTMLButtonElement} button - The button starring the search result
+ */
+async function starResult(result, button) {
+  const response = await fetch('bookmarks', {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({
+      url: result.dataset.url,
+      title: result.querySelector('h1 a').textContent,
+      description: result.querySelector('p')?.textContent ?? '',
+    }),
+  })
+  if (response.ok) {
+    button.textContent = '★'
+    button.classList.add('starred')
+    button.title = 'Starred, see the bookmarks page'
+  }
+}
+
+/**
+ * Adds the button starring the search result to each search result which does not have one yet
+ */
+function addStarButtons() {
+  for (const result of bookmarkedResults.querySelectorAll('.result')) {
+    if (result.querySelector('.bookmark')) {
+      continue
+    }
+    const button = document.createElement('button')
+    button.className = 'bookmark'
+    button.type = 'button'
+    button.title = 'Star this result'
+    button.textContent = '☆'
+    button.addEventListener('click', () => starResult(result, button))
+    result.querySelector('h1').appendChild(button)
+  }
+}
+
+// The streamed results are starred like the results rendered with the page.
+if (bookmarkedResults && hasProfile()) {
+  addStarButtons()
+  new MutationObserver(addStarButtons).observe(bookmarkedResults, { childList: true })
+}
@@ -0,0 +1,124 @@
+/**
+ * The round constants of the SHA-256 hash function
+ * @type {Uint32Array}
+ */
+const ROUND_CONSTANTS = new Uint32Array([
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1,
+    0x923f82a4, 0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3,
+    0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147,
+    0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+    0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208,
+    0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+])
+
+/**
+ * The number of the solutions tried before the page is given the chance to update
+ * @type {number}
+ */
+const SOLUTIONS_PER_STEP = 5000
+
+/**
+ * Rotates the bits of a 32 bits word to the right
+ * @param {number} word - The word to rotate
+ * @param {number} bits - The number of bits by which the word is rotated
+ * @returns {number} The rotated word
+ */
+function rotateRight(word, bits) {
+    return (word >>> bits) | (word << (32 - bits))
+}
+
+/**
+ * Computes the SHA-256 hash of a message, the hash is computed in javascript as the web crypto
+ * api is not available on the instances served over http
+ * @param {string} message - The message to hash
+ * @returns {Uint32Array} The eight 32 bits words of the hash
+ */
+function sha256(message) {
+    const bytes = new TextEncoder().encode(message)
+    const length = ((bytes.length + 72) >> 6) << 6
+    const padded = new Uint8Array(length)
+    padded.set(bytes)
+    padded[bytes.length] = 0x80
+    const view = new DataView(padded.buffer)
+    view.setUint32(length - 4, bytes.length * 8)
+
+    const hash = new Uint32Array([
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c,
+        0x1f83d9ab, 0x5be0cd19,
+    ])
+    const words = new Uint32Array(64)
+    for (let offset = 0; offset < length; offset += 64) {
+        for (let i = 0; i < 16; i++) {
+            words[i] = view.getUint32(offset + i * 4)
+        }
+        for (let i = 16; i < 64; i++) {
+            const s0 =
+                rotateRight(words[i - 15], 7) ^
+                rotateRight(words[i - 15], 18) ^
+                (words[i - 15] >>> 3)
+            const s1 =
+                rotateRight(words[i - 2], 17) ^
+                rotateRight(words[i - 2], 19) ^
+                (words[i - 2] >>> 10)
+            words[i] = words[i - 16] + s0 + words[i - 7] + s1
+        }
+
+        let [a, b, c, d, e, f, g, h] = hash
+        for (let i = 0; i < 64; i++) {
+            const t1 =
+                h +
+                (rotateRight(e, 6) ^ rotateRight(e, 11) ^ rotateRight(e, 25)) +
+                ((e & f) ^ (~e & g)) +
+                ROUND_CONSTANTS[i] +
+                words[i]
+            const t2 =
+                (rotateRight(a, 2) ^ rotateRight(a, 13) ^ rotateRight(a, 22)) +
+                ((a & b) ^ (a & c) ^ (b & c))
+            h = g
+            g = f
+            f = e
+            e = (d + t1) | 0
+            d = c
+            c = b
+            b = a
+            a = (t1 + t2) | 0
+        }
+        ;[a, b, c, d, e, f, g, h].forEach((word, i) => (hash[i] += word))
+    }
+    return hash
+}
+
+/**
+ * Searches the nonce solving the challenge of the page (the SHA-256 hash of `challenge:nonce`
+ * should start with the number of zero bits given by the difficulty) and then sends it to the
+ * server, which redirects back to the requested page
+ */
+function solveChallenge() {
+    const container = document.querySelector('.challenge_container')
+    const { challenge, difficulty, redirect } = container.dataset
+    const progress = container.querySelector('.challenge_progress')
+    let nonce = 0
+
+    const step = () => {
+        for (let i = 0; i < SOLUTIONS_PER_STEP; i++, nonce++) {
+            if (Math.clz32(sha256(`${challenge}:${nonce}`)[0]) >= difficulty) {
+                window.location.replace(
+                    `/challenge?challenge=${encodeURIComponent(
+                        challenge,
+                    )}&nonce=${nonce}&redirect=${encodeURIComponent(redirect)}`,
+                )
+                return
+            }
+        }
+        progress.textContent = `${nonce} solutions tried`
+        setTimeout(step, 0)
+    }
+    step()
+}
+
+solveChallenge()
@@ -30,8 +30,43 @@ function setClientSettingsOnPage(cookie) {
       case 'safe_search_levels':
         select_tag.value = cookie_value['safe_search_level']
         break
+      case 'languages':
+        select_tag.value = cookie_value['language'] || ''
+        break
+      case 'regions':
+        select_tag.value = cookie_value['region'] || ''
+        break
+      case 'time_ranges':
+        select_tag.value = cookie_value['time_range'] || ''
+        break
+      case 'highlights':
+        select_tag.value = cookie_value['highlight'] === false ? 'off' : 'on'
+        break
+      case 'results_per_page':
+        select_tag.value = cookie_value['results_per_page'] || ''
+        break
     }
   })
+  // Loop through all textarea tags and set their lines from the cookie
+  document.querySelectorAll('textarea').forEach((textarea_tag) => {
+    let value = cookie_value[textarea_tag.name] || []
+    textarea_tag.value = Array.isArray(value) ? value.join('\n') : value
+  })
+
+  // The search history can only be enabled when the settings are saved in a profile
+  let search_history = document.querySelector('input[name="search_history"]')
+  if (search_history) {
+    search_history.checked = cookie_value['search_history'] === true
+  }
+
+  // The engines selected for the other search categories than the general one
+  let category_engines = cookie_value['category_engines'] || {}
+  document.querySelectorAll('.category_engine').forEach((engine_checkbox) => {
+    engine_checkbox.checked = (
+      category_engines[engine_checkbox.dataset.category] || []
+    ).includes(engine_checkbox.value)
+  })
+
   let engines = document.querySelectorAll('.engine')
   let engines_cookie = cookie_value['engines']
 
@@ -71,6 +106,12 @@ document.addEventListener(
   'DOMContentLoaded',
   () => {
     try {
+      // The settings stored in the server-side profile of the user are provided by the page
+      let profile_settings = document.querySelector('.main_container').dataset
+        .settings
+      if (profile_settings) {
+        setClientSettingsOnPage(`appCookie=${profile_settings}`)
+      }
       // Decode the cookie value
       let cookie = decodeURIComponent(document.cookie)
       // Set the value of the input field to the decoded cookie value if it is not empty
@@ -78,7 +119,9 @@ document.addEventListener(
       if (cookie.length) {
         document.querySelector('.cookies input').value = cookie
         // This function displays the user provided settings on the settings page.
-        setClientSettingsOnPage(cookie)
+        if (!profile_settings) {
+          setClientSettingsOnPage(cookie)
+        }
       } else {
         document.querySelector('.cookies input').value =
           'No cookies have been saved on your system'
@@ -92,3 +135,21 @@ document.addEventListener(
   },
   false,
 )
+
+/**
+ * This function fills the settings to be imported from the selected exported settings file,
+ * which are then submitted with the import button of the cookies tab.
+ *
+ * @function
+ * @listens change
+ * @returns {void}
+ */
+document
+  .querySelector('.cookies .import_file')
+  ?.addEventListener('change', async (event) => {
+    let file = event.target.files[0]
+    if (file) {
+      document.querySelector('.cookies textarea[name="settings"]').value =
+        await file.text()
+    }
+  })
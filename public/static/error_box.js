@@ -5,3 +5,7 @@
 function toggleErrorBox() {
     document.querySelector('.dropdown_error_box').classList.toggle('show')
 }
+
+document
+    .querySelectorAll('.error_box_toggle_button')
+    .forEach((button) => button.addEventListener('click', toggleErrorBox))
@@ -5,7 +5,8 @@
 const searchBox = document.querySelector('input')
 
 /**
- * Redirects the user to the search results page with the query parameter
+ * Redirects the user to the search results page with the query parameter, the results are
+ * streamed to the page as the upstream search engines answer
  */
 function searchWeb() {
     const query = searchBox.value.trim()
@@ -14,21 +15,21 @@ function searchWeb() {
         if (query) {
             window.location.href = `search?q=${encodeURIComponent(
                 query,
-            )}&safesearch=${encodeURIComponent(safeSearchLevel)}`
+            )}&safesearch=${encodeURIComponent(safeSearchLevel)}&stream=true`
         }
     } catch (error) {
         if (query) {
-            window.location.href = `search?q=${encodeURIComponent(query)}`
+            window.location.href = `search?q=${encodeURIComponent(query)}&stream=true`
         }
     }
 }
 
 /**
- * Listens for the 'Enter' key press event on the search box and calls the searchWeb function
- * @param {KeyboardEvent} e - The keyboard event object
+ * Streams the search instead of sending the search form (which is only sent to the server when the
+ * scripts are disabled)
+ * @param {SubmitEvent} e - The submit event object
  */
-searchBox.addEventListener('keyup', (e) => {
-    if (e.key === 'Enter') {
-        searchWeb()
-    }
+document.querySelector('.search_bar').addEventListener('submit', (e) => {
+    e.preventDefault()
+    searchWeb()
 })
@@ -33,62 +33,6 @@ function setActiveTab(current_tab) {
     .classList.add('active')
 }
 
-/**
- * This function adds the functionality to save all the user selected preferences
- * to be saved in a cookie on the users machine.
- */
-function setClientSettings() {
-  // Create an object to store the user's preferences
-  let cookie_dictionary = new Object()
-
-  // Loop through all select tags and add their values to the cookie dictionary
-  document.querySelectorAll('select').forEach((select_tag) => {
-    switch (select_tag.name) {
-      case 'themes':
-        cookie_dictionary['theme'] = select_tag.value
-        break
-      case 'colorschemes':
-        cookie_dictionary['colorscheme'] = select_tag.value
-        break
-      case 'animations':
-        cookie_dictionary['animation'] = select_tag.value || null
-        break
-      case 'safe_search_levels':
-        cookie_dictionary['safe_search_level'] = Number(select_tag.value)
-        break
-    }
-  })
-
-  // Loop through all engine checkboxes and add their values to the cookie dictionary
-  let engines = []
-
-  document.querySelectorAll('.engine').forEach((engine_checkbox) => {
-    if (engine_checkbox.checked) {
-      engines.push(engine_checkbox.parentNode.parentNode.innerText.trim())
-    }
-  })
-
-  cookie_dictionary['engines'] = engines
-
-  // Set the expiration date for the cookie to 1 year from the current date
-  let expiration_date = new Date()
-  expiration_date.setFullYear(expiration_date.getFullYear() + 1)
-
-  // Save the cookie to the user's machine
-  document.cookie = `appCookie=${JSON.stringify(
-    cookie_dictionary,
-  )}; expires=${expiration_date.toUTCString()}`
-
-  // Display a success message to the user
-  document.querySelector('.message').innerText =
-    '✅ The settings have been saved sucessfully!!'
-
-  // Clear the success message after 10 seconds
-  setTimeout(() => {
-    document.querySelector('.message').innerText = ''
-  }, 10000)
-}
-
 /**
  * This functions gets the saved cookies if it is present on the user's machine If it
  * is available then it is parsed and converted to an object which is then used to
@@ -151,5 +95,26 @@ function getClientSettings() {
           )
       }
     }
+
+    // Add the custom stylesheet saved in the server-side profile of the user after the styles
+    // of the theme so that its rules take precedence.
+    if (cookie_value['stylesheet']) {
+      let stylesheet_link = document.createElement('link')
+      stylesheet_link.href = cookie_value['stylesheet']
+      stylesheet_link.rel = 'stylesheet'
+      stylesheet_link.type = 'text/css'
+      document.querySelector('head').appendChild(stylesheet_link)
+    }
   }
 }
+
+// The user's preferences are loaded once every page finishes loading.
+window.addEventListener('load', getClientSettings)
+
+// The controls of the settings page (this script is loaded by all the pages).
+document
+  .querySelectorAll('.settings .sidebar .btn')
+  .forEach((tab) => tab.addEventListener('click', () => setActiveTab(tab)))
+document
+  .querySelector('.settings .select_all')
+  ?.addEventListener('change', toggleAllSelection)
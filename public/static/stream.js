@@ -0,0 +1,150 @@
+/**
+ * Selects the container of the search results whose `data-stream` attribute holds the url from
+ * which the search results are streamed
+ * @type {HTMLElement}
+ */
+const resultsContainer = document.querySelector('.results_aggregated')
+
+/**
+ * Adds the rendered search results of an upstream search engine to the search page, the engines
+ * of a result which is already shown are added to it instead of showing the result twice
+ * @param {string} html - The rendered search results of the upstream search engine
+ */
+function addResults(html) {
+    const template = document.createElement('template')
+    template.innerHTML = html
+
+    for (const result of template.content.querySelectorAll('.result')) {
+        const shownResult = resultsContainer.querySelector(
+            `.result[data-url="${CSS.escape(result.dataset.url)}"]`,
+        )
+        if (!shownResult) {
+            resultsContainer.appendChild(result)
+            continue
+        }
+
+        const engines = shownResult.querySelector('.upstream_engines')
+        const score = engines.querySelector('.score')
+        for (const engine of result.querySelectorAll('.engine')) {
+            engines.insertBefore(engine, score)
+        }
+    }
+}
+
+/**
+ * Adds the errors of an upstream search engine to the error box of the search page
+ * @param {{engine: string, error: string, severity_color: string}[]} errors - The errors of the
+ * upstream search engine
+ */
+function addErrors(errors) {
+    if (errors.length === 0) {
+        return
+    }
+
+    const errorBox = document.querySelector('.dropdown_error_box')
+    errorBox.querySelector('.no_errors')?.remove()
+    document.querySelector('.error_box_toggle_button img').src = './images/warning.svg'
+
+    for (const { engine, error, severity_color } of errors) {
+        const item = document.createElement('div')
+        item.className = 'error_item'
+        for (const text of [engine, error]) {
+            const span = document.createElement('span')
+            span.className = 'engine_name'
+            span.textContent = text
+            item.appendChild(span)
+        }
+        const severity = document.createElement('span')
+        severity.className = 'severity_color'
+        severity.style.background = severity_color
+        item.appendChild(severity)
+        errorBox.appendChild(item)
+    }
+
+    const notice = document.querySelector('.engines_unavailable')
+    const engines = notice.textContent
+        .replace(/^Results from (.*) unavailable$/, '$1')
+        .split(', ')
+        .filter((engine) => engine !== '')
+    for (const { engine, error } of errors) {
+        if (error !== 'EmptyResultSet' && !engines.includes(engine)) {
+            engines.push(engine)
+        }
+    }
+    if (engines.length > 0) {
+        notice.textContent = `Results from ${engines.join(', ')} unavailable`
+        notice.hidden = false
+    }
+}
+
+/**
+ * Orders the streamed search results by their ranking among the results of all the upstream
+ * search engines, which is only known once all the engines have answered. The results which did
+ * not make it into the ranking are removed like they are left out of the search page.
+ * @param {{url: string, score: number}[]} ranking - The urls and the fused scores of the results in
+ * their ranked order
+ */
+function rankResults(ranking) {
+    const results = new Map(
+        Array.from(resultsContainer.querySelectorAll('.result'), (result) => [
+            result.dataset.url,
+            result,
+        ]),
+    )
+
+    for (const { url, score } of ranking) {
+        const result = results.get(url)
+        if (!result) {
+            continue
+        }
+        result.querySelector('.score').textContent = score.toFixed(4)
+        resultsContainer.appendChild(result)
+        results.delete(url)
+    }
+    for (const result of results.values()) {
+        result.remove()
+    }
+}
+
+/**
+ * Stops listening to the streamed search results and replaces the loading message with a notice
+ * if no results could be fetched
+ * @param {EventSource} source - The source of the streamed search results
+ */
+function finishStream(source) {
+    source.close()
+
+    const loading = resultsContainer.querySelector('.stream_loading')
+    if (!loading) {
+        return
+    }
+    if (resultsContainer.querySelector('.result')) {
+        loading.remove()
+    } else {
+        loading.textContent = 'Your search did not match any documents.'
+    }
+}
+
+/**
+ * Listens to the search results streamed by the server as the upstream search engines answer
+ */
+function streamResults() {
+    const source = new EventSource(resultsContainer.dataset.stream)
+
+    source.addEventListener('results', (event) => {
+        const { html, engineErrorsInfo } = JSON.parse(event.data)
+        addResults(html)
+        addErrors(engineErrorsInfo)
+        if (resultsContainer.querySelector('.result')) {
+            resultsContainer.querySelector('.stream_loading')?.remove()
+        }
+    })
+    source.addEventListener('done', (event) => {
+        rankResults(JSON.parse(event.data).ranking)
+        finishStream(source)
+    })
+    // The browser would otherwise reconnect and query the upstream search engines again.
+    source.addEventListener('error', () => finishStream(source))
+}
+
+streamResults()
@@ -5,8 +5,14 @@
 #[cfg(not(feature = "dhat-heap"))]
 use mimalloc::MiMalloc;
 
-use std::net::TcpListener;
-use websurfx::{cache::cacher::create_cache, config::parser::Config, run};
+use clap::Parser;
+use std::{fs, net::TcpListener, path::Path};
+use websurfx::{
+    cache::cacher::create_cache,
+    config::{generator::EXAMPLE_ENV_FILE, parser::Config},
+    handler::{set_config_directory, set_config_file_path},
+    run,
+};
 
 /// A dhat heap memory profiler
 #[cfg(feature = "dhat-heap")]
@@ -17,6 +23,55 @@ static ALLOC: dhat::Alloc = dhat::Alloc;
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
+/// A named struct which stores the parsed command line arguments provided to the application.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The path to the config file to use instead of looking it up on the default paths.
+    #[arg(short, long, value_name = "FILE")]
+    config: Option<String>,
+    /// The directory in which the config file and the filter lists should be looked up first.
+    #[arg(long, value_name = "DIR")]
+    config_dir: Option<String>,
+    /// The port on which the server should be launched (overrides the `port` config option).
+    #[arg(short, long)]
+    port: Option<u16>,
+    /// The ip address on which the server should be launched (overrides the `binding_ip` config
+    /// option).
+    #[arg(short, long, value_name = "IP")]
+    bind: Option<String>,
+    /// The path to the unix socket on which the server should be launched instead of the ip
+    /// address and the port (overrides the `unix_socket` config option).
+    #[arg(long, value_name = "PATH")]
+    unix_socket: Option<String>,
+    /// Print the default config file to the stdout and exit. If a directory is provided then the
+    /// default config file and an example environment file are written to it instead.
+    #[arg(long, value_name = "DIR", num_args = 0..=1, default_missing_value = "-")]
+    dump_default_config: Option<String>,
+    /// Parse the config file, report whether it is valid and exit.
+    #[arg(long)]
+    check_config: bool,
+}
+
+impl Cli {
+    /// A function which overrides the config options provided via the command line arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    fn apply_overrides(self, config: &mut Config) {
+        if let Some(port) = self.port {
+            config.port = port;
+        }
+        if let Some(binding_ip) = self.bind {
+            config.binding_ip = binding_ip;
+        }
+        if let Some(unix_socket) = self.unix_socket {
+            config.unix_socket = Some(unix_socket);
+        }
+    }
+}
+
 /// The function that launches the main server and registers all the routes of the website.
 ///
 /// # Error
@@ -29,18 +84,87 @@ async fn main() -> std::io::Result<()> {
     #[cfg(feature = "dhat-heap")]
     let _profiler = dhat::Profiler::new_heap();
 
+    let mut args = Cli::parse();
+
+    if let Some(directory) = args.dump_default_config.take() {
+        let default_config = Config::default().to_lua();
+        if directory == "-" {
+            print!("{default_config}");
+        } else {
+            let directory = Path::new(&directory);
+            fs::create_dir_all(directory)?;
+            fs::write(directory.join("config.lua"), default_config)?;
+            fs::write(directory.join("websurfx.env"), EXAMPLE_ENV_FILE)?;
+        }
+        return Ok(());
+    }
+
+    if let Some(config_directory) = args.config_dir.take() {
+        set_config_directory(config_directory)?;
+    }
+
+    if let Some(config_path) = args.config.take() {
+        set_config_file_path(config_path)?;
+    }
+
+    if args.check_config {
+        return match Config::parse(false) {
+            Ok(_) => {
+                println!("The config file is valid");
+                Ok(())
+            }
+            Err(error) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid config file: {error}"),
+            )),
+        };
+    }
+
     // Initialize the parsed config file.
-    let config = Config::parse(false).unwrap();
+    let mut config = Config::parse(false).map_err(|error| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid config file: {error}"),
+        )
+    })?;
+    args.apply_overrides(&mut config);
+    // The options overridden by the command line arguments are validated again.
+    config.validate().map_err(|error| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Invalid config: {error}"),
+        )
+    })?;
 
     let cache = create_cache(&config).await;
 
-    log::info!(
+    #[cfg(unix)]
+    if let Some(unix_socket) = config.unix_socket.clone() {
+        use std::os::unix::{fs::FileTypeExt, net::UnixListener};
+
+        tracing::info!("started server on the unix socket {unix_socket}");
+
+        // The socket file left behind by a previous run would prevent the binding.
+        if fs::symlink_metadata(&unix_socket).is_ok_and(|metadata| metadata.file_type().is_socket())
+        {
+            fs::remove_file(&unix_socket)?;
+        }
+        let listener = UnixListener::bind(&unix_socket)?;
+
+        return run(listener, config, cache)?.await;
+    }
+
+    tracing::info!(
         "started server on port {} and IP {}",
         config.port,
         config.binding_ip
     );
-    log::info!(
-        "Open http://{}:{}/ in your browser",
+    tracing::info!(
+        "Open {}://{}:{}/ in your browser",
+        match config.tls.certificate {
+            Some(_) => "https",
+            None => "http",
+        },
         config.binding_ip,
         config.port,
     );
@@ -49,3 +173,37 @@ async fn main() -> std::io::Result<()> {
 
     run(listener, config, cache)?.await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_overrides() {
+        let mut config = Config::default();
+        let (port, binding_ip) = (config.port, config.binding_ip.clone());
+
+        Cli::parse_from(["websurfx"]).apply_overrides(&mut config);
+        assert_eq!(
+            (config.port, config.binding_ip.as_str()),
+            (port, binding_ip.as_str())
+        );
+
+        Cli::parse_from(["websurfx", "--port", "9090", "-b", "0.0.0.0"])
+            .apply_overrides(&mut config);
+        assert_eq!((config.port, config.binding_ip.as_str()), (9090, "0.0.0.0"));
+    }
+
+    #[test]
+    fn test_cli_arguments() {
+        let args = Cli::parse_from(["websurfx", "--dump-default-config", "--check-config"]);
+        assert_eq!(args.dump_default_config.as_deref(), Some("-"));
+        assert!(args.check_config);
+
+        let args = Cli::parse_from(["websurfx", "-c", "/tmp/config.lua", "--config-dir", "/etc"]);
+        assert_eq!(args.config.as_deref(), Some("/tmp/config.lua"));
+        assert_eq!(args.config_dir.as_deref(), Some("/etc"));
+
+        assert!(Cli::try_parse_from(["websurfx", "--port", "not-a-port"]).is_err());
+    }
+}
@@ -7,9 +7,11 @@ use mini_moka::sync::Cache as MokaCache;
 #[cfg(feature = "memory-cache")]
 use mini_moka::sync::ConcurrentCacheExt;
 
-#[cfg(feature = "memory-cache")]
-use std::time::Duration;
-use tokio::sync::Mutex;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{watch, Mutex};
 
 use crate::{config::parser::Config, models::aggregation_models::SearchResults};
 
@@ -67,6 +69,43 @@ pub trait Cacher: Send + Sync {
         urls: &[String],
     ) -> Result<(), Report<CacheError>>;
 
+    /// A function which fetches the raw bytes (like the proxied favicons) cached with the
+    /// provided key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key of the cached bytes as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the cached bytes if the program executes normally otherwise returns a `CacheError`
+    /// if the bytes cannot be retrieved from the cache.
+    async fn cached_bytes(&mut self, key: &str) -> Result<Vec<u8>, Report<CacheError>>;
+
+    /// A function which caches the provided raw bytes (like the proxied favicons) with the
+    /// provided key.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key of the bytes as an argument.
+    /// * `bytes` - It takes the bytes to be cached as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns a unit type if the bytes are cached without a failure otherwise it returns a
+    /// `CacheError`.
+    async fn cache_bytes(&mut self, key: &str, bytes: &[u8]) -> Result<(), Report<CacheError>>;
+
+    /// A function which closes the connections of the cache backend, it is called when the server
+    /// shuts down once all the results being cached in the background are written.
+    async fn close(&mut self) {}
+
+    /// A function which checks whether the cache backend can be reached, the backends keeping the
+    /// data in the memory of the instance can always be reached.
+    async fn is_reachable(&mut self) -> bool {
+        true
+    }
+
     /// A helper function which computes the hash of the url and formats and returns it as string.
     ///
     /// # Arguments
@@ -206,12 +245,10 @@ pub trait Cacher: Send + Sync {
     }
 
     /// A helper function that compresses or encrypts search results before they're inserted into a cache store
-
+    ///
     /// # Arguments
     ///
     /// * `search_results` - A reference to the search_Results to process.
-    ///
-
     ///
     /// # Error
     /// Returns a Vec of compressed or encrypted bytes on success otherwise it returns a CacheError
@@ -244,17 +281,14 @@ pub trait Cacher: Send + Sync {
     }
 
     /// A helper function that decompresses or decrypts search results after they're fetched from the cache-store
-
+    ///
     /// # Arguments
     ///
     /// * `bytes` - A Vec of bytes stores in the cache.
-    ///
-
     ///
     /// # Error
     /// Returns the SearchResults struct on success otherwise it returns a CacheError
     /// on failure.
-
     #[allow(unused_mut)] // needs to be mutable when any of the features is enabled
     fn post_process_search_results(
         &mut self,
@@ -312,7 +346,7 @@ fn decompress_util(input: &[u8]) -> Result<Vec<u8>, Report<CacheError>> {
 #[async_trait::async_trait]
 impl Cacher for RedisCache {
     async fn build(config: &Config) -> Self {
-        log::info!(
+        tracing::info!(
             "Initialising redis cache. Listening to {}",
             &config.redis_url
         );
@@ -362,6 +396,33 @@ impl Cacher for RedisCache {
         self.cache_json(base64_strings, hashed_url_strings.into_iter())
             .await
     }
+
+    async fn cached_bytes(&mut self, key: &str) -> Result<Vec<u8>, Report<CacheError>> {
+        use base64::Engine;
+        let hashed_key: &str = &self.hash_url(key);
+        let base64_string = self.cached_json(hashed_key).await?;
+
+        base64::engine::general_purpose::STANDARD_NO_PAD
+            .decode(base64_string)
+            .map_err(|_| Report::new(CacheError::Base64DecodingOrEncodingError))
+    }
+
+    async fn cache_bytes(&mut self, key: &str, bytes: &[u8]) -> Result<(), Report<CacheError>> {
+        use base64::Engine;
+        let base64_string = base64::engine::general_purpose::STANDARD_NO_PAD.encode(bytes);
+        let hashed_key = self.hash_url(key);
+
+        self.cache_json(std::iter::once(base64_string), std::iter::once(hashed_key))
+            .await
+    }
+
+    async fn close(&mut self) {
+        self.close_connections();
+    }
+
+    async fn is_reachable(&mut self) -> bool {
+        self.ping().await
+    }
 }
 /// TryInto implementation for SearchResults from Vec<u8>
 use std::convert::TryInto;
@@ -382,6 +443,11 @@ impl TryInto<Vec<u8>> for &SearchResults {
     }
 }
 
+/// The maximum total size (in bytes) of the entries stored in the memory based cache backend, the
+/// least recently used entries are evicted once it is reached.
+#[cfg(feature = "memory-cache")]
+const MEMORY_CACHE_MAX_SIZE: u64 = 256 * 1024 * 1024;
+
 /// Memory based cache backend.
 #[cfg(feature = "memory-cache")]
 pub struct InMemoryCache {
@@ -393,10 +459,14 @@ pub struct InMemoryCache {
 #[async_trait::async_trait]
 impl Cacher for InMemoryCache {
     async fn build(config: &Config) -> Self {
-        log::info!("Initialising in-memory cache");
+        tracing::info!("Initialising in-memory cache");
 
         InMemoryCache {
             cache: MokaCache::builder()
+                .weigher(|key: &String, value: &Vec<u8>| {
+                    (key.len() + value.len()).try_into().unwrap_or(u32::MAX)
+                })
+                .max_capacity(MEMORY_CACHE_MAX_SIZE)
                 .time_to_live(Duration::from_secs(config.cache_expiry_time.into()))
                 .build(),
         }
@@ -424,6 +494,18 @@ impl Cacher for InMemoryCache {
         self.cache.sync();
         Ok(())
     }
+
+    async fn cached_bytes(&mut self, key: &str) -> Result<Vec<u8>, Report<CacheError>> {
+        self.cache
+            .get(&self.hash_url(key))
+            .ok_or_else(|| Report::new(CacheError::MissingValue))
+    }
+
+    async fn cache_bytes(&mut self, key: &str, bytes: &[u8]) -> Result<(), Report<CacheError>> {
+        self.cache.insert(self.hash_url(key), bytes.to_vec());
+        self.cache.sync();
+        Ok(())
+    }
 }
 
 /// Cache backend which utilises both memory and redis based caches.
@@ -443,7 +525,7 @@ pub struct HybridCache {
 #[async_trait::async_trait]
 impl Cacher for HybridCache {
     async fn build(config: &Config) -> Self {
-        log::info!("Initialising hybrid cache");
+        tracing::info!("Initialising hybrid cache");
         HybridCache {
             memory_cache: InMemoryCache::build(config).await,
             redis_cache: RedisCache::build(config).await,
@@ -469,6 +551,24 @@ impl Cacher for HybridCache {
 
         Ok(())
     }
+
+    async fn cached_bytes(&mut self, key: &str) -> Result<Vec<u8>, Report<CacheError>> {
+        match self.redis_cache.cached_bytes(key).await {
+            Ok(bytes) => Ok(bytes),
+            Err(_) => self.memory_cache.cached_bytes(key).await,
+        }
+    }
+
+    async fn cache_bytes(&mut self, key: &str, bytes: &[u8]) -> Result<(), Report<CacheError>> {
+        self.redis_cache.cache_bytes(key, bytes).await?;
+        self.memory_cache.cache_bytes(key, bytes).await?;
+
+        Ok(())
+    }
+
+    async fn close(&mut self) {
+        self.redis_cache.close().await;
+    }
 }
 
 /// Dummy cache backend
@@ -477,7 +577,7 @@ pub struct DisabledCache;
 #[async_trait::async_trait]
 impl Cacher for DisabledCache {
     async fn build(_config: &Config) -> Self {
-        log::info!("Caching is disabled");
+        tracing::info!("Caching is disabled");
         DisabledCache
     }
 
@@ -492,12 +592,22 @@ impl Cacher for DisabledCache {
     ) -> Result<(), Report<CacheError>> {
         Ok(())
     }
+
+    async fn cached_bytes(&mut self, _key: &str) -> Result<Vec<u8>, Report<CacheError>> {
+        Err(Report::new(CacheError::MissingValue))
+    }
+
+    async fn cache_bytes(&mut self, _key: &str, _bytes: &[u8]) -> Result<(), Report<CacheError>> {
+        Ok(())
+    }
 }
 
 /// A structure to efficiently share the cache between threads - as it is protected by a Mutex.
 pub struct SharedCache {
     /// The internal cache protected from concurrent access by a mutex
     cache: Mutex<Box<dyn Cacher>>,
+    /// The number of the results being cached in the background.
+    pending_writes: watch::Sender<usize>,
 }
 
 impl SharedCache {
@@ -511,6 +621,7 @@ impl SharedCache {
     pub fn new(cache: impl Cacher + 'static) -> Self {
         Self {
             cache: Mutex::new(Box::new(cache)),
+            pending_writes: watch::channel(0).0,
         }
     }
 
@@ -519,7 +630,7 @@ impl SharedCache {
     /// # Arguments
     ///
     /// * `url` - It takes the search url as an argument which will be used as the key to fetch the
-    /// cached results from the cache.
+    ///   cached results from the cache.
     ///
     /// # Error
     ///
@@ -536,9 +647,9 @@ impl SharedCache {
     /// # Arguments
     ///
     /// * `search_results` - It takes the `SearchResults` as an argument which are results that
-    /// needs to be cached.
+    ///   needs to be cached.
     /// * `url` - It takes the search url as an argument which will be used as the key for storing
-    /// results in the cache.
+    ///   results in the cache.
     ///
     /// # Error
     ///
@@ -552,6 +663,121 @@ impl SharedCache {
         let mut mut_cache = self.cache.lock().await;
         mut_cache.cache_results(search_results, urls).await
     }
+
+    /// A function which caches the results in the background, so that the response is not delayed
+    /// by the cache. The results being cached are waited for by `close` when the server shuts
+    /// down.
+    ///
+    /// # Arguments
+    ///
+    /// * `search_results` - It takes the `SearchResults` which needs to be cached as an argument.
+    /// * `urls` - It takes the search urls which will be used as the keys for storing the results
+    ///   in the cache as an argument.
+    pub fn cache_results_in_background(
+        self: Arc<Self>,
+        search_results: Vec<SearchResults>,
+        urls: Vec<String>,
+    ) {
+        self.pending_writes.send_modify(|writes| *writes += 1);
+        tokio::spawn(async move {
+            let _ = self.cache_results(&search_results, &urls).await;
+            self.pending_writes.send_modify(|writes| *writes -= 1);
+        });
+    }
+
+    /// A function which waits for the results being cached in the background and closes the
+    /// connections of the cache backend, it is called when the server shuts down.
+    pub async fn close(&self) {
+        let _ = self
+            .pending_writes
+            .subscribe()
+            .wait_for(|writes| *writes == 0)
+            .await;
+        self.cache.lock().await.close().await;
+    }
+
+    /// A function which checks whether the cache backend can be reached.
+    pub async fn is_reachable(&self) -> bool {
+        self.cache.lock().await.is_reachable().await
+    }
+
+    /// A getter function which retrieves the raw bytes cached with the provided key from the
+    /// internal cache. The bytes whose expiry time has passed are treated as missing.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key of the cached bytes as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the cached bytes if nothing goes wrong otherwise returns a `CacheError`.
+    pub async fn cached_bytes(&self, key: &str) -> Result<Vec<u8>, Report<CacheError>> {
+        let mut mut_cache = self.cache.lock().await;
+        let bytes = mut_cache.cached_bytes(key).await?;
+        strip_expiry(bytes, unix_time()).ok_or_else(|| Report::new(CacheError::MissingValue))
+    }
+
+    /// A setter function which caches the provided raw bytes with the provided key. The bytes
+    /// expire after the provided time to live or after the expiry time of the cache backend,
+    /// whichever comes first.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key of the bytes as an argument.
+    /// * `bytes` - It takes the bytes to be cached as an argument.
+    /// * `ttl` - It takes the time after which the cached bytes expire as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an unit type if the bytes are cached succesfully otherwise returns a `CacheError`
+    /// on a failure.
+    pub async fn cache_bytes(
+        &self,
+        key: &str,
+        bytes: &[u8],
+        ttl: Duration,
+    ) -> Result<(), Report<CacheError>> {
+        let mut mut_cache = self.cache.lock().await;
+        mut_cache
+            .cache_bytes(key, &with_expiry(bytes, unix_time() + ttl.as_secs()))
+            .await
+    }
+}
+
+/// A helper function which returns the current unix time in seconds.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// A helper function which prefixes the provided bytes with their expiry time, so that the bytes
+/// can expire independently of the expiry time of the cache backend.
+///
+/// # Arguments
+///
+/// * `bytes` - It takes the bytes to be cached as an argument.
+/// * `expires_at` - It takes the unix time (in seconds) at which the bytes expire as an argument.
+fn with_expiry(bytes: &[u8], expires_at: u64) -> Vec<u8> {
+    let mut prefixed_bytes = Vec::with_capacity(bytes.len() + 8);
+    prefixed_bytes.extend_from_slice(&expires_at.to_be_bytes());
+    prefixed_bytes.extend_from_slice(bytes);
+    prefixed_bytes
+}
+
+/// A helper function which strips the expiry time prefixed by `with_expiry` from the cached bytes.
+///
+/// # Arguments
+///
+/// * `bytes` - It takes the cached bytes as an argument.
+/// * `now` - It takes the current unix time (in seconds) as an argument.
+///
+/// # Returns
+///
+/// It returns the cached bytes without the prefix or `None` if they have expired.
+fn strip_expiry(mut bytes: Vec<u8>, now: u64) -> Option<Vec<u8>> {
+    let expires_at = u64::from_be_bytes(bytes.get(..8)?.try_into().ok()?);
+    (now < expires_at).then(|| bytes.split_off(8))
 }
 
 /// A function to initialise the cache backend.
@@ -570,3 +796,35 @@ pub async fn create_cache(config: &Config) -> impl Cacher {
 }
 
 //#[cfg(feature = "Compress-cache-results")]
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_bytes_expiry() {
+        let bytes = with_expiry(b"favicon", 100);
+
+        assert_eq!(strip_expiry(bytes.clone(), 99), Some(b"favicon".to_vec()));
+        assert_eq!(strip_expiry(bytes, 100), None);
+        assert_eq!(strip_expiry(with_expiry(b"", 100), 50), Some(Vec::new()));
+        assert_eq!(strip_expiry(b"short".to_vec(), 0), None);
+    }
+
+    #[cfg(feature = "memory-cache")]
+    #[tokio::test]
+    async fn test_close_waits_for_the_background_writes() {
+        let cache = Arc::new(SharedCache::new(
+            InMemoryCache::build(&Config::default()).await,
+        ));
+        let key = "http://127.0.0.1:8080/search?q=rust&page=0".to_owned();
+
+        cache
+            .clone()
+            .cache_results_in_background(vec![SearchResults::default()], vec![key.clone()]);
+        cache.close().await;
+
+        assert_eq!(*cache.pending_writes.borrow(), 0);
+        assert!(cache.cached_results(&key).await.is_ok());
+    }
+}
@@ -56,6 +56,27 @@ impl RedisCache {
         Ok(redis_cache)
     }
 
+    /// A function which drops the connections of the pool, so that they are closed once the
+    /// requests sent through them are answered. The cache should not be used afterwards.
+    pub fn close_connections(&mut self) {
+        self.connection_pool.clear();
+    }
+
+    /// A function which checks whether the redis server answers through one of the connections of
+    /// the pool.
+    pub async fn ping(&mut self) -> bool {
+        for connection in &mut self.connection_pool {
+            if redis::cmd("PING")
+                .query_async::<_, String>(connection)
+                .await
+                .is_ok()
+            {
+                return true;
+            }
+        }
+        false
+    }
+
     /// A function which fetches the cached json as json string from the redis server.
     ///
     /// # Arguments
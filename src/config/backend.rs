@@ -0,0 +1,139 @@
+//! This module provides the different config backends (file formats) which can be used to write
+//! the config file. Every backend loads the config options into the globals of a lua state so
+//! that all the backends share the same option parsing and validation code.
+
+use mlua::{Lua, Value};
+use std::path::Path;
+
+/// An enum type which provides different variants for the supported config file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// This variant handles the lua config files, which allow the config options to be computed
+    /// with lua code by advanced users.
+    Lua,
+    /// This variant handles the plain toml config files.
+    Toml,
+}
+
+impl ConfigFormat {
+    /// A function which picks the config format based on the file extension of the provided path.
+    /// Files with the `.toml` extension are parsed as toml and all other files are parsed as lua.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - It takes the path to the config file as an argument.
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Lua,
+        }
+    }
+
+    /// A function which loads the options from the provided config code into the globals of the
+    /// provided lua state.
+    ///
+    /// # Arguments
+    ///
+    /// * `lua` - It takes the lua state into which the options should be loaded as an argument.
+    /// * `config_code` - It takes the config code written in this format as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns a lua or toml parse error if the config code has a syntax error.
+    pub fn load(&self, lua: &Lua, config_code: &str) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            ConfigFormat::Lua => lua.load(config_code).exec()?,
+            ConfigFormat::Toml => {
+                let globals = lua.globals();
+                for (key, value) in config_code.parse::<toml::Table>()? {
+                    globals.set(key, toml_to_lua(lua, value)?)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A helper function which converts the provided toml value into the equivalent lua value. Dates
+/// and times are converted into strings as lua has no equivalent type.
+///
+/// # Arguments
+///
+/// * `lua` - It takes the lua state in which the value should be created as an argument.
+/// * `value` - It takes the toml value to be converted as an argument.
+///
+/// # Error
+///
+/// Returns a lua error if the lua value could not be allocated.
+fn toml_to_lua(lua: &Lua, value: toml::Value) -> mlua::Result<Value<'_>> {
+    Ok(match value {
+        toml::Value::String(string) => Value::String(lua.create_string(&string)?),
+        toml::Value::Integer(integer) => Value::Integer(integer),
+        toml::Value::Float(float) => Value::Number(float),
+        toml::Value::Boolean(boolean) => Value::Boolean(boolean),
+        toml::Value::Datetime(datetime) => Value::String(lua.create_string(datetime.to_string())?),
+        toml::Value::Array(array) => Value::Table(
+            lua.create_sequence_from(
+                array
+                    .into_iter()
+                    .map(|value| toml_to_lua(lua, value))
+                    .collect::<mlua::Result<Vec<Value<'_>>>>()?,
+            )?,
+        ),
+        toml::Value::Table(table) => {
+            let lua_table = lua.create_table()?;
+            for (key, value) in table {
+                lua_table.set(key, toml_to_lua(lua, value)?)?;
+            }
+            Value::Table(lua_table)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_format_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path("websurfx/websurfx.toml"),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path("websurfx/config.lua"),
+            ConfigFormat::Lua
+        );
+        assert_eq!(ConfigFormat::from_path("config"), ConfigFormat::Lua);
+    }
+
+    #[test]
+    fn test_toml_config_loaded_into_lua_globals() -> Result<(), Box<dyn std::error::Error>> {
+        let lua = Lua::new();
+        ConfigFormat::Toml.load(
+            &lua,
+            r#"
+port = 8080
+binding_ip = "127.0.0.1"
+
+[upstream_search_engines]
+DuckDuckGo = true
+Bing = { enabled = true, weight = 1.5 }
+"#,
+        )?;
+
+        let globals = lua.globals();
+        assert_eq!(globals.get::<_, u16>("port")?, 8080);
+        assert_eq!(globals.get::<_, String>("binding_ip")?, "127.0.0.1");
+
+        let engines: mlua::Table<'_> = globals.get("upstream_search_engines")?;
+        assert!(engines.get::<_, bool>("DuckDuckGo")?);
+        let bing: mlua::Table<'_> = engines.get("Bing")?;
+        assert_eq!(bing.get::<_, f32>("weight")?, 1.5);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,870 @@
+//! This module provides the functionality to generate a fully commented lua config file from the
+//! `Config` struct, so that the default config file never drifts from the options which are
+//! actually parsed by the server.
+
+use super::parser::{
+    Config, DEFAULT_CLIENT_REQUEST_TIMEOUT, DEFAULT_KEEP_ALIVE, DEFAULT_MAX_CONNECTIONS,
+    DEFAULT_RESULTS_PER_PAGE,
+};
+use crate::engines::reverse_image::ReverseImageEngine;
+use crate::models::parser_models::{
+    AdminConfig, AggregatorConfig, ApiConfig, ApiKey, BotChallengeConfig, EngineConfig,
+    HealthMonitorConfig, LockedSetting, LogFormat, OtlpConfig, PrivacyConfig, ProfilesConfig,
+    RateLimiter, RequestLimits, SafeSearchLevel, SearchRateLimiter, SecurityHeaders, Style,
+    StyleCatalog, TlsConfig, TorConfig, UsageStatsConfig,
+};
+use crate::results::blocklists::DEFAULT_BLOCKLIST_REFRESH_INTERVAL;
+use crate::results::filters::BUILTIN_RESULT_FILTERS;
+use crate::server::routes::proxy::DEFAULT_FAVICON_SERVICE;
+use crate::server::shutdown::DEFAULT_SHUTDOWN_TIMEOUT;
+use std::collections::HashMap;
+
+/// The example environment file which lists the environment variables read by the server.
+pub const EXAMPLE_ENV_FILE: &str = r#"# The environment in which the server runs, set it to `dev` to enable trace logs or to `prod`
+# to minify the css and js files when building the server.
+PKG_ENV=prod
+"#;
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            port: 8080,
+            binding_ip: "127.0.0.1".to_owned(),
+            unix_socket: None,
+            style: Style::new(
+                "simple".to_owned(),
+                "catppuccin-mocha".to_owned(),
+                Some("simple-frosted-glow".to_owned()),
+            ),
+            styles: StyleCatalog::scan(),
+            #[cfg(feature = "redis-cache")]
+            redis_url: "redis://127.0.0.1:8082".to_owned(),
+            #[cfg(any(feature = "redis-cache", feature = "memory-cache"))]
+            cache_expiry_time: 600,
+            aggregator: AggregatorConfig::default(),
+            logging: true,
+            debug: false,
+            log_level: None,
+            log_format: LogFormat::default(),
+            otlp: OtlpConfig::default(),
+            adaptive_window: false,
+            upstream_search_engines: HashMap::from(
+                [
+                    ("DuckDuckGo", true),
+                    ("Searx", false),
+                    ("Brave", false),
+                    ("Startpage", false),
+                    ("LibreX", false),
+                    ("Mojeek", false),
+                    ("Bing", false),
+                ]
+                .map(|(engine, enabled)| {
+                    (
+                        engine.to_owned(),
+                        EngineConfig {
+                            enabled,
+                            ..Default::default()
+                        },
+                    )
+                }),
+            ),
+            request_timeout: 30,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            keep_alive: DEFAULT_KEEP_ALIVE,
+            client_request_timeout: DEFAULT_CLIENT_REQUEST_TIMEOUT,
+            connect_timeout: None,
+            proxy: None,
+            dns_over_https: None,
+            user_agents: Vec::new(),
+            randomize_headers: false,
+            metrics: false,
+            secret_key: None,
+            threads: 10,
+            rate_limiter: RateLimiter {
+                number_of_requests: 20,
+                time_limit: 3,
+            },
+            search_rate_limiter: SearchRateLimiter::default(),
+            bot_challenge: BotChallengeConfig::default(),
+            trusted_proxies: Vec::new(),
+            safe_search: 1,
+            results_per_page: DEFAULT_RESULTS_PER_PAGE,
+            locked_settings: Vec::new(),
+            disabled_categories: Vec::new(),
+            request_limits: RequestLimits::default(),
+            category_engines: HashMap::new(),
+            reverse_image_engines: ReverseImageEngine::ALL
+                .map(|engine| engine.name().to_owned())
+                .to_vec(),
+            engine_scripts: Vec::new(),
+            engine_definitions: None,
+            admin: AdminConfig::default(),
+            api: ApiConfig::default(),
+            tls: TlsConfig::default(),
+            tor: TorConfig::default(),
+            privacy: PrivacyConfig::default(),
+            usage_stats: UsageStatsConfig::default(),
+            profiles: ProfilesConfig::default(),
+            health_monitor: HealthMonitorConfig::default(),
+            security_headers: SecurityHeaders::default(),
+            bangs: HashMap::new(),
+            blocked_domains: Vec::new(),
+            allowed_domains: Vec::new(),
+            blocklist_subscriptions: Vec::new(),
+            blocklist_refresh_interval: DEFAULT_BLOCKLIST_REFRESH_INTERVAL,
+            url_rewrites: HashMap::new(),
+            resolve_amp_urls: false,
+            result_filters: BUILTIN_RESULT_FILTERS.map(str::to_owned).to_vec(),
+            domain_weights: HashMap::new(),
+            favicon_service: Some(DEFAULT_FAVICON_SERVICE.to_owned()),
+            anonymous_redirects: false,
+            onion_address: None,
+        }
+    }
+}
+
+impl Config {
+    /// A function which generates a fully commented lua config file from the config options.
+    ///
+    /// # Returns
+    ///
+    /// It returns the generated lua config code which when parsed results in the same config.
+    pub fn to_lua(&self) -> String {
+        let mut lua = format!(
+            r#"-- ### General ###
+logging = {logging} -- an option to enable or disable logs.
+debug = {debug} -- an option to enable or disable debug mode.
+log_level = {log_level} -- the level of the logs (like `"warn"`) or the filter directives per module (like `"websurfx=debug,actix_web=warn"`) or `nil` to derive it from the `logging` and `debug` options (the `RUST_LOG` environment variable takes precedence).
+log_format = {log_format} -- the format of the logs, `"text"` for human readable lines or `"json"` for one json object per line.
+otlp = {{
+	endpoint = {otlp_endpoint}, -- The OTLP/HTTP endpoint of the OpenTelemetry collector (like `"http://127.0.0.1:4318"`) to which the spans of the requests are exported or `nil` to not export them (needs the `otlp` feature).
+	service_name = {otlp_service_name}, -- The name of the service under which the spans are exported.
+	sample_ratio = {otlp_sample_ratio}, -- The ratio (between `0` and `1`) of the requests whose spans are exported.
+}}
+threads = {threads} -- the amount of threads that the app will use to run (the value should be greater than 0).
+
+-- ### Server ###
+port = {port} -- port on which server should be launched
+binding_ip = {binding_ip} --ip address on the which server should be launched.
+unix_socket = {unix_socket} -- the path to the unix socket (like `"/run/websurfx/websurfx.sock"`) on which the server should be launched instead of the `binding_ip` and the `port` or `nil` to use them.
+production_use = {production_use} -- whether to use production mode or not (in other words this option should be used if it is to be used to host it on the server to provide a service to a large number of users (more than one))
+-- if production_use is set to true
+-- There will be a random delay before sending the request to the search engines, this is to prevent DDoSing the upstream search engines from a large number of simultaneous requests.
+request_timeout = {request_timeout} -- timeout for the search requests sent to the upstream search engines to be fetched (value in seconds).
+shutdown_timeout = {shutdown_timeout} -- the time given to the in-flight requests to complete when the server receives a `SIGTERM` or `SIGINT` signal (value in seconds).
+max_connections = {max_connections} -- the number of the connections each thread accepts at once (the value should be greater than 0).
+keep_alive = {keep_alive} -- the time during which the idle connections of the clients are kept open (value in seconds, `0` to close the connections after each request).
+client_request_timeout = {client_request_timeout} -- the time within which the clients should send the headers of their requests (value in seconds, `0` to never time out).
+connect_timeout = {connect_timeout} -- the time within which the connections to the upstream search engines should be established (value in seconds) or `nil` to only use the `request_timeout`.
+proxy = {proxy} -- the http(s) or socks5 proxy (like `"socks5h://127.0.0.1:9050"`) through which the requests to the upstream search engines are sent or `nil` to send them directly.
+dns_over_https = {dns_over_https} -- the DNS-over-HTTPS server (like `"https://cloudflare-dns.com/dns-query"`) through which the domain names of the upstream search engines are resolved or `nil` to use the resolver of the host.
+user_agents = {user_agents} -- the user agents picked at random for each request to the upstream search engines (they are generated when it is empty).
+randomize_headers = {randomize_headers} -- randomize the `Accept` and `Accept-Language` headers and the order of the headers sent to the upstream search engines.
+metrics = {metrics} -- export the metrics of the instance (the requests per route, the aggregation durations, the cache hits and the responses of the upstream search engines) in the Prometheus format on the `/metrics` route.
+secret_key = {secret_key} -- the secret from which the key signing the urls of the proxied images is derived (use `secret_key_file` to read it from a file, a random key is generated on startup when it is `nil`).
+rate_limiter = {{
+	number_of_requests = {number_of_requests}, -- The number of request that are allowed within a provided time limit.
+	time_limit = {time_limit}, -- The time limit in which the quantity of requests that should be accepted.
+}}
+search_rate_limiter = {{
+	burst = {search_burst}, -- The number of searches a client can send at once before it is rate limited.
+	per_minute = {search_per_minute}, -- The number of searches per minute a client can send in a sustained way.
+	redis = {search_rate_limiter_redis}, -- Track the rate of the clients in the redis server, so that it is shared by the replicas of the instance (needs the `redis-cache` feature).
+}}
+bot_challenge = {{
+	enabled = {bot_challenge_enabled}, -- Make the clients looking like bots (searching in bursts or without the headers sent by the browsers) solve a proof-of-work challenge before searching.
+	difficulty = {bot_challenge_difficulty}, -- The number of leading zero bits of the hash of a solved challenge (each additional bit doubles the work of the clients).
+	burst = {bot_challenge_burst}, -- The number of searches a client can send at once before it is challenged.
+	per_minute = {bot_challenge_per_minute}, -- The number of searches per minute a client can send in a sustained way before it is challenged.
+	validity = {bot_challenge_validity}, -- The number of seconds during which a client which solved a challenge is not challenged again.
+}}
+trusted_proxies = {trusted_proxies} -- the ip addresses or networks (like `"10.0.0.0/8"`) of the reverse proxies from which the client addresses forwarded in the `Forwarded` or `X-Forwarded-For` headers are trusted for the rate limiting.
+-- Set whether the server will use an adaptive/dynamic HTTPS window size, see https://httpwg.org/specs/rfc9113.html#fc-principles
+adaptive_window = {adaptive_window}
+request_limits = {{
+	max_query_length = {max_query_length}, -- The maximum number of characters allowed in the search query.
+	max_page = {max_page}, -- The maximum page number which can be requested.
+	max_results_per_page = {max_results_per_page}, -- The maximum number of results displayed on a single page.
+	max_query_string_length = {max_query_string_length}, -- The maximum length (in bytes) of the query string of a request.
+	max_cookie_size = {max_cookie_size}, -- The maximum size (in bytes) of the cookies sent with a request.
+	max_body_size = {max_body_size}, -- The maximum size (in bytes) of the body of a request.
+}}
+admin = {{
+	token = {admin_token}, -- The token which authenticates the requests to the admin api (the admin api is disabled when it is `nil`, use `token_file` to read it from a file).
+	state_file = {admin_state_file}, -- The file in which the changes made through the admin api are persisted (`nil` to use `$XDG_STATE_HOME/websurfx/state.json`).
+}}
+api = {{
+	keys = {api_keys}, -- The keys which give access to the json api under `/api/v1` (like `{{ {{ name = "alice", key = "<secret>", burst = 10, per_minute = 60 }} }}`, use `key_file` to read a key from a file, the json api is disabled when it is empty).
+	redis = {api_redis}, -- Track the quotas and the usage counters of the keys in the redis server, so that they are shared by the replicas of the instance (needs the `redis-cache` feature).
+}}
+tls = {{
+	certificate = {tls_certificate}, -- The PEM file containing the certificate chain with which the instance is served over https (`nil` to serve it over http).
+	private_key = {tls_private_key}, -- The PEM file containing the private key of the certificate.
+}}
+tor = {{
+	enabled = {tor_enabled}, -- Route the requests to the upstream search engines through Tor (the engines with their own `proxy` option are not routed through Tor).
+	socks_url = {tor_socks_url}, -- The url of the socks port of the Tor daemon.
+	isolate_streams = {tor_isolate_streams}, -- Send the concurrent searches through separate Tor circuits.
+	control_address = {tor_control_address}, -- The address of the control port (like `"127.0.0.1:9051"`) used to request new circuits when the engines block the requests, or `nil`.
+	control_password = {tor_control_password}, -- The password of the control port (use `control_password_file` to read it from a file).
+	newnym_after_blocks = {tor_newnym_after_blocks}, -- The number of consecutive blocked requests after which new circuits are requested (`0` to never request them).
+}}
+privacy = {{
+	no_query_logging = {no_query_logging}, -- Replace the search queries with their hashes wherever they would be written to the logs.
+}}
+usage_stats = {{
+	enabled = {usage_stats_enabled}, -- Count the searches per day along with the search categories and the upstream search engines used (the queries and the ip addresses are never stored), shown through the admin api.
+	public = {usage_stats_public}, -- Show the usage statistics on the public engine status page.
+	redis = {usage_stats_redis}, -- Track the usage statistics in the redis server, so that they are shared by the replicas of the instance (needs the `redis-cache` feature).
+	retention_days = {usage_stats_retention_days}, -- The number of days for which the usage statistics are kept.
+}}
+profiles = {{
+	enabled = {profiles_enabled}, -- Allow the users to save their settings in a server-side profile restored on other browsers with a restore code.
+	redis = {profiles_redis}, -- Store the profiles in the redis server, so that they are shared by the replicas of the instance (needs the `redis-cache` feature).
+	expiry_days = {profiles_expiry_days}, -- The number of days after which an unused profile is deleted.
+	max_size = {profiles_max_size}, -- The maximum size (in bytes) of the settings stored in a profile.
+	history = {profiles_history}, -- Allow the users of the profiles to opt into keeping their search history in their profile.
+	max_history = {profiles_max_history}, -- The maximum number of searches kept in the search history of a profile.
+}}
+security_headers = {{
+	content_security_policy = {content_security_policy}, -- The `Content-Security-Policy` header (`{{nonce}}` is replaced with the nonce generated for each response, `""` to not send it).
+	referrer_policy = {referrer_policy}, -- The `Referrer-Policy` header (`""` to not send it).
+	permissions_policy = {permissions_policy}, -- The `Permissions-Policy` header (`""` to not send it).
+	hsts_max_age = {hsts_max_age}, -- The max age of the `Strict-Transport-Security` header (value in seconds, like `31536000`) or `nil` to not send it (it should only be set when the instance is served over https).
+}}
+
+-- ### Search ###
+-- Filter results based on different levels. The levels provided are:
+-- {{{{
+-- 0 - Off ("off")
+-- 1 - Moderate ("moderate")
+-- 2 - Strict ("strict")
+-- 3 - Strict with the filter lists
+-- 4 - Strict with the filter lists and blocking of disallowed queries
+-- }}}}
+safe_search = {safe_search}
+results_per_page = {results_per_page} -- The default number of results shown on each page of the search page (up to `max_results_per_page`), which the users can change on the settings page.
+locked_settings = {locked_settings} -- The settings which the users cannot override with their cookie, the search parameters or the settings page (`safe_search`, `engines`, `language`, `region` and `time_range` can be locked), like `{{ "safe_search" }}` on a family-friendly instance.
+disabled_categories = {disabled_categories} -- The search categories (like `images` or `videos`) which cannot be searched on the instance.
+
+-- ### Website ###
+colorscheme = {colorscheme} -- the colorscheme name which should be used for the website theme (the name of a file in the `public/static/colorschemes` folder).
+theme = {theme} -- the theme name which should be used for the website (the name of a file in the `public/static/themes` folder).
+animation = {animation} -- the animation name which should be used with the theme or `nil` if you don't want any animations.
+favicon_service = {favicon_service} -- the service from which the favicons of the results are fetched through the instance (`{{domain}}` is replaced with the domain) or `nil` to hide the favicons.
+anonymous_redirects = {anonymous_redirects} -- route the links of the results through the `/r` redirect route, so that the sites of the results never learn the search query from the `Referer` header.
+onion_address = {onion_address} -- the url of the Tor onion service of the instance (like `"http://<address>.onion"`) advertised to the Tor Browser with the `Onion-Location` header or `nil` if there is none.
+"#,
+            logging = self.logging,
+            debug = self.debug,
+            log_level = self
+                .log_level
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            log_format = lua_string(self.log_format.name()),
+            otlp_endpoint = self
+                .otlp
+                .endpoint
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            otlp_service_name = lua_string(&self.otlp.service_name),
+            otlp_sample_ratio = self.otlp.sample_ratio,
+            threads = self.threads,
+            port = self.port,
+            binding_ip = lua_string(&self.binding_ip),
+            unix_socket = self
+                .unix_socket
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            production_use = self.aggregator.random_delay,
+            request_timeout = self.request_timeout,
+            shutdown_timeout = self.shutdown_timeout,
+            max_connections = self.max_connections,
+            keep_alive = self.keep_alive,
+            client_request_timeout = self.client_request_timeout,
+            connect_timeout = self
+                .connect_timeout
+                .map_or("nil".to_owned(), |timeout| timeout.to_string()),
+            proxy = self.proxy.as_deref().map_or("nil".to_owned(), lua_string),
+            dns_over_https = self
+                .dns_over_https
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            user_agents = lua_string_list(&self.user_agents),
+            randomize_headers = self.randomize_headers,
+            metrics = self.metrics,
+            secret_key = self
+                .secret_key
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            number_of_requests = self.rate_limiter.number_of_requests,
+            time_limit = self.rate_limiter.time_limit,
+            search_burst = self.search_rate_limiter.burst,
+            search_per_minute = self.search_rate_limiter.per_minute,
+            search_rate_limiter_redis = self.search_rate_limiter.redis,
+            bot_challenge_enabled = self.bot_challenge.enabled,
+            bot_challenge_difficulty = self.bot_challenge.difficulty,
+            bot_challenge_burst = self.bot_challenge.burst,
+            bot_challenge_per_minute = self.bot_challenge.per_minute,
+            bot_challenge_validity = self.bot_challenge.validity,
+            trusted_proxies = lua_string_list(
+                &self
+                    .trusted_proxies
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<String>>()
+            ),
+            adaptive_window = self.adaptive_window,
+            max_query_length = self.request_limits.max_query_length,
+            max_page = self.request_limits.max_page,
+            max_results_per_page = self.request_limits.max_results_per_page,
+            max_query_string_length = self.request_limits.max_query_string_length,
+            max_cookie_size = self.request_limits.max_cookie_size,
+            max_body_size = self.request_limits.max_body_size,
+            admin_token = self
+                .admin
+                .token
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            admin_state_file = self
+                .admin
+                .state_file
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            api_keys = lua_api_keys(&self.api.keys),
+            api_redis = self.api.redis,
+            tls_certificate = self
+                .tls
+                .certificate
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            tls_private_key = self
+                .tls
+                .private_key
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            tor_enabled = self.tor.enabled,
+            tor_socks_url = lua_string(&self.tor.socks_url),
+            tor_isolate_streams = self.tor.isolate_streams,
+            tor_control_address = self
+                .tor
+                .control_address
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            tor_control_password = self
+                .tor
+                .control_password
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            tor_newnym_after_blocks = self.tor.newnym_after_blocks,
+            no_query_logging = self.privacy.no_query_logging,
+            usage_stats_enabled = self.usage_stats.enabled,
+            usage_stats_public = self.usage_stats.public,
+            usage_stats_redis = self.usage_stats.redis,
+            usage_stats_retention_days = self.usage_stats.retention_days,
+            profiles_enabled = self.profiles.enabled,
+            profiles_redis = self.profiles.redis,
+            profiles_expiry_days = self.profiles.expiry_days,
+            profiles_max_size = self.profiles.max_size,
+            profiles_history = self.profiles.history,
+            profiles_max_history = self.profiles.max_history,
+            content_security_policy = lua_string(&self.security_headers.content_security_policy),
+            referrer_policy = lua_string(&self.security_headers.referrer_policy),
+            permissions_policy = lua_string(&self.security_headers.permissions_policy),
+            hsts_max_age = self
+                .security_headers
+                .hsts_max_age
+                .map_or("nil".to_owned(), |max_age| max_age.to_string()),
+            results_per_page = self.results_per_page,
+            locked_settings = lua_string_list(
+                &self
+                    .locked_settings
+                    .iter()
+                    .map(|setting| setting.name().to_owned())
+                    .collect::<Vec<String>>()
+            ),
+            disabled_categories = lua_string_list(&self.disabled_categories),
+            safe_search = match self.safe_search {
+                0..=2 => lua_string(SafeSearchLevel::from_level(self.safe_search).name()),
+                level => level.to_string(),
+            },
+            colorscheme = lua_string(&self.style.colorscheme),
+            theme = lua_string(&self.style.theme),
+            animation = self
+                .style
+                .animation
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            favicon_service = self
+                .favicon_service
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+            anonymous_redirects = self.anonymous_redirects,
+            onion_address = self
+                .onion_address
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string),
+        );
+
+        #[cfg(any(feature = "redis-cache", feature = "memory-cache"))]
+        lua.push_str("\n-- ### Caching ###\n");
+        #[cfg(feature = "redis-cache")]
+        lua.push_str(&format!(
+            "redis_url = {} -- redis connection url address on which the client should connect on (use `redis_url_file` to read it from a file).\n",
+            lua_string(&self.redis_url)
+        ));
+        #[cfg(any(feature = "redis-cache", feature = "memory-cache"))]
+        lua.push_str(&format!(
+            "cache_expiry_time = {} -- This option takes the expiry time of the search results (value in seconds and the value should be greater than or equal to 60 seconds).\n",
+            self.cache_expiry_time
+        ));
+
+        lua.push_str(
+            r#"
+-- ### Search Engines ###
+-- Each engine can either be enabled/disabled with a boolean or configured with a table of engine
+-- specific options (`enabled`, `weight`, `timeout`, `connect_timeout`, `requests_per_second`,
+-- `region`, `api_key` (or `api_key_file`) and `proxy`).
+upstream_search_engines = {
+"#,
+        );
+
+        let mut engines: Vec<(&String, &EngineConfig)> =
+            self.upstream_search_engines.iter().collect();
+        engines.sort_by_key(|(engine, _)| *engine);
+
+        for (engine, engine_config) in engines {
+            lua.push_str(&format!(
+                "    {} = {},\n",
+                lua_key(engine),
+                engine_config_to_lua(engine_config)
+            ));
+        }
+
+        lua.push_str(
+            "} -- select the upstream search engines from which the results should be fetched.\n",
+        );
+        lua.push_str(&format!(
+            "rank_constant = {} -- the rank constant of the reciprocal rank fusion used to rank the results (the higher it is the less the top results of every engine are favoured).\n",
+            self.aggregator.rank_constant
+        ));
+        lua.push_str(&format!(
+            "aggregation_deadline = {} -- the time budget of the aggregation (value in milliseconds) after which the results of the upstream search engines which have answered so far are returned or `nil` to wait for all the engines.\n",
+            self.aggregator
+                .deadline
+                .map_or("nil".to_owned(), |deadline| deadline.to_string())
+        ));
+        lua.push_str(&format!(
+            "circuit_breaker_threshold = {} -- the number of the consecutive failures (or timeouts) of an upstream search engine after which the engine is skipped for the cool-down period (`0` disables the circuit breakers).\n",
+            self.aggregator.circuit_breaker_threshold
+        ));
+        lua.push_str(&format!(
+            "circuit_breaker_cooldown = {} -- the time (value in seconds) for which a failing upstream search engine is skipped before a probe request is sent to it.\n",
+            self.aggregator.circuit_breaker_cooldown
+        ));
+        lua.push_str(&format!(
+            "engine_retries = {} -- the number of the times a request to an upstream search engine is retried after a transient error (a `429` or a `5xx` response or a failed connection), within the aggregation deadline (`0` disables the retries).\n",
+            self.aggregator.retries
+        ));
+        lua.push_str(&format!(
+            "retry_backoff = {} -- the time (value in milliseconds) to wait before the first retry, which doubles with every retry.\n",
+            self.aggregator.retry_backoff
+        ));
+        lua.push_str(&format!(
+            "retry_jitter = {} -- the largest random time (value in milliseconds) added to the wait before a retry.\n",
+            self.aggregator.retry_jitter
+        ));
+        lua.push_str(&format!(
+            "block_cooldown = {} -- the time (value in seconds) for which an upstream search engine which blocked the instance (with a `403` or a `429` response, a CAPTCHA or a block page) is skipped, which doubles every time the engine blocks the instance again (`0` disables the backoff).\n",
+            self.aggregator.block_cooldown
+        ));
+        lua.push_str(&format!(
+            "max_block_cooldown = {} -- the longest time (value in seconds) for which a blocked upstream search engine is skipped.\n",
+            self.aggregator.max_block_cooldown
+        ));
+        lua.push_str(&format!(
+            "max_engines = {} -- the number of the upstream search engines to which each search is sent, picked from the selected engines based on their recent success rate and latency (`nil` to send each search to all the selected engines).\n",
+            self.aggregator
+                .max_engines
+                .map_or("nil".to_owned(), |max_engines| max_engines.to_string())
+        ));
+        lua.push_str(&format!(
+            "exploration_rate = {} -- the probability (between 0 and 1) with which an engine which is not among the best performing ones is picked, so that the statistics of all the engines stay fresh.\n",
+            self.aggregator.exploration_rate
+        ));
+        lua.push_str(&format!(
+            "health_monitor = {{\n\
+             \tenabled = {}, -- Periodically send a canary query to each enabled upstream search engine, so that the engines which stopped answering (or whose markup changed) are shown on the engine status page before the users notice.\n\
+             \tinterval = {}, -- The interval (value in seconds, at least 60) at which the canary queries are sent.\n\
+             \tquery = {}, -- The canary query, which should be answered with results by every engine.\n\
+             }}\n",
+            self.health_monitor.enabled,
+            self.health_monitor.interval,
+            lua_string(&self.health_monitor.query)
+        ));
+        lua.push_str(&format!(
+            "reverse_image_engines = {} -- the upstream search engines to which the reverse image searches (`/search/image?url=`) are sent (`TinEye`, `Yandex` and `BingVisual` are supported).\n",
+            lua_string_list(&self.reverse_image_engines)
+        ));
+        lua.push_str(&format!(
+            "engine_scripts = {} -- the paths of the lua scripts defining additional upstream search engines (see the docs).\n",
+            lua_string_list(&self.engine_scripts)
+        ));
+        lua.push_str(&format!(
+            "engine_definitions = {} -- the directory of the TOML and JSON files defining additional upstream search engines (see the docs).\n",
+            self.engine_definitions
+                .as_deref()
+                .map_or("nil".to_owned(), lua_string)
+        ));
+
+        lua.push_str(
+            "-- The factors by which the scores of the results from the matching domains are multiplied\n\
+             -- to boost (greater than 1) or to penalize (less than 1) them. For example: `[\"*.org\"] = 1.2`.\n\
+             domain_weights = {\n",
+        );
+
+        let mut domain_weights: Vec<(&String, &f32)> = self.domain_weights.iter().collect();
+        domain_weights.sort_by_key(|(pattern, _)| *pattern);
+
+        for (pattern, weight) in domain_weights {
+            lua.push_str(&format!("    {} = {:?},\n", lua_key(pattern), weight));
+        }
+
+        lua.push_str("}\n");
+
+        lua.push_str(
+            r#"
+-- The upstream search engines which should be used by default for each search category
+-- (general/images/videos/news/...), the engines enabled above are used for the categories which
+-- are not listed here. For example: `images = { "Bing", "DuckDuckGo" }`.
+category_engines = {
+"#,
+        );
+
+        let mut categories: Vec<(&String, &Vec<String>)> = self.category_engines.iter().collect();
+        categories.sort_by_key(|(category, _)| *category);
+
+        for (category, engines) in categories {
+            lua.push_str(&format!(
+                "    {} = {},\n",
+                lua_key(category),
+                lua_string_list(engines)
+            ));
+        }
+
+        lua.push_str("}\n");
+
+        lua.push_str(
+            r#"
+-- ### Bangs ###
+-- Custom bangs which redirect the user to the provided url when the search query contains
+-- `!<bang>`, `{query}` in the url is replaced with the rest of the search query. These take
+-- precedence over the built-in bangs. For example: `w = "https://en.wikipedia.org/w/index.php?search={query}"`.
+bangs = {
+"#,
+        );
+
+        let mut bangs: Vec<(&String, &String)> = self.bangs.iter().collect();
+        bangs.sort();
+
+        for (bang, url_template) in bangs {
+            lua.push_str(&format!(
+                "    {} = {},\n",
+                lua_key(bang),
+                lua_string(url_template)
+            ));
+        }
+
+        lua.push_str("}\n");
+
+        lua.push_str(&format!(
+            r#"
+-- ### Domain Filters ###
+-- The patterns of the domains whose results should be dropped and to which the results should be
+-- restricted (if any). `*` matches any characters and the subdomains of the domains are matched
+-- too, the patterns wrapped in slashes (like `/^spam[0-9]+\.net$/`) are regexes.
+blocked_domains = {} -- for example: `{{ "pinterest.com", "*.content-farm.*" }}`.
+allowed_domains = {} -- the results are not restricted if it is empty.
+-- The urls of the remote blocklists in the uBlacklist format whose matching results should be
+-- dropped, they are fetched in the background and refreshed periodically.
+blocklist_subscriptions = {} -- for example: `{{ "https://example.com/uBlacklist.txt" }}`.
+blocklist_refresh_interval = {} -- the interval after which the blocklists are refreshed (value in seconds).
+"#,
+            lua_string_list(&self.blocked_domains),
+            lua_string_list(&self.allowed_domains),
+            lua_string_list(&self.blocklist_subscriptions),
+            self.blocklist_refresh_interval,
+        ));
+
+        lua.push_str(
+            r#"
+-- ### Url Rewrites ###
+-- The hosts of the sites along with the urls of the privacy respecting frontends to which the
+-- links of the search results pointing to these sites (or to their subdomains) are rewritten.
+url_rewrites = {
+"#,
+        );
+
+        let mut url_rewrites: Vec<(&String, &String)> = self.url_rewrites.iter().collect();
+        url_rewrites.sort();
+
+        for (host, frontend) in url_rewrites {
+            lua.push_str(&format!(
+                "    {} = {},\n",
+                lua_key(host),
+                lua_string(frontend)
+            ));
+        }
+
+        lua.push_str("}\n");
+
+        lua.push_str(&format!(
+            "-- The AMP urls of the results are rewritten to the canonical urls of the pages, these are\n\
+             -- resolved with `HEAD` requests when it is enabled and guessed otherwise.\n\
+             resolve_amp_urls = {}\n",
+            self.resolve_amp_urls
+        ));
+
+        lua.push_str(&format!(
+            "\n-- ### Result Filters ###\n\
+             -- The built-in filters run over the results in the provided order (`trackers`, `domains`,\n\
+             -- `blocklists` and `rewrites` are supported), the filters left out are not run. The safe\n\
+             -- search is always enforced before them.\n\
+             result_filters = {}\n",
+            lua_string_list(&self.result_filters)
+        ));
+
+        lua
+    }
+}
+
+/// A helper function which converts the engine specific options into a lua value. If only the
+/// `enabled` option differs from the defaults then a boolean is generated otherwise a table of
+/// the options which differ from the defaults is generated.
+///
+/// # Arguments
+///
+/// * `engine_config` - It takes the engine specific options as an argument.
+fn engine_config_to_lua(engine_config: &EngineConfig) -> String {
+    let mut options: Vec<String> = Vec::new();
+
+    if engine_config.weight != EngineConfig::default().weight {
+        options.push(format!("weight = {:?}", engine_config.weight));
+    }
+    if let Some(timeout) = engine_config.timeout {
+        options.push(format!("timeout = {timeout}"));
+    }
+    if let Some(connect_timeout) = engine_config.connect_timeout {
+        options.push(format!("connect_timeout = {connect_timeout}"));
+    }
+    if let Some(requests_per_second) = engine_config.requests_per_second {
+        options.push(format!("requests_per_second = {requests_per_second:?}"));
+    }
+    for (key, value) in [
+        ("region", &engine_config.region),
+        ("api_key", &engine_config.api_key),
+        ("proxy", &engine_config.proxy),
+    ] {
+        if let Some(value) = value {
+            options.push(format!("{key} = {}", lua_string(value)));
+        }
+    }
+
+    if options.is_empty() {
+        return engine_config.enabled.to_string();
+    }
+
+    options.insert(0, format!("enabled = {}", engine_config.enabled));
+    format!("{{ {} }}", options.join(", "))
+}
+
+/// A helper function which converts the provided string into a quoted lua string.
+///
+/// # Arguments
+///
+/// * `value` - It takes the string to be quoted as an argument.
+fn lua_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for character in value.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            _ => quoted.push(character),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A helper function which renders the api keys as a lua list of tables.
+///
+/// # Arguments
+///
+/// * `keys` - It takes the api keys to be rendered as an argument.
+fn lua_api_keys(keys: &[ApiKey]) -> String {
+    if keys.is_empty() {
+        return "{}".to_owned();
+    }
+
+    format!(
+        "{{ {} }}",
+        keys.iter()
+            .map(|key| format!(
+                "{{ name = {}, key = {}, burst = {}, per_minute = {} }}",
+                lua_string(&key.name),
+                lua_string(&key.key),
+                key.burst,
+                key.per_minute
+            ))
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+/// A helper function which converts the provided strings into a lua table (sequence) of quoted
+/// strings.
+///
+/// # Arguments
+///
+/// * `values` - It takes the strings to be converted as an argument.
+fn lua_string_list(values: &[String]) -> String {
+    if values.is_empty() {
+        return "{}".to_owned();
+    }
+
+    format!(
+        "{{ {} }}",
+        values
+            .iter()
+            .map(|value| lua_string(value))
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+/// A helper function which converts the provided string into a lua table key, the key is only
+/// quoted if it is not a valid lua identifier.
+///
+/// # Arguments
+///
+/// * `key` - It takes the table key as an argument.
+fn lua_key(key: &str) -> String {
+    let is_identifier = key
+        .chars()
+        .next()
+        .is_some_and(|character| character.is_ascii_alphabetic() || character == '_')
+        && key
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || character == '_');
+
+    match is_identifier {
+        true => key.to_owned(),
+        false => format!("[{}]", lua_string(key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::backend::ConfigFormat;
+
+    #[test]
+    fn test_generated_config_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let generated_config = Config::default().to_lua();
+        let parsed_config = Config::parse_str(&generated_config, ConfigFormat::Lua, true)?;
+
+        assert_eq!(parsed_config.to_lua(), generated_config);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shipped_config_matches_the_defaults() -> Result<(), Box<dyn std::error::Error>> {
+        let shipped_config = Config::parse_str(
+            include_str!("../../websurfx/config.lua"),
+            ConfigFormat::Lua,
+            true,
+        )?;
+
+        assert_eq!(shipped_config.to_lua(), Config::default().to_lua());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_locked_settings_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config {
+            locked_settings: vec![LockedSetting::SafeSearch, LockedSetting::Engines],
+            disabled_categories: vec!["images".to_owned()],
+            ..Default::default()
+        };
+        let parsed_config = Config::parse_str(&config.to_lua(), ConfigFormat::Lua, true)?;
+
+        assert_eq!(parsed_config.locked_settings, config.locked_settings);
+        assert!(parsed_config.is_locked(LockedSetting::SafeSearch));
+        assert!(!parsed_config.is_locked(LockedSetting::Language));
+        assert!(parsed_config.is_category_disabled("images"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_category_engines_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config {
+            category_engines: HashMap::from([(
+                "images".to_owned(),
+                vec!["Bing".to_owned(), "DuckDuckGo".to_owned()],
+            )]),
+            ..Default::default()
+        };
+        let parsed_config = Config::parse_str(&config.to_lua(), ConfigFormat::Lua, true)?;
+
+        assert_eq!(
+            parsed_config.default_engines(Some("images")),
+            vec!["Bing", "DuckDuckGo"]
+        );
+        assert_eq!(parsed_config.default_engines(None), vec!["DuckDuckGo"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_api_keys_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+        let config = Config {
+            api: ApiConfig {
+                keys: vec![ApiKey {
+                    name: "alice".to_owned(),
+                    key: "secret".to_owned(),
+                    burst: 5,
+                    per_minute: 120,
+                }],
+                redis: false,
+            },
+            ..Default::default()
+        };
+        let generated_config = config.to_lua();
+        let parsed_config = Config::parse_str(&generated_config, ConfigFormat::Lua, true)?;
+
+        assert_eq!(parsed_config.api.keys[0].key, "secret");
+        assert_eq!(parsed_config.to_lua(), generated_config);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_engine_config_to_lua() {
+        assert_eq!(engine_config_to_lua(&EngineConfig::default()), "false");
+        assert_eq!(
+            engine_config_to_lua(&EngineConfig {
+                enabled: true,
+                weight: 1.5,
+                region: Some("us".to_owned()),
+                ..Default::default()
+            }),
+            r#"{ enabled = true, weight = 1.5, region = "us" }"#
+        );
+    }
+}
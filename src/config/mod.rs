@@ -1,4 +1,9 @@
-//! This module provides the modules which handles the functionality to parse the lua config
-//! and convert the config options into rust readable form.
+//! This module provides the modules which handles the functionality to parse the lua or toml
+//! config and convert the config options into rust readable form.
 
+pub mod backend;
+pub mod error;
+pub mod generator;
 pub mod parser;
+pub mod reloader;
+pub mod state;
@@ -0,0 +1,289 @@
+//! This module provides the functionality to share the parsed config between all the routes and
+//! to reload it atomically when the config file changes or when a `SIGHUP` signal is received.
+
+use super::{
+    parser::Config,
+    state::{
+        apply_engine_overrides, load_engine_overrides, save_engine_overrides, state_file_path,
+        EngineOverride, EngineOverrides,
+    },
+};
+use crate::{
+    engines::registry::load_engines,
+    handler::{file_path, FileType},
+};
+use std::{
+    fs,
+    sync::{Arc, Mutex, PoisonError, RwLock},
+    time::{Duration, SystemTime},
+};
+
+/// The interval (in seconds) at which the config file is checked for modifications.
+const CONFIG_WATCH_INTERVAL: u64 = 5;
+
+/// A structure to share the parsed config between threads. The config is stored behind an `Arc`
+/// so that a reload swaps the whole config at once and requests that are already being handled
+/// keep using the config they started with.
+pub struct SharedConfig {
+    /// The currently active config protected from concurrent access by a read-write lock.
+    config: RwLock<Arc<Config>>,
+    /// The upstream search engine options changed at runtime through the admin api which are
+    /// applied on top of the options provided in the config file.
+    engine_overrides: Mutex<EngineOverrides>,
+}
+
+impl SharedConfig {
+    /// A function that creates a new `SharedConfig` from the parsed config and applies the
+    /// upstream search engine overrides persisted in the state file on top of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    pub fn new(mut config: Config) -> Self {
+        let engine_overrides = load_engine_overrides(&state_file_path(&config));
+        apply_engine_overrides(&mut config, &engine_overrides);
+        load_engines(&config);
+
+        Self {
+            config: RwLock::new(Arc::new(config)),
+            engine_overrides: Mutex::new(engine_overrides),
+        }
+    }
+
+    /// A getter function which returns a snapshot of the currently active config.
+    pub fn load(&self) -> Arc<Config> {
+        self.config
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// A function which reparses the config file and replaces the currently active config with
+    /// the newly parsed one. The options which can only take effect on startup like the `port`,
+    /// `binding_ip` and `threads` options are carried over from the currently active config.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the config file could not be parsed, in which case the currently active
+    /// config is left untouched.
+    pub fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.replace(Config::parse(true)?);
+        Ok(())
+    }
+
+    /// A helper function which replaces the currently active config with the provided config
+    /// while carrying over the options which can only take effect on startup and applying the
+    /// upstream search engine overrides on top of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_config` - It takes the newly parsed config struct as an argument.
+    fn replace(&self, mut new_config: Config) {
+        let current_config = self.load();
+
+        new_config.port = current_config.port;
+        new_config.binding_ip = current_config.binding_ip.clone();
+        new_config.threads = current_config.threads;
+
+        let engine_overrides = self
+            .engine_overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        apply_engine_overrides(&mut new_config, &engine_overrides);
+        load_engines(&new_config);
+
+        *self.config.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(new_config);
+    }
+
+    /// A function which overrides the options of the provided upstream search engine at runtime,
+    /// persists the overrides to the state file and applies them to the currently active config.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the upstream search engine as an argument.
+    /// * `engine_override` - It takes the options to be overridden as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the state file could not be written, in which case the currently
+    /// active config is left untouched.
+    pub fn override_engine(
+        &self,
+        engine: &str,
+        engine_override: EngineOverride,
+    ) -> Result<(), std::io::Error> {
+        let mut engine_overrides = self
+            .engine_overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        let mut new_engine_overrides = engine_overrides.clone();
+        new_engine_overrides
+            .entry(engine.to_owned())
+            .or_default()
+            .merge(engine_override);
+
+        let mut new_config = Config::clone(&self.load());
+        save_engine_overrides(&state_file_path(&new_config), &new_engine_overrides)?;
+        apply_engine_overrides(&mut new_config, &new_engine_overrides);
+
+        *engine_overrides = new_engine_overrides;
+        *self.config.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(new_config);
+        Ok(())
+    }
+}
+
+/// A helper function which returns the last modification time of the config file.
+fn config_modified_time() -> Option<SystemTime> {
+    fs::metadata(file_path(FileType::Config).ok()?)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// A function which watches the config file for modifications and listens for the `SIGHUP`
+/// signal (on unix systems) and reloads the shared config whenever one of these occur.
+///
+/// # Arguments
+///
+/// * `shared_config` - It takes the shared config which should be reloaded as an argument.
+pub async fn watch_config(shared_config: Arc<SharedConfig>) {
+    let mut last_modified = config_modified_time();
+    let mut interval = tokio::time::interval(Duration::from_secs(CONFIG_WATCH_INTERVAL));
+
+    #[cfg(unix)]
+    let mut sighup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => Some(signal),
+        Err(error) => {
+            tracing::error!("Unable to listen for the SIGHUP signal: {error}");
+            None
+        }
+    };
+
+    loop {
+        #[cfg(unix)]
+        let reload_requested = match sighup.as_mut() {
+            Some(signal) => tokio::select! {
+                _ = signal.recv() => true,
+                _ = interval.tick() => false,
+            },
+            None => {
+                interval.tick().await;
+                false
+            }
+        };
+
+        #[cfg(not(unix))]
+        let reload_requested = {
+            interval.tick().await;
+            false
+        };
+
+        let modified = config_modified_time();
+        if !reload_requested && modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        match shared_config.reload() {
+            Ok(_) => tracing::info!("Config file reloaded successfully"),
+            Err(error) => {
+                tracing::error!("Failed to reload the config file, keeping the old config: {error}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A helper function which builds a config whose state file is stored in the provided
+    /// temporary directory and whose startup options differ from the default ones.
+    fn config(state_directory: &TempDir) -> Config {
+        let mut config = Config {
+            port: 1234,
+            binding_ip: "10.0.0.1".to_owned(),
+            threads: 3,
+            request_timeout: Config::default().request_timeout + 1,
+            ..Default::default()
+        };
+        config.admin.state_file = Some(
+            state_directory
+                .path()
+                .join("state.json")
+                .to_string_lossy()
+                .into_owned(),
+        );
+        config
+    }
+
+    #[test]
+    fn test_reload_carries_over_the_startup_options() -> Result<(), Box<dyn std::error::Error>> {
+        let state_directory = TempDir::new()?;
+        let shared_config = SharedConfig::new(config(&state_directory));
+
+        shared_config.replace(Config {
+            request_timeout: 5,
+            ..Default::default()
+        });
+
+        let config = shared_config.load();
+        assert_eq!(config.port, 1234);
+        assert_eq!(config.binding_ip, "10.0.0.1");
+        assert_eq!(config.threads, 3);
+        assert_eq!(config.request_timeout, 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_keeps_the_engine_overrides() -> Result<(), Box<dyn std::error::Error>> {
+        let state_directory = TempDir::new()?;
+        let shared_config = SharedConfig::new(config(&state_directory));
+
+        shared_config.override_engine(
+            "Bing",
+            EngineOverride {
+                enabled: Some(true),
+                weight: Some(2.0),
+            },
+        )?;
+        shared_config.replace(Config::default());
+
+        let bing = &shared_config.load().upstream_search_engines["Bing"];
+        assert!(bing.enabled);
+        assert_eq!(bing.weight, 2.0);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sighup_reloads_the_config() -> Result<(), Box<dyn std::error::Error>> {
+        let state_directory = TempDir::new()?;
+        let shared_config = Arc::new(SharedConfig::new(config(&state_directory)));
+        let watcher = tokio::spawn(watch_config(shared_config.clone()));
+
+        // Give the watcher the time to register the signal handler before the signal is sent.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        std::process::Command::new("kill")
+            .args(["-HUP", &std::process::id().to_string()])
+            .status()?;
+
+        let default_timeout = Config::default().request_timeout;
+        for _ in 0..50 {
+            if shared_config.load().request_timeout == default_timeout {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        watcher.abort();
+
+        let config = shared_config.load();
+        assert_eq!(config.request_timeout, default_timeout);
+        assert_eq!(config.port, 1234);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+//! This module provides the functionality to persist the upstream search engine options changed
+//! at runtime through the admin api to a state file, so that the changes survive restarts and
+//! config reloads.
+
+use super::parser::Config;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, io::Error, path::Path};
+
+/// The constant holding the name of the state file.
+const STATE_FILE_NAME: &str = "state.json";
+
+/// A named struct which stores the upstream search engine options which have been overridden at
+/// runtime. The options which are `None` are left as provided in the config file.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct EngineOverride {
+    /// It stores the option to whether enable or disable the upstream search engine by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// It stores the weight of the results provided by the upstream search engine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f32>,
+}
+
+impl EngineOverride {
+    /// A function which merges the provided overrides on top of the current overrides, the
+    /// options which are not provided are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - It takes the overrides to be merged as an argument.
+    pub fn merge(&mut self, other: EngineOverride) {
+        self.enabled = other.enabled.or(self.enabled);
+        self.weight = other.weight.or(self.weight);
+    }
+}
+
+/// A type alias for the overrides of all the upstream search engines keyed by the engine name.
+pub type EngineOverrides = HashMap<String, EngineOverride>;
+
+/// A function which returns the path to the state file. The path provided via the
+/// `admin.state_file` option is used if present otherwise the state file is stored under
+/// `$XDG_STATE_HOME/websurfx` (or `$HOME/.local/state/websurfx` if it is not set).
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+pub fn state_file_path(config: &Config) -> String {
+    if let Some(state_file) = &config.admin.state_file {
+        return state_file.clone();
+    }
+
+    match std::env::var("XDG_STATE_HOME") {
+        Ok(state_home) if !state_home.is_empty() => {
+            format!("{state_home}/websurfx/{STATE_FILE_NAME}")
+        }
+        _ => match std::env::var("HOME") {
+            Ok(home) => format!("{home}/.local/state/websurfx/{STATE_FILE_NAME}"),
+            Err(_) => format!("./websurfx/{STATE_FILE_NAME}"),
+        },
+    }
+}
+
+/// A function which loads the upstream search engine overrides from the state file. An empty
+/// set of overrides is returned if the state file does not exist or could not be parsed.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path to the state file as an argument.
+pub fn load_engine_overrides(path: &str) -> EngineOverrides {
+    let Ok(state) = fs::read_to_string(path) else {
+        return EngineOverrides::new();
+    };
+
+    serde_json::from_str(&state).unwrap_or_else(|error| {
+        tracing::error!("Unable to parse the state file at {path}, ignoring it: {error}");
+        EngineOverrides::new()
+    })
+}
+
+/// A function which writes the upstream search engine overrides to the state file. The state is
+/// first written to a temporary file which then replaces the state file, so that the state file
+/// is never left partially written.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path to the state file as an argument.
+/// * `overrides` - It takes the overrides to be persisted as an argument.
+///
+/// # Error
+///
+/// Returns an io error if the state file could not be written.
+pub fn save_engine_overrides(path: &str, overrides: &EngineOverrides) -> Result<(), Error> {
+    if let Some(parent) = Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temporary_path = format!("{path}.tmp");
+    fs::write(&temporary_path, serde_json::to_vec_pretty(overrides)?)?;
+    fs::rename(temporary_path, path)
+}
+
+/// A function which applies the upstream search engine overrides on top of the options provided
+/// in the config file. The overrides for the engines which are not present in the config file
+/// are ignored.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct to be modified as an argument.
+/// * `overrides` - It takes the overrides to be applied as an argument.
+pub fn apply_engine_overrides(config: &mut Config, overrides: &EngineOverrides) {
+    for (engine, engine_override) in overrides {
+        let Some(engine_config) = config.upstream_search_engines.get_mut(engine) else {
+            continue;
+        };
+        if let Some(enabled) = engine_override.enabled {
+            engine_config.enabled = enabled;
+        }
+        if let Some(weight) = engine_override.weight {
+            engine_config.weight = weight;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_engine_overrides() {
+        let mut config = Config::default();
+        let mut engine_override = EngineOverride {
+            enabled: Some(true),
+            weight: None,
+        };
+        engine_override.merge(EngineOverride {
+            enabled: None,
+            weight: Some(2.0),
+        });
+
+        apply_engine_overrides(
+            &mut config,
+            &EngineOverrides::from([
+                ("Bing".to_owned(), engine_override),
+                ("Unknown".to_owned(), engine_override),
+            ]),
+        );
+
+        let bing = &config.upstream_search_engines["Bing"];
+        assert!(bing.enabled);
+        assert_eq!(bing.weight, 2.0);
+        assert!(!config.upstream_search_engines.contains_key("Unknown"));
+    }
+}
@@ -3,15 +3,18 @@
 //! number if provided.
 
 use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use regex::Regex;
 use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
+use crate::models::locale_models::market;
+use crate::models::parser_models::{EngineConfig, SafeSearchLevel};
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SearchEngine, SearchOptions, TimeRange};
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -34,35 +37,29 @@ impl Bing {
                 "h2 a",
                 ".tpcn a.tilk",
                 ".b_caption p",
-            )?,
+            )?
+            .with_corrected_query("#sp_requery a")?
+            .with_related_queries(".b_rs li a")?,
         })
     }
 }
 
 #[async_trait::async_trait]
 impl SearchEngine for Bing {
-    async fn results(
+    fn name(&self) -> &'static str {
+        "bing"
+    }
+
+    async fn fetch_results(
         &self,
         query: &str,
         page: u32,
         user_agent: &str,
         client: &Client,
-        _safe_search: u8,
-    ) -> Result<HashMap<String, SearchResult>, EngineError> {
-        // Bing uses `start results from this number` convention
-        // So, for 10 results per page, page 0 starts at 1, page 1
-        // starts at 11, and so on.
-        let results_per_page = 10;
-        let start_result = results_per_page * page + 1;
-
-        let url: String = match page {
-            0 => {
-                format!("https://www.bing.com/search?q={query}")
-            }
-            _ => {
-                format!("https://www.bing.com/search?q={query}&first={start_result}")
-            }
-        };
+        options: &SearchOptions,
+        engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError> {
+        let url = search_url(query, page, options, engine_config);
 
         let query_params: Vec<(&str, &str)> = vec![
             ("_EDGE_V", "1"),
@@ -90,7 +87,13 @@ impl SearchEngine for Bing {
         .change_context(EngineError::UnexpectedError)?;
 
         let document: Html = Html::parse_document(
-            &Bing::fetch_html_from_upstream(self, &url, header_map, client).await?,
+            &Bing::fetch_html_from_upstream(
+                self,
+                &url,
+                options.request_headers(header_map),
+                client,
+            )
+            .await?,
         );
 
         // Bing is very aggressive in finding matches
@@ -121,4 +124,143 @@ impl SearchEngine for Bing {
                 ))
             })
     }
+
+    fn supports_query_operators(&self) -> bool {
+        true
+    }
+
+    fn supports_safe_search(&self) -> bool {
+        true
+    }
+
+    fn blocked_page_signatures(&self) -> &'static [&'static str] {
+        &["b_captcha", "/turing/captcha"]
+    }
+}
+
+/// A helper function which builds the url of the search page of Bing with the parameters
+/// selecting the page, the region, the language, the safe search level and the time range.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query as an argument.
+/// * `page` - It takes the page number as an argument.
+/// * `options` - It takes the search options selected by the user as an argument.
+/// * `engine_config` - It takes the config of the engine provided via the config file as an
+///   argument.
+fn search_url(
+    query: &str,
+    page: u32,
+    options: &SearchOptions,
+    engine_config: &EngineConfig,
+) -> String {
+    // Bing uses `start results from this number` convention
+    // So, for 10 results per page, page 0 starts at 1, page 1
+    // starts at 11, and so on.
+    let results_per_page = 10;
+    let start_result = results_per_page * page + 1;
+
+    let mut url: String = match page {
+        0 => {
+            format!("https://www.bing.com/search?q={query}")
+        }
+        _ => {
+            format!("https://www.bing.com/search?q={query}&first={start_result}")
+        }
+    };
+
+    // Bing uses the `cc` parameter to select the country from which the results are preferred
+    // and the `mkt` parameter to select the market. The region selected by the user takes
+    // precedence over the one provided via the config file.
+    if let Some(region) = options
+        .region
+        .as_deref()
+        .or(engine_config.region.as_deref())
+    {
+        url.push_str(&format!("&cc={region}"));
+        if let Some(market) = market(region, options.language.as_deref()) {
+            url.push_str(&format!("&mkt={market}"));
+        }
+    }
+
+    // Bing uses the `adlt` parameter to select the safe search level.
+    url.push_str(&format!(
+        "&adlt={}",
+        SafeSearchLevel::from_level(options.safe_search).name()
+    ));
+
+    // Bing uses the `setlang` parameter to select the language of the results.
+    if let Some(language) = &options.language {
+        url.push_str(&format!("&setlang={language}"));
+    }
+
+    // Bing uses the `ex1` filter to restrict the results to a time range, the past year is
+    // provided as a custom range of days since the unix epoch.
+    if let Some(time_range) = options.time_range {
+        let filter = match time_range {
+            TimeRange::Day => "ez1".to_owned(),
+            TimeRange::Week => "ez2".to_owned(),
+            TimeRange::Month => "ez3".to_owned(),
+            TimeRange::Year => {
+                let today = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_secs() / 86400)
+                    .unwrap_or_default();
+                format!("ez5_{}_{today}", today.saturating_sub(365))
+            }
+        };
+        url.push_str(&format!("&filters=ex1%3a%22{filter}%22"));
+    }
+
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_url_language_and_time_range() {
+        let options = SearchOptions {
+            language: Some("de".to_owned()),
+            time_range: Some(TimeRange::Week),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            search_url("rust", 1, &options, &EngineConfig::default()),
+            "https://www.bing.com/search?q=rust&first=11&adlt=off&setlang=de&filters=ex1%3a%22ez2%22"
+        );
+        assert_eq!(
+            search_url(
+                "rust",
+                0,
+                &SearchOptions::default(),
+                &EngineConfig::default()
+            ),
+            "https://www.bing.com/search?q=rust&adlt=off"
+        );
+    }
+    #[test]
+    fn test_search_url_region() {
+        let engine_config = EngineConfig {
+            region: Some("us".to_owned()),
+            ..Default::default()
+        };
+        let options = SearchOptions {
+            region: Some("ch".to_owned()),
+            language: Some("fr".to_owned()),
+            safe_search: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            search_url("rust", 0, &options, &engine_config),
+            "https://www.bing.com/search?q=rust&cc=ch&mkt=fr-CH&adlt=strict&setlang=fr"
+        );
+        assert_eq!(
+            search_url("rust", 0, &SearchOptions::default(), &engine_config),
+            "https://www.bing.com/search?q=rust&cc=us&mkt=en-US&adlt=off"
+        );
+    }
 }
@@ -0,0 +1,204 @@
+//! This module provides the backoff of the upstream search engines which blocked the requests of
+//! the instance (with a `403` or a `429` response, a CAPTCHA or a block page). A blocked engine is
+//! skipped for a cool-down period, which doubles every time the engine blocks the instance again
+//! right after its cool-down period, so that the engine is given time to lift the block instead of
+//! being sent more requests which would prolong it.
+
+use crate::models::engine_models::EngineError;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// A static variable which stores the blocks of the upstream search engines, keyed by the name of
+/// the engine.
+static ENGINE_BLOCKS: OnceLock<Mutex<HashMap<&'static str, EngineBlock>>> = OnceLock::new();
+
+/// A named struct which stores the block of an upstream search engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct EngineBlock {
+    /// It stores the number of the consecutive blocks of the engine, which is reset once the
+    /// engine answers with its results.
+    strikes: u32,
+    /// It stores the time until which the engine is skipped.
+    blocked_until: Instant,
+}
+
+impl EngineBlock {
+    /// A function which records a new block of the engine and escalates its cool-down period.
+    ///
+    /// # Arguments
+    ///
+    /// * `cooldown` - It takes the cool-down period of the first block as an argument.
+    /// * `max_cooldown` - It takes the longest cool-down period as an argument.
+    /// * `now` - It takes the current time as an argument.
+    fn strike(&mut self, cooldown: Duration, max_cooldown: Duration, now: Instant) {
+        self.strikes = self.strikes.saturating_add(1);
+        self.blocked_until = now
+            + cooldown
+                .saturating_mul(1 << (self.strikes - 1).min(16))
+                .min(max_cooldown);
+    }
+}
+
+/// A named struct which stores the state of a blocked upstream search engine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockedEngine {
+    /// It stores the name of the engine.
+    pub engine: &'static str,
+    /// It stores the number of the consecutive blocks of the engine.
+    pub strikes: u32,
+    /// It stores the time left until the engine is sent requests again.
+    pub remaining: Duration,
+}
+
+/// A function which checks whether the provided upstream search engine is cooling down after it
+/// blocked the instance.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine as an argument.
+///
+/// # Returns
+///
+/// It returns the time left until the engine is sent requests again, or `None` if the engine can
+/// be sent requests.
+pub fn blocked_for(engine: &str) -> Option<Duration> {
+    let now = Instant::now();
+    ENGINE_BLOCKS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(engine)
+        .map(|block| block.blocked_until.saturating_duration_since(now))
+        .filter(|remaining| !remaining.is_zero())
+}
+
+/// A function which records the response of the provided upstream search engine, the engines
+/// which blocked the request are skipped for their escalating cool-down period while the engines
+/// which answered (even without any result) are forgiven their previous blocks.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine as an argument.
+/// * `error` - It takes the error returned by the engine or `None` if the engine returned its
+///   results as an argument.
+/// * `cooldown` - It takes the cool-down period of the first block as an argument, `0` disables
+///   the backoff.
+/// * `max_cooldown` - It takes the longest cool-down period as an argument.
+pub fn record_response(
+    engine: &'static str,
+    error: Option<&EngineError>,
+    cooldown: Duration,
+    max_cooldown: Duration,
+) {
+    if cooldown.is_zero() {
+        return;
+    }
+
+    let mut blocks = ENGINE_BLOCKS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    match error {
+        Some(EngineError::Blocked) => {
+            let now = Instant::now();
+            let block = blocks.entry(engine).or_insert(EngineBlock {
+                strikes: 0,
+                blocked_until: now,
+            });
+            // The requests sent before the engine was blocked may still be answered with a block
+            // during the cool-down period, which does not escalate it further.
+            if block.blocked_until <= now {
+                block.strike(cooldown, max_cooldown, now);
+                tracing::warn!(
+                    engine,
+                    strikes = block.strikes,
+                    cooldown_secs = block.blocked_until.saturating_duration_since(now).as_secs(),
+                    "the engine blocked the instance"
+                );
+            }
+        }
+        None | Some(EngineError::EmptyResultSet) => {
+            blocks.remove(engine);
+        }
+        Some(_) => (),
+    }
+}
+
+/// A function which lists the upstream search engines which are cooling down after they blocked
+/// the instance.
+///
+/// # Returns
+///
+/// It returns the blocked engines in the alphabetical order of their names.
+pub fn blocked_engines() -> Vec<BlockedEngine> {
+    let now = Instant::now();
+    let mut engines: Vec<BlockedEngine> = ENGINE_BLOCKS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|(engine, block)| BlockedEngine {
+            engine,
+            strikes: block.strikes,
+            remaining: block.blocked_until.saturating_duration_since(now),
+        })
+        .filter(|engine| !engine.remaining.is_zero())
+        .collect();
+    engines.sort_unstable_by_key(|engine| engine.engine);
+    engines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escalating_cooldowns() {
+        let cooldown = Duration::from_secs(60);
+        let max_cooldown = Duration::from_secs(300);
+        let now = Instant::now();
+        let mut block = EngineBlock {
+            strikes: 0,
+            blocked_until: now,
+        };
+
+        block.strike(cooldown, max_cooldown, now);
+        assert_eq!(block.blocked_until, now + Duration::from_secs(60));
+        block.strike(cooldown, max_cooldown, now);
+        assert_eq!(block.blocked_until, now + Duration::from_secs(120));
+        block.strike(cooldown, max_cooldown, now);
+        assert_eq!(block.blocked_until, now + Duration::from_secs(240));
+        // The cool-down period never exceeds the longest one.
+        block.strike(cooldown, max_cooldown, now);
+        assert_eq!(block.blocked_until, now + max_cooldown);
+    }
+
+    #[test]
+    fn test_blocked_engines() {
+        let cooldown = Duration::from_secs(60);
+        let blocked = Some(&EngineError::Blocked);
+        record_response("blocktest", blocked, cooldown, cooldown * 10);
+        // The blocks answered during the cool-down period do not escalate it.
+        record_response("blocktest", blocked, cooldown, cooldown * 10);
+        // The other errors neither block the engine nor forgive its blocks.
+        record_response(
+            "blocktest",
+            Some(&EngineError::TimedOut),
+            cooldown,
+            cooldown * 10,
+        );
+        assert!(blocked_for("blocktest").is_some_and(|remaining| remaining <= cooldown));
+        assert!(blocked_engines()
+            .iter()
+            .any(|engine| engine.engine == "blocktest" && engine.strikes == 1));
+
+        record_response("blocktest", None, cooldown, cooldown * 10);
+        assert!(blocked_for("blocktest").is_none());
+        // The backoff is disabled with a cool-down period of `0`.
+        record_response("blocktest", blocked, Duration::ZERO, cooldown);
+        assert!(blocked_for("blocktest").is_none());
+    }
+}
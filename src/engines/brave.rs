@@ -6,11 +6,13 @@ use std::collections::HashMap;
 
 use reqwest::{header::HeaderMap, Client};
 use scraper::Html;
+use serde::Deserialize;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
+use crate::models::parser_models::EngineConfig;
 use error_stack::{Report, Result, ResultExt};
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SearchEngine, SearchOptions};
 
 use super::search_result_parser::SearchResultParser;
 
@@ -33,26 +35,162 @@ impl Brave {
             )?,
         })
     }
+
+    /// Fetches the results from the Brave search api, which is used instead of scraping the
+    /// website when an api key has been provided via the config file.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the search api with the search parameters as an argument.
+    /// * `api_key` - It takes the api key provided via the config file as an argument.
+    /// * `client` - It takes the http client used to send the request as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an `EngineError` if the api could not be reached, if it rejected the api key or
+    /// if the response could not be deserialized.
+    async fn api_results(
+        &self,
+        url: &str,
+        api_key: &str,
+        client: &Client,
+    ) -> Result<EngineResults, EngineError> {
+        let header_map = HeaderMap::try_from(&HashMap::from([
+            ("ACCEPT".to_string(), "application/json".to_string()),
+            ("X-SUBSCRIPTION-TOKEN".to_string(), api_key.to_string()),
+        ]))
+        .change_context(EngineError::UnexpectedError)?;
+
+        let response: ApiResponse = serde_json::from_slice(
+            &Brave::fetch_json_as_bytes_from_upstream(self, url, header_map, client).await?,
+        )
+        .change_context(EngineError::UnexpectedError)?;
+
+        let results: HashMap<String, SearchResult> = response
+            .web
+            .map(|web| web.results)
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+            .map(|(index, result)| {
+                let mut search_result =
+                    SearchResult::new(&result.title, &result.url, &result.description, &["brave"]);
+                search_result.rank = index + 1;
+                (search_result.url.clone(), search_result)
+            })
+            .collect();
+
+        if results.is_empty() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+
+        Ok(EngineResults {
+            results,
+            corrected_query: response.query.and_then(|query| query.altered),
+            ..Default::default()
+        })
+    }
+}
+
+/// A named struct which stores the parts of the Brave search api response which are used.
+#[derive(Deserialize)]
+struct ApiResponse {
+    /// It stores the information about the search query.
+    query: Option<ApiQuery>,
+    /// It stores the web search results.
+    web: Option<ApiWebResults>,
+}
+
+/// A named struct which stores the information about the search query provided by the api.
+#[derive(Deserialize)]
+struct ApiQuery {
+    /// It stores the spelling correction of the search query used by the api (if any).
+    altered: Option<String>,
+}
+
+/// A named struct which stores the web search results provided by the api.
+#[derive(Deserialize)]
+struct ApiWebResults {
+    /// It stores the list of web search results.
+    results: Vec<ApiResult>,
+}
+
+/// A named struct which stores a single web search result provided by the api.
+#[derive(Deserialize)]
+struct ApiResult {
+    /// It stores the title of the result.
+    title: String,
+    /// It stores the url of the result.
+    url: String,
+    /// It stores the description of the result.
+    #[serde(default)]
+    description: String,
 }
 
 #[async_trait::async_trait]
 impl SearchEngine for Brave {
-    async fn results(
+    fn name(&self) -> &'static str {
+        "brave"
+    }
+
+    async fn fetch_results(
         &self,
         query: &str,
         page: u32,
         user_agent: &str,
         client: &Client,
-        safe_search: u8,
-    ) -> Result<HashMap<String, SearchResult>, EngineError> {
-        let url = format!("https://search.brave.com/search?q={query}&offset={page}");
-
-        let safe_search_level = match safe_search {
+        options: &SearchOptions,
+        engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError> {
+        let safe_search_level = match options.safe_search {
             0 => "off",
             1 => "moderate",
             _ => "strict",
         };
 
+        // The official search api is used instead of scraping the website when an api key has
+        // been provided via the config file.
+        if let Some(api_key) = &engine_config.api_key {
+            let mut url = format!(
+                "https://api.search.brave.com/res/v1/web/search?q={query}&offset={page}&safesearch={safe_search_level}"
+            );
+            if let Some(time_range) = options.time_range {
+                url.push_str(&format!("&freshness=p{}", &time_range.name()[..1]));
+            }
+            if let Some(region) = &options.region {
+                url.push_str(&format!("&country={region}"));
+            }
+            if let Some(language) = &options.language {
+                url.push_str(&format!("&search_lang={language}"));
+            }
+            return self.api_results(&url, api_key, client).await;
+        }
+
+        let mut url = format!("https://search.brave.com/search?q={query}&offset={page}");
+
+        // Brave uses the `tf` parameter to restrict the results to a time range.
+        if let Some(time_range) = options.time_range {
+            url.push_str(&format!("&tf=p{}", &time_range.name()[..1]));
+        }
+
+        let mut cookie = format!("safe_search={safe_search_level}");
+
+        // Brave uses the `search_lang` cookie to select the language of the results.
+        if let Some(language) = &options.language {
+            let search_lang = match language.as_str() {
+                "ja" => "jp",
+                "zh" => "zh-hans",
+                language => language,
+            };
+            cookie.push_str(&format!("; search_lang={search_lang}"));
+        }
+
+        // Brave uses the `country` cookie to select the country from which the results are
+        // preferred.
+        if let Some(region) = &options.region {
+            cookie.push_str(&format!("; country={region}"));
+        }
+
         let header_map = HeaderMap::try_from(&HashMap::from([
             ("USER_AGENT".to_string(), user_agent.to_string()),
             (
@@ -60,15 +198,18 @@ impl SearchEngine for Brave {
                 "application/x-www-form-urlencoded".to_string(),
             ),
             ("REFERER".to_string(), "https://google.com/".to_string()),
-            (
-                "COOKIE".to_string(),
-                format!("safe_search={safe_search_level}"),
-            ),
+            ("COOKIE".to_string(), cookie),
         ]))
         .change_context(EngineError::UnexpectedError)?;
 
         let document: Html = Html::parse_document(
-            &Brave::fetch_html_from_upstream(self, &url, header_map, client).await?,
+            &Brave::fetch_html_from_upstream(
+                self,
+                &url,
+                options.request_headers(header_map),
+                client,
+            )
+            .await?,
         );
 
         if let Some(no_result_msg) = self.parser.parse_for_no_results(&document).nth(0) {
@@ -92,4 +233,16 @@ impl SearchEngine for Brave {
                 })
             })
     }
+
+    fn supports_query_operators(&self) -> bool {
+        true
+    }
+
+    fn supports_safe_search(&self) -> bool {
+        true
+    }
+
+    fn blocked_page_signatures(&self) -> &'static [&'static str] {
+        &["/search/captcha", "pow-captcha"]
+    }
 }
@@ -0,0 +1,231 @@
+//! This module provides the circuit breakers of the upstream search engines, which trip after
+//! consecutive failures of an engine, so that a dead engine is skipped for a cool-down period
+//! instead of adding its timeout to every search. Once the cool-down period is over, a single
+//! probe request is sent to the engine and the breaker is closed again if the engine answers.
+
+use crate::models::engine_models::EngineError;
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// A static variable which stores the circuit breakers of the upstream search engines, keyed by
+/// the name of the engine.
+static CIRCUIT_BREAKERS: OnceLock<Mutex<HashMap<&'static str, CircuitBreaker>>> = OnceLock::new();
+
+/// The state of the circuit breaker of an upstream search engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CircuitState {
+    /// The requests are sent to the engine, the number of its consecutive failures is counted.
+    Closed {
+        /// It stores the number of the consecutive failures of the engine.
+        failures: u32,
+    },
+    /// The engine is skipped until the end of the cool-down period.
+    Open {
+        /// It stores the time at which the breaker was tripped.
+        opened_at: Instant,
+    },
+    /// The cool-down period is over and a probe request was sent to the engine, the engine is
+    /// skipped by the other requests until the probe request has answered.
+    HalfOpen {
+        /// It stores the time at which the probe request was sent.
+        probed_at: Instant,
+    },
+}
+
+/// A circuit breaker which tracks the consecutive failures of an upstream search engine.
+struct CircuitBreaker {
+    /// It stores the current state of the breaker.
+    state: CircuitState,
+}
+
+impl CircuitBreaker {
+    /// Creates a closed circuit breaker.
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed { failures: 0 },
+        }
+    }
+
+    /// A function which checks whether a new request can be sent to the engine, the first
+    /// request after the cool-down period becomes the probe request of the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `cooldown` - It takes the time for which the engine is skipped once the breaker has
+    ///   tripped as an argument.
+    /// * `now` - It takes the current time as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns `true` if the request can be sent, otherwise `false`.
+    fn allow(&mut self, cooldown: Duration, now: Instant) -> bool {
+        match self.state {
+            CircuitState::Closed { .. } => true,
+            // The probe request is given the same amount of time as the cool-down period before
+            // another one is sent, so that a probe lost with its task does not keep the breaker
+            // half-open forever.
+            CircuitState::Open { opened_at: since }
+            | CircuitState::HalfOpen { probed_at: since }
+                if now.saturating_duration_since(since) >= cooldown =>
+            {
+                self.state = CircuitState::HalfOpen { probed_at: now };
+                true
+            }
+            CircuitState::Open { .. } | CircuitState::HalfOpen { .. } => false,
+        }
+    }
+
+    /// A function which records the response of a request sent to the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `failed` - It takes whether the engine failed to answer as an argument.
+    /// * `failure_threshold` - It takes the number of the consecutive failures after which the
+    ///   breaker trips as an argument.
+    /// * `now` - It takes the current time as an argument.
+    fn record(&mut self, failed: bool, failure_threshold: u32, now: Instant) {
+        self.state = match (self.state, failed) {
+            (_, false) => CircuitState::Closed { failures: 0 },
+            (CircuitState::Closed { failures }, true) if failures + 1 < failure_threshold => {
+                CircuitState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (_, true) => CircuitState::Open { opened_at: now },
+        };
+    }
+}
+
+/// A helper function which checks whether the error returned by an upstream search engine counts
+/// as a failure of the engine. The engines answering without any result and the requests which
+/// were never sent are not counted as failing.
+///
+/// # Arguments
+///
+/// * `error` - It takes the error returned by the engine as an argument.
+fn is_failure(error: &EngineError) -> bool {
+    matches!(
+        error,
+        EngineError::RequestError
+            | EngineError::UnexpectedError
+            | EngineError::Blocked
+            | EngineError::TimedOut
+    )
+}
+
+/// A function which checks whether a new request can be sent to the provided upstream search
+/// engine.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine as an argument.
+/// * `failure_threshold` - It takes the number of the consecutive failures after which the engine
+///   is skipped as an argument, `0` disables the circuit breaker.
+/// * `cooldown` - It takes the time for which the engine is skipped as an argument.
+///
+/// # Returns
+///
+/// It returns `true` if the request can be sent, or `false` if the engine should be skipped for
+/// this request.
+pub fn allow_request(engine: &'static str, failure_threshold: u32, cooldown: Duration) -> bool {
+    if failure_threshold == 0 {
+        return true;
+    }
+
+    CIRCUIT_BREAKERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(engine)
+        .or_insert_with(CircuitBreaker::new)
+        .allow(cooldown, Instant::now())
+}
+
+/// A function which records the response of the provided upstream search engine in its circuit
+/// breaker.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine as an argument.
+/// * `error` - It takes the error returned by the engine or `None` if the engine returned its
+///   results as an argument.
+/// * `failure_threshold` - It takes the number of the consecutive failures after which the engine
+///   is skipped as an argument, `0` disables the circuit breaker.
+pub fn record_response(engine: &'static str, error: Option<&EngineError>, failure_threshold: u32) {
+    if failure_threshold == 0 {
+        return;
+    }
+
+    CIRCUIT_BREAKERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(engine)
+        .or_insert_with(CircuitBreaker::new)
+        .record(
+            error.is_some_and(is_failure),
+            failure_threshold,
+            Instant::now(),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker() {
+        let cooldown = Duration::from_secs(30);
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+
+        // The breaker trips after the consecutive failures only.
+        breaker.record(true, 3, now);
+        breaker.record(false, 3, now);
+        breaker.record(true, 3, now);
+        breaker.record(true, 3, now);
+        assert!(breaker.allow(cooldown, now));
+        breaker.record(true, 3, now);
+        assert!(!breaker.allow(cooldown, now + Duration::from_secs(10)));
+
+        // A single probe request is sent once the cool-down period is over.
+        let later = now + cooldown;
+        assert!(breaker.allow(cooldown, later));
+        assert!(!breaker.allow(cooldown, later));
+
+        // The breaker trips again if the probe request fails.
+        breaker.record(true, 3, later);
+        assert!(!breaker.allow(cooldown, later + Duration::from_secs(1)));
+
+        // The breaker is closed once the probe request succeeds.
+        let latest = later + cooldown;
+        assert!(breaker.allow(cooldown, latest));
+        breaker.record(false, 3, latest);
+        assert!(breaker.allow(cooldown, latest));
+        assert!(breaker.allow(cooldown, latest));
+    }
+
+    #[test]
+    fn test_lost_probe_request() {
+        let cooldown = Duration::from_secs(30);
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker::new();
+
+        breaker.record(true, 1, now);
+        assert!(breaker.allow(cooldown, now + cooldown));
+        // Another probe request is sent if the first one never answered.
+        assert!(breaker.allow(cooldown, now + cooldown * 2));
+    }
+
+    #[test]
+    fn test_failures() {
+        assert!(is_failure(&EngineError::TimedOut));
+        assert!(is_failure(&EngineError::Blocked));
+        assert!(!is_failure(&EngineError::EmptyResultSet));
+        assert!(!is_failure(&EngineError::RateLimited));
+        assert!(!is_failure(&EngineError::CircuitOpen));
+    }
+}
@@ -0,0 +1,548 @@
+//! The `declarative` module handles the upstream search engines defined declaratively in the TOML
+//! or JSON files of the directory provided via the `engine_definitions` config option. A definition
+//! provides the url template of the engine along with the css selectors locating the results in
+//! its response, so that packs of engines can be maintained without writing any code.
+//!
+//! # Example
+//!
+//! ```toml
+//! name = "example"
+//! categories = ["general"]
+//! url = "https://example.com/search?q={query}&page={page}&lang={language}"
+//!
+//! [headers]
+//! Accept = "text/html"
+//!
+//! [pagination]
+//! start = 1
+//! step = 1
+//!
+//! [selectors]
+//! results = ".result"
+//! title = "h3"
+//! url = "h3 a"
+//! description = ".snippet"
+//! no_results = ".no-results"
+//! ```
+
+use std::{collections::HashMap, fs, path::Path};
+
+use error_stack::{Report, Result, ResultExt};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT},
+    Client, Url,
+};
+use scraper::{ElementRef, Html, Selector};
+use serde::Deserialize;
+
+use super::registry::intern;
+use crate::{
+    config::error::ConfigError,
+    models::{
+        aggregation_models::{EngineResults, SearchResult},
+        engine_models::{EngineError, SearchEngine, SearchOptions},
+        parser_models::EngineConfig,
+    },
+    query::bangs::encode_query,
+};
+
+/// A named struct which stores the definition of an upstream search engine as provided in a
+/// definition file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EngineDefinition {
+    /// It stores the name of the engine.
+    name: String,
+    /// It stores the search categories of the engine.
+    #[serde(default)]
+    categories: Vec<String>,
+    /// It stores the template of the url of the requests sent to the engine.
+    url: String,
+    /// It stores the headers sent along with the requests.
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    /// It stores how the pages of the results are numbered by the engine.
+    #[serde(default)]
+    pagination: Pagination,
+    /// It stores the css selectors locating the results in the response of the engine.
+    selectors: SelectorDefinitions,
+}
+
+/// A named struct which stores how the pages of the results are numbered by an upstream search
+/// engine, the `{page}` placeholder of the url template is replaced with `start + page * step`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+struct Pagination {
+    /// It stores the number of the first page (or the offset of its first result).
+    start: u32,
+    /// It stores the difference between the numbers of the consecutive pages, which is the
+    /// number of the results per page for the engines paginating with an offset.
+    step: u32,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Pagination { start: 1, step: 1 }
+    }
+}
+
+/// A named struct which stores the css selectors of a definition file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SelectorDefinitions {
+    /// It stores the selector locating each result.
+    results: String,
+    /// It stores the selector locating the title within a result.
+    title: String,
+    /// It stores the selector locating the link (or the text) of the url within a result.
+    url: String,
+    /// It stores the selector locating the description within a result.
+    description: Option<String>,
+    /// It stores the selector locating the element shown when nothing was found.
+    no_results: Option<String>,
+}
+
+/// A named struct which stores the parsed css selectors of a declarative engine.
+struct ResultSelectors {
+    /// It stores the selector locating each result.
+    results: Selector,
+    /// It stores the selector locating the title within a result.
+    title: Selector,
+    /// It stores the selector locating the link (or the text) of the url within a result.
+    url: Selector,
+    /// It stores the selector locating the description within a result.
+    description: Option<Selector>,
+    /// It stores the selector locating the element shown when nothing was found.
+    no_results: Option<Selector>,
+}
+
+/// An upstream search engine defined in a definition file.
+pub struct DeclarativeEngine {
+    /// It stores the name of the engine.
+    name: &'static str,
+    /// It stores the search categories of the engine.
+    categories: Vec<&'static str>,
+    /// It stores the template of the url of the requests sent to the engine.
+    url: String,
+    /// It stores the headers sent along with the requests.
+    headers: HeaderMap,
+    /// It stores how the pages of the results are numbered by the engine.
+    pagination: Pagination,
+    /// It stores the css selectors locating the results in the response of the engine.
+    selectors: ResultSelectors,
+}
+
+impl DeclarativeEngine {
+    /// Constructs the upstream search engine described by the provided definition.
+    ///
+    /// # Arguments
+    ///
+    /// * `definition` - It takes the deserialized definition file as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns the reason why the definition is invalid if its name is empty, if its url template
+    /// does not contain the `{query}` placeholder or is not a valid http(s) url, or if one of its
+    /// headers or of its selectors is not valid.
+    fn from_definition(definition: EngineDefinition) -> std::result::Result<Self, String> {
+        let name = definition.name.trim().to_lowercase();
+        if name.is_empty() {
+            return Err("the `name` of the engine is empty".to_owned());
+        }
+        if !definition.url.contains("{query}") {
+            return Err("the `url` does not contain the `{query}` placeholder".to_owned());
+        }
+        let categories = if definition.categories.is_empty() {
+            vec!["general"]
+        } else {
+            definition
+                .categories
+                .iter()
+                .map(|category| intern(&category.to_lowercase()))
+                .collect()
+        };
+
+        let mut headers = HeaderMap::new();
+        for (header, value) in &definition.headers {
+            headers.insert(
+                HeaderName::try_from(header)
+                    .map_err(|_| format!("`{header}` is not a valid header name"))?,
+                HeaderValue::try_from(value)
+                    .map_err(|_| format!("the value of the `{header}` header is not valid"))?,
+            );
+        }
+
+        let selectors = &definition.selectors;
+        let engine = DeclarativeEngine {
+            name: intern(&name),
+            categories,
+            url: definition.url.clone(),
+            headers,
+            pagination: definition.pagination,
+            selectors: ResultSelectors {
+                results: parse_selector(&selectors.results)?,
+                title: parse_selector(&selectors.title)?,
+                url: parse_selector(&selectors.url)?,
+                description: selectors
+                    .description
+                    .as_deref()
+                    .map(parse_selector)
+                    .transpose()?,
+                no_results: selectors
+                    .no_results
+                    .as_deref()
+                    .map(parse_selector)
+                    .transpose()?,
+            },
+        };
+
+        let url = engine.build_url("query", 0, &SearchOptions::default());
+        match Url::parse(&url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(engine),
+            _ => Err("the `url` is not a valid http(s) url".to_owned()),
+        }
+    }
+
+    /// A helper function which fills the url template of the engine in.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query as an argument.
+    /// * `page` - It takes the page number (starting from `0`) as an argument.
+    /// * `options` - It takes the search preferences of the user as an argument.
+    fn build_url(&self, query: &str, page: u32, options: &SearchOptions) -> String {
+        let page = self.pagination.start + page * self.pagination.step;
+        self.url
+            .replace("{query}", &encode_query(query))
+            .replace("{page}", &page.to_string())
+            .replace("{safe_search}", &options.safe_search.to_string())
+            .replace(
+                "{language}",
+                options.language.as_deref().unwrap_or_default(),
+            )
+            .replace("{region}", options.region.as_deref().unwrap_or_default())
+            .replace(
+                "{time_range}",
+                options
+                    .time_range
+                    .map_or("", |time_range| time_range.name()),
+            )
+    }
+
+    /// A helper function which extracts the results from the response of the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - It takes the body of the response as an argument.
+    /// * `base_url` - It takes the url of the request as an argument, against which the relative
+    ///   urls of the results are resolved.
+    ///
+    /// # Error
+    ///
+    /// Returns an `EmptyResultSet` error if the engine found nothing or if none of the results
+    /// could be extracted.
+    fn parse_results(&self, body: &str, base_url: &Url) -> Result<EngineResults, EngineError> {
+        let document = Html::parse_document(body);
+        if let Some(no_results) = &self.selectors.no_results {
+            if document.select(no_results).next().is_some() {
+                return Err(Report::new(EngineError::EmptyResultSet));
+            }
+        }
+
+        let results: HashMap<String, SearchResult> = document
+            .select(&self.selectors.results)
+            .filter_map(|result| {
+                let title = element_text(&result.select(&self.selectors.title).next()?);
+                let link = result.select(&self.selectors.url).next()?;
+                let url = match link.value().attr("href") {
+                    Some(href) => base_url.join(href.trim()).ok()?,
+                    None => Url::parse(&element_text(&link)).ok()?,
+                };
+                if !matches!(url.scheme(), "http" | "https") {
+                    return None;
+                }
+                let description = self
+                    .selectors
+                    .description
+                    .as_ref()
+                    .and_then(|description| result.select(description).next())
+                    .map(|description| element_text(&description))
+                    .unwrap_or_default();
+                Some(SearchResult::new(
+                    &title,
+                    url.as_str(),
+                    &description,
+                    &[self.name],
+                ))
+            })
+            .enumerate()
+            .map(|(index, mut search_result)| {
+                search_result.rank = index + 1;
+                (search_result.url.clone(), search_result)
+            })
+            .collect();
+
+        if results.is_empty() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+        Ok(EngineResults::from(results))
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchEngine for DeclarativeEngine {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn categories(&self) -> &[&'static str] {
+        &self.categories
+    }
+
+    async fn fetch_results(
+        &self,
+        query: &str,
+        page: u32,
+        user_agent: &str,
+        client: &Client,
+        options: &SearchOptions,
+        _engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError> {
+        let url = Url::parse(&self.build_url(query, page, options))
+            .change_context(EngineError::UnexpectedError)?;
+
+        let mut header_map = self.headers.clone();
+        header_map.insert(
+            USER_AGENT,
+            HeaderValue::from_str(user_agent).change_context(EngineError::UnexpectedError)?,
+        );
+
+        let body = self
+            .fetch_html_from_upstream(url.as_str(), options.request_headers(header_map), client)
+            .await?;
+        self.parse_results(&body, &url)
+    }
+
+    fn supports_safe_search(&self) -> bool {
+        self.url.contains("{safe_search}")
+    }
+}
+
+/// A function which loads the upstream search engines defined in the TOML (`.toml`) and JSON
+/// (`.json`) files of the provided directory, the other files are ignored.
+///
+/// # Arguments
+///
+/// * `directory` - It takes the path of the directory as an argument.
+///
+/// # Error
+///
+/// Returns a `ConfigError` pointing at the `engine_definitions` option if the directory could not
+/// be read or if one of the definition files is not valid.
+pub fn load_definitions(
+    directory: &str,
+) -> std::result::Result<Vec<DeclarativeEngine>, ConfigError> {
+    let invalid = |reason: String| ConfigError::InvalidOption {
+        key: "engine_definitions".to_owned(),
+        reason,
+    };
+
+    let mut paths: Vec<_> = fs::read_dir(directory)
+        .map_err(|error| {
+            invalid(format!(
+                "the directory `{directory}` cannot be read: {error}"
+            ))
+        })?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    // The definitions are loaded in a stable order, so that the last of the definitions with the
+    // same name always wins.
+    paths.sort_unstable();
+
+    let mut engines = Vec::new();
+    for path in paths {
+        let Some(definition) = read_definition(&path)
+            .map_err(|reason| invalid(format!("`{}`: {reason}", path.display())))?
+        else {
+            continue;
+        };
+        engines.push(
+            DeclarativeEngine::from_definition(definition)
+                .map_err(|reason| invalid(format!("`{}`: {reason}", path.display())))?,
+        );
+    }
+    Ok(engines)
+}
+
+/// A helper function which reads and deserializes a definition file based on its extension.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path of the file as an argument.
+///
+/// # Returns
+///
+/// It returns the definition or `None` if the file is neither a TOML nor a JSON file.
+fn read_definition(path: &Path) -> std::result::Result<Option<EngineDefinition>, String> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    let Some(extension @ ("toml" | "json")) = extension.as_deref() else {
+        return Ok(None);
+    };
+
+    let source = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let definition = match extension {
+        "toml" => toml::from_str(&source).map_err(|error| error.to_string())?,
+        _ => serde_json::from_str(&source).map_err(|error| error.to_string())?,
+    };
+    Ok(Some(definition))
+}
+
+/// A helper function which parses a css selector of a definition file.
+///
+/// # Arguments
+///
+/// * `selector` - It takes the css selector as an argument.
+fn parse_selector(selector: &str) -> std::result::Result<Selector, String> {
+    Selector::parse(selector)
+        .map_err(|error| format!("`{selector}` is not a valid selector: {error}"))
+}
+
+/// A helper function which returns the text of an element with its whitespace collapsed.
+///
+/// # Arguments
+///
+/// * `element` - It takes the element as an argument.
+fn element_text(element: &ElementRef<'_>) -> String {
+    element
+        .text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"
+        name = "Example"
+        url = "https://example.com/search?q={query}&first={page}&lang={language}"
+
+        [headers]
+        Accept = "text/html"
+
+        [pagination]
+        start = 0
+        step = 10
+
+        [selectors]
+        results = ".result"
+        title = "h3"
+        url = "h3 a"
+        description = ".snippet"
+        no_results = ".no-results"
+    "#;
+
+    fn engine() -> DeclarativeEngine {
+        DeclarativeEngine::from_definition(toml::from_str(DEFINITION).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_declarative_engine() {
+        let engine = engine();
+        assert_eq!(engine.name(), "example");
+        assert_eq!(engine.categories(), ["general"]);
+        assert!(!engine.supports_safe_search());
+
+        let options = SearchOptions {
+            language: Some("fr".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            engine.build_url("rust lang", 2, &options),
+            "https://example.com/search?q=rust+lang&first=20&lang=fr"
+        );
+
+        let base_url = Url::parse("https://example.com/search?q=rust").unwrap();
+        let results = engine
+            .parse_results(
+                r#"<div class="result">
+                    <h3><a href="/rust">Rust</a></h3>
+                    <p class="snippet">A   <b>language</b></p>
+                </div>
+                <div class="result"><h3>No link</h3></div>
+                <div class="result"><h3><a href="https://www.rust-lang.org/">Rust</a></h3></div>"#,
+                &base_url,
+            )
+            .unwrap()
+            .results;
+        assert_eq!(results.len(), 2);
+        let result = &results["https://example.com/rust"];
+        assert_eq!(result.title, "Rust");
+        assert_eq!(result.description, "A language");
+        assert_eq!(result.rank, 1);
+        assert_eq!(results["https://www.rust-lang.org/"].rank, 2);
+
+        assert!(engine
+            .parse_results(r#"<p class="no-results">Nothing</p>"#, &base_url)
+            .is_err());
+    }
+
+    #[test]
+    fn test_json_definitions() {
+        let definition: EngineDefinition = serde_json::from_str(
+            r#"{
+                "name": "example",
+                "categories": ["news"],
+                "url": "https://example.com/news?q={query}&safe={safe_search}",
+                "selectors": { "results": "article", "title": "h2", "url": "a" }
+            }"#,
+        )
+        .unwrap();
+        let engine = DeclarativeEngine::from_definition(definition).unwrap();
+        assert_eq!(engine.categories(), ["news"]);
+        assert_eq!(engine.pagination, Pagination::default());
+        assert!(engine.supports_safe_search());
+    }
+
+    #[test]
+    fn test_invalid_definitions_are_rejected() {
+        let definition = |url: &str, title: &str| EngineDefinition {
+            name: "example".to_owned(),
+            categories: Vec::new(),
+            url: url.to_owned(),
+            headers: HashMap::new(),
+            pagination: Pagination::default(),
+            selectors: SelectorDefinitions {
+                results: ".result".to_owned(),
+                title: title.to_owned(),
+                url: "a".to_owned(),
+                description: None,
+                no_results: None,
+            },
+        };
+
+        assert!(DeclarativeEngine::from_definition(definition(
+            "https://example.com/?q={query}",
+            "h3"
+        ))
+        .is_ok());
+        assert!(
+            DeclarativeEngine::from_definition(definition("https://example.com/", "h3")).is_err()
+        );
+        assert!(
+            DeclarativeEngine::from_definition(definition("ftp://example.com/{query}", "h3"))
+                .is_err()
+        );
+        assert!(DeclarativeEngine::from_definition(definition(
+            "https://example.com/?q={query}",
+            "h3["
+        ))
+        .is_err());
+        assert!(load_definitions("missing_engine_definitions").is_err());
+    }
+}
@@ -8,9 +8,11 @@ use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
+use crate::models::locale_models::supported_region;
+use crate::models::parser_models::{EngineConfig, SafeSearchLevel};
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SearchEngine, SearchOptions};
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -33,34 +35,37 @@ impl DuckDuckGo {
                 ".result__title>.result__a",
                 ".result__url",
                 ".result__snippet",
-            )?,
+            )?
+            .with_corrected_query("#did_you_mean a")?,
         })
     }
 }
 
 #[async_trait::async_trait]
 impl SearchEngine for DuckDuckGo {
-    async fn results(
+    fn name(&self) -> &'static str {
+        "duckduckgo"
+    }
+
+    async fn fetch_results(
         &self,
         query: &str,
         page: u32,
         user_agent: &str,
         client: &Client,
-        _safe_search: u8,
-    ) -> Result<HashMap<String, SearchResult>, EngineError> {
-        // Page number can be missing or empty string and so appropriate handling is required
-        // so that upstream server recieves valid page number.
-        let url: String = match page {
-            0 => {
-                format!("https://html.duckduckgo.com/html/?q={query}&s=&dc=&v=1&o=json&api=/d.js")
-            }
-            _ => {
-                format!(
-                    "https://duckduckgo.com/html/?q={query}&s={}&dc={}&v=1&o=json&api=/d.js",
-                    page * 30,
-                    page * 30 + 1
-                )
-            }
+        options: &SearchOptions,
+        engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError> {
+        let url = search_url(query, page, options);
+
+        // DuckDuckGo uses the `kl` cookie to select the region and the language of the results.
+        let region = region_code(options, engine_config);
+
+        // DuckDuckGo uses the `kp` cookie to select the safe search level.
+        let safe_search_level = match SafeSearchLevel::from_level(options.safe_search) {
+            SafeSearchLevel::Off => "-2",
+            SafeSearchLevel::Moderate => "-1",
+            SafeSearchLevel::Strict => "1",
         };
 
         // initializing HeaderMap and adding appropriate headers.
@@ -71,12 +76,21 @@ impl SearchEngine for DuckDuckGo {
                 "CONTENT_TYPE".to_string(),
                 "application/x-www-form-urlencoded".to_string(),
             ),
-            ("COOKIE".to_string(), "kl=wt-wt".to_string()),
+            (
+                "COOKIE".to_string(),
+                format!("kl={region}; kp={safe_search_level}"),
+            ),
         ]))
         .change_context(EngineError::UnexpectedError)?;
 
         let document: Html = Html::parse_document(
-            &DuckDuckGo::fetch_html_from_upstream(self, &url, header_map, client).await?,
+            &DuckDuckGo::fetch_html_from_upstream(
+                self,
+                &url,
+                options.request_headers(header_map),
+                client,
+            )
+            .await?,
         );
 
         if self.parser.parse_for_no_results(&document).next().is_some() {
@@ -94,4 +108,195 @@ impl SearchEngine for DuckDuckGo {
                 ))
             })
     }
+
+    fn supports_query_operators(&self) -> bool {
+        true
+    }
+
+    fn supports_safe_search(&self) -> bool {
+        true
+    }
+
+    fn blocked_page_signatures(&self) -> &'static [&'static str] {
+        &["anomaly-modal", "challenge-form"]
+    }
+}
+
+/// A helper function which returns the DuckDuckGo region code (`kl` cookie) for the provided
+/// country code.
+///
+/// # Arguments
+///
+/// * `country` - It takes the lowercase ISO 3166-1 alpha-2 country code as an argument.
+fn duckduckgo_region_from_country(country: &str) -> Option<&'static str> {
+    Some(match country {
+        "ar" => "ar-es",
+        "au" => "au-en",
+        "at" => "at-de",
+        "be" => "be-nl",
+        "br" => "br-pt",
+        "ca" => "ca-en",
+        "cn" => "cn-zh",
+        "fr" => "fr-fr",
+        "de" => "de-de",
+        "in" => "in-en",
+        "it" => "it-it",
+        "jp" => "jp-jp",
+        "kr" => "kr-kr",
+        "mx" => "mx-es",
+        "nl" => "nl-nl",
+        "pl" => "pl-pl",
+        "pt" => "pt-pt",
+        "ru" => "ru-ru",
+        "es" => "es-es",
+        "se" => "se-sv",
+        "ch" => "ch-de",
+        "tr" => "tr-tr",
+        "ua" => "ua-uk",
+        "gb" => "uk-en",
+        "us" => "us-en",
+        _ => return None,
+    })
+}
+
+/// A helper function which returns the DuckDuckGo region code (`kl` cookie) for the provided
+/// language code.
+///
+/// # Arguments
+///
+/// * `language` - It takes the ISO 639-1 language code as an argument.
+fn duckduckgo_region(language: &str) -> Option<&'static str> {
+    Some(match language {
+        "ar" => "xa-ar",
+        "de" => "de-de",
+        "en" => "us-en",
+        "es" => "es-es",
+        "fr" => "fr-fr",
+        "hi" => "in-en",
+        "it" => "it-it",
+        "ja" => "jp-jp",
+        "ko" => "kr-kr",
+        "nl" => "nl-nl",
+        "pl" => "pl-pl",
+        "pt" => "pt-pt",
+        "ru" => "ru-ru",
+        "sv" => "se-sv",
+        "tr" => "tr-tr",
+        "uk" => "ua-uk",
+        "zh" => "cn-zh",
+        _ => return None,
+    })
+}
+
+/// A helper function which builds the url of the search page of DuckDuckGo with the parameters
+/// selecting the page and the time range.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query as an argument.
+/// * `page` - It takes the page number as an argument.
+/// * `options` - It takes the search options selected by the user as an argument.
+fn search_url(query: &str, page: u32, options: &SearchOptions) -> String {
+    // Page number can be missing or empty string and so appropriate handling is required
+    // so that upstream server recieves valid page number.
+    let mut url: String = match page {
+        0 => {
+            format!("https://html.duckduckgo.com/html/?q={query}&s=&dc=&v=1&o=json&api=/d.js")
+        }
+        _ => {
+            format!(
+                "https://duckduckgo.com/html/?q={query}&s={}&dc={}&v=1&o=json&api=/d.js",
+                page * 30,
+                page * 30 + 1
+            )
+        }
+    };
+
+    // DuckDuckGo uses the `df` parameter to restrict the results to a time range.
+    if let Some(time_range) = options.time_range {
+        url.push_str(&format!("&df={}", &time_range.name()[..1]));
+    }
+
+    url
+}
+
+/// A helper function which returns the region code passed to DuckDuckGo via the `kl` cookie,
+/// `wt-wt` stands for no region. The region selected by the user takes precedence over the one
+/// provided via the config file which takes precedence over the selected language.
+///
+/// # Arguments
+///
+/// * `options` - It takes the search options selected by the user as an argument.
+/// * `engine_config` - It takes the config of the engine provided via the config file as an
+///   argument.
+fn region_code(options: &SearchOptions, engine_config: &EngineConfig) -> &'static str {
+    options
+        .region
+        .as_deref()
+        .or(engine_config.region.as_deref())
+        .and_then(supported_region)
+        .and_then(duckduckgo_region_from_country)
+        .or_else(|| options.language.as_deref().and_then(duckduckgo_region))
+        .unwrap_or("wt-wt")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::engine_models::{is_blocked_page, TimeRange};
+
+    #[test]
+    fn test_blocked_page() {
+        let engine = DuckDuckGo::new().unwrap();
+        let signatures = engine.blocked_page_signatures();
+
+        assert!(is_blocked_page(
+            r#"<div class="anomaly-modal__title">Unfortunately, bots use DuckDuckGo too.</div>"#,
+            signatures
+        ));
+        assert!(!is_blocked_page(
+            r#"<div class="results"><div class="result">captcha</div></div>"#,
+            signatures
+        ));
+    }
+
+    #[test]
+    fn test_search_url_time_range() {
+        let options = SearchOptions {
+            time_range: Some(TimeRange::Month),
+            ..Default::default()
+        };
+
+        assert!(search_url("rust", 0, &options).ends_with("&df=m"));
+        assert!(!search_url("rust", 0, &SearchOptions::default()).contains("&df="));
+    }
+    #[test]
+    fn test_region_code() {
+        let engine_config = EngineConfig {
+            region: Some("GB".to_owned()),
+            ..Default::default()
+        };
+        let options = |region: Option<&str>, language: Option<&str>| SearchOptions {
+            region: region.map(str::to_owned),
+            language: language.map(str::to_owned),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            region_code(&options(Some("de"), Some("fr")), &engine_config),
+            "de-de"
+        );
+        assert_eq!(
+            region_code(&options(None, Some("fr")), &engine_config),
+            "uk-en"
+        );
+        assert_eq!(
+            region_code(&options(None, Some("fr")), &EngineConfig::default()),
+            "fr-fr"
+        );
+        assert_eq!(
+            region_code(&options(Some("xx"), None), &EngineConfig::default()),
+            "wt-wt"
+        );
+    }
 }
@@ -7,8 +7,9 @@ use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::aggregation_models::{EngineResults, SearchResult};
+use crate::models::engine_models::{EngineError, SearchEngine, SearchOptions};
+use crate::models::parser_models::EngineConfig;
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -41,6 +42,10 @@ impl LibreX {
 
 #[async_trait::async_trait]
 impl SearchEngine for LibreX {
+    fn name(&self) -> &'static str {
+        "librex"
+    }
+
     /// Retrieves search results from LibreX based on the provided query, page, user agent, and client.
     ///
     /// # Arguments
@@ -49,20 +54,22 @@ impl SearchEngine for LibreX {
     /// * `page` - The page number for pagination.
     /// * `user_agent` - The user agent string.
     /// * `client` - The reqwest client for making HTTP requests.
-    /// * `_safe_search` - A parameter for safe search (not currently used).
+    /// * `options` - The search preferences of the user like the language of the results.
+    /// * `_engine_config` - The engine specific options provided via the config file (not currently used).
     ///
     /// # Returns
     ///
-    /// Returns a `Result` containing a `HashMap` of search results if successful, otherwise an `EngineError`.
+    /// Returns a `Result` containing the `EngineResults` scraped from the engine if successful, otherwise an `EngineError`.
     /// The `Err` variant is explicit for better documentation.
-    async fn results(
+    async fn fetch_results(
         &self,
         query: &str,
         page: u32,
         user_agent: &str,
         client: &Client,
-        _safe_search: u8,
-    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        options: &SearchOptions,
+        _engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError> {
         // Page number can be missing or empty string and so appropriate handling is required
         // so that upstream server recieves valid page number.
         let url: String = format!(
@@ -77,13 +84,22 @@ impl SearchEngine for LibreX {
             ("CONTENT_TYPE".to_string(), "application/x-www-form-urlencoded".to_string()),
             (
                 "COOKIE".to_string(),
-                "theme=amoled; disable_special=on; disable_frontends=on; language=en; number_of_results=10; safe_search=on; save=1".to_string(),
+                format!(
+                    "theme=amoled; disable_special=on; disable_frontends=on; language={}; number_of_results=10; safe_search=on; save=1",
+                    options.language.as_deref().unwrap_or("en")
+                ),
             ),
         ]))
         .change_context(EngineError::UnexpectedError)?;
 
         let document: Html = Html::parse_document(
-            &LibreX::fetch_html_from_upstream(self, &url, header_map, client).await?,
+            &LibreX::fetch_html_from_upstream(
+                self,
+                &url,
+                options.request_headers(header_map),
+                client,
+            )
+            .await?,
         );
 
         if self.parser.parse_for_no_results(&document).next().is_some() {
@@ -0,0 +1,418 @@
+//! The `lua` module handles the upstream search engines defined in lua scripts provided via the
+//! `engine_scripts` config option. A script builds the request sent to the engine with its
+//! `request` function and extracts the results from the response of the engine with its `parse`
+//! function, so that simple engines can be added or patched without recompiling the server.
+//!
+//! # Example
+//!
+//! ```lua
+//! name = "example"
+//! categories = { "general" }
+//!
+//! function request(query, page, options)
+//!     return {
+//!         url = "https://example.com/search?q=" .. encode(query) .. "&page=" .. (page + 1),
+//!         headers = { ["Accept-Language"] = options.language },
+//!     }
+//! end
+//!
+//! function parse(body)
+//!     local results = {}
+//!     for _, result in ipairs(select_all(body, ".result")) do
+//!         local link = select_all(result.html, "a")[1]
+//!         table.insert(results, {
+//!             title = link.text,
+//!             url = link.attrs.href,
+//!             description = select_all(result.html, ".snippet")[1].text,
+//!         })
+//!     end
+//!     return results
+//! end
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use error_stack::{Report, Result, ResultExt};
+use mlua::{Function, HookTriggers, Lua, Table};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT},
+    Client, Url,
+};
+use scraper::{Html, Selector};
+
+use super::registry::intern;
+use crate::{
+    models::{
+        aggregation_models::{EngineResults, SearchResult},
+        engine_models::{EngineError, SearchEngine, SearchOptions},
+        parser_models::EngineConfig,
+    },
+    query::bangs::encode_query,
+};
+
+/// The longest time for which a script can run each time it is called, after which it is stopped
+/// with an error so that a slow or looping script cannot hold the threads of the server.
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The number of the lua instructions run between two checks of the `SCRIPT_TIMEOUT`.
+const SCRIPT_TIMEOUT_CHECK_INTERVAL: u32 = 1_000;
+
+/// An upstream search engine defined in a lua script.
+pub struct LuaEngine {
+    /// It stores the search categories provided by the `categories` global of the script.
+    categories: Vec<&'static str>,
+    /// It stores the script defining the engine, which is shared with the blocking threads on
+    /// which it is run.
+    script: Arc<LuaScript>,
+}
+
+/// The lua script defining an upstream search engine.
+struct LuaScript {
+    /// It stores the name of the engine provided by the `name` global of the script.
+    name: &'static str,
+    /// It stores the path of the script, which is used as the name of the lua chunk so that the
+    /// errors point at the script.
+    path: String,
+    /// It stores the source code of the script, which is run in a new lua state for each request
+    /// as the lua states cannot be shared between the threads.
+    source: String,
+}
+
+impl LuaEngine {
+    /// Loads the upstream search engine defined in the lua script at the provided path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - It takes the path of the lua script as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns a lua error if the script could not be read or run, or if it does not define the
+    /// `name` of the engine along with its `request` and `parse` functions.
+    pub fn from_script(path: &str) -> mlua::Result<Self> {
+        let source = fs::read_to_string(path).map_err(mlua::Error::external)?;
+        let lua = script_state(path, &source)?;
+        let globals = lua.globals();
+
+        let name: String = globals
+            .get::<_, Option<String>>("name")?
+            .filter(|name| !name.trim().is_empty())
+            .ok_or_else(|| {
+                mlua::Error::RuntimeError("the `name` of the engine is missing".to_owned())
+            })?;
+        for function in ["request", "parse"] {
+            if globals.get::<_, Option<Function<'_>>>(function)?.is_none() {
+                return Err(mlua::Error::RuntimeError(format!(
+                    "the `{function}` function is missing"
+                )));
+            }
+        }
+        let categories: Vec<&'static str> = globals
+            .get::<_, Option<Vec<String>>>("categories")?
+            .unwrap_or_else(|| vec!["general".to_owned()])
+            .iter()
+            .map(|category| intern(&category.to_lowercase()))
+            .collect();
+
+        Ok(LuaEngine {
+            categories,
+            script: Arc::new(LuaScript {
+                name: intern(&name.trim().to_lowercase()),
+                path: path.to_owned(),
+                source,
+            }),
+        })
+    }
+}
+
+impl LuaScript {
+    /// A helper function which calls the `request` function of the script to build the url and
+    /// the headers of the request sent to the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query as an argument.
+    /// * `page` - It takes the page number (starting from `0`) as an argument.
+    /// * `options` - It takes the search preferences of the user as an argument.
+    fn build_request(
+        &self,
+        query: &str,
+        page: u32,
+        options: &SearchOptions,
+    ) -> mlua::Result<(String, HashMap<String, String>)> {
+        let lua = script_state(&self.path, &self.source)?;
+        let lua_options = lua.create_table()?;
+        lua_options.set("safe_search", options.safe_search)?;
+        lua_options.set("language", options.language.as_deref())?;
+        lua_options.set("region", options.region.as_deref())?;
+        lua_options.set(
+            "time_range",
+            options.time_range.map(|time_range| time_range.name()),
+        )?;
+
+        let request: Table<'_> =
+            lua.globals()
+                .get::<_, Function<'_>>("request")?
+                .call((query, page, lua_options))?;
+        Ok((
+            request.get("url")?,
+            request
+                .get::<_, Option<HashMap<String, String>>>("headers")?
+                .unwrap_or_default(),
+        ))
+    }
+
+    /// A helper function which calls the `parse` function of the script to extract the results
+    /// from the response of the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - It takes the body of the response as an argument.
+    fn parse_results(&self, body: &str) -> mlua::Result<HashMap<String, SearchResult>> {
+        let lua = script_state(&self.path, &self.source)?;
+        let results: Vec<Table<'_>> = lua.globals().get::<_, Function<'_>>("parse")?.call(body)?;
+
+        let mut search_results = HashMap::new();
+        for result in results {
+            // Only the absolute http(s) urls are kept, so that the scripts cannot put links like
+            // `javascript:` urls on the search page.
+            let url: String = result.get("url")?;
+            if !Url::parse(url.trim()).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+                continue;
+            }
+            let mut search_result = SearchResult::new(
+                &result.get::<_, String>("title")?,
+                url.trim(),
+                &result
+                    .get::<_, Option<String>>("description")?
+                    .unwrap_or_default(),
+                &[self.name],
+            );
+            search_result.rank = search_results.len() + 1;
+            search_results.insert(search_result.url.clone(), search_result);
+        }
+        Ok(search_results)
+    }
+}
+
+#[async_trait::async_trait]
+impl SearchEngine for LuaEngine {
+    fn name(&self) -> &'static str {
+        self.script.name
+    }
+
+    fn categories(&self) -> &[&'static str] {
+        &self.categories
+    }
+
+    async fn fetch_results(
+        &self,
+        query: &str,
+        page: u32,
+        user_agent: &str,
+        client: &Client,
+        options: &SearchOptions,
+        _engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError> {
+        // The scripts are run on the blocking threads, so that they never hold the threads
+        // handling the other requests.
+        let (script, request_query, request_options) =
+            (self.script.clone(), query.to_owned(), options.clone());
+        let (url, headers) = tokio::task::spawn_blocking(move || {
+            script.build_request(&request_query, page, &request_options)
+        })
+        .await
+        .change_context(EngineError::UnexpectedError)?
+        .change_context(EngineError::UnexpectedError)?;
+
+        let mut header_map = HeaderMap::new();
+        header_map.insert(
+            USER_AGENT,
+            HeaderValue::from_str(user_agent).change_context(EngineError::UnexpectedError)?,
+        );
+        for (name, value) in headers {
+            header_map.insert(
+                HeaderName::try_from(name).change_context(EngineError::UnexpectedError)?,
+                HeaderValue::try_from(value).change_context(EngineError::UnexpectedError)?,
+            );
+        }
+
+        let body = self
+            .fetch_html_from_upstream(&url, options.request_headers(header_map), client)
+            .await?;
+        let script = self.script.clone();
+        let results = tokio::task::spawn_blocking(move || script.parse_results(&body))
+            .await
+            .change_context(EngineError::UnexpectedError)?
+            .change_context(EngineError::UnexpectedError)?;
+        if results.is_empty() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+        Ok(EngineResults::from(results))
+    }
+}
+
+/// A helper function which runs the provided script in a new lua state, along with the helper
+/// functions available to the scripts. The JIT compiler is turned off so that the state can stop
+/// the script with an error once it ran for longer than the `SCRIPT_TIMEOUT`. The helper functions
+/// are:
+///
+/// * `encode(text)` percent-encodes the text to be put in a url.
+/// * `select_all(html, selector)` returns the elements of the html matching the css selector, as
+///   tables containing their `text`, their inner `html` and their `attrs`.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path of the script as an argument.
+/// * `source` - It takes the source code of the script as an argument.
+fn script_state(path: &str, source: &str) -> mlua::Result<Lua> {
+    let lua = Lua::new();
+    // The instruction hooks are not run by the code compiled by the JIT compiler.
+    lua.load("if jit then jit.off() end").exec()?;
+    let deadline = Instant::now() + SCRIPT_TIMEOUT;
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(SCRIPT_TIMEOUT_CHECK_INTERVAL),
+        move |_, _| match Instant::now() > deadline {
+            true => Err(mlua::Error::RuntimeError(format!(
+                "the script ran for longer than {} secs",
+                SCRIPT_TIMEOUT.as_secs()
+            ))),
+            false => Ok(()),
+        },
+    );
+    let globals = lua.globals();
+    globals.set(
+        "encode",
+        lua.create_function(|_, text: String| Ok(encode_query(&text)))?,
+    )?;
+    globals.set(
+        "select_all",
+        lua.create_function(|lua, (html, selector): (String, String)| {
+            let selector = Selector::parse(&selector).map_err(|error| {
+                mlua::Error::RuntimeError(format!("`{selector}` is not a valid selector: {error}"))
+            })?;
+            let elements = lua.create_table()?;
+            for element in Html::parse_fragment(&html).select(&selector) {
+                let attrs = lua.create_table()?;
+                for (name, value) in element.value().attrs() {
+                    attrs.set(name, value)?;
+                }
+                let item = lua.create_table()?;
+                item.set("text", element.text().collect::<String>().trim())?;
+                item.set("html", element.inner_html())?;
+                item.set("attrs", attrs)?;
+                elements.push(item)?;
+            }
+            Ok(elements)
+        })?,
+    )?;
+    drop(globals);
+
+    lua.load(source).set_name(path).exec()?;
+    Ok(lua)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        name = "Example"
+
+        function request(query, page, options)
+            return {
+                url = "https://example.com/search?q=" .. encode(query) .. "&page=" .. (page + 1),
+                headers = { ["Accept-Language"] = options.language },
+            }
+        end
+
+        function parse(body)
+            local results = {}
+            for _, result in ipairs(select_all(body, ".result")) do
+                local link = select_all(result.html, "a")[1]
+                table.insert(results, {
+                    title = link.text,
+                    url = link.attrs.href,
+                    description = select_all(result.html, ".snippet")[1].text,
+                })
+            end
+            return results
+        end
+    "#;
+
+    fn engine() -> LuaEngine {
+        let path = std::env::temp_dir().join("websurfx_example_engine.lua");
+        fs::write(&path, SCRIPT).unwrap();
+        LuaEngine::from_script(path.to_str().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_lua_engine() {
+        let engine = engine();
+        assert_eq!(engine.name(), "example");
+        assert_eq!(engine.categories(), ["general"]);
+
+        let options = SearchOptions {
+            language: Some("fr".to_owned()),
+            ..Default::default()
+        };
+        let (url, headers) = engine
+            .script
+            .build_request("rust lang", 1, &options)
+            .unwrap();
+        assert_eq!(url, "https://example.com/search?q=rust+lang&page=2");
+        assert_eq!(headers["Accept-Language"], "fr");
+
+        let results = engine
+            .script
+            .parse_results(
+                r#"<div class="result">
+                    <a href="javascript:alert(1)">Script</a>
+                    <p class="snippet">Not a link</p>
+                </div>
+                <div class="result">
+                    <a href="https://www.rust-lang.org/">Rust</a>
+                    <p class="snippet">A <b>language</b></p>
+                </div>"#,
+            )
+            .unwrap();
+        // The results whose url is not an http(s) url are dropped.
+        assert_eq!(results.len(), 1);
+        let result = &results["https://www.rust-lang.org/"];
+        assert_eq!(result.title, "Rust");
+        assert_eq!(result.description, "A language");
+        assert_eq!(result.engine.as_slice(), ["example"]);
+        assert_eq!(result.rank, 1);
+    }
+
+    #[test]
+    fn test_looping_scripts_are_stopped() {
+        let path = std::env::temp_dir().join("websurfx_looping_engine.lua");
+        fs::write(
+            &path,
+            SCRIPT.replace(
+                "local results = {}",
+                "while true do end\nlocal results = {}",
+            ),
+        )
+        .unwrap();
+        let engine = LuaEngine::from_script(path.to_str().unwrap()).unwrap();
+
+        let started_at = Instant::now();
+        assert!(engine.script.parse_results("").is_err());
+        assert!(started_at.elapsed() < SCRIPT_TIMEOUT * 5);
+    }
+
+    #[test]
+    fn test_invalid_scripts_are_rejected() {
+        let path = std::env::temp_dir().join("websurfx_invalid_engine.lua");
+        fs::write(&path, "name = 'invalid'\nfunction request() end").unwrap();
+        assert!(LuaEngine::from_script(path.to_str().unwrap()).is_err());
+        assert!(LuaEngine::from_script("missing_engine.lua").is_err());
+    }
+}
@@ -4,10 +4,17 @@
 //! code. Moreover, it also provides a custom error for the upstream search engine handling code.
 
 pub mod bing;
+pub mod block_backoff;
 pub mod brave;
+pub mod circuit_breaker;
+pub mod declarative;
 pub mod duckduckgo;
 pub mod librex;
+pub mod lua;
 pub mod mojeek;
+pub mod rate_limit;
+pub mod registry;
+pub mod reverse_image;
 pub mod search_result_parser;
 pub mod searx;
 pub mod startpage;
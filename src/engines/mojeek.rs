@@ -8,9 +8,10 @@ use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
+use crate::models::parser_models::EngineConfig;
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SearchEngine, SearchOptions};
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -40,14 +41,19 @@ impl Mojeek {
 
 #[async_trait::async_trait]
 impl SearchEngine for Mojeek {
-    async fn results(
+    fn name(&self) -> &'static str {
+        "mojeek"
+    }
+
+    async fn fetch_results(
         &self,
         query: &str,
         page: u32,
         user_agent: &str,
         client: &Client,
-        safe_search: u8,
-    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        options: &SearchOptions,
+        _engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError> {
         // Mojeek uses `start results from this number` convention
         // So, for 10 results per page, page 0 starts at 1, page 1
         // starts at 11, and so on.
@@ -73,7 +79,11 @@ impl SearchEngine for Mojeek {
             "You",
         ];
         let qss = search_engines.join("%2C");
-        let safe = if safe_search == 0 { "0" } else { "1" };
+        let safe = if options.safe_search == 0 { "0" } else { "1" };
+        // Mojeek uses the `lb` parameter to bias the results towards the provided language.
+        let language = options.language.as_deref().unwrap_or("en");
+        // Mojeek uses the `rb` parameter to bias the results towards the provided region.
+        let region = options.region.as_deref().unwrap_or_default().to_uppercase();
 
         // Mojeek detects automated requests, these are preferences that are
         // able to circumvent the countermeasure. Some of these are
@@ -87,7 +97,8 @@ impl SearchEngine for Mojeek {
             ("tlen", "100"),
             ("ref", "1"),
             ("hp", "minimal"),
-            ("lb", "en"),
+            ("lb", language),
+            ("rb", &region),
             ("qss", &qss),
             ("safe", safe),
         ];
@@ -125,7 +136,13 @@ impl SearchEngine for Mojeek {
         .change_context(EngineError::UnexpectedError)?;
 
         let document: Html = Html::parse_document(
-            &Mojeek::fetch_html_from_upstream(self, &url, header_map, client).await?,
+            &Mojeek::fetch_html_from_upstream(
+                self,
+                &url,
+                options.request_headers(header_map),
+                client,
+            )
+            .await?,
         );
 
         if let Some(no_result_msg) = self.parser.parse_for_no_results(&document).nth(0) {
@@ -148,4 +165,12 @@ impl SearchEngine for Mojeek {
                 ))
             })
     }
+
+    fn supports_safe_search(&self) -> bool {
+        true
+    }
+
+    fn blocked_page_signatures(&self) -> &'static [&'static str] {
+        &["appears to be sending automated queries"]
+    }
 }
@@ -0,0 +1,153 @@
+//! This module provides the token buckets which limit the rate of the requests sent to each
+//! upstream search engine, so that a busy instance does not get blocked by the engines. The
+//! requests exceeding the rate are queued briefly and the engine is skipped for the requests which
+//! would have to wait longer than that.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// The longest time for which a request waits for its turn before the upstream search engine is
+/// skipped instead.
+pub const MAX_QUEUE_DELAY: Duration = Duration::from_secs(1);
+
+/// A static variable which stores the token buckets of the upstream search engines, keyed by the
+/// name of the engine.
+static TOKEN_BUCKETS: OnceLock<Mutex<HashMap<&'static str, TokenBucket>>> = OnceLock::new();
+
+/// A token bucket which is refilled continuously at the configured rate and holds at most a
+/// second worth of tokens, so that the engines only see short bursts.
+struct TokenBucket {
+    /// It stores the number of the tokens left in the bucket, it becomes negative when the
+    /// requests are queued.
+    tokens: f64,
+    /// It stores the time at which the tokens were last refilled.
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Creates a full token bucket.
+    ///
+    /// # Arguments
+    ///
+    /// * `requests_per_second` - It takes the rate at which the bucket is refilled as an argument.
+    /// * `now` - It takes the current time as an argument.
+    fn new(requests_per_second: f64, now: Instant) -> Self {
+        Self {
+            tokens: burst_size(requests_per_second),
+            refilled_at: now,
+        }
+    }
+
+    /// A function which takes a token from the bucket for a new request.
+    ///
+    /// # Arguments
+    ///
+    /// * `requests_per_second` - It takes the rate at which the bucket is refilled as an argument.
+    /// * `now` - It takes the current time as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the time for which the request should wait for its turn, or `None` if it would
+    /// have to wait longer than `MAX_QUEUE_DELAY`, in which case no token is taken.
+    fn reserve(&mut self, requests_per_second: f64, now: Instant) -> Option<Duration> {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * requests_per_second).min(burst_size(requests_per_second));
+        self.refilled_at = now;
+
+        // The delays too long to be represented (with the tiny rates) are skipped as well.
+        let delay = Duration::try_from_secs_f64((1.0 - self.tokens).max(0.0) / requests_per_second)
+            .ok()
+            .filter(|delay| *delay <= MAX_QUEUE_DELAY)?;
+
+        self.tokens -= 1.0;
+        Some(delay)
+    }
+}
+
+/// A helper function which returns the number of the tokens a full bucket holds.
+///
+/// # Arguments
+///
+/// * `requests_per_second` - It takes the rate at which the bucket is refilled as an argument.
+fn burst_size(requests_per_second: f64) -> f64 {
+    requests_per_second.max(1.0)
+}
+
+/// A function which reserves the turn of a new request to the provided upstream search engine.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the upstream search engine as an argument.
+/// * `requests_per_second` - It takes the rate of the requests allowed for the engine as an
+///   argument.
+///
+/// # Returns
+///
+/// It returns the time for which the request should wait before it is sent, or `None` if the
+/// engine should be skipped for this request.
+pub fn reserve_request(engine: &'static str, requests_per_second: f32) -> Option<Duration> {
+    let requests_per_second = f64::from(requests_per_second);
+    let now = Instant::now();
+
+    TOKEN_BUCKETS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(engine)
+        .or_insert_with(|| TokenBucket::new(requests_per_second, now))
+        .reserve(requests_per_second, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, now);
+
+        // The burst is sent right away and the following requests are queued.
+        assert_eq!(bucket.reserve(2.0, now), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(2.0, now), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(2.0, now), Some(Duration::from_millis(500)));
+        assert_eq!(bucket.reserve(2.0, now), Some(Duration::from_secs(1)));
+        // The engine is skipped once the requests would have to wait too long.
+        assert_eq!(bucket.reserve(2.0, now), None);
+
+        // The bucket is refilled over time, but never beyond its burst size.
+        let later = now + Duration::from_secs(10);
+        assert_eq!(bucket.reserve(2.0, later), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(2.0, later), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(2.0, later), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_slow_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(0.5, now);
+
+        assert_eq!(bucket.reserve(0.5, now), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(0.5, now), None);
+        assert_eq!(
+            bucket.reserve(0.5, now + Duration::from_millis(1500)),
+            Some(Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn test_tiny_rate_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(1e-30, now);
+
+        // The delay overflowing a `Duration` skips the engine instead of panicking.
+        assert_eq!(bucket.reserve(1e-30, now), Some(Duration::ZERO));
+        assert_eq!(bucket.reserve(1e-30, now), None);
+    }
+}
@@ -0,0 +1,236 @@
+//! This module provides the registry of the upstream search engines, which maps the names of the
+//! engines to the engine structs implementing the `SearchEngine` trait, so that the engines are
+//! looked up by their name wherever they are needed instead of being matched one by one. The
+//! engine structs are constructed once when the registry is built (from the built-in engines, the
+//! `engine_definitions` and the `engine_scripts`) and shared by all the searches.
+
+use super::{
+    bing::Bing, brave::Brave, declarative::load_definitions, duckduckgo::DuckDuckGo,
+    librex::LibreX, lua::LuaEngine, mojeek::Mojeek, searx::Searx, startpage::Startpage,
+};
+use crate::{
+    config::{error::ConfigError, parser::Config},
+    models::engine_models::{EngineError, EngineHandler, SearchEngine},
+};
+use error_stack::Result;
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{Arc, Mutex, PoisonError, RwLock},
+};
+
+/// The type of the functions constructing the built-in upstream search engines.
+type EngineConstructor = fn() -> Result<Arc<dyn SearchEngine>, EngineError>;
+
+/// The constructors of the upstream search engines built into the server.
+const BUILTIN_ENGINES: [EngineConstructor; 7] = [
+    || Ok(Arc::new(Bing::new()?)),
+    || Ok(Arc::new(Brave::new()?)),
+    || Ok(Arc::new(DuckDuckGo::new()?)),
+    || Ok(Arc::new(LibreX::new()?)),
+    || Ok(Arc::new(Mojeek::new()?)),
+    || Ok(Arc::new(Searx::new()?)),
+    || Ok(Arc::new(Startpage::new()?)),
+];
+
+/// A static variable which stores the registry of the upstream search engines, which is rebuilt
+/// whenever the config is loaded.
+static ENGINE_REGISTRY: RwLock<Option<Arc<EngineRegistry>>> = RwLock::new(None);
+
+/// A static variable which stores the names of the engines and of the categories defined at
+/// runtime, so that each of them is only leaked once however many times the config is reloaded.
+static INTERNED_NAMES: Mutex<Option<HashSet<&'static str>>> = Mutex::new(None);
+
+/// A named struct which stores the upstream search engines which can be searched.
+#[derive(Clone, Default)]
+pub struct EngineRegistry {
+    /// It stores the engine structs keyed by the names of the engines.
+    engines: BTreeMap<&'static str, Arc<dyn SearchEngine>>,
+}
+
+impl EngineRegistry {
+    /// Constructs a registry containing the upstream search engines built into the server, the
+    /// engines which could not be constructed are left out of the registry.
+    pub fn builtin() -> Self {
+        let mut registry = EngineRegistry::default();
+        for constructor in BUILTIN_ENGINES {
+            match constructor() {
+                Ok(engine) => registry.register(engine),
+                Err(error) => {
+                    tracing::error!("Unable to construct an upstream search engine: {error:?}")
+                }
+            }
+        }
+        registry
+    }
+
+    /// Constructs a registry containing the upstream search engines built into the server along
+    /// with the engines defined in the files of the `engine_definitions` directory and in the lua
+    /// scripts provided via the `engine_scripts` config option, each of which replaces the engines
+    /// of the same name registered before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns a `ConfigError` pointing at the `engine_definitions` or at the `engine_scripts`
+    /// option if one of the definitions or of the scripts could not be loaded.
+    pub fn from_config(config: &Config) -> std::result::Result<Self, ConfigError> {
+        let mut registry = EngineRegistry::builtin();
+        if let Some(directory) = &config.engine_definitions {
+            for engine in load_definitions(directory)? {
+                registry.register(Arc::new(engine));
+            }
+        }
+        for path in &config.engine_scripts {
+            let engine =
+                LuaEngine::from_script(path).map_err(|error| ConfigError::InvalidOption {
+                    key: "engine_scripts".to_owned(),
+                    reason: format!("`{path}` is not a valid engine script: {error}"),
+                })?;
+            registry.register(Arc::new(engine));
+        }
+        Ok(registry)
+    }
+
+    /// A function which registers an upstream search engine under its name, replacing the engine
+    /// previously registered under the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the engine struct as an argument.
+    pub fn register(&mut self, engine: Arc<dyn SearchEngine>) {
+        self.engines.insert(engine.name(), engine);
+    }
+
+    /// A function which looks up an upstream search engine by its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the name of the engine (ignoring the case) as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the handler of the engine or `None` if no engine is registered under the name.
+    pub fn handler(&self, name: &str) -> Option<EngineHandler> {
+        self.engines
+            .get(name.to_lowercase().as_str())
+            .cloned()
+            .map(EngineHandler::from_engine)
+    }
+
+    /// A function which returns the names of the registered engines in the alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.engines.keys().copied()
+    }
+
+    /// A function which returns the names of the registered engines which can answer the
+    /// searches of the provided search category.
+    ///
+    /// # Arguments
+    ///
+    /// * `category` - It takes the name of the search category as an argument.
+    pub fn category_engines(&self, category: &str) -> Vec<&'static str> {
+        self.engines
+            .iter()
+            .filter(|(_, engine)| {
+                engine
+                    .categories()
+                    .iter()
+                    .any(|engine_category| engine_category.eq_ignore_ascii_case(category))
+            })
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+/// A function which replaces the registry of the upstream search engines with the one built from
+/// the provided config, it is called whenever the config is loaded. The current registry is kept
+/// if one of the engine scripts could not be loaded.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+pub fn load_engines(config: &Config) {
+    match EngineRegistry::from_config(config) {
+        Ok(registry) => {
+            *ENGINE_REGISTRY
+                .write()
+                .unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(registry));
+        }
+        Err(error) => tracing::error!("Unable to load the upstream search engines: {error}"),
+    }
+}
+
+/// A function which returns a name which lives as long as the server, for the names of the
+/// engines and of the categories which are only known at runtime.
+///
+/// # Arguments
+///
+/// * `name` - It takes the name as an argument.
+pub fn intern(name: &str) -> &'static str {
+    let mut names = INTERNED_NAMES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let names = names.get_or_insert_with(HashSet::new);
+    match names.get(name) {
+        Some(interned) => *interned,
+        None => {
+            let interned: &'static str = Box::leak(name.to_owned().into_boxed_str());
+            names.insert(interned);
+            interned
+        }
+    }
+}
+
+/// A function which returns the registry of the upstream search engines, the built-in engines are
+/// registered on the first call if no config was loaded yet.
+pub fn registry() -> Arc<EngineRegistry> {
+    if let Some(registry) = ENGINE_REGISTRY
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        return registry.clone();
+    }
+
+    ENGINE_REGISTRY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(|| Arc::new(EngineRegistry::builtin()))
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_engines() {
+        let registry = EngineRegistry::builtin();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            vec![
+                "bing",
+                "brave",
+                "duckduckgo",
+                "librex",
+                "mojeek",
+                "searx",
+                "startpage"
+            ]
+        );
+        assert_eq!(registry.handler("DuckDuckGo").unwrap().name(), "duckduckgo");
+        assert!(registry.handler("google").is_none());
+        assert_eq!(registry.category_engines("general").len(), 7);
+        assert!(registry.category_engines("images").is_empty());
+    }
+
+    #[test]
+    fn test_intern() {
+        let name = intern(&String::from("example"));
+        assert_eq!(name, "example");
+        assert!(std::ptr::eq(name, intern("example")));
+    }
+}
@@ -0,0 +1,638 @@
+//! The `reverse_image` module handles the fetching of the pages which contain the image provided
+//! by the user (either its url or the uploaded image) from the upstream search engines which
+//! support searching by image (TinEye, Yandex and Bing Visual).
+
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use error_stack::{Report, Result, ResultExt};
+use reqwest::{
+    header::{HeaderMap, CONTENT_TYPE},
+    Client, RequestBuilder, Response,
+};
+use scraper::Html;
+use serde::Deserialize;
+
+use super::search_result_parser::SearchResultParser;
+use crate::{
+    models::{
+        aggregation_models::{EngineResults, SearchResult},
+        engine_models::{is_blocked_response, EngineError, SearchOptions},
+    },
+    query::bangs::encode_query,
+};
+
+/// An enum type which provides the image whose pages are searched by the upstream search engines.
+#[derive(Clone, Debug)]
+pub enum ReverseImage {
+    /// The public url of the image, from which the upstream search engines fetch the image.
+    Url(String),
+    /// The image uploaded by the user, whose bytes are forwarded to the upstream search engines.
+    Upload {
+        /// It stores the bytes of the image.
+        bytes: Vec<u8>,
+        /// It stores the media type of the image.
+        content_type: String,
+    },
+}
+
+impl ReverseImage {
+    /// A function which returns the key under which the results of the reverse image search are
+    /// cached, the uploaded images are identified by the hash of their bytes.
+    pub fn cache_key(&self) -> String {
+        match self {
+            ReverseImage::Url(url) => format!("reverse_image:{url}"),
+            ReverseImage::Upload { bytes, .. } => {
+                format!("reverse_image:upload:{}", blake3::hash(bytes).to_hex())
+            }
+        }
+    }
+}
+
+/// An enum type which provides the upstream search engines which support searching by image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReverseImageEngine {
+    /// The TinEye reverse image search engine, whose results are fetched as json.
+    TinEye,
+    /// The Yandex image search engine, whose results are scraped from its website.
+    Yandex,
+    /// The Bing Visual Search engine, whose results are fetched as json from the knowledge api of
+    /// Bing.
+    BingVisual,
+}
+
+/// The url of the knowledge api of Bing, to which the images are sent by the Bing Visual Search.
+const BING_VISUAL_URL: &str =
+    "https://www.bing.com/images/api/custom/knowledge?iss=SBI&FORM=SBIWEB";
+
+/// A named struct which deserializes the json response of Yandex to an uploaded image, which holds
+/// the query string of the page listing the pages which contain the image.
+#[derive(Deserialize)]
+struct YandexUploadResponse {
+    /// It stores the blocks of the page, the first of which holds the query string.
+    #[serde(default)]
+    blocks: Vec<YandexBlock>,
+}
+
+/// A named struct which deserializes a block of the json response of Yandex to an uploaded image.
+#[derive(Deserialize)]
+struct YandexBlock {
+    /// It stores the parameters of the block.
+    params: YandexBlockParams,
+}
+
+/// A named struct which deserializes the parameters of a block of the json response of Yandex to
+/// an uploaded image.
+#[derive(Deserialize)]
+struct YandexBlockParams {
+    /// It stores the query string of the page listing the pages which contain the image.
+    url: String,
+}
+
+/// A named struct which deserializes the json response of the knowledge api of Bing.
+#[derive(Deserialize)]
+struct BingVisualResponse {
+    /// It stores the groups of the actions run on the provided image.
+    #[serde(default)]
+    tags: Vec<BingVisualTag>,
+}
+
+/// A named struct which deserializes a group of actions in the json response of the knowledge api
+/// of Bing.
+#[derive(Deserialize)]
+struct BingVisualTag {
+    /// It stores the actions run on the provided image.
+    #[serde(default)]
+    actions: Vec<BingVisualAction>,
+}
+
+/// A named struct which deserializes an action in the json response of the knowledge api of Bing,
+/// the pages which contain the provided image are listed by the `PagesIncluding` action.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BingVisualAction {
+    /// It stores the type of the action.
+    action_type: String,
+    /// It stores the data found by the action.
+    #[serde(default)]
+    data: Option<BingVisualData>,
+}
+
+/// A named struct which deserializes the data of an action in the json response of the knowledge
+/// api of Bing.
+#[derive(Deserialize)]
+struct BingVisualData {
+    /// It stores the pages found by the action.
+    #[serde(default)]
+    value: Vec<BingVisualPage>,
+}
+
+/// A named struct which deserializes a page which contains the provided image in the json response
+/// of the knowledge api of Bing.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BingVisualPage {
+    /// It stores the title of the page.
+    #[serde(default)]
+    name: String,
+    /// It stores the url of the page.
+    host_page_url: String,
+    /// It stores the url of the page as shown by Bing.
+    #[serde(default)]
+    host_page_display_url: String,
+}
+
+/// A named struct which deserializes the json response of TinEye.
+#[derive(Deserialize)]
+struct TinEyeResponse {
+    /// It stores the images matching the provided image.
+    #[serde(default)]
+    matches: Vec<TinEyeMatch>,
+}
+
+/// A named struct which deserializes an image matching the provided image in the json response of
+/// TinEye.
+#[derive(Deserialize)]
+struct TinEyeMatch {
+    /// It stores the domain on which the image was found.
+    domain: String,
+    /// It stores the pages on which the image was found.
+    #[serde(default)]
+    backlinks: Vec<TinEyeBacklink>,
+}
+
+/// A named struct which deserializes a page on which a matching image was found in the json
+/// response of TinEye.
+#[derive(Deserialize)]
+struct TinEyeBacklink {
+    /// It stores the url of the page.
+    backlink: String,
+    /// It stores the file name of the image on the page.
+    #[serde(default)]
+    image_name: String,
+    /// It stores the date on which the page was crawled.
+    #[serde(default)]
+    crawl_date: String,
+}
+
+impl ReverseImageEngine {
+    /// All the upstream search engines which support searching by image.
+    pub const ALL: [ReverseImageEngine; 3] = [
+        ReverseImageEngine::TinEye,
+        ReverseImageEngine::Yandex,
+        ReverseImageEngine::BingVisual,
+    ];
+
+    /// A function which parses the engine from its name (ignoring the case).
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the name of the engine as an argument.
+    pub fn from_name(name: &str) -> Option<Self> {
+        ReverseImageEngine::ALL
+            .into_iter()
+            .find(|engine| engine.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// A function which returns the name of the engine.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReverseImageEngine::TinEye => "TinEye",
+            ReverseImageEngine::Yandex => "Yandex",
+            ReverseImageEngine::BingVisual => "BingVisual",
+        }
+    }
+
+    /// A helper function which builds the request for the page of the engine listing the pages
+    /// which contain the image at the provided url.
+    ///
+    /// # Arguments
+    ///
+    /// * `image_url` - It takes the url of the image as an argument.
+    /// * `client` - It takes the http client used to send the requests to the upstream engine as
+    ///   an argument.
+    fn search_request(&self, image_url: &str, client: &Client) -> RequestBuilder {
+        match self {
+            ReverseImageEngine::TinEye => client.get(format!(
+                "https://tineye.com/api/v1/result_json/?page=1&url={}",
+                encode_query(image_url)
+            )),
+            ReverseImageEngine::Yandex => client.get(format!(
+                "https://yandex.com/images/search?rpt=imageview&url={}",
+                encode_query(image_url)
+            )),
+            ReverseImageEngine::BingVisual => {
+                let knowledge_request = serde_json::json!({
+                    "imageInfo": { "url": image_url, "source": "Url" }
+                })
+                .to_string();
+                multipart_request(
+                    client.post(BING_VISUAL_URL),
+                    &[("knowledgeRequest", None, knowledge_request.as_bytes())],
+                )
+            }
+        }
+    }
+
+    /// A helper function which builds the request uploading the image to the upstream search
+    /// engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - It takes the bytes of the image as an argument.
+    /// * `content_type` - It takes the media type of the image as an argument.
+    /// * `client` - It takes the http client used to send the requests to the upstream engine as
+    ///   an argument.
+    fn upload_request(&self, bytes: &[u8], content_type: &str, client: &Client) -> RequestBuilder {
+        match self {
+            ReverseImageEngine::TinEye => multipart_request(
+                client.post("https://tineye.com/api/v1/result_json/?page=1"),
+                &[("image", Some(content_type), bytes)],
+            ),
+            ReverseImageEngine::Yandex => multipart_request(
+                client.post("https://yandex.com/images/search?rpt=imageview&format=json&request=%7B%22blocks%22%3A%5B%7B%22block%22%3A%22b-page_type_search-by-image__link%22%7D%5D%7D"),
+                &[("upfile", Some(content_type), bytes)],
+            ),
+            // Bing takes the uploaded images encoded in base64.
+            ReverseImageEngine::BingVisual => {
+                let knowledge_request = serde_json::json!({
+                    "imageInfo": { "source": "ImageUpload" }
+                })
+                .to_string();
+                multipart_request(
+                    client.post(BING_VISUAL_URL),
+                    &[
+                        ("knowledgeRequest", None, knowledge_request.as_bytes()),
+                        ("imageBase64", None, STANDARD.encode(bytes).as_bytes()),
+                    ],
+                )
+            }
+        }
+    }
+
+    /// This function fetches the pages which contain the provided image from the upstream search
+    /// engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `image` - It takes the url or the bytes of the image as an argument.
+    /// * `user_agent` - It takes a random user agent string as an argument.
+    /// * `client` - It takes the http client used to send the requests to the upstream engine as
+    ///   an argument.
+    /// * `options` - It takes the search preferences of the user as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an `EngineError` if the upstream engine could not be reached, refused the request
+    /// or did not find any page containing the image.
+    pub async fn results(
+        &self,
+        image: &ReverseImage,
+        user_agent: &str,
+        client: &Client,
+        options: &SearchOptions,
+    ) -> Result<EngineResults, EngineError> {
+        let header_map = HeaderMap::try_from(&HashMap::from([(
+            "USER_AGENT".to_string(),
+            user_agent.to_string(),
+        )]))
+        .change_context(EngineError::UnexpectedError)?;
+        let headers = options.request_headers(header_map);
+
+        let request = match image {
+            ReverseImage::Url(image_url) => self.search_request(image_url, client),
+            ReverseImage::Upload {
+                bytes,
+                content_type,
+            } => self.upload_request(bytes, content_type, client),
+        };
+        let mut response = request
+            .headers(headers.clone())
+            .send()
+            .await
+            .change_context(EngineError::RequestError)?;
+        if let (ReverseImageEngine::Yandex, ReverseImage::Upload { .. }) = (self, image) {
+            response = yandex_upload_results(response, headers, client).await?;
+        }
+        if is_blocked_response(&response) {
+            return Err(Report::new(EngineError::Blocked));
+        }
+        let body = response
+            .text()
+            .await
+            .change_context(EngineError::RequestError)?;
+
+        let results = self.parse_results(&body)?;
+        if results.results.is_empty() {
+            return Err(Report::new(EngineError::EmptyResultSet));
+        }
+        Ok(results)
+    }
+
+    /// A helper function which parses the pages containing the image from the response of the
+    /// upstream search engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - It takes the body of the response as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an `EngineError` if the response could not be parsed.
+    fn parse_results(&self, body: &str) -> Result<EngineResults, EngineError> {
+        match self {
+            ReverseImageEngine::TinEye => {
+                let response: TinEyeResponse =
+                    serde_json::from_str(body).change_context(EngineError::UnexpectedError)?;
+
+                let results: HashMap<String, SearchResult> = response
+                    .matches
+                    .into_iter()
+                    .flat_map(|image| {
+                        image.backlinks.into_iter().map(move |backlink| {
+                            let title = match backlink.image_name.is_empty() {
+                                true => image.domain.clone(),
+                                false => format!("{} - {}", backlink.image_name, image.domain),
+                            };
+                            let description = match backlink.crawl_date.is_empty() {
+                                true => String::new(),
+                                false => format!("Crawled on {}", backlink.crawl_date),
+                            };
+                            SearchResult::new(&title, &backlink.backlink, &description, &["tineye"])
+                        })
+                    })
+                    .enumerate()
+                    .map(|(index, mut result)| {
+                        result.rank = index + 1;
+                        (result.url.clone(), result)
+                    })
+                    .collect();
+                Ok(EngineResults::from(results))
+            }
+            ReverseImageEngine::Yandex => {
+                let parser = SearchResultParser::new(
+                    ".CbirSites-Empty",
+                    ".CbirSites-Item",
+                    ".CbirSites-ItemTitle a",
+                    ".CbirSites-ItemTitle a",
+                    ".CbirSites-ItemDescription",
+                )?;
+                let document = Html::parse_document(body);
+                parser.parse_for_results(&document, |title, url, desc| {
+                    url.value().attr("href").map(|url| {
+                        SearchResult::new(
+                            title.inner_html().trim(),
+                            url,
+                            desc.inner_html().trim(),
+                            &["yandex"],
+                        )
+                    })
+                })
+            }
+            ReverseImageEngine::BingVisual => {
+                let response: BingVisualResponse =
+                    serde_json::from_str(body).change_context(EngineError::UnexpectedError)?;
+
+                let results: HashMap<String, SearchResult> = response
+                    .tags
+                    .into_iter()
+                    .flat_map(|tag| tag.actions)
+                    .filter(|action| action.action_type == "PagesIncluding")
+                    .filter_map(|action| action.data)
+                    .flat_map(|data| data.value)
+                    .enumerate()
+                    .map(|(index, page)| {
+                        let mut result = SearchResult::new(
+                            &page.name,
+                            &page.host_page_url,
+                            &page.host_page_display_url,
+                            &["bingvisual"],
+                        );
+                        result.rank = index + 1;
+                        (result.url.clone(), result)
+                    })
+                    .collect();
+                Ok(EngineResults::from(results))
+            }
+        }
+    }
+}
+
+/// A helper function which fetches the page listing the pages which contain the image uploaded to
+/// Yandex, which answers the upload with the url of that page.
+///
+/// # Arguments
+///
+/// * `response` - It takes the response of Yandex to the upload as an argument.
+/// * `headers` - It takes the headers sent to Yandex as an argument.
+/// * `client` - It takes the http client used to send the requests to Yandex as an argument.
+///
+/// # Error
+///
+/// Returns an `EngineError` if Yandex refused the upload or the page could not be fetched.
+async fn yandex_upload_results(
+    response: Response,
+    headers: HeaderMap,
+    client: &Client,
+) -> Result<Response, EngineError> {
+    if is_blocked_response(&response) {
+        return Err(Report::new(EngineError::Blocked));
+    }
+    let body = response
+        .text()
+        .await
+        .change_context(EngineError::RequestError)?;
+    let upload: YandexUploadResponse =
+        serde_json::from_str(&body).change_context(EngineError::UnexpectedError)?;
+    let query = upload
+        .blocks
+        .into_iter()
+        .next()
+        .map(|block| block.params.url)
+        .ok_or_else(|| Report::new(EngineError::UnexpectedError))?;
+    client
+        .get(format!("https://yandex.com/images/search?{query}"))
+        .headers(headers)
+        .send()
+        .await
+        .change_context(EngineError::RequestError)
+}
+
+/// A helper function which sends the provided fields as the `multipart/form-data` body of the
+/// request.
+///
+/// # Arguments
+///
+/// * `request` - It takes the request to which the body is added as an argument.
+/// * `fields` - It takes the names, the optional media types and the values of the fields as an
+///   argument.
+fn multipart_request(
+    request: RequestBuilder,
+    fields: &[(&str, Option<&str>, &[u8])],
+) -> RequestBuilder {
+    let (content_type, body) = multipart_body(fields);
+    request.header(CONTENT_TYPE, content_type).body(body)
+}
+
+/// A helper function which builds a `multipart/form-data` body from the provided fields, the
+/// fields with a media type are sent as files.
+///
+/// # Arguments
+///
+/// * `fields` - It takes the names, the optional media types and the values of the fields as an
+///   argument.
+///
+/// # Returns
+///
+/// It returns the value of the `Content-Type` header (holding the boundary) along with the body.
+fn multipart_body(fields: &[(&str, Option<&str>, &[u8])]) -> (String, Vec<u8>) {
+    let boundary = format!("websurfx{:032x}", rand::random::<u128>());
+    let mut body = Vec::new();
+    for (name, content_type, value) in fields {
+        body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+        match content_type {
+            Some(content_type) => body.extend_from_slice(
+                format!(
+                    "Content-Disposition: form-data; name=\"{name}\"; filename=\"image\"\r\nContent-Type: {content_type}\r\n\r\n"
+                )
+                .as_bytes(),
+            ),
+            None => body.extend_from_slice(
+                format!("Content-Disposition: form-data; name=\"{name}\"\r\n\r\n").as_bytes(),
+            ),
+        }
+        body.extend_from_slice(value);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(
+            ReverseImageEngine::from_name("tineye"),
+            Some(ReverseImageEngine::TinEye)
+        );
+        assert_eq!(
+            ReverseImageEngine::from_name("bingvisual"),
+            Some(ReverseImageEngine::BingVisual)
+        );
+        assert_eq!(ReverseImageEngine::from_name("Bing"), None);
+    }
+
+    #[test]
+    fn test_search_request() {
+        let client = Client::new();
+        let request = ReverseImageEngine::Yandex
+            .search_request("https://example.com/cat.png?size=2", &client)
+            .build()
+            .unwrap();
+        assert_eq!(
+            request.url().as_str(),
+            "https://yandex.com/images/search?rpt=imageview&url=https%3A%2F%2Fexample.com%2Fcat.png%3Fsize%3D2"
+        );
+
+        let request = ReverseImageEngine::BingVisual
+            .upload_request(b"\x89PNG", "image/png", &client)
+            .build()
+            .unwrap();
+        let content_type = request.headers()[CONTENT_TYPE].to_str().unwrap();
+        let body = request.body().and_then(|body| body.as_bytes()).unwrap();
+        let fields = crate::server::multipart::parse_multipart(content_type, body).unwrap();
+        assert_eq!(fields[1].name, "imageBase64");
+        assert_eq!(fields[1].data, STANDARD.encode(b"\x89PNG").as_bytes());
+    }
+
+    #[test]
+    fn test_multipart_body() {
+        let (content_type, body) = multipart_body(&[
+            ("request", None, b"{}".as_slice()),
+            ("upfile", Some("image/png"), b"\x89PNG".as_slice()),
+        ]);
+
+        let fields = crate::server::multipart::parse_multipart(&content_type, &body).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(
+            (fields[0].name.as_str(), fields[0].data),
+            ("request", b"{}".as_slice())
+        );
+        assert_eq!(fields[1].content_type.as_deref(), Some("image/png"));
+        assert_eq!(fields[1].data, b"\x89PNG");
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let upload = |bytes: &[u8]| ReverseImage::Upload {
+            bytes: bytes.to_vec(),
+            content_type: "image/png".to_owned(),
+        };
+        assert_eq!(upload(b"cat").cache_key(), upload(b"cat").cache_key());
+        assert_ne!(upload(b"cat").cache_key(), upload(b"dog").cache_key());
+        assert_eq!(
+            ReverseImage::Url("https://example.com/cat.png".to_owned()).cache_key(),
+            "reverse_image:https://example.com/cat.png"
+        );
+    }
+
+    #[test]
+    fn test_parse_tineye_results() {
+        let body = r#"{"matches": [
+            {"domain": "example.com", "backlinks": [
+                {"url": "https://example.com/cat.png", "backlink": "https://example.com/cats", "image_name": "cat.png", "crawl_date": "2023-01-01"},
+                {"url": "https://example.com/cat.png", "backlink": "https://example.com/pets", "image_name": "", "crawl_date": ""}
+            ]}
+        ]}"#;
+
+        let results = ReverseImageEngine::TinEye.parse_results(body).unwrap();
+        assert_eq!(results.results.len(), 2);
+        let result = &results.results["https://example.com/cats"];
+        assert_eq!(result.title, "cat.png - example.com");
+        assert_eq!(result.description, "Crawled on 2023-01-01");
+        assert_eq!(result.rank, 1);
+        assert_eq!(
+            results.results["https://example.com/pets"].title,
+            "example.com"
+        );
+    }
+
+    #[test]
+    fn test_parse_bing_visual_results() {
+        let body = r#"{"tags": [
+            {"actions": [{"actionType": "VisualSearch", "data": {"value": [
+                {"name": "Dog", "hostPageUrl": "https://example.com/dogs"}
+            ]}}]},
+            {"actions": [{"actionType": "PagesIncluding", "data": {"value": [
+                {"name": "Cats", "hostPageUrl": "https://example.com/cats", "hostPageDisplayUrl": "example.com/cats"},
+                {"name": "Pets", "hostPageUrl": "https://example.com/pets"}
+            ]}}]}
+        ]}"#;
+
+        let results = ReverseImageEngine::BingVisual.parse_results(body).unwrap();
+        assert_eq!(results.results.len(), 2);
+        let result = &results.results["https://example.com/cats"];
+        assert_eq!(result.title, "Cats");
+        assert_eq!(result.description, "example.com/cats");
+        assert_eq!(result.rank, 1);
+    }
+
+    #[test]
+    fn test_parse_yandex_results() {
+        let body = r#"<ul>
+            <li class="CbirSites-Item">
+                <div class="CbirSites-ItemTitle"><a href="https://example.com/cats">Cats</a></div>
+                <div class="CbirSites-ItemDescription">A page about <b>cats</b></div>
+            </li>
+        </ul>"#;
+
+        let results = ReverseImageEngine::Yandex.parse_results(body).unwrap();
+        let result = &results.results["https://example.com/cats"];
+        assert_eq!(result.title, "Cats");
+        assert_eq!(result.engine.as_slice(), ["yandex"]);
+    }
+}
@@ -1,7 +1,10 @@
 //! This modules provides helper functionalities for parsing a html document into internal SearchResult.
 use std::collections::HashMap;
 
-use crate::models::{aggregation_models::SearchResult, engine_models::EngineError};
+use crate::models::{
+    aggregation_models::{EngineResults, SearchResult},
+    engine_models::EngineError,
+};
 use error_stack::{Report, Result};
 use scraper::{html::Select, ElementRef, Html, Selector};
 
@@ -17,6 +20,11 @@ pub struct SearchResultParser {
     result_url: Selector,
     /// selector to locate the description relative to the search result item.
     result_desc: Selector,
+    /// selector to locate the spelling correction of the search query, for the engines which
+    /// provide one.
+    corrected_query: Option<Selector>,
+    /// selector to locate the related searches, for the engines which provide them.
+    related_queries: Option<Selector>,
 }
 
 impl SearchResultParser {
@@ -34,21 +42,47 @@ impl SearchResultParser {
             result_title: new_selector(result_title_selector)?,
             result_url: new_selector(result_url_selector)?,
             result_desc: new_selector(result_desc_selector)?,
+            corrected_query: None,
+            related_queries: None,
         })
     }
 
+    /// Adds the selector to locate the spelling correction of the search query suggested by the
+    /// engine, if it is a valid css expression, otherwise it returns an EngineError. The text of
+    /// the element is used or its `value` attribute if the element is an input.
+    pub fn with_corrected_query(
+        mut self,
+        corrected_query_selector: &str,
+    ) -> Result<SearchResultParser, EngineError> {
+        self.corrected_query = Some(new_selector(corrected_query_selector)?);
+        Ok(self)
+    }
+
+    /// Adds the selector to locate the related searches suggested by the engine, if it is a valid
+    /// css expression, otherwise it returns an EngineError. The text of the elements is used or
+    /// their `value` attribute if the elements are inputs.
+    pub fn with_related_queries(
+        mut self,
+        related_queries_selector: &str,
+    ) -> Result<SearchResultParser, EngineError> {
+        self.related_queries = Some(new_selector(related_queries_selector)?);
+        Ok(self)
+    }
+
     /// Parse the html and returns element representing the 'no result found' response.
     pub fn parse_for_no_results<'a>(&'a self, document: &'a Html) -> Select<'a, 'a> {
         document.select(&self.no_result)
     }
 
     /// Parse the html, and convert the results to SearchResult with the help of the builder function
+    /// along with the spelling correction of the search query and the related searches if the
+    /// engine provides them.
     pub fn parse_for_results(
         &self,
         document: &Html,
         builder: impl Fn(&ElementRef<'_>, &ElementRef<'_>, &ElementRef<'_>) -> Option<SearchResult>,
-    ) -> Result<HashMap<String, SearchResult>, EngineError> {
-        let res = document
+    ) -> Result<EngineResults, EngineError> {
+        let results: HashMap<String, SearchResult> = document
             .select(&self.results)
             .filter_map(|result| {
                 let title = result.select(&self.result_title).next();
@@ -59,10 +93,48 @@ impl SearchResultParser {
                     _ => None,
                 }
             })
-            .map(|search_result| (search_result.url.clone(), search_result))
+            .enumerate()
+            .map(|(index, mut search_result)| {
+                search_result.rank = index + 1;
+                (search_result.url.clone(), search_result)
+            })
             .collect();
-        Ok(res)
+        Ok(EngineResults {
+            results,
+            corrected_query: self.parse_for_corrected_query(document),
+            related_queries: self.parse_for_related_queries(document),
+        })
+    }
+
+    /// Parse the html for the spelling correction of the search query suggested by the engine.
+    fn parse_for_corrected_query(&self, document: &Html) -> Option<String> {
+        document
+            .select(self.corrected_query.as_ref()?)
+            .next()
+            .and_then(|element| query_text(&element))
     }
+
+    /// Parse the html for the related searches suggested by the engine.
+    fn parse_for_related_queries(&self, document: &Html) -> Vec<String> {
+        match &self.related_queries {
+            Some(selector) => document
+                .select(selector)
+                .filter_map(|element| query_text(&element))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Extracts the search query suggested by the element, which is its `value` attribute for the
+/// input elements and its text for all the other elements.
+fn query_text(element: &ElementRef<'_>) -> Option<String> {
+    let text = match element.value().attr("value") {
+        Some(value) => value.to_owned(),
+        None => element.text().collect::<String>(),
+    };
+    let text = text.trim().trim_matches('"').trim();
+    (!text.is_empty()).then(|| text.to_owned())
 }
 
 /// Create a Selector struct, if the given parameter is a valid css expression, otherwise convert it into an EngineError.
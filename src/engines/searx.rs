@@ -8,8 +8,10 @@ use scraper::Html;
 use std::collections::HashMap;
 
 use super::search_result_parser::SearchResultParser;
-use crate::models::aggregation_models::SearchResult;
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::aggregation_models::{EngineResults, SearchResult};
+use crate::models::engine_models::{EngineError, SearchEngine, SearchOptions};
+use crate::models::locale_models::market;
+use crate::models::parser_models::EngineConfig;
 use error_stack::{Report, Result, ResultExt};
 
 /// A new Searx engine type defined in-order to implement the `SearchEngine` trait which allows to
@@ -29,32 +31,50 @@ impl Searx {
                 "h3>a",
                 "h3>a",
                 ".content",
-            )?,
+            )?
+            .with_corrected_query("#corrections input[name=\"q\"]")?
+            .with_related_queries("#suggestions input[name=\"q\"]")?,
         })
     }
 }
 
 #[async_trait::async_trait]
 impl SearchEngine for Searx {
-    async fn results(
+    fn name(&self) -> &'static str {
+        "searx"
+    }
+
+    async fn fetch_results(
         &self,
         query: &str,
         page: u32,
         user_agent: &str,
         client: &Client,
-        mut safe_search: u8,
-    ) -> Result<HashMap<String, SearchResult>, EngineError> {
+        options: &SearchOptions,
+        _engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError> {
         // Page number can be missing or empty string and so appropriate handling is required
         // so that upstream server recieves valid page number.
-        if safe_search == 3 {
-            safe_search = 2;
-        };
+        let safe_search = options.safe_search.min(2);
+        // Searx uses the `language` parameter to select both the language and the region of the
+        // results (for example `de-CH`).
+        let language = options
+            .region
+            .as_deref()
+            .and_then(|region| market(region, options.language.as_deref()))
+            .or_else(|| options.language.clone())
+            .unwrap_or_else(|| "auto".to_owned());
 
-        let url: String = format!(
-            "https://searx.be/search?q={query}&pageno={}&safesearch={safe_search}",
+        let mut url: String = format!(
+            "https://searx.be/search?q={query}&pageno={}&safesearch={safe_search}&language={language}",
             page + 1
         );
 
+        // Searx uses the `time_range` parameter to restrict the results to a time range.
+        if let Some(time_range) = options.time_range {
+            url.push_str(&format!("&time_range={}", time_range.name()));
+        }
+
         // initializing headers and adding appropriate headers.
         let header_map = HeaderMap::try_from(&HashMap::from([
             ("USER_AGENT".to_string(), user_agent.to_string()),
@@ -65,7 +85,13 @@ impl SearchEngine for Searx {
         .change_context(EngineError::UnexpectedError)?;
 
         let document: Html = Html::parse_document(
-            &Searx::fetch_html_from_upstream(self, &url, header_map, client).await?,
+            &Searx::fetch_html_from_upstream(
+                self,
+                &url,
+                options.request_headers(header_map),
+                client,
+            )
+            .await?,
         );
 
         if let Some(no_result_msg) = self.parser.parse_for_no_results(&document).nth(1) {
@@ -89,4 +115,8 @@ impl SearchEngine for Searx {
                 })
             })
     }
+
+    fn supports_safe_search(&self) -> bool {
+        true
+    }
 }
@@ -8,9 +8,10 @@ use reqwest::header::HeaderMap;
 use reqwest::Client;
 use scraper::Html;
 
-use crate::models::aggregation_models::SearchResult;
+use crate::models::aggregation_models::{EngineResults, SearchResult};
+use crate::models::parser_models::{EngineConfig, SafeSearchLevel};
 
-use crate::models::engine_models::{EngineError, SearchEngine};
+use crate::models::engine_models::{EngineError, SearchEngine, SearchOptions};
 
 use error_stack::{Report, Result, ResultExt};
 
@@ -40,20 +41,20 @@ impl Startpage {
 
 #[async_trait::async_trait]
 impl SearchEngine for Startpage {
-    async fn results(
+    fn name(&self) -> &'static str {
+        "startpage"
+    }
+
+    async fn fetch_results(
         &self,
         query: &str,
         page: u32,
         user_agent: &str,
         client: &Client,
-        _safe_search: u8,
-    ) -> Result<HashMap<String, SearchResult>, EngineError> {
-        // Page number can be missing or empty string and so appropriate handling is required
-        // so that upstream server recieves valid page number.
-        let url: String = format!(
-            "https://startpage.com/do/dsearch?q={query}&num=10&start={}",
-            page * 10,
-        );
+        options: &SearchOptions,
+        _engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError> {
+        let url = search_url(query, page, options);
 
         // initializing HeaderMap and adding appropriate headers.
         let header_map = HeaderMap::try_from(&HashMap::from([
@@ -68,7 +69,13 @@ impl SearchEngine for Startpage {
         .change_context(EngineError::UnexpectedError)?;
 
         let document: Html = Html::parse_document(
-            &Startpage::fetch_html_from_upstream(self, &url, header_map, client).await?,
+            &Startpage::fetch_html_from_upstream(
+                self,
+                &url,
+                options.request_headers(header_map),
+                client,
+            )
+            .await?,
         );
 
         if self.parser.parse_for_no_results(&document).next().is_some() {
@@ -86,4 +93,101 @@ impl SearchEngine for Startpage {
                 ))
             })
     }
+
+    fn supports_query_operators(&self) -> bool {
+        true
+    }
+
+    fn supports_safe_search(&self) -> bool {
+        true
+    }
+
+    fn blocked_page_signatures(&self) -> &'static [&'static str] {
+        &["/sp/captcha", "captcha-form"]
+    }
+}
+
+/// A helper function which returns the name used by Startpage for the provided language code.
+///
+/// # Arguments
+///
+/// * `language` - It takes the ISO 639-1 language code as an argument.
+fn startpage_language(language: &str) -> Option<&'static str> {
+    Some(match language {
+        "de" => "deutsch",
+        "en" => "english",
+        "es" => "espanol",
+        "fr" => "francais",
+        "it" => "italiano",
+        "nl" => "nederlands",
+        "pl" => "polski",
+        "pt" => "portugues",
+        _ => return None,
+    })
+}
+
+/// A helper function which builds the url of the search page of Startpage with the parameters
+/// selecting the page, the family filter, the language and the time range.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query as an argument.
+/// * `page` - It takes the page number as an argument.
+/// * `options` - It takes the search options selected by the user as an argument.
+fn search_url(query: &str, page: u32, options: &SearchOptions) -> String {
+    // Page number can be missing or empty string and so appropriate handling is required
+    // so that upstream server recieves valid page number.
+    // Startpage uses the `qadf` parameter to enable or disable the family filter.
+    let family_filter = match SafeSearchLevel::from_level(options.safe_search) {
+        SafeSearchLevel::Off => "none",
+        _ => "heavy",
+    };
+
+    let mut url: String = format!(
+        "https://startpage.com/do/dsearch?q={query}&num=10&start={}&qadf={family_filter}",
+        page * 10,
+    );
+
+    // Startpage uses the `language` parameter with the name of the language to select the
+    // language of the results.
+    if let Some(language) = options.language.as_deref().and_then(startpage_language) {
+        url.push_str(&format!("&language={language}"));
+    }
+
+    // Startpage uses the `with_date` parameter to restrict the results to a time range.
+    if let Some(time_range) = options.time_range {
+        url.push_str(&format!("&with_date={}", &time_range.name()[..1]));
+    }
+
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::engine_models::TimeRange;
+
+    #[test]
+    fn test_search_url_language_and_time_range() {
+        let options = SearchOptions {
+            language: Some("fr".to_owned()),
+            time_range: Some(TimeRange::Year),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            search_url("rust", 2, &options),
+            "https://startpage.com/do/dsearch?q=rust&num=10&start=20&qadf=none&language=francais&with_date=y"
+        );
+        // The languages which Startpage does not support are not passed to it.
+        let options = SearchOptions {
+            language: Some("ja".to_owned()),
+            safe_search: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            search_url("rust", 0, &options),
+            "https://startpage.com/do/dsearch?q=rust&num=10&start=0&qadf=heavy"
+        );
+    }
 }
@@ -0,0 +1,103 @@
+//! This module provides the functionality to look up the files of the theme folder (the css and
+//! js files, the images and the `robots.txt` file). The files found in the theme folder on the
+//! filesystem override the ones embedded into the binary with the `embed-assets` feature, so
+//! that a single binary can be deployed without the theme folder.
+
+use std::{collections::BTreeSet, fs::read_dir, path::Path};
+
+use super::{file_path, FileType};
+
+/// The files of the theme folder embedded into the binary.
+#[cfg(feature = "embed-assets")]
+#[derive(rust_embed::RustEmbed)]
+#[folder = "public/"]
+struct EmbeddedAssets;
+
+/// A function which returns the embedded file at the provided path of the theme folder.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path of the file relative to the theme folder (like
+///   `static/themes/simple.css`) as an argument.
+#[cfg(feature = "embed-assets")]
+pub fn embedded_asset(path: &str) -> Option<rust_embed::EmbeddedFile> {
+    EmbeddedAssets::get(path)
+}
+
+/// A function which checks whether the file at the provided path of the theme folder exists on
+/// the filesystem or is embedded into the binary.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path of the file relative to the theme folder as an argument.
+pub fn asset_exists(path: &str) -> bool {
+    #[cfg(feature = "embed-assets")]
+    if EmbeddedAssets::get(path).is_some() {
+        return true;
+    }
+
+    file_path(FileType::Theme)
+        .is_ok_and(|public_folder| Path::new(public_folder).join(path).is_file())
+}
+
+/// A function which lists the names of the files in the provided folder of the theme folder,
+/// both on the filesystem and embedded into the binary.
+///
+/// # Arguments
+///
+/// * `folder` - It takes the path of the folder relative to the theme folder (like
+///   `static/themes`) as an argument.
+///
+/// # Returns
+///
+/// It returns the sorted names of the files without duplicates.
+pub fn asset_names(folder: &str) -> Vec<String> {
+    let mut names: BTreeSet<String> = BTreeSet::new();
+
+    if let Ok(entries) = file_path(FileType::Theme)
+        .and_then(|public_folder| read_dir(Path::new(public_folder).join(folder)))
+    {
+        names.extend(
+            entries
+                .filter_map(Result::ok)
+                .filter(|entry| entry.path().is_file())
+                .filter_map(|entry| entry.file_name().into_string().ok()),
+        );
+    }
+
+    #[cfg(feature = "embed-assets")]
+    {
+        let prefix = format!("{}/", folder.trim_end_matches('/'));
+        names.extend(EmbeddedAssets::iter().filter_map(|path| {
+            path.strip_prefix(&prefix)
+                .filter(|name| !name.contains('/'))
+                .map(str::to_owned)
+        }));
+    }
+
+    names.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_names() {
+        let themes = asset_names("static/themes");
+        assert!(themes.contains(&"simple.css".to_owned()));
+        assert!(themes.windows(2).all(|names| names[0] < names[1]));
+
+        assert!(asset_exists("static/themes/simple.css"));
+        assert!(!asset_exists("static/themes/missing.css"));
+        assert!(!asset_exists("static/themes"));
+    }
+
+    #[cfg(feature = "embed-assets")]
+    #[test]
+    fn test_embedded_asset() {
+        let asset = embedded_asset("robots.txt").unwrap();
+        assert_eq!(asset.metadata.mimetype(), "text/plain");
+        assert!(embedded_asset("missing.txt").is_none());
+    }
+}
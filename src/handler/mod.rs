@@ -1,6 +1,8 @@
 //! This module provides the functionality to handle theme folder present on different paths and
 //! provide one appropriate path on which it is present and can be used.
 
+pub mod assets;
+
 use std::collections::HashMap;
 use std::io::Error;
 use std::path::Path;
@@ -13,10 +15,15 @@ const PUBLIC_DIRECTORY_NAME: &str = "public";
 const COMMON_DIRECTORY_NAME: &str = "websurfx";
 /// The constant holding the name of the config file.
 const CONFIG_FILE_NAME: &str = "config.lua";
+/// The constant holding the name of the toml config file which is used when the lua config file
+/// is not present in the same directory.
+const TOML_CONFIG_FILE_NAME: &str = "websurfx.toml";
 /// The constant holding the name of the AllowList text file.
 const ALLOWLIST_FILE_NAME: &str = "allowlist.txt";
 /// The constant holding the name of the BlockList text file.
 const BLOCKLIST_FILE_NAME: &str = "blocklist.txt";
+/// The constant holding the name of the nsfw domain list text file.
+const NSFW_DOMAIN_LIST_FILE_NAME: &str = "nsfw_domains.txt";
 
 /// An enum type which provides different variants to handle paths for various files/folders.
 #[derive(Hash, PartialEq, Eq, Debug)]
@@ -27,6 +34,8 @@ pub enum FileType {
     AllowList,
     /// This variant handles all the paths associated with the BlockList text file.
     BlockList,
+    /// This variant handles all the paths associated with the nsfw domain list text file.
+    NsfwDomainList,
     /// This variant handles all the paths associated with the public folder (Theme folder).
     Theme,
 }
@@ -34,67 +43,211 @@ pub enum FileType {
 /// A static variable which stores the different filesystem paths for various file/folder types.
 static FILE_PATHS_FOR_DIFF_FILE_TYPES: OnceLock<HashMap<FileType, Vec<String>>> = OnceLock::new();
 
+/// A static variable which stores the config directory provided explicitly by the user (for
+/// example via the `--config-dir` command line argument).
+static CONFIG_DIRECTORY_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// A static variable which stores the config file path provided explicitly by the user (for
+/// example via the `--config` command line argument).
+static CONFIG_FILE_PATH_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// A function which overrides the path on which the config file is looked up so that the config
+/// file is only read from the provided path instead of the default paths.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path to the config file as an argument.
+///
+/// # Error
+///
+/// Returns an error if the config file path has already been overridden.
+pub fn set_config_file_path(path: String) -> Result<(), Error> {
+    CONFIG_FILE_PATH_OVERRIDE.set(path).map_err(|_| {
+        Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "The config file path has already been set",
+        )
+    })
+}
+
+/// A function which overrides the directory in which the config file and the filter lists are
+/// looked up first. It should be called before any file path is requested.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path to the config directory as an argument.
+///
+/// # Error
+///
+/// Returns an error if the config directory has already been overridden.
+pub fn set_config_directory(path: String) -> Result<(), Error> {
+    CONFIG_DIRECTORY_OVERRIDE.set(path).map_err(|_| {
+        Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            "The config directory has already been set",
+        )
+    })
+}
+
+/// A helper function which returns the directories in which the config file and the filter lists
+/// are looked up in the order in which they should be checked. The directories are:
+///
+/// 1. The directory provided explicitly by the user (via the `--config-dir` argument).
+/// 2. `$XDG_CONFIG_HOME/websurfx` (or `$HOME/.config/websurfx` if it is not set).
+/// 3. `websurfx` under each of the directories in `$XDG_CONFIG_DIRS` (or `/etc/xdg/websurfx`
+///    if it is not set).
+/// 4. The directory provided via the `WEBSURFX_CONFIG_DIR` environment variable at compile time.
+/// 5. The `websurfx` folder under the project folder (or codebase in other words).
+///
+/// # Arguments
+///
+/// * `config_directory` - It takes the config directory provided explicitly by the user as an
+///   argument.
+/// * `env` - It takes a function which looks up the environment variables as an argument.
+fn config_directories(
+    config_directory: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Vec<String> {
+    let mut directories: Vec<String> = config_directory.map(str::to_owned).into_iter().collect();
+
+    match env("XDG_CONFIG_HOME") {
+        Some(config_home) if !config_home.is_empty() => {
+            directories.push(format!("{config_home}/{COMMON_DIRECTORY_NAME}"))
+        }
+        _ => {
+            if let Some(home) = env("HOME") {
+                directories.push(format!("{home}/.config/{COMMON_DIRECTORY_NAME}"))
+            }
+        }
+    }
+
+    match env("XDG_CONFIG_DIRS") {
+        Some(config_dirs) if !config_dirs.is_empty() => directories.extend(
+            config_dirs
+                .split(':')
+                .filter(|directory| !directory.is_empty())
+                .map(|directory| format!("{directory}/{COMMON_DIRECTORY_NAME}")),
+        ),
+        _ => directories.push(format!("/etc/xdg/{COMMON_DIRECTORY_NAME}")),
+    }
+
+    if let Some(directory) = option_env!("WEBSURFX_CONFIG_DIR") {
+        directories.push(directory.to_owned());
+    }
+
+    directories.push(format!("./{COMMON_DIRECTORY_NAME}"));
+    directories
+}
+
+/// A helper function which returns the directories in which the public folder (theme folder) is
+/// looked up in the order in which they should be checked. The directories are:
+///
+/// 1. `$XDG_DATA_HOME/websurfx` (or `$HOME/.local/share/websurfx` if it is not set).
+/// 2. `websurfx` under each of the directories in `$XDG_DATA_DIRS` (or `/usr/local/share` and
+///    `/usr/share` if it is not set).
+/// 3. `/opt/websurfx`.
+/// 4. The directory provided via the `WEBSURFX_DATA_DIR` environment variable at compile time.
+/// 5. The project folder (or codebase in other words).
+///
+/// # Arguments
+///
+/// * `env` - It takes a function which looks up the environment variables as an argument.
+fn public_directories(env: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut directories: Vec<String> = Vec::new();
+
+    match env("XDG_DATA_HOME") {
+        Some(data_home) if !data_home.is_empty() => {
+            directories.push(format!("{data_home}/{COMMON_DIRECTORY_NAME}"))
+        }
+        _ => {
+            if let Some(home) = env("HOME") {
+                directories.push(format!("{home}/.local/share/{COMMON_DIRECTORY_NAME}"))
+            }
+        }
+    }
+
+    let data_dirs = env("XDG_DATA_DIRS")
+        .filter(|data_dirs| !data_dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_owned());
+    directories.extend(
+        data_dirs
+            .split(':')
+            .filter(|directory| !directory.is_empty())
+            .map(|directory| format!("{directory}/{COMMON_DIRECTORY_NAME}")),
+    );
+
+    directories.push(format!("/opt/{COMMON_DIRECTORY_NAME}"));
+
+    if let Some(directory) = option_env!("WEBSURFX_DATA_DIR") {
+        directories.push(directory.to_owned());
+    }
+
+    directories.push(".".to_owned());
+    directories
+}
+
 /// A function which returns an appropriate path for thr provided file type by checking if the path
 /// for the given file type exists on that path.
 ///
 /// # Error
 ///
 /// Returns a `<File Name> folder/file not found!!` error if the give file_type folder/file is not
-/// present on the path on which it is being tested.
+/// present on the path on which it is being tested. If the config file path has been overridden
+/// using `set_config_file_path` then only the overridden path is checked for the config file.
 ///
 /// # Example
 ///
-/// If this function is give the file_type of Theme variant then the theme folder is checked by the
-/// following steps:
-///
-/// 1. `/opt/websurfx` if it not present here then it fallbacks to the next one (2)
-/// 2. Under project folder ( or codebase in other words) if it is not present
-///    here then it returns an error as mentioned above.
+/// If this function is give the file_type of Theme variant then the theme folder is checked under
+/// each of the directories returned by `public_directories` in order and the first one which
+/// exists is returned, if it is not present under any of them then it returns an error as
+/// mentioned above. The config file and the filter lists are checked similarly under each of the
+/// directories returned by `config_directories`.
 pub fn file_path(file_type: FileType) -> Result<&'static str, Error> {
-    let home = env!("HOME");
+    if let (FileType::Config, Some(path)) = (&file_type, CONFIG_FILE_PATH_OVERRIDE.get()) {
+        return match Path::new(path).exists() {
+            true => Ok(path),
+            false => Err(Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Config file not found at {path}!!"),
+            )),
+        };
+    }
 
     let file_path: &Vec<String> = FILE_PATHS_FOR_DIFF_FILE_TYPES
         .get_or_init(|| {
+            let env = |name: &str| std::env::var(name).ok();
+            let config_directories =
+                config_directories(CONFIG_DIRECTORY_OVERRIDE.get().map(String::as_str), env);
+            let config_file_paths = |file_name: &str| -> Vec<String> {
+                config_directories
+                    .iter()
+                    .map(|directory| format!("{directory}/{file_name}"))
+                    .collect()
+            };
+
             HashMap::from([
                 (
                     FileType::Config,
-                    vec![
-                        format!(
-                            "{}/.config/{}/{}",
-                            home, COMMON_DIRECTORY_NAME, CONFIG_FILE_NAME
-                        ),
-                        format!("/etc/xdg/{}/{}", COMMON_DIRECTORY_NAME, CONFIG_FILE_NAME),
-                        format!("./{}/{}", COMMON_DIRECTORY_NAME, CONFIG_FILE_NAME),
-                    ],
+                    config_directories
+                        .iter()
+                        .flat_map(|directory| {
+                            [CONFIG_FILE_NAME, TOML_CONFIG_FILE_NAME]
+                                .map(|file_name| format!("{directory}/{file_name}"))
+                        })
+                        .collect(),
                 ),
                 (
                     FileType::Theme,
-                    vec![
-                        format!("/opt/websurfx/{}/", PUBLIC_DIRECTORY_NAME),
-                        format!("./{}/", PUBLIC_DIRECTORY_NAME),
-                    ],
+                    public_directories(env)
+                        .iter()
+                        .map(|directory| format!("{directory}/{PUBLIC_DIRECTORY_NAME}/"))
+                        .collect(),
                 ),
+                (FileType::AllowList, config_file_paths(ALLOWLIST_FILE_NAME)),
+                (FileType::BlockList, config_file_paths(BLOCKLIST_FILE_NAME)),
                 (
-                    FileType::AllowList,
-                    vec![
-                        format!(
-                            "{}/.config/{}/{}",
-                            home, COMMON_DIRECTORY_NAME, ALLOWLIST_FILE_NAME
-                        ),
-                        format!("/etc/xdg/{}/{}", COMMON_DIRECTORY_NAME, ALLOWLIST_FILE_NAME),
-                        format!("./{}/{}", COMMON_DIRECTORY_NAME, ALLOWLIST_FILE_NAME),
-                    ],
-                ),
-                (
-                    FileType::BlockList,
-                    vec![
-                        format!(
-                            "{}/.config/{}/{}",
-                            home, COMMON_DIRECTORY_NAME, BLOCKLIST_FILE_NAME
-                        ),
-                        format!("/etc/xdg/{}/{}", COMMON_DIRECTORY_NAME, BLOCKLIST_FILE_NAME),
-                        format!("./{}/{}", COMMON_DIRECTORY_NAME, BLOCKLIST_FILE_NAME),
-                    ],
+                    FileType::NsfwDomainList,
+                    config_file_paths(NSFW_DOMAIN_LIST_FILE_NAME),
                 ),
             ])
         })
@@ -113,3 +266,93 @@ pub fn file_path(file_type: FileType) -> Result<&'static str, Error> {
         format!("{:?} file/folder not found!!", file_type),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A helper function which builds an environment variable lookup from the provided pairs.
+    fn env<'a>(variables: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |name| {
+            variables
+                .iter()
+                .find(|(variable, _)| *variable == name)
+                .map(|(_, value)| (*value).to_owned())
+        }
+    }
+
+    /// A helper function which drops the directory provided at compile time (if any) so that the
+    /// tests do not depend on the build environment.
+    fn without_build_directory(
+        mut directories: Vec<String>,
+        variable: Option<&str>,
+    ) -> Vec<String> {
+        directories.retain(|directory| Some(directory.as_str()) != variable);
+        directories
+    }
+
+    #[test]
+    fn test_config_directories_order() {
+        let directories = config_directories(
+            Some("/custom"),
+            env(&[
+                ("HOME", "/home/user"),
+                ("XDG_CONFIG_HOME", "/xdg/config"),
+                ("XDG_CONFIG_DIRS", "/etc/a::/etc/b"),
+            ]),
+        );
+        assert_eq!(
+            without_build_directory(directories, option_env!("WEBSURFX_CONFIG_DIR")),
+            vec![
+                "/custom",
+                "/xdg/config/websurfx",
+                "/etc/a/websurfx",
+                "/etc/b/websurfx",
+                "./websurfx",
+            ]
+        );
+
+        let directories = config_directories(
+            None,
+            env(&[("HOME", "/home/user"), ("XDG_CONFIG_HOME", "")]),
+        );
+        assert_eq!(
+            without_build_directory(directories, option_env!("WEBSURFX_CONFIG_DIR")),
+            vec![
+                "/home/user/.config/websurfx",
+                "/etc/xdg/websurfx",
+                "./websurfx",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_public_directories_order() {
+        let directories = public_directories(env(&[
+            ("HOME", "/home/user"),
+            ("XDG_DATA_HOME", "/xdg/data"),
+            ("XDG_DATA_DIRS", "/usr/share"),
+        ]));
+        assert_eq!(
+            without_build_directory(directories, option_env!("WEBSURFX_DATA_DIR")),
+            vec![
+                "/xdg/data/websurfx",
+                "/usr/share/websurfx",
+                "/opt/websurfx",
+                ".",
+            ]
+        );
+
+        let directories = public_directories(env(&[("HOME", "/home/user")]));
+        assert_eq!(
+            without_build_directory(directories, option_env!("WEBSURFX_DATA_DIR")),
+            vec![
+                "/home/user/.local/share/websurfx",
+                "/usr/local/share/websurfx",
+                "/usr/share/websurfx",
+                "/opt/websurfx",
+                ".",
+            ]
+        );
+    }
+}
@@ -10,36 +10,76 @@ pub mod config;
 pub mod engines;
 pub mod handler;
 pub mod models;
+pub mod query;
 pub mod results;
 pub mod server;
 pub mod templates;
 
-use std::net::TcpListener;
+use std::{future::Future, net::TcpListener, time::Duration};
 
-use crate::server::router;
+use crate::server::{
+    bot_challenge::BotChallenge,
+    client_ip::{resolve_client_ip, ClientIpKeyExtractor},
+    profiles::Profiles,
+    rate_limit::{ClientRateLimiter, SearchRateLimit},
+    router,
+    static_files::static_files,
+    usage_stats::UsageStats,
+};
 
 use actix_cors::Cors;
-use actix_files as fs;
 use actix_governor::{Governor, GovernorConfigBuilder};
 use actix_web::{
-    dev::Server,
-    http::header,
-    middleware::{Compress, Logger},
+    http::{header, KeepAlive},
+    middleware::Compress,
     web, App, HttpServer,
 };
 use cache::cacher::{Cacher, SharedCache};
-use config::parser::Config;
+use config::{
+    parser::Config,
+    reloader::{watch_config, SharedConfig},
+};
 use handler::{file_path, FileType};
+use results::{
+    aggregator::upstream_client, blocklists::refresh_blocklists, health_monitor::monitor_engines,
+};
+
+/// The listener on which the web server accepts the connections.
+pub enum Listener {
+    /// A TCP listener bound to the address and the port provided with the `binding_ip` and `port`
+    /// options.
+    Tcp(TcpListener),
+    /// A unix domain socket listener bound to the path provided with the `unix_socket` option.
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+impl From<TcpListener> for Listener {
+    fn from(listener: TcpListener) -> Self {
+        Listener::Tcp(listener)
+    }
+}
 
-/// Runs the web server on the provided TCP listener and returns a `Server` instance.
+#[cfg(unix)]
+impl From<std::os::unix::net::UnixListener> for Listener {
+    fn from(listener: std::os::unix::net::UnixListener) -> Self {
+        Listener::Unix(listener)
+    }
+}
+
+/// Runs the web server on the provided TCP or unix domain socket listener and returns a future
+/// which completes once the server has shut down gracefully (on a `SIGTERM` or `SIGINT` signal)
+/// and the results being cached have been written.
 ///
 /// # Arguments
 ///
-/// * `listener` - A `TcpListener` instance representing the address and port to listen on.
+/// * `listener` - A `TcpListener` instance representing the address and port to listen on or a
+///   `UnixListener` instance representing the unix domain socket to listen on.
 ///
 /// # Returns
 ///
-/// Returns a `Result` containing a `Server` instance on success, or an `std::io::Error` on failure.
+/// Returns a `Result` containing the future running the server on success, or an `std::io::Error`
+/// on failure.
 ///
 /// # Example
 ///
@@ -56,15 +96,60 @@ use handler::{file_path, FileType};
 /// }
 /// ```
 pub fn run(
-    listener: TcpListener,
+    listener: impl Into<Listener>,
     config: Config,
     cache: impl Cacher + 'static,
-) -> std::io::Result<Server> {
-    let public_folder_path: &str = file_path(FileType::Theme)?;
+) -> std::io::Result<impl Future<Output = std::io::Result<()>>> {
+    // The files embedded into the binary are served when the theme folder cannot be found.
+    let public_folder_path: Option<&str> = match file_path(FileType::Theme) {
+        Ok(public_folder_path) => Some(public_folder_path),
+        Err(_) if cfg!(feature = "embed-assets") => None,
+        Err(error) => return Err(error),
+    };
 
     let cloned_config_threads_opt: u8 = config.threads;
 
     let cache = web::Data::new(SharedCache::new(cache));
+    let shared_cache = cache.clone().into_inner();
+    let shutdown_timeout = config.shutdown_timeout;
+    let keep_alive = match config.keep_alive {
+        0 => KeepAlive::Disabled,
+        keep_alive => KeepAlive::Timeout(Duration::from_secs(u64::from(keep_alive))),
+    };
+    let max_connections = config.max_connections as usize;
+    let client_request_timeout = Duration::from_secs(u64::from(config.client_request_timeout));
+    let max_body_size = config.request_limits.max_body_size;
+
+    let shared_config = web::Data::new(SharedConfig::new(config.clone()));
+
+    // A single client is shared by all the workers, so that the connections to the upstream
+    // search engines are pooled and kept alive between the searches.
+    let client = web::Data::new(upstream_client(&config).map_err(std::io::Error::other)?);
+
+    // The rate of the searches of the clients is shared by all the workers.
+    let search_rate_limit = SearchRateLimit::new(&config)?;
+    // The suspicious clients are also tracked by all the workers.
+    let bot_challenge = BotChallenge::new(&config)?;
+    // The quotas and the usage counters of the api keys are also shared by all the workers.
+    let api_limiter = web::Data::new(ClientRateLimiter::new(&config, config.api.redis)?);
+    // The usage statistics are also shared by all the workers.
+    let usage_stats = web::Data::new(UsageStats::new(&config)?);
+    // The server-side preference profiles are also shared by all the workers.
+    let profiles = web::Data::new(Profiles::new(&config)?);
+
+    // The certificate and the private key are loaded before the server starts, so that an invalid
+    // certificate is reported on startup.
+    let tls_config = server::tls::server_config(&config.tls)?;
+
+    // Reload the config on config file modifications or on receiving a `SIGHUP` signal.
+    tokio::spawn(watch_config(shared_config.clone().into_inner()));
+    // Fetch the subscribed blocklists and refresh them periodically.
+    tokio::spawn(refresh_blocklists(shared_config.clone().into_inner()));
+    // Send the canary queries to the upstream search engines periodically.
+    tokio::spawn(monitor_engines(
+        shared_config.clone().into_inner(),
+        client.get_ref().clone(),
+    ));
 
     let server = HttpServer::new(move || {
         let cors: Cors = Cors::default()
@@ -80,36 +165,142 @@ pub fn run(
         App::new()
             // Compress the responses provided by the server for the client requests.
             .wrap(Compress::default())
-            .wrap(Logger::default()) // added logging middleware for logging.
-            .app_data(web::Data::new(config.clone()))
+            .app_data(shared_config.clone())
             .app_data(cache.clone())
+            .app_data(client.clone())
+            .app_data(api_limiter.clone())
+            .app_data(usage_stats.clone())
+            .app_data(profiles.clone())
+            // Limit the size of the bodies sent without a `Content-Length` header.
+            .app_data(web::FormConfig::default().limit(max_body_size))
+            .app_data(web::JsonConfig::default().limit(max_body_size))
+            .app_data(web::PayloadConfig::new(max_body_size))
             .wrap(cors)
+            // Advertise the onion service of the instance to the Tor Browser.
+            .wrap_fn(server::onion::add_onion_location)
+            // Reject the oversized requests before they are parsed.
+            .wrap_fn(server::request_size::limit_request_size)
+            // Replace the error responses without a body and the errors of the routes with the
+            // error page (or a json error for the api clients).
+            .wrap_fn(server::error_pages::render_error_pages)
+            // Send the security headers (with a new nonce for the content security policy).
+            .wrap_fn(server::security_headers::add_security_headers)
+            // Limit the rate of the searches sent by each client.
+            .wrap(search_rate_limit.clone())
+            // Make the clients looking like bots solve a challenge before searching.
+            .wrap(bot_challenge.clone())
             .wrap(Governor::new(
                 &GovernorConfigBuilder::default()
+                    .key_extractor(ClientIpKeyExtractor)
                     .per_second(config.rate_limiter.time_limit as u64)
                     .burst_size(config.rate_limiter.number_of_requests as u32)
                     .finish()
                     .unwrap(),
             ))
+            // Resolve the ip addresses of the clients through the trusted proxies before the
+            // requests are rate limited.
+            .wrap_fn(resolve_client_ip)
+            // Record the requests handled per route, including the ones rejected by the other
+            // middlewares.
+            .wrap_fn(server::metrics::record_metrics)
+            // Handle each request within a span carrying its request id and log the completed
+            // requests, the search queries are passed through the privacy module before they are
+            // logged.
+            .wrap_fn(server::request_log::trace_requests)
             // Serve images and static files (css and js files).
-            .service(
-                fs::Files::new("/static", format!("{}/static", public_folder_path))
-                    .show_files_listing(),
-            )
-            .service(
-                fs::Files::new("/images", format!("{}/images", public_folder_path))
-                    .show_files_listing(),
-            )
+            .service(static_files(
+                "/static",
+                public_folder_path.map(|path| format!("{}/static", path)),
+            ))
+            .service(static_files(
+                "/images",
+                public_folder_path.map(|path| format!("{}/images", path)),
+            ))
             .service(router::robots_data) // robots.txt
+            .service(server::routes::health::healthz) // liveness probe
+            .service(server::routes::health::readyz) // readiness probe
+            .service(server::routes::metrics::metrics) // prometheus metrics
             .service(router::index) // index page
             .service(server::routes::search::search) // search page
+            .service(server::routes::search::search_form) // search page (form parameters)
+            .service(server::routes::search::lucky) // "I'm feeling lucky" redirect
+            .service(server::routes::search::reverse_image) // reverse image search page
+            .service(server::routes::search::reverse_image_upload) // reverse image search of uploads
+            .service(server::routes::search::stream) // streamed search results
+            .service(
+                // The proxy routes are rate limited separately, as every search page requests
+                // many of them.
+                web::scope("/proxy")
+                    .wrap(Governor::new(
+                        &GovernorConfigBuilder::default()
+                            .key_extractor(ClientIpKeyExtractor)
+                            .per_millisecond(server::routes::proxy::PROXY_RATE_LIMIT_INTERVAL)
+                            .burst_size(server::routes::proxy::PROXY_RATE_LIMIT_BURST)
+                            .finish()
+                            .unwrap(),
+                    ))
+                    .service(server::routes::proxy::favicon) // favicon proxy
+                    .service(server::routes::proxy::image), // image proxy
+            )
+            .service(server::routes::redirect::redirect) // anonymous redirect
+            .service(server::routes::challenge::challenge) // bot challenge solutions
             .service(router::about) // about page
+            .service(server::routes::status::status) // engine status page
+            .service(server::routes::history::history) // search history page
+            .service(server::routes::history::clear_history)
+            .service(server::routes::bookmarks::bookmarks) // bookmarks page
+            .service(server::routes::bookmarks::add_bookmark)
+            .service(server::routes::bookmarks::remove_bookmark)
             .service(router::settings) // settings page
+            .service(server::routes::settings::save_settings)
+            .service(server::routes::settings::export_settings)
+            .service(server::routes::settings::import_settings)
+            .service(server::routes::settings::custom_stylesheet)
+            .service(server::routes::admin::engines) // admin api
+            .service(server::routes::admin::update_engine)
+            .service(server::routes::admin::api_keys)
+            .service(server::routes::admin::usage_stats)
+            .service(server::routes::api::search) // json api
+            .service(server::routes::api::usage)
+            .service(server::routes::api::info)
+            .service(server::routes::api::themes)
             .default_service(web::route().to(router::not_found)) // error page
     })
     .workers(cloned_config_threads_opt as usize)
-    // Start server on 127.0.0.1 with the user provided port number. for example 127.0.0.1:8080.
-    .listen(listener)?
-    .run();
-    Ok(server)
+    .max_connections(max_connections)
+    .keep_alive(keep_alive)
+    .client_request_timeout(client_request_timeout)
+    // The signals are handled by the `stop_on_signal` function instead, which also drains the
+    // in-flight requests on a `SIGINT` signal.
+    .disable_signals()
+    .shutdown_timeout(u64::from(shutdown_timeout));
+
+    // Start server on 127.0.0.1 with the user provided port number. for example 127.0.0.1:8080,
+    // over https if the `tls` option is provided, or on the user provided unix socket.
+    let server = match (listener.into(), tls_config) {
+        (Listener::Tcp(listener), Some(tls_config)) => {
+            server.listen_rustls_0_21(listener, tls_config)?
+        }
+        (Listener::Tcp(listener), None) => server.listen(listener)?,
+        #[cfg(unix)]
+        (Listener::Unix(listener), _) => server.listen_uds(listener)?,
+    };
+    let server = server.run();
+    tokio::spawn(server::shutdown::stop_on_signal(server.handle()));
+
+    Ok(async move {
+        server.await?;
+        // The results of the last requests are written to the cache before the server exits.
+        if tokio::time::timeout(
+            Duration::from_secs(u64::from(shutdown_timeout)),
+            shared_cache.close(),
+        )
+        .await
+        .is_err()
+        {
+            tracing::error!("The results being cached could not be written before the shutdown");
+        }
+        server::telemetry::shutdown();
+        Ok(())
+    })
 }
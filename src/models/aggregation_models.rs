@@ -2,8 +2,13 @@
 //! data scraped from the upstream search engines.
 
 use super::engine_models::EngineError;
+use crate::results::sanitize::{html_to_text, sanitize_html};
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
 
 /// A named struct to store the raw scraped search results scraped search results from the
 /// upstream search engines before aggregating it.It derives the Clone trait which is needed
@@ -12,42 +17,58 @@ use smallvec::SmallVec;
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResult {
-    /// The title of the search result.
+    /// The title of the search result as plain text (which is escaped when it is rendered).
     pub title: String,
     /// The url which is accessed when clicked on it
     pub url: String,
-    /// The description of the search result.
+    /// The description of the search result as sanitized html, which only contains the inline
+    /// formatting tags.
     pub description: String,
     /// The names of the upstream engines from which this results were provided.
     pub engine: SmallVec<[String; 0]>,
+    /// The position of the result in the results of the upstream engine (starting from 1), which
+    /// is only used while aggregating the results.
+    #[serde(skip)]
+    pub rank: usize,
+    /// The score of the result used to rank the aggregated results, computed from the positions
+    /// of the result in the results of the upstream engines and the weights of these engines.
+    #[serde(default)]
+    pub score: f32,
 }
 
 impl SearchResult {
-    /// Constructs a new `RawSearchResult` with the given arguments needed for the struct.
+    /// Constructs a new `RawSearchResult` with the given arguments needed for the struct. The
+    /// title is converted into plain text and the description is sanitized, as these are scraped
+    /// from the upstream search engines and cannot be trusted.
     ///
     /// # Arguments
     ///
     /// * `title` - The title of the search result.
     /// * `url` - The url which is accessed when clicked on it
-    /// (href url in html in simple words).
+    ///   (href url in html in simple words).
     /// * `description` - The description of the search result.
     /// * `engine` - The names of the upstream engines from which this results were provided.
     pub fn new(title: &str, url: &str, description: &str, engine: &[&str]) -> Self {
         SearchResult {
-            title: title.to_owned(),
+            title: html_to_text(title),
             url: url.to_owned(),
-            description: description.to_owned(),
+            description: sanitize_html(description),
             engine: engine.iter().map(|name| name.to_string()).collect(),
+            rank: Default::default(),
+            score: Default::default(),
         }
     }
 
-    /// A function which adds the engine name provided as a string into a vector of strings.
+    /// A function which adds the engine name provided as a string into a vector of strings, if it
+    /// is not already present.
     ///
     /// # Arguments
     ///
     /// * `engine` - Takes an engine name provided as a String.
     pub fn add_engines(&mut self, engine: &str) {
-        self.engine.push(engine.to_owned())
+        if !self.engine.iter().any(|name| name == engine) {
+            self.engine.push(engine.to_owned())
+        }
     }
 
     /// A function which returns the engine name stored from the struct as a string.
@@ -60,6 +81,27 @@ impl SearchResult {
     }
 }
 
+/// A named struct which stores the results scraped from a single upstream search engine along with
+/// the extra information provided by the engine about the search query.
+#[derive(Clone, Default)]
+pub struct EngineResults {
+    /// It stores the scraped search results keyed by their url.
+    pub results: HashMap<String, SearchResult>,
+    /// It stores the spelling correction of the search query suggested by the upstream engine.
+    pub corrected_query: Option<String>,
+    /// It stores the related searches suggested by the upstream engine.
+    pub related_queries: Vec<String>,
+}
+
+impl From<HashMap<String, SearchResult>> for EngineResults {
+    fn from(results: HashMap<String, SearchResult>) -> Self {
+        Self {
+            results,
+            ..Default::default()
+        }
+    }
+}
+
 /// A named struct that stores the error info related to the upstream search engines.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct EngineErrorInfo {
@@ -79,7 +121,7 @@ impl EngineErrorInfo {
     /// # Arguments
     ///
     /// * `error` - It takes the error type which occured while fetching the result from a particular
-    /// search engine.
+    ///   search engine.
     /// * `engine` - It takes the name of the engine that failed to provide the requested search results.
     pub fn new(error: &EngineError, engine: &str) -> Self {
         Self {
@@ -88,6 +130,11 @@ impl EngineErrorInfo {
                 EngineError::RequestError => "RequestError".to_owned(),
                 EngineError::EmptyResultSet => "EmptyResultSet".to_owned(),
                 EngineError::UnexpectedError => "UnexpectedError".to_owned(),
+                EngineError::Blocked => "Blocked".to_owned(),
+                EngineError::RateLimited => "RateLimited".to_owned(),
+                EngineError::TimedOut => "TimedOut".to_owned(),
+                EngineError::CircuitOpen => "CircuitOpen".to_owned(),
+                EngineError::CoolingDown => "CoolingDown".to_owned(),
             },
             engine: engine.to_owned(),
             severity_color: match error {
@@ -95,9 +142,25 @@ impl EngineErrorInfo {
                 EngineError::RequestError => "green".to_owned(),
                 EngineError::EmptyResultSet => "blue".to_owned(),
                 EngineError::UnexpectedError => "red".to_owned(),
+                EngineError::Blocked => "orange".to_owned(),
+                EngineError::RateLimited => "blue".to_owned(),
+                EngineError::TimedOut => "green".to_owned(),
+                EngineError::CircuitOpen => "orange".to_owned(),
+                EngineError::CoolingDown => "orange".to_owned(),
             },
         }
     }
+
+    /// A function which checks whether the engine failed to answer the search, unlike the engines
+    /// which answered without any result.
+    ///
+    /// # Returns
+    ///
+    /// It returns `true` if the results of the engine are missing from the search results,
+    /// otherwise `false`.
+    pub fn is_unavailable(&self) -> bool {
+        self.error != "EmptyResultSet"
+    }
 }
 
 /// A named struct to store, serialize, deserialize the all the search results scraped and
@@ -124,6 +187,27 @@ pub struct SearchResults {
     /// Stores the flag option which holds the check value that whether any search engines were
     /// selected or not.
     pub no_engines_selected: bool,
+    /// Stores the spelling correction of the search query suggested by the upstream engines.
+    pub corrected_query: Option<String>,
+    /// Stores the deduplicated related searches suggested by the upstream engines.
+    pub related_queries: Vec<String>,
+    /// Stores the time (in milliseconds) taken to fetch the results from the upstream engines and
+    /// to aggregate them.
+    #[serde(default)]
+    pub response_time: u64,
+    /// Stores the number of results provided by each of the upstream engines which responded
+    /// successfully, sorted by the names of the engines so that the same results are always
+    /// serialized the same way.
+    #[serde(default)]
+    pub engine_result_counts: BTreeMap<String, usize>,
+    /// Stores the number (starting from `1`) of the page of the search page on which the results
+    /// are shown.
+    #[serde(default)]
+    pub page: u32,
+    /// Stores the flag option which holds the check value that whether more results are
+    /// available on the next page of the search page or not.
+    #[serde(default)]
+    pub has_next_page: bool,
 }
 
 impl SearchResults {
@@ -132,11 +216,11 @@ impl SearchResults {
     /// # Arguments
     ///
     /// * `results` - Takes an argument of individual serializable `SearchResult` struct
-    /// and stores it into a vector of `SearchResult` structs.
+    ///   and stores it into a vector of `SearchResult` structs.
     /// * `page_query` - Takes an argument of current page`s search query `q` provided in
-    /// the search url.
+    ///   the search url.
     /// * `engine_errors_info` - Takes an array of structs which contains information regarding
-    /// which engines failed with their names, reason and their severity color name.
+    ///   which engines failed with their names, reason and their severity color name.
     pub fn new(results: Vec<SearchResult>, engine_errors_info: &[EngineErrorInfo]) -> Self {
         Self {
             results,
@@ -145,6 +229,12 @@ impl SearchResults {
             filtered: Default::default(),
             safe_search_level: Default::default(),
             no_engines_selected: Default::default(),
+            corrected_query: Default::default(),
+            related_queries: Default::default(),
+            response_time: Default::default(),
+            engine_result_counts: Default::default(),
+            page: Default::default(),
+            has_next_page: Default::default(),
         }
     }
 
@@ -177,8 +267,241 @@ impl SearchResults {
         self.no_engines_selected
     }
 
+    /// A setter function to set the spelling correction of the search query.
+    ///
+    /// # Arguments
+    ///
+    /// * `corrected_query` - It takes the corrected search query as an argument.
+    pub fn set_corrected_query(&mut self, corrected_query: Option<String>) {
+        self.corrected_query = corrected_query;
+    }
+
+    /// A setter function to set the related searches.
+    ///
+    /// # Arguments
+    ///
+    /// * `related_queries` - It takes the deduplicated related searches as an argument.
+    pub fn set_related_queries(&mut self, related_queries: Vec<String>) {
+        self.related_queries = related_queries;
+    }
+
+    /// A setter function to set the statistics of the aggregation.
+    ///
+    /// # Arguments
+    ///
+    /// * `response_time` - It takes the time taken to fetch and aggregate the results as an
+    ///   argument.
+    /// * `engine_result_counts` - It takes the number of results provided by each of the upstream
+    ///   engines as an argument.
+    pub fn set_stats(
+        &mut self,
+        response_time: Duration,
+        engine_result_counts: BTreeMap<String, usize>,
+    ) {
+        self.response_time = response_time.as_millis() as u64;
+        self.engine_result_counts = engine_result_counts;
+    }
+
+    /// A function which returns the number of upstream engines which provided at least one of
+    /// the results.
+    pub fn contributing_engines(&self) -> usize {
+        self.engine_result_counts
+            .values()
+            .filter(|count| **count > 0)
+            .count()
+    }
+
+    /// A function which lists the upstream search engines which failed to answer the search
+    /// (because they errored, timed out or were skipped), so that the search page can tell that
+    /// their results are missing.
+    ///
+    /// # Returns
+    ///
+    /// It returns the names of the unavailable engines in the order in which their errors were
+    /// recorded.
+    pub fn unavailable_engines(&self) -> Vec<&str> {
+        let mut engines: Vec<&str> = Vec::new();
+        for error in self.engine_errors_info.iter() {
+            if error.is_unavailable() && !engines.contains(&error.engine.as_str()) {
+                engines.push(&error.engine);
+            }
+        }
+        engines
+    }
+
+    /// A function which checks whether all the upstream search engines to which the search was
+    /// sent failed to answer it, in which case the empty results should neither be cached nor
+    /// shown as if the search had no result.
+    pub fn all_engines_failed(&self) -> bool {
+        self.engine_result_counts.is_empty()
+            && !self.engine_errors_info.is_empty()
+            && self
+                .engine_errors_info
+                .iter()
+                .all(EngineErrorInfo::is_unavailable)
+    }
+
+    /// A function which checks whether the search results can be cached. The empty results and
+    /// the results of a search which most of the upstream search engines failed to answer are
+    /// not cached, so that a transient outage of the engines does not keep being served from the
+    /// cache once the engines have recovered. The results of the disallowed queries and of the
+    /// searches without any selected engine do not depend on the engines and are always cached.
+    pub fn is_cacheable(&self) -> bool {
+        if self.disallowed || self.no_engines_selected {
+            return true;
+        }
+
+        let failed = self.unavailable_engines().len();
+        let answered_empty = self
+            .engine_errors_info
+            .iter()
+            .filter(|error| !error.is_unavailable())
+            .count();
+        !self.results.is_empty() && failed <= self.engine_result_counts.len() + answered_empty
+    }
+
+    /// A setter function to set the page of the search page on which the results are shown.
+    ///
+    /// # Arguments
+    ///
+    /// * `page` - It takes the number (starting from `1`) of the page as an argument.
+    /// * `has_next_page` - It takes a boolean which indicates whether more results are available
+    ///   on the next page as an argument.
+    pub fn set_page(&mut self, page: u32, has_next_page: bool) {
+        self.page = page;
+        self.has_next_page = has_next_page;
+    }
+
     /// A setter function to set the `no_engines_selected` to true.
     pub fn set_no_engines_selected(&mut self) {
         self.no_engines_selected = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_results_json_shape() {
+        let mut result = SearchResult::new(
+            "Rust",
+            "https://www.rust-lang.org/",
+            "A <b>language</b>",
+            &["duckduckgo", "bing"],
+        );
+        result.rank = 1;
+        result.score = 0.5;
+        let mut search_results = SearchResults::new(vec![result], &[]);
+        search_results.set_stats(
+            Duration::from_millis(120),
+            BTreeMap::from([("duckduckgo".to_owned(), 1)]),
+        );
+
+        let json = serde_json::to_value(&search_results).unwrap();
+        assert_eq!(
+            json["results"][0],
+            serde_json::json!({
+                "title": "Rust",
+                "url": "https://www.rust-lang.org/",
+                "description": "A <b>language</b>",
+                "engine": ["duckduckgo", "bing"],
+                "score": 0.5,
+            })
+        );
+        assert_eq!(json["responseTime"], 120);
+        assert_eq!(json["engineResultCounts"]["duckduckgo"], 1);
+        assert!(json["engineErrorsInfo"].as_array().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unavailable_engines() {
+        let search_results = SearchResults::new(
+            Vec::new(),
+            &[
+                EngineErrorInfo::new(&EngineError::TimedOut, "bing"),
+                EngineErrorInfo::new(&EngineError::EmptyResultSet, "brave"),
+                EngineErrorInfo::new(&EngineError::CoolingDown, "startpage"),
+                EngineErrorInfo::new(&EngineError::RequestError, "bing"),
+            ],
+        );
+        assert_eq!(
+            search_results.unavailable_engines(),
+            vec!["bing", "startpage"]
+        );
+
+        // The errors are kept in the json of the search results.
+        let json = serde_json::to_value(&search_results).unwrap();
+        assert_eq!(json["engineErrorsInfo"][0]["engine"], "bing");
+        assert_eq!(json["engineErrorsInfo"][0]["error"], "TimedOut");
+    }
+
+    #[test]
+    fn test_all_engines_failed() {
+        let timed_out = EngineErrorInfo::new(&EngineError::TimedOut, "bing");
+        let empty = EngineErrorInfo::new(&EngineError::EmptyResultSet, "brave");
+        assert!(!SearchResults::default().all_engines_failed());
+        // The engines answering without any result did not fail.
+        assert!(!SearchResults::new(Vec::new(), &[timed_out.clone(), empty]).all_engines_failed());
+
+        let mut search_results = SearchResults::new(Vec::new(), &[timed_out]);
+        assert!(search_results.all_engines_failed());
+        search_results.set_stats(
+            Duration::from_millis(120),
+            BTreeMap::from([("duckduckgo".to_owned(), 1)]),
+        );
+        assert!(!search_results.all_engines_failed());
+    }
+
+    #[test]
+    fn test_cacheable_results() {
+        let result = || {
+            SearchResult::new(
+                "Rust",
+                "https://www.rust-lang.org/",
+                "A language",
+                &["duckduckgo"],
+            )
+        };
+        let timed_out = |engine: &str| EngineErrorInfo::new(&EngineError::TimedOut, engine);
+        let answered = |engines: &[&str]| {
+            engines
+                .iter()
+                .map(|engine| (engine.to_string(), 1))
+                .collect::<BTreeMap<String, usize>>()
+        };
+
+        let mut search_results = SearchResults::new(vec![result()], &[timed_out("bing")]);
+        search_results.set_stats(Duration::ZERO, answered(&["duckduckgo"]));
+        assert!(search_results.is_cacheable());
+
+        // Most of the engines failed.
+        let mut search_results =
+            SearchResults::new(vec![result()], &[timed_out("bing"), timed_out("brave")]);
+        search_results.set_stats(Duration::ZERO, answered(&["duckduckgo"]));
+        assert!(!search_results.is_cacheable());
+
+        // The empty results are not cached unless they do not depend on the engines.
+        assert!(!SearchResults::default().is_cacheable());
+        let mut search_results = SearchResults::default();
+        search_results.set_disallowed();
+        assert!(search_results.is_cacheable());
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let mut search_results = SearchResults::default();
+        assert_eq!(search_results.contributing_engines(), 0);
+
+        search_results.set_stats(
+            Duration::from_micros(42_999),
+            BTreeMap::from([
+                ("duckduckgo".to_owned(), 10),
+                ("bing".to_owned(), 0),
+                ("brave".to_owned(), 3),
+            ]),
+        );
+        assert_eq!(search_results.response_time, 42);
+        assert_eq!(search_results.contributing_engines(), 2);
+    }
+}
@@ -1,10 +1,14 @@
 //! This module provides the error enum to handle different errors associated while requesting data from
 //! the upstream search engines with the search query provided by the user.
 
-use super::aggregation_models::SearchResult;
+use super::{aggregation_models::EngineResults, parser_models::EngineConfig};
+use crate::{
+    engines::registry::registry, query::operators::ParsedQuery,
+    results::user_agent::randomize_headers,
+};
 use error_stack::{Report, Result, ResultExt};
-use reqwest::Client;
-use std::{collections::HashMap, fmt};
+use reqwest::{header::HeaderMap, Client};
+use std::{fmt, sync::Arc};
 
 /// A custom error type used for handle engine associated errors.
 #[derive(Debug)]
@@ -22,6 +26,23 @@ pub enum EngineError {
     /// Selector errors and all other errors occurring within the code handling
     /// the `upstream search engines`.
     UnexpectedError,
+    /// This variant handles the responses in which the upstream search engine refused to provide
+    /// the results because it detected the requests as automated (like the rate limited or the
+    /// captcha responses).
+    Blocked,
+    /// This variant handles the requests which were not sent to the upstream search engine as
+    /// the configured rate of the requests to the engine was exceeded.
+    RateLimited,
+    /// This variant handles the upstream search engines which did not answer in time, either
+    /// within their request timeout or by the deadline of the aggregation, whose results are left
+    /// out of the search results.
+    TimedOut,
+    /// This variant handles the requests which were not sent to the upstream search engine as
+    /// the circuit breaker of the engine has tripped after its consecutive failures.
+    CircuitOpen,
+    /// This variant handles the requests which were not sent to the upstream search engine as
+    /// the engine is cooling down after it blocked the requests of the instance.
+    CoolingDown,
 }
 
 impl fmt::Display for EngineError {
@@ -42,15 +63,193 @@ impl fmt::Display for EngineError {
             EngineError::UnexpectedError => {
                 write!(f, "An unexpected error occurred while processing the data")
             }
+            EngineError::Blocked => {
+                write!(f, "The upstream search engine blocked the request")
+            }
+            EngineError::RateLimited => {
+                write!(
+                    f,
+                    "The request was not sent as the rate limit of the upstream search engine was exceeded"
+                )
+            }
+            EngineError::TimedOut => {
+                write!(f, "The upstream search engine did not answer in time")
+            }
+            EngineError::CircuitOpen => {
+                write!(
+                    f,
+                    "The request was not sent as the upstream search engine failed repeatedly"
+                )
+            }
+            EngineError::CoolingDown => {
+                write!(
+                    f,
+                    "The request was not sent as the upstream search engine recently blocked the requests"
+                )
+            }
         }
     }
 }
 
 impl error_stack::Context for EngineError {}
 
+/// A helper function which checks whether the upstream search engine refused to provide the
+/// results, which it signals with the `403 Forbidden` or the `429 Too Many Requests` status.
+///
+/// # Arguments
+///
+/// * `response` - It takes the response of the upstream search engine as an argument.
+pub fn is_blocked_response(response: &reqwest::Response) -> bool {
+    matches!(
+        response.status(),
+        reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::TOO_MANY_REQUESTS
+    )
+}
+
+/// A helper function which checks whether the page returned by the upstream search engine is a
+/// CAPTCHA or a block page rather than a page of results.
+///
+/// # Arguments
+///
+/// * `page` - It takes the html of the page returned by the upstream search engine as an argument.
+/// * `signatures` - It takes the signatures of the CAPTCHA and the block pages of the engine as an
+///   argument.
+pub fn is_blocked_page(page: &str, signatures: &[&str]) -> bool {
+    signatures.iter().any(|signature| page.contains(signature))
+}
+
+/// A helper function which checks the status of the response of the upstream search engine, the
+/// engines which refused to provide the results are reported as `Blocked` and the engines which
+/// failed with a server error as `RequestError`. The status is attached to the error, so that
+/// the transient errors can be retried.
+///
+/// # Arguments
+///
+/// * `response` - It takes the response of the upstream search engine as an argument.
+///
+/// # Error
+///
+/// It returns an `EngineError` if the status of the response is not a successful one.
+fn check_response_status(response: &reqwest::Response) -> Result<(), EngineError> {
+    if is_blocked_response(response) {
+        return Err(Report::new(EngineError::Blocked).attach_printable(response.status()));
+    }
+    if response.status().is_server_error() {
+        return Err(Report::new(EngineError::RequestError).attach_printable(response.status()));
+    }
+    Ok(())
+}
+
+/// A named struct which stores the search preferences of the user which are passed to the
+/// upstream search engines along with the search query.
+#[derive(Clone, Debug, Default)]
+pub struct SearchOptions {
+    /// It stores the safe search level used to filter the results.
+    pub safe_search: u8,
+    /// It stores the ISO 639-1 code of the language in which the results should preferably be
+    /// written.
+    pub language: Option<String>,
+    /// It stores the lowercase ISO 3166-1 alpha-2 code of the country from which the results
+    /// should preferably be provided.
+    pub region: Option<String>,
+    /// It stores the time range to which the results should be restricted by their date of
+    /// publication.
+    pub time_range: Option<TimeRange>,
+    /// It stores the patterns of the domains whose results should be dropped.
+    pub blocked_domains: Vec<String>,
+    /// It stores the patterns of the domains to which the results should be restricted.
+    pub allowed_domains: Vec<String>,
+    /// It stores the option to randomize the `Accept` and `Accept-Language` headers and the
+    /// order of the headers sent to the upstream search engines, which is provided via the
+    /// config file.
+    pub randomize_headers: bool,
+}
+
+impl SearchOptions {
+    /// A function which returns the headers to be sent to the upstream search engine, which are
+    /// randomized if the `randomize_headers` option is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `header_map` - It takes the headers built by the upstream search engine as an argument.
+    pub fn request_headers(&self, header_map: HeaderMap) -> HeaderMap {
+        match self.randomize_headers {
+            true => randomize_headers(header_map, self.language.as_deref()),
+            false => header_map,
+        }
+    }
+}
+
+/// An enum type which provides different variants for the time ranges to which the results can
+/// be restricted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeRange {
+    /// This variant restricts the results to the ones published in the past day.
+    Day,
+    /// This variant restricts the results to the ones published in the past week.
+    Week,
+    /// This variant restricts the results to the ones published in the past month.
+    Month,
+    /// This variant restricts the results to the ones published in the past year.
+    Year,
+}
+
+impl TimeRange {
+    /// All the time ranges in increasing order of their length.
+    pub const ALL: [TimeRange; 4] = [
+        TimeRange::Day,
+        TimeRange::Week,
+        TimeRange::Month,
+        TimeRange::Year,
+    ];
+
+    /// A function which parses the time range from its name as used in the `time_range` search
+    /// parameter.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the name of the time range as an argument.
+    pub fn from_name(name: &str) -> Option<Self> {
+        TimeRange::ALL
+            .into_iter()
+            .find(|time_range| time_range.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// A function which returns the name of the time range as used in the `time_range` search
+    /// parameter.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeRange::Day => "day",
+            TimeRange::Week => "week",
+            TimeRange::Month => "month",
+            TimeRange::Year => "year",
+        }
+    }
+
+    /// A function which returns the human readable name of the time range shown in the UI.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            TimeRange::Day => "Past day",
+            TimeRange::Week => "Past week",
+            TimeRange::Month => "Past month",
+            TimeRange::Year => "Past year",
+        }
+    }
+}
+
 /// A trait to define common behavior for all search engines.
 #[async_trait::async_trait]
 pub trait SearchEngine: Sync + Send {
+    /// This function returns the name (in lowercase) under which the engine is registered, which
+    /// is used to select the engine in the config file and in the settings of the users.
+    fn name(&self) -> &'static str;
+
+    /// This function returns the search categories (like `general` or `images`) whose searches
+    /// the upstream engine can answer.
+    fn categories(&self) -> &[&'static str] {
+        &["general"]
+    }
+
     /// This helper function fetches/requests the search results from the upstream search engine in
     /// an html form.
     ///
@@ -75,15 +274,24 @@ pub trait SearchEngine: Sync + Send {
         client: &Client,
     ) -> Result<String, EngineError> {
         // fetch the html from upstream search engine
-        Ok(client
+        let response = client
             .get(url)
             .headers(header_map) // add spoofed headers to emulate human behavior
             .send()
             .await
-            .change_context(EngineError::RequestError)?
+            .change_context(EngineError::RequestError)?;
+        check_response_status(&response)?;
+
+        let html = response
             .text()
             .await
-            .change_context(EngineError::RequestError)?)
+            .change_context(EngineError::RequestError)?;
+        if is_blocked_page(&html, self.blocked_page_signatures()) {
+            return Err(Report::new(EngineError::Blocked)
+                .attach_printable("the engine answered with a CAPTCHA or a block page"));
+        }
+
+        Ok(html)
     }
 
     /// This helper function fetches/requests the json search results from the upstream search engine as a vector of bytes.
@@ -109,13 +317,15 @@ pub trait SearchEngine: Sync + Send {
         client: &Client,
     ) -> Result<Vec<u8>, EngineError> {
         // fetch the json response from upstream search engine
-
-        Ok(client
+        let response = client
             .get(url)
             .headers(header_map) // add spoofed headers to emulate human behavior
             .send()
             .await
-            .change_context(EngineError::RequestError)?
+            .change_context(EngineError::RequestError)?;
+        check_response_status(&response)?;
+
+        Ok(response
             .bytes()
             .await
             .change_context(EngineError::RequestError)?
@@ -125,14 +335,17 @@ pub trait SearchEngine: Sync + Send {
     /// This function scrapes results from the upstream engine and puts all the scraped results like
     /// title, visiting_url (href in html),engine (from which engine it was fetched from) and description
     /// in a RawSearchResult and then adds that to HashMap whose keys are url and values are RawSearchResult
-    /// struct and then returns it within a Result enum.
+    /// struct and then returns it along with the spelling correction of the query suggested by the
+    /// engine (if any) within a Result enum.
     ///
     /// # Arguments
     ///
     /// * `query` - Takes the user provided query to query to the upstream search engine with.
     /// * `page` - Takes an u32 as an argument.
     /// * `user_agent` - Takes a random user agent string as an argument.
-    /// * `request_timeout` - Takes a time (secs) as a value which controls the server request timeout.
+    /// * `client` - Takes the http client used to send the requests to the upstream engine.
+    /// * `options` - Takes the search preferences of the user like the safe search level.
+    /// * `engine_config` - Takes the engine specific options provided via the config file.
     ///
     /// # Errors
     ///
@@ -140,88 +353,101 @@ pub trait SearchEngine: Sync + Send {
     /// reach the above `upstream search engine` page or if the `upstream search engine` is unable to
     /// provide results for the requested search query and also returns error if the scraping selector
     /// or HeaderMap fails to initialize.
-    async fn results(
+    async fn fetch_results(
         &self,
         query: &str,
         page: u32,
         user_agent: &str,
         client: &Client,
-        safe_search: u8,
-    ) -> Result<HashMap<String, SearchResult>, EngineError>;
+        options: &SearchOptions,
+        engine_config: &EngineConfig,
+    ) -> Result<EngineResults, EngineError>;
+
+    /// This function returns whether the upstream engine understands the advanced query
+    /// operators (`site:`, `-exclusion`, `"exact phrases"` and `filetype:`). The query sent to
+    /// the engines which do not understand them is stripped of the operators and the operators
+    /// are instead enforced by filtering the results returned by the engine.
+    fn supports_query_operators(&self) -> bool {
+        false
+    }
+
+    /// This function builds the search query sent to the upstream engines which understand the
+    /// advanced query operators by translating the parsed operators into the query syntax of
+    /// the engine. The engines whose syntax differs from the common one override it.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query parsed into the search terms and the operators as an
+    /// argument.
+    fn engine_query(&self, query: &ParsedQuery) -> String {
+        query.to_engine_query()
+    }
+
+    /// This function returns whether the upstream engine filters its results based on the safe
+    /// search level. The results of the engines which do not are instead filtered against the
+    /// list of nsfw domains when safe search is enabled.
+    fn supports_safe_search(&self) -> bool {
+        false
+    }
+
+    /// This function returns the signatures (the distinctive parts of the html) of the CAPTCHA
+    /// and the block pages served by the upstream engine when it detects the requests as
+    /// automated, so that these pages are reported as `Blocked` errors instead of as empty
+    /// results.
+    fn blocked_page_signatures(&self) -> &'static [&'static str] {
+        &[]
+    }
 }
 
 /// A named struct which stores the engine struct with the name of the associated engine.
+#[derive(Clone)]
 pub struct EngineHandler {
-    /// It stores the engine struct wrapped in a box smart pointer as the engine struct implements
+    /// It stores the engine struct shared by all the searches, as the engine struct implements
     /// the `SearchEngine` trait.
-    engine: Box<dyn SearchEngine>,
+    engine: Arc<dyn SearchEngine>,
     /// It stores the name of the engine to which the struct is associated to.
     name: &'static str,
 }
 
-impl Clone for EngineHandler {
-    fn clone(&self) -> Self {
-        Self::new(self.name).unwrap()
-    }
-}
-
 impl EngineHandler {
-    /// Parses an engine name into an engine handler.
+    /// Parses an engine name into an engine handler by looking the engine up in the registry of
+    /// the upstream search engines.
     ///
     /// # Arguments
     ///
-    /// * `engine_name` - It takes the name of the engine to which the struct was associated to.
+    /// * `engine_name` - It takes the name of the engine (ignoring the case) to which the struct
+    ///   was associated to.
     ///
-    /// # Returns
+    /// # Error
     ///
-    /// It returns an option either containing the value or a none if the engine is unknown
+    /// It returns a `NoSuchEngineFound` error if no engine is registered under the provided name.
     pub fn new(engine_name: &str) -> Result<Self, EngineError> {
-        let engine: (&'static str, Box<dyn SearchEngine>) =
-            match engine_name.to_lowercase().as_str() {
-                "duckduckgo" => {
-                    let engine = crate::engines::duckduckgo::DuckDuckGo::new()?;
-                    ("duckduckgo", Box::new(engine))
-                }
-                "searx" => {
-                    let engine = crate::engines::searx::Searx::new()?;
-                    ("searx", Box::new(engine))
-                }
-                "brave" => {
-                    let engine = crate::engines::brave::Brave::new()?;
-                    ("brave", Box::new(engine))
-                }
-                "startpage" => {
-                    let engine = crate::engines::startpage::Startpage::new()?;
-                    ("startpage", Box::new(engine))
-                }
-                "librex" => {
-                    let engine = crate::engines::librex::LibreX::new()?;
-                    ("librex", Box::new(engine))
-                }
-                "mojeek" => {
-                    let engine = crate::engines::mojeek::Mojeek::new()?;
-                    ("mojeek", Box::new(engine))
-                }
-                "bing" => {
-                    let engine = crate::engines::bing::Bing::new()?;
-                    ("bing", Box::new(engine))
-                }
-                _ => {
-                    return Err(Report::from(EngineError::NoSuchEngineFound(
-                        engine_name.to_string(),
-                    )))
-                }
-            };
-
-        Ok(Self {
-            engine: engine.1,
-            name: engine.0,
-        })
+        registry()
+            .handler(engine_name)
+            .ok_or_else(|| Report::new(EngineError::NoSuchEngineFound(engine_name.to_string())))
+    }
+
+    /// Constructs a new engine handler for the provided engine, which is associated to the name
+    /// of the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the engine struct as an argument.
+    pub fn from_engine(engine: Arc<dyn SearchEngine>) -> Self {
+        Self {
+            name: engine.name(),
+            engine,
+        }
+    }
+
+    /// This function returns the name of the engine to which the struct is associated to.
+    pub fn name(&self) -> &'static str {
+        self.name
     }
 
     /// This function converts the EngineHandler type into a tuple containing the engine name and
     /// the associated engine struct.
-    pub fn into_name_engine(self) -> (&'static str, Box<dyn SearchEngine>) {
+    pub fn into_name_engine(self) -> (&'static str, Arc<dyn SearchEngine>) {
         (self.name, self.engine)
     }
 }
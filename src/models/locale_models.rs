@@ -0,0 +1,121 @@
+//! This module provides the languages and the regions which can be selected by the users to bias
+//! the results provided by the upstream search engines.
+
+/// The languages which can be selected by the users as pairs of the ISO 639-1 language code and
+/// the name of the language displayed in the UI.
+pub const LANGUAGES: [(&str, &str); 17] = [
+    ("ar", "العربية"),
+    ("de", "Deutsch"),
+    ("en", "English"),
+    ("es", "Español"),
+    ("fr", "Français"),
+    ("hi", "हिन्दी"),
+    ("it", "Italiano"),
+    ("ja", "日本語"),
+    ("ko", "한국어"),
+    ("nl", "Nederlands"),
+    ("pl", "Polski"),
+    ("pt", "Português"),
+    ("ru", "Русский"),
+    ("sv", "Svenska"),
+    ("tr", "Türkçe"),
+    ("uk", "Українська"),
+    ("zh", "中文"),
+];
+
+/// A function which checks whether the provided language code is one of the languages which can
+/// be selected by the users and returns it in the canonical lowercase form.
+///
+/// # Arguments
+///
+/// * `language` - It takes the language code provided by the user as an argument.
+pub fn supported_language(language: &str) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(language))
+        .map(|(code, _)| *code)
+}
+
+/// The regions which can be selected by the users as triples of the lowercase ISO 3166-1 alpha-2
+/// country code, the name of the region displayed in the UI and the market code (the main
+/// language of the region along with the country code) used by some upstream search engines.
+pub const REGIONS: [(&str, &str, &str); 25] = [
+    ("ar", "Argentina", "es-AR"),
+    ("au", "Australia", "en-AU"),
+    ("at", "Austria", "de-AT"),
+    ("be", "Belgium", "nl-BE"),
+    ("br", "Brazil", "pt-BR"),
+    ("ca", "Canada", "en-CA"),
+    ("cn", "China", "zh-CN"),
+    ("fr", "France", "fr-FR"),
+    ("de", "Germany", "de-DE"),
+    ("in", "India", "en-IN"),
+    ("it", "Italy", "it-IT"),
+    ("jp", "Japan", "ja-JP"),
+    ("kr", "Korea", "ko-KR"),
+    ("mx", "Mexico", "es-MX"),
+    ("nl", "Netherlands", "nl-NL"),
+    ("pl", "Poland", "pl-PL"),
+    ("pt", "Portugal", "pt-PT"),
+    ("ru", "Russia", "ru-RU"),
+    ("es", "Spain", "es-ES"),
+    ("se", "Sweden", "sv-SE"),
+    ("ch", "Switzerland", "de-CH"),
+    ("tr", "Turkey", "tr-TR"),
+    ("ua", "Ukraine", "uk-UA"),
+    ("gb", "United Kingdom", "en-GB"),
+    ("us", "United States", "en-US"),
+];
+
+/// A function which checks whether the provided country code is one of the regions which can be
+/// selected by the users and returns it in the canonical lowercase form.
+///
+/// # Arguments
+///
+/// * `region` - It takes the country code provided by the user as an argument.
+pub fn supported_region(region: &str) -> Option<&'static str> {
+    REGIONS
+        .iter()
+        .find(|(code, _, _)| code.eq_ignore_ascii_case(region))
+        .map(|(code, _, _)| *code)
+}
+
+/// A function which returns the market code (for example `de-DE`) for the provided region. If a
+/// language is provided then the market is built from the language and the region instead of the
+/// main language of the region.
+///
+/// # Arguments
+///
+/// * `region` - It takes the lowercase country code of the region as an argument.
+/// * `language` - It takes the optional ISO 639-1 language code as an argument.
+pub fn market(region: &str, language: Option<&str>) -> Option<String> {
+    let (code, _, market) = REGIONS.iter().find(|(code, _, _)| *code == region)?;
+    Some(match language {
+        Some(language) => format!("{language}-{}", code.to_uppercase()),
+        None => (*market).to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supported_language() {
+        assert_eq!(supported_language("DE"), Some("de"));
+        assert_eq!(supported_language("xx"), None);
+        assert_eq!(supported_language(""), None);
+    }
+    #[test]
+    fn test_supported_region() {
+        assert_eq!(supported_region("GB"), Some("gb"));
+        assert_eq!(supported_region("uk"), None);
+    }
+
+    #[test]
+    fn test_market() {
+        assert_eq!(market("de", None), Some("de-DE".to_owned()));
+        assert_eq!(market("ch", Some("fr")), Some("fr-CH".to_owned()));
+        assert_eq!(market("xx", Some("fr")), None);
+    }
+}
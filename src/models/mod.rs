@@ -4,5 +4,6 @@
 
 pub mod aggregation_models;
 pub mod engine_models;
+pub mod locale_models;
 pub mod parser_models;
 pub mod server_models;
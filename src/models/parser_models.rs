@@ -1,6 +1,9 @@
 //! This module provides public models for handling, storing and serializing parsed config file
 //! options from config.lua by grouping them together.
 
+use crate::handler::assets::asset_names;
+use serde::Serialize;
+
 /// A named struct which stores,deserializes, serializes and groups the parsed config file options
 /// of theme and colorscheme names into the Style struct which derives the `Clone`, `Serialize`
 /// and Deserialize traits where the `Clone` trait is derived for allowing the struct to be
@@ -29,7 +32,7 @@ impl Style {
     ///
     /// * `theme` - It takes the parsed theme option used to set a theme for the website.
     /// * `colorscheme` - It takes the parsed colorscheme option used to set a colorscheme
-    /// for the theme being used.
+    ///   for the theme being used.
     pub fn new(theme: String, colorscheme: String, animation: Option<String>) -> Self {
         Style {
             theme,
@@ -39,12 +42,125 @@ impl Style {
     }
 }
 
+/// A named struct which stores the names of the themes, the colorschemes and the animations
+/// available in the `public/static` folder (and embedded into the binary), which are scanned when
+/// the config is parsed so that a style is available as soon as its file is added to the folder.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct StyleCatalog {
+    /// It stores the names of the themes in the alphabetical order.
+    pub themes: Vec<String>,
+    /// It stores the names of the colorschemes in the alphabetical order.
+    pub colorschemes: Vec<String>,
+    /// It stores the names of the animations in the alphabetical order.
+    pub animations: Vec<String>,
+}
+
+impl StyleCatalog {
+    /// A function which scans the `themes`, `colorschemes` and `animations` folders of the
+    /// `public/static` folder for the stylesheets of the styles.
+    pub fn scan() -> Self {
+        let style_names = |folder: &str| -> Vec<String> {
+            asset_names(&format!("static/{folder}"))
+                .into_iter()
+                .filter_map(|file_name| file_name.strip_suffix(".css").map(str::to_owned))
+                .collect()
+        };
+        StyleCatalog {
+            themes: style_names("themes"),
+            colorschemes: style_names("colorschemes"),
+            animations: style_names("animations"),
+        }
+    }
+
+    /// A function which returns the names of the styles of the provided kind.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder` - It takes the name of the folder of the styles (`themes`, `colorschemes` or
+    ///   `animations`) as an argument.
+    pub fn names(&self, folder: &str) -> &[String] {
+        match folder {
+            "themes" => &self.themes,
+            "colorschemes" => &self.colorschemes,
+            "animations" => &self.animations,
+            _ => &[],
+        }
+    }
+
+    /// A function which checks whether the provided style is available.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder` - It takes the name of the folder of the style (`themes`, `colorschemes` or
+    ///   `animations`) as an argument.
+    /// * `name` - It takes the name of the style as an argument.
+    pub fn contains(&self, folder: &str, name: &str) -> bool {
+        self.names(folder)
+            .iter()
+            .any(|style_name| style_name == name)
+    }
+}
+
 /// Configuration options for the aggregator.
 #[derive(Clone)]
 pub struct AggregatorConfig {
     /// It stores the option to whether enable or disable random delays between
     /// requests.
     pub random_delay: bool,
+    /// It stores the rank constant of the reciprocal rank fusion used to rank the aggregated
+    /// results, the higher it is the less the top positions of every engine are favoured.
+    pub rank_constant: u32,
+    /// It stores the time budget (in milliseconds) of the aggregation, after which the search
+    /// results are returned with the results of the upstream search engines which have answered
+    /// so far instead of waiting for the slowest engine.
+    pub deadline: Option<u64>,
+    /// It stores the number of the consecutive failures of an upstream search engine after which
+    /// the engine is skipped for the cool-down period, `0` disables the circuit breakers.
+    pub circuit_breaker_threshold: u32,
+    /// It stores the time (in seconds) for which a failing upstream search engine is skipped
+    /// before a probe request is sent to it.
+    pub circuit_breaker_cooldown: u64,
+    /// It stores the number of the times a request to an upstream search engine is retried after
+    /// a transient error (like a `429` or a `5xx` response or a reset connection).
+    pub retries: u8,
+    /// It stores the time (in milliseconds) to wait before the first retry, which doubles with
+    /// every retry.
+    pub retry_backoff: u64,
+    /// It stores the largest random time (in milliseconds) added to the wait before a retry.
+    pub retry_jitter: u64,
+    /// It stores the time (in seconds) for which an upstream search engine which blocked the
+    /// instance is skipped, which doubles with every block following the cool-down period, `0`
+    /// disables the backoff.
+    pub block_cooldown: u64,
+    /// It stores the longest time (in seconds) for which a blocked upstream search engine is
+    /// skipped.
+    pub max_block_cooldown: u64,
+    /// It stores the number of the upstream search engines to which each search is sent, which
+    /// are picked adaptively from the selected engines based on their recent performance, or
+    /// `None` to send each search to all the selected engines.
+    pub max_engines: Option<usize>,
+    /// It stores the probability (between `0` and `1`) with which an engine which is not among
+    /// the best performing ones is picked, so that the statistics of all the engines stay fresh.
+    pub exploration_rate: f32,
+}
+
+impl Default for AggregatorConfig {
+    fn default() -> Self {
+        AggregatorConfig {
+            random_delay: false,
+            rank_constant: 60,
+            deadline: None,
+            circuit_breaker_threshold: 5,
+            circuit_breaker_cooldown: 30,
+            retries: 1,
+            retry_backoff: 200,
+            retry_jitter: 100,
+            block_cooldown: 60,
+            max_block_cooldown: 3600,
+            max_engines: None,
+            exploration_rate: 0.1,
+        }
+    }
 }
 
 /// Configuration options for the rate limiter middleware.
@@ -55,3 +171,511 @@ pub struct RateLimiter {
     /// The time limit in which the quantity of requests that should be accepted.
     pub time_limit: u8,
 }
+
+/// Configuration options for the rate limiter of the search routes, which limits the searches
+/// sent by each client ip address.
+#[derive(Clone)]
+pub struct SearchRateLimiter {
+    /// The number of searches a client can send at once before it is rate limited.
+    pub burst: u32,
+    /// The number of searches per minute a client can send in a sustained way.
+    pub per_minute: u32,
+    /// Whether the rate of the clients is tracked in the redis server, so that it is shared by
+    /// all the replicas of the instance.
+    pub redis: bool,
+}
+
+impl Default for SearchRateLimiter {
+    fn default() -> Self {
+        SearchRateLimiter {
+            burst: 20,
+            per_minute: 30,
+            redis: false,
+        }
+    }
+}
+
+/// Configuration options for the proof-of-work challenge which the clients looking like bots
+/// (sending searches in bursts or without the headers sent by the browsers) should solve before
+/// their searches are accepted.
+#[derive(Clone)]
+pub struct BotChallengeConfig {
+    /// Whether the suspicious clients are challenged.
+    pub enabled: bool,
+    /// The number of leading zero bits the hash of a solved challenge should have.
+    pub difficulty: u8,
+    /// The number of searches a client can send at once before it is challenged.
+    pub burst: u32,
+    /// The number of searches per minute a client can send in a sustained way before it is
+    /// challenged.
+    pub per_minute: u32,
+    /// The number of seconds during which the searches of a client which solved a challenge are
+    /// accepted without a new challenge.
+    pub validity: u64,
+}
+
+impl Default for BotChallengeConfig {
+    fn default() -> Self {
+        BotChallengeConfig {
+            enabled: false,
+            difficulty: 16,
+            burst: 10,
+            per_minute: 10,
+            validity: 86400,
+        }
+    }
+}
+
+/// Configuration options for limiting the values accepted from the search parameters.
+#[derive(Clone)]
+pub struct RequestLimits {
+    /// The maximum number of characters allowed in the search query.
+    pub max_query_length: usize,
+    /// The maximum page number which can be requested.
+    pub max_page: u32,
+    /// The maximum number of results displayed on a single page.
+    pub max_results_per_page: usize,
+    /// The maximum length (in bytes) of the query string of a request.
+    pub max_query_string_length: usize,
+    /// The maximum size (in bytes) of the cookies sent with a request.
+    pub max_cookie_size: usize,
+    /// The maximum size (in bytes) of the body of a request.
+    pub max_body_size: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        RequestLimits {
+            max_query_length: 512,
+            max_page: 100,
+            max_results_per_page: 100,
+            max_query_string_length: 4096,
+            max_cookie_size: 8192,
+            max_body_size: 65536,
+        }
+    }
+}
+
+/// The safe search levels which can be selected by the users. The levels 3 and 4 which can only
+/// be set by the server administrator behave like the `Strict` level with the filter lists
+/// applied on top of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SafeSearchLevel {
+    /// No content is filtered.
+    Off,
+    /// The explicit content is filtered.
+    Moderate,
+    /// All the sensitive content is filtered.
+    Strict,
+}
+
+impl SafeSearchLevel {
+    /// The safe search levels which can be selected by the users in the order of their values.
+    pub const ALL: [SafeSearchLevel; 3] = [
+        SafeSearchLevel::Off,
+        SafeSearchLevel::Moderate,
+        SafeSearchLevel::Strict,
+    ];
+
+    /// A function which converts the numeric safe search level into the named safe search level.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - It takes the numeric safe search level as an argument.
+    pub fn from_level(level: u8) -> Self {
+        match level {
+            0 => SafeSearchLevel::Off,
+            1 => SafeSearchLevel::Moderate,
+            _ => SafeSearchLevel::Strict,
+        }
+    }
+
+    /// A function which converts the name of the safe search level into the named safe search
+    /// level.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the case insensitive name of the safe search level as an argument.
+    pub fn from_name(name: &str) -> Option<Self> {
+        SafeSearchLevel::ALL
+            .into_iter()
+            .find(|level| level.name().eq_ignore_ascii_case(name))
+    }
+
+    /// A function which returns the numeric value of the safe search level.
+    pub fn level(self) -> u8 {
+        self as u8
+    }
+
+    /// A function which returns the name of the safe search level.
+    pub fn name(self) -> &'static str {
+        match self {
+            SafeSearchLevel::Off => "off",
+            SafeSearchLevel::Moderate => "moderate",
+            SafeSearchLevel::Strict => "strict",
+        }
+    }
+
+    /// A function which returns the name of the safe search level displayed in the UI.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            SafeSearchLevel::Off => "Off",
+            SafeSearchLevel::Moderate => "Moderate",
+            SafeSearchLevel::Strict => "Strict",
+        }
+    }
+}
+
+/// The settings of the users which can be locked to the values of the config with the
+/// `locked_settings` option, so that the users cannot override them with their cookie, the search
+/// parameters or the settings page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockedSetting {
+    /// The safe search level is always the `safe_search` option.
+    SafeSearch,
+    /// The upstream search engines are always the engines enabled in the config.
+    Engines,
+    /// The language of the results is always selected automatically.
+    Language,
+    /// The results are never restricted to a region.
+    Region,
+    /// The results are never restricted to a time range.
+    TimeRange,
+}
+
+impl LockedSetting {
+    /// The settings which can be locked.
+    pub const ALL: [LockedSetting; 5] = [
+        LockedSetting::SafeSearch,
+        LockedSetting::Engines,
+        LockedSetting::Language,
+        LockedSetting::Region,
+        LockedSetting::TimeRange,
+    ];
+
+    /// A function which converts the name of the setting into the lockable setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the name of the setting as an argument.
+    pub fn from_name(name: &str) -> Option<Self> {
+        LockedSetting::ALL
+            .into_iter()
+            .find(|setting| setting.name() == name)
+    }
+
+    /// A function which returns the name of the setting used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            LockedSetting::SafeSearch => "safe_search",
+            LockedSetting::Engines => "engines",
+            LockedSetting::Language => "language",
+            LockedSetting::Region => "region",
+            LockedSetting::TimeRange => "time_range",
+        }
+    }
+}
+
+/// The formats in which the logs are written to the stdout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The logs are written as human readable lines.
+    #[default]
+    Text,
+    /// The logs are written as json objects (one per line), which can be ingested by the log
+    /// collectors without being parsed.
+    Json,
+}
+
+impl LogFormat {
+    /// A function which converts the name of the log format into the log format.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the case insensitive name of the log format as an argument.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [LogFormat::Text, LogFormat::Json]
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    /// A function which returns the name of the log format.
+    pub fn name(self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+/// Configuration options for the admin api which allows the instance admins to change the
+/// upstream search engine options at runtime.
+#[derive(Clone, Default)]
+pub struct AdminConfig {
+    /// It stores the token which authenticates the requests to the admin api. The admin api is
+    /// disabled when no token is provided.
+    pub token: Option<String>,
+    /// It stores the path to the state file in which the changes made through the admin api are
+    /// persisted. The default state file path is used when no path is provided.
+    pub state_file: Option<String>,
+}
+
+/// Configuration options of an api key which gives access to the json api.
+#[derive(Clone)]
+pub struct ApiKey {
+    /// It stores the name under which the usage of the key is reported.
+    pub name: String,
+    /// It stores the secret key sent with the requests to the json api.
+    pub key: String,
+    /// It stores the number of requests which can be sent at once with the key.
+    pub burst: u32,
+    /// It stores the number of requests per minute which can be sent with the key in a sustained
+    /// way.
+    pub per_minute: u32,
+}
+
+impl ApiKey {
+    /// The number of requests which can be sent at once with a key by default.
+    pub const DEFAULT_BURST: u32 = 10;
+    /// The number of requests per minute which can be sent with a key by default.
+    pub const DEFAULT_PER_MINUTE: u32 = 60;
+}
+
+/// Configuration options for the json api served under `/api/v1`.
+#[derive(Clone, Default)]
+pub struct ApiConfig {
+    /// It stores the api keys which give access to the json api. The json api is disabled when
+    /// no key is provided.
+    pub keys: Vec<ApiKey>,
+    /// Whether the quotas and the usage counters of the keys are tracked in the redis server, so
+    /// that they are shared by all the replicas of the instance.
+    pub redis: bool,
+}
+
+/// Configuration options for serving the instance over https without a reverse proxy terminating
+/// the TLS connections.
+#[derive(Clone, Default)]
+pub struct TlsConfig {
+    /// It stores the path to the PEM file containing the certificate chain of the instance.
+    pub certificate: Option<String>,
+    /// It stores the path to the PEM file containing the private key of the certificate.
+    pub private_key: Option<String>,
+}
+
+/// Configuration options which keep the search queries of the users private on the instance.
+#[derive(Clone, Default)]
+pub struct PrivacyConfig {
+    /// It stores the option to replace the search queries with their hashes wherever they would be
+    /// written to the logs.
+    pub no_query_logging: bool,
+}
+
+/// Configuration options for the opt-in anonymized usage statistics of the instance.
+#[derive(Clone)]
+pub struct UsageStatsConfig {
+    /// Whether the searches are counted in the usage statistics.
+    pub enabled: bool,
+    /// Whether the usage statistics are shown on the public engine status page.
+    pub public: bool,
+    /// Whether the usage statistics are tracked in the redis server, so that they are shared by
+    /// all the replicas of the instance.
+    pub redis: bool,
+    /// It stores the number of days for which the usage statistics are kept.
+    pub retention_days: u16,
+}
+
+impl Default for UsageStatsConfig {
+    fn default() -> Self {
+        UsageStatsConfig {
+            enabled: false,
+            public: false,
+            redis: false,
+            retention_days: 30,
+        }
+    }
+}
+
+/// Configuration options for the server-side preference profiles, which store the settings of the
+/// users on the instance instead of in their cookies.
+#[derive(Clone)]
+pub struct ProfilesConfig {
+    /// Whether the users can save their settings in a server-side profile.
+    pub enabled: bool,
+    /// Whether the profiles are stored in the redis server, so that they are shared by all the
+    /// replicas of the instance and survive restarts.
+    pub redis: bool,
+    /// It stores the number of days after which an unused profile is deleted.
+    pub expiry_days: u16,
+    /// It stores the maximum size (in bytes) of the settings stored in a profile.
+    pub max_size: usize,
+    /// Whether the users of the profiles can opt into keeping their search history in their
+    /// profile.
+    pub history: bool,
+    /// It stores the maximum number of searches kept in the search history of a profile.
+    pub max_history: usize,
+}
+
+impl Default for ProfilesConfig {
+    fn default() -> Self {
+        ProfilesConfig {
+            enabled: false,
+            redis: false,
+            expiry_days: 365,
+            max_size: 64 * 1024,
+            history: false,
+            max_history: 100,
+        }
+    }
+}
+
+/// Configuration options for the background health monitor, which periodically sends a canary
+/// query to each enabled upstream search engine.
+#[derive(Clone)]
+pub struct HealthMonitorConfig {
+    /// Whether the canary queries are sent to the upstream search engines.
+    pub enabled: bool,
+    /// It stores the interval (in seconds) at which the canary queries are sent.
+    pub interval: u64,
+    /// It stores the canary query, which should be answered with results by every engine.
+    pub query: String,
+}
+
+impl Default for HealthMonitorConfig {
+    fn default() -> Self {
+        HealthMonitorConfig {
+            enabled: false,
+            interval: 600,
+            query: "wikipedia".to_owned(),
+        }
+    }
+}
+
+/// Configuration options to export the spans of the requests to an OpenTelemetry collector (like
+/// Jaeger or Tempo) over OTLP.
+#[derive(Clone)]
+pub struct OtlpConfig {
+    /// It stores the url of the OTLP/HTTP endpoint of the collector, the spans are not exported
+    /// if it is not provided.
+    pub endpoint: Option<String>,
+    /// It stores the name of the service under which the spans are exported.
+    pub service_name: String,
+    /// It stores the ratio (between `0` and `1`) of the requests whose spans are exported.
+    pub sample_ratio: f64,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        OtlpConfig {
+            endpoint: None,
+            service_name: "websurfx".to_owned(),
+            sample_ratio: 1.0,
+        }
+    }
+}
+
+/// Configuration options for the security headers sent with all the responses of the instance.
+#[derive(Clone)]
+pub struct SecurityHeaders {
+    /// It stores the value of the `Content-Security-Policy` header, in which `{nonce}` is replaced
+    /// with the nonce generated for each response, or an empty string to not send the header.
+    pub content_security_policy: String,
+    /// It stores the value of the `Referrer-Policy` header or an empty string to not send it.
+    pub referrer_policy: String,
+    /// It stores the value of the `Permissions-Policy` header or an empty string to not send it.
+    pub permissions_policy: String,
+    /// It stores the max age (in seconds) of the `Strict-Transport-Security` header, which is not
+    /// sent when it is `None`.
+    pub hsts_max_age: Option<u64>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        SecurityHeaders {
+            content_security_policy: "default-src 'self'; script-src 'self' 'nonce-{nonce}'; \
+                style-src 'self' 'nonce-{nonce}'; img-src 'self' data:; object-src 'none'; \
+                base-uri 'self'; form-action 'self'; frame-ancestors 'none'"
+                .to_owned(),
+            referrer_policy: "no-referrer".to_owned(),
+            permissions_policy: "camera=(), geolocation=(), microphone=(), payment=(), usb=()"
+                .to_owned(),
+            hsts_max_age: None,
+        }
+    }
+}
+
+/// Configuration options for routing the requests to the upstream search engines through the Tor
+/// network, so that the ip address of the instance cannot be blocked or profiled.
+#[derive(Clone)]
+pub struct TorConfig {
+    /// It stores the option to whether route the requests through Tor or not.
+    pub enabled: bool,
+    /// It stores the url of the socks port of the Tor daemon.
+    pub socks_url: String,
+    /// It stores the option to whether send the concurrent searches through separate Tor circuits
+    /// or not.
+    pub isolate_streams: bool,
+    /// It stores the address of the control port of the Tor daemon which is used to request new
+    /// circuits when the upstream search engines block the requests.
+    pub control_address: Option<String>,
+    /// It stores the password which authenticates the connections to the control port.
+    pub control_password: Option<String>,
+    /// It stores the number of consecutive blocked requests after which new circuits are
+    /// requested, new circuits are never requested if it is `0`.
+    pub newnym_after_blocks: u32,
+}
+
+impl Default for TorConfig {
+    fn default() -> Self {
+        TorConfig {
+            enabled: false,
+            socks_url: "socks5h://127.0.0.1:9050".to_owned(),
+            isolate_streams: true,
+            control_address: None,
+            control_password: None,
+            newnym_after_blocks: 3,
+        }
+    }
+}
+
+/// Configuration options for an individual upstream search engine.
+#[derive(Clone)]
+pub struct EngineConfig {
+    /// It stores the option to whether enable or disable the upstream search engine by default.
+    pub enabled: bool,
+    /// It stores the weight of the results provided by the upstream search engine which is used
+    /// when ranking the aggregated results.
+    pub weight: f32,
+    /// It stores the request timeout (secs) for the upstream search engine which overrides the
+    /// global `request_timeout` option.
+    pub timeout: Option<u8>,
+    /// It stores the connect timeout (secs) for the upstream search engine which overrides the
+    /// global `connect_timeout` option.
+    pub connect_timeout: Option<u8>,
+    /// It stores the default region of the results of the upstream search engine as a country
+    /// code which is mapped to the region code of the engine.
+    pub region: Option<String>,
+    /// It stores the key of the official search api of the upstream search engine (for the
+    /// engines which support it).
+    pub api_key: Option<String>,
+    /// It stores the url of the proxy through which the requests to the upstream search engine
+    /// should be sent.
+    pub proxy: Option<String>,
+    /// It stores the number of requests per second which can be sent to the upstream search
+    /// engine, the requests are not limited if it is not set.
+    pub requests_per_second: Option<f32>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig {
+            enabled: false,
+            weight: 1.0,
+            timeout: None,
+            connect_timeout: None,
+            region: None,
+            api_key: None,
+            proxy: None,
+            requests_per_second: None,
+        }
+    }
+}
@@ -1,11 +1,21 @@
 //! This module provides the models to parse cookies and search parameters from the search
 //! engine website.
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::BTreeMap};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
 use super::parser_models::Style;
 
+/// The version of the format of the `appCookie` cookie written by the settings form.
+pub const COOKIE_VERSION: u32 = 1;
+
+/// The migrations of the settings cookie, the migration at the index `n` upgrades the fields of
+/// the cookies written with the version `n` of the format to the version `n + 1`. A migration
+/// should be added here whenever the format of the cookie changes in an incompatible way (like a
+/// renamed field or a field whose values have changed), along with bumping `COOKIE_VERSION`.
+const COOKIE_MIGRATIONS: [fn(&mut Map<String, Value>); COOKIE_VERSION as usize] = [migrate_v0];
+
 /// A named struct which deserializes all the user provided search parameters and stores them.
 #[derive(Deserialize)]
 pub struct SearchParams {
@@ -18,20 +28,109 @@ pub struct SearchParams {
     /// It stores the search parameter `safesearch` (or safe search level in simple words) of the
     /// search url.
     pub safesearch: Option<u8>,
+    /// It stores the search parameter `category` (or the search category tab in simple words) of
+    /// the search url.
+    pub category: Option<String>,
+    /// It stores the search parameter `lang` (or the language of the results in simple words) of
+    /// the search url.
+    pub lang: Option<String>,
+    /// It stores the search parameter `region` (or the country from which the results should be
+    /// provided in simple words) of the search url.
+    pub region: Option<String>,
+    /// It stores the search parameter `time_range` (or the time range to which the results should
+    /// be restricted in simple words) of the search url.
+    pub time_range: Option<String>,
+    /// It stores the search parameter `engines` (or the comma separated list of upstream search
+    /// engines to be used for this request only in simple words) of the search url.
+    pub engines: Option<String>,
+    /// It stores the search parameter `refine` (or the additional search terms used to narrow the
+    /// results of the search query in simple words) of the search url.
+    pub refine: Option<String>,
+    /// It stores the search parameter `format` (or the format of the response, `json` to get the
+    /// search results as json instead of the search page in simple words) of the search url.
+    pub format: Option<String>,
+    /// It stores the search parameter `stream` (or whether the results should be streamed to the
+    /// search page as the upstream search engines answer in simple words) of the search url.
+    pub stream: Option<bool>,
+    /// It stores the CSRF token submitted with the search form sent to the `POST /search` route.
+    pub csrf_token: Option<String>,
+    /// It stores the search parameter `preferences` (or the settings of the user encoded in the
+    /// url, which take precedence over the settings cookie in simple words) of the search url.
+    pub preferences: Option<String>,
 }
 
-/// A named struct which is used to deserialize the cookies fetched from the client side.
-#[allow(dead_code)]
+/// A named struct which deserializes the parameters of the reverse image search route.
 #[derive(Deserialize)]
+pub struct ReverseImageParams {
+    /// It stores the url of the image whose pages should be searched.
+    pub url: Option<String>,
+    /// It stores the format of the response (`json` to get the search results as json instead of
+    /// the search page).
+    pub format: Option<String>,
+}
+
+/// The search categories for which the users can select other upstream search engines than the
+/// ones used for the general search category (the `engines` of the cookie).
+pub const ENGINE_CATEGORIES: [&str; 3] = ["images", "videos", "news"];
+
+/// A named struct which is used to deserialize the cookies fetched from the client side and to
+/// serialize the cookie written by the settings form.
+#[allow(dead_code)]
+#[derive(Deserialize, Serialize)]
 pub struct Cookie<'a> {
+    /// It stores the version of the format of the cookie (`0` for the cookies written before the
+    /// format was versioned).
+    #[serde(default)]
+    pub version: u32,
     /// It stores the theme name used in the website.
     pub theme: Cow<'a, str>,
     /// It stores the colorscheme name used for the website theme.
     pub colorscheme: Cow<'a, str>,
+    /// It stores the animation name used for the website theme.
+    #[serde(default)]
+    pub animation: Option<Cow<'a, str>>,
     /// It stores the user selected upstream search engines selected from the UI.
     pub engines: Cow<'a, Vec<Cow<'a, str>>>,
+    /// It stores the upstream search engines selected by the user from the UI for each of the
+    /// `ENGINE_CATEGORIES`, the `engines` are used for the categories which are not listed.
+    #[serde(default)]
+    pub category_engines: BTreeMap<String, Vec<String>>,
     /// It stores the user selected safe search level from the UI.
     pub safe_search_level: u8,
+    /// It stores the user selected language of the results from the UI.
+    #[serde(default)]
+    pub language: Option<Cow<'a, str>>,
+    /// It stores the user selected region of the results from the UI.
+    #[serde(default)]
+    pub region: Option<Cow<'a, str>>,
+    /// It stores the user selected time range of the results from the UI.
+    #[serde(default)]
+    pub time_range: Option<Cow<'a, str>>,
+    /// It stores the patterns of the domains whose results should be dropped provided by the user
+    /// from the UI.
+    #[serde(default)]
+    pub blocked_domains: Vec<String>,
+    /// It stores the patterns of the domains to which the results should be restricted provided by
+    /// the user from the UI.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    /// It stores whether the search terms should be highlighted in the descriptions of the
+    /// results (which is the default) as selected by the user from the UI.
+    #[serde(default)]
+    pub highlight: Option<bool>,
+    /// It stores the number of the results shown on each page of the search page as selected by
+    /// the user from the UI (the `results_per_page` option is used when it is `None`).
+    #[serde(default)]
+    pub results_per_page: Option<u16>,
+    /// It stores the custom stylesheet provided by the user from the UI, which can only be saved
+    /// in the server-side profile of the user.
+    #[serde(default)]
+    pub custom_css: Option<String>,
+    /// It stores whether the searches should be kept in the search history of the server-side
+    /// profile of the user as selected by the user from the UI (the history is off when it is
+    /// `None`).
+    #[serde(default)]
+    pub search_history: Option<bool>,
 }
 
 impl<'a> Cookie<'a> {
@@ -39,10 +138,124 @@ impl<'a> Cookie<'a> {
     pub fn build(style: &'a Style, mut engines: Vec<Cow<'a, str>>, safe_search_level: u8) -> Self {
         engines.sort();
         Self {
+            version: COOKIE_VERSION,
             theme: Cow::Borrowed(&style.theme),
             colorscheme: Cow::Borrowed(&style.colorscheme),
+            animation: style.animation.as_deref().map(Cow::Borrowed),
             engines: Cow::Owned(engines),
+            category_engines: BTreeMap::new(),
             safe_search_level,
+            language: None,
+            region: None,
+            time_range: None,
+            blocked_domains: Vec::new(),
+            allowed_domains: Vec::new(),
+            highlight: None,
+            results_per_page: None,
+            custom_css: None,
+            search_history: None,
+        }
+    }
+
+    /// A function which parses the value of the settings cookie. The cookies written with an
+    /// older version of the format are migrated to the current version and the fields which are
+    /// missing or invalid (like the fields added after the cookie was written) are taken from the
+    /// provided default settings, so that the settings of the user are kept as much as possible
+    /// instead of being dropped altogether.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - It takes the value of the settings cookie as an argument.
+    /// * `defaults` - It takes the settings used when the cookie is missing as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the settings of the user, which are the default settings if the cookie is not a
+    /// json object.
+    pub fn parse(value: &str, defaults: Cookie<'a>) -> Cookie<'a> {
+        let Ok(Value::Object(mut fields)) = serde_json::from_str::<Value>(value) else {
+            return defaults;
+        };
+        let Ok(Value::Object(mut settings)) = serde_json::to_value(&defaults) else {
+            return defaults;
+        };
+
+        let version = fields
+            .get("version")
+            .and_then(Value::as_u64)
+            .unwrap_or_default() as usize;
+        for migrate in COOKIE_MIGRATIONS.iter().skip(version) {
+            migrate(&mut fields);
+        }
+        fields.insert("version".to_owned(), Value::from(COOKIE_VERSION));
+
+        // Each field of the cookie is kept only if it is valid, so that an invalid field does not
+        // drop the other settings of the user.
+        for (name, field) in fields {
+            let default = settings.insert(name.clone(), field);
+            if serde_json::from_value::<Cookie<'_>>(Value::Object(settings.clone())).is_err() {
+                match default {
+                    Some(default) => settings.insert(name, default),
+                    None => settings.remove(&name),
+                };
+            }
         }
+
+        serde_json::from_value(Value::Object(settings)).unwrap_or(defaults)
+    }
+}
+
+/// A helper function which upgrades the cookies written by the settings page before the format
+/// of the cookie was versioned, the highlighting of the search terms was stored as `true` when it
+/// was enabled (`null` is used now) and the empty animation was stored as an empty string.
+///
+/// # Arguments
+///
+/// * `fields` - It takes the fields of the cookie as an argument.
+fn migrate_v0(fields: &mut Map<String, Value>) {
+    if fields.get("highlight") == Some(&Value::Bool(true)) {
+        fields.insert("highlight".to_owned(), Value::Null);
+    }
+    if fields.get("animation").and_then(Value::as_str) == Some("") {
+        fields.insert("animation".to_owned(), Value::Null);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_migration() {
+        let style = Style::new("simple".to_owned(), "catppuccin-mocha".to_owned(), None);
+        let defaults = || Cookie::build(&style, vec![Cow::Borrowed("DuckDuckGo")], 1);
+
+        // The cookies written before the format was versioned are upgraded.
+        let cookie = Cookie::parse(
+            r#"{"theme":"simple","colorscheme":"nord","animation":"","engines":["Bing"],"safe_search_level":2,"highlight":true}"#,
+            defaults(),
+        );
+        assert_eq!(cookie.version, COOKIE_VERSION);
+        assert_eq!(cookie.colorscheme, "nord");
+        assert_eq!(cookie.animation, None);
+        assert_eq!(*cookie.engines, ["Bing"]);
+        assert_eq!(cookie.safe_search_level, 2);
+        assert_eq!(cookie.highlight, None);
+
+        // The missing and invalid fields are taken from the defaults.
+        let cookie = Cookie::parse(
+            r#"{"version":1,"colorscheme":"nord","safe_search_level":"high","language":"de","unknown":0}"#,
+            defaults(),
+        );
+        assert_eq!(cookie.theme, style.theme);
+        assert_eq!(cookie.colorscheme, "nord");
+        assert_eq!(*cookie.engines, ["DuckDuckGo"]);
+        assert_eq!(cookie.safe_search_level, 1);
+        assert_eq!(cookie.language.as_deref(), Some("de"));
+
+        // The cookies which are not a json object are ignored.
+        let cookie = Cookie::parse("not json", defaults());
+        assert_eq!(cookie.colorscheme, style.colorscheme);
+        assert_eq!(*cookie.engines, ["DuckDuckGo"]);
     }
 }
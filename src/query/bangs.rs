@@ -0,0 +1,156 @@
+//! This module provides the functionality to handle DuckDuckGo style bangs in the search query,
+//! like `!w rust` which redirects the user to the Wikipedia search for `rust` or `!images cats`
+//! which switches the search category to images. A lone `!` (like `! rust`) redirects the user to
+//! the top ranked result.
+
+use std::collections::HashMap;
+
+/// The placeholder in the bang url templates which is replaced with the search query.
+pub const QUERY_PLACEHOLDER: &str = "{query}";
+
+/// The built-in bangs provided by the server along with the url templates to which the user
+/// should be redirected.
+const BUILTIN_BANGS: &[(&str, &str)] = &[
+    ("a", "https://www.amazon.com/s?k={query}"),
+    ("aw", "https://wiki.archlinux.org/index.php?search={query}"),
+    ("crates", "https://crates.io/search?q={query}"),
+    ("ddg", "https://duckduckgo.com/?q={query}"),
+    ("g", "https://www.google.com/search?q={query}"),
+    ("gh", "https://github.com/search?q={query}"),
+    ("imdb", "https://www.imdb.com/find?q={query}"),
+    (
+        "mdn",
+        "https://developer.mozilla.org/en-US/search?q={query}",
+    ),
+    ("osm", "https://www.openstreetmap.org/search?query={query}"),
+    ("r", "https://www.reddit.com/search/?q={query}"),
+    ("rs", "https://docs.rs/releases/search?query={query}"),
+    ("so", "https://stackoverflow.com/search?q={query}"),
+    (
+        "w",
+        "https://en.wikipedia.org/wiki/Special:Search?search={query}",
+    ),
+    (
+        "wt",
+        "https://en.wiktionary.org/wiki/Special:Search?search={query}",
+    ),
+    ("yt", "https://www.youtube.com/results?search_query={query}"),
+];
+
+/// The search categories which can be switched to with the internal bangs (for example
+/// `!images`) instead of redirecting the user.
+pub const CATEGORY_BANGS: &[&str] = &[
+    "general", "images", "videos", "news", "maps", "music", "science", "it",
+];
+
+/// An enum type which provides different variants for the actions triggered by a bang.
+#[derive(Debug, PartialEq)]
+pub enum Bang {
+    /// This variant handles the bangs which redirect the user to an external website and stores
+    /// the url to which the user should be redirected.
+    Redirect(String),
+    /// This variant handles the internal bangs which switch the search category and stores the
+    /// name of the category.
+    Category(&'static str),
+    /// This variant handles the lone `!` which redirects the user to the top ranked result of the
+    /// search query ("I'm feeling lucky").
+    Lucky,
+}
+
+/// A function which finds the first bang in the search query and resolves it against the custom
+/// bangs provided via the `bangs` config option and the built-in bangs. The custom bangs take
+/// precedence over the built-in bangs so that instances can override them.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query provided by the user as an argument.
+/// * `custom_bangs` - It takes the custom bangs provided via the config file as an argument.
+///
+/// # Returns
+///
+/// It returns the action triggered by the bang along with the search query without the bang or
+/// `None` if the search query does not contain a known bang.
+pub fn parse_bang(query: &str, custom_bangs: &HashMap<String, String>) -> Option<(Bang, String)> {
+    let words: Vec<&str> = query.split_whitespace().collect();
+
+    words.iter().enumerate().find_map(|(index, word)| {
+        let name = word.strip_prefix('!')?.to_lowercase();
+        let remaining_query = words
+            .iter()
+            .enumerate()
+            .filter_map(|(other_index, word)| (other_index != index).then_some(*word))
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        let bang = if name.is_empty() {
+            Bang::Lucky
+        } else if let Some(category) = CATEGORY_BANGS.iter().find(|category| **category == name) {
+            Bang::Category(category)
+        } else {
+            let url_template = custom_bangs.get(&name).map(String::as_str).or_else(|| {
+                BUILTIN_BANGS
+                    .iter()
+                    .find_map(|(bang, url_template)| (*bang == name).then_some(*url_template))
+            })?;
+            Bang::Redirect(url_template.replace(QUERY_PLACEHOLDER, &encode_query(&remaining_query)))
+        };
+
+        Some((bang, remaining_query))
+    })
+}
+
+/// A function which percent encodes the search query so that it can be safely inserted into the
+/// query string of an url.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query to be encoded as an argument.
+pub fn encode_query(query: &str) -> String {
+    query
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            b' ' => "+".to_owned(),
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bang() {
+        let custom_bangs = HashMap::from([(
+            "w".to_owned(),
+            "https://wiki.example.com/?q={query}".to_owned(),
+        )]);
+
+        assert_eq!(
+            parse_bang("serde !gh", &HashMap::new()),
+            Some((
+                Bang::Redirect("https://github.com/search?q=serde".to_owned()),
+                "serde".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse_bang("!w rust & c++", &custom_bangs),
+            Some((
+                Bang::Redirect("https://wiki.example.com/?q=rust+%26+c%2B%2B".to_owned()),
+                "rust & c++".to_owned()
+            ))
+        );
+        assert_eq!(
+            parse_bang("!Images cute cats", &HashMap::new()),
+            Some((Bang::Category("images"), "cute cats".to_owned()))
+        );
+        assert_eq!(
+            parse_bang("! rust book", &HashMap::new()),
+            Some((Bang::Lucky, "rust book".to_owned()))
+        );
+        assert_eq!(parse_bang("hello !unknown world", &HashMap::new()), None);
+    }
+}
@@ -0,0 +1,7 @@
+//! This module provides the modules which handle the functionality to process the search query
+//! provided by the user before it is sent to the upstream search engines.
+
+pub mod bangs;
+pub mod normalize;
+pub mod operators;
+pub mod privacy;
@@ -0,0 +1,36 @@
+//! This module provides the functionality to normalize the search query provided by the user, so
+//! that the same search query written differently (with extra whitespace, invisible control
+//! characters or a different unicode composition) is sent to the upstream search engines and
+//! cached only once.
+
+use unicode_normalization::UnicodeNormalization;
+
+/// A function which normalizes the provided search query. It applies the unicode NFC
+/// normalization, strips the control characters (other than whitespace), collapses the runs of
+/// whitespace into a single space and trims the search query.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query provided by the user as an argument.
+pub fn normalize_query(query: &str) -> String {
+    query
+        .nfc()
+        .filter(|character| character.is_whitespace() || !character.is_control())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!(normalize_query("  rust \t\n book  "), "rust book");
+        assert_eq!(normalize_query("ru\u{0}st\u{7f} book"), "rust book");
+        // `e` followed by the combining acute accent is composed into `é`.
+        assert_eq!(normalize_query("caf\u{65}\u{301}"), "caf\u{e9}");
+    }
+}
@@ -0,0 +1,253 @@
+//! This module provides the functionality to parse the advanced query operators (`site:`,
+//! `-exclusion`, `"exact phrases"` and `filetype:`) from the search query, so that they can be
+//! passed to the upstream search engines which understand them and enforced by filtering the
+//! results of the upstream search engines which do not.
+
+use crate::models::aggregation_models::SearchResult;
+use reqwest::Url;
+
+/// A named struct which stores the search query split into the plain search terms and the
+/// advanced query operators.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ParsedQuery {
+    /// It stores the plain search terms.
+    pub terms: Vec<String>,
+    /// It stores the phrases which should be matched exactly (`"exact phrase"`).
+    pub phrases: Vec<String>,
+    /// It stores the words which should not be present in the results (`-word`).
+    pub excluded: Vec<String>,
+    /// It stores the domains to which the results should be restricted (`site:example.com`).
+    pub sites: Vec<String>,
+    /// It stores the file extensions to which the results should be restricted (`filetype:pdf`).
+    pub filetypes: Vec<String>,
+}
+
+impl ParsedQuery {
+    /// A function which parses the advanced query operators from the provided search query.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query provided by the user as an argument.
+    pub fn parse(query: &str) -> Self {
+        let mut parsed_query = ParsedQuery::default();
+        let mut rest = query.trim_start();
+
+        while !rest.is_empty() {
+            if let Some(quoted) = rest.strip_prefix('"') {
+                let (phrase, remaining) = quoted.split_once('"').unwrap_or((quoted, ""));
+                let phrase = phrase.split_whitespace().collect::<Vec<&str>>().join(" ");
+                if !phrase.is_empty() {
+                    parsed_query.phrases.push(phrase);
+                }
+                rest = remaining.trim_start();
+                continue;
+            }
+
+            let (word, remaining) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            rest = remaining.trim_start();
+
+            let lowercase_word = word.to_lowercase();
+            if let Some(site) = lowercase_word
+                .strip_prefix("site:")
+                .filter(|site| !site.is_empty())
+            {
+                parsed_query
+                    .sites
+                    .push(site.trim_start_matches("www.").to_owned());
+            } else if let Some(filetype) = lowercase_word
+                .strip_prefix("filetype:")
+                .filter(|filetype| !filetype.is_empty())
+            {
+                parsed_query
+                    .filetypes
+                    .push(filetype.trim_start_matches('.').to_owned());
+            } else if let Some(excluded) = word.strip_prefix('-').filter(|word| !word.is_empty()) {
+                parsed_query.excluded.push(excluded.to_owned());
+            } else {
+                parsed_query.terms.push(word.to_owned());
+            }
+        }
+
+        parsed_query
+    }
+
+    /// A function which returns whether the search query contains any advanced query operators.
+    pub fn has_operators(&self) -> bool {
+        !(self.phrases.is_empty()
+            && self.excluded.is_empty()
+            && self.sites.is_empty()
+            && self.filetypes.is_empty())
+    }
+
+    /// A function which builds the search query for the upstream search engines which understand
+    /// the advanced query operators in the common `site:`/`filetype:` syntax. Multiple domains or
+    /// file extensions are combined with `OR`, as a result should match any of them.
+    pub fn to_engine_query(&self) -> String {
+        self.terms
+            .iter()
+            .cloned()
+            .chain(self.phrases.iter().map(|phrase| format!("\"{phrase}\"")))
+            .chain(self.excluded.iter().map(|word| format!("-{word}")))
+            .chain(any_of("site", &self.sites))
+            .chain(any_of("filetype", &self.filetypes))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// A function which builds the search query without the advanced query operators for the
+    /// upstream search engines which do not understand them. The exact phrases are kept as plain
+    /// search terms.
+    pub fn to_plain_query(&self) -> String {
+        self.terms
+            .iter()
+            .chain(self.phrases.iter())
+            .map(String::as_str)
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// A function which checks whether the provided search result satisfies all the advanced
+    /// query operators.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - It takes the search result to be checked as an argument.
+    pub fn matches(&self, result: &SearchResult) -> bool {
+        let text = format!("{} {}", result.title, result.description).to_lowercase();
+        let url = Url::parse(&result.url).ok();
+
+        let host_matches = || {
+            let Some(host) = url.as_ref().and_then(Url::host_str) else {
+                return false;
+            };
+            let host = host.to_lowercase();
+            self.sites
+                .iter()
+                .any(|site| host == *site || host.ends_with(&format!(".{site}")))
+        };
+        let filetype_matches = || {
+            let Some(path) = url.as_ref().map(|url| url.path().to_lowercase()) else {
+                return false;
+            };
+            self.filetypes
+                .iter()
+                .any(|filetype| path.ends_with(&format!(".{filetype}")))
+        };
+
+        (self.sites.is_empty() || host_matches())
+            && (self.filetypes.is_empty() || filetype_matches())
+            && self
+                .phrases
+                .iter()
+                .all(|phrase| text.contains(&phrase.to_lowercase()))
+            && !self.excluded.iter().any(|excluded| {
+                let excluded_words = words(excluded);
+                !excluded_words.is_empty()
+                    && words(&text)
+                        .windows(excluded_words.len())
+                        .any(|window| window == excluded_words)
+            })
+    }
+}
+
+/// A helper function which builds the provided operator for each of the provided values and
+/// combines them with `OR` when there are multiple values.
+///
+/// # Arguments
+///
+/// * `operator` - It takes the name of the operator (for example `site`) as an argument.
+/// * `values` - It takes the values of the operator as an argument.
+fn any_of(operator: &str, values: &[String]) -> Option<String> {
+    let operators: Vec<String> = values
+        .iter()
+        .map(|value| format!("{operator}:{value}"))
+        .collect();
+    match operators.len() {
+        0 => None,
+        1 => operators.into_iter().next(),
+        _ => Some(format!("({})", operators.join(" OR "))),
+    }
+}
+
+/// A helper function which splits the provided text into its lowercase words, ignoring the
+/// punctuation between them.
+///
+/// # Arguments
+///
+/// * `text` - It takes the text to be split as an argument.
+fn words(text: &str) -> Vec<String> {
+    text.split(|character: char| !character.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_operators() {
+        let parsed_query =
+            ParsedQuery::parse(r#"rust "error handling" -python site:www.docs.rs filetype:PDF"#);
+
+        assert_eq!(
+            parsed_query,
+            ParsedQuery {
+                terms: vec!["rust".to_owned()],
+                phrases: vec!["error handling".to_owned()],
+                excluded: vec!["python".to_owned()],
+                sites: vec!["docs.rs".to_owned()],
+                filetypes: vec!["pdf".to_owned()],
+            }
+        );
+        assert_eq!(
+            parsed_query.to_engine_query(),
+            r#"rust "error handling" -python site:docs.rs filetype:pdf"#
+        );
+        assert_eq!(parsed_query.to_plain_query(), "rust error handling");
+    }
+
+    #[test]
+    fn test_query_operators_match_results() {
+        let parsed_query = ParsedQuery::parse(r#""error handling" -python site:docs.rs"#);
+        let result = |url: &str, description: &str| {
+            SearchResult::new("Title", url, description, &["duckduckgo"])
+        };
+
+        assert!(parsed_query.matches(&result(
+            "https://docs.rs/anyhow",
+            "Flexible error handling for rust"
+        )));
+        assert!(!parsed_query.matches(&result(
+            "https://example.com/anyhow",
+            "Flexible error handling for rust"
+        )));
+        assert!(!parsed_query.matches(&result(
+            "https://docs.rs/anyhow",
+            "Error handling in python"
+        )));
+        assert!(!parsed_query.matches(&result("https://docs.rs/anyhow", "Flexible errors")));
+    }
+
+    #[test]
+    fn test_excluded_terms_match_whole_words() {
+        let parsed_query = ParsedQuery::parse("tutorial -go -e-mail");
+        let result =
+            |url: &str, title: &str| SearchResult::new(title, url, "A tutorial", &["duckduckgo"]);
+
+        assert!(parsed_query.matches(&result("https://www.google.com/", "Google tutorial")));
+        assert!(parsed_query.matches(&result("https://go.dev/", "Getting started")));
+        assert!(!parsed_query.matches(&result("https://example.com/", "Learn Go today")));
+        assert!(!parsed_query.matches(&result("https://example.com/", "Your E-Mail inbox")));
+        assert!(parsed_query.matches(&result("https://example.com/", "Email basics")));
+    }
+
+    #[test]
+    fn test_engine_query_combines_multiple_sites() {
+        assert_eq!(
+            ParsedQuery::parse("rust site:docs.rs site:crates.io filetype:pdf").to_engine_query(),
+            "rust (site:docs.rs OR site:crates.io) filetype:pdf"
+        );
+    }
+}
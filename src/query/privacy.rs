@@ -0,0 +1,168 @@
+//! This module provides the single code path through which the search queries (and the urls and
+//! errors which can contain them) are written to the logs, so that the search queries never
+//! appear in the logs in plaintext when the `privacy.no_query_logging` option is enabled.
+
+use std::borrow::Cow;
+
+use actix_web::{dev::ServiceRequest, http::header, web};
+use error_stack::Report;
+
+use crate::{
+    config::reloader::SharedConfig,
+    models::{engine_models::EngineError, parser_models::PrivacyConfig},
+};
+
+/// The number of hex characters of the hash kept in place of a redacted search query, which is
+/// enough to tell the queries apart in the logs.
+const HASH_LENGTH: usize = 16;
+
+/// A function which returns the form of the search query which can be written to the logs, the
+/// search query is replaced with (the start of) its hash when the queries should not be logged.
+///
+/// # Arguments
+///
+/// * `privacy` - It takes the privacy config options as an argument.
+/// * `query` - It takes the search query as an argument.
+pub fn loggable_query<'a>(privacy: &PrivacyConfig, query: &'a str) -> Cow<'a, str> {
+    match privacy.no_query_logging {
+        true => Cow::Owned(format!(
+            "hash:{}",
+            &blake3::hash(query.as_bytes()).to_hex()[..HASH_LENGTH]
+        )),
+        false => Cow::Borrowed(query),
+    }
+}
+
+/// A function which returns the form of the url which can be written to the logs, the query
+/// string (which contains the search query of the search pages) is replaced with its hash when
+/// the queries should not be logged.
+///
+/// # Arguments
+///
+/// * `privacy` - It takes the privacy config options as an argument.
+/// * `url` - It takes the url (or the path and the query string of a url) as an argument.
+pub fn loggable_url<'a>(privacy: &PrivacyConfig, url: &'a str) -> Cow<'a, str> {
+    match url.split_once('?') {
+        Some((path, query_string)) if privacy.no_query_logging => {
+            Cow::Owned(format!("{path}?{}", loggable_query(privacy, query_string)))
+        }
+        _ => Cow::Borrowed(url),
+    }
+}
+
+/// A function which returns the form of the error returned by an upstream search engine which can
+/// be written to the logs. Only the kind of the error is kept when the queries should not be
+/// logged, as the attached errors contain the urls of the requests sent to the engine.
+///
+/// # Arguments
+///
+/// * `privacy` - It takes the privacy config options as an argument.
+/// * `error` - It takes the error returned by the upstream search engine as an argument.
+pub fn loggable_engine_error(privacy: &PrivacyConfig, error: &Report<EngineError>) -> String {
+    match privacy.no_query_logging {
+        true => error.current_context().to_string(),
+        false => format!("{error:?}"),
+    }
+}
+
+/// A helper function which returns the privacy config options of the running server.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be logged as an argument.
+fn request_privacy(req: &ServiceRequest) -> PrivacyConfig {
+    req.app_data::<web::Data<SharedConfig>>()
+        .map(|config| config.load().privacy.clone())
+        .unwrap_or_default()
+}
+
+/// A function which returns the request line of the request to be logged by the `trace_requests`
+/// middleware.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be logged as an argument.
+pub fn loggable_request_line(req: &ServiceRequest) -> String {
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map_or("/", |path_and_query| path_and_query.as_str());
+    format!(
+        "{} {} {:?}",
+        req.method(),
+        loggable_url(&request_privacy(req), path_and_query),
+        req.version()
+    )
+}
+
+/// A function which returns the `Referer` header of the request to be logged by the
+/// `trace_requests` middleware, as the referring search page contains the search query.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be logged as an argument.
+pub fn loggable_referer(req: &ServiceRequest) -> String {
+    match req
+        .headers()
+        .get(header::REFERER)
+        .and_then(|referer| referer.to_str().ok())
+    {
+        Some(referer) => loggable_url(&request_privacy(req), referer).into_owned(),
+        None => "-".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parser::Config;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_loggable_query_and_url() {
+        let privacy = PrivacyConfig {
+            no_query_logging: true,
+        };
+
+        assert_eq!(loggable_query(&PrivacyConfig::default(), "rust"), "rust");
+        let hashed_query = loggable_query(&privacy, "rust");
+        assert!(hashed_query.starts_with("hash:"));
+        assert!(!hashed_query.contains("rust"));
+        assert_eq!(hashed_query, loggable_query(&privacy, "rust"));
+
+        assert_eq!(loggable_url(&privacy, "/about"), "/about");
+        let url = loggable_url(&privacy, "/search?q=rust&page=2");
+        assert!(url.starts_with("/search?hash:"));
+        assert!(!url.contains("rust"));
+    }
+
+    #[test]
+    fn test_loggable_engine_error() {
+        let error = Report::new(EngineError::RequestError)
+            .attach_printable("https://www.bing.com/search?q=rust");
+        let privacy = PrivacyConfig {
+            no_query_logging: true,
+        };
+
+        assert!(loggable_engine_error(&PrivacyConfig::default(), &error).contains("q=rust"));
+        assert_eq!(
+            loggable_engine_error(&privacy, &error),
+            EngineError::RequestError.to_string()
+        );
+    }
+
+    #[test]
+    fn test_loggable_request() {
+        let mut config = Config::default();
+        config.privacy.no_query_logging = true;
+        let req = TestRequest::get()
+            .uri("/search?q=rust")
+            .insert_header((header::REFERER, "http://127.0.0.1:8080/search?q=rust"))
+            .app_data(web::Data::new(SharedConfig::new(config)))
+            .to_srv_request();
+
+        assert!(loggable_request_line(&req).starts_with("GET /search?hash:"));
+        assert!(!loggable_request_line(&req).contains("rust"));
+        assert!(!loggable_referer(&req).contains("rust"));
+    }
+}
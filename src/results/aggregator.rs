@@ -1,30 +1,275 @@
 //! This module provides the functionality to scrape and gathers all the results from the upstream
 //! search engines and then removes duplicate results.
 
+use super::amp::{resolve_amp_urls, rewrite_amp_url};
+use super::engine_selection::select_engines;
+use super::engine_stats::ENGINE_STATS;
+use super::filters::{filter_results, FilterContext};
+use super::rerank::{registered_rerankers, DomainWeights, Reranker};
+use super::resolver::DohResolver;
+use super::tor::{isolated_socks_url, report_response};
+use super::urls::canonical_url;
 use super::user_agent::random_user_agent;
 use crate::config::parser::Config;
-use crate::handler::{file_path, FileType};
+use crate::engines::{
+    block_backoff::{self, blocked_for},
+    circuit_breaker::{self, allow_request},
+    rate_limit::reserve_request,
+    reverse_image::{ReverseImage, ReverseImageEngine},
+};
 use crate::models::{
-    aggregation_models::{EngineErrorInfo, SearchResult, SearchResults},
-    engine_models::{EngineError, EngineHandler},
+    aggregation_models::{EngineErrorInfo, EngineResults, SearchResult, SearchResults},
+    engine_models::{EngineError, EngineHandler, SearchEngine, SearchOptions},
+    parser_models::EngineConfig,
 };
+use crate::query::{operators::ParsedQuery, privacy::loggable_engine_error};
+use crate::server::metrics::METRICS;
 use error_stack::Report;
+use rand::Rng;
 use regex::Regex;
-use reqwest::{Client, ClientBuilder};
-use std::time::{SystemTime, UNIX_EPOCH};
+use reqwest::{Client, ClientBuilder, Proxy};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     io::{BufReader, Read},
     time::Duration,
 };
-use std::{fs::File, io::BufRead};
+use std::{fs::File, future::Future, io::BufRead};
 use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+/// A constant for holding the prebuilt Clients for the engines which use a proxy or their own
+/// connect timeout, keyed by the proxy url and the connect timeout.
+static CACHED_CLIENTS: OnceLock<Mutex<HashMap<ClientKey, Client>>> = OnceLock::new();
+
+/// The time for which the idle connections to the upstream search engines are kept open in the
+/// connection pool of the clients.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// The interval at which the keep-alive probes are sent on the connections to the upstream search
+/// engines, so that the pooled connections are not dropped by the network in between.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The maximum number of related searches shown below the search results.
+const MAX_RELATED_QUERIES: usize = 8;
+
+/// The key of the prebuilt clients, which is made of the proxy url and the connect timeout.
+type ClientKey = (Option<String>, Option<u8>);
+
+/// A named struct which stores the requests sent to the upstream search engines selected for a
+/// search, whose responses are either awaited all together by the aggregation or streamed as they
+/// arrive.
+pub struct EngineRequests {
+    /// It stores the time at which the requests were sent (after the random delay).
+    pub started_at: Instant,
+    /// It stores the names of the upstream engines which filter their results based on the safe
+    /// search level themselves.
+    pub safe_search_engines: Vec<&'static str>,
+    /// It stores the requests sent to each of the selected upstream engines.
+    pub requests: Vec<EngineRequest>,
+}
+
+/// A named struct which stores the request sent to an upstream search engine.
+pub struct EngineRequest {
+    /// It stores the name of the upstream engine.
+    pub name: &'static str,
+    /// It stores the weight of the upstream engine in the ranking of the results.
+    pub weight: f32,
+    /// It stores the optional deadline by which the upstream engine should have answered.
+    deadline: Option<Instant>,
+    /// It stores whether the request is sent through Tor.
+    routed_through_tor: bool,
+    /// It stores the task sending the request to the upstream engine.
+    task: JoinHandle<Result<EngineResults, Report<EngineError>>>,
+}
+
+/// A named struct which stores the response of an upstream search engine.
+pub struct EngineResponse {
+    /// It stores the name of the upstream engine.
+    pub name: &'static str,
+    /// It stores the weight of the upstream engine in the ranking of the results.
+    pub weight: f32,
+    /// It stores the results returned by the upstream engine or the error it failed with.
+    pub results: Result<EngineResults, Report<EngineError>>,
+}
+
+impl EngineRequest {
+    /// A function which waits for the response of the upstream engine. The engine is given up on
+    /// with a `TimedOut` error if it has not answered by the deadline of the aggregation, so that
+    /// the results of the other engines are not held back by the slowest one. The response is
+    /// recorded in the metrics and reported to the Tor circuit rotation.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    pub async fn response(self, config: &Config) -> EngineResponse {
+        let mut task = self.task;
+        let response = match self.deadline {
+            Some(deadline) => match tokio::time::timeout_at(deadline.into(), &mut task).await {
+                Ok(response) => response,
+                Err(_) => {
+                    task.abort();
+                    Ok(Err(Report::new(EngineError::TimedOut)))
+                }
+            },
+            None => task.await,
+        };
+        let results = response.unwrap_or_else(|_| Err(Report::new(EngineError::UnexpectedError)));
 
-/// A constant for holding the prebuilt Client globally in the app.
-static CLIENT: std::sync::OnceLock<Client> = std::sync::OnceLock::new();
+        if self.routed_through_tor {
+            let blocked = matches!(
+                &results,
+                Err(error) if matches!(error.current_context(), EngineError::Blocked)
+            );
+            report_response(&config.tor, blocked);
+        }
+        METRICS.record_engine_response(
+            self.name,
+            results.as_ref().err().map(|error| error.current_context()),
+        );
 
-/// Aliases for long type annotations
-type FutureVec = Vec<JoinHandle<Result<HashMap<String, SearchResult>, Report<EngineError>>>>;
+        EngineResponse {
+            name: self.name,
+            weight: self.weight,
+            results,
+        }
+    }
+}
+
+/// A named struct which aggregates the responses of the upstream search engines, the results are
+/// merged on their canonical url and the errors are kept so that they can be shown to the user.
+#[derive(Default)]
+pub struct Aggregation {
+    /// It stores the aggregated results keyed by their canonical url.
+    result_map: HashMap<String, SearchResult>,
+    /// It stores the errors returned by the upstream engines.
+    engine_errors_info: Vec<EngineErrorInfo>,
+    /// It stores the spelling corrections of the search query suggested by the upstream engines.
+    corrected_queries: Vec<String>,
+    /// It stores the related searches suggested by the upstream engines.
+    related_queries: Vec<String>,
+    /// It stores the number of results returned by each upstream engine.
+    engine_result_counts: BTreeMap<String, usize>,
+}
+
+impl Aggregation {
+    /// A function which merges the response of an upstream engine into the aggregation, using the
+    /// reciprocal rank fusion for the results returned by multiple engines.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - It takes the response of the upstream engine as an argument.
+    /// * `config` - It takes the parsed config struct as an argument.
+    pub fn add_response(&mut self, response: EngineResponse, config: &Config) {
+        match response.results {
+            Ok(mut results) => {
+                self.corrected_queries
+                    .extend(results.corrected_query.take());
+                self.related_queries.append(&mut results.related_queries);
+                self.engine_result_counts
+                    .insert(response.name.to_owned(), results.results.len());
+                merge_results(
+                    &mut self.result_map,
+                    response.name,
+                    results.results,
+                    response.weight,
+                    config.aggregator.rank_constant,
+                    config.resolve_amp_urls,
+                );
+            }
+            Err(error) => {
+                tracing::error!(
+                    "Engine Error: {}",
+                    loggable_engine_error(&config.privacy, &error)
+                );
+                self.engine_errors_info
+                    .push(EngineErrorInfo::new(error.current_context(), response.name));
+            }
+        }
+    }
+
+    /// A function which resolves the AMP urls of the aggregated results (when it is enabled) and
+    /// then runs the result filters over them.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `context` - It takes the search whose results are filtered as an argument.
+    /// * `client` - It takes the client used to resolve the AMP urls as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if one of the result filters failed to filter the results.
+    pub async fn finalize(
+        &mut self,
+        config: &Config,
+        context: &FilterContext<'_>,
+        client: &Client,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        finalize_results(&mut self.result_map, config, context, client).await
+    }
+
+    /// A function which merges the finalized results of another aggregation into the aggregation,
+    /// the fused scores of the results found in both are added up. It is used to rank the results
+    /// of all the upstream engines once their results have been streamed one engine at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - It takes the finalized aggregation of the results of other engines as an
+    ///   argument.
+    pub fn fuse(&mut self, other: &Aggregation) {
+        for (url, result) in &other.result_map {
+            match self.result_map.get_mut(url) {
+                Some(fused) => {
+                    result
+                        .engine
+                        .iter()
+                        .for_each(|engine| fused.add_engines(engine));
+                    fused.score += result.score;
+                }
+                None => {
+                    self.result_map.insert(url.to_owned(), result.clone());
+                }
+            }
+        }
+    }
+
+    /// A function which ranks the aggregated results without building the search results shown to
+    /// the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query as an argument.
+    /// * `config` - It takes the parsed config struct as an argument.
+    pub fn ranked_results(self, query: &str, config: &Config) -> Vec<SearchResult> {
+        rank_results(self.result_map, query, config)
+    }
+
+    /// A function which ranks the aggregated results and builds the search results shown to the
+    /// user along with the errors of the upstream engines and the suggested queries.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query as an argument.
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `started_at` - It takes the time at which the requests to the upstream engines were sent
+    ///   as an argument.
+    pub fn into_search_results(
+        self,
+        query: &str,
+        config: &Config,
+        started_at: Instant,
+    ) -> SearchResults {
+        let results: Vec<SearchResult> = rank_results(self.result_map, query, config);
+
+        let mut search_results = SearchResults::new(results, &self.engine_errors_info);
+        search_results.set_corrected_query(most_suggested_query(query, self.corrected_queries));
+        search_results.set_related_queries(dedup_related_queries(query, self.related_queries));
+        search_results.set_stats(started_at.elapsed(), self.engine_result_counts);
+        search_results
+    }
+}
 
 /// The function aggregates the scraped results from the user-selected upstream search engines.
 /// These engines can be chosen either from the user interface (UI) or from the configuration file.
@@ -53,134 +298,954 @@ type FutureVec = Vec<JoinHandle<Result<HashMap<String, SearchResult>, Report<Eng
 ///
 /// * `query` - Accepts a string to query with the above upstream search engines.
 /// * `page` - Accepts an u32 page number.
-/// * `random_delay` - Accepts a boolean value to add a random delay before making the request.
-/// * `debug` - Accepts a boolean value to enable or disable debug mode option.
+/// * `config` - Accepts the parsed config struct, which controls the random delay before the
+///   requests, the request timeout, the proxies and the ranking of the results.
 /// * `upstream_search_engines` - Accepts a vector of search engine names which was selected by the
-/// * `request_timeout` - Accepts a time (secs) as a value which controls the server request timeout.
-/// user through the UI or the config file.
+///   user through the UI or the config file.
+/// * `client` - Accepts the shared client (built once on the start of the server) through which
+///   the requests are sent to the upstream search engines which do not use a proxy.
+/// * `options` - Accepts the search preferences of the user like the safe search level and the
+///   language of the results.
 ///
 /// # Error
 ///
 /// Returns an error a reqwest and scraping selector errors if any error occurs in the results
 /// function in either `searx` or `duckduckgo` or both otherwise returns a `SearchResults struct`
 /// containing appropriate values.
+#[tracing::instrument(
+    name = "aggregate",
+    skip_all,
+    fields(page = page, engines = upstream_search_engines.len())
+)]
 pub async fn aggregate(
     query: &str,
     page: u32,
     config: &Config,
     upstream_search_engines: &[EngineHandler],
-    safe_search: u8,
+    client: &Client,
+    options: &SearchOptions,
 ) -> Result<SearchResults, Box<dyn std::error::Error>> {
-    let client = CLIENT.get_or_init(|| {
-        ClientBuilder::new()
-            .timeout(Duration::from_secs(config.request_timeout as u64)) // Add timeout to request to avoid DDOSing the server
-            .https_only(true)
-            .gzip(true)
-            .brotli(true)
-            .http2_adaptive_window(config.adaptive_window)
-            .build()
-            .unwrap()
-    });
+    let engine_requests = send_engine_requests(
+        query,
+        page,
+        config,
+        upstream_search_engines,
+        client,
+        options,
+    )
+    .await?;
 
-    let user_agent: &str = random_user_agent();
+    // get upstream responses
+    let responses = futures::future::join_all(
+        engine_requests
+            .requests
+            .into_iter()
+            .map(|request| request.response(config)),
+    )
+    .await;
 
-    // Add a random delay before making the request.
-    if config.aggregator.random_delay || !config.debug {
+    // aggregate search results, removing duplicates and handling errors the upstream engines returned
+    let mut aggregation = Aggregation::default();
+    for response in responses {
+        aggregation.add_response(response, config);
+    }
+    aggregation
+        .finalize(
+            config,
+            &FilterContext {
+                query,
+                options,
+                safe_search_engines: &engine_requests.safe_search_engines,
+            },
+            client,
+        )
+        .await?;
+
+    METRICS.record_aggregation(engine_requests.started_at.elapsed());
+    Ok(aggregation.into_search_results(query, config, engine_requests.started_at))
+}
+
+/// A function which sends the search query to the upstream search engines without waiting for
+/// their responses. The random delay, the selection of the best performing engines and the set up
+/// of the clients (including the Tor circuit) are done once for the search, then a task is
+/// spawned for the request to each selected engine.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query as an argument.
+/// * `page` - It takes the page number as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `upstream_search_engines` - It takes the upstream search engines selected by the user as an
+///   argument.
+/// * `client` - It takes the shared client through which the requests are sent to the upstream
+///   search engines which do not use a proxy as an argument.
+/// * `options` - It takes the search preferences of the user as an argument.
+///
+/// # Error
+///
+/// Returns an error if the proxy urls are invalid or if the clients could not be built.
+#[tracing::instrument(
+    name = "send_engine_requests",
+    skip_all,
+    fields(page = page, engines = upstream_search_engines.len())
+)]
+pub async fn send_engine_requests(
+    query: &str,
+    page: u32,
+    config: &Config,
+    upstream_search_engines: &[EngineHandler],
+    client: &Client,
+    options: &SearchOptions,
+) -> Result<EngineRequests, Box<dyn std::error::Error>> {
+    let tor_client: Option<Client> = tor_client(config)?;
+
+    // Add a random delay before making the request, there is nothing to delay when no engine is
+    // selected.
+    if !upstream_search_engines.is_empty() && (config.aggregator.random_delay || !config.debug) {
         let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.subsec_nanos() as f32;
         let delay = ((nanos / 1_0000_0000 as f32).floor() as u64) + 1;
         tokio::time::sleep(Duration::from_secs(delay)).await;
     }
 
-    let mut names: Vec<&str> = Vec::with_capacity(0);
+    let started_at = Instant::now();
+    let deadline = config
+        .aggregator
+        .deadline
+        .map(|deadline| started_at + Duration::from_millis(deadline));
+    let parsed_query = ParsedQuery::parse(query);
 
-    // create tasks for upstream result fetching
-    let mut tasks: FutureVec = FutureVec::new();
+    let mut safe_search_engines: Vec<&'static str> = Vec::new();
+    let mut requests: Vec<EngineRequest> = Vec::new();
 
-    for engine_handler in upstream_search_engines {
+    // The search is only sent to the best performing engines when more engines are selected than
+    // needed.
+    let engine_handlers = select_engines(
+        upstream_search_engines,
+        config.aggregator.max_engines,
+        config.aggregator.exploration_rate,
+    );
+    for engine_handler in engine_handlers {
         let (name, search_engine) = engine_handler.to_owned().into_name_engine();
-        names.push(name);
-        let query: String = query.to_owned();
-        tasks.push(tokio::spawn(async move {
-            search_engine
-                .results(&query, page, user_agent, client, safe_search)
-                .await
-        }));
+
+        // The engines which do not understand the query operators are sent the query without
+        // them and their results are filtered against the operators instead.
+        let filter_query: Option<ParsedQuery> = (parsed_query.has_operators()
+            && !search_engine.supports_query_operators())
+        .then(|| parsed_query.clone());
+        // The engines which support safe search are trusted to filter their results, the results
+        // of the other engines are filtered against the list of nsfw domains instead.
+        if search_engine.supports_safe_search() {
+            safe_search_engines.push(name);
+        }
+
+        let query: String = match (&filter_query, parsed_query.has_operators()) {
+            (Some(_), _) => parsed_query.to_plain_query(),
+            (None, true) => search_engine.engine_query(&parsed_query),
+            (None, false) => query.to_owned(),
+        };
+
+        let engine_config: EngineConfig = upstream_engine_config(config, name);
+        let (client, routed_through_tor) =
+            search_client(&engine_config, tor_client.as_ref(), config, client)?;
+
+        let search = Arc::new(EngineSearch {
+            search_engine,
+            query,
+            page,
+            // A different user agent is picked for each upstream engine, so that the requests
+            // sent for a single search cannot be linked together by their user agent.
+            user_agent: random_user_agent(&config.user_agents),
+            client,
+            options: options.clone(),
+            engine_config: engine_config.clone(),
+            filter_query,
+        });
+        let task = spawn_engine_request(name, &engine_config, config, deadline, move || {
+            let search = search.clone();
+            async move { search.fetch().await }
+        });
+        requests.push(EngineRequest {
+            name,
+            weight: engine_config.weight,
+            deadline,
+            routed_through_tor,
+            task,
+        });
     }
 
-    // get upstream responses
-    let mut responses = Vec::with_capacity(tasks.len());
+    Ok(EngineRequests {
+        started_at,
+        safe_search_engines,
+        requests,
+    })
+}
+
+/// A named struct which stores what is needed to send the search query to an upstream search
+/// engine, so that the request can be sent again when it is retried.
+struct EngineSearch {
+    /// It stores the upstream search engine.
+    search_engine: Arc<dyn SearchEngine>,
+    /// It stores the search query sent to the upstream engine.
+    query: String,
+    /// It stores the page number.
+    page: u32,
+    /// It stores the user agent sent to the upstream engine.
+    user_agent: String,
+    /// It stores the client through which the request is sent.
+    client: Client,
+    /// It stores the search preferences of the user.
+    options: SearchOptions,
+    /// It stores the options of the upstream engine.
+    engine_config: EngineConfig,
+    /// It stores the query operators against which the results are filtered when the upstream
+    /// engine does not understand them.
+    filter_query: Option<ParsedQuery>,
+}
+
+impl EngineSearch {
+    /// A function which fetches the results of the search query from the upstream engine and
+    /// filters them against the query operators which the engine does not understand.
+    ///
+    /// # Error
+    ///
+    /// Returns an `EngineError` if the results could not be fetched from the upstream engine.
+    async fn fetch(&self) -> Result<EngineResults, Report<EngineError>> {
+        self.search_engine
+            .fetch_results(
+                &self.query,
+                self.page,
+                &self.user_agent,
+                &self.client,
+                &self.options,
+                &self.engine_config,
+            )
+            .await
+            .map(|mut results| {
+                if let Some(filter_query) = &self.filter_query {
+                    results
+                        .results
+                        .retain(|_, result| filter_query.matches(result));
+                }
+                results
+            })
+    }
+}
+
+/// A helper function which spawns the task sending a request to an upstream engine. The engines
+/// which blocked the instance and the engines whose circuit breaker has tripped are skipped, the
+/// rate limited engines wait for their turn and the transient errors are retried while they can be
+/// answered by the deadline. The response is recorded in the statistics of the engine.
+///
+/// # Arguments
+///
+/// * `name` - It takes the name of the upstream engine as an argument.
+/// * `engine_config` - It takes the options of the upstream engine as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `deadline` - It takes the optional deadline of the aggregation as an argument.
+/// * `fetch` - It takes the function sending the request to the upstream engine as an argument,
+///   which is called again for each retry.
+fn spawn_engine_request<F, Fut>(
+    name: &'static str,
+    engine_config: &EngineConfig,
+    config: &Config,
+    deadline: Option<Instant>,
+    fetch: F,
+) -> JoinHandle<Result<EngineResults, Report<EngineError>>>
+where
+    F: Fn() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<EngineResults, Report<EngineError>>> + Send,
+{
+    let circuit_breaker_threshold = config.aggregator.circuit_breaker_threshold;
+    let circuit_breaker_cooldown = Duration::from_secs(config.aggregator.circuit_breaker_cooldown);
+    let block_cooldown = Duration::from_secs(config.aggregator.block_cooldown);
+    let max_block_cooldown = Duration::from_secs(config.aggregator.max_block_cooldown);
+    let retries = u32::from(config.aggregator.retries);
+    let retry_backoff = config.aggregator.retry_backoff;
+    let retry_jitter = config.aggregator.retry_jitter;
+    let requests_per_second = engine_config.requests_per_second;
+
+    // The timeout is applied on each request rather than on the client so that the engine
+    // specific timeouts can be used and so that changes made to the timeouts take effect
+    // when the config is reloaded.
+    let request_timeout =
+        Duration::from_secs(engine_config.timeout.unwrap_or(config.request_timeout) as u64);
+
+    // The engines which blocked the instance and the engines whose circuit breaker has
+    // tripped are skipped without sending the request, so that the block is not prolonged and
+    // a dead engine does not add its timeout to every search.
+    let cooling_down: bool = blocked_for(name).is_some();
+    let circuit_closed: bool =
+        !cooling_down && allow_request(name, circuit_breaker_threshold, circuit_breaker_cooldown);
+    // The turn of the request is reserved before the task is spawned, so that the requests
+    // are queued in the order of the searches.
+    let rate_limit_delay: Option<Option<Duration>> = requests_per_second
+        .filter(|_| circuit_closed)
+        .map(|requests_per_second| reserve_request(name, requests_per_second));
+
+    // The requests to each engine are logged within their own span (inside the span of the
+    // search request), so that the slow engines can be found in the logs.
+    let engine_span = tracing::info_span!("engine", engine = name);
+    tokio::spawn(
+        async move {
+            if cooling_down {
+                return Err(Report::new(EngineError::CoolingDown));
+            }
+            if !circuit_closed {
+                return Err(Report::new(EngineError::CircuitOpen));
+            }
+            match rate_limit_delay {
+                Some(None) => return Err(Report::new(EngineError::RateLimited)),
+                Some(Some(delay)) => tokio::time::sleep(delay).await,
+                None => (),
+            }
 
-    for task in tasks {
-        if let Ok(result) = task.await {
-            responses.push(result)
+            let requested_at = Instant::now();
+            let mut attempt: u32 = 0;
+            let response = loop {
+                // The engines are also given up on by the deadline of the aggregation, so
+                // that their statistics record the timeout.
+                let request_timeout = deadline.map_or(request_timeout, |deadline| {
+                    request_timeout.min(deadline.saturating_duration_since(Instant::now()))
+                });
+                let response = tokio::time::timeout(request_timeout, fetch())
+                    .await
+                    .unwrap_or_else(|_| Err(Report::new(EngineError::TimedOut)))
+                    .map_err(timed_out_error);
+
+                match response {
+                    Err(error) if attempt < retries && is_transient_error(&error) => {
+                        // The retries take their turn like the other requests to the engine
+                        // and are only sent if they can be answered by the deadline.
+                        let delay = retry_delay(retry_backoff, retry_jitter, attempt);
+                        let delay = match requests_per_second {
+                            Some(requests_per_second) => reserve_request(name, requests_per_second)
+                                .map(|rate_limit_delay| rate_limit_delay.max(delay)),
+                            None => Some(delay),
+                        };
+                        match delay.filter(|delay| {
+                            deadline.is_none_or(|deadline| Instant::now() + *delay < deadline)
+                        }) {
+                            Some(delay) => {
+                                tracing::debug!(
+                                    attempt = attempt + 1,
+                                    delay_ms = delay.as_millis() as u64,
+                                    "retrying the engine"
+                                );
+                                tokio::time::sleep(delay).await;
+                                attempt += 1;
+                            }
+                            None => break Err(error),
+                        }
+                    }
+                    response => break response,
+                }
+            };
+            let latency = requested_at.elapsed();
+            let error = response.as_ref().err().map(|error| error.current_context());
+            ENGINE_STATS.record(name, latency, error);
+            circuit_breaker::record_response(name, error, circuit_breaker_threshold);
+            block_backoff::record_response(name, error, block_cooldown, max_block_cooldown);
+            tracing::debug!(
+                duration_ms = latency.as_millis() as u64,
+                results = response.as_ref().map_or(0, |results| results.results.len()),
+                "engine responded"
+            );
+            response
         }
+        .instrument(engine_span),
+    )
+}
+
+/// A function that fetches the pages which contain the provided image from the upstream search
+/// engines which support searching by image, and aggregates them like the results of a search
+/// query. The requests go through the same engine layer as the searches: they are sent through
+/// the same proxies (or Tor), they take their turn within the `requests_per_second` of the
+/// engines, they are retried on the transient errors and they are counted in the statistics, the
+/// circuit breakers and the backoff of the engines.
+///
+/// # Arguments
+///
+/// * `image` - It takes the url or the uploaded bytes of the image provided by the user as an
+///   argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `client` - It takes the shared client through which the requests are sent when they are not
+///   sent through a proxy as an argument.
+/// * `options` - It takes the search preferences of the user as an argument.
+///
+/// # Error
+///
+/// Returns an error if the clients through which the requests are sent could not be built.
+pub async fn aggregate_reverse_image(
+    image: &ReverseImage,
+    config: &Config,
+    client: &Client,
+    options: &SearchOptions,
+) -> Result<SearchResults, Box<dyn std::error::Error>> {
+    let tor_client: Option<Client> = tor_client(config)?;
+    let started_at = Instant::now();
+    let deadline = config
+        .aggregator
+        .deadline
+        .map(|deadline| started_at + Duration::from_millis(deadline));
+
+    let mut requests: Vec<EngineRequest> = Vec::new();
+    for engine in config
+        .reverse_image_engines
+        .iter()
+        .filter_map(|engine| ReverseImageEngine::from_name(engine))
+    {
+        let name = engine.name();
+        let engine_config: EngineConfig = upstream_engine_config(config, name);
+        let (client, routed_through_tor) =
+            search_client(&engine_config, tor_client.as_ref(), config, client)?;
+
+        let search = Arc::new(ReverseImageSearch {
+            engine,
+            image: image.clone(),
+            user_agent: random_user_agent(&config.user_agents),
+            client,
+            options: options.clone(),
+        });
+        let task = spawn_engine_request(name, &engine_config, config, deadline, move || {
+            let search = search.clone();
+            async move { search.fetch().await }
+        });
+        requests.push(EngineRequest {
+            name,
+            weight: engine_config.weight,
+            deadline,
+            routed_through_tor,
+            task,
+        });
     }
 
-    // aggregate search results, removing duplicates and handling errors the upstream engines returned
-    let mut result_map: HashMap<String, SearchResult> = HashMap::new();
-    let mut engine_errors_info: Vec<EngineErrorInfo> = Vec::new();
-
-    let mut handle_error = |error: &Report<EngineError>, engine_name: &'static str| {
-        log::error!("Engine Error: {:?}", error);
-        engine_errors_info.push(EngineErrorInfo::new(
-            error.downcast_ref::<EngineError>().unwrap(),
-            engine_name,
-        ));
+    let responses =
+        futures::future::join_all(requests.into_iter().map(|request| request.response(config)))
+            .await;
+
+    let mut aggregation = Aggregation::default();
+    for response in responses {
+        aggregation.add_response(response, config);
+    }
+    aggregation
+        .finalize(
+            config,
+            &FilterContext {
+                query: "",
+                options,
+                safe_search_engines: &[],
+            },
+            client,
+        )
+        .await?;
+
+    METRICS.record_aggregation(started_at.elapsed());
+    Ok(aggregation.into_search_results("", config, started_at))
+}
+
+/// A named struct which stores what is needed to send the image to an upstream search engine
+/// which supports searching by image, so that the request can be sent again when it is retried.
+struct ReverseImageSearch {
+    /// It stores the upstream search engine.
+    engine: ReverseImageEngine,
+    /// It stores the url or the uploaded bytes of the image.
+    image: ReverseImage,
+    /// It stores the user agent sent to the upstream engine.
+    user_agent: String,
+    /// It stores the client through which the request is sent.
+    client: Client,
+    /// It stores the search preferences of the user.
+    options: SearchOptions,
+}
+
+impl ReverseImageSearch {
+    /// A function which fetches the pages which contain the image from the upstream engine.
+    ///
+    /// # Error
+    ///
+    /// Returns an `EngineError` if the pages could not be fetched from the upstream engine.
+    async fn fetch(&self) -> Result<EngineResults, Report<EngineError>> {
+        self.engine
+            .results(&self.image, &self.user_agent, &self.client, &self.options)
+            .await
+    }
+}
+
+/// A helper function which resolves the AMP urls of the aggregated results (when it is enabled)
+/// and then runs the result filters over them, so that the filters rewriting the links (like the
+/// `url_rewrites` and the stripping of the tracking query parameters) also apply to the resolved
+/// urls.
+///
+/// # Arguments
+///
+/// * `result_map` - It takes the aggregated results keyed by their canonical url as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `context` - It takes the search whose results are filtered as an argument.
+/// * `client` - It takes the client used to resolve the AMP urls as an argument.
+///
+/// # Error
+///
+/// Returns an error if one of the result filters failed to filter the results.
+async fn finalize_results(
+    result_map: &mut HashMap<String, SearchResult>,
+    config: &Config,
+    context: &FilterContext<'_>,
+    client: &Client,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if config.resolve_amp_urls {
+        let request_timeout = Duration::from_secs(config.request_timeout as u64);
+        resolve_amp_urls(result_map.values_mut(), client, request_timeout).await;
+    }
+    filter_results(config, context, result_map)
+}
+
+/// A helper function which merges the results returned by an upstream engine into the aggregated
+/// results. The AMP urls are rewritten to the canonical urls and the results are merged on their
+/// canonical url (without the tracking query parameters) so that
+/// the same page returned with slightly different urls by multiple engines is shown only once,
+/// the scores provided by all the engines which returned the result are added up.
+///
+/// # Arguments
+///
+/// * `result_map` - It takes the aggregated results keyed by their canonical url as an argument.
+/// * `engine` - It takes the name of the upstream engine as an argument.
+/// * `results` - It takes the results returned by the upstream engine as an argument.
+/// * `weight` - It takes the weight of the upstream engine as an argument.
+/// * `rank_constant` - It takes the rank constant provided via the config file as an argument.
+/// * `resolve_amp_urls` - It takes a boolean which indicates whether the AMP urls are resolved
+///   later by fetching them as an argument.
+fn merge_results(
+    result_map: &mut HashMap<String, SearchResult>,
+    engine: &str,
+    results: HashMap<String, SearchResult>,
+    weight: f32,
+    rank_constant: u32,
+    resolve_amp_urls: bool,
+) {
+    results.into_values().for_each(|mut value| {
+        value.url = rewrite_amp_url(&value.url, !resolve_amp_urls);
+        let score = weight * reciprocal_rank(value.rank, rank_constant);
+        result_map
+            .entry(canonical_url(&value.url))
+            .and_modify(|result| {
+                result.add_engines(engine);
+                result.score += score;
+            })
+            .or_insert_with(|| -> SearchResult {
+                value.score = score;
+                value
+            });
+    });
+}
+
+/// A helper function which computes the score given to a result by an upstream engine with a
+/// weight of `1.0` using the reciprocal rank fusion, in which the scores given by all the engines
+/// which returned the result are added up. The rank constant dampens the advantage of the top
+/// positions so that the results returned by many engines are ranked above the results returned
+/// at the top by a single engine.
+///
+/// # Arguments
+///
+/// * `rank` - It takes the position of the result in the results of the engine (starting from 1)
+///   as an argument.
+/// * `rank_constant` - It takes the rank constant provided via the config file as an argument.
+fn reciprocal_rank(rank: usize, rank_constant: u32) -> f32 {
+    1.0 / (rank_constant as f32 + rank.max(1) as f32)
+}
+
+/// A helper function which picks the spelling correction of the search query suggested by the most
+/// upstream engines. The suggestions which only differ from the search query by their case are
+/// ignored.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query provided by the user as an argument.
+/// * `corrected_queries` - It takes the spelling corrections suggested by the upstream engines as
+///   an argument.
+fn most_suggested_query(query: &str, corrected_queries: Vec<String>) -> Option<String> {
+    let mut suggestions: HashMap<String, usize> = HashMap::new();
+    for corrected_query in corrected_queries {
+        if !corrected_query.eq_ignore_ascii_case(query.trim()) {
+            *suggestions.entry(corrected_query).or_default() += 1;
+        }
+    }
+
+    suggestions
+        .into_iter()
+        .max_by(|(query_a, count_a), (query_b, count_b)| {
+            count_a.cmp(count_b).then_with(|| query_b.cmp(query_a))
+        })
+        .map(|(corrected_query, _)| corrected_query)
+}
+
+/// A helper function which removes the duplicate related searches (ignoring their case and
+/// whitespace) and the ones matching the search query while keeping the order in which they were
+/// suggested. At most `MAX_RELATED_QUERIES` related searches are kept.
+///
+/// # Arguments
+///
+/// * `query` - It takes the search query provided by the user as an argument.
+/// * `related_queries` - It takes the related searches suggested by the upstream engines as an
+///   argument.
+fn dedup_related_queries(query: &str, related_queries: Vec<String>) -> Vec<String> {
+    let normalize = |query: &str| {
+        query
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .to_lowercase()
     };
 
-    for _ in 0..responses.len() {
-        let response = responses.pop().unwrap();
-        let engine = names.pop().unwrap();
+    let mut seen: HashSet<String> = HashSet::from([normalize(query)]);
+    related_queries
+        .into_iter()
+        .filter(|related_query| seen.insert(normalize(related_query)))
+        .take(MAX_RELATED_QUERIES)
+        .collect()
+}
+
+/// A helper function which returns the client through which the requests of a search are sent
+/// through Tor, when the requests are routed through Tor. The searches are spread over the
+/// isolation slots when the streams are isolated, so that the concurrent searches are sent through
+/// separate Tor circuits. The clients of the slots are cached like the other proxied clients.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Error
+///
+/// Returns an error if the url of the Tor proxy is invalid or if the client could not be built.
+fn tor_client(config: &Config) -> Result<Option<Client>, Box<dyn std::error::Error>> {
+    Ok(match (config.tor.enabled, config.tor.isolate_streams) {
+        (true, true) => Some(proxied_client(
+            &isolated_socks_url(&config.tor.socks_url)?,
+            config,
+        )?),
+        (true, false) => Some(proxied_client(&config.tor.socks_url, config)?),
+        (false, _) => None,
+    })
+}
 
-        if result_map.is_empty() {
-            match response {
-                Ok(results) => {
-                    result_map = results.clone();
-                }
-                Err(error) => {
-                    handle_error(&error, engine);
-                }
-            }
-            continue;
+/// A helper function which returns the options of the upstream search engine with the provided
+/// name, or the default options when the engine is not configured.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `name` - It takes the name of the upstream engine as an argument.
+fn upstream_engine_config(config: &Config, name: &str) -> EngineConfig {
+    config
+        .upstream_search_engines
+        .iter()
+        .find(|(engine, _)| engine.eq_ignore_ascii_case(name))
+        .map(|(_, engine_config)| engine_config.clone())
+        .unwrap_or_default()
+}
+
+/// A helper function which picks the client through which the requests of a search are sent to an
+/// upstream engine, which uses the proxy of the engine, the global proxy or the Tor client of the
+/// search.
+///
+/// # Arguments
+///
+/// * `engine_config` - It takes the options of the upstream engine as an argument.
+/// * `tor_client` - It takes the Tor client of the search when the requests are routed through Tor
+///   as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `client` - It takes the shared client used when no proxy is configured as an argument.
+///
+/// # Returns
+///
+/// It returns the client along with whether the requests are sent through Tor.
+///
+/// # Error
+///
+/// Returns an error if the proxy url is invalid or if the client could not be built.
+fn search_client(
+    engine_config: &EngineConfig,
+    tor_client: Option<&Client>,
+    config: &Config,
+    client: &Client,
+) -> Result<(Client, bool), Box<dyn std::error::Error>> {
+    let proxy_url = upstream_proxy(
+        engine_config.proxy.as_deref(),
+        tor_client.is_some(),
+        config.proxy.as_deref(),
+    );
+    let connect_timeout = engine_config.connect_timeout.or(config.connect_timeout);
+    let client: Client = match (proxy_url, tor_client) {
+        (Some(proxy_url), _) => cached_client(Some(proxy_url), connect_timeout, config)?,
+        (None, Some(tor_client)) => tor_client.clone(),
+        (None, None) if engine_config.connect_timeout.is_some() => {
+            cached_client(None, connect_timeout, config)?
         }
+        (None, None) => client.clone(),
+    };
+    let routed_through_tor = engine_config.proxy.is_none() && tor_client.is_some();
+    Ok((client, routed_through_tor))
+}
 
-        match response {
-            Ok(result) => {
-                result.into_iter().for_each(|(key, value)| {
-                    result_map
-                        .entry(key)
-                        .and_modify(|result| {
-                            result.add_engines(engine);
-                        })
-                        .or_insert_with(|| -> SearchResult { value });
-                });
-            }
-            Err(error) => {
-                handle_error(&error, engine);
-            }
+/// A helper function which picks the proxy through which the requests to an upstream engine are
+/// sent. The engine specific proxy takes precedence over Tor and the global proxy.
+///
+/// # Arguments
+///
+/// * `engine_proxy` - It takes the proxy configured for the upstream engine as an argument.
+/// * `tor_enabled` - It takes a boolean which indicates whether the requests are routed through
+///   Tor as an argument.
+/// * `global_proxy` - It takes the global proxy provided via the config file as an argument.
+///
+/// # Returns
+///
+/// It returns the url of the proxy or `None` if the requests are sent through Tor or directly.
+fn upstream_proxy<'a>(
+    engine_proxy: Option<&'a str>,
+    tor_enabled: bool,
+    global_proxy: Option<&'a str>,
+) -> Option<&'a str> {
+    engine_proxy.or(global_proxy.filter(|_| !tor_enabled))
+}
+
+/// A function which returns the client through which the requests to the provided upstream
+/// search engine are sent outside of the searches (like the canary queries of the health
+/// monitor), which uses the proxy of the engine, the global proxy or Tor like the searches do.
+///
+/// # Arguments
+///
+/// * `engine_config` - It takes the options of the upstream search engine as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `client` - It takes the shared client used when no proxy is configured as an argument.
+///
+/// # Error
+///
+/// Returns an error if the proxy url is invalid or if the client could not be built.
+pub fn engine_client(
+    engine_config: &EngineConfig,
+    config: &Config,
+    client: &Client,
+) -> Result<Client, Box<dyn std::error::Error>> {
+    let proxy_url = upstream_proxy(
+        engine_config.proxy.as_deref(),
+        config.tor.enabled,
+        config.proxy.as_deref(),
+    )
+    .or(config.tor.enabled.then_some(config.tor.socks_url.as_str()));
+    let connect_timeout = engine_config.connect_timeout.or(config.connect_timeout);
+
+    match proxy_url {
+        Some(proxy_url) => cached_client(Some(proxy_url), connect_timeout, config),
+        None if engine_config.connect_timeout.is_some() => {
+            cached_client(None, connect_timeout, config)
         }
+        None => Ok(client.clone()),
     }
+}
 
-    if safe_search >= 3 {
-        let mut blacklist_map: HashMap<String, SearchResult> = HashMap::new();
-        filter_with_lists(
-            &mut result_map,
-            &mut blacklist_map,
-            file_path(FileType::BlockList)?,
-        )?;
+/// A helper function which returns the prebuilt client which sends its requests through the
+/// provided proxy. The client is built on the first use and reused afterwards.
+///
+/// # Arguments
+///
+/// * `proxy_url` - It takes the url of the proxy as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Error
+///
+/// Returns an error if the proxy url is invalid or if the client could not be built.
+fn proxied_client(proxy_url: &str, config: &Config) -> Result<Client, Box<dyn std::error::Error>> {
+    cached_client(Some(proxy_url), config.connect_timeout, config)
+}
 
-        filter_with_lists(
-            &mut blacklist_map,
-            &mut result_map,
-            file_path(FileType::AllowList)?,
-        )?;
+/// A helper function which returns the prebuilt client which sends its requests through the
+/// provided proxy (if any) and gives up on the connections which are not established within the
+/// provided connect timeout. The client is built on the first use and reused afterwards.
+///
+/// # Arguments
+///
+/// * `proxy_url` - It takes the optional url of the proxy as an argument.
+/// * `connect_timeout` - It takes the optional connect timeout (secs) as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Error
+///
+/// Returns an error if the proxy url is invalid or if the client could not be built.
+fn cached_client(
+    proxy_url: Option<&str>,
+    connect_timeout: Option<u8>,
+    config: &Config,
+) -> Result<Client, Box<dyn std::error::Error>> {
+    let key = (proxy_url.map(str::to_owned), connect_timeout);
+    let mut clients = CACHED_CLIENTS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    if let Some(client) = clients.get(&key) {
+        return Ok(client.clone());
+    }
+
+    let mut builder = client_builder(config);
+    if let Some(proxy_url) = proxy_url {
+        builder = builder.proxy(Proxy::all(proxy_url)?);
+    }
+    if let Some(connect_timeout) = connect_timeout {
+        builder = builder.connect_timeout(Duration::from_secs(connect_timeout as u64));
+    }
+    let client = builder.build()?;
+
+    clients.insert(key, client.clone());
+    Ok(client)
+}
+
+/// A helper function which reports the requests to the upstream search engines which failed as
+/// the connection or the response was not received in time as `TimedOut` errors, so that they can
+/// be told apart from the other request errors.
+///
+/// # Arguments
+///
+/// * `error` - It takes the error returned by the upstream search engine as an argument.
+fn timed_out_error(error: Report<EngineError>) -> Report<EngineError> {
+    let timed_out = !matches!(error.current_context(), EngineError::TimedOut)
+        && error
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(reqwest::Error::is_timeout);
+    match timed_out {
+        true => error.change_context(EngineError::TimedOut),
+        false => error,
+    }
+}
+
+/// A helper function which checks whether the error returned by an upstream search engine is a
+/// transient one worth retrying, which are the `429 Too Many Requests` and the server error
+/// responses along with the failed and the reset connections.
+///
+/// # Arguments
+///
+/// * `error` - It takes the error returned by the upstream search engine as an argument.
+fn is_transient_error(error: &Report<EngineError>) -> bool {
+    if let Some(status) = error.downcast_ref::<reqwest::StatusCode>() {
+        return *status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+    }
+    let Some(error) = error.downcast_ref::<reqwest::Error>() else {
+        return false;
+    };
+    if error.is_connect() {
+        return true;
+    }
 
-        drop(blacklist_map);
+    let mut source = std::error::Error::source(error);
+    while let Some(error) = source {
+        if error
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|error| error.kind() == std::io::ErrorKind::ConnectionReset)
+        {
+            return true;
+        }
+        source = error.source();
     }
+    false
+}
 
-    let results: Vec<SearchResult> = result_map.into_values().collect();
+/// A helper function which computes the time to wait before a request to an upstream search
+/// engine is retried, which doubles with every retry and is spread by a random jitter so that the
+/// retries of the concurrent searches are not sent at once.
+///
+/// # Arguments
+///
+/// * `backoff` - It takes the time (in milliseconds) to wait before the first retry as an argument.
+/// * `jitter` - It takes the largest random time (in milliseconds) added to the wait as an
+///   argument.
+/// * `attempt` - It takes the number of the retries already sent as an argument.
+fn retry_delay(backoff: u64, jitter: u64, attempt: u32) -> Duration {
+    let jitter = match jitter {
+        0 => 0,
+        jitter => rand::thread_rng().gen_range(0..=jitter),
+    };
+    Duration::from_millis(
+        backoff
+            .saturating_mul(1 << attempt.min(16))
+            .saturating_add(jitter),
+    )
+}
 
-    Ok(SearchResults::new(results, &engine_errors_info))
+/// A function which builds the client shared by all the requests to the upstream search engines
+/// which are not sent through a proxy. It is built once on the start of the server and stored in
+/// the app data, so that the connections (and the HTTP/2 sessions) to the engines are pooled and
+/// reused across the searches.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Error
+///
+/// Returns an error if the client could not be built.
+pub fn upstream_client(config: &Config) -> reqwest::Result<Client> {
+    client_builder(config).build()
+}
+
+/// A helper function which returns the builder of the clients through which the requests to the
+/// upstream search engines are sent. The domain names of the engines are resolved through the
+/// configured DNS-over-HTTPS server, unless they are resolved by the proxy (like with the
+/// `socks5h` proxies).
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+fn client_builder(config: &Config) -> ClientBuilder {
+    let builder = ClientBuilder::new()
+        .https_only(true)
+        .gzip(true)
+        .brotli(true)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(KEEP_ALIVE_INTERVAL)
+        .http2_keep_alive_interval(KEEP_ALIVE_INTERVAL)
+        .http2_keep_alive_while_idle(true)
+        .http2_adaptive_window(config.adaptive_window);
+    let builder = match config.connect_timeout {
+        Some(connect_timeout) => {
+            builder.connect_timeout(Duration::from_secs(connect_timeout as u64))
+        }
+        None => builder,
+    };
+
+    match config
+        .dns_over_https
+        .as_deref()
+        .and_then(|url| DohResolver::new(url).ok())
+    {
+        Some(resolver) => builder.dns_resolver(Arc::new(resolver)),
+        None => builder,
+    }
+}
+
+/// A helper function which orders the aggregated results by their fused score, runs the reranker
+/// chain over them and then keeps only the configured number of top ranked results.
+///
+/// # Arguments
+///
+/// * `result_map` - It takes the aggregated results keyed by their url as an argument.
+/// * `query` - It takes the search query as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+fn rank_results(
+    result_map: HashMap<String, SearchResult>,
+    query: &str,
+    config: &Config,
+) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = result_map.into_values().collect();
+    // The results are sorted before they are truncated, as the order of the map is arbitrary.
+    results.sort_by(|result_a, result_b| result_b.score.total_cmp(&result_a.score));
+
+    let domain_weights = DomainWeights::new(&config.domain_weights);
+    for reranker in std::iter::once(&domain_weights as &dyn Reranker)
+        .chain(registered_rerankers().iter().map(Box::as_ref))
+    {
+        reranker.rerank(query, &mut results);
+    }
+
+    results.truncate(config.request_limits.max_results_per_page);
+    results
 }
 
 /// Filters a map of search results using a list of regex patterns.
@@ -206,7 +1271,7 @@ pub fn filter_with_lists(
 
         // Iterate over each search result in the map and check if it matches the regex pattern
         for (url, search_result) in map_to_be_filtered.clone().into_iter() {
-            if re.is_match(&url.to_lowercase())
+            if re.is_match(&search_result.url.to_lowercase())
                 || re.is_match(&search_result.title.to_lowercase())
                 || re.is_match(&search_result.description.to_lowercase())
             {
@@ -242,6 +1307,8 @@ mod tests {
                 description: "This domain is for use in illustrative examples in documents."
                     .to_owned(),
                 engine: smallvec!["Google".to_owned(), "Bing".to_owned()],
+                rank: 0,
+                score: 0.0,
             },
         );
         map_to_be_filtered.insert(
@@ -251,6 +1318,8 @@ mod tests {
                 url: "https://www.rust-lang.org/".to_owned(),
                 description: "A systems programming language that runs blazingly fast, prevents segfaults, and guarantees thread safety.".to_owned(),
                 engine: smallvec!["Google".to_owned(), "DuckDuckGo".to_owned()],
+                rank: 0,
+                score: 0.0,
             },
         );
 
@@ -286,6 +1355,8 @@ mod tests {
                 description: "This domain is for use in illustrative examples in documents."
                     .to_owned(),
                 engine: smallvec!["Google".to_owned(), "Bing".to_owned()],
+                rank: 0,
+                score: 0.0,
             },
         );
         map_to_be_filtered.insert(
@@ -295,6 +1366,8 @@ mod tests {
                 url: "https://www.rust-lang.org/".to_owned(),
                 description: "A systems programming language that runs blazingly fast, prevents segfaults, and guarantees thread safety.".to_owned(),
                 engine: smallvec!["Google".to_owned(), "DuckDuckGo".to_owned()],
+                rank: 0,
+                score: 0.0,
             },
         );
 
@@ -346,6 +1419,8 @@ mod tests {
                 description: "This domain is for use in illustrative examples in documents."
                     .to_owned(),
                 engine: smallvec!["Google".to_owned(), "Bing".to_owned()],
+                rank: 0,
+                score: 0.0,
             },
         );
 
@@ -364,4 +1439,307 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_most_suggested_query() {
+        assert_eq!(
+            most_suggested_query(
+                "rust programing",
+                vec![
+                    "rust programming".to_owned(),
+                    "rust programs".to_owned(),
+                    "rust programming".to_owned(),
+                ]
+            ),
+            Some("rust programming".to_owned())
+        );
+        assert_eq!(most_suggested_query("Rust", vec!["rust".to_owned()]), None);
+    }
+
+    #[test]
+    fn test_dedup_related_queries() {
+        assert_eq!(
+            dedup_related_queries(
+                "rust",
+                vec![
+                    "rust book".to_owned(),
+                    "Rust".to_owned(),
+                    "rust  Book".to_owned(),
+                    "rust async".to_owned(),
+                ]
+            ),
+            vec!["rust book".to_owned(), "rust async".to_owned()]
+        );
+    }
+
+    /// A helper function which builds the results returned by an upstream engine from the
+    /// provided urls in the order in which the engine ranked them.
+    fn engine_results(engine: &str, urls: &[&str]) -> HashMap<String, SearchResult> {
+        urls.iter()
+            .enumerate()
+            .map(|(index, url)| {
+                let mut result = SearchResult::new(url, url, "", &[engine]);
+                result.rank = index + 1;
+                (url.to_string(), result)
+            })
+            .collect()
+    }
+
+    /// A helper function which merges the results of the provided engines with the provided
+    /// weights and returns the urls of the aggregated results in their ranked order.
+    fn ranked_urls(engines: &[(&str, f32, &[&str])]) -> Vec<String> {
+        let config = Config::default();
+        let mut result_map = HashMap::new();
+        for (engine, weight, urls) in engines {
+            merge_results(
+                &mut result_map,
+                engine,
+                engine_results(engine, urls),
+                *weight,
+                config.aggregator.rank_constant,
+                false,
+            );
+        }
+
+        rank_results(result_map, "", &config)
+            .into_iter()
+            .map(|result| result.url)
+            .collect()
+    }
+
+    #[test]
+    fn test_reciprocal_rank() {
+        assert_eq!(reciprocal_rank(1, 60), 1.0 / 61.0);
+        assert_eq!(reciprocal_rank(0, 60), reciprocal_rank(1, 60));
+        assert!(reciprocal_rank(1, 60) > reciprocal_rank(2, 60));
+    }
+
+    #[test]
+    fn test_merged_results_are_ranked_by_their_fused_scores() {
+        // The result returned by both engines is ranked above the top results of each engine.
+        assert_eq!(
+            ranked_urls(&[
+                ("bing", 1.0, &["https://a.com/", "https://c.com/"]),
+                ("brave", 1.0, &["https://b.com/", "https://c.com/"]),
+            ])[0],
+            "https://c.com/"
+        );
+
+        let mut result_map = HashMap::new();
+        merge_results(
+            &mut result_map,
+            "bing",
+            engine_results("bing", &["https://example.com/?utm_source=bing"]),
+            1.0,
+            60,
+            false,
+        );
+        merge_results(
+            &mut result_map,
+            "brave",
+            engine_results("brave", &["https://example.com/"]),
+            1.0,
+            60,
+            false,
+        );
+        assert_eq!(result_map.len(), 1);
+        let result = result_map.values().next().unwrap();
+        assert_eq!(result.engine.len(), 2);
+        assert_eq!(result.score, 2.0 * reciprocal_rank(1, 60));
+    }
+
+    #[test]
+    fn test_merged_results_are_ranked_with_the_engine_weights() {
+        let engines = |bing_weight: f32| {
+            ranked_urls(&[
+                ("bing", bing_weight, &["https://a.com/", "https://b.com/"]),
+                ("brave", 1.0, &["https://b.com/", "https://a.com/"]),
+            ])
+        };
+
+        assert_eq!(engines(2.0), vec!["https://a.com/", "https://b.com/"]);
+        assert_eq!(engines(0.5), vec!["https://b.com/", "https://a.com/"]);
+        // The results of an engine with a weight of zero do not affect the ranking.
+        assert_eq!(
+            ranked_urls(&[
+                ("bing", 0.0, &["https://a.com/"]),
+                ("brave", 1.0, &["https://b.com/", "https://a.com/"]),
+            ]),
+            vec!["https://b.com/", "https://a.com/"]
+        );
+    }
+
+    #[test]
+    fn test_fused_aggregations_are_ranked_like_a_single_aggregation() {
+        let config = Config::default();
+        let response = |name: &'static str, urls: &[&str]| EngineResponse {
+            name,
+            weight: 1.0,
+            results: Ok(EngineResults::from(engine_results(name, urls))),
+        };
+
+        // The results streamed one engine at a time are ranked together once all the engines have
+        // answered.
+        let mut fused = Aggregation::default();
+        for (name, urls) in [
+            ("bing", ["https://a.com/", "https://c.com/"]),
+            ("brave", ["https://b.com/", "https://c.com/"]),
+        ] {
+            let mut aggregation = Aggregation::default();
+            aggregation.add_response(response(name, &urls), &config);
+            fused.fuse(&aggregation);
+        }
+
+        let results = fused.ranked_results("", &config);
+        assert_eq!(results[0].url, "https://c.com/");
+        assert_eq!(results[0].engine.len(), 2);
+        assert_eq!(
+            results.len(),
+            ranked_urls(&[
+                ("bing", 1.0, &["https://a.com/", "https://c.com/"]),
+                ("brave", 1.0, &["https://b.com/", "https://c.com/"]),
+            ])
+            .len()
+        );
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let error = |error: EngineError, status: reqwest::StatusCode| {
+            Report::new(error).attach_printable(status)
+        };
+
+        assert!(is_transient_error(&error(
+            EngineError::Blocked,
+            reqwest::StatusCode::TOO_MANY_REQUESTS
+        )));
+        assert!(is_transient_error(&error(
+            EngineError::RequestError,
+            reqwest::StatusCode::BAD_GATEWAY
+        )));
+        assert!(!is_transient_error(&error(
+            EngineError::Blocked,
+            reqwest::StatusCode::FORBIDDEN
+        )));
+        assert!(!is_transient_error(&Report::new(EngineError::TimedOut)));
+        assert!(!is_transient_error(&Report::new(
+            EngineError::EmptyResultSet
+        )));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(200, 0, 0), Duration::from_millis(200));
+        assert_eq!(retry_delay(200, 0, 2), Duration::from_millis(800));
+        let delay = retry_delay(200, 100, 1);
+        assert!(delay >= Duration::from_millis(400) && delay <= Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_upstream_proxy() {
+        let engine_proxy = Some("socks5h://127.0.0.1:1080");
+        let global_proxy = Some("http://127.0.0.1:3128");
+
+        assert_eq!(
+            upstream_proxy(engine_proxy, true, global_proxy),
+            engine_proxy
+        );
+        assert_eq!(upstream_proxy(None, false, global_proxy), global_proxy);
+        assert_eq!(upstream_proxy(None, true, global_proxy), None);
+        assert_eq!(upstream_proxy(None, false, None), None);
+    }
+
+    #[test]
+    fn test_proxied_client() {
+        let config = Config::default();
+
+        assert!(proxied_client("socks5h://127.0.0.1:1080", &config).is_ok());
+        assert!(proxied_client("socks5h://127.0.0.1:1080", &config).is_ok());
+        assert!(proxied_client("not a proxy url", &config).is_err());
+        assert!(cached_client(None, Some(3), &config).is_ok());
+    }
+
+    #[test]
+    fn test_upstream_client() {
+        let config = Config {
+            dns_over_https: Some("https://dns.example.com/dns-query".to_owned()),
+            ..Default::default()
+        };
+
+        assert!(upstream_client(&Config::default()).is_ok());
+        assert!(upstream_client(&config).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_resolved_amp_urls_are_filtered() {
+        // A local site which answers every request, standing in for the origin of the AMP page.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0; 1024];
+                let _ = std::io::Read::read(&mut stream, &mut request);
+                let _ = stream.write_all(
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                );
+            }
+        });
+
+        let config = Config {
+            resolve_amp_urls: true,
+            url_rewrites: HashMap::from([(
+                "127.0.0.1".to_owned(),
+                "https://frontend.example.com".to_owned(),
+            )]),
+            ..Default::default()
+        };
+        let amp_url = format!("http://{address}/article/amp");
+        let mut result_map = HashMap::from([(
+            amp_url.clone(),
+            SearchResult::new("Title", &amp_url, "Description", &["bing"]),
+        )]);
+        let options = SearchOptions::default();
+        let context = FilterContext {
+            query: "query",
+            options: &options,
+            safe_search_engines: &[],
+        };
+
+        finalize_results(&mut result_map, &config, &context, &Client::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            result_map
+                .values()
+                .map(|result| result.url.as_str())
+                .collect::<Vec<&str>>(),
+            ["https://frontend.example.com/article/"]
+        );
+    }
+
+    #[test]
+    fn test_rank_results_keeps_the_top_scored_results() {
+        let mut config = Config::default();
+        config.request_limits.max_results_per_page = 2;
+
+        let result_map: HashMap<String, SearchResult> = [0.1, 0.7, 0.3, 0.5]
+            .into_iter()
+            .enumerate()
+            .map(|(index, score)| {
+                let url = format!("https://example{index}.com/");
+                let mut result = SearchResult::new("Title", &url, "Description", &["bing"]);
+                result.score = score;
+                (url, result)
+            })
+            .collect();
+
+        let results = rank_results(result_map, "query", &config);
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result.score)
+                .collect::<Vec<f32>>(),
+            vec![0.7, 0.5]
+        );
+    }
 }
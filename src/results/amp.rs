@@ -0,0 +1,206 @@
+//! This module provides the functionality to rewrite the AMP urls of the search results to the
+//! canonical (non-AMP) urls of the pages, so that the users do not land on the AMP caches hosted
+//! by Google.
+
+use crate::models::aggregation_models::SearchResult;
+use reqwest::{Client, Url};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// The query parameters which are only used to request or to track the AMP version of a page.
+const AMP_PARAMS: &[&str] = &["amp", "amp_js_v", "amp_gsa", "amp_r", "usqp", "outputtype"];
+
+/// A function which rewrites the provided url of a page served by an AMP cache (like
+/// `https://www.google.com/amp/s/example.com/page` or
+/// `https://example-com.cdn.ampproject.org/c/s/example.com/page`) to the url of the page on its
+/// origin. When `strip_markers` is set the AMP markers (like the `amp.` subdomain, the `/amp` path
+/// segment or the `amp=1` query parameter) are also removed from the url on the guess that the
+/// resulting url is the canonical url of the page. The url is returned as it is if it is not an
+/// AMP url.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+/// * `strip_markers` - It takes a boolean which indicates whether the AMP markers should be
+///   removed from the url as an argument.
+pub fn rewrite_amp_url(url: &str, strip_markers: bool) -> String {
+    let url = amp_cache_origin(url).unwrap_or_else(|| url.to_owned());
+
+    match strip_markers {
+        true => strip_amp_markers(&url).unwrap_or(url),
+        false => url,
+    }
+}
+
+/// A helper function which returns the url of the page on its origin if the provided url points
+/// to a page served by the Google or the Cloudflare AMP caches.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+fn amp_cache_origin(url: &str) -> Option<String> {
+    let parsed_url = Url::parse(url.trim()).ok()?;
+    let host = parsed_url.host_str()?;
+
+    let prefixes: &[(&str, &str)] = if host.ends_with(".cdn.ampproject.org") {
+        &[
+            ("/c/s/", "https://"),
+            ("/v/s/", "https://"),
+            ("/c/", "http://"),
+            ("/v/", "http://"),
+        ]
+    } else if host.split('.').any(|label| label == "google") {
+        &[("/amp/s/", "https://"), ("/amp/", "http://")]
+    } else {
+        return None;
+    };
+
+    let (origin_path, scheme) = prefixes.iter().find_map(|(prefix, scheme)| {
+        parsed_url
+            .path()
+            .strip_prefix(prefix)
+            .map(|origin_path| (origin_path, scheme))
+    })?;
+    let mut origin_url = Url::parse(&format!("{scheme}{origin_path}")).ok()?;
+    set_query_without_amp_params(&mut origin_url, &parsed_url);
+    origin_url.set_fragment(parsed_url.fragment());
+
+    Some(origin_url.into())
+}
+
+/// A function which removes the AMP markers (the `amp.` subdomain, the `/amp` path segment, the
+/// `.amp` extension and the AMP query parameters) from the provided url and returns the resulting
+/// url which is likely the canonical url of the page. Returns `None` if the url has no AMP
+/// markers.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+pub fn strip_amp_markers(url: &str) -> Option<String> {
+    let parsed_url = Url::parse(url.trim()).ok()?;
+    let mut canonical_url = parsed_url.clone();
+
+    if let Some(host) = parsed_url
+        .host_str()
+        .and_then(|host| host.strip_prefix("amp."))
+    {
+        canonical_url.set_host(Some(host)).ok()?;
+    }
+
+    let path = parsed_url.path();
+    let path = match path.trim_end_matches('/').strip_suffix("/amp") {
+        Some(path) => format!("{path}/"),
+        None => path
+            .replace(".amp.html", ".html")
+            .trim_end_matches(".amp")
+            .to_owned(),
+    };
+    canonical_url.set_path(&path);
+
+    set_query_without_amp_params(&mut canonical_url, &parsed_url);
+
+    (canonical_url != parsed_url).then(|| canonical_url.into())
+}
+
+/// A helper function which sets the query of the provided url to the query of the source url
+/// without the AMP query parameters.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url whose query should be set as an argument.
+/// * `source_url` - It takes the url whose query should be copied as an argument.
+fn set_query_without_amp_params(url: &mut Url, source_url: &Url) {
+    let params: Vec<(String, String)> = source_url
+        .query_pairs()
+        .into_owned()
+        .filter(|(name, _)| !AMP_PARAMS.contains(&name.to_lowercase().as_str()))
+        .collect();
+
+    if params.is_empty() {
+        url.set_query(None);
+    } else if params.len() != source_url.query_pairs().count() {
+        url.query_pairs_mut().clear().extend_pairs(params);
+    } else {
+        url.set_query(source_url.query());
+    }
+}
+
+/// A function which resolves the AMP urls of the provided search results by sending a `HEAD`
+/// request to the urls without the AMP markers. The url of a search result is replaced with the
+/// url at which the request finally succeeded (after following the redirects) and is kept as it
+/// is if the request failed.
+///
+/// # Arguments
+///
+/// * `results` - It takes the search results whose AMP urls should be resolved as an argument.
+/// * `client` - It takes the client used to send the requests as an argument.
+/// * `timeout` - It takes the timeout of the requests as an argument.
+pub async fn resolve_amp_urls<'a>(
+    results: impl IntoIterator<Item = &'a mut SearchResult>,
+    client: &Client,
+    timeout: Duration,
+) {
+    let tasks: Vec<(&mut SearchResult, JoinHandle<Option<String>>)> = results
+        .into_iter()
+        .filter_map(|result| {
+            let canonical_url = strip_amp_markers(&result.url)?;
+            let client = client.clone();
+            Some((
+                result,
+                tokio::spawn(async move {
+                    let response = client
+                        .head(&canonical_url)
+                        .timeout(timeout)
+                        .send()
+                        .await
+                        .ok()?;
+                    response
+                        .status()
+                        .is_success()
+                        .then(|| response.url().to_string())
+                }),
+            ))
+        })
+        .collect();
+
+    for (result, task) in tasks {
+        if let Ok(Some(url)) = task.await {
+            result.url = url;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_amp_url() {
+        assert_eq!(
+            rewrite_amp_url("https://www.google.com/amp/s/example.com/news/story", false),
+            "https://example.com/news/story"
+        );
+        assert_eq!(
+            rewrite_amp_url(
+                "https://example-com.cdn.ampproject.org/c/s/example.com/news/story/amp?amp_js_v=0.1",
+                true
+            ),
+            "https://example.com/news/story/"
+        );
+        assert_eq!(
+            rewrite_amp_url(
+                "https://amp.example.com/news/story.amp.html?id=1&amp=1",
+                true
+            ),
+            "https://example.com/news/story.html?id=1"
+        );
+        assert_eq!(
+            rewrite_amp_url("https://amp.example.com/news/story", false),
+            "https://amp.example.com/news/story"
+        );
+        assert_eq!(
+            rewrite_amp_url("https://example.com/examples?id=1", true),
+            "https://example.com/examples?id=1"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! This module provides the functionality to subscribe to remote blocklists written in the
+//! uBlacklist format, which are fetched and refreshed periodically by a background task and used
+//! to drop the matching results.
+
+use crate::config::reloader::SharedConfig;
+use regex::Regex;
+use reqwest::{Client, Url};
+use std::{
+    collections::HashMap,
+    sync::{Arc, PoisonError, RwLock},
+    time::{Duration, Instant},
+};
+
+/// The default interval (in seconds) after which the subscribed blocklists are refreshed.
+pub const DEFAULT_BLOCKLIST_REFRESH_INTERVAL: u64 = 86400;
+
+/// The interval (in seconds) at which the subscriptions are checked for the blocklists which
+/// should be fetched or refreshed.
+const BLOCKLIST_CHECK_INTERVAL: u64 = 60;
+
+/// The blocklist merged from all the subscribed blocklists which have been fetched so far.
+static SUBSCRIBED_BLOCKLIST: RwLock<Option<Arc<Blocklist>>> = RwLock::new(None);
+
+/// A named struct which stores the compiled rules of a blocklist written in the uBlacklist format.
+#[derive(Clone, Default)]
+pub struct Blocklist {
+    /// It stores the rules matching the urls of the results which should be dropped.
+    blocked: Vec<Regex>,
+    /// It stores the rules (prefixed with `@`) matching the urls of the results which should be
+    /// kept even if they match a blocking rule.
+    unblocked: Vec<Regex>,
+}
+
+impl Blocklist {
+    /// A function which parses the provided blocklist written in the uBlacklist format. Every
+    /// line is either a match pattern (like `*://*.example.com/*`) or a regex wrapped in slashes
+    /// (like `/^https?:\/\/example\.(com|org)\//`), the rules prefixed with `@` unblock the
+    /// matching results and the lines starting with `#` are comments. The unsupported or invalid
+    /// rules are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `list` - It takes the contents of the blocklist as an argument.
+    pub fn parse(list: &str) -> Self {
+        let mut blocklist = Blocklist::default();
+
+        for line in list.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (rules, rule) = match line.strip_prefix('@') {
+                Some(rule) => (&mut blocklist.unblocked, rule.trim()),
+                None => (&mut blocklist.blocked, line),
+            };
+            let regex = match rule
+                .strip_prefix('/')
+                .and_then(|regex| regex.strip_suffix('/'))
+            {
+                Some(regex) => Regex::new(regex).ok(),
+                None => match_pattern(rule),
+            };
+            rules.extend(regex);
+        }
+
+        blocklist
+    }
+
+    /// A function which adds all the rules of the provided blocklist to this blocklist.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - It takes the blocklist whose rules should be added as an argument.
+    pub fn extend(&mut self, other: &Blocklist) {
+        self.blocked.extend(other.blocked.iter().cloned());
+        self.unblocked.extend(other.unblocked.iter().cloned());
+    }
+
+    /// A function which returns whether the blocklist has no blocking rules and hence keeps all
+    /// the results.
+    pub fn is_empty(&self) -> bool {
+        self.blocked.is_empty()
+    }
+
+    /// A function which checks whether the result with the provided url should be dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the search result as an argument.
+    pub fn is_blocked(&self, url: &str) -> bool {
+        // The url is parsed so that the host is lowercased and the empty path is written as `/`.
+        let url = Url::parse(url)
+            .map(String::from)
+            .unwrap_or_else(|_| url.to_owned());
+
+        self.blocked.iter().any(|rule| rule.is_match(&url))
+            && !self.unblocked.iter().any(|rule| rule.is_match(&url))
+    }
+}
+
+/// A helper function which converts the provided match pattern (like `*://*.example.com/*`) into
+/// a regex matched against the urls of the results.
+///
+/// # Arguments
+///
+/// * `pattern` - It takes the match pattern as an argument.
+fn match_pattern(pattern: &str) -> Option<Regex> {
+    let (scheme, rest) = pattern.split_once("://")?;
+    let (host, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/*"),
+    };
+
+    let scheme = match scheme {
+        "*" => "https?".to_owned(),
+        scheme => regex::escape(scheme),
+    };
+    let host = match host {
+        "*" => "[^/]+".to_owned(),
+        host => match host.strip_prefix("*.") {
+            Some(domain) => format!(r"([^/]+\.)?{}", regex::escape(&domain.to_lowercase())),
+            None => regex::escape(&host.to_lowercase()),
+        },
+    };
+    let path = path
+        .split('*')
+        .map(regex::escape)
+        .collect::<Vec<String>>()
+        .join(".*");
+
+    Regex::new(&format!("^{scheme}://{host}(:[0-9]+)?{path}$")).ok()
+}
+
+/// A function which returns the blocklist merged from all the subscribed blocklists which have
+/// been fetched so far.
+pub fn subscribed_blocklist() -> Option<Arc<Blocklist>> {
+    SUBSCRIBED_BLOCKLIST
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// A function which runs forever and fetches the blocklists provided via the
+/// `blocklist_subscriptions` config option. Every blocklist is refreshed once the
+/// `blocklist_refresh_interval` has elapsed since it was last fetched and the previously fetched
+/// version of a blocklist is kept if it could not be refreshed. The subscriptions are picked up
+/// again whenever the config is reloaded.
+///
+/// # Arguments
+///
+/// * `shared_config` - It takes the shared config as an argument.
+pub async fn refresh_blocklists(shared_config: Arc<SharedConfig>) {
+    let client = Client::new();
+    let mut fetched_blocklists: HashMap<String, (Instant, Blocklist)> = HashMap::new();
+    let mut interval = tokio::time::interval(Duration::from_secs(BLOCKLIST_CHECK_INTERVAL));
+
+    loop {
+        interval.tick().await;
+
+        let config = shared_config.load();
+        let refresh_interval = Duration::from_secs(config.blocklist_refresh_interval);
+
+        let subscriptions_count = fetched_blocklists.len();
+        fetched_blocklists.retain(|url, _| config.blocklist_subscriptions.contains(url));
+        let mut changed = fetched_blocklists.len() != subscriptions_count;
+
+        for url in &config.blocklist_subscriptions {
+            if fetched_blocklists
+                .get(url)
+                .is_some_and(|(fetched_at, _)| fetched_at.elapsed() < refresh_interval)
+            {
+                continue;
+            }
+
+            let response = client
+                .get(url)
+                .timeout(Duration::from_secs(config.request_timeout as u64))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            let list = match response {
+                Ok(response) => response.text().await,
+                Err(error) => Err(error),
+            };
+
+            match list {
+                Ok(list) => {
+                    fetched_blocklists
+                        .insert(url.clone(), (Instant::now(), Blocklist::parse(&list)));
+                    changed = true;
+                }
+                Err(error) => tracing::error!("Unable to fetch the blocklist at {url}: {error}"),
+            }
+        }
+
+        if changed {
+            let mut blocklist = Blocklist::default();
+            for (_, fetched_blocklist) in fetched_blocklists.values() {
+                blocklist.extend(fetched_blocklist);
+            }
+            *SUBSCRIBED_BLOCKLIST
+                .write()
+                .unwrap_or_else(PoisonError::into_inner) =
+                (!blocklist.is_empty()).then(|| Arc::new(blocklist));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_blocklist() {
+        let blocklist = Blocklist::parse(
+            r#"
+# A comment
+*://*.pinterest.com/*
+https://example.com/spam/*
+/^https?:\/\/[a-z]+\.content-farm\.net\//
+@*://help.pinterest.com/*
+"#,
+        );
+
+        assert!(blocklist.is_blocked("https://www.pinterest.com/pin/1"));
+        assert!(blocklist.is_blocked("http://pinterest.com"));
+        assert!(blocklist.is_blocked("https://example.com/spam/page"));
+        assert!(blocklist.is_blocked("https://www.content-farm.net/article"));
+        assert!(!blocklist.is_blocked("https://help.pinterest.com/article"));
+        assert!(!blocklist.is_blocked("https://example.com/ham"));
+        assert!(!blocklist.is_blocked("http://example.com/spam/page"));
+    }
+}
@@ -0,0 +1,136 @@
+//! This module provides the functionality to filter the search results based on their domains
+//! with the block and allow lists provided via the config file or by the user, so that content
+//! farms can be dropped or the results can be restricted to approved domains.
+
+use regex::Regex;
+use reqwest::Url;
+
+/// A named struct which stores the compiled domain patterns of a block list and an allow list.
+#[derive(Default)]
+pub struct DomainFilter {
+    /// It stores the patterns of the domains whose results should be dropped.
+    blocked: Vec<Regex>,
+    /// It stores the patterns of the domains to which the results should be restricted, the
+    /// results are not restricted if it is empty.
+    allowed: Vec<Regex>,
+}
+
+impl DomainFilter {
+    /// A function which compiles the provided block and allow lists into a domain filter. The
+    /// invalid patterns are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocked` - It takes the patterns of the domains to be blocked as an argument.
+    /// * `allowed` - It takes the patterns of the domains to be allowed as an argument.
+    pub fn new(blocked: &[String], allowed: &[String]) -> Self {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|pattern| match domain_pattern(pattern) {
+                    Ok(regex) => Some(regex),
+                    Err(error) => {
+                        tracing::warn!("Skipping the invalid domain pattern `{pattern}`: {error}");
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        DomainFilter {
+            blocked: compile(blocked),
+            allowed: compile(allowed),
+        }
+    }
+
+    /// A function which returns whether the filter has no patterns and hence keeps all the
+    /// results.
+    pub fn is_empty(&self) -> bool {
+        self.blocked.is_empty() && self.allowed.is_empty()
+    }
+
+    /// A function which checks whether the result with the provided url should be kept, it
+    /// should neither match a blocked pattern nor miss all the allowed patterns (if any).
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the search result as an argument.
+    pub fn is_allowed(&self, url: &str) -> bool {
+        let Some(host) = url_host(url) else {
+            return self.allowed.is_empty();
+        };
+
+        !self.blocked.iter().any(|pattern| pattern.is_match(&host))
+            && (self.allowed.is_empty()
+                || self.allowed.iter().any(|pattern| pattern.is_match(&host)))
+    }
+}
+
+/// A function which returns the lowercased host of the provided url without the `www.` prefix,
+/// which is matched against the domain patterns.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+pub fn url_host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    Some(host.trim_start_matches("www.").to_owned())
+}
+
+/// A function which compiles the provided domain pattern into a regex matched against the host of
+/// the results. The patterns wrapped in slashes (like `/^.*\.example\.(com|org)$/`) are regexes,
+/// all the other patterns are globs in which `*` matches any characters and which also match the
+/// subdomains of the domain (so `example.com` matches `docs.example.com`).
+///
+/// # Arguments
+///
+/// * `pattern` - It takes the domain pattern as an argument.
+///
+/// # Error
+///
+/// Returns a regex error if the pattern is an invalid regex.
+pub fn domain_pattern(pattern: &str) -> Result<Regex, regex::Error> {
+    let pattern = pattern.trim();
+    match pattern
+        .strip_prefix('/')
+        .and_then(|pattern| pattern.strip_suffix('/'))
+    {
+        Some(regex) => Regex::new(regex),
+        None => Regex::new(&format!(
+            r"^(.+\.)?{}$",
+            pattern
+                .trim_start_matches("www.")
+                .to_lowercase()
+                .split('*')
+                .map(regex::escape)
+                .collect::<Vec<String>>()
+                .join(".*")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_filter() {
+        let filter = DomainFilter::new(
+            &[
+                "pinterest.com".to_owned(),
+                "/^spam[0-9]+\\.net$/".to_owned(),
+            ],
+            &[],
+        );
+        assert!(!filter.is_allowed("https://www.pinterest.com/pin/1"));
+        assert!(!filter.is_allowed("https://uk.Pinterest.com/pin/1"));
+        assert!(!filter.is_allowed("https://spam42.net"));
+        assert!(filter.is_allowed("https://notpinterest.com"));
+        assert!(filter.is_allowed("https://spam.net"));
+
+        let filter = DomainFilter::new(&[], &["*.rust-lang.org".to_owned()]);
+        assert!(filter.is_allowed("https://doc.rust-lang.org/book"));
+        assert!(!filter.is_allowed("https://example.com"));
+    }
+}
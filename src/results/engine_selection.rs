@@ -0,0 +1,130 @@
+//! This module provides the adaptive selection of the upstream search engines, which picks the
+//! engines sent each search from the enabled ones based on their recent success rate and latency,
+//! so that the slow and the failing engines do not hold back the searches when more engines are
+//! enabled than needed.
+
+use super::{engine_stats::ENGINE_STATS, health_monitor::HEALTH_CHECKS};
+use crate::{engines::block_backoff::blocked_for, models::engine_models::EngineHandler};
+use rand::Rng;
+
+/// The number of the recent responses of an upstream search engine below which the engine is
+/// always picked, so that the statistics of the new engines are gathered first.
+const MIN_RESPONSES: usize = 5;
+
+/// A function which picks the upstream search engines to which a search is sent. The engines with
+/// the best score (their recent success rate discounted by their median latency) are picked, and
+/// once in a while (with the provided exploration rate) one of them is swapped for another engine,
+/// so that the statistics of the engines which are not picked keep being updated.
+///
+/// # Arguments
+///
+/// * `engines` - It takes the upstream search engines selected for the search as an argument.
+/// * `max_engines` - It takes the number of the engines to which the search is sent (or `None` to
+///   send it to all the engines) as an argument.
+/// * `exploration_rate` - It takes the probability (between `0` and `1`) with which an engine which
+///   is not among the best ones is picked as an argument.
+///
+/// # Returns
+///
+/// It returns the picked engines in the order in which they were provided.
+pub fn select_engines(
+    engines: &[EngineHandler],
+    max_engines: Option<usize>,
+    exploration_rate: f32,
+) -> Vec<&EngineHandler> {
+    let Some(max_engines) = max_engines.filter(|max_engines| *max_engines < engines.len()) else {
+        return engines.iter().collect();
+    };
+
+    let scores: Vec<f64> = engines
+        .iter()
+        .map(|engine| engine_score(engine.name()))
+        .collect();
+    let mut rng = rand::thread_rng();
+    let explored: Option<usize> =
+        (rng.gen::<f32>() < exploration_rate).then(|| rng.gen_range(max_engines..engines.len()));
+
+    let picked = pick_engines(&scores, max_engines, explored);
+    tracing::debug!(
+        engines = ?picked.iter().map(|index| engines[*index].name()).collect::<Vec<_>>(),
+        "picked the engines"
+    );
+    picked.into_iter().map(|index| &engines[index]).collect()
+}
+
+/// A helper function which computes the score of an upstream search engine from its recent
+/// responses, the higher the better.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the engine as an argument.
+fn engine_score(engine: &str) -> f64 {
+    // The blocked engines would not be sent the search anyway.
+    if blocked_for(engine).is_some() {
+        return 0.0;
+    }
+    let Some(stats) = ENGINE_STATS
+        .engine(engine)
+        .filter(|stats| stats.requests >= MIN_RESPONSES)
+    else {
+        return f64::INFINITY;
+    };
+
+    let score = stats.success_rate / (1.0 + stats.p50_latency_ms as f64 / 1000.0);
+    match HEALTH_CHECKS.engine(engine) {
+        Some(check) if !check.passed() => score / 2.0,
+        _ => score,
+    }
+}
+
+/// A helper function which picks the engines with the best scores.
+///
+/// # Arguments
+///
+/// * `scores` - It takes the scores of the engines as an argument.
+/// * `max_engines` - It takes the number of the engines to pick (lower than the number of the
+///   engines) as an argument.
+/// * `explored` - It takes the rank (from `max_engines` onwards) of the engine picked in place of
+///   the last of the best engines (if any) as an argument.
+///
+/// # Returns
+///
+/// It returns the indices of the picked engines in ascending order.
+fn pick_engines(scores: &[f64], max_engines: usize, explored: Option<usize>) -> Vec<usize> {
+    let mut ranked: Vec<usize> = (0..scores.len()).collect();
+    ranked.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+    if let Some(explored) = explored {
+        ranked.swap(max_engines - 1, explored);
+    }
+    ranked.truncate(max_engines);
+    ranked.sort_unstable();
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_engines() {
+        let scores = [0.4, f64::INFINITY, 0.9, 0.1];
+
+        // The new engines are picked first, then the best ones.
+        assert_eq!(pick_engines(&scores, 2, None), vec![1, 2]);
+        assert_eq!(pick_engines(&scores, 3, None), vec![0, 1, 2]);
+        // The explored engine replaces the last of the best engines.
+        assert_eq!(pick_engines(&scores, 2, Some(3)), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_select_all_engines() {
+        let engines = vec![
+            EngineHandler::new("bing").unwrap(),
+            EngineHandler::new("brave").unwrap(),
+        ];
+
+        assert_eq!(select_engines(&engines, None, 0.5).len(), 2);
+        assert_eq!(select_engines(&engines, Some(5), 0.5).len(), 2);
+        assert_eq!(select_engines(&engines, Some(1), 0.5).len(), 1);
+    }
+}
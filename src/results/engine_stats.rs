@@ -0,0 +1,301 @@
+//! This module provides the registry of the statistics of the upstream search engines (their
+//! recent success rate and latency and their last error), which is updated by the aggregator
+//! with every response of the engines and shared by all the workers of the server.
+
+use crate::models::{aggregation_models::EngineErrorInfo, engine_models::EngineError};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The number of the most recent responses of each engine from which the statistics are
+/// computed.
+pub const STATS_WINDOW: usize = 100;
+
+/// The success rate below which an upstream search engine is considered degraded.
+const DEGRADED_SUCCESS_RATE: f64 = 0.9;
+
+/// The success rate below which an upstream search engine is considered down.
+const DOWN_SUCCESS_RATE: f64 = 0.5;
+
+/// A static variable which stores the statistics of the upstream search engines.
+pub static ENGINE_STATS: EngineStatsRegistry = EngineStatsRegistry::new();
+
+/// A named struct which stores a response of an upstream search engine.
+struct EngineResponse {
+    /// It stores the time taken by the engine to answer (or to time out).
+    latency: Duration,
+    /// It stores the kind of the error returned by the engine or `None` if the engine answered
+    /// with its results.
+    error: Option<String>,
+}
+
+/// A named struct which stores the recent responses of an upstream search engine.
+#[derive(Default)]
+struct EngineHistory {
+    /// It stores the most recent responses of the engine, the oldest response first.
+    responses: VecDeque<EngineResponse>,
+    /// It stores the last error returned by the engine.
+    last_error: Option<LastError>,
+}
+
+/// A named struct which stores the last error returned by an upstream search engine.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct LastError {
+    /// It stores the kind of the error (like `TimedOut` or `Blocked`).
+    pub error: String,
+    /// It stores the time (in seconds since the unix epoch) at which the error was returned.
+    pub at: u64,
+}
+
+/// A named struct which stores the statistics of an upstream search engine computed from its
+/// recent responses.
+#[derive(Clone, Debug, Serialize)]
+pub struct EngineStats {
+    /// It stores the name of the engine.
+    pub engine: String,
+    /// It stores the number of the recent responses from which the statistics are computed.
+    pub requests: usize,
+    /// It stores the ratio (between `0` and `1`) of the recent responses with the results.
+    pub success_rate: f64,
+    /// It stores the median latency (in milliseconds) of the recent responses.
+    pub p50_latency_ms: u64,
+    /// It stores the 95th percentile of the latency (in milliseconds) of the recent responses.
+    pub p95_latency_ms: u64,
+    /// It stores the number of the recent responses with an error keyed by the kind of the error.
+    pub errors: BTreeMap<String, usize>,
+    /// It stores the last error returned by the engine.
+    pub last_error: Option<LastError>,
+}
+
+impl EngineStats {
+    /// A function which returns the health of the engine derived from its recent success rate.
+    pub fn status(&self) -> EngineStatus {
+        match self.success_rate {
+            rate if rate < DOWN_SUCCESS_RATE => EngineStatus::Down,
+            rate if rate < DEGRADED_SUCCESS_RATE => EngineStatus::Degraded,
+            _ => EngineStatus::Up,
+        }
+    }
+}
+
+/// The health of an upstream search engine derived from its recent responses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EngineStatus {
+    /// Most of the recent requests to the engine were answered with the results.
+    Up,
+    /// Some of the recent requests to the engine failed.
+    Degraded,
+    /// Most of the recent requests to the engine failed.
+    Down,
+    /// The engine blocked the instance and is skipped until the end of its cool-down period.
+    Blocked,
+    /// The engine did not answer since the start of the server.
+    Unknown,
+}
+
+impl EngineStatus {
+    /// A function which returns the name of the health of the engine.
+    pub fn name(self) -> &'static str {
+        match self {
+            EngineStatus::Up => "up",
+            EngineStatus::Degraded => "degraded",
+            EngineStatus::Down => "down",
+            EngineStatus::Blocked => "blocked",
+            EngineStatus::Unknown => "unknown",
+        }
+    }
+}
+
+/// A named struct which stores the recent responses of all the upstream search engines.
+pub struct EngineStatsRegistry {
+    /// It stores the recent responses of each engine keyed by the name of the engine.
+    engines: Mutex<BTreeMap<String, EngineHistory>>,
+}
+
+impl EngineStatsRegistry {
+    /// Constructs a new registry without any recorded response.
+    pub const fn new() -> Self {
+        EngineStatsRegistry {
+            engines: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// A function which records a response of an upstream search engine, the oldest response of
+    /// the engine is forgotten once more than `STATS_WINDOW` responses are recorded. The engines
+    /// answering without any result are not counted as failing.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the engine as an argument.
+    /// * `latency` - It takes the time taken by the engine to answer as an argument.
+    /// * `error` - It takes the error returned by the engine or `None` if the engine returned its
+    ///   results as an argument.
+    pub fn record(&self, engine: &str, latency: Duration, error: Option<&EngineError>) {
+        let mut engines = self.engines.lock().unwrap_or_else(PoisonError::into_inner);
+        let history = engines.entry(engine.to_owned()).or_default();
+
+        let error = error
+            .filter(|error| !matches!(error, EngineError::EmptyResultSet))
+            .map(|error| EngineErrorInfo::new(error, engine).error);
+        if history.responses.len() == STATS_WINDOW {
+            history.responses.pop_front();
+        }
+        if let Some(error) = &error {
+            history.last_error = Some(LastError {
+                error: error.clone(),
+                at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |now| now.as_secs()),
+            });
+        }
+        history
+            .responses
+            .push_back(EngineResponse { latency, error });
+    }
+
+    /// A function which computes the statistics of all the upstream search engines which
+    /// answered since the start of the server.
+    ///
+    /// # Returns
+    ///
+    /// It returns the statistics of the engines in the alphabetical order of their names.
+    pub fn snapshot(&self) -> Vec<EngineStats> {
+        self.engines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(engine, history)| engine_stats(engine, history))
+            .collect()
+    }
+
+    /// A function which computes the statistics of an upstream search engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the engine as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the statistics of the engine or `None` if the engine did not answer since the
+    /// start of the server.
+    pub fn engine(&self, engine: &str) -> Option<EngineStats> {
+        self.engines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(engine)
+            .map(|history| engine_stats(engine, history))
+    }
+}
+
+impl Default for EngineStatsRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A helper function which computes the statistics of an engine from its recent responses.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the engine as an argument.
+/// * `history` - It takes the recent responses of the engine as an argument.
+fn engine_stats(engine: &str, history: &EngineHistory) -> EngineStats {
+    let requests = history.responses.len();
+    let mut errors: BTreeMap<String, usize> = BTreeMap::new();
+    for error in history
+        .responses
+        .iter()
+        .filter_map(|response| response.error.as_ref())
+    {
+        *errors.entry(error.clone()).or_default() += 1;
+    }
+    let successes = requests - errors.values().sum::<usize>();
+    let mut latencies: Vec<Duration> = history
+        .responses
+        .iter()
+        .map(|response| response.latency)
+        .collect();
+    latencies.sort_unstable();
+
+    EngineStats {
+        engine: engine.to_owned(),
+        requests,
+        success_rate: match requests {
+            0 => 1.0,
+            _ => successes as f64 / requests as f64,
+        },
+        p50_latency_ms: percentile(&latencies, 0.5).as_millis() as u64,
+        p95_latency_ms: percentile(&latencies, 0.95).as_millis() as u64,
+        errors,
+        last_error: history.last_error.clone(),
+    }
+}
+
+/// A helper function which returns the percentile of the sorted latencies with the nearest rank
+/// method.
+///
+/// # Arguments
+///
+/// * `latencies` - It takes the latencies sorted in the ascending order as an argument.
+/// * `percentile` - It takes the percentile (between `0` and `1`) as an argument.
+fn percentile(latencies: &[Duration], percentile: f64) -> Duration {
+    let rank = (percentile * latencies.len() as f64).ceil() as usize;
+    latencies
+        .get(rank.saturating_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_stats() {
+        let registry = EngineStatsRegistry::new();
+        for latency in 1..=20 {
+            registry.record("bing", Duration::from_millis(latency * 10), None);
+        }
+        registry.record(
+            "bing",
+            Duration::from_secs(30),
+            Some(&EngineError::TimedOut),
+        );
+        registry.record(
+            "bing",
+            Duration::from_millis(5),
+            Some(&EngineError::EmptyResultSet),
+        );
+
+        let stats = registry.engine("bing").unwrap();
+        assert_eq!(stats.requests, 22);
+        assert_eq!(stats.success_rate, 21.0 / 22.0);
+        assert_eq!(stats.p50_latency_ms, 100);
+        assert_eq!(stats.p95_latency_ms, 200);
+        assert_eq!(stats.errors, BTreeMap::from([("TimedOut".to_owned(), 1)]));
+        assert_eq!(stats.status(), EngineStatus::Up);
+        assert_eq!(stats.last_error.unwrap().error, "TimedOut");
+        assert!(registry.engine("brave").is_none());
+    }
+
+    #[test]
+    fn test_engine_stats_window() {
+        let registry = EngineStatsRegistry::new();
+        registry.record("bing", Duration::from_secs(1), Some(&EngineError::Blocked));
+        for _ in 0..STATS_WINDOW {
+            registry.record("bing", Duration::from_millis(100), None);
+        }
+
+        let stats = registry.snapshot().pop().unwrap();
+        assert_eq!(stats.requests, STATS_WINDOW);
+        assert_eq!(stats.success_rate, 1.0);
+        assert_eq!(stats.p95_latency_ms, 100);
+        assert!(stats.errors.is_empty());
+        // The last error is kept once the failed response leaves the window.
+        assert_eq!(stats.last_error.unwrap().error, "Blocked");
+    }
+}
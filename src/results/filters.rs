@@ -0,0 +1,457 @@
+//! This module provides the pipeline of the filters which transform or drop the aggregated search
+//! results (like the stripping of the tracking query parameters, the domain filters and the
+//! blocklists), so that the cross-cutting transforms are kept out of the aggregator. The safe
+//! search is always enforced first, then the built-in filters are run in the order provided via
+//! the `result_filters` config option, followed by the custom filters registered by the projects
+//! using `websurfx` as a library.
+
+use super::{
+    aggregator::filter_with_lists, blocklists::subscribed_blocklist, domain_filter::DomainFilter,
+    rewrites::rewrite_url, urls::strip_tracking_params,
+};
+use crate::{
+    config::parser::Config,
+    handler::{file_path, FileType},
+    models::{aggregation_models::SearchResult, engine_models::SearchOptions},
+};
+use reqwest::Url;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, OnceLock},
+};
+
+/// The names of the built-in result filters in the order in which they are run by default. The
+/// safe search is enforced outside of these filters, so that it cannot be turned off.
+pub const BUILTIN_RESULT_FILTERS: [&str; 4] = ["trackers", "domains", "blocklists", "rewrites"];
+
+/// The custom result filters registered when the server is started.
+static RESULT_FILTERS: OnceLock<Vec<Box<dyn ResultFilter>>> = OnceLock::new();
+
+/// A constant for holding the list of nsfw domains which is loaded from the disk on its first use.
+static NSFW_DOMAINS: OnceLock<Arc<HashSet<String>>> = OnceLock::new();
+
+/// A named struct which stores the search whose results are filtered.
+pub struct FilterContext<'a> {
+    /// It stores the search query provided by the user.
+    pub query: &'a str,
+    /// It stores the search preferences of the user.
+    pub options: &'a SearchOptions,
+    /// It stores the names of the upstream engines which filter their results based on the safe
+    /// search level themselves.
+    pub safe_search_engines: &'a [&'static str],
+}
+
+/// A trait for the filters which transform or drop the aggregated search results. The filters
+/// are called in a chain after the results of the upstream engines are merged and before they are
+/// ranked, every filter can rewrite, drop or annotate the results.
+pub trait ResultFilter: Send + Sync {
+    /// A function which filters the provided search results.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - It takes the search whose results are filtered as an argument.
+    /// * `results` - It takes the aggregated search results keyed by their canonical url as an
+    ///   argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the results could not be filtered, which fails the search.
+    fn filter(
+        &self,
+        context: &FilterContext<'_>,
+        results: &mut HashMap<String, SearchResult>,
+    ) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+/// A function which registers the custom result filters which are called (in the provided order)
+/// on every search after the built-in filters. It should be called once before the server is
+/// started.
+///
+/// # Arguments
+///
+/// * `filters` - It takes the custom result filters as an argument.
+///
+/// # Error
+///
+/// Returns the provided filters back if the filters have already been registered.
+pub fn register_result_filters(
+    filters: Vec<Box<dyn ResultFilter>>,
+) -> Result<(), Vec<Box<dyn ResultFilter>>> {
+    RESULT_FILTERS.set(filters)
+}
+
+/// A function which returns the custom result filters registered when the server was started.
+pub fn registered_result_filters() -> &'static [Box<dyn ResultFilter>] {
+    RESULT_FILTERS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// A function which enforces the safe search level of the user over the aggregated search results
+/// and then runs the built-in result filters provided via the `result_filters` config option
+/// followed by the registered custom filters over them.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `context` - It takes the search whose results are filtered as an argument.
+/// * `results` - It takes the aggregated search results keyed by their canonical url as an
+///   argument.
+///
+/// # Error
+///
+/// Returns an error if one of the filters failed to filter the results.
+pub fn filter_results(
+    config: &Config,
+    context: &FilterContext<'_>,
+    results: &mut HashMap<String, SearchResult>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    SafeSearchFilter::new(context.options).filter(context, results)?;
+    for filter in config
+        .result_filters
+        .iter()
+        .filter_map(|name| builtin_filter(name, config, context.options))
+    {
+        filter.filter(context, results)?;
+    }
+    for filter in registered_result_filters() {
+        filter.filter(context, results)?;
+    }
+    Ok(())
+}
+
+/// A helper function which constructs the built-in result filter with the provided name.
+///
+/// # Arguments
+///
+/// * `name` - It takes the name of the filter as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `options` - It takes the search preferences of the user as an argument.
+///
+/// # Returns
+///
+/// It returns the filter or `None` if there is no built-in filter with the provided name or if
+/// the filter has nothing to filter for the search.
+fn builtin_filter<'a>(
+    name: &str,
+    config: &'a Config,
+    options: &SearchOptions,
+) -> Option<Box<dyn ResultFilter + 'a>> {
+    match name {
+        "trackers" => Some(Box::new(TrackerFilter)),
+        "domains" => {
+            // The results should be allowed by both the domain lists of the instance and of the
+            // user.
+            let domain_filters: Vec<DomainFilter> = [
+                DomainFilter::new(&config.blocked_domains, &config.allowed_domains),
+                DomainFilter::new(&options.blocked_domains, &options.allowed_domains),
+            ]
+            .into_iter()
+            .filter(|domain_filter| !domain_filter.is_empty())
+            .collect();
+            (!domain_filters.is_empty()).then(|| {
+                Box::new(DomainFilters {
+                    filters: domain_filters,
+                }) as Box<dyn ResultFilter>
+            })
+        }
+        "blocklists" => Some(Box::new(BlocklistFilter)),
+        "rewrites" if !config.url_rewrites.is_empty() => Some(Box::new(RewriteFilter {
+            rewrites: &config.url_rewrites,
+        })),
+        _ => None,
+    }
+}
+
+/// The built-in filter which strips the tracking query parameters from the urls of the results.
+struct TrackerFilter;
+
+impl ResultFilter for TrackerFilter {
+    fn filter(
+        &self,
+        _context: &FilterContext<'_>,
+        results: &mut HashMap<String, SearchResult>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for result in results.values_mut() {
+            result.url = strip_tracking_params(&result.url);
+        }
+        Ok(())
+    }
+}
+
+/// The filter which enforces the safe search level of the user, it drops the results from the nsfw
+/// domains when safe search is enabled (unless one of the upstream engines which returned the
+/// result filters its results based on the safe search level itself), along with the results
+/// matching the blocklist (but not the allowlist) of the instance when the safe search level is
+/// `3` or more.
+struct SafeSearchFilter {
+    /// It stores the list of nsfw domains or `None` if safe search is disabled.
+    nsfw_domains: Option<Arc<HashSet<String>>>,
+}
+
+impl SafeSearchFilter {
+    /// Constructs the filter enforcing the safe search level of the provided search preferences.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - It takes the search preferences of the user as an argument.
+    fn new(options: &SearchOptions) -> Self {
+        SafeSearchFilter {
+            nsfw_domains: (options.safe_search >= 1).then(|| {
+                NSFW_DOMAINS
+                    .get_or_init(|| Arc::new(load_nsfw_domains()))
+                    .clone()
+            }),
+        }
+    }
+}
+
+impl ResultFilter for SafeSearchFilter {
+    fn filter(
+        &self,
+        context: &FilterContext<'_>,
+        results: &mut HashMap<String, SearchResult>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(nsfw_domains) = &self.nsfw_domains {
+            results.retain(|_, result| {
+                result
+                    .engine
+                    .iter()
+                    .any(|engine| context.safe_search_engines.contains(&engine.as_str()))
+                    || !is_nsfw_domain(&result.url, nsfw_domains)
+            });
+        }
+
+        if context.options.safe_search >= 3 {
+            let mut blacklist_map: HashMap<String, SearchResult> = HashMap::new();
+            filter_with_lists(results, &mut blacklist_map, file_path(FileType::BlockList)?)?;
+            filter_with_lists(&mut blacklist_map, results, file_path(FileType::AllowList)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// The built-in filter which drops the results which are not allowed by the domain lists of the
+/// instance and of the user.
+struct DomainFilters {
+    /// It stores the compiled domain lists.
+    filters: Vec<DomainFilter>,
+}
+
+impl ResultFilter for DomainFilters {
+    fn filter(
+        &self,
+        _context: &FilterContext<'_>,
+        results: &mut HashMap<String, SearchResult>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        results.retain(|_, result| {
+            self.filters
+                .iter()
+                .all(|domain_filter| domain_filter.is_allowed(&result.url))
+        });
+        Ok(())
+    }
+}
+
+/// The built-in filter which drops the results blocked by the subscribed blocklists.
+struct BlocklistFilter;
+
+impl ResultFilter for BlocklistFilter {
+    fn filter(
+        &self,
+        _context: &FilterContext<'_>,
+        results: &mut HashMap<String, SearchResult>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(blocklist) = subscribed_blocklist() {
+            results.retain(|_, result| !blocklist.is_blocked(&result.url));
+        }
+        Ok(())
+    }
+}
+
+/// The built-in filter which rewrites the links of the results to the frontends provided via the
+/// `url_rewrites` config option.
+struct RewriteFilter<'a> {
+    /// It stores the hosts of the sites along with the urls of their frontends.
+    rewrites: &'a HashMap<String, String>,
+}
+
+impl ResultFilter for RewriteFilter<'_> {
+    fn filter(
+        &self,
+        _context: &FilterContext<'_>,
+        results: &mut HashMap<String, SearchResult>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for result in results.values_mut() {
+            result.url = rewrite_url(&result.url, self.rewrites);
+        }
+        Ok(())
+    }
+}
+
+/// A helper function which loads the list of nsfw domains from the nsfw domain list file. An
+/// empty list is returned if the file does not exist or could not be read.
+fn load_nsfw_domains() -> HashSet<String> {
+    let Some(contents) = file_path(FileType::NsfwDomainList)
+        .ok()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+    else {
+        tracing::error!("The nsfw domain list could not be read, the results will not be filtered");
+        return HashSet::new();
+    };
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// A helper function which checks whether the provided url belongs to one of the provided nsfw
+/// domains or to one of their subdomains.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+/// * `nsfw_domains` - It takes the list of nsfw domains as an argument.
+fn is_nsfw_domain(url: &str, nsfw_domains: &HashSet<String>) -> bool {
+    let Some(host) = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+    else {
+        return false;
+    };
+
+    let mut domain = host.as_str();
+    loop {
+        if nsfw_domains.contains(domain) {
+            return true;
+        }
+        match domain.split_once('.') {
+            Some((_, parent_domain)) => domain = parent_domain,
+            None => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(urls: &[(&str, &str)]) -> HashMap<String, SearchResult> {
+        urls.iter()
+            .map(|(url, engine)| (url.to_string(), SearchResult::new("", url, "", &[*engine])))
+            .collect()
+    }
+
+    fn urls(results: &HashMap<String, SearchResult>) -> Vec<&str> {
+        let mut urls: Vec<&str> = results.values().map(|result| result.url.as_str()).collect();
+        urls.sort_unstable();
+        urls
+    }
+
+    #[test]
+    fn test_is_nsfw_domain() {
+        let nsfw_domains = HashSet::from(["nsfw.example".to_owned()]);
+
+        assert!(is_nsfw_domain("https://nsfw.example/page", &nsfw_domains));
+        assert!(is_nsfw_domain("https://www.NSFW.example", &nsfw_domains));
+        assert!(!is_nsfw_domain("https://example.com", &nsfw_domains));
+        assert!(!is_nsfw_domain("https://notnsfw.example", &nsfw_domains));
+    }
+
+    #[test]
+    fn test_nsfw_filter() {
+        let filter = SafeSearchFilter {
+            nsfw_domains: Some(Arc::new(HashSet::from(["nsfw.example".to_owned()]))),
+        };
+        let options = SearchOptions {
+            safe_search: 1,
+            ..Default::default()
+        };
+        let context = FilterContext {
+            query: "query",
+            options: &options,
+            safe_search_engines: &["brave"],
+        };
+
+        let mut results = results(&[
+            ("https://nsfw.example/a", "bing"),
+            ("https://nsfw.example/b", "brave"),
+            ("https://example.com/", "bing"),
+        ]);
+        filter.filter(&context, &mut results).unwrap();
+        // The results of the engines filtering them based on safe search are trusted.
+        assert_eq!(
+            urls(&results),
+            ["https://example.com/", "https://nsfw.example/b"]
+        );
+    }
+
+    #[test]
+    fn test_filter_results() {
+        let config = Config {
+            blocked_domains: vec!["blocked.example".to_owned()],
+            url_rewrites: HashMap::from([(
+                "youtube.com".to_owned(),
+                "https://yewtu.be".to_owned(),
+            )]),
+            ..Default::default()
+        };
+        let options = SearchOptions::default();
+        let context = FilterContext {
+            query: "query",
+            options: &options,
+            safe_search_engines: &[],
+        };
+        let mut search_results = results(&[
+            ("https://www.youtube.com/watch?v=abc&si=xyz", "bing"),
+            ("https://blocked.example/", "bing"),
+        ]);
+
+        filter_results(&config, &context, &mut search_results).unwrap();
+        assert_eq!(urls(&search_results), ["https://yewtu.be/watch?v=abc"]);
+
+        // The filters left out of the config option are not run.
+        let config = Config {
+            result_filters: vec!["rewrites".to_owned()],
+            ..config
+        };
+        let mut search_results = results(&[
+            ("https://www.youtube.com/watch?v=abc&si=xyz", "bing"),
+            ("https://blocked.example/", "bing"),
+        ]);
+        filter_results(&config, &context, &mut search_results).unwrap();
+        assert_eq!(
+            urls(&search_results),
+            [
+                "https://blocked.example/",
+                "https://yewtu.be/watch?v=abc&si=xyz"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_safe_search_cannot_be_turned_off() {
+        // The safe search is enforced even when none of the built-in filters are run.
+        let config = Config {
+            result_filters: Vec::new(),
+            ..Default::default()
+        };
+        let options = SearchOptions {
+            safe_search: 1,
+            ..Default::default()
+        };
+        let context = FilterContext {
+            query: "query",
+            options: &options,
+            safe_search_engines: &[],
+        };
+        let mut search_results = results(&[
+            ("https://www.pornhub.com/", "bing"),
+            ("https://example.com/", "bing"),
+        ]);
+
+        filter_results(&config, &context, &mut search_results).unwrap();
+        assert_eq!(urls(&search_results), ["https://example.com/"]);
+    }
+}
@@ -0,0 +1,283 @@
+//! This module provides the background health monitor of the upstream search engines, which
+//! periodically sends a canary query to each enabled engine and records whether its results could
+//! be parsed, so that the engines which stopped answering or whose markup changed are noticed
+//! before the users do.
+
+use super::{
+    aggregator::engine_client,
+    engine_stats::{EngineStats, EngineStatus, ENGINE_STATS},
+    user_agent::random_user_agent,
+};
+use crate::{
+    config::{parser::Config, reloader::SharedConfig},
+    engines::{block_backoff, circuit_breaker, rate_limit::reserve_request},
+    models::{
+        aggregation_models::EngineErrorInfo,
+        engine_models::{EngineError, EngineHandler, SearchOptions},
+    },
+};
+use error_stack::Report;
+use reqwest::Client;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// The shortest interval (in seconds) at which the canary queries can be sent.
+pub const MIN_HEALTH_CHECK_INTERVAL: u64 = 60;
+
+/// A static variable which stores the last health check of each upstream search engine.
+pub static HEALTH_CHECKS: HealthCheckRegistry = HealthCheckRegistry::new();
+
+/// A named struct which stores the outcome of a canary query sent to an upstream search engine.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct HealthCheck {
+    /// It stores the time (in seconds since the unix epoch) at which the canary query was sent.
+    pub at: u64,
+    /// It stores the time (in milliseconds) taken by the engine to answer (or to time out).
+    pub latency_ms: u64,
+    /// It stores the number of the results parsed from the response of the engine.
+    pub results: usize,
+    /// It stores the kind of the error returned by the engine (like `TimedOut` or
+    /// `EmptyResultSet`) or `None` if the results of the engine could be parsed.
+    pub error: Option<String>,
+}
+
+impl HealthCheck {
+    /// A function which checks whether the engine answered the canary query with results.
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// A named struct which stores the last health check of all the upstream search engines.
+pub struct HealthCheckRegistry {
+    /// It stores the last health check of each engine keyed by the name of the engine.
+    engines: Mutex<BTreeMap<String, HealthCheck>>,
+}
+
+impl HealthCheckRegistry {
+    /// Constructs a new registry without any health check.
+    pub const fn new() -> Self {
+        HealthCheckRegistry {
+            engines: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// A function which records the health check of an upstream search engine, replacing its
+    /// previous health check.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the engine as an argument.
+    /// * `check` - It takes the outcome of the canary query as an argument.
+    pub fn record(&self, engine: &str, check: HealthCheck) {
+        self.engines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(engine.to_owned(), check);
+    }
+
+    /// A function which returns the last health check of an upstream search engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the engine as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the last health check of the engine or `None` if the engine was not checked
+    /// since the start of the server.
+    pub fn engine(&self, engine: &str) -> Option<HealthCheck> {
+        self.engines
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(engine)
+            .cloned()
+    }
+}
+
+impl Default for HealthCheckRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A function which returns the health of an upstream search engine derived from its recent
+/// responses and from its last health check, the engines which failed their last health check
+/// are shown at least as degraded as their markup may have changed.
+///
+/// # Arguments
+///
+/// * `stats` - It takes the statistics of the engine (if any) as an argument.
+/// * `check` - It takes the last health check of the engine (if any) as an argument.
+pub fn engine_status(stats: Option<&EngineStats>, check: Option<&HealthCheck>) -> EngineStatus {
+    let status = stats.map_or(EngineStatus::Unknown, EngineStats::status);
+    match (status, check) {
+        (EngineStatus::Up | EngineStatus::Unknown, Some(check)) if !check.passed() => {
+            EngineStatus::Degraded
+        }
+        _ => status,
+    }
+}
+
+/// A function which runs forever and sends the canary query provided via the `health_monitor`
+/// config option to each enabled upstream search engine once the `health_monitor.interval` has
+/// elapsed since the last checks. The outcome of the checks is recorded in the statistics of the
+/// engines (shown on the engine status page), in their circuit breakers and in their backoff,
+/// unlike the searches
+/// the engines answering without any result fail the check as the canary query should always be
+/// answered. The options are picked up again whenever the config is reloaded.
+///
+/// # Arguments
+///
+/// * `shared_config` - It takes the shared config as an argument.
+/// * `client` - It takes the shared client through which the requests to the upstream search
+///   engines are sent as an argument.
+pub async fn monitor_engines(shared_config: Arc<SharedConfig>, client: Client) {
+    let mut interval = tokio::time::interval(Duration::from_secs(MIN_HEALTH_CHECK_INTERVAL));
+    let mut checked_at: Option<Instant> = None;
+
+    loop {
+        interval.tick().await;
+
+        let config = shared_config.load();
+        if !config.health_monitor.enabled
+            || checked_at.is_some_and(|checked_at| {
+                checked_at.elapsed() < Duration::from_secs(config.health_monitor.interval)
+            })
+        {
+            continue;
+        }
+        checked_at = Some(Instant::now());
+
+        let engines = config
+            .upstream_search_engines
+            .iter()
+            .filter(|(_, engine_config)| engine_config.enabled)
+            .map(|(engine, _)| check_engine(engine, &config, &client));
+        futures::future::join_all(engines).await;
+    }
+}
+
+/// A helper function which sends the canary query to an upstream search engine and records the
+/// outcome of the check.
+///
+/// # Arguments
+///
+/// * `engine` - It takes the name of the engine as provided via the config file as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `client` - It takes the shared client as an argument.
+async fn check_engine(engine: &str, config: &Config, client: &Client) {
+    let Ok(engine_handler) = EngineHandler::new(engine) else {
+        return;
+    };
+    let (name, search_engine) = engine_handler.into_name_engine();
+    let engine_config = config
+        .upstream_search_engines
+        .get(engine)
+        .cloned()
+        .unwrap_or_default();
+
+    // The engines cooling down after they blocked the instance are not checked, so that their
+    // block is not prolonged.
+    if block_backoff::blocked_for(name).is_some() {
+        return;
+    }
+
+    // The canary queries take their turn like the searches, so that they never get the engine
+    // rate limited.
+    if let Some(requests_per_second) = engine_config.requests_per_second {
+        match reserve_request(name, requests_per_second) {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => return,
+        }
+    }
+
+    let client = match engine_client(&engine_config, config, client) {
+        Ok(client) => client,
+        Err(error) => {
+            tracing::error!("Unable to check the health of the {name} engine: {error}");
+            return;
+        }
+    };
+    let request_timeout =
+        Duration::from_secs(engine_config.timeout.unwrap_or(config.request_timeout) as u64);
+    let user_agent = random_user_agent(&config.user_agents);
+
+    let requested_at = Instant::now();
+    let response = tokio::time::timeout(
+        request_timeout,
+        search_engine.fetch_results(
+            &config.health_monitor.query,
+            1,
+            &user_agent,
+            &client,
+            &SearchOptions::default(),
+            &engine_config,
+        ),
+    )
+    .await
+    .unwrap_or_else(|_| Err(Report::new(EngineError::TimedOut)));
+    let latency = requested_at.elapsed();
+
+    let error = response.as_ref().err().map(|error| error.current_context());
+    ENGINE_STATS.record(name, latency, error);
+    circuit_breaker::record_response(name, error, config.aggregator.circuit_breaker_threshold);
+    block_backoff::record_response(
+        name,
+        error,
+        Duration::from_secs(config.aggregator.block_cooldown),
+        Duration::from_secs(config.aggregator.max_block_cooldown),
+    );
+
+    let check = HealthCheck {
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_secs()),
+        latency_ms: latency.as_millis() as u64,
+        results: response.as_ref().map_or(0, |results| results.results.len()),
+        error: error.map(|error| EngineErrorInfo::new(error, name).error),
+    };
+    if !check.passed() {
+        tracing::warn!(
+            engine = name,
+            error = check.error.as_deref(),
+            "the engine failed its health check"
+        );
+    }
+    HEALTH_CHECKS.record(name, check);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_checks() {
+        let registry = HealthCheckRegistry::new();
+        let check = |error: Option<&str>| HealthCheck {
+            at: 1,
+            latency_ms: 100,
+            results: 0,
+            error: error.map(str::to_owned),
+        };
+        registry.record("bing", check(Some("EmptyResultSet")));
+        assert!(!registry.engine("bing").unwrap().passed());
+
+        assert_eq!(
+            engine_status(None, registry.engine("bing").as_ref()),
+            EngineStatus::Degraded
+        );
+
+        registry.record("bing", check(None));
+        assert!(registry.engine("bing").unwrap().passed());
+        assert_eq!(
+            engine_status(None, registry.engine("bing").as_ref()),
+            EngineStatus::Unknown
+        );
+        assert!(registry.engine("brave").is_none());
+    }
+}
@@ -0,0 +1,92 @@
+//! This module provides the functionality to highlight the search terms in the sanitized
+//! descriptions of the search results, so that the users can scan the results quickly.
+
+use crate::query::operators::ParsedQuery;
+use regex::{Captures, Regex, RegexBuilder};
+
+/// The minimum number of characters a search term should have to be highlighted.
+const MIN_TERM_LENGTH: usize = 2;
+
+/// A named struct which stores the compiled regex matching the search terms to be highlighted.
+pub struct Highlighter {
+    /// It stores the regex which matches the html tags and entities (which are kept as they are)
+    /// or the search terms.
+    regex: Regex,
+}
+
+impl Highlighter {
+    /// A function which builds the highlighter for the plain search terms of the provided search
+    /// query (the advanced query operators are not highlighted). Returns `None` if the query has
+    /// no terms to be highlighted.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query provided by the user as an argument.
+    pub fn new(query: &str) -> Option<Self> {
+        let plain_query = ParsedQuery::parse(query).to_plain_query();
+        let mut terms: Vec<&str> = plain_query
+            .split_whitespace()
+            .map(|term| term.trim_matches(|character: char| !character.is_alphanumeric()))
+            .filter(|term| term.chars().count() >= MIN_TERM_LENGTH)
+            .collect();
+        if terms.is_empty() {
+            return None;
+        }
+        // The longer terms are matched first so that a term is not cut by its own prefix.
+        terms.sort_by_key(|term| std::cmp::Reverse(term.len()));
+        terms.dedup();
+
+        let pattern = format!(
+            "(<[^>]*>|&[#a-zA-Z0-9]+;)|({})",
+            terms
+                .iter()
+                .map(|term| regex::escape(term))
+                .collect::<Vec<String>>()
+                .join("|")
+        );
+        // The case insensitive matching of the non-ascii terms needs the unicode tables of the
+        // regex crate, the terms are matched case sensitively if these are unavailable.
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .build()
+            .or_else(|_| Regex::new(&pattern))
+            .ok()?;
+
+        Some(Highlighter { regex })
+    }
+
+    /// A function which wraps the search terms found in the text of the provided sanitized html
+    /// description in `<em>` tags. The html tags and entities are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `description` - It takes the sanitized description of the search result as an argument.
+    pub fn highlight(&self, description: &str) -> String {
+        self.regex
+            .replace_all(description, |captures: &Captures<'_>| {
+                match captures.get(1) {
+                    Some(markup) => markup.as_str().to_owned(),
+                    None => format!("<em>{}</em>", &captures[2]),
+                }
+            })
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        let highlighter = Highlighter::new(r#"rust "memory safety" site:example.com a"#).unwrap();
+
+        assert_eq!(
+            highlighter.highlight(
+                r#"<b>Rust</b> guarantees memory safety &amp; helps <a href="rust">rustaceans</a>"#
+            ),
+            r#"<b><em>Rust</em></b> guarantees <em>memory</em> <em>safety</em> &amp; helps <a href="rust"><em>rust</em>aceans</a>"#
+        );
+        assert!(Highlighter::new("a site:example.com").is_none());
+    }
+}
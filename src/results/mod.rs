@@ -3,4 +3,18 @@
 //! provides various models to aggregate search results into a standardized form.
 
 pub mod aggregator;
-mod user_agent;
+pub mod amp;
+pub mod blocklists;
+pub mod domain_filter;
+pub mod engine_selection;
+pub mod engine_stats;
+pub mod filters;
+pub mod health_monitor;
+pub mod highlight;
+pub mod rerank;
+pub mod resolver;
+pub mod rewrites;
+pub mod sanitize;
+pub mod tor;
+pub mod urls;
+pub mod user_agent;
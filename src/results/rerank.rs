@@ -0,0 +1,119 @@
+//! This module provides the hook to re-rank the aggregated search results with a chain of custom
+//! scorers, so that the ranking can be tuned (for example by boosting the non-commercial domains
+//! or by penalizing the paywalled domains) without changing the aggregator.
+
+use super::domain_filter::{domain_pattern, url_host};
+use crate::models::aggregation_models::SearchResult;
+use regex::Regex;
+use std::{collections::HashMap, sync::OnceLock};
+
+/// The custom rerankers registered when the server is started.
+static RERANKERS: OnceLock<Vec<Box<dyn Reranker>>> = OnceLock::new();
+
+/// A trait for the scorers which re-rank the aggregated search results. The rerankers are called
+/// in a chain after the results are sorted by their score and before they are paginated, every
+/// reranker can reorder, drop or annotate the results and the order left by the last reranker is
+/// the final order of the results.
+pub trait Reranker: Send + Sync {
+    /// A function which re-ranks the provided search results.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - It takes the search query provided by the user as an argument.
+    /// * `results` - It takes the aggregated search results sorted by their score as an argument.
+    fn rerank(&self, query: &str, results: &mut Vec<SearchResult>);
+}
+
+/// A function which registers the custom rerankers which are called (in the provided order) on
+/// every search after the rerankers configured via the config file. It should be called once
+/// before the server is started.
+///
+/// # Arguments
+///
+/// * `rerankers` - It takes the custom rerankers as an argument.
+///
+/// # Error
+///
+/// Returns the provided rerankers back if the rerankers have already been registered.
+pub fn register_rerankers(rerankers: Vec<Box<dyn Reranker>>) -> Result<(), Vec<Box<dyn Reranker>>> {
+    RERANKERS.set(rerankers)
+}
+
+/// A function which returns the custom rerankers registered when the server was started.
+pub fn registered_rerankers() -> &'static [Box<dyn Reranker>] {
+    RERANKERS.get().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// A named struct which stores the compiled domain patterns along with the factors by which the
+/// scores of the results from the matching domains are multiplied.
+#[derive(Default)]
+pub struct DomainWeights {
+    /// It stores the compiled domain patterns along with their factors.
+    weights: Vec<(Regex, f32)>,
+}
+
+impl DomainWeights {
+    /// A function which compiles the domain weights provided via the config file. The invalid
+    /// patterns are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `weights` - It takes the domain patterns along with their factors as an argument.
+    pub fn new(weights: &HashMap<String, f32>) -> Self {
+        let weights = weights
+            .iter()
+            .filter_map(|(pattern, weight)| match domain_pattern(pattern) {
+                Ok(regex) => Some((regex, *weight)),
+                Err(error) => {
+                    tracing::warn!("Skipping the invalid domain pattern `{pattern}`: {error}");
+                    None
+                }
+            })
+            .collect();
+
+        DomainWeights { weights }
+    }
+}
+
+impl Reranker for DomainWeights {
+    fn rerank(&self, _query: &str, results: &mut Vec<SearchResult>) {
+        if self.weights.is_empty() {
+            return;
+        }
+
+        for result in results.iter_mut() {
+            let Some(host) = url_host(&result.url) else {
+                continue;
+            };
+            for (pattern, weight) in &self.weights {
+                if pattern.is_match(&host) {
+                    result.score *= weight;
+                }
+            }
+        }
+        results.sort_by(|result_a, result_b| result_b.score.total_cmp(&result_a.score));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_weights() {
+        let mut results = vec![
+            SearchResult::new("Paywalled", "https://news.example.com/a", "", &["bing"]),
+            SearchResult::new("Wiki", "https://www.wikipedia.org/wiki/A", "", &["bing"]),
+            SearchResult::new("Shop", "https://shop.example.net/a", "", &["bing"]),
+        ];
+        for (index, result) in results.iter_mut().enumerate() {
+            result.score = 3.0 - index as f32;
+        }
+
+        let weights = HashMap::from([("example.com".to_owned(), 0.1), ("*.org".to_owned(), 1.5)]);
+        DomainWeights::new(&weights).rerank("a", &mut results);
+
+        let titles: Vec<&str> = results.iter().map(|result| result.title.as_str()).collect();
+        assert_eq!(titles, ["Wiki", "Shop", "Paywalled"]);
+    }
+}
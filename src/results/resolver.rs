@@ -0,0 +1,199 @@
+//! This module provides a resolver which resolves the domain names of the upstream search engines
+//! through a DNS-over-HTTPS server, so that the resolver of the host (usually the one of the ISP)
+//! never sees which upstream search engines are contacted by the instance.
+
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    header::ACCEPT,
+    Client,
+};
+use serde::Deserialize;
+use std::{
+    error::Error,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+/// The DNS record types of the IPv4 (`A`) and the IPv6 (`AAAA`) addresses.
+const ADDRESS_RECORD_TYPES: [u16; 2] = [1, 28];
+
+/// The timeout of the queries sent to the DNS-over-HTTPS server.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A named struct which deserializes the JSON responses of the DNS-over-HTTPS servers.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DnsResponse {
+    /// It stores the response code of the query (`0` if the query succeeded).
+    status: u16,
+    /// It stores the records answering the query.
+    #[serde(default)]
+    answer: Vec<DnsAnswer>,
+}
+
+/// A named struct which deserializes a record of the JSON responses of the DNS-over-HTTPS
+/// servers.
+#[derive(Deserialize)]
+struct DnsAnswer {
+    /// It stores the type of the record.
+    #[serde(rename = "type")]
+    record_type: u16,
+    /// It stores the data of the record (the ip address for the address records).
+    data: String,
+}
+
+/// A resolver which queries the configured DNS-over-HTTPS server with the JSON API (supported by
+/// servers like `https://cloudflare-dns.com/dns-query` or `https://dns.google/resolve`).
+#[derive(Clone)]
+pub struct DohResolver {
+    /// It stores the url of the DNS-over-HTTPS server.
+    url: String,
+    /// It stores the client through which the queries are sent. The domain name of the
+    /// DNS-over-HTTPS server itself is resolved by the resolver of the host.
+    client: Client,
+}
+
+impl DohResolver {
+    /// Creates a resolver which queries the provided DNS-over-HTTPS server.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - It takes the url of the DNS-over-HTTPS server as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the client could not be built.
+    pub fn new(url: &str) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            url: url.to_owned(),
+            client: Client::builder()
+                .https_only(true)
+                .timeout(LOOKUP_TIMEOUT)
+                .build()?,
+        })
+    }
+
+    /// A helper function which queries the records of the provided type for the provided domain
+    /// name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - It takes the domain name to be resolved as an argument.
+    /// * `record_type` - It takes the type of the queried records as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an error if the query fails or if the response of the server is invalid.
+    async fn lookup(
+        &self,
+        name: &str,
+        record_type: u16,
+    ) -> Result<Vec<IpAddr>, Box<dyn Error + Send + Sync>> {
+        let body = self
+            .client
+            .get(&self.url)
+            .query(&[("name", name), ("type", &record_type.to_string())])
+            .header(ACCEPT, "application/dns-json")
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        parse_addresses(&body, record_type)
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let (ipv4, ipv6) = futures::join!(
+                resolver.lookup(name.as_str(), ADDRESS_RECORD_TYPES[0]),
+                resolver.lookup(name.as_str(), ADDRESS_RECORD_TYPES[1])
+            );
+
+            // A failed IPv6 query is ignored as long as the IPv4 addresses were resolved.
+            let addresses: Vec<IpAddr> = match (ipv4, ipv6) {
+                (Err(error), Err(_)) => return Err(error),
+                (ipv4, ipv6) => ipv4
+                    .unwrap_or_default()
+                    .into_iter()
+                    .chain(ipv6.unwrap_or_default())
+                    .collect(),
+            };
+            if addresses.is_empty() {
+                return Err(format!("no addresses were found for `{}`", name.as_str()).into());
+            }
+
+            let addrs: Addrs = Box::new(
+                addresses
+                    .into_iter()
+                    .map(|address| SocketAddr::new(address, 0)),
+            );
+            Ok(addrs)
+        })
+    }
+}
+
+/// A helper function which parses the addresses of the provided type from the JSON response of a
+/// DNS-over-HTTPS server. The other records (like the `CNAME` records leading to the addresses)
+/// are skipped.
+///
+/// # Arguments
+///
+/// * `body` - It takes the body of the response as an argument.
+/// * `record_type` - It takes the type of the queried records as an argument.
+///
+/// # Error
+///
+/// Returns an error if the response is invalid or if the query failed.
+fn parse_addresses(
+    body: &[u8],
+    record_type: u16,
+) -> Result<Vec<IpAddr>, Box<dyn Error + Send + Sync>> {
+    let response: DnsResponse = serde_json::from_slice(body)?;
+    if response.status != 0 {
+        return Err(format!(
+            "the query failed with the response code {}",
+            response.status
+        )
+        .into());
+    }
+
+    Ok(response
+        .answer
+        .into_iter()
+        .filter(|answer| answer.record_type == record_type)
+        .filter_map(|answer| answer.data.parse().ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addresses() {
+        let body = br#"{
+            "Status": 0,
+            "Answer": [
+                {"name": "www.bing.com", "type": 5, "TTL": 300, "data": "www-bing-com.a-0001.a-msedge.net."},
+                {"name": "www-bing-com.a-0001.a-msedge.net", "type": 1, "TTL": 60, "data": "13.107.21.200"},
+                {"name": "www-bing-com.a-0001.a-msedge.net", "type": 1, "TTL": 60, "data": "204.79.197.200"}
+            ]
+        }"#;
+
+        assert_eq!(
+            parse_addresses(body, 1).unwrap(),
+            vec![
+                "13.107.21.200".parse::<IpAddr>().unwrap(),
+                "204.79.197.200".parse::<IpAddr>().unwrap()
+            ]
+        );
+        assert!(parse_addresses(body, 28).unwrap().is_empty());
+        assert!(parse_addresses(br#"{"Status": 3}"#, 1).is_err());
+        assert!(parse_addresses(b"not json", 1).is_err());
+    }
+}
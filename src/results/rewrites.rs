@@ -0,0 +1,76 @@
+//! This module provides the functionality to rewrite the links of the search results pointing to
+//! the configured sites (like `youtube.com` or `reddit.com`) to the privacy respecting frontends of
+//! these sites.
+
+use reqwest::Url;
+use std::collections::HashMap;
+
+/// A function which rewrites the provided url to the frontend configured for its host (or for one
+/// of its parent domains, so that `m.youtube.com` is rewritten by the rule for `youtube.com`). The
+/// path, the query and the fragment of the url are kept and appended to the url of the frontend.
+/// The url is returned as it is if it could not be parsed or no rule matches its host.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+/// * `rewrites` - It takes the hosts along with the urls of the frontends to which the links
+///   should be rewritten as an argument.
+pub fn rewrite_url(url: &str, rewrites: &HashMap<String, String>) -> String {
+    let Ok(parsed_url) = Url::parse(url.trim()) else {
+        return url.to_owned();
+    };
+    let Some(host) = parsed_url.host_str() else {
+        return url.to_owned();
+    };
+
+    let frontend = std::iter::successors(Some(host), |domain| {
+        domain.split_once('.').map(|(_, parent)| parent)
+    })
+    .find_map(|domain| rewrites.get(domain));
+    let Some(frontend) = frontend else {
+        return url.to_owned();
+    };
+
+    let mut rewritten_url = frontend.trim_end_matches('/').to_owned();
+    rewritten_url.push_str(parsed_url.path());
+    if let Some(query) = parsed_url.query() {
+        rewritten_url.push('?');
+        rewritten_url.push_str(query);
+    }
+    if let Some(fragment) = parsed_url.fragment() {
+        rewritten_url.push('#');
+        rewritten_url.push_str(fragment);
+    }
+
+    rewritten_url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_url() {
+        let rewrites = HashMap::from([
+            ("youtube.com".to_owned(), "https://yewtu.be".to_owned()),
+            (
+                "reddit.com".to_owned(),
+                "https://redlib.example.com/".to_owned(),
+            ),
+        ]);
+
+        assert_eq!(
+            rewrite_url("https://www.youtube.com/watch?v=abc#t=10", &rewrites),
+            "https://yewtu.be/watch?v=abc#t=10"
+        );
+        assert_eq!(
+            rewrite_url("https://old.reddit.com/r/rust/", &rewrites),
+            "https://redlib.example.com/r/rust/"
+        );
+        assert_eq!(
+            rewrite_url("https://notyoutube.com/watch", &rewrites),
+            "https://notyoutube.com/watch"
+        );
+        assert_eq!(rewrite_url("not a url", &rewrites), "not a url");
+    }
+}
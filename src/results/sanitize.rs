@@ -0,0 +1,69 @@
+//! This module provides the functionality to sanitize the titles and the descriptions of the
+//! search results scraped from the upstream search engines, so that a malicious snippet cannot
+//! inject markup or scripts into the search page.
+
+use ammonia::Builder;
+use scraper::Html;
+use std::{collections::HashSet, sync::OnceLock};
+
+/// The inline formatting tags which are kept in the descriptions of the search results (the
+/// upstream engines use these to highlight the query terms).
+const ALLOWED_TAGS: &[&str] = &["b", "strong", "em", "i", "mark", "br"];
+
+/// A constant for holding the prebuilt sanitizer globally in the app.
+static SANITIZER: OnceLock<Builder<'static>> = OnceLock::new();
+
+/// A function which sanitizes the provided html snippet so that only the inline formatting tags
+/// (without any attributes) are kept, the contents of the `script` and `style` tags are dropped
+/// and the other tags are removed while keeping their text.
+///
+/// # Arguments
+///
+/// * `html` - It takes the html snippet scraped from the upstream search engine as an argument.
+pub fn sanitize_html(html: &str) -> String {
+    SANITIZER
+        .get_or_init(|| {
+            let mut sanitizer = Builder::empty();
+            sanitizer
+                .add_tags(ALLOWED_TAGS)
+                .clean_content_tags(HashSet::from(["script", "style"]));
+            sanitizer
+        })
+        .clean(html)
+        .to_string()
+}
+
+/// A function which converts the provided html snippet into plain text by removing all the tags
+/// and decoding the html entities. The returned text should be escaped before it is rendered.
+///
+/// # Arguments
+///
+/// * `html` - It takes the html snippet scraped from the upstream search engine as an argument.
+pub fn html_to_text(html: &str) -> String {
+    Html::parse_fragment(&sanitize_html(html))
+        .root_element()
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_html() {
+        assert_eq!(
+            sanitize_html(
+                r#"<b>Rust</b> <a href="javascript:alert(1)" onclick="x()">is</a> <em class="x">fast</em><script>alert(1)</script>"#
+            ),
+            "<b>Rust</b> is <em>fast</em>"
+        );
+        assert_eq!(sanitize_html("1 < 2 &amp; 3"), "1 &lt; 2 &amp; 3");
+        assert_eq!(
+            html_to_text("<strong>Rust</strong> &amp; <img src=x onerror=alert(1)>Cargo"),
+            "Rust & Cargo"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! This module provides the functionality to route the requests to the upstream search engines
+//! through the Tor network with separate circuits for every search, and to request new circuits
+//! through the control port of the Tor daemon when the engines block the requests repeatedly.
+
+use crate::models::parser_models::TorConfig;
+use reqwest::Url;
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The number of isolation slots (sets of socks credentials) the searches are spread over. Every
+/// slot is served by its own cached client, so that the connection pools are reused across the
+/// searches while the number of clients stays bounded.
+pub const ISOLATION_SLOTS: u64 = 32;
+
+/// The timeout of the connections to the control port of the Tor daemon.
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The number of consecutive requests which were blocked by the upstream search engines.
+static CONSECUTIVE_BLOCKS: AtomicU32 = AtomicU32::new(0);
+
+/// A counter which assigns the isolation slots to the searches in turn.
+static ISOLATION_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A random salt generated on startup which makes the socks credentials of the isolation slots
+/// differ between the runs of the server.
+static ISOLATION_SALT: OnceLock<String> = OnceLock::new();
+
+/// A function which builds the url of the socks port of the Tor daemon with the credentials of
+/// the next isolation slot. The Tor daemon isolates the streams opened with different socks
+/// credentials on separate circuits, so the concurrent searches are sent through different
+/// circuits and cannot be linked with each other.
+///
+/// # Arguments
+///
+/// * `socks_url` - It takes the url of the socks port of the Tor daemon as an argument.
+///
+/// # Error
+///
+/// Returns an error if the url of the socks port is invalid.
+pub fn isolated_socks_url(socks_url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let salt = ISOLATION_SALT.get_or_init(|| {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos());
+        format!("{nanos}:{}", std::process::id())
+    });
+    let slot = ISOLATION_COUNTER.fetch_add(1, Ordering::Relaxed) % ISOLATION_SLOTS;
+    let token = blake3::hash(format!("{salt}:{slot}").as_bytes()).to_hex();
+
+    let mut url = Url::parse(socks_url)?;
+    url.set_username("websurfx")
+        .and_then(|_| url.set_password(Some(&token[..32])))
+        .map_err(|_| "the socks url cannot have credentials")?;
+    Ok(url.into())
+}
+
+/// A function which records whether the upstream search engine blocked the request sent through
+/// Tor. New circuits are requested through the control port (if it is configured) once the
+/// configured number of consecutive requests have been blocked.
+///
+/// # Arguments
+///
+/// * `tor` - It takes the Tor config options as an argument.
+/// * `blocked` - It takes a boolean which indicates whether the request was blocked as an
+///   argument.
+pub fn report_response(tor: &TorConfig, blocked: bool) {
+    let Some(blocks) = record_response(&CONSECUTIVE_BLOCKS, tor.newnym_after_blocks, blocked)
+    else {
+        return;
+    };
+    let Some(control_address) = tor.control_address.clone() else {
+        return;
+    };
+    let control_password = tor.control_password.clone();
+    tokio::task::spawn_blocking(move || {
+        match send_newnym(&control_address, control_password.as_deref()) {
+            Ok(()) => tracing::info!("Requested new Tor circuits after {blocks} blocked requests"),
+            Err(error) => tracing::error!("Unable to request new Tor circuits: {error}"),
+        }
+    });
+}
+
+/// A helper function which updates the provided counter of consecutive blocked requests.
+///
+/// # Arguments
+///
+/// * `counter` - It takes the counter of consecutive blocked requests as an argument.
+/// * `threshold` - It takes the number of consecutive blocked requests after which new circuits
+///   should be requested (`0` never requests them) as an argument.
+/// * `blocked` - It takes a boolean which indicates whether the request was blocked as an
+///   argument.
+///
+/// # Returns
+///
+/// It returns the number of consecutive blocked requests when the threshold has been reached, in
+/// which case the counter is reset, otherwise it returns `None`.
+fn record_response(counter: &AtomicU32, threshold: u32, blocked: bool) -> Option<u32> {
+    if !blocked {
+        counter.store(0, Ordering::Relaxed);
+        return None;
+    }
+
+    let blocks = counter.fetch_add(1, Ordering::Relaxed) + 1;
+    if threshold == 0 || blocks < threshold {
+        return None;
+    }
+    counter.store(0, Ordering::Relaxed);
+    Some(blocks)
+}
+
+/// A helper function which sends the `NEWNYM` signal to the control port of the Tor daemon, so
+/// that the new requests are sent through new circuits.
+///
+/// # Arguments
+///
+/// * `control_address` - It takes the address of the control port as an argument.
+/// * `control_password` - It takes the password of the control port as an argument.
+///
+/// # Error
+///
+/// Returns an error if the control port could not be reached or if it rejected the commands.
+fn send_newnym(control_address: &str, control_password: Option<&str>) -> io::Result<()> {
+    let address = control_address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid control address"))?;
+    let mut stream = TcpStream::connect_timeout(&address, CONTROL_TIMEOUT)?;
+    stream.set_read_timeout(Some(CONTROL_TIMEOUT))?;
+
+    let authenticate = match control_password {
+        Some(password) => format!(
+            "AUTHENTICATE \"{}\"\r\n",
+            password.replace('\\', "\\\\").replace('"', "\\\"")
+        ),
+        None => "AUTHENTICATE\r\n".to_owned(),
+    };
+    stream.write_all(format!("{authenticate}SIGNAL NEWNYM\r\nQUIT\r\n").as_bytes())?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    // Both the `AUTHENTICATE` and the `SIGNAL` commands are answered with `250 OK` on success.
+    match reply
+        .lines()
+        .take(2)
+        .filter(|line| line.starts_with("250"))
+        .count()
+    {
+        2 => Ok(()),
+        _ => Err(io::Error::other(reply.trim().to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashSet, io::BufRead, io::BufReader, net::TcpListener, thread};
+
+    #[test]
+    fn test_isolated_socks_url() {
+        let urls: HashSet<String> = (0..ISOLATION_SLOTS)
+            .map(|_| isolated_socks_url("socks5h://127.0.0.1:9050").unwrap())
+            .collect();
+
+        assert_eq!(urls.len(), ISOLATION_SLOTS as usize);
+        assert!(urls
+            .iter()
+            .all(|url| url.starts_with("socks5h://websurfx:") && url.ends_with("@127.0.0.1:9050")));
+    }
+
+    #[test]
+    fn test_record_response() {
+        let counter = AtomicU32::new(0);
+
+        assert_eq!(record_response(&counter, 3, true), None);
+        assert_eq!(record_response(&counter, 3, true), None);
+        assert_eq!(record_response(&counter, 3, false), None);
+        assert_eq!(record_response(&counter, 3, true), None);
+        assert_eq!(record_response(&counter, 3, true), None);
+        assert_eq!(record_response(&counter, 3, true), Some(3));
+        assert_eq!(counter.load(Ordering::Relaxed), 0);
+
+        assert_eq!(record_response(&counter, 0, true), None);
+        assert_eq!(record_response(&counter, 0, true), None);
+    }
+
+    /// A helper function which starts a fake control port answering the `AUTHENTICATE` command
+    /// with the provided reply and returns its address along with a handle to the commands it
+    /// received.
+    fn fake_control_port(authenticate_reply: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut commands = String::new();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    break;
+                }
+                let reply = match line.split_whitespace().next() {
+                    Some("AUTHENTICATE") => authenticate_reply,
+                    Some("QUIT") => "250 closing connection\r\n",
+                    _ => "250 OK\r\n",
+                };
+                stream.write_all(reply.as_bytes()).unwrap();
+                commands.push_str(&line);
+                if line.starts_with("QUIT") || !reply.starts_with("250") {
+                    break;
+                }
+            }
+            commands
+        });
+
+        (address, handle)
+    }
+
+    #[test]
+    fn test_send_newnym() {
+        let (address, handle) = fake_control_port("250 OK\r\n");
+
+        send_newnym(&address, Some(r#"pass"word"#)).unwrap();
+        assert_eq!(
+            handle.join().unwrap(),
+            "AUTHENTICATE \"pass\\\"word\"\r\nSIGNAL NEWNYM\r\nQUIT\r\n"
+        );
+    }
+
+    #[test]
+    fn test_send_newnym_with_wrong_password() {
+        let (address, handle) = fake_control_port("515 Authentication failed\r\n");
+
+        assert!(send_newnym(&address, Some("wrong")).is_err());
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+//! This module provides the functionality to normalize the urls of the search results, so that the
+//! same page returned by multiple upstream search engines with slightly different urls is
+//! recognized as a single result, and to strip the tracking query parameters (in the style of the
+//! ClearURLs rules) from them.
+
+use reqwest::Url;
+
+/// The query parameters which are only used to track the users and never change the content of
+/// the page.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid",
+    "gclid",
+    "gclsrc",
+    "dclid",
+    "wbraid",
+    "gbraid",
+    "msclkid",
+    "yclid",
+    "ttclid",
+    "twclid",
+    "li_fat_id",
+    "mc_cid",
+    "mc_eid",
+    "igshid",
+    "_ga",
+    "_gl",
+    "_hsenc",
+    "_hsmi",
+    "mkt_tok",
+    "oly_anon_id",
+    "oly_enc_id",
+    "vero_id",
+    "rb_clickid",
+    "s_cid",
+    "ref_src",
+    "spm",
+];
+
+/// The prefixes of the query parameters which are only used to track the users.
+const TRACKING_PARAM_PREFIXES: &[&str] = &["utm_", "pk_", "hsa_", "mtm_"];
+
+/// The query parameters which are only used to track the users on specific sites (and which may
+/// have a meaning on other sites) along with the domains of the sites, the subdomains of the
+/// domains are matched too.
+const SITE_TRACKING_PARAMS: &[(&str, &[&str])] = &[
+    (
+        "amazon.com",
+        &[
+            "ref",
+            "ref_",
+            "pf_rd_p",
+            "pf_rd_r",
+            "pd_rd_w",
+            "pd_rd_wg",
+            "pd_rd_r",
+            "pd_rd_i",
+            "content-id",
+            "crid",
+            "sprefix",
+            "qid",
+            "sr",
+        ],
+    ),
+    ("youtube.com", &["si", "feature", "pp"]),
+    ("youtu.be", &["si", "feature"]),
+    ("twitter.com", &["s", "t", "ref_url"]),
+    ("x.com", &["s", "t", "ref_url"]),
+    ("reddit.com", &["share_id", "ref", "ref_source"]),
+    ("instagram.com", &["igsh"]),
+    ("spotify.com", &["si"]),
+    ("linkedin.com", &["trk", "trackingId", "refId", "lipi"]),
+];
+
+/// A function which returns whether the provided query parameter is only used to track the users.
+///
+/// # Arguments
+///
+/// * `name` - It takes the name of the query parameter as an argument.
+pub fn is_tracking_param(name: &str) -> bool {
+    let name = name.to_lowercase();
+    TRACKING_PARAMS.contains(&name.as_str())
+        || TRACKING_PARAM_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+}
+
+/// A helper function which returns whether the provided query parameter is only used to track the
+/// users on the site with the provided host.
+///
+/// # Arguments
+///
+/// * `host` - It takes the host of the url as an argument.
+/// * `name` - It takes the name of the query parameter as an argument.
+fn is_site_tracking_param(host: &str, name: &str) -> bool {
+    is_tracking_param(name)
+        || SITE_TRACKING_PARAMS.iter().any(|(domain, params)| {
+            (host == *domain || host.ends_with(&format!(".{domain}"))) && params.contains(&name)
+        })
+}
+
+/// A function which removes the tracking query parameters from the provided url, so that
+/// following the search result does not leak the campaign tracking to the site. The url is
+/// returned as it is if it could not be parsed or has no tracking query parameters.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+pub fn strip_tracking_params(url: &str) -> String {
+    let Ok(mut parsed_url) = Url::parse(url.trim()) else {
+        return url.to_owned();
+    };
+    let host = parsed_url.host_str().unwrap_or_default().to_owned();
+
+    let (tracking_params, params): (Vec<_>, Vec<_>) = parsed_url
+        .query_pairs()
+        .into_owned()
+        .partition(|(name, _)| is_site_tracking_param(&host, name));
+    if tracking_params.is_empty() {
+        return url.to_owned();
+    }
+
+    if params.is_empty() {
+        parsed_url.set_query(None);
+    } else {
+        parsed_url.query_pairs_mut().clear().extend_pairs(params);
+    }
+
+    parsed_url.into()
+}
+
+/// A function which builds the canonical form of the provided url which is used as the key to
+/// merge the duplicate results. The scheme, the `www.` prefix, the default port, the fragment, the
+/// trailing slash and the tracking query parameters are removed and the host is lowercased. The
+/// url is returned as it is if it could not be parsed.
+///
+/// # Arguments
+///
+/// * `url` - It takes the url of the search result as an argument.
+pub fn canonical_url(url: &str) -> String {
+    let Ok(parsed_url) = Url::parse(url.trim()) else {
+        return url.to_owned();
+    };
+    let Some(host) = parsed_url.host_str() else {
+        return url.to_owned();
+    };
+
+    let mut canonical_url = host.trim_start_matches("www.").to_owned();
+    // The default port of the scheme is already omitted by the url parser.
+    if let Some(port) = parsed_url.port() {
+        canonical_url.push_str(&format!(":{port}"));
+    }
+    canonical_url.push_str(parsed_url.path().trim_end_matches('/'));
+
+    let query: Vec<String> = parsed_url
+        .query_pairs()
+        .filter(|(name, _)| !is_site_tracking_param(host, name))
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect();
+    if !query.is_empty() {
+        canonical_url.push('?');
+        canonical_url.push_str(&query.join("&"));
+    }
+
+    canonical_url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_url() {
+        let canonical = "example.com/page?id=1";
+
+        assert_eq!(canonical_url("https://example.com/page?id=1"), canonical);
+        assert_eq!(
+            canonical_url("http://www.Example.com:80/page/?id=1"),
+            canonical
+        );
+        assert_eq!(
+            canonical_url("https://example.com:443/page?utm_source=feed&id=1&fbclid=abc#top"),
+            canonical
+        );
+        assert_eq!(
+            canonical_url("https://example.com:8443/page"),
+            "example.com:8443/page"
+        );
+        assert_eq!(canonical_url("not a url"), "not a url");
+    }
+
+    #[test]
+    fn test_strip_tracking_params() {
+        assert_eq!(
+            strip_tracking_params("https://example.com/page?utm_source=feed&id=1&fbclid=abc#top"),
+            "https://example.com/page?id=1#top"
+        );
+        assert_eq!(
+            strip_tracking_params("https://www.youtube.com/watch?v=abc&si=xyz"),
+            "https://www.youtube.com/watch?v=abc"
+        );
+        assert_eq!(
+            strip_tracking_params("https://example.com/page?gclid=abc"),
+            "https://example.com/page"
+        );
+        // The site specific parameters are kept on the other sites.
+        assert_eq!(
+            strip_tracking_params("https://example.com/watch?v=abc&si=xyz"),
+            "https://example.com/watch?v=abc&si=xyz"
+        );
+        assert_eq!(strip_tracking_params("not a url"), "not a url");
+    }
+}
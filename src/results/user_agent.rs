@@ -1,19 +1,47 @@
-//! This module provides the functionality to generate random user agent string.
+//! This module provides the functionality to generate random user agent string and to randomize
+//! the other request headers sent to the upstream search engines, so that the requests of the
+//! instance are harder to fingerprint.
 
 use std::sync::OnceLock;
 
 use fake_useragent::{Browsers, UserAgents, UserAgentsBuilder};
+use rand::seq::SliceRandom;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE};
 
 /// A static variable which stores the initially build `UserAgents` struct. So as it can be resused
 /// again and again without the need of reinitializing the `UserAgents` struct.
 static USER_AGENTS: OnceLock<UserAgents> = OnceLock::new();
 
+/// The values of the `Accept` header sent by the common browsers.
+const ACCEPT_HEADERS: [&str; 3] = [
+    "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+    "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8",
+    "text/html,application/xhtml+xml,application/xml;q=0.9,image/webp,image/apng,*/*;q=0.8",
+];
+
+/// The values of the `Accept-Language` header sent when no language has been selected.
+const ACCEPT_LANGUAGE_HEADERS: [&str; 4] = [
+    "en-US,en;q=0.9",
+    "en-US,en;q=0.5",
+    "en-GB,en;q=0.9",
+    "en,en-US;q=0.8",
+];
+
 /// A function to generate random user agent to improve privacy of the user.
 ///
+/// # Arguments
+///
+/// * `user_agents` - It takes the pool of user agents provided via the config file as an
+///   argument, the user agents are generated if the pool is empty.
+///
 /// # Returns
 ///
-/// A randomly generated user agent string.
-pub fn random_user_agent() -> &'static str {
+/// A randomly picked user agent string.
+pub fn random_user_agent(user_agents: &[String]) -> String {
+    if let Some(user_agent) = user_agents.choose(&mut rand::thread_rng()) {
+        return user_agent.clone();
+    }
+
     USER_AGENTS
         .get_or_init(|| {
             UserAgentsBuilder::new()
@@ -31,4 +59,88 @@ pub fn random_user_agent() -> &'static str {
                 .build()
         })
         .random()
+        .to_owned()
+}
+
+/// A function which adds the `Accept` and `Accept-Language` headers with randomly picked values
+/// (unless they are already provided) to the provided headers and shuffles the order in which
+/// the headers are sent.
+///
+/// # Arguments
+///
+/// * `header_map` - It takes the headers built by the upstream search engine as an argument.
+/// * `language` - It takes the optional ISO 639-1 code of the language selected by the user as
+///   an argument.
+pub fn randomize_headers(header_map: HeaderMap, language: Option<&str>) -> HeaderMap {
+    let mut rng = rand::thread_rng();
+
+    // The name is only provided with the first value of the headers which have multiple values.
+    let mut last_name = None;
+    let mut headers: Vec<_> = header_map
+        .into_iter()
+        .filter_map(|(name, value)| {
+            last_name = name.or(last_name.take());
+            Some((last_name.clone()?, value))
+        })
+        .collect();
+
+    if !headers.iter().any(|(name, _)| name == ACCEPT) {
+        if let Some(accept) = ACCEPT_HEADERS.choose(&mut rng) {
+            headers.push((ACCEPT, HeaderValue::from_static(accept)));
+        }
+    }
+
+    if !headers.iter().any(|(name, _)| name == ACCEPT_LANGUAGE) {
+        let accept_language = match language {
+            Some(language) => [
+                format!("{language},en;q=0.5"),
+                format!("{language},en-US;q=0.7,en;q=0.3"),
+                format!("{language};q=0.9,en;q=0.8"),
+            ]
+            .choose(&mut rng)
+            .cloned(),
+            None => ACCEPT_LANGUAGE_HEADERS
+                .choose(&mut rng)
+                .map(|value| (*value).to_owned()),
+        };
+        if let Some(value) = accept_language.and_then(|value| HeaderValue::try_from(value).ok()) {
+            headers.push((ACCEPT_LANGUAGE, value));
+        }
+    }
+
+    headers.shuffle(&mut rng);
+    headers.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::USER_AGENT;
+
+    #[test]
+    fn test_random_user_agent_from_the_pool() {
+        let user_agents = vec!["agent-a".to_owned(), "agent-b".to_owned()];
+
+        for _ in 0..10 {
+            assert!(user_agents.contains(&random_user_agent(&user_agents)));
+        }
+    }
+
+    #[test]
+    fn test_randomize_headers() {
+        let header_map = HeaderMap::from_iter([
+            (USER_AGENT, HeaderValue::from_static("agent")),
+            (ACCEPT, HeaderValue::from_static("application/json")),
+        ]);
+
+        let headers = randomize_headers(header_map, Some("de"));
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers[USER_AGENT], "agent");
+        assert_eq!(headers[ACCEPT], "application/json");
+        assert!(headers[ACCEPT_LANGUAGE].to_str().unwrap().starts_with("de"));
+
+        let headers = randomize_headers(HeaderMap::new(), None);
+        assert!(ACCEPT_HEADERS.contains(&headers[ACCEPT].to_str().unwrap()));
+        assert!(ACCEPT_LANGUAGE_HEADERS.contains(&headers[ACCEPT_LANGUAGE].to_str().unwrap()));
+    }
 }
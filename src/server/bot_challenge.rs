@@ -0,0 +1,443 @@
+//! This module provides the proof-of-work challenge which the clients looking like bots (sending
+//! their searches in bursts or without the headers sent by all the browsers) should solve before
+//! their searches are accepted, so that scraping a public instance costs real computing time.
+//! Once a challenge is solved, a signed cookie tied to the ip address of the client lets its
+//! searches through for the time provided with the `bot_challenge.validity` option.
+
+use actix_web::{
+    body::EitherBody,
+    cookie::{time, Cookie, SameSite},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        header::{self, ContentType, HeaderMap},
+        Method,
+    },
+    web, Error, HttpRequest, HttpResponse,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use sha2::{Digest, Sha256};
+use std::{
+    net::IpAddr,
+    rc::Rc,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    client_ip::request_client_ip,
+    rate_limit::{is_search_route, is_stream_follow_up, ClientRateLimiter, Quota},
+    signing::{sign_url, verify_url},
+};
+use crate::{
+    config::{parser::Config, reloader::SharedConfig},
+    templates::views,
+};
+
+/// The name of the cookie which lets the searches of the clients which solved a challenge through.
+pub const CHALLENGE_COOKIE: &str = "bot_challenge";
+
+/// The purpose for which the challenges are signed.
+const CHALLENGE_PURPOSE: &str = "challenge";
+
+/// The purpose for which the cookies of the solved challenges are signed.
+const CHALLENGE_COOKIE_PURPOSE: &str = "challenge_cookie";
+
+/// The number of seconds during which a challenge can be solved.
+const CHALLENGE_EXPIRY: u64 = 300;
+
+/// A helper function which returns the current unix time in seconds.
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// A function which generates a new challenge for the provided client, which can be solved until
+/// it expires.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `ip` - It takes the ip address of the client as an argument.
+///
+/// # Returns
+///
+/// It returns the challenge in the `<expiry>.<random>.<signature>` form.
+pub fn new_challenge(config: &Config, ip: IpAddr) -> String {
+    let expires = unix_time() + CHALLENGE_EXPIRY;
+    let random: String = rand::random::<[u8; 16]>()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let signature = sign_url(
+        config,
+        CHALLENGE_PURPOSE,
+        &format!("{ip}:{expires}:{random}"),
+    );
+    format!("{expires}.{random}.{signature}")
+}
+
+/// A function which checks whether the provided nonce solves the provided challenge, that is
+/// whether the challenge was generated by the instance for the provided client and has not
+/// expired, and whether the SHA-256 hash of `<challenge>:<nonce>` starts with the number of zero
+/// bits provided with the `bot_challenge.difficulty` option.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `ip` - It takes the ip address of the client as an argument.
+/// * `challenge` - It takes the challenge generated by `new_challenge` as an argument.
+/// * `nonce` - It takes the solution found by the client as an argument.
+pub fn verify_solution(config: &Config, ip: IpAddr, challenge: &str, nonce: &str) -> bool {
+    let mut parts = challenge.splitn(3, '.');
+    let (Some(expires), Some(random), Some(signature)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    if expires
+        .parse::<u64>()
+        .map_or(true, |expires| expires < unix_time())
+        || !verify_url(
+            config,
+            CHALLENGE_PURPOSE,
+            &format!("{ip}:{expires}:{random}"),
+            signature,
+        )
+    {
+        return false;
+    }
+
+    let hash = Sha256::digest(format!("{challenge}:{nonce}").as_bytes());
+    u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]).leading_zeros()
+        >= u32::from(config.bot_challenge.difficulty)
+}
+
+/// A function which builds the cookie which lets the searches of the provided client through
+/// once it solved a challenge.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `ip` - It takes the ip address of the client as an argument.
+pub fn challenge_cookie(req: &HttpRequest, config: &Config, ip: IpAddr) -> Cookie<'static> {
+    let validity = config.bot_challenge.validity;
+    let expires = unix_time() + validity;
+    let signature = sign_url(config, CHALLENGE_COOKIE_PURPOSE, &format!("{ip}:{expires}"));
+    Cookie::build(CHALLENGE_COOKIE, format!("{expires}.{signature}"))
+        .path("/")
+        .max_age(time::Duration::seconds(
+            i64::try_from(validity).unwrap_or(i64::MAX),
+        ))
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(req.connection_info().scheme() == "https")
+        .finish()
+}
+
+/// A function which checks whether the provided cookie was given to the provided client for a
+/// solved challenge and has not expired.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `ip` - It takes the ip address of the client as an argument.
+/// * `cookie` - It takes the value of the `bot_challenge` cookie as an argument.
+pub fn verify_challenge_cookie(config: &Config, ip: IpAddr, cookie: &str) -> bool {
+    cookie.split_once('.').is_some_and(|(expires, signature)| {
+        expires
+            .parse::<u64>()
+            .is_ok_and(|expires| expires >= unix_time())
+            && verify_url(
+                config,
+                CHALLENGE_COOKIE_PURPOSE,
+                &format!("{ip}:{expires}"),
+                signature,
+            )
+    })
+}
+
+/// A function which checks whether the request lacks one of the headers sent by all the browsers
+/// (the `User-Agent`, the `Accept` and the `Accept-Language` headers).
+///
+/// # Arguments
+///
+/// * `headers` - It takes the headers of the request as an argument.
+pub fn lacks_browser_headers(headers: &HeaderMap) -> bool {
+    [header::USER_AGENT, header::ACCEPT, header::ACCEPT_LANGUAGE]
+        .iter()
+        .any(|name| headers.get(name).map_or(true, |value| value.is_empty()))
+}
+
+/// A middleware which makes the clients looking like bots solve a challenge before their searches
+/// are accepted, when the `bot_challenge` option is enabled. The clients are challenged when they
+/// send their searches faster than the `bot_challenge.burst` and `bot_challenge.per_minute`
+/// options allow or without the headers sent by the browsers, unless they already solved a
+/// challenge. The other routes are never challenged.
+#[derive(Clone)]
+pub struct BotChallenge {
+    /// It stores the rate limiter in which the searches of the clients are tracked.
+    limiter: Arc<ClientRateLimiter>,
+}
+
+impl BotChallenge {
+    /// Creates the middleware challenging the suspicious clients, the rate of the clients is
+    /// tracked in memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the rate limiter could not be created.
+    pub fn new(config: &Config) -> std::io::Result<Self> {
+        Ok(BotChallenge {
+            limiter: Arc::new(ClientRateLimiter::new(config, false)?),
+        })
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for BotChallenge
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = BotChallengeMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(BotChallengeMiddleware {
+            service: Rc::new(service),
+            challenge: self.clone(),
+        }))
+    }
+}
+
+/// The service created by the `BotChallenge` middleware.
+pub struct BotChallengeMiddleware<S> {
+    /// It stores the wrapped service.
+    service: Rc<S>,
+    /// It stores the middleware challenging the suspicious clients.
+    challenge: BotChallenge,
+}
+
+impl<S, B> Service<ServiceRequest> for BotChallengeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let challenge = self.challenge.clone();
+
+        Box::pin(async move {
+            let config = match req.app_data::<web::Data<SharedConfig>>() {
+                Some(config) if is_search_route(req.path()) => config.load(),
+                _ => {
+                    return service
+                        .call(req)
+                        .await
+                        .map(ServiceResponse::map_into_left_body)
+                }
+            };
+            let options = &config.bot_challenge;
+            let ip = request_client_ip(req.request());
+            let solved = req
+                .cookie(CHALLENGE_COOKIE)
+                .is_some_and(|cookie| verify_challenge_cookie(&config, ip, cookie.value()));
+            // The results streamed to the search page were counted with the page itself.
+            if !options.enabled
+                || solved
+                || is_stream_follow_up(req.request(), &challenge.limiter).await
+            {
+                return service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body);
+            }
+
+            let quota = Quota::new(options.burst, options.per_minute);
+            let is_suspicious = lacks_browser_headers(req.headers())
+                || challenge
+                    .limiter
+                    .check(&ip.to_string(), &quota)
+                    .await
+                    .is_err();
+            if !is_suspicious {
+                return service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body);
+            }
+
+            // The forms sent with a `POST` request cannot be sent again after the redirect.
+            let redirect = match req.method() == Method::GET {
+                true => req
+                    .uri()
+                    .path_and_query()
+                    .map_or("/", |path_and_query| path_and_query.as_str()),
+                false => "/",
+            }
+            .to_owned();
+            let style = &config.style;
+            let response = HttpResponse::Forbidden()
+                .insert_header((header::CACHE_CONTROL, "no-store"))
+                .content_type(ContentType::html())
+                .body(
+                    views::challenge::challenge(
+                        &style.colorscheme,
+                        &style.theme,
+                        &style.animation,
+                        &new_challenge(&config, ip),
+                        options.difficulty,
+                        &redirect,
+                    )
+                    .0,
+                );
+            Ok(req.into_response(response).map_into_right_body())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::parser_models::BotChallengeConfig;
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+
+    /// A helper function which finds the nonce solving the provided challenge.
+    fn solve(config: &Config, ip: IpAddr, challenge: &str) -> String {
+        (0..)
+            .map(|nonce: u64| nonce.to_string())
+            .find(|nonce| verify_solution(config, ip, challenge, nonce))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_verify_solution() {
+        let config = Config {
+            bot_challenge: BotChallengeConfig {
+                difficulty: 8,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let challenge = new_challenge(&config, ip);
+        let nonce = solve(&config, ip, &challenge);
+
+        assert!(verify_solution(&config, ip, &challenge, &nonce));
+        // The challenges are tied to the client and cannot be forged.
+        assert!(!verify_solution(
+            &config,
+            "203.0.113.8".parse().unwrap(),
+            &challenge,
+            &nonce
+        ));
+        let forged = format!("{}.{}", unix_time() + 60, &challenge[11..]);
+        assert!(!verify_solution(&config, ip, &forged, &nonce));
+        assert!(!verify_solution(&config, ip, "not a challenge", &nonce));
+    }
+
+    #[test]
+    fn test_challenge_cookie() {
+        let config = Config::default();
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let cookie = challenge_cookie(&TestRequest::default().to_http_request(), &config, ip);
+
+        assert!(verify_challenge_cookie(&config, ip, cookie.value()));
+        assert!(!verify_challenge_cookie(
+            &config,
+            "203.0.113.8".parse().unwrap(),
+            cookie.value()
+        ));
+        assert!(!verify_challenge_cookie(&config, ip, "4102444800.forged"));
+    }
+
+    #[actix_web::test]
+    async fn test_bot_challenge() {
+        let config = Config {
+            bot_challenge: BotChallengeConfig {
+                enabled: true,
+                burst: 1,
+                per_minute: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config.clone())))
+                .wrap(BotChallenge::new(&config).unwrap())
+                .route("/search", web::get().to(HttpResponse::Ok))
+                .route("/about", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let request = |path: &str| {
+            TestRequest::get()
+                .uri(path)
+                .peer_addr("203.0.113.7:4711".parse().unwrap())
+                .insert_header((header::USER_AGENT, "Mozilla/5.0"))
+                .insert_header((header::ACCEPT, "text/html"))
+                .insert_header((header::ACCEPT_LANGUAGE, "en"))
+        };
+
+        assert_eq!(
+            call_service(&app, request("/search?q=rust").to_request())
+                .await
+                .status(),
+            StatusCode::OK
+        );
+        // The burst of the client is exceeded.
+        assert_eq!(
+            call_service(&app, request("/search?q=rust").to_request())
+                .await
+                .status(),
+            StatusCode::FORBIDDEN
+        );
+        // The other routes are not challenged.
+        assert_eq!(
+            call_service(&app, request("/about").to_request())
+                .await
+                .status(),
+            StatusCode::OK
+        );
+
+        // The clients which solved a challenge are not challenged again.
+        let cookie = challenge_cookie(
+            &TestRequest::default().to_http_request(),
+            &config,
+            "203.0.113.7".parse().unwrap(),
+        );
+        assert_eq!(
+            call_service(&app, request("/search?q=rust").cookie(cookie).to_request())
+                .await
+                .status(),
+            StatusCode::OK
+        );
+
+        // The clients without the headers of the browsers are challenged right away.
+        let request = TestRequest::get()
+            .uri("/search?q=rust")
+            .peer_addr("203.0.113.8:4711".parse().unwrap())
+            .to_request();
+        assert_eq!(
+            call_service(&app, request).await.status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! This module provides the functionality to identify the clients of the instance by their ip
+//! address, so that the requests can be rate limited per client. The address of the client is
+//! only taken from the `Forwarded` or the `X-Forwarded-For` headers when the request was sent by
+//! one of the trusted proxies, as any client could set these headers otherwise.
+
+use actix_governor::{KeyExtractor, SimpleKeyExtractionError};
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::header::{HeaderMap, FORWARDED, X_FORWARDED_FOR},
+    web, Error, HttpMessage, HttpRequest,
+};
+use std::{
+    fmt,
+    future::Future,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+};
+
+use crate::config::reloader::SharedConfig;
+
+/// The ip address of the client which sent the request, as resolved through the trusted proxies.
+/// It is stored in the extensions of the requests by the `resolve_client_ip` middleware.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+/// A network of ip addresses (like `10.0.0.0/8` or `fd00::/8`) or a single ip address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpNetwork {
+    /// It stores the address of the network.
+    address: IpAddr,
+    /// It stores the number of the leading bits of the address identifying the network.
+    prefix: u8,
+}
+
+impl IpNetwork {
+    /// A function which parses the network from its CIDR notation (like `10.0.0.0/8`), a single
+    /// ip address is parsed as the network containing only this address.
+    ///
+    /// # Arguments
+    ///
+    /// * `network` - It takes the network in the CIDR notation or the ip address as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the parsed network or `None` if it is not a valid network.
+    pub fn parse(network: &str) -> Option<Self> {
+        let (address, prefix) = match network.trim().split_once('/') {
+            Some((address, prefix)) => (address.parse().ok()?, Some(prefix.parse().ok()?)),
+            None => (network.trim().parse().ok()?, None),
+        };
+        let bits = address_bits(address).1;
+        let prefix = prefix.unwrap_or(bits);
+        (prefix <= bits).then_some(IpNetwork { address, prefix })
+    }
+
+    /// A function which checks whether the provided ip address belongs to the network, the ipv4
+    /// addresses mapped to ipv6 addresses are checked as ipv4 addresses.
+    ///
+    /// # Arguments
+    ///
+    /// * `ip` - It takes the ip address to be checked as an argument.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ((network, bits), (ip, ip_bits)) =
+            (address_bits(self.address), address_bits(ip.to_canonical()));
+        if bits != ip_bits {
+            return false;
+        }
+        let shift = u32::from(bits - self.prefix);
+        network.checked_shr(shift).unwrap_or(0) == ip.checked_shr(shift).unwrap_or(0)
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    /// Formats the network in the CIDR notation, or as a single ip address if the network contains
+    /// only this address, so that it is parsed back as the same network.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.prefix == address_bits(self.address).1 {
+            true => write!(f, "{}", self.address),
+            false => write!(f, "{}/{}", self.address, self.prefix),
+        }
+    }
+}
+
+/// A helper function which returns the bits of the provided ip address along with their number.
+///
+/// # Arguments
+///
+/// * `address` - It takes the ip address as an argument.
+fn address_bits(address: IpAddr) -> (u128, u8) {
+    match address {
+        IpAddr::V4(address) => (u128::from(u32::from(address)), 32),
+        IpAddr::V6(address) => (u128::from(address), 128),
+    }
+}
+
+/// A function which resolves the ip address of the client which sent the request. The addresses
+/// forwarded by the proxies are walked from the closest one and the first address which is not a
+/// trusted proxy is the client. The requests received on a unix socket have no peer address and
+/// are always sent by the reverse proxy, so their forwarded addresses are trusted.
+///
+/// # Arguments
+///
+/// * `peer` - It takes the ip address of the peer of the connection as an argument.
+/// * `headers` - It takes the headers of the request as an argument.
+/// * `trusted_proxies` - It takes the networks of the trusted proxies as an argument.
+pub fn client_ip(
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    trusted_proxies: &[IpNetwork],
+) -> IpAddr {
+    let is_trusted = |ip: IpAddr| trusted_proxies.iter().any(|network| network.contains(ip));
+
+    let mut client = peer;
+    if peer.is_none_or(is_trusted) {
+        for address in forwarded_addresses(headers).into_iter().rev() {
+            // The addresses which cannot be parsed (like `unknown`) end the chain of proxies.
+            let Some(ip) = address else { break };
+            client = Some(ip);
+            if !is_trusted(ip) {
+                break;
+            }
+        }
+    }
+
+    // The requests received on a unix socket without a forwarded address share a single client.
+    client.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
+
+/// A helper function which returns the addresses forwarded by the proxies in the `Forwarded`
+/// header (or in the `X-Forwarded-For` header if it is missing), ordered from the client to the
+/// closest proxy.
+///
+/// # Arguments
+///
+/// * `headers` - It takes the headers of the request as an argument.
+fn forwarded_addresses(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    let values = |name| {
+        headers
+            .get_all(name)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<&str>>()
+    };
+
+    match headers.contains_key(FORWARDED) {
+        true => values(FORWARDED)
+            .into_iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("for"))
+                    .and_then(|(_, address)| parse_ip(address.trim_matches('"')))
+            })
+            .collect(),
+        false => values(X_FORWARDED_FOR).into_iter().map(parse_ip).collect(),
+    }
+}
+
+/// A helper function which parses the ip address forwarded by a proxy, which may be enclosed in
+/// brackets and followed by the port of the client.
+///
+/// # Arguments
+///
+/// * `address` - It takes the forwarded address (like `203.0.113.7` or `[2001:db8::1]:4711`) as an
+///   argument.
+fn parse_ip(address: &str) -> Option<IpAddr> {
+    address
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| {
+            address
+                .parse::<SocketAddr>()
+                .ok()
+                .map(|address| address.ip())
+        })
+        .or_else(|| address.strip_prefix('[')?.strip_suffix(']')?.parse().ok())
+}
+
+/// A middleware (used with `wrap_fn`) which resolves the ip address of the client which sent the
+/// request through the trusted proxies provided with the `trusted_proxies` option and stores it
+/// in the extensions of the request.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be handled as an argument.
+/// * `service` - It takes the wrapped service as an argument.
+pub fn resolve_client_ip<S, B>(
+    req: ServiceRequest,
+    service: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let config = req
+        .app_data::<web::Data<SharedConfig>>()
+        .map(|config| config.load());
+    let trusted_proxies = config
+        .as_ref()
+        .map_or(&[][..], |config| config.trusted_proxies.as_slice());
+
+    let ip = client_ip(
+        req.peer_addr().map(|peer| peer.ip()),
+        req.headers(),
+        trusted_proxies,
+    );
+    req.extensions_mut().insert(ClientIp(ip));
+
+    service.call(req)
+}
+
+/// A function which returns the ip address of the client which sent the request, as resolved by
+/// the `resolve_client_ip` middleware (or the ip address of the peer if the middleware is not
+/// used).
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+pub fn request_client_ip(req: &HttpRequest) -> IpAddr {
+    match req.extensions().get::<ClientIp>() {
+        Some(ClientIp(ip)) => *ip,
+        None => client_ip(req.peer_addr().map(|peer| peer.ip()), req.headers(), &[]),
+    }
+}
+
+/// A key extractor for the rate limiting middleware which identifies the clients by the ip
+/// address resolved by the `resolve_client_ip` middleware (or by the ip address of the peer if
+/// the middleware is not used).
+#[derive(Clone)]
+pub struct ClientIpKeyExtractor;
+
+impl KeyExtractor for ClientIpKeyExtractor {
+    type Key = IpAddr;
+    type KeyExtractionError = SimpleKeyExtractionError<&'static str>;
+
+    fn extract(&self, req: &ServiceRequest) -> Result<Self::Key, Self::KeyExtractionError> {
+        Ok(request_client_ip(req.request()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, TestRequest},
+        App, HttpRequest, HttpResponse,
+    };
+
+    fn ip(address: &str) -> IpAddr {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn test_ip_network() {
+        let network = IpNetwork::parse("10.0.0.0/8").unwrap();
+        assert!(network.contains(ip("10.20.30.40")));
+        assert!(network.contains(ip("::ffff:10.1.2.3")));
+        assert!(!network.contains(ip("11.0.0.1")));
+        assert!(!network.contains(ip("fd00::1")));
+
+        assert!(IpNetwork::parse("fd00::/8")
+            .unwrap()
+            .contains(ip("fd12::1")));
+        assert!(IpNetwork::parse("0.0.0.0/0")
+            .unwrap()
+            .contains(ip("203.0.113.7")));
+        assert!(IpNetwork::parse("127.0.0.1")
+            .unwrap()
+            .contains(ip("127.0.0.1")));
+        assert!(!IpNetwork::parse("127.0.0.1")
+            .unwrap()
+            .contains(ip("127.0.0.2")));
+
+        assert_eq!(IpNetwork::parse("10.0.0.0/33"), None);
+        assert_eq!(IpNetwork::parse("localhost"), None);
+
+        // The networks are formatted back to the notation from which they are parsed.
+        for network in ["10.0.0.0/8", "fd00::/8", "127.0.0.1"] {
+            assert_eq!(IpNetwork::parse(network).unwrap().to_string(), network);
+        }
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let trusted_proxies = [IpNetwork::parse("10.0.0.0/8").unwrap()];
+        let headers = |name: &str, value: &str| {
+            TestRequest::default()
+                .insert_header((name, value))
+                .to_http_request()
+                .headers()
+                .clone()
+        };
+        let forwarded_for = headers("x-forwarded-for", "198.51.100.1, 203.0.113.7, 10.0.0.2");
+
+        // The forwarded addresses are ignored unless the peer is a trusted proxy.
+        assert_eq!(
+            client_ip(Some(ip("192.0.2.9")), &forwarded_for, &trusted_proxies),
+            ip("192.0.2.9")
+        );
+        // The addresses prepended by the client itself are ignored.
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.1")), &forwarded_for, &trusted_proxies),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.1")), &HeaderMap::new(), &trusted_proxies),
+            ip("10.0.0.1")
+        );
+
+        let forwarded = headers(
+            "forwarded",
+            "for=\"[2001:db8::1]:4711\";proto=https, for=10.0.0.3",
+        );
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.1")), &forwarded, &trusted_proxies),
+            ip("2001:db8::1")
+        );
+        let unknown = headers("forwarded", "for=unknown, for=10.0.0.3");
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.1")), &unknown, &trusted_proxies),
+            ip("10.0.0.3")
+        );
+
+        // The requests received on a unix socket are always sent by the reverse proxy.
+        assert_eq!(
+            client_ip(None, &headers("x-forwarded-for", "203.0.113.7"), &[]),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip(None, &HeaderMap::new(), &[]),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_resolve_client_ip() {
+        use crate::config::parser::Config;
+
+        let config = Config {
+            trusted_proxies: vec![IpNetwork::parse("10.0.0.0/8").unwrap()],
+            ..Default::default()
+        };
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .wrap_fn(resolve_client_ip)
+                .route(
+                    "/",
+                    web::get().to(|req: HttpRequest| async move {
+                        let client_ip = req.extensions().get::<ClientIp>().copied();
+                        HttpResponse::Ok().body(client_ip.unwrap().0.to_string())
+                    }),
+                ),
+        )
+        .await;
+
+        let request = TestRequest::get()
+            .peer_addr("10.0.0.1:4711".parse().unwrap())
+            .insert_header(("x-forwarded-for", "203.0.113.7"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert_eq!(body, "203.0.113.7");
+    }
+}
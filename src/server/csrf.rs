@@ -0,0 +1,134 @@
+//! This module provides the functionality to protect the forms of the website changing the state
+//! of the user (the settings form and the `POST /search` route) against the cross-site request
+//! forgeries with the double-submit cookie pattern. A random token is set in a cookie and
+//! embedded into the forms, and the submitted forms are only accepted when both tokens match, as
+//! another site can send the cookie along with a forged form but cannot read its value.
+
+use actix_web::{
+    cookie::{Cookie, SameSite},
+    HttpRequest,
+};
+
+use crate::server::signing::tokens_match;
+
+/// The name of the cookie storing the CSRF token.
+pub const CSRF_COOKIE: &str = "csrf_token";
+
+/// The name of the form field in which the CSRF token is submitted.
+pub const CSRF_FIELD: &str = "csrf_token";
+
+/// The number of random bytes in a CSRF token (which is sent hex encoded).
+const CSRF_TOKEN_BYTES: usize = 32;
+
+/// A function which returns the CSRF token to embed into the forms of the page, the token of the
+/// `csrf_token` cookie is reused if the request has a valid one so that the forms of the pages
+/// opened in other tabs stay valid.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+pub fn csrf_token(req: &HttpRequest) -> String {
+    req.cookie(CSRF_COOKIE)
+        .map(|cookie| cookie.value().to_owned())
+        .filter(|token| is_valid_token(token))
+        .unwrap_or_else(|| {
+            rand::random::<[u8; CSRF_TOKEN_BYTES]>()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect()
+        })
+}
+
+/// A function which builds the cookie storing the provided CSRF token, it should be sent along
+/// with the pages embedding the token into their forms.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `token` - It takes the CSRF token as an argument.
+pub fn csrf_cookie(req: &HttpRequest, token: &str) -> Cookie<'static> {
+    Cookie::build(CSRF_COOKIE, token.to_owned())
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .secure(req.connection_info().scheme() == "https")
+        .finish()
+}
+
+/// A function which checks that the CSRF token submitted with a form matches the token of the
+/// `csrf_token` cookie.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `submitted_token` - It takes the token submitted in the `csrf_token` field of the form as an
+///   argument.
+///
+/// # Returns
+///
+/// It returns `true` if both tokens are present and match.
+pub fn verify_csrf_token(req: &HttpRequest, submitted_token: Option<&str>) -> bool {
+    match (req.cookie(CSRF_COOKIE), submitted_token) {
+        (Some(cookie), Some(submitted_token)) => {
+            is_valid_token(cookie.value()) && tokens_match(submitted_token, cookie.value())
+        }
+        _ => false,
+    }
+}
+
+/// A helper function which checks whether the provided token has the form of the tokens generated
+/// by the server.
+///
+/// # Arguments
+///
+/// * `token` - It takes the token as an argument.
+fn is_valid_token(token: &str) -> bool {
+    token.len() == CSRF_TOKEN_BYTES * 2 && token.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_csrf_token() {
+        let token = csrf_token(&TestRequest::default().to_http_request());
+        assert!(is_valid_token(&token));
+
+        // The token of the cookie is reused, unless it was not generated by the server.
+        let req = TestRequest::default()
+            .cookie(Cookie::new(CSRF_COOKIE, token.clone()))
+            .to_http_request();
+        assert_eq!(csrf_token(&req), token);
+        let req = TestRequest::default()
+            .cookie(Cookie::new(CSRF_COOKIE, "forged"))
+            .to_http_request();
+        assert_ne!(csrf_token(&req), "forged");
+
+        let cookie = csrf_cookie(&req, &token);
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        assert_eq!(cookie.http_only(), Some(true));
+    }
+
+    #[test]
+    fn test_verify_csrf_token() {
+        let token = csrf_token(&TestRequest::default().to_http_request());
+        let req = TestRequest::default()
+            .cookie(Cookie::new(CSRF_COOKIE, token.clone()))
+            .to_http_request();
+        assert!(verify_csrf_token(&req, Some(&token)));
+        assert!(!verify_csrf_token(&req, Some(&token[1..])));
+        assert!(!verify_csrf_token(&req, None));
+        assert!(!verify_csrf_token(
+            &TestRequest::default().to_http_request(),
+            Some(&token)
+        ));
+
+        // An empty cookie matching an empty field is not accepted.
+        let req = TestRequest::default()
+            .cookie(Cookie::new(CSRF_COOKIE, ""))
+            .to_http_request();
+        assert!(!verify_csrf_token(&req, Some("")));
+    }
+}
@@ -0,0 +1,349 @@
+//! This module provides the unified error responses of the website. The error responses without a
+//! body (like the `404 Not Found` of the unknown pages) and the errors returned by the routes are
+//! replaced with the themed error page, or with a json error body for the api clients, while
+//! keeping their status. The routes can return an `HttpError` to choose the status and the message
+//! of the error page.
+
+use actix_web::{
+    body::{BodySize, EitherBody, MessageBody},
+    dev::{Service, ServiceRequest, ServiceResponse},
+    error::QueryPayloadError,
+    http::{
+        header::{self, ContentType},
+        StatusCode,
+    },
+    web, Error, HttpMessage, HttpRequest, HttpResponse, ResponseError,
+};
+use serde::Serialize;
+use std::{fmt, future::Future};
+
+use crate::{
+    config::{parser::Config, reloader::SharedConfig},
+    server::request_log::RequestId,
+    templates::views,
+};
+
+/// An error which is sent back to the client with its status and its message, on the error page or
+/// in a json error body.
+#[derive(Debug)]
+pub struct HttpError {
+    /// It stores the status of the response.
+    pub status: StatusCode,
+    /// It stores the message explaining the error to the user.
+    pub message: String,
+}
+
+impl HttpError {
+    /// Constructs a new `HttpError` with the provided status and message.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - It takes the status of the response as an argument.
+    /// * `message` - It takes the message explaining the error as an argument.
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        HttpError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// Constructs a new `400 Bad Request` error with the provided message.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - It takes the message explaining the error as an argument.
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        HttpError::new(StatusCode::BAD_REQUEST, message)
+    }
+}
+
+/// A named struct which serializes the json error body sent back to the api clients.
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    /// It stores the message explaining the error.
+    error: &'a str,
+    /// It stores the id of the request or `None` if the request has no id.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<&'a str>,
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+impl ResponseError for HttpError {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl From<QueryPayloadError> for HttpError {
+    fn from(_: QueryPayloadError) -> Self {
+        HttpError::bad_request("The parameters of the request are invalid.")
+    }
+}
+
+/// A function which returns the message shown on the error page for the provided status.
+///
+/// # Arguments
+///
+/// * `status` - It takes the status of the response as an argument.
+pub fn status_message(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "The request is invalid.",
+        StatusCode::UNAUTHORIZED => "The request is not authenticated.",
+        StatusCode::FORBIDDEN => "The request is not allowed.",
+        StatusCode::NOT_FOUND => "The requested page does not exist.",
+        StatusCode::METHOD_NOT_ALLOWED => "The request method is not allowed for this page.",
+        StatusCode::PAYLOAD_TOO_LARGE => "The request is too large.",
+        StatusCode::URI_TOO_LONG => "The address of the request is too long.",
+        StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE => "The cookies of the request are too large.",
+        StatusCode::TOO_MANY_REQUESTS => "Too many requests were sent, please retry later.",
+        status if status.is_server_error() => {
+            "Something went wrong on our side, please try again later."
+        }
+        _ => "The request could not be handled.",
+    }
+}
+
+/// A function which checks whether the client expects a json response instead of a page, which is
+/// the case for the json api, the `format=json` parameter and the requests accepting json but not
+/// html.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+pub fn wants_json(req: &HttpRequest) -> bool {
+    let accept = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .unwrap_or_default();
+
+    req.path().starts_with("/api/")
+        || req
+            .query_string()
+            .split('&')
+            .any(|param| param == "format=json")
+        || (accept.contains("application/json") && !accept.contains("text/html"))
+}
+
+/// A function which builds the error response sent back to the client, the themed error page (the
+/// not found page for the `404 Not Found` status) or a json error body for the api clients. The id
+/// of the request is shown along with the message, so that the users can report the failures.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `status` - It takes the status of the response as an argument.
+/// * `message` - It takes the message explaining the error as an argument.
+pub fn error_response(req: &HttpRequest, status: StatusCode, message: &str) -> HttpResponse {
+    let mut response = HttpResponse::build(status);
+    let extensions = req.extensions();
+    let request_id = extensions
+        .get::<RequestId>()
+        .map(|RequestId(request_id)| request_id.as_str());
+    if wants_json(req) {
+        return response.json(ErrorBody {
+            error: message,
+            request_id,
+        });
+    }
+
+    let config = req.app_data::<web::Data<SharedConfig>>().map_or_else(
+        || std::sync::Arc::new(Config::default()),
+        |config| config.load(),
+    );
+    let style = &config.style;
+    let page = match status {
+        StatusCode::NOT_FOUND => {
+            views::not_found::not_found(&style.colorscheme, &style.theme, &style.animation)
+        }
+        _ => views::error::error(
+            &style.colorscheme,
+            &style.theme,
+            &style.animation,
+            &format!(
+                "{} {}",
+                status.as_u16(),
+                status.canonical_reason().unwrap_or_default()
+            ),
+            message,
+            request_id,
+        ),
+    };
+    response.content_type(ContentType::html()).body(page.0)
+}
+
+/// A middleware (used with `wrap_fn`) which replaces the error responses without a body and the
+/// errors returned by the routes with the error page (or the json error body), the headers of the
+/// original response (like the `Retry-After` header) are kept.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be handled as an argument.
+/// * `service` - It takes the wrapped service as an argument.
+pub fn render_error_pages<S, B>(
+    req: ServiceRequest,
+    service: &S,
+) -> impl Future<Output = Result<ServiceResponse<EitherBody<B>>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    B: MessageBody,
+{
+    let response = service.call(req);
+    async move {
+        let response = response.await?;
+        let status = response.status();
+        if !(status.is_client_error() || status.is_server_error()) {
+            return Ok(response.map_into_left_body());
+        }
+
+        // The errors returned by the routes as an `HttpError` (even when boxed) keep their status
+        // and their message.
+        let http_error = response.response().error().and_then(|error| {
+            error.as_error::<HttpError>().or_else(|| {
+                error
+                    .as_error::<Box<dyn std::error::Error>>()
+                    .and_then(|error| error.downcast_ref::<HttpError>())
+            })
+        });
+        let (status, message) = match http_error {
+            Some(http_error) => (http_error.status, http_error.message.clone()),
+            None if response.response().error().is_some()
+                || matches!(
+                    response.response().body().size(),
+                    BodySize::None | BodySize::Sized(0)
+                ) =>
+            {
+                (status, status_message(status).to_owned())
+            }
+            None => return Ok(response.map_into_left_body()),
+        };
+
+        let (req, original) = response.into_parts();
+        let mut error = error_response(&req, status, &message);
+        for (name, value) in original.headers() {
+            if !matches!(
+                *name,
+                header::CONTENT_TYPE | header::CONTENT_LENGTH | header::CONTENT_ENCODING
+            ) {
+                error.headers_mut().insert(name.clone(), value.clone());
+            }
+        }
+        Ok(ServiceResponse::new(req, error).map_into_right_body())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, read_body, TestRequest},
+        App,
+    };
+
+    #[test]
+    fn test_wants_json() {
+        let wants_json = |uri: &str, accept: &str| {
+            wants_json(
+                &TestRequest::get()
+                    .uri(uri)
+                    .insert_header((header::ACCEPT, accept))
+                    .to_http_request(),
+            )
+        };
+        assert!(wants_json("/api/v1/search", "text/html"));
+        assert!(wants_json("/search?q=rust&format=json", "text/html"));
+        assert!(wants_json("/search?q=rust", "application/json"));
+        assert!(!wants_json("/search?q=rust", "text/html,application/json"));
+        assert!(!wants_json("/search?q=rust", "*/*"));
+    }
+
+    #[actix_web::test]
+    async fn test_render_error_pages() {
+        let app = init_service(
+            App::new()
+                .wrap_fn(render_error_pages)
+                .route(
+                    "/invalid",
+                    web::get().to(|| async {
+                        Err::<HttpResponse, _>(Box::<dyn std::error::Error>::from(
+                            HttpError::bad_request("The page number is invalid."),
+                        ))
+                    }),
+                )
+                .route(
+                    "/failure",
+                    web::get().to(|| async {
+                        Err::<HttpResponse, _>(Box::<dyn std::error::Error>::from("secret"))
+                    }),
+                )
+                .route(
+                    "/limited",
+                    web::get().to(|| async {
+                        HttpResponse::TooManyRequests()
+                            .insert_header((header::RETRY_AFTER, "5"))
+                            .body("Too many searches")
+                    }),
+                )
+                .default_service(web::route().to(HttpResponse::NotFound)),
+        )
+        .await;
+        let get = |uri: &str| TestRequest::get().uri(uri).to_request();
+
+        let response = call_service(&app, get("/invalid")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = read_body(response).await;
+        assert!(String::from_utf8_lossy(&body).contains("The page number is invalid."));
+
+        // The details of the internal errors are never sent to the client.
+        let response = call_service(&app, get("/failure?format=json")).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            read_body(response).await,
+            r#"{"error":"Something went wrong on our side, please try again later."}"#
+        );
+
+        let response = call_service(&app, get("/missing")).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = read_body(response).await;
+        assert!(String::from_utf8_lossy(&body).contains("404 Page Not Found!"));
+
+        // The error responses with a body are kept.
+        let response = call_service(&app, get("/limited")).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "5");
+        assert_eq!(read_body(response).await, "Too many searches");
+    }
+
+    #[actix_web::test]
+    async fn test_error_pages_request_id() {
+        let app = init_service(
+            App::new()
+                .wrap_fn(render_error_pages)
+                .wrap_fn(crate::server::request_log::trace_requests)
+                .default_service(web::route().to(HttpResponse::InternalServerError)),
+        )
+        .await;
+
+        let response = call_service(
+            &app,
+            TestRequest::get().uri("/failure?format=json").to_request(),
+        )
+        .await;
+        let request_id = response
+            .headers()
+            .get(crate::server::request_log::X_REQUEST_ID)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        let body: serde_json::Value = serde_json::from_slice(&read_body(response).await).unwrap();
+        assert_eq!(body["request_id"], request_id);
+    }
+}
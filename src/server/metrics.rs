@@ -0,0 +1,431 @@
+//! This module provides the metrics of the instance (the requests handled per route, the duration
+//! of the aggregations, the cache lookups and the responses of the upstream search engines) which
+//! are exported in the Prometheus text format on the `/metrics` route.
+
+use crate::{
+    engines::block_backoff::blocked_engines, models::engine_models::EngineError,
+    results::engine_stats::ENGINE_STATS,
+};
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    Error,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+/// The upper bounds (in seconds) of the buckets of the duration histograms.
+const DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// The label of the requests which did not match any route, so that the requests sent to random
+/// paths do not create a new time series each.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// A static variable which stores the metrics recorded since the start of the server.
+pub static METRICS: Metrics = Metrics::new();
+
+/// A named struct which stores the observations of a duration histogram.
+#[derive(Clone, Default)]
+struct Histogram {
+    /// It stores the number of the observations falling into each bucket (and into the buckets
+    /// with a lower bound), in the order of the `DURATION_BUCKETS`.
+    buckets: [u64; DURATION_BUCKETS.len()],
+    /// It stores the number of the observations.
+    count: u64,
+    /// It stores the sum of the observed durations in seconds.
+    sum: f64,
+}
+
+impl Histogram {
+    /// Constructs a new histogram without any observation.
+    const fn new() -> Self {
+        Histogram {
+            buckets: [0; DURATION_BUCKETS.len()],
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    /// A function which records a duration into the histogram.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - It takes the observed duration as an argument.
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bucket, upper_bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if seconds <= upper_bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    /// A function which writes the samples of the histogram in the Prometheus text format.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - It takes the exported metrics to which the samples are appended as an
+    ///   argument.
+    /// * `name` - It takes the name of the histogram as an argument.
+    /// * `labels` - It takes the labels of the histogram (like `route="/search"`) or an empty
+    ///   string as an argument.
+    fn write(&self, output: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (bucket, upper_bound) in self.buckets.iter().zip(DURATION_BUCKETS) {
+            let _ = writeln!(
+                output,
+                "{name}_bucket{{{labels}{separator}le=\"{upper_bound}\"}} {bucket}"
+            );
+        }
+        let _ = writeln!(
+            output,
+            "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {}",
+            self.count
+        );
+        let labels = match labels.is_empty() {
+            true => String::new(),
+            false => format!("{{{labels}}}"),
+        };
+        let _ = writeln!(output, "{name}_sum{labels} {}", self.sum);
+        let _ = writeln!(output, "{name}_count{labels} {}", self.count);
+    }
+}
+
+/// A named struct which stores the metrics of the instance.
+pub struct Metrics {
+    /// It stores the number of the requests handled, keyed by their route, method and status.
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>,
+    /// It stores the durations of the requests handled, keyed by their route.
+    request_durations: Mutex<BTreeMap<String, Histogram>>,
+    /// It stores the durations of the aggregations of the results of the upstream search
+    /// engines.
+    aggregation_durations: Mutex<Histogram>,
+    /// It stores the number of the searches whose results were found in the cache.
+    cache_hits: AtomicU64,
+    /// It stores the number of the searches whose results were not found in the cache.
+    cache_misses: AtomicU64,
+    /// It stores the number of the responses of the upstream search engines, keyed by the engine
+    /// and the outcome (`success`, `error`, `timeout` or `blocked`) of the response.
+    engine_responses: Mutex<BTreeMap<(String, &'static str), u64>>,
+}
+
+impl Metrics {
+    /// Constructs the metrics without any recorded value.
+    const fn new() -> Self {
+        Metrics {
+            requests: Mutex::new(BTreeMap::new()),
+            request_durations: Mutex::new(BTreeMap::new()),
+            aggregation_durations: Mutex::new(Histogram::new()),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            engine_responses: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// A function which records a request handled by the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `route` - It takes the pattern of the route which handled the request as an argument.
+    /// * `method` - It takes the method of the request as an argument.
+    /// * `status` - It takes the status of the response as an argument.
+    /// * `duration` - It takes the time taken to handle the request as an argument.
+    pub fn record_request(&self, route: &str, method: &str, status: u16, duration: Duration) {
+        *self
+            .requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry((route.to_owned(), method.to_owned(), status))
+            .or_default() += 1;
+        self.request_durations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(route.to_owned())
+            .or_default()
+            .observe(duration);
+    }
+
+    /// A function which records the duration of an aggregation of the results of the upstream
+    /// search engines.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - It takes the time taken by the aggregation as an argument.
+    pub fn record_aggregation(&self, duration: Duration) {
+        self.aggregation_durations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .observe(duration);
+    }
+
+    /// A function which records a lookup of the results of a search in the cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `hit` - It takes whether the results were found in the cache as an argument.
+    pub fn record_cache_lookup(&self, hit: bool) {
+        let counter = match hit {
+            true => &self.cache_hits,
+            false => &self.cache_misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A function which records the response of an upstream search engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - It takes the name of the upstream search engine as an argument.
+    /// * `error` - It takes the error returned by the engine or `None` if the engine returned its
+    ///   results as an argument.
+    pub fn record_engine_response(&self, engine: &str, error: Option<&EngineError>) {
+        let outcome = match error {
+            None => "success",
+            Some(EngineError::TimedOut) => "timeout",
+            Some(EngineError::Blocked) => "blocked",
+            Some(_) => "error",
+        };
+        *self
+            .engine_responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry((engine.to_owned(), outcome))
+            .or_default() += 1;
+    }
+
+    /// A function which exports the recorded metrics in the Prometheus text format.
+    ///
+    /// # Returns
+    ///
+    /// It returns the exported metrics as a string.
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+
+        output.push_str("# HELP websurfx_http_requests_total The number of the requests handled per route, method and status.\n");
+        output.push_str("# TYPE websurfx_http_requests_total counter\n");
+        for ((route, method, status), count) in self
+            .requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            let _ = writeln!(
+                output,
+                "websurfx_http_requests_total{{route=\"{}\",method=\"{}\",status=\"{status}\"}} {count}",
+                escape_label(route),
+                escape_label(method),
+            );
+        }
+
+        output.push_str("# HELP websurfx_http_request_duration_seconds The time taken to handle the requests per route.\n");
+        output.push_str("# TYPE websurfx_http_request_duration_seconds histogram\n");
+        for (route, histogram) in self
+            .request_durations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            histogram.write(
+                &mut output,
+                "websurfx_http_request_duration_seconds",
+                &format!("route=\"{}\"", escape_label(route)),
+            );
+        }
+
+        output.push_str("# HELP websurfx_aggregation_duration_seconds The time taken to aggregate the results of the upstream search engines.\n");
+        output.push_str("# TYPE websurfx_aggregation_duration_seconds histogram\n");
+        self.aggregation_durations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write(&mut output, "websurfx_aggregation_duration_seconds", "");
+
+        output.push_str(
+            "# HELP websurfx_cache_hits_total The number of the searches answered from the cache.\n",
+        );
+        output.push_str("# TYPE websurfx_cache_hits_total counter\n");
+        let _ = writeln!(
+            output,
+            "websurfx_cache_hits_total {}",
+            self.cache_hits.load(Ordering::Relaxed)
+        );
+        output.push_str("# HELP websurfx_cache_misses_total The number of the searches not found in the cache.\n");
+        output.push_str("# TYPE websurfx_cache_misses_total counter\n");
+        let _ = writeln!(
+            output,
+            "websurfx_cache_misses_total {}",
+            self.cache_misses.load(Ordering::Relaxed)
+        );
+
+        output.push_str("# HELP websurfx_engine_responses_total The number of the responses of the upstream search engines per outcome.\n");
+        output.push_str("# TYPE websurfx_engine_responses_total counter\n");
+        for ((engine, outcome), count) in self
+            .engine_responses
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            let _ = writeln!(
+                output,
+                "websurfx_engine_responses_total{{engine=\"{}\",outcome=\"{outcome}\"}} {count}",
+                escape_label(engine),
+            );
+        }
+
+        let engine_stats = ENGINE_STATS.snapshot();
+        output.push_str("# HELP websurfx_engine_success_ratio The ratio of the recent responses of the upstream search engines with their results.\n");
+        output.push_str("# TYPE websurfx_engine_success_ratio gauge\n");
+        for stats in &engine_stats {
+            let _ = writeln!(
+                output,
+                "websurfx_engine_success_ratio{{engine=\"{}\"}} {}",
+                escape_label(&stats.engine),
+                stats.success_rate
+            );
+        }
+        output.push_str("# HELP websurfx_engine_latency_seconds The percentiles of the latency of the recent responses of the upstream search engines.\n");
+        output.push_str("# TYPE websurfx_engine_latency_seconds gauge\n");
+        for stats in &engine_stats {
+            for (quantile, latency_ms) in [
+                ("0.5", stats.p50_latency_ms),
+                ("0.95", stats.p95_latency_ms),
+            ] {
+                let _ = writeln!(
+                    output,
+                    "websurfx_engine_latency_seconds{{engine=\"{}\",quantile=\"{quantile}\"}} {}",
+                    escape_label(&stats.engine),
+                    latency_ms as f64 / 1000.0
+                );
+            }
+        }
+
+        output.push_str("# HELP websurfx_engine_blocked_seconds The time left until the upstream search engines which blocked the instance are sent requests again.\n");
+        output.push_str("# TYPE websurfx_engine_blocked_seconds gauge\n");
+        for blocked_engine in blocked_engines() {
+            let _ = writeln!(
+                output,
+                "websurfx_engine_blocked_seconds{{engine=\"{}\"}} {}",
+                escape_label(blocked_engine.engine),
+                blocked_engine.remaining.as_secs()
+            );
+        }
+
+        output
+    }
+}
+
+/// A helper function which escapes the backslashes, the double quotes and the line feeds of a
+/// label value, as required by the Prometheus text format.
+///
+/// # Arguments
+///
+/// * `value` - It takes the value of the label as an argument.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// A middleware (used with `wrap_fn`) which records the route, the method, the status and the
+/// duration of the requests handled by the server. The requests are labelled with the pattern of
+/// their route (like `/admin/engines/{engine}`) rather than their path, so that the number of the
+/// time series stays bounded.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be handled as an argument.
+/// * `service` - It takes the wrapped service as an argument.
+pub fn record_metrics<S, B>(
+    req: ServiceRequest,
+    service: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let started_at = Instant::now();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_owned());
+    let method = req.method().clone();
+    let response = service.call(req);
+
+    async move {
+        let response = response.await;
+        let status = match &response {
+            Ok(response) => response.status(),
+            Err(error) => error.as_response_error().status_code(),
+        };
+        METRICS.record_request(
+            &route,
+            method.as_str(),
+            status.as_u16(),
+            started_at.elapsed(),
+        );
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram_buckets_are_cumulative() {
+        let mut histogram = Histogram::new();
+        histogram.observe(Duration::from_millis(30));
+        histogram.observe(Duration::from_secs(20));
+
+        let mut output = String::new();
+        histogram.write(&mut output, "duration", "route=\"/search\"");
+        assert!(output.contains("duration_bucket{route=\"/search\",le=\"0.025\"} 0\n"));
+        assert!(output.contains("duration_bucket{route=\"/search\",le=\"0.05\"} 1\n"));
+        assert!(output.contains("duration_bucket{route=\"/search\",le=\"10\"} 1\n"));
+        assert!(output.contains("duration_bucket{route=\"/search\",le=\"+Inf\"} 2\n"));
+        assert!(output.contains("duration_count{route=\"/search\"} 2\n"));
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::new();
+        metrics.record_request("/search", "GET", 200, Duration::from_millis(120));
+        metrics.record_cache_lookup(true);
+        metrics.record_cache_lookup(false);
+        metrics.record_cache_lookup(false);
+        metrics.record_engine_response("duckduckgo", None);
+        metrics.record_engine_response("bing", Some(&EngineError::TimedOut));
+        metrics.record_engine_response("bing", Some(&EngineError::Blocked));
+        metrics.record_engine_response("bing", Some(&EngineError::RequestError));
+
+        let output = metrics.render();
+        assert!(output.contains(
+            "websurfx_http_requests_total{route=\"/search\",method=\"GET\",status=\"200\"} 1\n"
+        ));
+        assert!(output.contains(
+            "websurfx_http_request_duration_seconds_bucket{route=\"/search\",le=\"0.25\"} 1\n"
+        ));
+        assert!(output.contains("websurfx_aggregation_duration_seconds_count 0\n"));
+        assert!(output.contains("websurfx_cache_hits_total 1\n"));
+        assert!(output.contains("websurfx_cache_misses_total 2\n"));
+        assert!(output.contains(
+            "websurfx_engine_responses_total{engine=\"duckduckgo\",outcome=\"success\"} 1\n"
+        ));
+        assert!(output
+            .contains("websurfx_engine_responses_total{engine=\"bing\",outcome=\"timeout\"} 1\n"));
+        assert!(output
+            .contains("websurfx_engine_responses_total{engine=\"bing\",outcome=\"error\"} 1\n"));
+        assert!(output
+            .contains("websurfx_engine_responses_total{engine=\"bing\",outcome=\"blocked\"} 1\n"));
+        assert!(output.contains("# TYPE websurfx_engine_blocked_seconds gauge\n"));
+    }
+}
@@ -3,5 +3,23 @@
 //! the search route. Also, caches the next, current and previous search results in the search
 //! routes with the help of the redis server.
 
+pub mod bot_challenge;
+pub mod client_ip;
+pub mod csrf;
+pub mod error_pages;
+pub mod metrics;
+pub mod multipart;
+pub mod onion;
+pub mod profiles;
+pub mod rate_limit;
+pub mod request_log;
+pub mod request_size;
 pub mod router;
 pub mod routes;
+pub mod security_headers;
+pub mod shutdown;
+pub mod signing;
+pub mod static_files;
+pub mod telemetry;
+pub mod tls;
+pub mod usage_stats;
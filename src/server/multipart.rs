@@ -0,0 +1,137 @@
+//! This module provides the functionality to parse the `multipart/form-data` bodies of the forms
+//! uploading a file (like the image of the reverse image search). The bodies are small enough to
+//! be buffered, as they are limited by the `request_limits.max_body_size` option.
+
+/// A named struct which stores a field of a `multipart/form-data` body.
+pub struct MultipartField<'a> {
+    /// It stores the name of the field.
+    pub name: String,
+    /// It stores the name of the uploaded file, when the field is a file.
+    pub filename: Option<String>,
+    /// It stores the media type of the field, when it was provided.
+    pub content_type: Option<String>,
+    /// It stores the value of the field.
+    pub data: &'a [u8],
+}
+
+/// A function which parses the fields of a `multipart/form-data` body.
+///
+/// # Arguments
+///
+/// * `content_type` - It takes the `Content-Type` header of the request, which holds the boundary
+///   separating the fields, as an argument.
+/// * `body` - It takes the body of the request as an argument.
+///
+/// # Returns
+///
+/// It returns the fields of the body or `None` if the body is not a valid `multipart/form-data`
+/// body.
+pub fn parse_multipart<'a>(content_type: &str, body: &'a [u8]) -> Option<Vec<MultipartField<'a>>> {
+    let (media_type, params) = content_type.split_once(';')?;
+    if !media_type
+        .trim()
+        .eq_ignore_ascii_case("multipart/form-data")
+    {
+        return None;
+    }
+    let boundary = header_param(params, "boundary").filter(|boundary| !boundary.is_empty())?;
+    let delimiter = format!("\r\n--{boundary}");
+
+    // The preamble before the first boundary is ignored.
+    let first_boundary = find(body, &delimiter.as_bytes()[2..])?;
+    let mut rest = &body[first_boundary + delimiter.len() - 2..];
+    let mut fields = Vec::new();
+    // The last boundary is followed by `--`.
+    while !rest.starts_with(b"--") {
+        rest = rest.strip_prefix(b"\r\n")?;
+        let headers_end = find(rest, b"\r\n\r\n")?;
+        let headers = std::str::from_utf8(&rest[..headers_end]).ok()?;
+        rest = &rest[headers_end + 4..];
+        let data_end = find(rest, delimiter.as_bytes())?;
+        let data = &rest[..data_end];
+        rest = &rest[data_end + delimiter.len()..];
+
+        let (mut name, mut filename, mut content_type) = (None, None, None);
+        for header in headers.split("\r\n") {
+            let (header, value) = header.split_once(':')?;
+            if header.trim().eq_ignore_ascii_case("content-disposition") {
+                let params = value.split_once(';').map_or("", |(_, params)| params);
+                name = header_param(params, "name").map(str::to_owned);
+                filename = header_param(params, "filename").map(str::to_owned);
+            } else if header.trim().eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.trim().to_owned());
+            }
+        }
+        fields.push(MultipartField {
+            name: name?,
+            filename,
+            content_type,
+            data,
+        });
+    }
+
+    Some(fields)
+}
+
+/// A helper function which returns the value of a parameter of a header (like the `boundary` of
+/// the `Content-Type` header), without the quotes around it.
+///
+/// # Arguments
+///
+/// * `params` - It takes the parameters of the header (separated by `;`) as an argument.
+/// * `key` - It takes the name of the parameter as an argument.
+fn header_param<'a>(params: &'a str, key: &str) -> Option<&'a str> {
+    params.split(';').find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case(key)
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// A helper function which returns the position of the first occurrence of the needle in the
+/// haystack.
+///
+/// # Arguments
+///
+/// * `haystack` - It takes the bytes to search in as an argument.
+/// * `needle` - It takes the bytes to search for as an argument.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multipart() {
+        let body = b"preamble\r\n--xyz\r\n\
+            Content-Disposition: form-data; name=\"csrf_token\"\r\n\r\n\
+            abc\r\n--xyz\r\n\
+            Content-Disposition: form-data; name=\"image\"; filename=\"cat.png\"\r\n\
+            Content-Type: image/png\r\n\r\n\
+            \x89PNG\r\n\x1a\n\r\n--xyz--\r\n";
+
+        let fields = parse_multipart("multipart/form-data; boundary=\"xyz\"", body).unwrap();
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].name, "csrf_token");
+        assert_eq!(fields[0].data, b"abc");
+        assert_eq!(fields[0].filename, None);
+        assert_eq!(fields[1].name, "image");
+        assert_eq!(fields[1].filename.as_deref(), Some("cat.png"));
+        assert_eq!(fields[1].content_type.as_deref(), Some("image/png"));
+        assert_eq!(fields[1].data, b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn test_parse_invalid_multipart() {
+        let body = b"--xyz\r\nContent-Disposition: form-data; name=\"q\"\r\n\r\nrust";
+        // The body is truncated before the last boundary.
+        assert!(parse_multipart("multipart/form-data; boundary=xyz", body).is_none());
+        assert!(parse_multipart("application/x-www-form-urlencoded", b"q=rust").is_none());
+        assert!(parse_multipart("multipart/form-data; boundary=", body).is_none());
+    }
+}
@@ -0,0 +1,113 @@
+//! This module provides the functionality to advertise the onion address of the instance, so that
+//! the Tor Browser offers to switch to the Tor onion service when the instance is visited through
+//! its clearnet address.
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::header::{HeaderName, HeaderValue},
+    web, Error,
+};
+use std::future::Future;
+
+use crate::config::reloader::SharedConfig;
+
+/// The name of the header through which the onion address of the instance is advertised.
+pub const ONION_LOCATION: HeaderName = HeaderName::from_static("onion-location");
+
+/// A function which builds the url of the page with the provided path on the onion service.
+///
+/// # Arguments
+///
+/// * `onion_address` - It takes the url of the onion service (like `http://<address>.onion`) as
+///   an argument.
+/// * `path_and_query` - It takes the path and the query of the requested page as an argument.
+pub fn onion_location(onion_address: &str, path_and_query: &str) -> String {
+    format!("{}{path_and_query}", onion_address.trim_end_matches('/'))
+}
+
+/// A middleware (used with `wrap_fn`) which adds the `Onion-Location` header pointing at the
+/// same page on the onion service to all the responses, unless the onion service itself was
+/// requested.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be handled as an argument.
+/// * `service` - It takes the wrapped service as an argument.
+pub fn add_onion_location<S, B>(
+    req: ServiceRequest,
+    service: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let location = req
+        .app_data::<web::Data<SharedConfig>>()
+        .and_then(|config| config.load().onion_address.clone())
+        .filter(|_| !req.connection_info().host().ends_with(".onion"))
+        .and_then(|onion_address| {
+            let path_and_query = req
+                .uri()
+                .path_and_query()
+                .map_or("/", |path_and_query| path_and_query.as_str());
+            HeaderValue::try_from(onion_location(&onion_address, path_and_query)).ok()
+        });
+
+    let response = service.call(req);
+    async move {
+        let mut response = response.await?;
+        if let Some(location) = location {
+            response.headers_mut().insert(ONION_LOCATION, location);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parser::Config;
+    use actix_web::{
+        test::{call_service, init_service, TestRequest},
+        App, HttpResponse,
+    };
+
+    #[test]
+    fn test_onion_location() {
+        assert_eq!(
+            onion_location("http://example.onion/", "/search?q=rust"),
+            "http://example.onion/search?q=rust"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_add_onion_location() {
+        let config = Config {
+            onion_address: Some("http://example.onion".to_owned()),
+            ..Default::default()
+        };
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .wrap_fn(add_onion_location)
+                .route("/search", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+
+        let request = TestRequest::get()
+            .uri("/search?q=rust")
+            .insert_header(("host", "search.example.com"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(
+            response.headers().get(ONION_LOCATION).unwrap(),
+            "http://example.onion/search?q=rust"
+        );
+
+        let request = TestRequest::get()
+            .uri("/search?q=rust")
+            .insert_header(("host", "example.onion"))
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert!(!response.headers().contains_key(ONION_LOCATION));
+    }
+}
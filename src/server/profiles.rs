@@ -0,0 +1,630 @@
+//! This module provides the server-side preference profiles, which store the settings of the
+//! users on the instance instead of in their cookies (so that the settings are not bounded by the
+//! size of the cookies). The browser of the user only keeps the random token of its profile in the
+//! `profile` cookie, which is also shown to the user as a restore code to load the same settings
+//! on other browsers. The profiles are kept in memory or in the redis server under the hashes of
+//! their tokens (the ids of the profiles), so that the tokens cannot be read back from the store.
+//! The users of the profiles can also opt into keeping their search history in their profile and
+//! star the search results to keep them as bookmarks.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::config::parser::Config;
+#[cfg(feature = "redis-cache")]
+use crate::server::rate_limit::RedisStore;
+use serde::{Deserialize, Serialize};
+
+/// The name of the cookie storing the token of the profile of the user.
+pub const PROFILE_COOKIE: &str = "profile";
+
+/// The maximum size (in bytes) of the custom stylesheet which can be stored in a profile.
+pub const MAX_CUSTOM_CSS_SIZE: usize = 8 * 1024;
+
+/// The maximum number of bookmarks which can be kept in a profile.
+pub const MAX_BOOKMARKS: usize = 1000;
+
+/// The number of seconds in a day.
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The store in which the profiles are kept.
+enum ProfileStore {
+    /// The profiles are kept in the memory of the instance, keyed by the hashes of their tokens
+    /// along with the time (in seconds since the unix epoch) at which they expire.
+    Memory(Mutex<HashMap<String, (String, u64)>>),
+    /// The profiles are kept in the redis server, so that they are shared by all the replicas of
+    /// the instance.
+    #[cfg(feature = "redis-cache")]
+    Redis(Box<RedisStore>),
+}
+
+/// A named struct which stores a search kept in the search history of a profile.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// It stores the search query.
+    pub query: String,
+    /// It stores the time (in seconds since the unix epoch) at which the search was sent.
+    pub at: u64,
+}
+
+/// A named struct which stores a search result starred by the user and kept in the bookmarks of a
+/// profile.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// It stores the url of the search result.
+    pub url: String,
+    /// It stores the title of the search result.
+    pub title: String,
+    /// It stores the description of the search result.
+    #[serde(default)]
+    pub description: String,
+    /// It stores the time (in seconds since the unix epoch) at which the result was starred.
+    pub at: u64,
+}
+
+/// The server-side profile of the user, as shown on the settings page.
+pub enum ProfileStatus<'a> {
+    /// The profiles are not enabled on the instance.
+    Disabled,
+    /// The user has no profile (or it has expired).
+    Missing,
+    /// The user has a profile.
+    Saved {
+        /// It stores the restore code (the token) of the profile.
+        restore_code: &'a str,
+        /// It stores the serialized settings stored in the profile.
+        settings: &'a str,
+    },
+}
+
+/// The server-side preference profiles, which are only used when the `profiles.enabled` option is
+/// set.
+pub struct Profiles {
+    /// It stores the store in which the profiles are kept.
+    store: ProfileStore,
+}
+
+impl Profiles {
+    /// Creates the profiles kept in memory or in the redis server, as selected with the
+    /// `profiles.redis` option.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the redis url is invalid while the profiles should be kept in the
+    /// redis server.
+    #[cfg_attr(not(feature = "redis-cache"), allow(unused_variables))]
+    pub fn new(config: &Config) -> std::io::Result<Self> {
+        #[cfg(feature = "redis-cache")]
+        let store = match config.profiles.redis {
+            true => ProfileStore::Redis(Box::new(RedisStore::new(&config.redis_url)?)),
+            false => ProfileStore::Memory(Mutex::default()),
+        };
+        #[cfg(not(feature = "redis-cache"))]
+        let store = ProfileStore::Memory(Mutex::default());
+
+        Ok(Profiles { store })
+    }
+
+    /// A function which reads the settings stored in a profile, the expiry of the profile is
+    /// renewed every time it is read.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `token` - It takes the token of the profile as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the settings stored in the profile or `None` if the profile does not exist (or
+    /// has expired) or could not be read from the redis server.
+    pub async fn load(&self, config: &Config, token: &str) -> Option<String> {
+        if !is_profile_token(token) {
+            return None;
+        }
+        self.load_by_id(config, &profile_id(token)).await
+    }
+
+    /// A function which reads the settings stored in a profile from the id of the profile
+    /// instead of its token, the expiry of the profile is renewed every time it is read.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `id` - It takes the id of the profile (the hash of its token) as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the settings stored in the profile or `None` if the profile does not exist (or
+    /// has expired) or could not be read from the redis server.
+    pub async fn load_by_id(&self, config: &Config, id: &str) -> Option<String> {
+        if !is_profile_id(id) {
+            return None;
+        }
+        self.read(config, &profile_key(id)).await
+    }
+
+    /// A function which stores the settings of a profile, replacing the settings previously
+    /// stored in it.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `token` - It takes the token of the profile as an argument.
+    /// * `settings` - It takes the serialized settings of the user as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the profile could not be stored in the redis server.
+    pub async fn save(&self, config: &Config, token: &str, settings: &str) -> std::io::Result<()> {
+        self.write(config, &profile_key(&profile_id(token)), settings)
+            .await
+    }
+
+    /// A function which reads the search history kept in a profile, the most recent search
+    /// first.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `token` - It takes the token of the profile as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the searches of the history or an empty list if the profile has no history (or
+    /// it could not be read from the redis server).
+    pub async fn history(&self, config: &Config, token: &str) -> Vec<HistoryEntry> {
+        if !is_profile_token(token) {
+            return Vec::new();
+        }
+        self.read(config, &history_key(&profile_id(token)))
+            .await
+            .and_then(|history| serde_json::from_str(&history).ok())
+            .unwrap_or_default()
+    }
+
+    /// A function which adds a search to the search history kept in a profile. The search is
+    /// moved to the top of the history if it was already in it and the oldest searches are
+    /// dropped once the history holds more than `profiles.max_history` searches.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `token` - It takes the token of the profile as an argument.
+    /// * `query` - It takes the search query as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the history could not be stored in the redis server.
+    pub async fn record_search(
+        &self,
+        config: &Config,
+        token: &str,
+        query: &str,
+    ) -> std::io::Result<()> {
+        let mut history = self.history(config, token).await;
+        history.retain(|entry| entry.query != query);
+        history.insert(
+            0,
+            HistoryEntry {
+                query: query.to_owned(),
+                at: unix_time(),
+            },
+        );
+        history.truncate(config.profiles.max_history);
+
+        let history = serde_json::to_string(&history).map_err(std::io::Error::other)?;
+        self.write(config, &history_key(&profile_id(token)), &history)
+            .await
+    }
+
+    /// A function which deletes the search history kept in a profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - It takes the token of the profile as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the history could not be deleted from the redis server.
+    pub async fn clear_history(&self, token: &str) -> std::io::Result<()> {
+        self.delete(&history_key(&profile_id(token))).await
+    }
+
+    /// A function which reads the bookmarks kept in a profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `token` - It takes the token of the profile as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the bookmarks, the most recently starred result first, or an empty list if the
+    /// profile has no bookmarks (or they could not be read from the redis server).
+    pub async fn bookmarks(&self, config: &Config, token: &str) -> Vec<Bookmark> {
+        if !is_profile_token(token) {
+            return Vec::new();
+        }
+        self.read(config, &bookmarks_key(&profile_id(token)))
+            .await
+            .and_then(|bookmarks| serde_json::from_str(&bookmarks).ok())
+            .unwrap_or_default()
+    }
+
+    /// A function which adds a search result to the bookmarks kept in a profile. A result which
+    /// was already starred is moved to the top of the bookmarks and the oldest bookmarks are
+    /// dropped once the profile holds more than `MAX_BOOKMARKS` bookmarks.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `token` - It takes the token of the profile as an argument.
+    /// * `bookmark` - It takes the starred search result as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the bookmarks could not be stored in the redis server.
+    pub async fn add_bookmark(
+        &self,
+        config: &Config,
+        token: &str,
+        bookmark: Bookmark,
+    ) -> std::io::Result<()> {
+        let mut bookmarks = self.bookmarks(config, token).await;
+        bookmarks.retain(|starred| starred.url != bookmark.url);
+        bookmarks.insert(0, bookmark);
+        bookmarks.truncate(MAX_BOOKMARKS);
+        self.write_bookmarks(config, token, &bookmarks).await
+    }
+
+    /// A function which removes a search result from the bookmarks kept in a profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `token` - It takes the token of the profile as an argument.
+    /// * `url` - It takes the url of the search result as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the bookmarks could not be stored in the redis server.
+    pub async fn remove_bookmark(
+        &self,
+        config: &Config,
+        token: &str,
+        url: &str,
+    ) -> std::io::Result<()> {
+        let mut bookmarks = self.bookmarks(config, token).await;
+        bookmarks.retain(|starred| starred.url != url);
+        match bookmarks.is_empty() {
+            true => self.delete(&bookmarks_key(&profile_id(token))).await,
+            false => self.write_bookmarks(config, token, &bookmarks).await,
+        }
+    }
+
+    /// A helper function which stores the bookmarks of a profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `token` - It takes the token of the profile as an argument.
+    /// * `bookmarks` - It takes the bookmarks of the profile as an argument.
+    async fn write_bookmarks(
+        &self,
+        config: &Config,
+        token: &str,
+        bookmarks: &[Bookmark],
+    ) -> std::io::Result<()> {
+        let bookmarks = serde_json::to_string(bookmarks).map_err(std::io::Error::other)?;
+        self.write(config, &bookmarks_key(&profile_id(token)), &bookmarks)
+            .await
+    }
+
+    /// A helper function which reads a value from the store, the expiry of the value is renewed
+    /// every time it is read.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `key` - It takes the key of the value as an argument.
+    async fn read(&self, config: &Config, key: &str) -> Option<String> {
+        let expiry = u64::from(config.profiles.expiry_days) * SECONDS_PER_DAY;
+        match &self.store {
+            ProfileStore::Memory(profiles) => {
+                let now = unix_time();
+                let mut profiles = profiles.lock().unwrap_or_else(PoisonError::into_inner);
+                profiles.retain(|_, (_, expires_at)| *expires_at > now);
+                profiles.get_mut(key).map(|(value, expires_at)| {
+                    *expires_at = now + expiry;
+                    value.clone()
+                })
+            }
+            #[cfg(feature = "redis-cache")]
+            ProfileStore::Redis(redis_store) => {
+                let value: redis::RedisResult<(Option<String>,)> = async {
+                    redis::pipe()
+                        .get(key)
+                        .expire(key, expiry as i64)
+                        .ignore()
+                        .query_async(&mut redis_store.connection().await?)
+                        .await
+                }
+                .await;
+                value
+                    .map_err(|error| {
+                        tracing::error!("The profile could not be read from redis: {error}")
+                    })
+                    .ok()?
+                    .0
+            }
+        }
+    }
+
+    /// A helper function which stores a value in the store, replacing the value previously stored
+    /// under the same key.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `key` - It takes the key of the value as an argument.
+    /// * `value` - It takes the value to be stored as an argument.
+    async fn write(&self, config: &Config, key: &str, value: &str) -> std::io::Result<()> {
+        let expiry = u64::from(config.profiles.expiry_days) * SECONDS_PER_DAY;
+        match &self.store {
+            ProfileStore::Memory(profiles) => {
+                profiles
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(key.to_owned(), (value.to_owned(), unix_time() + expiry));
+                Ok(())
+            }
+            #[cfg(feature = "redis-cache")]
+            ProfileStore::Redis(redis_store) => {
+                let mut connection = redis_store
+                    .connection()
+                    .await
+                    .map_err(std::io::Error::other)?;
+                redis::cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("EX")
+                    .arg(expiry)
+                    .query_async::<_, ()>(&mut connection)
+                    .await
+                    .map_err(std::io::Error::other)
+            }
+        }
+    }
+
+    /// A helper function which deletes a value from the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key of the value as an argument.
+    async fn delete(&self, key: &str) -> std::io::Result<()> {
+        match &self.store {
+            ProfileStore::Memory(profiles) => {
+                profiles
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .remove(key);
+                Ok(())
+            }
+            #[cfg(feature = "redis-cache")]
+            ProfileStore::Redis(redis_store) => {
+                let mut connection = redis_store
+                    .connection()
+                    .await
+                    .map_err(std::io::Error::other)?;
+                redis::cmd("DEL")
+                    .arg(key)
+                    .query_async::<_, ()>(&mut connection)
+                    .await
+                    .map_err(std::io::Error::other)
+            }
+        }
+    }
+}
+
+/// A function which generates the random token of a new profile, which is also its restore code.
+pub fn new_profile_token() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// A function which checks whether the provided value has the format of the token of a profile
+/// (32 lowercase hexadecimal digits).
+///
+/// # Arguments
+///
+/// * `token` - It takes the value to be checked as an argument.
+pub fn is_profile_token(token: &str) -> bool {
+    token.len() == 32
+        && token
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
+
+/// A function which returns the id of a profile, which is the hash of its token so that the id
+/// can be shared (like in the url of the custom stylesheet of the profile) without revealing the
+/// token.
+///
+/// # Arguments
+///
+/// * `token` - It takes the token of the profile as an argument.
+pub fn profile_id(token: &str) -> String {
+    blake3::hash(token.as_bytes()).to_hex().to_string()
+}
+
+/// A helper function which checks whether the provided value has the format of the id of a
+/// profile (64 lowercase hexadecimal digits).
+///
+/// # Arguments
+///
+/// * `id` - It takes the value to be checked as an argument.
+fn is_profile_id(id: &str) -> bool {
+    id.len() == 64
+        && id
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
+
+/// A helper function which returns the key under which a profile is stored.
+///
+/// # Arguments
+///
+/// * `id` - It takes the id of the profile as an argument.
+fn profile_key(id: &str) -> String {
+    format!("websurfx:profile:{id}")
+}
+
+/// A helper function which returns the key under which the search history of a profile is
+/// stored.
+///
+/// # Arguments
+///
+/// * `id` - It takes the id of the profile as an argument.
+fn history_key(id: &str) -> String {
+    format!("websurfx:history:{id}")
+}
+
+/// A helper function which returns the key under which the bookmarks of a profile are stored.
+///
+/// # Arguments
+///
+/// * `id` - It takes the id of the profile as an argument.
+fn bookmarks_key(id: &str) -> String {
+    format!("websurfx:bookmarks:{id}")
+}
+
+/// A function which returns the current time in seconds since the unix epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_profiles() {
+        let config = Config::default();
+        let profiles = Profiles::new(&config).unwrap();
+        let token = new_profile_token();
+        assert!(is_profile_token(&token));
+        assert_eq!(profiles.load(&config, &token).await, None);
+
+        profiles.save(&config, &token, "{}").await.unwrap();
+        profiles
+            .save(&config, &token, r#"{"theme":"simple"}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            profiles.load(&config, &token).await.as_deref(),
+            Some(r#"{"theme":"simple"}"#)
+        );
+        assert_eq!(profiles.load(&config, &new_profile_token()).await, None);
+
+        // The profile can also be read from its id, which is not its token.
+        let id = profile_id(&token);
+        assert_ne!(id, token);
+        assert_eq!(
+            profiles.load_by_id(&config, &id).await.as_deref(),
+            Some(r#"{"theme":"simple"}"#)
+        );
+        assert_eq!(profiles.load_by_id(&config, &token).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_search_history() {
+        let mut config = Config::default();
+        config.profiles.max_history = 2;
+        let profiles = Profiles::new(&config).unwrap();
+        let token = new_profile_token();
+        assert!(profiles.history(&config, &token).await.is_empty());
+
+        for query in ["rust", "lua", "rust", "zig"] {
+            profiles
+                .record_search(&config, &token, query)
+                .await
+                .unwrap();
+        }
+        let queries = |history: Vec<HistoryEntry>| {
+            history
+                .into_iter()
+                .map(|entry| entry.query)
+                .collect::<Vec<String>>()
+        };
+        // The repeated searches are moved to the top and the oldest searches are dropped.
+        assert_eq!(
+            queries(profiles.history(&config, &token).await),
+            ["zig", "rust"]
+        );
+        assert!(profiles
+            .history(&config, &new_profile_token())
+            .await
+            .is_empty());
+
+        profiles.clear_history(&token).await.unwrap();
+        assert!(profiles.history(&config, &token).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bookmarks() {
+        let config = Config::default();
+        let profiles = Profiles::new(&config).unwrap();
+        let token = new_profile_token();
+        let bookmark = |url: &str| Bookmark {
+            url: url.to_owned(),
+            title: "Title".to_owned(),
+            description: String::new(),
+            at: unix_time(),
+        };
+        for url in ["https://a.com/", "https://b.com/", "https://a.com/"] {
+            profiles
+                .add_bookmark(&config, &token, bookmark(url))
+                .await
+                .unwrap();
+        }
+        let urls = |bookmarks: Vec<Bookmark>| {
+            bookmarks
+                .into_iter()
+                .map(|bookmark| bookmark.url)
+                .collect::<Vec<String>>()
+        };
+        // The results starred again are moved to the top instead of being kept twice.
+        assert_eq!(
+            urls(profiles.bookmarks(&config, &token).await),
+            ["https://a.com/", "https://b.com/"]
+        );
+        assert!(profiles
+            .bookmarks(&config, &new_profile_token())
+            .await
+            .is_empty());
+
+        profiles
+            .remove_bookmark(&config, &token, "https://a.com/")
+            .await
+            .unwrap();
+        assert_eq!(
+            urls(profiles.bookmarks(&config, &token).await),
+            ["https://b.com/"]
+        );
+    }
+
+    #[test]
+    fn test_profile_tokens() {
+        assert_ne!(new_profile_token(), new_profile_token());
+        assert!(!is_profile_token("0123456789ABCDEF0123456789abcdef"));
+        assert!(!is_profile_token("0123456789abcdef"));
+        assert!(!is_profile_token("../0123456789abcdef0123456789abc"));
+    }
+}
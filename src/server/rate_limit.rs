@@ -0,0 +1,748 @@
+//! This module provides the rate limiter of the search routes, which limits the searches sent by
+//! each client ip address, so that public instances are protected from the scrapers sending their
+//! queries through the instance. The rate of the clients is tracked in memory or in the redis
+//! server, so that it can be shared by all the replicas of the instance.
+
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{self, ContentType},
+    web, Error, HttpRequest, HttpResponse,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    rc::Rc,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use super::{
+    bot_challenge::unix_time,
+    client_ip::request_client_ip,
+    signing::{sign_url, verify_url},
+};
+use crate::config::{parser::Config, reloader::SharedConfig};
+
+/// The path of the route streaming the search results to the search page.
+const STREAM_ROUTE: &str = "/search/stream";
+
+/// The purpose for which the urls of the streamed search results are signed.
+const STREAM_URL_PURPOSE: &str = "stream";
+
+/// The number of seconds during which the search page can request its streamed search results.
+const STREAM_URL_EXPIRY: u64 = 60;
+
+/// The number of the clients tracked in memory above which the clients whose rate is no longer
+/// limited are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// The lua script which limits the rate of a client in the redis server with the same algorithm
+/// as the in memory store, using the clock of the redis server so that all the replicas agree on
+/// the time. It returns the number of microseconds after which the client should retry, or `0`
+/// if the request is allowed.
+#[cfg(feature = "redis-cache")]
+const REDIS_SCRIPT: &str = r"
+redis.replicate_commands()
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000000 + tonumber(time[2])
+local interval = tonumber(ARGV[1])
+local tolerance = tonumber(ARGV[2])
+local ahead = math.max((tonumber(redis.call('GET', KEYS[1])) or now) - now, 0)
+if ahead > tolerance then
+    return ahead - tolerance
+end
+redis.call('SET', KEYS[1], now + ahead + interval, 'PX', math.ceil((ahead + interval) / 1000))
+return 0
+";
+
+/// The number of requests a client can send at once and in a sustained way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quota {
+    /// It stores the time after which a client is allowed to send one more request.
+    interval: Duration,
+    /// It stores how far ahead of the current time the bucket of a client can be emptied before
+    /// its requests are rejected.
+    tolerance: Duration,
+}
+
+impl Quota {
+    /// Creates the quota allowing the provided burst of requests and sustained rate.
+    ///
+    /// # Arguments
+    ///
+    /// * `burst` - It takes the number of requests a client can send at once as an argument.
+    /// * `per_minute` - It takes the number of requests per minute a client can send in a
+    ///   sustained way as an argument.
+    pub fn new(burst: u32, per_minute: u32) -> Self {
+        let interval = Duration::from_secs(60) / per_minute.max(1);
+        Quota {
+            interval,
+            tolerance: interval * burst.saturating_sub(1),
+        }
+    }
+
+    /// A helper function which takes a token from the bucket of a client.
+    ///
+    /// # Arguments
+    ///
+    /// * `full_at` - It takes the time at which the bucket of the client will be full again as
+    ///   an argument.
+    /// * `now` - It takes the current time as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the new time at which the bucket will be full again, or the time after which
+    /// the client should retry if the bucket is empty.
+    fn reserve(&self, full_at: Instant, now: Instant) -> Result<Instant, Duration> {
+        let ahead = full_at.saturating_duration_since(now);
+        if ahead > self.tolerance {
+            return Err(ahead - self.tolerance);
+        }
+        Ok(now + ahead + self.interval)
+    }
+}
+
+/// The store in which the rate of the clients is tracked.
+enum RateLimitStore {
+    /// The rate of the clients is tracked in the memory of the instance.
+    Memory {
+        /// It stores the time at which the bucket of each client will be full again.
+        buckets: Mutex<HashMap<String, Instant>>,
+        /// It stores the number of the requests counted for each client.
+        requests: Mutex<HashMap<String, u64>>,
+        /// It stores the unix time until which each claimed key is kept.
+        claimed: Mutex<HashMap<String, u64>>,
+    },
+    /// The rate of the clients is tracked in the redis server, so that it is shared by all the
+    /// replicas of the instance.
+    #[cfg(feature = "redis-cache")]
+    Redis(Box<RedisStore>),
+}
+
+/// The redis server in which the rate of the clients (or the usage statistics) are tracked.
+#[cfg(feature = "redis-cache")]
+pub(crate) struct RedisStore {
+    /// It stores the client of the redis server.
+    client: redis::Client,
+    /// It stores the connection to the redis server, which is established with the first request.
+    connection: tokio::sync::OnceCell<redis::aio::ConnectionManager>,
+}
+
+#[cfg(feature = "redis-cache")]
+impl RedisStore {
+    /// Creates the store of the redis server with the provided url, the connection is only
+    /// established with the first request.
+    ///
+    /// # Arguments
+    ///
+    /// * `redis_url` - It takes the url of the redis server as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the redis url is invalid.
+    pub(crate) fn new(redis_url: &str) -> std::io::Result<Self> {
+        Ok(RedisStore {
+            client: redis::Client::open(redis_url).map_err(std::io::Error::other)?,
+            connection: tokio::sync::OnceCell::new(),
+        })
+    }
+
+    /// A function which returns the connection to the redis server, it is established with the
+    /// first call.
+    ///
+    /// # Error
+    ///
+    /// Returns a redis error if the redis server could not be reached.
+    pub(crate) async fn connection(&self) -> redis::RedisResult<redis::aio::ConnectionManager> {
+        self.connection
+            .get_or_try_init(|| self.client.get_connection_manager())
+            .await
+            .cloned()
+    }
+}
+
+/// A rate limiter which allows each client to send a burst of requests and then limits it to a
+/// sustained rate of requests (with the generic cell rate algorithm, a variant of the token bucket
+/// which only stores a single time per client). It also counts the requests of the clients, so
+/// that the usage of the api keys can be reported.
+pub struct ClientRateLimiter {
+    /// It stores the store in which the rate of the clients is tracked.
+    store: RateLimitStore,
+}
+
+impl ClientRateLimiter {
+    /// Creates a rate limiter tracking the rate of the clients in memory or in the redis server.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `redis` - It takes whether the rate of the clients is tracked in the redis server as an
+    ///   argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the redis url is invalid while the rate of the clients should be
+    /// tracked in the redis server.
+    #[cfg_attr(not(feature = "redis-cache"), allow(unused_variables))]
+    pub fn new(config: &Config, redis: bool) -> std::io::Result<Self> {
+        let memory_store = || RateLimitStore::Memory {
+            buckets: Mutex::default(),
+            requests: Mutex::default(),
+            claimed: Mutex::default(),
+        };
+
+        #[cfg(feature = "redis-cache")]
+        let store = match redis {
+            true => RateLimitStore::Redis(Box::new(RedisStore::new(&config.redis_url)?)),
+            false => memory_store(),
+        };
+        #[cfg(not(feature = "redis-cache"))]
+        let store = memory_store();
+
+        Ok(ClientRateLimiter { store })
+    }
+
+    /// A function which checks whether a new request of the provided client is within its quota.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - It takes the key identifying the client (like its ip address) as an argument.
+    /// * `quota` - It takes the quota of the client as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the time after which the client should retry if the request is rejected, the
+    /// requests are allowed if the rate cannot be tracked in the redis server.
+    pub async fn check(&self, client: &str, quota: &Quota) -> Result<(), Duration> {
+        match &self.store {
+            RateLimitStore::Memory { buckets, .. } => {
+                let now = Instant::now();
+                let mut buckets = buckets.lock().unwrap_or_else(PoisonError::into_inner);
+                if buckets.len() >= MAX_TRACKED_CLIENTS {
+                    buckets.retain(|_, full_at| *full_at > now);
+                }
+                let full_at = buckets.entry(client.to_owned()).or_insert(now);
+                *full_at = quota.reserve(*full_at, now)?;
+                Ok(())
+            }
+            #[cfg(feature = "redis-cache")]
+            RateLimitStore::Redis(redis_store) => {
+                let retry_after: redis::RedisResult<u64> = async {
+                    redis::Script::new(REDIS_SCRIPT)
+                        .key(format!("websurfx:rate_limit:{client}"))
+                        .arg(quota.interval.as_micros() as u64)
+                        .arg(quota.tolerance.as_micros() as u64)
+                        .invoke_async(&mut redis_store.connection().await?)
+                        .await
+                }
+                .await;
+                match retry_after {
+                    Ok(0) => Ok(()),
+                    Ok(micros) => Err(Duration::from_micros(micros)),
+                    Err(error) => {
+                        tracing::error!(
+                            "The rate of the client could not be tracked in redis: {error}"
+                        );
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+
+    /// A function which counts a new request of the provided client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - It takes the key identifying the client (like the name of its api key) as an
+    ///   argument.
+    pub async fn count_request(&self, client: &str) {
+        match &self.store {
+            RateLimitStore::Memory { requests, .. } => {
+                *requests
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .entry(client.to_owned())
+                    .or_default() += 1;
+            }
+            #[cfg(feature = "redis-cache")]
+            RateLimitStore::Redis(redis_store) => {
+                use redis::AsyncCommands;
+
+                let counted: redis::RedisResult<u64> = async {
+                    redis_store
+                        .connection()
+                        .await?
+                        .incr(format!("websurfx:requests:{client}"), 1)
+                        .await
+                }
+                .await;
+                if let Err(error) = counted {
+                    tracing::error!(
+                        "The request of the client could not be counted in redis: {error}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// A function which returns the number of the requests counted for the provided client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - It takes the key identifying the client as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the number of the requests or `None` if it could not be read from the redis
+    /// server.
+    pub async fn requests(&self, client: &str) -> Option<u64> {
+        match &self.store {
+            RateLimitStore::Memory { requests, .. } => Some(
+                requests
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .get(client)
+                    .copied()
+                    .unwrap_or_default(),
+            ),
+            #[cfg(feature = "redis-cache")]
+            RateLimitStore::Redis(redis_store) => {
+                use redis::AsyncCommands;
+
+                let requests: redis::RedisResult<Option<u64>> = async {
+                    redis_store
+                        .connection()
+                        .await?
+                        .get(format!("websurfx:requests:{client}"))
+                        .await
+                }
+                .await;
+                requests
+                    .map(Option::unwrap_or_default)
+                    .map_err(|error| {
+                        tracing::error!(
+                            "The requests of the client could not be read from redis: {error}"
+                        )
+                    })
+                    .ok()
+            }
+        }
+    }
+
+    /// A function which claims the provided key until the provided unix time, so that what it
+    /// identifies (like the signature of an url) can only be used once.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - It takes the key to be claimed as an argument.
+    /// * `expires` - It takes the unix time until which the key is kept as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns `true` if the key was not claimed before, the keys are allowed if they cannot be
+    /// claimed in the redis server.
+    pub async fn claim(&self, key: &str, expires: u64) -> bool {
+        match &self.store {
+            RateLimitStore::Memory { claimed, .. } => {
+                let now = unix_time();
+                let mut claimed = claimed.lock().unwrap_or_else(PoisonError::into_inner);
+                if claimed.len() >= MAX_TRACKED_CLIENTS {
+                    claimed.retain(|_, expires| *expires >= now);
+                }
+                match claimed.get(key) {
+                    Some(claimed_until) if *claimed_until >= now => false,
+                    _ => {
+                        claimed.insert(key.to_owned(), expires);
+                        true
+                    }
+                }
+            }
+            #[cfg(feature = "redis-cache")]
+            RateLimitStore::Redis(redis_store) => {
+                let claimed: redis::RedisResult<Option<String>> = async {
+                    redis::cmd("SET")
+                        .arg(format!("websurfx:claimed:{key}"))
+                        .arg(1)
+                        .arg("NX")
+                        .arg("EX")
+                        .arg(expires.saturating_sub(unix_time()).max(1))
+                        .query_async(&mut redis_store.connection().await?)
+                        .await
+                }
+                .await;
+                claimed
+                    .map(|claimed| claimed.is_some())
+                    .unwrap_or_else(|error| {
+                        tracing::error!("The key could not be claimed in redis: {error}");
+                        true
+                    })
+            }
+        }
+    }
+}
+
+/// A function which returns the value of the `Retry-After` header for the provided time, it is
+/// rounded up to a whole number of seconds so that the client does not retry too early.
+///
+/// # Arguments
+///
+/// * `retry_after` - It takes the time after which the client should retry as an argument.
+pub fn retry_after_secs(retry_after: Duration) -> u64 {
+    (retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)).max(1)
+}
+
+/// A helper function which checks whether the requested path is one of the search routes (the
+/// search page, the json api, the streamed search results and the other search routes).
+///
+/// # Arguments
+///
+/// * `path` - It takes the requested path as an argument.
+pub fn is_search_route(path: &str) -> bool {
+    path == "/search" || path.starts_with("/search/")
+}
+
+/// A function which signs the url of the streamed search results of the search page for the
+/// provided client, so that the request streaming the results is not counted as another search
+/// (the search page requesting it was already counted). The signature expires after a minute and
+/// can only be used once.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `ip` - It takes the ip address of the client as an argument.
+/// * `url` - It takes the url of the streamed search results as an argument.
+///
+/// # Returns
+///
+/// It returns the url with the `expires` and the `signature` parameters.
+pub fn sign_stream_url(config: &Config, ip: IpAddr, url: &str) -> String {
+    let query = url.split_once('?').map_or("", |(_, query)| query);
+    let expires = unix_time() + STREAM_URL_EXPIRY;
+    let signature = sign_url(
+        config,
+        STREAM_URL_PURPOSE,
+        &format!("{ip}:{expires}:{query}"),
+    );
+    format!("{url}&expires={expires}&signature={signature}")
+}
+
+/// A named struct which stores in the extensions of a request whether it streams the search
+/// results of a search page, so that the signature of its url is only claimed once.
+#[derive(Clone, Copy)]
+struct StreamFollowUp(bool);
+
+/// A function which checks whether the request streams the search results of a search page sent
+/// to the same client, that is whether it carries a valid signature from `sign_stream_url` which
+/// has neither expired nor been used by another request yet. The signature is claimed in the
+/// provided rate limiter until it expires, so that the signed url cannot be replayed to search
+/// without being counted.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `limiter` - It takes the rate limiter in which the signature is claimed as an argument.
+pub async fn is_stream_follow_up(req: &HttpRequest, limiter: &ClientRateLimiter) -> bool {
+    // The middlewares checking the same request agree on it, as the signature can only be claimed
+    // by the first of them.
+    let checked = req.extensions().get::<StreamFollowUp>().copied();
+    if let Some(StreamFollowUp(follow_up)) = checked {
+        return follow_up;
+    }
+
+    let follow_up = match stream_signature(req) {
+        Some((expires, signature)) => limiter.claim(signature, expires).await,
+        None => false,
+    };
+    req.extensions_mut().insert(StreamFollowUp(follow_up));
+    follow_up
+}
+
+/// A helper function which returns the expiry and the signature of the url of the streamed search
+/// results when the signature is valid for the client and has not expired yet.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+fn stream_signature(req: &HttpRequest) -> Option<(u64, &str)> {
+    let config = req
+        .app_data::<web::Data<SharedConfig>>()
+        .filter(|_| req.path() == STREAM_ROUTE)?;
+    let (query, signed) = req.query_string().rsplit_once("&expires=")?;
+    let (expires, signature) = signed.split_once("&signature=")?;
+    let expires = expires
+        .parse::<u64>()
+        .ok()
+        .filter(|expires| *expires >= unix_time())?;
+
+    verify_url(
+        &config.load(),
+        STREAM_URL_PURPOSE,
+        &format!("{}:{expires}:{query}", request_client_ip(req)),
+        signature,
+    )
+    .then_some((expires, signature))
+}
+
+/// A middleware which limits the rate of the searches sent by each client ip address with the
+/// `search_rate_limiter` option and answers the rejected searches with the `429 Too Many
+/// Requests` status and the `Retry-After` header. The other routes are not limited by it.
+#[derive(Clone)]
+pub struct SearchRateLimit {
+    /// It stores the rate limiter in which the searches of the clients are tracked.
+    limiter: Arc<ClientRateLimiter>,
+    /// It stores the quota of the searches of each client.
+    quota: Quota,
+}
+
+impl SearchRateLimit {
+    /// Creates the rate limiter of the search routes from the `search_rate_limiter` option.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the redis url is invalid while the rate of the clients should be
+    /// tracked in the redis server.
+    pub fn new(config: &Config) -> std::io::Result<Self> {
+        let options = &config.search_rate_limiter;
+        Ok(SearchRateLimit {
+            limiter: Arc::new(ClientRateLimiter::new(config, options.redis)?),
+            quota: Quota::new(options.burst, options.per_minute),
+        })
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SearchRateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = SearchRateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SearchRateLimitMiddleware {
+            service: Rc::new(service),
+            rate_limit: self.clone(),
+        }))
+    }
+}
+
+/// The service created by the `SearchRateLimit` middleware.
+pub struct SearchRateLimitMiddleware<S> {
+    /// It stores the wrapped service.
+    service: Rc<S>,
+    /// It stores the rate limiter of the search routes.
+    rate_limit: SearchRateLimit,
+}
+
+impl<S, B> Service<ServiceRequest> for SearchRateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let rate_limit = self.rate_limit.clone();
+
+        Box::pin(async move {
+            // The results streamed to the search page were counted with the page itself.
+            if !is_search_route(req.path())
+                || is_stream_follow_up(req.request(), &rate_limit.limiter).await
+            {
+                return service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body);
+            }
+
+            let ip = request_client_ip(req.request());
+            match rate_limit
+                .limiter
+                .check(&ip.to_string(), &rate_limit.quota)
+                .await
+            {
+                Ok(()) => service
+                    .call(req)
+                    .await
+                    .map(ServiceResponse::map_into_left_body),
+                Err(retry_after) => {
+                    let retry_after = retry_after_secs(retry_after);
+                    let response = HttpResponse::TooManyRequests()
+                        .insert_header((header::RETRY_AFTER, retry_after))
+                        .content_type(ContentType::plaintext())
+                        .body(format!("Too many searches, retry in {retry_after} seconds"));
+                    Ok(req.into_response(response).map_into_right_body())
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::parser_models::SearchRateLimiter;
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, TestRequest},
+        web, App,
+    };
+
+    #[test]
+    fn test_reserve() {
+        let quota = Quota::new(2, 60);
+        let now = Instant::now();
+
+        // The burst is allowed right away and the following requests are rejected.
+        let full_at = quota.reserve(now, now).unwrap();
+        let full_at = quota.reserve(full_at, now).unwrap();
+        assert_eq!(quota.reserve(full_at, now), Err(Duration::from_secs(1)));
+
+        // A request is allowed again once a token was refilled.
+        let later = now + Duration::from_secs(1);
+        let full_at = quota.reserve(full_at, later).unwrap();
+        assert_eq!(
+            quota.reserve(full_at, later + Duration::from_millis(400)),
+            Err(Duration::from_millis(600))
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_check() {
+        let limiter = ClientRateLimiter::new(&Config::default(), false).unwrap();
+        let quota = Quota::new(2, 1);
+
+        assert_eq!(limiter.check("203.0.113.7", &quota).await, Ok(()));
+        assert_eq!(limiter.check("203.0.113.7", &quota).await, Ok(()));
+        let retry_after = limiter.check("203.0.113.7", &quota).await.unwrap_err();
+        assert!(retry_after > Duration::from_secs(59) && retry_after <= Duration::from_secs(60));
+        assert_eq!(retry_after_secs(retry_after), 60);
+        // The clients are limited separately.
+        assert_eq!(limiter.check("203.0.113.8", &quota).await, Ok(()));
+    }
+
+    #[actix_web::test]
+    async fn test_count_request() {
+        let limiter = ClientRateLimiter::new(&Config::default(), false).unwrap();
+
+        assert_eq!(limiter.requests("alice").await, Some(0));
+        limiter.count_request("alice").await;
+        limiter.count_request("alice").await;
+        assert_eq!(limiter.requests("alice").await, Some(2));
+        assert_eq!(limiter.requests("bob").await, Some(0));
+    }
+
+    #[actix_web::test]
+    async fn test_search_rate_limit() {
+        let rate_limit = SearchRateLimit::new(&Config {
+            search_rate_limiter: SearchRateLimiter {
+                burst: 1,
+                per_minute: 1,
+                redis: false,
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        let app = init_service(
+            App::new()
+                .wrap(rate_limit)
+                .route("/search", web::get().to(HttpResponse::Ok))
+                .route("/about", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let request = |path: &str| {
+            TestRequest::get()
+                .uri(path)
+                .peer_addr("203.0.113.7:4711".parse().unwrap())
+                .to_request()
+        };
+
+        assert_eq!(
+            call_service(&app, request("/search")).await.status(),
+            StatusCode::OK
+        );
+        let response = call_service(&app, request("/search")).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "60");
+        // The other routes are not limited.
+        assert_eq!(
+            call_service(&app, request("/about")).await.status(),
+            StatusCode::OK
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_stream_follow_up_is_not_counted() {
+        let config = Config {
+            search_rate_limiter: SearchRateLimiter {
+                burst: 1,
+                per_minute: 1,
+                redis: false,
+            },
+            ..Default::default()
+        };
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let signed_url = sign_stream_url(&config, ip, "search/stream?q=rust");
+        let app = init_service(
+            App::new()
+                .wrap(SearchRateLimit::new(&config).unwrap())
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .route("/search", web::get().to(HttpResponse::Ok))
+                .route("/search/stream", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let request = |path: &str, ip: &str| {
+            TestRequest::get()
+                .uri(path)
+                .peer_addr(format!("{ip}:4711").parse().unwrap())
+                .to_request()
+        };
+
+        assert_eq!(
+            call_service(&app, request("/search?q=rust", "203.0.113.7"))
+                .await
+                .status(),
+            StatusCode::OK
+        );
+        // The stream requested by the search page is not counted as another search.
+        assert_eq!(
+            call_service(&app, request(&format!("/{signed_url}"), "203.0.113.7"))
+                .await
+                .status(),
+            StatusCode::OK
+        );
+        // The signed url can only be used once.
+        let response = call_service(&app, request(&format!("/{signed_url}"), "203.0.113.7")).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        // The streams whose url was not signed for the client are counted.
+        let response = call_service(&app, request("/search/stream?q=rust", "203.0.113.7")).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let tampered_url = signed_url.replace("q=rust", "q=other");
+        let response =
+            call_service(&app, request(&format!("/{tampered_url}"), "203.0.113.7")).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            call_service(&app, request(&format!("/{signed_url}"), "203.0.113.8"))
+                .await
+                .status(),
+            StatusCode::OK
+        );
+        let response = call_service(&app, request(&format!("/{signed_url}"), "203.0.113.8")).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -0,0 +1,135 @@
+//! This module provides the middleware which runs each request within its own span carrying a
+//! request id, so that all the logs written while handling a request (including the ones of the
+//! upstream search engines) can be told apart, and which logs the completed requests.
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderName, HeaderValue},
+    Error, HttpMessage,
+};
+use std::{future::Future, time::Instant};
+use tracing::Instrument;
+
+use crate::{
+    query::privacy::{loggable_referer, loggable_request_line},
+    server::client_ip::request_client_ip,
+};
+
+/// The number of random bytes of a request id.
+const REQUEST_ID_BYTES: usize = 8;
+
+/// The header carrying the id of the request in the responses.
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// A named struct which stores the id of the request in the extensions of the request.
+#[derive(Clone)]
+pub struct RequestId(pub String);
+
+/// A function which generates a new random request id.
+///
+/// # Returns
+///
+/// It returns the request id as a hex string.
+pub fn new_request_id() -> String {
+    rand::random::<[u8; REQUEST_ID_BYTES]>()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// A middleware (used with `wrap_fn`) which handles each request within a `request` span
+/// carrying the request id, sends the request id back in the `X-Request-Id` header (so that the
+/// users can report the failures which the operators can find in the logs) and logs the
+/// completed requests with their status and duration. The
+/// search queries in the request line and in the `Referer` header are passed through the privacy
+/// module before they are logged.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be handled as an argument.
+/// * `service` - It takes the wrapped service as an argument.
+pub fn trace_requests<S, B>(
+    req: ServiceRequest,
+    service: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let request_id = new_request_id();
+    let span = tracing::info_span!("request", request_id = %request_id);
+    let request_line = loggable_request_line(&req);
+    let referer = loggable_referer(&req);
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .unwrap_or("-")
+        .to_owned();
+    // The request id is only made of hex digits, so it is always a valid header value.
+    let header_value = HeaderValue::from_str(&request_id).ok();
+    req.extensions_mut().insert(RequestId(request_id));
+
+    let started_at = Instant::now();
+    let response = span.in_scope(|| service.call(req));
+
+    async move {
+        let mut response = response.await;
+        if let (Ok(response), Some(header_value)) = (&mut response, header_value) {
+            response.headers_mut().insert(X_REQUEST_ID, header_value);
+        }
+        let duration_ms = started_at.elapsed().as_millis() as u64;
+        match &response {
+            Ok(response) => tracing::info!(
+                client_ip = %request_client_ip(response.request()),
+                request = %request_line,
+                status = response.status().as_u16(),
+                referer = %referer,
+                user_agent = %user_agent,
+                duration_ms,
+                "request completed"
+            ),
+            Err(error) => tracing::info!(
+                request = %request_line,
+                status = error.as_response_error().status_code().as_u16(),
+                referer = %referer,
+                user_agent = %user_agent,
+                duration_ms,
+                "request failed"
+            ),
+        }
+        response
+    }
+    .instrument(span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        test::{call_service, init_service, read_body, TestRequest},
+        web, App, HttpRequest, HttpResponse,
+    };
+
+    #[actix_web::test]
+    async fn test_trace_requests() {
+        let app = init_service(App::new().wrap_fn(trace_requests).route(
+            "/",
+            web::get().to(|req: HttpRequest| async move {
+                let request_id = req.extensions().get::<RequestId>().cloned();
+                match request_id {
+                    Some(RequestId(request_id)) => HttpResponse::Ok().body(request_id),
+                    None => HttpResponse::InternalServerError().finish(),
+                }
+            }),
+        ))
+        .await;
+
+        let response = call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        assert!(response.status().is_success());
+        let header = response.headers().get(X_REQUEST_ID).cloned().unwrap();
+        let request_id = read_body(response).await;
+        assert_eq!(request_id, header.as_bytes());
+        assert_eq!(request_id.len(), REQUEST_ID_BYTES * 2);
+        assert!(request_id.iter().all(u8::is_ascii_hexdigit));
+    }
+}
@@ -0,0 +1,141 @@
+//! This module provides the functionality to reject the oversized requests (with a too long query
+//! string, too large cookies or a too large body) before they are parsed, with the limits
+//! provided via the `request_limits` option.
+
+use actix_web::{
+    body::EitherBody,
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::{header, StatusCode},
+    web, Error, HttpResponse,
+};
+use futures::future::{ready, Either, Ready};
+use std::future::Future;
+
+use crate::{config::reloader::SharedConfig, models::parser_models::RequestLimits};
+
+/// A function which checks the size of the query string, of the cookies and of the body of the
+/// request against the provided limits.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be checked as an argument.
+/// * `limits` - It takes the limits provided via the `request_limits` option as an argument.
+///
+/// # Returns
+///
+/// It returns `None` if the request is within the limits otherwise it returns the status with
+/// which the request should be rejected.
+pub fn oversized_status(req: &ServiceRequest, limits: &RequestLimits) -> Option<StatusCode> {
+    if req.query_string().len() > limits.max_query_string_length {
+        return Some(StatusCode::URI_TOO_LONG);
+    }
+
+    let cookie_size: usize = req
+        .headers()
+        .get_all(header::COOKIE)
+        .map(|cookie| cookie.len())
+        .sum();
+    if cookie_size > limits.max_cookie_size {
+        return Some(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE);
+    }
+
+    let body_size = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<usize>().ok());
+    if body_size.is_some_and(|size| size > limits.max_body_size) {
+        return Some(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    None
+}
+
+/// A middleware (used with `wrap_fn`) which rejects the oversized requests with an empty response
+/// (which is replaced with the error page) instead of passing them to the routes.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be handled as an argument.
+/// * `service` - It takes the wrapped service as an argument.
+pub fn limit_request_size<S, B>(
+    req: ServiceRequest,
+    service: &S,
+) -> Either<
+    impl Future<Output = Result<ServiceResponse<EitherBody<B>>, Error>>,
+    Ready<Result<ServiceResponse<EitherBody<B>>, Error>>,
+>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let status = req
+        .app_data::<web::Data<SharedConfig>>()
+        .and_then(|config| oversized_status(&req, &config.load().request_limits));
+
+    match status {
+        Some(status) => Either::Right(ready(Ok(req
+            .into_response(HttpResponse::build(status).finish())
+            .map_into_right_body()))),
+        None => {
+            let response = service.call(req);
+            Either::Left(async move { Ok(response.await?.map_into_left_body()) })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parser::Config;
+    use actix_web::{
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+
+    #[actix_web::test]
+    async fn test_limit_request_size() {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(Config::default())))
+                .wrap_fn(limit_request_size)
+                .route("/search", web::route().to(HttpResponse::Ok)),
+        )
+        .await;
+        let limits = RequestLimits::default();
+
+        let request = TestRequest::get().uri("/search?q=rust").to_request();
+        assert_eq!(call_service(&app, request).await.status(), StatusCode::OK);
+
+        let request = TestRequest::get()
+            .uri(&format!(
+                "/search?q={}",
+                "a".repeat(limits.max_query_string_length)
+            ))
+            .to_request();
+        assert_eq!(
+            call_service(&app, request).await.status(),
+            StatusCode::URI_TOO_LONG
+        );
+
+        let request = TestRequest::get()
+            .uri("/search?q=rust")
+            .insert_header((
+                header::COOKIE,
+                format!("appCookie={}", "a".repeat(limits.max_cookie_size)),
+            ))
+            .to_request();
+        assert_eq!(
+            call_service(&app, request).await.status(),
+            StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE
+        );
+
+        let request = TestRequest::post()
+            .uri("/search")
+            .set_payload("a".repeat(limits.max_body_size + 1))
+            .to_request();
+        assert_eq!(
+            call_service(&app, request).await.status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
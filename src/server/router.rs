@@ -2,54 +2,100 @@
 //! meta search engine website and provide appropriate response to each route/page
 //! when requested.
 
+#[cfg(feature = "embed-assets")]
+use crate::server::static_files::embedded_file_response;
 use crate::{
-    config::parser::Config,
+    config::reloader::SharedConfig,
     handler::{file_path, FileType},
+    server::{
+        csrf::{csrf_cookie, csrf_token},
+        error_pages::{error_response, status_message},
+        profiles::{ProfileStatus, Profiles, PROFILE_COOKIE},
+        routes::settings::{encode_preferences, user_settings, PREFERENCES_PARAM},
+        static_files::STATIC_CACHE_CONTROL,
+    },
+};
+use actix_files::NamedFile;
+use actix_web::{
+    get,
+    http::header::{self, ContentType},
+    web, HttpRequest, HttpResponse,
 };
-use actix_web::{get, http::header::ContentType, web, HttpRequest, HttpResponse};
-use std::fs::read_to_string;
 
-/// Handles the route of index page or main page of the `websurfx` meta search engine website.
+/// Handles the route of index page or main page of the `websurfx` meta search engine website, the
+/// page is sent along with the `csrf_token` cookie whose token is embedded into its search form.
 #[get("/")]
-pub async fn index(config: web::Data<Config>) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
-        crate::templates::views::index::index(
-            &config.style.colorscheme,
-            &config.style.theme,
-            &config.style.animation,
-        )
-        .0,
-    ))
+pub async fn index(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config = config.load();
+    let csrf_token = csrf_token(&req);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .cookie(csrf_cookie(&req, &csrf_token))
+        .body(
+            crate::templates::views::index::index(
+                &config.style.colorscheme,
+                &config.style.theme,
+                &config.style.animation,
+                &csrf_token,
+            )
+            .0,
+        ))
 }
 
 /// Handles the route of any other accessed route/page which is not provided by the
-/// website essentially the 404 error page.
-pub async fn not_found(
-    config: web::Data<Config>,
-) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
-        crate::templates::views::not_found::not_found(
-            &config.style.colorscheme,
-            &config.style.theme,
-            &config.style.animation,
-        )
-        .0,
-    ))
+/// website essentially the 404 error page (or a json error for the api clients).
+pub async fn not_found(req: HttpRequest) -> HttpResponse {
+    error_response(
+        &req,
+        StatusCode::NOT_FOUND,
+        status_message(StatusCode::NOT_FOUND),
+    )
 }
 
-/// Handles the route of robots.txt page of the `websurfx` meta search engine website.
+/// Handles the route of robots.txt page of the `websurfx` meta search engine website, it is
+/// served like the other static files of the theme folder.
 #[get("/robots.txt")]
-pub async fn robots_data(_req: HttpRequest) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    let page_content: String =
-        read_to_string(format!("{}/robots.txt", file_path(FileType::Theme)?))?;
-    Ok(HttpResponse::Ok()
-        .content_type(ContentType::plaintext())
-        .body(page_content))
+pub async fn robots_data(req: HttpRequest) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let file = match file_path(FileType::Theme) {
+        Ok(public_folder_path) => {
+            NamedFile::open_async(format!("{}/robots.txt", public_folder_path)).await
+        }
+        Err(error) => Err(error),
+    };
+
+    let mut response = match file {
+        Ok(file) => file.into_response(&req),
+        Err(error) => embedded_robots_data(&req).ok_or(error)?,
+    };
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, STATIC_CACHE_CONTROL);
+    Ok(response)
+}
+
+/// A helper function which serves the robots.txt file embedded into the binary when it is
+/// missing from the theme folder.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+#[cfg_attr(not(feature = "embed-assets"), allow(unused_variables))]
+fn embedded_robots_data(req: &HttpRequest) -> Option<HttpResponse> {
+    #[cfg(feature = "embed-assets")]
+    return embedded_file_response(req, "robots.txt");
+    #[cfg(not(feature = "embed-assets"))]
+    None
 }
 
 /// Handles the route of about page of the `websurfx` meta search engine website.
 #[get("/about")]
-pub async fn about(config: web::Data<Config>) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+pub async fn about(
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config = config.load();
     Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
         crate::templates::views::about::about(
             &config.style.colorscheme,
@@ -60,19 +106,61 @@ pub async fn about(config: web::Data<Config>) -> Result<HttpResponse, Box<dyn st
     ))
 }
 
-/// Handles the route of settings page of the `websurfx` meta search engine website.
+/// Handles the route of settings page of the `websurfx` meta search engine website, the page is
+/// sent along with the `csrf_token` cookie whose token is embedded into its form. The page shows
+/// a confirmation with the `saved=true` parameter, to which the settings form redirects. When the
+/// user has a server-side profile, its settings and its restore code are also shown on the page,
+/// along with the search url carrying the saved settings in its `preferences` parameter.
 #[get("/settings")]
 pub async fn settings(
-    config: web::Data<Config>,
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
-        crate::templates::views::settings::settings(
-            config.safe_search,
-            &config.style.colorscheme,
-            &config.style.theme,
-            &config.style.animation,
-            &config.upstream_search_engines,
-        )?
-        .0,
-    ))
+    let config = config.load();
+    let csrf_token = csrf_token(&req);
+    let profiles = req
+        .app_data::<web::Data<Profiles>>()
+        .filter(|_| config.profiles.enabled);
+    let profile_token = req.cookie(PROFILE_COOKIE);
+    let profile_settings = match (profiles, &profile_token) {
+        (Some(profiles), Some(token)) => profiles.load(&config, token.value()).await,
+        _ => None,
+    };
+    let profile = match (profiles, &profile_token, &profile_settings) {
+        (Some(_), Some(token), Some(settings)) => ProfileStatus::Saved {
+            restore_code: token.value(),
+            settings,
+        },
+        (Some(_), _, _) => ProfileStatus::Missing,
+        (None, _, _) => ProfileStatus::Disabled,
+    };
+    let preferences_url = user_settings(&req, &config).await.map(|settings| {
+        let connection_info = req.connection_info();
+        format!(
+            "{}://{}/search?{PREFERENCES_PARAM}={}&q=",
+            connection_info.scheme(),
+            connection_info.host(),
+            encode_preferences(&settings)
+        )
+    });
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .cookie(csrf_cookie(&req, &csrf_token))
+        .body(
+            crate::templates::views::settings::settings(
+                &csrf_token,
+                &config,
+                &config
+                    .upstream_search_engines
+                    .iter()
+                    .map(|(engine, engine_config)| (engine.clone(), engine_config.enabled))
+                    .collect(),
+                req.query_string()
+                    .split('&')
+                    .any(|param| param == "saved=true"),
+                &profile,
+                preferences_url.as_deref(),
+            )?
+            .0,
+        ))
 }
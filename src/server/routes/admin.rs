@@ -0,0 +1,251 @@
+//! This module handles the admin api routes which allow the instance admins to change the
+//! upstream search engine options at runtime.
+
+use crate::{
+    config::{parser::Config, reloader::SharedConfig, state::EngineOverride},
+    server::{
+        rate_limit::ClientRateLimiter,
+        routes::api::{api_key_usage, ApiKeyUsage},
+        signing::tokens_match,
+        usage_stats::UsageStats,
+    },
+};
+use actix_web::{get, http::header, post, web, HttpRequest, HttpResponse};
+use std::collections::HashMap;
+
+/// Handles the route which lists the current options of all the upstream search engines. The
+/// requests should be authenticated with the token provided via the `admin.token` option.
+///
+/// # Example
+///
+/// ```bash
+/// curl -H "Authorization: Bearer <token>" "http://127.0.0.1:8080/admin/engines"
+/// ```
+#[get("/admin/engines")]
+pub async fn engines(req: HttpRequest, config: web::Data<SharedConfig>) -> HttpResponse {
+    let config = config.load();
+    if let Some(response) = authorize(&req, &config) {
+        return response;
+    }
+
+    let engines: HashMap<&str, EngineOverride> = config
+        .upstream_search_engines
+        .iter()
+        .map(|(engine, engine_config)| {
+            (
+                engine.as_str(),
+                EngineOverride {
+                    enabled: Some(engine_config.enabled),
+                    weight: Some(engine_config.weight),
+                },
+            )
+        })
+        .collect();
+
+    HttpResponse::Ok().json(engines)
+}
+
+/// Handles the route which enables/disables an upstream search engine or changes its weight at
+/// runtime. The changes are persisted to the state file so that they survive restarts. The
+/// requests should be authenticated with the token provided via the `admin.token` option.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer <token>" -H "Content-Type: application/json" \
+///     -d '{"enabled": false}' "http://127.0.0.1:8080/admin/engines/Bing"
+/// ```
+#[post("/admin/engines/{engine}")]
+pub async fn update_engine(
+    req: HttpRequest,
+    engine: web::Path<String>,
+    engine_override: web::Json<EngineOverride>,
+    shared_config: web::Data<SharedConfig>,
+) -> HttpResponse {
+    let config = shared_config.load();
+    if let Some(response) = authorize(&req, &config) {
+        return response;
+    }
+
+    if !config.upstream_search_engines.contains_key(engine.as_str()) {
+        return HttpResponse::NotFound().json(HashMap::from([(
+            "error",
+            format!("The upstream search engine `{engine}` is not configured"),
+        )]));
+    }
+
+    if engine_override
+        .weight
+        .is_some_and(|weight| !weight.is_finite() || weight < 0.0)
+    {
+        return HttpResponse::BadRequest().json(HashMap::from([(
+            "error",
+            "The weight should be a non negative number".to_owned(),
+        )]));
+    }
+
+    match shared_config.override_engine(&engine, engine_override.into_inner()) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(error) => {
+            tracing::error!("Unable to persist the upstream search engine options: {error}");
+            HttpResponse::InternalServerError().json(HashMap::from([(
+                "error",
+                "Unable to persist the upstream search engine options".to_owned(),
+            )]))
+        }
+    }
+}
+
+/// Handles the route which lists the quotas of the keys provided via the `api.keys` option along
+/// with the number of requests sent with each key. The requests should be authenticated with the
+/// token provided via the `admin.token` option.
+///
+/// # Example
+///
+/// ```bash
+/// curl -H "Authorization: Bearer <token>" "http://127.0.0.1:8080/admin/api_keys"
+/// ```
+#[get("/admin/api_keys")]
+pub async fn api_keys(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    api_limiter: web::Data<ClientRateLimiter>,
+) -> HttpResponse {
+    let config = config.load();
+    if let Some(response) = authorize(&req, &config) {
+        return response;
+    }
+
+    let mut usage: Vec<ApiKeyUsage> = Vec::with_capacity(config.api.keys.len());
+    for api_key in &config.api.keys {
+        usage.push(api_key_usage(&api_limiter, api_key).await);
+    }
+
+    HttpResponse::Ok().json(usage)
+}
+
+/// Handles the route which lists the anonymized usage statistics of the instance per day (the
+/// number of the searches and of the unique visitors along with the search categories and the
+/// upstream search engines used), when the `usage_stats.enabled` option is set. The requests
+/// should be authenticated with the token provided via the `admin.token` option.
+///
+/// # Example
+///
+/// ```bash
+/// curl -H "Authorization: Bearer <token>" "http://127.0.0.1:8080/admin/usage_stats"
+/// ```
+#[get("/admin/usage_stats")]
+pub async fn usage_stats(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    usage_stats: web::Data<UsageStats>,
+) -> HttpResponse {
+    let config = config.load();
+    if let Some(response) = authorize(&req, &config) {
+        return response;
+    }
+
+    if !config.usage_stats.enabled {
+        return HttpResponse::NotFound().json(HashMap::from([(
+            "error",
+            "The usage statistics are disabled",
+        )]));
+    }
+    match usage_stats.daily_usage(&config).await {
+        Some(days) => HttpResponse::Ok().json(days),
+        None => HttpResponse::InternalServerError().json(HashMap::from([(
+            "error",
+            "Unable to read the usage statistics",
+        )])),
+    }
+}
+
+/// A helper function which checks whether the request is authenticated with the token provided
+/// via the `admin.token` option.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Returns
+///
+/// It returns `None` if the request is authenticated otherwise it returns the response which
+/// should be sent back, a `404 Not Found` if the admin api is disabled or a `401 Unauthorized`
+/// if the token is missing or invalid. An empty admin token never authenticates a request.
+fn authorize(req: &HttpRequest, config: &Config) -> Option<HttpResponse> {
+    let Some(token) = config
+        .admin
+        .token
+        .as_deref()
+        .filter(|token| !token.trim().is_empty())
+    else {
+        return Some(HttpResponse::NotFound().finish());
+    };
+
+    let provided_token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided_token {
+        Some(provided_token) if tokens_match(provided_token, token) => None,
+        _ => Some(
+            HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .finish(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test::TestRequest};
+
+    /// A helper function which returns the status of the response sent back by `authorize` for
+    /// the provided admin token and `Authorization` header.
+    fn authorize_status(token: Option<&str>, authorization: Option<&str>) -> Option<StatusCode> {
+        let mut config = Config::default();
+        config.admin.token = token.map(str::to_owned);
+
+        let mut req = TestRequest::default();
+        if let Some(authorization) = authorization {
+            req = req.insert_header((header::AUTHORIZATION, authorization));
+        }
+        authorize(&req.to_http_request(), &config).map(|response| response.status())
+    }
+
+    #[test]
+    fn test_authorize() {
+        assert_eq!(
+            authorize_status(Some("secret"), Some("Bearer secret")),
+            None
+        );
+        assert_eq!(
+            authorize_status(Some("secret"), Some("Bearer wrong")),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            authorize_status(Some("secret"), None),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            authorize_status(None, Some("Bearer secret")),
+            Some(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[test]
+    fn test_empty_token_never_authorizes() {
+        assert_eq!(
+            authorize_status(Some(""), Some("Bearer ")),
+            Some(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            authorize_status(Some("  "), Some("Bearer   ")),
+            Some(StatusCode::NOT_FOUND)
+        );
+    }
+}
@@ -0,0 +1,381 @@
+//! This module handles the json api routes which give programmatic access to the search results
+//! to the holders of the api keys provided with the `api.keys` option, each key being limited to
+//! its own quota of requests, along with the public route describing the instance.
+
+use crate::{
+    cache::cacher::SharedCache,
+    config::{parser::Config, reloader::SharedConfig},
+    models::{parser_models::ApiKey, server_models::SearchParams},
+    server::{
+        error_pages::HttpError,
+        rate_limit::{retry_after_secs, ClientRateLimiter, Quota},
+        routes::search::search_response,
+        signing::tokens_match,
+    },
+};
+use actix_web::{get, http::header, web, HttpRequest, HttpResponse};
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The quota and the usage of an api key.
+#[derive(Serialize)]
+pub struct ApiKeyUsage {
+    /// It stores the name of the key.
+    pub name: String,
+    /// It stores the number of requests which can be sent at once with the key.
+    pub burst: u32,
+    /// It stores the number of requests per minute which can be sent with the key.
+    pub per_minute: u32,
+    /// It stores the number of requests sent with the key, or `None` if it could not be read
+    /// from the redis server.
+    pub requests: Option<u64>,
+}
+
+/// Handles the route which returns the search results as json, it takes the same search
+/// parameters as the search page. The requests should be authenticated with one of the keys
+/// provided via the `api.keys` option and are limited to the quota of the key.
+///
+/// # Example
+///
+/// ```bash
+/// curl -H "Authorization: Bearer <key>" "http://127.0.0.1:8080/api/v1/search?q=sweden&page=2"
+/// ```
+#[get("/api/v1/search")]
+pub async fn search(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+    client: web::Data<Client>,
+    api_limiter: web::Data<ClientRateLimiter>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let api_key = match authorize(&req, &config.load()) {
+        Ok(api_key) => api_key,
+        Err(response) => return Ok(response),
+    };
+
+    let quota = Quota::new(api_key.burst, api_key.per_minute);
+    if let Err(retry_after) = api_limiter.check(&limiter_key(&api_key), &quota).await {
+        let retry_after = retry_after_secs(retry_after);
+        return Ok(HttpResponse::TooManyRequests()
+            .insert_header((header::RETRY_AFTER, retry_after))
+            .json(HashMap::from([(
+                "error",
+                format!("The quota of the key is exceeded, retry in {retry_after} seconds"),
+            )])));
+    }
+    api_limiter.count_request(&limiter_key(&api_key)).await;
+
+    let mut params = web::Query::<SearchParams>::from_query(req.query_string())
+        .map_err(HttpError::from)?
+        .into_inner();
+    if params
+        .q
+        .as_deref()
+        .is_none_or(|query| query.trim().is_empty())
+    {
+        return Ok(HttpResponse::BadRequest()
+            .json(HashMap::from([("error", "The `q` parameter is missing")])));
+    }
+    params.format = Some("json".to_owned());
+    params.stream = None;
+
+    search_response(req, params, config, cache, client, false).await
+}
+
+/// A named struct which serializes the description of the instance.
+#[derive(Serialize)]
+pub struct InstanceInfo<'a> {
+    /// It stores the version of the server.
+    pub version: &'static str,
+    /// It stores the git commit from which the server was built or `None` if it is unknown.
+    pub commit: Option<&'static str>,
+    /// It stores the names of the upstream search engines enabled by default.
+    pub engines: Vec<&'a str>,
+    /// It stores the names of the search categories configured with the `category_engines`
+    /// option.
+    pub categories: Vec<&'a str>,
+}
+
+/// Handles the route which describes the instance (its version, the git commit from which it was
+/// built, its enabled upstream search engines and its search categories), so that the federation
+/// peers and the monitoring tools can introspect the instance. This route does not need an api
+/// key.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/api/v1/info"
+/// ```
+#[get("/api/v1/info")]
+pub async fn info(config: web::Data<SharedConfig>) -> HttpResponse {
+    let config = config.load();
+    let mut engines: Vec<&str> = config
+        .upstream_search_engines
+        .iter()
+        .filter(|(_, engine_config)| engine_config.enabled)
+        .map(|(engine, _)| engine.as_str())
+        .collect();
+    engines.sort_unstable();
+    let mut categories: Vec<&str> = config
+        .category_engines
+        .keys()
+        .map(String::as_str)
+        .filter(|category| !config.is_category_disabled(category))
+        .collect();
+    categories.sort_unstable();
+
+    HttpResponse::Ok().json(InstanceInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        // The commit is provided by the build script, it is empty when the server is not built
+        // from a git checkout.
+        commit: Some(env!("WEBSURFX_GIT_COMMIT")).filter(|commit| !commit.is_empty()),
+        engines,
+        categories,
+    })
+}
+
+/// Handles the route which lists the themes, the colorschemes and the animations available on the
+/// instance (as scanned from the `public/static` folder when the config was last loaded), so that
+/// the clients can offer the same styles as the settings page. This route does not need an api
+/// key.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/api/v1/themes"
+/// ```
+#[get("/api/v1/themes")]
+pub async fn themes(config: web::Data<SharedConfig>) -> HttpResponse {
+    HttpResponse::Ok().json(&config.load().styles)
+}
+
+/// Handles the route which returns the quota of the key with which the request is authenticated
+/// and the number of requests sent with it.
+///
+/// # Example
+///
+/// ```bash
+/// curl -H "Authorization: Bearer <key>" "http://127.0.0.1:8080/api/v1/usage"
+/// ```
+#[get("/api/v1/usage")]
+pub async fn usage(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    api_limiter: web::Data<ClientRateLimiter>,
+) -> HttpResponse {
+    match authorize(&req, &config.load()) {
+        Ok(api_key) => HttpResponse::Ok().json(api_key_usage(&api_limiter, &api_key).await),
+        Err(response) => response,
+    }
+}
+
+/// A function which returns the quota and the usage of the provided api key.
+///
+/// # Arguments
+///
+/// * `api_limiter` - It takes the rate limiter in which the requests of the keys are counted as
+///   an argument.
+/// * `api_key` - It takes the api key as an argument.
+pub async fn api_key_usage(api_limiter: &ClientRateLimiter, api_key: &ApiKey) -> ApiKeyUsage {
+    ApiKeyUsage {
+        name: api_key.name.clone(),
+        burst: api_key.burst,
+        per_minute: api_key.per_minute,
+        requests: api_limiter.requests(&limiter_key(api_key)).await,
+    }
+}
+
+/// A helper function which returns the key under which the requests of the provided api key are
+/// tracked by the rate limiter.
+///
+/// # Arguments
+///
+/// * `api_key` - It takes the api key as an argument.
+fn limiter_key(api_key: &ApiKey) -> String {
+    format!("api_key:{}", api_key.name)
+}
+
+/// A helper function which finds the api key with which the request is authenticated.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Returns
+///
+/// It returns the api key if the request is authenticated otherwise it returns the response
+/// which should be sent back, a `404 Not Found` if the json api is disabled or a `401
+/// Unauthorized` if the key is missing or invalid.
+fn authorize(req: &HttpRequest, config: &Config) -> Result<ApiKey, HttpResponse> {
+    if config.api.keys.is_empty() {
+        return Err(HttpResponse::NotFound().finish());
+    }
+
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|provided_key| {
+            config
+                .api
+                .keys
+                .iter()
+                .find(|api_key| tokens_match(provided_key, &api_key.key))
+        })
+        .cloned()
+        .ok_or_else(|| {
+            HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .json(HashMap::from([(
+                    "error",
+                    "The api key is missing or invalid",
+                )]))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache::cacher::create_cache, results::aggregator::upstream_client};
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, read_body, TestRequest},
+        App,
+    };
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.api.keys = vec![ApiKey {
+            name: "alice".to_owned(),
+            key: "secret".to_owned(),
+            burst: 2,
+            per_minute: 1,
+        }];
+        config
+    }
+
+    #[test]
+    fn test_authorize() {
+        let status = |config: &Config, authorization: Option<&str>| {
+            let mut req = TestRequest::default();
+            if let Some(authorization) = authorization {
+                req = req.insert_header((header::AUTHORIZATION, authorization));
+            }
+            authorize(&req.to_http_request(), config).map(|api_key| api_key.name)
+        };
+
+        assert_eq!(
+            status(&config(), Some("Bearer secret")).ok().as_deref(),
+            Some("alice")
+        );
+        assert_eq!(
+            status(&config(), Some("Bearer wrong"))
+                .unwrap_err()
+                .status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(&config(), None).unwrap_err().status(),
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(&Config::default(), Some("Bearer secret"))
+                .unwrap_err()
+                .status(),
+            StatusCode::NOT_FOUND
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_quota_and_usage() {
+        let config = config();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedCache::new(
+                    create_cache(&config).await,
+                )))
+                .app_data(web::Data::new(upstream_client(&config).unwrap()))
+                .app_data(web::Data::new(
+                    ClientRateLimiter::new(&config, false).unwrap(),
+                ))
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .service(search)
+                .service(usage),
+        )
+        .await;
+        let request = |uri: &str| {
+            TestRequest::get()
+                .uri(uri)
+                .insert_header((header::AUTHORIZATION, "Bearer secret"))
+                .to_request()
+        };
+
+        let response = call_service(&app, request("/api/v1/search")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = call_service(&app, request("/api/v1/search?q=")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = call_service(&app, request("/api/v1/search?q=rust")).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "60");
+
+        let response = call_service(&app, request("/api/v1/usage")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(response).await,
+            r#"{"name":"alice","burst":2,"per_minute":1,"requests":2}"#
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_info() {
+        let mut config = Config::default();
+        config.category_engines =
+            HashMap::from([("images".to_owned(), vec!["DuckDuckGo".to_owned()])]);
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .service(info),
+        )
+        .await;
+
+        // The route is public even though no api key is provided.
+        let response =
+            call_service(&app, TestRequest::get().uri("/api/v1/info").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let info: serde_json::Value = serde_json::from_slice(&read_body(response).await).unwrap();
+        assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+        // Only the engines enabled by default are listed.
+        let engines = info["engines"].as_array().unwrap();
+        assert!(engines.contains(&serde_json::json!("DuckDuckGo")));
+        assert!(!engines.contains(&serde_json::json!("Bing")));
+        assert_eq!(info["categories"], serde_json::json!(["images"]));
+    }
+
+    #[actix_web::test]
+    async fn test_themes() {
+        use crate::models::parser_models::StyleCatalog;
+
+        let mut config = Config::default();
+        config.styles = StyleCatalog {
+            themes: vec!["simple".to_owned()],
+            colorschemes: vec!["nord".to_owned()],
+            animations: Vec::new(),
+        };
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .service(themes),
+        )
+        .await;
+
+        let response =
+            call_service(&app, TestRequest::get().uri("/api/v1/themes").to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(response).await,
+            r#"{"themes":["simple"],"colorschemes":["nord"],"animations":[]}"#
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! This module handles the routes of the bookmarks, which are the search results starred by the
+//! users with a server-side profile (to keep them across the searches of a research session), the
+//! page listing them and their json export.
+
+use crate::{
+    config::{parser::Config, reloader::SharedConfig},
+    server::{
+        csrf::{csrf_cookie, csrf_token, verify_csrf_token, CSRF_FIELD},
+        error_pages::HttpError,
+        profiles::{is_profile_token, unix_time, Bookmark, Profiles, PROFILE_COOKIE},
+    },
+};
+use actix_web::{
+    get,
+    http::{
+        header::{self, ContentDisposition, ContentType, DispositionParam, DispositionType},
+        StatusCode,
+    },
+    post, web, HttpRequest, HttpResponse,
+};
+use reqwest::Url;
+use serde::Deserialize;
+
+/// The name of the file in which the bookmarks are exported.
+const EXPORT_FILE_NAME: &str = "websurfx-bookmarks.json";
+
+/// The maximum length (in bytes) of each field of a starred search result.
+const MAX_FIELD_LENGTH: usize = 2048;
+
+/// A named struct which deserializes the parameters of the bookmarks page.
+#[derive(Deserialize)]
+struct BookmarksParams {
+    /// It stores the format of the bookmarks, they are exported as a json file when it is `json`.
+    format: Option<String>,
+}
+
+/// A named struct which deserializes the search result starred by the user.
+#[derive(Deserialize)]
+pub struct StarredResult {
+    /// It stores the url of the search result.
+    url: String,
+    /// It stores the title of the search result.
+    title: String,
+    /// It stores the description of the search result.
+    #[serde(default)]
+    description: String,
+}
+
+/// Handles the route of the bookmarks page, which lists the search results starred by the user
+/// (the most recently starred result first) along with the forms removing them. The bookmarks are
+/// exported as a json file instead when the `format` parameter is `json`.
+///
+/// # Example
+///
+/// ```bash
+/// curl -b "profile=<restore code>" "http://127.0.0.1:8080/bookmarks?format=json"
+/// ```
+#[get("/bookmarks")]
+pub async fn bookmarks(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let profiles = bookmark_profiles(&req, &config)?;
+    let params = web::Query::<BookmarksParams>::from_query(req.query_string())
+        .map_err(HttpError::from)?
+        .into_inner();
+
+    let bookmarks = match req.cookie(PROFILE_COOKIE) {
+        Some(token) => profiles.bookmarks(&config, token.value()).await,
+        None => Vec::new(),
+    };
+
+    if params.format.as_deref() == Some("json") {
+        let exported = serde_json::to_string_pretty(&bookmarks).map_err(|_| {
+            HttpError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "The bookmarks could not be exported.",
+            )
+        })?;
+        return Ok(HttpResponse::Ok()
+            .content_type(ContentType::json())
+            .insert_header(ContentDisposition {
+                disposition: DispositionType::Attachment,
+                parameters: vec![DispositionParam::Filename(EXPORT_FILE_NAME.to_owned())],
+            })
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .body(exported));
+    }
+
+    let csrf_token = csrf_token(&req);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .insert_header((header::REFERRER_POLICY, "no-referrer"))
+        .cookie(csrf_cookie(&req, &csrf_token))
+        .body(
+            crate::templates::views::bookmarks::bookmarks(
+                &config.style.colorscheme,
+                &config.style.theme,
+                &config.style.animation,
+                &csrf_token,
+                &bookmarks,
+                req.cookie(PROFILE_COOKIE).is_some(),
+            )
+            .0,
+        ))
+}
+
+/// Handles the route starring a search result, which is added to the bookmarks kept in the
+/// server-side profile of the user. The result is sent as json by the script of the search page,
+/// so the route cannot be reached by the forms of other sites (and the `profile` cookie is not
+/// sent along with the cross-site requests either), and a `204 No Content` response is sent back.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -b "profile=<restore code>" -H "Content-Type: application/json" \
+///   -d '{"url":"https://www.rust-lang.org/","title":"Rust"}' "http://127.0.0.1:8080/bookmarks"
+/// ```
+#[post("/bookmarks")]
+pub async fn add_bookmark(
+    req: HttpRequest,
+    result: web::Json<StarredResult>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let profiles = bookmark_profiles(&req, &config)?;
+    let result = result.into_inner();
+
+    if !Url::parse(&result.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https")) {
+        return Err(HttpError::bad_request(
+            "Only the http and https urls can be starred.",
+        ));
+    }
+    if [&result.url, &result.title, &result.description]
+        .iter()
+        .any(|field| field.len() > MAX_FIELD_LENGTH)
+    {
+        return Err(HttpError::bad_request(format!(
+            "The fields of the starred result should not be longer than {MAX_FIELD_LENGTH} bytes."
+        )));
+    }
+
+    // The bookmarks are only kept along with an existing profile.
+    let token = match req.cookie(PROFILE_COOKIE) {
+        Some(token) if profiles.load(&config, token.value()).await.is_some() => token,
+        _ => {
+            return Err(HttpError::bad_request(
+                "The bookmarks can only be kept in a server-side profile.",
+            ))
+        }
+    };
+
+    profiles
+        .add_bookmark(
+            &config,
+            token.value(),
+            Bookmark {
+                url: result.url,
+                title: result.title,
+                description: result.description,
+                at: unix_time(),
+            },
+        )
+        .await
+        .map_err(|error| {
+            tracing::error!("The bookmark could not be saved: {error}");
+            HttpError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "The bookmark could not be saved.",
+            )
+        })?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Handles the route removing a search result (whose url is provided in the `url` field of the
+/// form) from the bookmarks of the user, and redirects the user back to the bookmarks page. The
+/// form should carry the token of the `csrf_token` cookie (set along with the bookmarks page) in
+/// its `csrf_token` field.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -b "profile=<restore code>; csrf_token=<token>" \
+///   -d "url=https://www.rust-lang.org/&csrf_token=<token>" \
+///   "http://127.0.0.1:8080/bookmarks/remove"
+/// ```
+#[post("/bookmarks/remove")]
+pub async fn remove_bookmark(
+    req: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let field = |name: &str| {
+        form.iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    };
+    if !verify_csrf_token(&req, field(CSRF_FIELD)) {
+        return Err(HttpError::new(
+            StatusCode::FORBIDDEN,
+            "The form has expired or was sent from another site, please remove the bookmark again.",
+        ));
+    }
+
+    let profiles = bookmark_profiles(&req, &config)?;
+    if let (Some(token), Some(url)) = (
+        req.cookie(PROFILE_COOKIE)
+            .filter(|token| is_profile_token(token.value())),
+        field("url"),
+    ) {
+        profiles
+            .remove_bookmark(&config, token.value(), url)
+            .await
+            .map_err(|error| {
+                tracing::error!("The bookmark could not be removed: {error}");
+                HttpError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "The bookmark could not be removed.",
+                )
+            })?;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, "/bookmarks"))
+        .finish())
+}
+
+/// A helper function which returns the server-side profiles in which the bookmarks are kept.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Error
+///
+/// Returns a `404 Not Found` error if the profiles are not enabled on the instance.
+fn bookmark_profiles<'a>(
+    req: &'a HttpRequest,
+    config: &Config,
+) -> Result<&'a web::Data<Profiles>, HttpError> {
+    req.app_data::<web::Data<Profiles>>()
+        .filter(|_| config.profiles.enabled)
+        .ok_or_else(|| {
+            HttpError::new(
+                StatusCode::NOT_FOUND,
+                "The bookmarks are not enabled on this instance.",
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{csrf::CSRF_COOKIE, profiles::new_profile_token};
+    use actix_web::{
+        cookie,
+        test::{call_service, init_service, read_body, TestRequest},
+        App,
+    };
+
+    #[actix_web::test]
+    async fn test_bookmarks() {
+        let mut config = Config::default();
+        config.profiles.enabled = true;
+        let profiles = web::Data::new(Profiles::new(&config).unwrap());
+        let token = new_profile_token();
+        profiles.save(&config, &token, "{}").await.unwrap();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config.clone())))
+                .app_data(profiles.clone())
+                .service(bookmarks)
+                .service(add_bookmark)
+                .service(remove_bookmark),
+        )
+        .await;
+        let star = |token: &str, url: &str| {
+            TestRequest::post()
+                .uri("/bookmarks")
+                .cookie(cookie::Cookie::new(PROFILE_COOKIE, token.to_owned()))
+                .set_json(serde_json::json!({"url": url, "title": "Rust"}))
+                .to_request()
+        };
+
+        let response = call_service(&app, star(&token, "https://www.rust-lang.org/")).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = call_service(&app, star(&token, "javascript:alert(1)")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        // The bookmarks are not kept without a saved profile.
+        let response = call_service(
+            &app,
+            star(&new_profile_token(), "https://www.rust-lang.org/"),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/bookmarks?format=json")
+                .cookie(cookie::Cookie::new(PROFILE_COOKIE, token.clone()))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let exported: Vec<Bookmark> = serde_json::from_slice(&read_body(response).await).unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].url, "https://www.rust-lang.org/");
+
+        let csrf_token = csrf_token(&TestRequest::default().to_http_request());
+        let response = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/bookmarks/remove")
+                .cookie(cookie::Cookie::new(PROFILE_COOKIE, token.clone()))
+                .cookie(cookie::Cookie::new(CSRF_COOKIE, csrf_token.clone()))
+                .set_form([
+                    ("url", "https://www.rust-lang.org/"),
+                    (CSRF_FIELD, csrf_token.as_str()),
+                ])
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert!(profiles.bookmarks(&config, &token).await.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_bookmarks_disabled() {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(Config::default())))
+                .service(bookmarks),
+        )
+        .await;
+        let response = call_service(&app, TestRequest::get().uri("/bookmarks").to_request()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,133 @@
+//! This module handles the route to which the solutions of the bot challenges are sent, it gives
+//! the clients which solved their challenge the cookie letting their searches through.
+
+use crate::{
+    config::reloader::SharedConfig,
+    server::{
+        bot_challenge::{challenge_cookie, verify_solution},
+        client_ip::request_client_ip,
+        error_pages::HttpError,
+    },
+};
+use actix_web::{
+    get,
+    http::{header, StatusCode},
+    web, HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+
+/// A named struct which deserializes the parameters of the challenge route.
+#[derive(Deserialize)]
+pub struct ChallengeParams {
+    /// It stores the challenge given to the client.
+    challenge: String,
+    /// It stores the solution of the challenge found by the client.
+    nonce: String,
+    /// It stores the path of the page to which the client is redirected.
+    redirect: Option<String>,
+}
+
+/// Handles the route which checks the solution of a bot challenge and redirects the client back
+/// to the requested page with the cookie letting its searches through, a `403` error is returned
+/// if the challenge is not solved.
+///
+/// # Example
+///
+/// ```bash
+/// curl -i "http://127.0.0.1:8080/challenge?challenge=...&nonce=42&redirect=%2Fsearch%3Fq%3Drust"
+/// ```
+#[get("/challenge")]
+pub async fn challenge(
+    req: HttpRequest,
+    params: web::Query<ChallengeParams>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let ip = request_client_ip(&req);
+    if !verify_solution(&config, ip, &params.challenge, &params.nonce) {
+        return Err(HttpError::new(
+            StatusCode::FORBIDDEN,
+            "The challenge was not solved or has expired, please search again.",
+        ));
+    }
+
+    // Only the pages of the instance are redirected to, so that the route cannot be abused as an
+    // open redirect.
+    let redirect = params
+        .redirect
+        .as_deref()
+        .filter(|redirect| redirect.starts_with('/') && !redirect.starts_with("//"))
+        .filter(|redirect| !redirect.contains('\\'))
+        .unwrap_or("/");
+    Ok(HttpResponse::SeeOther()
+        .cookie(challenge_cookie(&req, &config, ip))
+        .insert_header((header::LOCATION, redirect))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::parser::Config, models::parser_models::BotChallengeConfig,
+        server::bot_challenge::new_challenge,
+    };
+    use actix_web::{
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+
+    #[actix_web::test]
+    async fn test_challenge() {
+        let config = Config {
+            bot_challenge: BotChallengeConfig {
+                difficulty: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config.clone())))
+                .service(challenge),
+        )
+        .await;
+        let ip = "203.0.113.7".parse().unwrap();
+        let challenge = new_challenge(&config, ip);
+        let nonce = (0..)
+            .map(|nonce: u64| nonce.to_string())
+            .find(|nonce| verify_solution(&config, ip, &challenge, nonce))
+            .unwrap();
+        let request = |nonce: &str, redirect: &str| {
+            TestRequest::get()
+                .uri(&format!(
+                    "/challenge?challenge={challenge}&nonce={nonce}&redirect={redirect}"
+                ))
+                .peer_addr("203.0.113.7:4711".parse().unwrap())
+                .to_request()
+        };
+
+        let response = call_service(&app, request(&nonce, "%2Fsearch%3Fq%3Drust")).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(
+            response.headers().get(header::LOCATION).unwrap(),
+            "/search?q=rust"
+        );
+        assert!(response
+            .response()
+            .cookies()
+            .any(|cookie| cookie.name() == "bot_challenge"));
+
+        // The other sites are never redirected to.
+        let response = call_service(&app, request(&nonce, "%2F%2Fexample.com")).await;
+        assert_eq!(response.headers().get(header::LOCATION).unwrap(), "/");
+
+        let wrong_nonce = (0..)
+            .map(|nonce: u64| nonce.to_string())
+            .find(|nonce| !verify_solution(&config, ip, &challenge, nonce))
+            .unwrap();
+        let response = call_service(&app, request(&wrong_nonce, "%2F")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
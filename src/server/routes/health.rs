@@ -0,0 +1,130 @@
+//! This module handles the health check routes, which tell the container orchestrators (like the
+//! `HEALTHCHECK` of docker or the probes of kubernetes) whether the instance is alive and whether
+//! it is ready to answer the searches.
+
+use crate::{cache::cacher::SharedCache, config::reloader::SharedConfig};
+use actix_web::{get, web, HttpResponse};
+use serde::Serialize;
+use std::time::Duration;
+
+/// The time after which the cache backend is considered unreachable.
+const CACHE_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A named struct which serializes the checks made by the readiness route.
+#[derive(Serialize)]
+pub struct ReadinessChecks {
+    /// It stores whether the config was loaded.
+    pub config: bool,
+    /// It stores whether the cache backend can be reached.
+    pub cache: bool,
+    /// It stores whether at least one upstream search engine is enabled.
+    pub engines: bool,
+}
+
+/// A named struct which serializes the response of the health check routes.
+#[derive(Serialize)]
+pub struct HealthStatus {
+    /// It stores the status of the instance (`ok` or `unavailable`).
+    pub status: &'static str,
+    /// It stores the checks made by the readiness route.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checks: Option<ReadinessChecks>,
+}
+
+/// Handles the liveness route, which answers as long as the server process is running.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/healthz"
+/// ```
+#[get("/healthz")]
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(HealthStatus {
+        status: "ok",
+        checks: None,
+    })
+}
+
+/// Handles the readiness route, which checks that the config was loaded, that the cache backend
+/// can be reached and that at least one upstream search engine is enabled. A `503` response is
+/// returned when one of the checks fails.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/readyz"
+/// ```
+#[get("/readyz")]
+pub async fn readyz(
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+) -> HttpResponse {
+    let config = config.load();
+    let checks = ReadinessChecks {
+        config: true,
+        cache: tokio::time::timeout(CACHE_CHECK_TIMEOUT, cache.is_reachable())
+            .await
+            .unwrap_or(false),
+        engines: !config.default_engines(None).is_empty(),
+    };
+
+    let is_ready = checks.config && checks.cache && checks.engines;
+    let mut response = match is_ready {
+        true => HttpResponse::Ok(),
+        false => HttpResponse::ServiceUnavailable(),
+    };
+    response.json(HealthStatus {
+        status: if is_ready { "ok" } else { "unavailable" },
+        checks: Some(checks),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache::cacher::DisabledCache, config::parser::Config};
+    use actix_web::{
+        http::StatusCode,
+        test::{call_service, init_service, read_body, TestRequest},
+        App,
+    };
+
+    #[actix_web::test]
+    async fn test_health_routes() {
+        let app = |config: Config| {
+            init_service(
+                App::new()
+                    .app_data(web::Data::new(SharedConfig::new(config)))
+                    .app_data(web::Data::new(SharedCache::new(DisabledCache)))
+                    .service(healthz)
+                    .service(readyz),
+            )
+        };
+        let get = |uri: &str| TestRequest::get().uri(uri).to_request();
+
+        let app_with_engines = app(Config::default()).await;
+        let response = call_service(&app_with_engines, get("/healthz")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(read_body(response).await, r#"{"status":"ok"}"#);
+        let response = call_service(&app_with_engines, get("/readyz")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            read_body(response).await,
+            r#"{"status":"ok","checks":{"config":true,"cache":true,"engines":true}}"#
+        );
+
+        let mut config = Config::default();
+        config
+            .upstream_search_engines
+            .values_mut()
+            .for_each(|engine| engine.enabled = false);
+        let app_without_engines = app(config).await;
+        let response = call_service(&app_without_engines, get("/readyz")).await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            read_body(response).await,
+            r#"{"status":"unavailable","checks":{"config":true,"cache":true,"engines":false}}"#
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! This module handles the routes of the search history page, which lists the searches kept in
+//! the server-side profiles of the users who have enabled their search history and wipes the
+//! history of a profile.
+
+use crate::{
+    config::{parser::Config, reloader::SharedConfig},
+    models::server_models::Cookie,
+    server::{
+        csrf::{csrf_cookie, csrf_token, verify_csrf_token, CSRF_FIELD},
+        error_pages::HttpError,
+        profiles::{Profiles, PROFILE_COOKIE},
+    },
+};
+use actix_web::{
+    get,
+    http::{
+        header::{self, ContentType},
+        StatusCode,
+    },
+    post, web, HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+
+/// A named struct which deserializes the parameters of the search history page.
+#[derive(Deserialize)]
+struct HistoryParams {
+    /// It stores the text with which the searches of the history are filtered.
+    q: Option<String>,
+}
+
+/// Handles the route of the search history page, which lists the searches kept in the
+/// server-side profile of the user (the most recent search first) when the `profiles.history`
+/// option is set. The searches are filtered with the optional `q` parameter and the page is sent
+/// along with the `csrf_token` cookie whose token is embedded into the form clearing the history.
+///
+/// # Example
+///
+/// ```bash
+/// curl -b "profile=<restore code>" "http://127.0.0.1:8080/history?q=rust"
+/// ```
+#[get("/history")]
+pub async fn history(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let profiles = history_profiles(&req, &config)?;
+    let filter = web::Query::<HistoryParams>::from_query(req.query_string())
+        .map_err(HttpError::from)?
+        .into_inner()
+        .q
+        .unwrap_or_default();
+
+    let (enabled, mut entries) = match req.cookie(PROFILE_COOKIE) {
+        Some(token) => {
+            let defaults = Cookie::build(&config.style, Vec::new(), config.safe_search);
+            let enabled = profiles
+                .load(&config, token.value())
+                .await
+                .is_some_and(|settings| {
+                    Cookie::parse(&settings, defaults).search_history == Some(true)
+                });
+            (enabled, profiles.history(&config, token.value()).await)
+        }
+        None => (false, Vec::new()),
+    };
+    let terms = filter.trim().to_lowercase();
+    entries.retain(|entry| entry.query.to_lowercase().contains(&terms));
+
+    let csrf_token = csrf_token(&req);
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .cookie(csrf_cookie(&req, &csrf_token))
+        .body(
+            crate::templates::views::history::history(
+                &config.style.colorscheme,
+                &config.style.theme,
+                &config.style.animation,
+                &csrf_token,
+                &entries,
+                &filter,
+                enabled,
+            )
+            .0,
+        ))
+}
+
+/// Handles the route wiping the search history kept in the server-side profile of the user, and
+/// redirects the user back to the search history page. The form should carry the token of the
+/// `csrf_token` cookie (set along with the search history page) in its `csrf_token` field.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -b "profile=<restore code>; csrf_token=<token>" -d "csrf_token=<token>" \
+///   "http://127.0.0.1:8080/history/clear"
+/// ```
+#[post("/history/clear")]
+pub async fn clear_history(
+    req: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let submitted_token = form
+        .iter()
+        .find(|(field, _)| field == CSRF_FIELD)
+        .map(|(_, value)| value.as_str());
+    if !verify_csrf_token(&req, submitted_token) {
+        return Err(HttpError::new(
+            StatusCode::FORBIDDEN,
+            "The form has expired or was sent from another site, please clear the history again.",
+        ));
+    }
+
+    let profiles = history_profiles(&req, &config)?;
+    if let Some(token) = req.cookie(PROFILE_COOKIE) {
+        profiles
+            .clear_history(token.value())
+            .await
+            .map_err(|error| {
+                tracing::error!("The search history could not be cleared: {error}");
+                HttpError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "The search history could not be cleared.",
+                )
+            })?;
+    }
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, "/history"))
+        .finish())
+}
+
+/// A helper function which returns the server-side profiles in which the search histories are
+/// kept.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Error
+///
+/// Returns a `404 Not Found` error if the profiles or the search history are not enabled on the
+/// instance.
+fn history_profiles<'a>(
+    req: &'a HttpRequest,
+    config: &Config,
+) -> Result<&'a web::Data<Profiles>, HttpError> {
+    req.app_data::<web::Data<Profiles>>()
+        .filter(|_| config.profiles.enabled && config.profiles.history)
+        .ok_or_else(|| {
+            HttpError::new(
+                StatusCode::NOT_FOUND,
+                "The search history is not enabled on this instance.",
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::{csrf::CSRF_COOKIE, profiles::new_profile_token};
+    use actix_web::{
+        cookie,
+        test::{call_service, init_service, read_body, TestRequest},
+        App,
+    };
+
+    #[actix_web::test]
+    async fn test_search_history() {
+        let mut config = Config::default();
+        config.profiles.enabled = true;
+        config.profiles.history = true;
+        let profiles = web::Data::new(Profiles::new(&config).unwrap());
+        let token = new_profile_token();
+        profiles
+            .save(&config, &token, r#"{"search_history":true}"#)
+            .await
+            .unwrap();
+        for query in ["rust lang", "lua"] {
+            profiles
+                .record_search(&config, &token, query)
+                .await
+                .unwrap();
+        }
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .app_data(profiles.clone())
+                .service(history)
+                .service(clear_history),
+        )
+        .await;
+        let page = |uri: &str| {
+            TestRequest::get()
+                .uri(uri)
+                .cookie(cookie::Cookie::new(PROFILE_COOKIE, token.clone()))
+                .to_request()
+        };
+
+        let response = call_service(&app, page("/history?q=RUST")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = String::from_utf8(read_body(response).await.to_vec()).unwrap();
+        assert!(body.contains("search?q=rust+lang"));
+        assert!(!body.contains("search?q=lua"));
+
+        // The history is only cleared with the CSRF token.
+        let csrf_token = csrf_token(&TestRequest::default().to_http_request());
+        let clear = |submitted_token: &str| {
+            TestRequest::post()
+                .uri("/history/clear")
+                .cookie(cookie::Cookie::new(PROFILE_COOKIE, token.clone()))
+                .cookie(cookie::Cookie::new(CSRF_COOKIE, csrf_token.clone()))
+                .set_form([(CSRF_FIELD, submitted_token)])
+                .to_request()
+        };
+        let response = call_service(&app, clear("invalid")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = call_service(&app, clear(&csrf_token)).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert!(profiles
+            .history(&Config::default(), &token)
+            .await
+            .is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_search_history_disabled() {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(Config::default())))
+                .service(history),
+        )
+        .await;
+        let response = call_service(&app, TestRequest::get().uri("/history").to_request()).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,73 @@
+//! This module handles the metrics route, which exports the metrics of the instance in the
+//! Prometheus text format so that they can be scraped to build dashboards and alerts.
+
+use crate::{
+    config::reloader::SharedConfig,
+    server::{error_pages::HttpError, metrics::METRICS},
+};
+use actix_web::{
+    get,
+    http::{header, StatusCode},
+    web, HttpResponse,
+};
+
+/// Handles the metrics route when the `metrics` option is enabled, a `404` error is returned
+/// otherwise.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/metrics"
+/// ```
+#[get("/metrics")]
+pub async fn metrics(config: web::Data<SharedConfig>) -> Result<HttpResponse, HttpError> {
+    if !config.load().metrics {
+        return Err(HttpError::new(
+            StatusCode::NOT_FOUND,
+            "The metrics are disabled on this instance.",
+        ));
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(METRICS.render()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parser::Config;
+    use actix_web::{
+        test::{call_service, init_service, read_body, TestRequest},
+        App,
+    };
+
+    #[actix_web::test]
+    async fn test_metrics_route() {
+        for enabled in [false, true] {
+            let config = Config {
+                metrics: enabled,
+                ..Default::default()
+            };
+            let app = init_service(
+                App::new()
+                    .app_data(web::Data::new(SharedConfig::new(config)))
+                    .service(metrics),
+            )
+            .await;
+            let response =
+                call_service(&app, TestRequest::get().uri("/metrics").to_request()).await;
+            if !enabled {
+                assert_eq!(response.status(), StatusCode::NOT_FOUND);
+                continue;
+            }
+
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = read_body(response).await;
+            assert!(std::str::from_utf8(&body)
+                .unwrap()
+                .contains("# TYPE websurfx_http_requests_total counter"));
+        }
+    }
+}
@@ -1,3 +1,14 @@
 //! This module provides modules to handle various routes in the search engine website.
 
+pub mod admin;
+pub mod api;
+pub mod bookmarks;
+pub mod challenge;
+pub mod health;
+pub mod history;
+pub mod metrics;
+pub mod proxy;
+pub mod redirect;
 pub mod search;
+pub mod settings;
+pub mod status;
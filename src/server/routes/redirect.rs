@@ -0,0 +1,114 @@
+//! This module handles the redirect route through which the links of the search results can be
+//! routed, so that the sites of the results never learn the search query or the instance from
+//! the `Referer` header.
+
+use crate::{
+    config::{parser::Config, reloader::SharedConfig},
+    query::bangs::encode_query,
+    server::signing::{sign_url, verify_url},
+};
+use actix_web::{get, http::header, web, HttpResponse};
+use reqwest::Url;
+use serde::Deserialize;
+
+/// The purpose for which the urls of the redirect route are signed.
+const REDIRECT_URL_PURPOSE: &str = "redirect";
+
+/// A named struct which deserializes the parameters of the redirect route.
+#[derive(Deserialize)]
+pub struct RedirectParams {
+    /// It stores the url to which the user should be redirected.
+    url: String,
+    /// It stores the signature of the url generated by `redirect_url`.
+    hmac: String,
+}
+
+/// Handles the route which redirects the user to the provided url without leaking the search
+/// page through the `Referer` header. Only the urls signed by the instance are redirected to, so
+/// that the route cannot be abused as an open redirect, a `403` response is returned otherwise.
+///
+/// # Example
+///
+/// ```bash
+/// curl -i "http://127.0.0.1:8080/r?url=https%3A%2F%2Fwww.rust-lang.org%2F&hmac=..."
+/// ```
+#[get("/r")]
+pub async fn redirect(
+    params: web::Query<RedirectParams>,
+    config: web::Data<SharedConfig>,
+) -> HttpResponse {
+    let config = config.load();
+    let is_web_url =
+        Url::parse(&params.url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+    if !is_web_url || !verify_url(&config, REDIRECT_URL_PURPOSE, &params.url, &params.hmac) {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, params.url.as_str()))
+        .insert_header((header::REFERRER_POLICY, "no-referrer"))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .finish()
+}
+
+/// A function which returns the url of the redirect route for the provided url along with its
+/// signature.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `url` - It takes the url to which the user should be redirected as an argument.
+pub fn redirect_url(config: &Config, url: &str) -> String {
+    format!(
+        "r?url={}&hmac={}",
+        encode_query(url),
+        sign_url(config, REDIRECT_URL_PURPOSE, url)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+
+    /// A helper function which sends a request to the redirect route and returns the response.
+    async fn get_redirect(config: &Config, query: &str) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config.clone())))
+                .service(redirect),
+        )
+        .await;
+        test::call_service(&app, test::TestRequest::get().uri(query).to_request()).await
+    }
+
+    #[actix_web::test]
+    async fn test_redirect() {
+        let config = Config {
+            secret_key: Some("secret".to_owned()),
+            ..Default::default()
+        };
+        let url = "https://www.rust-lang.org/learn?lang=en";
+
+        let response = get_redirect(&config, &format!("/{}", redirect_url(&config, url))).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(response.headers().get(header::LOCATION).unwrap(), url);
+        assert_eq!(
+            response.headers().get(header::REFERRER_POLICY).unwrap(),
+            "no-referrer"
+        );
+
+        let forged_url = format!(
+            "/r?url={}&hmac={}",
+            encode_query("https://evil.example/"),
+            sign_url(&config, REDIRECT_URL_PURPOSE, url)
+        );
+        let response = get_redirect(&config, &forged_url).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let script_url = "javascript:alert(1)";
+        let response =
+            get_redirect(&config, &format!("/{}", redirect_url(&config, script_url))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
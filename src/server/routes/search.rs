@@ -2,23 +2,62 @@
 
 use crate::{
     cache::cacher::SharedCache,
-    config::parser::Config,
+    config::{parser::Config, reloader::SharedConfig},
+    engines::{block_backoff::blocked_for, reverse_image::ReverseImage},
     handler::{file_path, FileType},
     models::{
-        aggregation_models::SearchResults,
-        engine_models::EngineHandler,
-        server_models::{self, SearchParams},
+        aggregation_models::{SearchResult, SearchResults},
+        engine_models::{EngineHandler, SearchOptions, TimeRange},
+        locale_models::{supported_language, supported_region},
+        parser_models::LockedSetting,
+        server_models::{self, ReverseImageParams, SearchParams},
     },
-    results::aggregator::aggregate,
+    query::{
+        bangs::{encode_query, parse_bang, Bang},
+        normalize::normalize_query,
+    },
+    results::{
+        aggregator::{aggregate, aggregate_reverse_image, send_engine_requests, Aggregation},
+        filters::FilterContext,
+        highlight::Highlighter,
+    },
+    server::{
+        client_ip::request_client_ip,
+        csrf::{csrf_cookie, csrf_token, verify_csrf_token},
+        error_pages::{self, HttpError},
+        metrics::METRICS,
+        multipart::parse_multipart,
+        profiles::{Profiles, PROFILE_COOKIE},
+        rate_limit::sign_stream_url,
+        routes::{proxy::is_proxiable_url, settings::user_settings},
+        static_files::etag_matches,
+        usage_stats::UsageStats,
+    },
+    templates::partials::search_result::search_result,
+};
+use actix_web::{
+    get,
+    http::{
+        header::{self, ContentEncoding, ContentType, ETag, EntityTag, HeaderValue},
+        StatusCode,
+    },
+    post, web, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use futures::{
+    future::try_join_all,
+    stream::{unfold, FuturesUnordered, StreamExt},
 };
-use actix_web::{get, http::header::ContentType, web, HttpRequest, HttpResponse};
 use regex::Regex;
+use reqwest::Client;
 use std::{
     borrow::Cow,
+    collections::HashSet,
     fs::File,
     io::{BufRead, BufReader, Read},
+    sync::Arc,
+    time::Duration,
 };
-use tokio::join;
+use tokio::sync::mpsc;
 
 /// Handles the route of search page of the `websurfx` meta search engine website and it takes
 /// two search url parameters `q` and `page` where `page` parameter is optional.
@@ -34,106 +73,764 @@ use tokio::join;
 /// ```bash
 /// curl "http://127.0.0.1:8080/search?q=sweden"
 /// ```
+///
+/// The upstream search engines selected via the cookie or the config file can be overridden for a
+/// single request with the `engines` parameter.
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/search?q=sweden&engines=duckduckgo,searx"
+/// ```
+///
+/// The results can be narrowed down without querying the upstream search engines again with the
+/// `refine` parameter.
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/search?q=sweden&refine=stockholm"
+/// ```
+///
+/// The search results can be fetched as json (along with the engines which returned each result
+/// and its fused score) with the `format` parameter.
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/search?q=sweden&format=json"
+/// ```
 #[get("/search")]
 pub async fn search(
     req: HttpRequest,
-    config: web::Data<Config>,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+    client: web::Data<Client>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let params = web::Query::<SearchParams>::from_query(req.query_string())
+        .map_err(HttpError::from)?
+        .into_inner();
+    search_response(req, params, config, cache, client, false).await
+}
+
+/// Handles the route of search page of the `websurfx` meta search engine website when the search
+/// parameters are sent as a form instead of the url, so that the search query never ends up in
+/// the access logs of the reverse proxies and the browser history. It takes the same search
+/// parameters as the `GET` route and renders the same search page. The form should carry the
+/// token of the `csrf_token` cookie (set along with the index and the search pages, whose search
+/// bars are sent to this route) in its `csrf_token` field, so that other sites cannot send
+/// searches on behalf of the user.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -b "csrf_token=<token>" -d "q=sweden&page=2&csrf_token=<token>" \
+///   "http://127.0.0.1:8080/search"
+/// ```
+#[post("/search")]
+pub async fn search_form(
+    req: HttpRequest,
+    params: web::Form<SearchParams>,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+    client: web::Data<Client>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    if !verify_csrf_token(&req, params.csrf_token.as_deref()) {
+        return Ok(error_response(
+            HttpResponse::Forbidden(),
+            &config.load(),
+            "Invalid Form",
+            "The form has expired or was sent from another site, please search again.",
+        ));
+    }
+    search_response(req, params.into_inner(), config, cache, client, false).await
+}
+
+/// Handles the "I'm feeling lucky" route of the `websurfx` meta search engine website which takes
+/// the same search url parameters as the search page and redirects the user to the top ranked
+/// result of the search query. The search page is shown instead if no results could be fetched.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/search/lucky?q=sweden"
+/// ```
+#[get("/search/lucky")]
+pub async fn lucky(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+    client: web::Data<Client>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let params = web::Query::<SearchParams>::from_query(req.query_string())
+        .map_err(HttpError::from)?
+        .into_inner();
+    search_response(req, params, config, cache, client, true).await
+}
+
+/// Handles the reverse image search route of the `websurfx` meta search engine website, which
+/// lists the pages containing the image at the provided url as returned by the upstream search
+/// engines supporting the search by image (configured with the `reverse_image_engines` option).
+/// The results are rendered on the search page or returned as json with the `format` parameter.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/search/image?url=https%3A%2F%2Fexample.com%2Fcat.png"
+/// ```
+#[get("/search/image")]
+pub async fn reverse_image(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+    client: web::Data<Client>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config = config.load();
+    let params = web::Query::<ReverseImageParams>::from_query(req.query_string())
+        .map_err(HttpError::from)?;
+    let Some(image_url) = params
+        .url
+        .as_deref()
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+    else {
+        return Ok(HttpResponse::TemporaryRedirect()
+            .insert_header(("location", "/"))
+            .finish());
+    };
+
+    // Only the public https urls are sent to the upstream search engines, which fetch the image.
+    if !is_proxiable_url(image_url) {
+        return Ok(bad_request(
+            &config,
+            "Invalid Image Url",
+            "The url of the image should be a public https url.",
+        ));
+    }
+
+    reverse_image_response(
+        &req,
+        &config,
+        &cache,
+        &client,
+        &ReverseImage::Url(image_url.to_owned()),
+        image_url,
+        params.format.as_deref(),
+    )
+    .await
+}
+
+/// Handles the upload of an image to the reverse image search of the `websurfx` meta search engine
+/// website, which lists the pages containing the uploaded image like the reverse image search
+/// route. It takes a `multipart/form-data` body with the image in the `image` field, the csrf
+/// token of the search form in the `csrf_token` field and optionally the `format` field. The size
+/// of the image is limited by the `max_body_size` of the `request_limits` option.
+///
+/// # Example
+///
+/// ```bash
+/// curl -b "csrf_token=<token>" -F "csrf_token=<token>" -F "format=json" \
+///     -F "image=@cat.png" "http://127.0.0.1:8080/search/image"
+/// ```
+#[post("/search/image")]
+pub async fn reverse_image_upload(
+    req: HttpRequest,
+    body: web::Bytes,
+    config: web::Data<SharedConfig>,
+    cache: web::Data<SharedCache>,
+    client: web::Data<Client>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config = config.load();
+    let fields = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| parse_multipart(content_type, &body))
+        .unwrap_or_default();
+    let field = |name: &str| fields.iter().find(|field| field.name == name);
+    let text_field =
+        |name: &str| field(name).and_then(|field| std::str::from_utf8(field.data).ok());
+
+    if !verify_csrf_token(&req, text_field("csrf_token")) {
+        return Ok(error_response(
+            HttpResponse::Forbidden(),
+            &config,
+            "Invalid Form",
+            "The form has expired or was sent from another site, please upload the image again.",
+        ));
+    }
+
+    let Some((bytes, content_type)) = field("image").and_then(|image| {
+        image
+            .content_type
+            .as_deref()
+            .filter(|content_type| content_type.starts_with("image/") && !image.data.is_empty())
+            .map(|content_type| (image.data, content_type))
+    }) else {
+        return Ok(bad_request(
+            &config,
+            "Invalid Image",
+            "An image should be uploaded in the image field of the form.",
+        ));
+    };
+
+    reverse_image_response(
+        &req,
+        &config,
+        &cache,
+        &client,
+        &ReverseImage::Upload {
+            bytes: bytes.to_vec(),
+            content_type: content_type.to_owned(),
+        },
+        "",
+        text_field("format"),
+    )
+    .await
+}
+
+/// A helper function which fetches the pages containing the provided image (from the cache when
+/// they were cached) and renders them on the search page or as json.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request of the user as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `cache` - It takes the cache of the search results as an argument.
+/// * `client` - It takes the shared client through which the requests are sent to the upstream
+///   engines as an argument.
+/// * `image` - It takes the url or the uploaded bytes of the image as an argument.
+/// * `query` - It takes the text shown in the search bar of the search page as an argument.
+/// * `format` - It takes the requested format of the response as an argument.
+///
+/// # Error
+///
+/// Returns an error if the pages could not be fetched or the response could not be rendered.
+async fn reverse_image_response(
+    req: &HttpRequest,
+    config: &Config,
+    cache: &SharedCache,
+    client: &Client,
+    image: &ReverseImage,
+    query: &str,
+    format: Option<&str>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let cookie = user_settings(req, config).await;
+    let search_settings = search_settings(config, cookie.as_deref(), None);
+    let cache_key = image.cache_key();
+    let cached_results = cache.cached_results(&cache_key).await;
+    METRICS.record_cache_lookup(cached_results.is_ok());
+    let search_results = match cached_results {
+        Ok(results) => results,
+        Err(_) => {
+            let options = SearchOptions {
+                safe_search: search_settings.safe_search_level,
+                language: search_settings.language.as_deref().map(str::to_owned),
+                region: search_settings.region.as_deref().map(str::to_owned),
+                randomize_headers: config.randomize_headers,
+                ..Default::default()
+            };
+            let results = aggregate_reverse_image(image, config, client, &options).await?;
+            if results.is_cacheable() {
+                cache
+                    .cache_results(std::slice::from_ref(&results), &[cache_key])
+                    .await?;
+            }
+            results
+        }
+    };
+
+    if format == Some("json") {
+        let mut response = HttpResponse::Ok();
+        response.content_type(ContentType::json());
+        return Ok(search_page_response(
+            req,
+            response,
+            serde_json::to_vec(&search_results)?,
+        ));
+    }
+
+    let csrf_token = csrf_token(req);
+    let mut response = HttpResponse::Ok();
+    response
+        .content_type(ContentType::html())
+        .insert_header((header::REFERRER_POLICY, "no-referrer"))
+        .cookie(csrf_cookie(req, &csrf_token));
+    Ok(search_page_response(
+        req,
+        response,
+        crate::templates::views::search::search(
+            config,
+            query,
+            None,
+            None,
+            &[],
+            None,
+            &search_results,
+            &csrf_token,
+        )
+        .0
+        .into_bytes(),
+    ))
+}
+
+/// Handles the route which streams the search results of the `websurfx` meta search engine website
+/// as server-sent events, a `results` event carrying the rendered results of an upstream search
+/// engine is sent as soon as the engine answers and a `done` event carrying the ranking of the
+/// results of all the engines is sent once all the engines have answered. It takes the same search
+/// url parameters as the search page, which requests it when it is opened with the `stream=true`
+/// parameter. The streamed results are not cached.
+///
+/// # Example
+///
+/// ```bash
+/// curl -N "http://127.0.0.1:8080/search/stream?q=sweden"
+/// ```
+#[get("/search/stream")]
+pub async fn stream(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+    client: web::Data<Client>,
+) -> Result<HttpResponse, Box<dyn std::error::Error>> {
+    let config = config.load();
+    let params = web::Query::<SearchParams>::from_query(req.query_string())
+        .map_err(HttpError::from)?
+        .into_inner();
+    let query = normalize_query(params.q.as_deref().unwrap_or_default());
+    let (query, category) = match parse_bang(&query, &config.bangs) {
+        Some((Bang::Category(category), remaining_query)) => {
+            (remaining_query, Some(category.to_owned()))
+        }
+        Some((_, remaining_query)) => (remaining_query, params.category.clone()),
+        None => (query, params.category.clone()),
+    };
+
+    if query.is_empty() {
+        return Ok(bad_request(
+            &config,
+            "Empty Search Query",
+            "The search query should not be empty.",
+        ));
+    }
+
+    if let Some(response) = check_category(&config, category.as_deref()) {
+        return Ok(response);
+    }
+    if let Some(response) = check_request_limits(&config, &query, params.page) {
+        return Ok(response);
+    }
+
+    let cookie = user_settings(&req, &config).await;
+    let search_settings =
+        request_search_settings(&config, cookie.as_deref(), &params, category.as_deref());
+    // The streamed results are not cached, so the query is checked against the blocklist on each
+    // request like the queries missing from the cache on the search page.
+    if search_settings.safe_search_level == 4
+        && is_disallowed_query(file_path(FileType::BlockList)?, &query)?
+    {
+        return Ok(error_response(
+            HttpResponse::Forbidden(),
+            &config,
+            "Disallowed Query",
+            "The search query is not allowed on this instance.",
+        ));
+    }
+    let options = search_options(&config, &search_settings);
+    let highlight = search_settings.highlight.unwrap_or(true);
+    let results_per_page = usize::from(
+        search_settings
+            .results_per_page
+            .unwrap_or(config.results_per_page),
+    );
+    let page = params.page.unwrap_or(1).max(1) - 1;
+
+    let engines: Vec<EngineHandler> = search_settings
+        .engines
+        .iter()
+        .filter_map(|engine| EngineHandler::new(engine).ok())
+        .collect();
+
+    // The events are sent from a task of their own, which stops sending the remaining events once
+    // the stream is closed by the browser.
+    let (sender, receiver) = mpsc::channel::<web::Bytes>(engines.len() + 1);
+    tokio::spawn(stream_results(
+        StreamedSearch {
+            query,
+            page,
+            config,
+            engines,
+            client: client.get_ref().clone(),
+            options,
+            highlight,
+            results_per_page,
+        },
+        sender,
+    ));
+    let events = unfold(receiver, |mut receiver| async move {
+        let event = receiver.recv().await?;
+        Some((Ok::<_, actix_web::Error>(event), receiver))
+    });
+
+    // The events are neither compressed nor buffered by the reverse proxies, which would hold
+    // them back until the end of the stream.
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .insert_header(ContentEncoding::Identity)
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(events))
+}
+
+/// A named struct which stores the search whose results are streamed to the search page.
+struct StreamedSearch {
+    /// It stores the normalized search query.
+    query: String,
+    /// It stores the page number (starting from 0).
+    page: u32,
+    /// It stores the parsed config struct.
+    config: Arc<Config>,
+    /// It stores the upstream search engines selected by the user.
+    engines: Vec<EngineHandler>,
+    /// It stores the shared client through which the requests are sent to the upstream search
+    /// engines.
+    client: Client,
+    /// It stores the search preferences of the user.
+    options: SearchOptions,
+    /// It stores whether the search terms are highlighted in the descriptions of the results.
+    highlight: bool,
+    /// It stores the number of results shown on the search page.
+    results_per_page: usize,
+}
+
+/// A helper function which sends the search query to the upstream search engines and sends the
+/// results of each engine as a `results` server-sent event as soon as the engine answers. The
+/// random delay, the selection of the engines and the set up of the clients are done once for the
+/// whole search. Once all the engines have answered, the results of all the engines are ranked
+/// together and the `done` event carrying the ranked results is sent.
+///
+/// # Arguments
+///
+/// * `search` - It takes the search whose results are streamed as an argument.
+/// * `sender` - It takes the sending half of the channel through which the events are streamed as
+///   an argument.
+async fn stream_results(search: StreamedSearch, sender: mpsc::Sender<web::Bytes>) {
+    let StreamedSearch {
+        query,
+        page,
+        config,
+        engines,
+        client,
+        options,
+        highlight,
+        results_per_page,
+    } = search;
+
+    let engine_requests =
+        match send_engine_requests(&query, page, &config, &engines, &client, &options).await {
+            Ok(engine_requests) => Some(engine_requests),
+            Err(error) => {
+                tracing::error!(
+                    "Failed to send the requests to the upstream search engines: {error}"
+                );
+                None
+            }
+        };
+    let Some(engine_requests) = engine_requests else {
+        let _ = sender.send(done_event(Vec::new(), results_per_page)).await;
+        return;
+    };
+    let started_at = engine_requests.started_at;
+    let (config, client) = (config.as_ref(), &client);
+    let context = &FilterContext {
+        query: &query,
+        options: &options,
+        safe_search_engines: &engine_requests.safe_search_engines,
+    };
+
+    let mut responses: FuturesUnordered<_> = engine_requests
+        .requests
+        .into_iter()
+        .map(|request| async move {
+            let mut aggregation = Aggregation::default();
+            aggregation.add_response(request.response(config).await, config);
+            match aggregation.finalize(config, context, client).await {
+                Ok(()) => Some(aggregation),
+                Err(error) => {
+                    tracing::error!("Failed to filter the results: {error}");
+                    None
+                }
+            }
+        })
+        .collect();
+
+    let mut fused = Aggregation::default();
+    while let Some(aggregation) = responses.next().await {
+        let results = match aggregation {
+            Some(aggregation) => {
+                fused.fuse(&aggregation);
+                aggregation.into_search_results(&query, config, started_at)
+            }
+            None => SearchResults::default(),
+        };
+        if sender
+            .send(stream_event(config, &query, highlight, results))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+
+    METRICS.record_aggregation(started_at.elapsed());
+    let _ = sender
+        .send(done_event(
+            fused.ranked_results(&query, config),
+            results_per_page,
+        ))
+        .await;
+}
+
+/// A helper function which builds the `done` server-sent event, whose data is a json object
+/// containing the urls and the fused scores of the results of all the upstream search engines in
+/// their ranked order, so that the streamed results can be ordered and paginated like on the
+/// search page.
+///
+/// # Arguments
+///
+/// * `results` - It takes the ranked results of all the upstream search engines as an argument.
+/// * `results_per_page` - It takes the number of results shown on the search page as an argument.
+fn done_event(results: Vec<SearchResult>, results_per_page: usize) -> web::Bytes {
+    let ranking: Vec<serde_json::Value> = results
+        .into_iter()
+        .take(results_per_page)
+        .map(|result| serde_json::json!({"url": result.url, "score": result.score}))
+        .collect();
+    let data = serde_json::json!({ "ranking": ranking });
+    web::Bytes::from(format!("event: done\ndata: {data}\n\n"))
+}
+
+/// A helper function which builds the `results` server-sent event for the search results of an
+/// upstream search engine, whose data is a json object containing the rendered results and the
+/// errors of the engine.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `query` - It takes the normalized search query as an argument.
+/// * `highlight` - It takes a boolean which indicates whether the search terms should be
+///   highlighted in the descriptions of the results as an argument.
+/// * `results` - It takes the results of the upstream search engine as an argument.
+fn stream_event(
+    config: &Config,
+    query: &str,
+    highlight: bool,
+    mut results: SearchResults,
+) -> web::Bytes {
+    if let Some(highlighter) = highlight.then(|| Highlighter::new(query)).flatten() {
+        for result in results.results.iter_mut() {
+            result.description = highlighter.highlight(&result.description);
+        }
+    }
+
+    let html: String = results
+        .results
+        .iter()
+        .map(|result| search_result(config, result).0)
+        .collect();
+    let data = serde_json::json!({
+        "html": html,
+        "engineErrorsInfo": results.engine_errors_info(),
+    });
+    web::Bytes::from(format!("event: results\ndata: {data}\n\n"))
+}
+
+/// A helper function which builds the response for the search page and the "I'm feeling lucky"
+/// routes.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `params` - It takes the search parameters provided via the url or the form as an argument.
+/// * `config` - It takes the shared config as an argument.
+/// * `cache` - It takes the shared cache as an argument.
+/// * `client` - It takes the shared client through which the requests are sent to the upstream
+///   search engines as an argument.
+/// * `feeling_lucky` - It takes a boolean which indicates whether the user should be redirected to the
+///   top ranked result instead of being shown the search page.
+///
+/// # Error
+///
+/// Returns an error if the results could not be fetched.
+pub(crate) async fn search_response(
+    req: HttpRequest,
+    params: SearchParams,
+    config: web::Data<SharedConfig>,
     cache: web::Data<SharedCache>,
+    client: web::Data<Client>,
+    mut feeling_lucky: bool,
 ) -> Result<HttpResponse, Box<dyn std::error::Error>> {
-    use std::sync::Arc;
-    let params = web::Query::<SearchParams>::from_query(req.query_string())?;
+    let config = config.load();
     match &params.q {
         Some(query) => {
-            if query.trim().is_empty() {
+            let mut category = params.category.clone();
+            // The query is normalized before anything else so that the same query written
+            // differently is sent to the upstream search engines and cached only once.
+            let query = normalize_query(query);
+            let query = match parse_bang(&query, &config.bangs) {
+                Some((Bang::Redirect(url), _)) => {
+                    return Ok(HttpResponse::Found()
+                        .insert_header(("location", url))
+                        .finish())
+                }
+                Some((Bang::Category(bang_category), remaining_query)) => {
+                    category = Some(bang_category.to_owned());
+                    Cow::Owned(remaining_query)
+                }
+                Some((Bang::Lucky, remaining_query)) => {
+                    feeling_lucky = true;
+                    Cow::Owned(remaining_query)
+                }
+                None => Cow::Borrowed(query.as_str()),
+            };
+            let query: &str = &query;
+
+            if query.is_empty() {
                 return Ok(HttpResponse::TemporaryRedirect()
                     .insert_header(("location", "/"))
                     .finish());
             }
 
-            let cookie = req.cookie("appCookie");
-
-            // Get search settings using the user's cookie or from the server's config
-            let mut search_settings: server_models::Cookie<'_> = cookie
-                .and_then(|cookie_value| serde_json::from_str(cookie_value.value()).ok())
-                .unwrap_or_else(|| {
-                    server_models::Cookie::build(
-                        &config.style,
-                        config
-                            .upstream_search_engines
-                            .iter()
-                            .filter_map(|(engine, enabled)| {
-                                enabled.then_some(Cow::Borrowed(engine.as_str()))
-                            })
-                            .collect(),
-                        config.safe_search,
-                    )
-                });
+            if let Some(response) = check_category(&config, category.as_deref()) {
+                return Ok(response);
+            }
+            if let Some(response) = check_request_limits(&config, query, params.page) {
+                return Ok(response);
+            }
 
-            search_settings.safe_search_level = get_safesearch_level(
-                &Some(search_settings.safe_search_level),
-                &params.safesearch,
-                config.safe_search,
-            );
+            let cookie = user_settings(&req, &config).await;
+            let search_settings =
+                request_search_settings(&config, cookie.as_deref(), &params, category.as_deref());
+            let time_range = search_settings
+                .time_range
+                .as_deref()
+                .and_then(TimeRange::from_name);
+            record_usage(&req, &config, category.as_deref(), &search_settings.engines);
+            record_history(&req, &config, query, &search_settings);
 
-            // Closure wrapping the results function capturing local references
-            let get_results = |page| results(&config, &cache, query, page, &search_settings);
+            // The refined results and the results of the safe search level 4 (whose query is
+            // checked against the blocklist first) are always rendered with the page. Only the
+            // first page is streamed, as the results of the next pages follow the results shown
+            // on the previous pages, which are only known once the previous pages of the upstream
+            // search engines have been fetched.
+            let refine = params
+                .refine
+                .as_deref()
+                .map(normalize_query)
+                .filter(|refine| !refine.is_empty());
+            if params.stream == Some(true)
+                && !feeling_lucky
+                && params.format.is_none()
+                && refine.is_none()
+                && params.page.unwrap_or(1) <= 1
+                && search_settings.safe_search_level < 4
+                && !search_settings.engines.is_empty()
+            {
+                let mut search_results = SearchResults::default();
+                search_results.set_safe_search_level(search_settings.safe_search_level);
+                // The streamed results are the results of the first page of each upstream search
+                // engine, which are not known before the page is rendered.
+                search_results.set_page(1, 1 < config.request_limits.max_page);
+                let stream_url = sign_stream_url(
+                    &config,
+                    request_client_ip(&req),
+                    &stream_url(query, &params, time_range, category.as_deref()),
+                );
+                let csrf_token = csrf_token(&req);
+                return Ok(HttpResponse::Ok()
+                    .content_type(ContentType::html())
+                    .insert_header((header::REFERRER_POLICY, "no-referrer"))
+                    .cookie(csrf_cookie(&req, &csrf_token))
+                    .body(
+                        crate::templates::views::search::search(
+                            &config,
+                            query,
+                            time_range,
+                            None,
+                            &preserved_params(&params),
+                            Some(&stream_url),
+                            &search_results,
+                            &csrf_token,
+                        )
+                        .0,
+                    ));
+            }
 
             // .max(1) makes sure that the page >= 0.
             let page = params.page.unwrap_or(1).max(1) - 1;
-            let previous_page = page.saturating_sub(1);
-            let next_page = page + 1;
-
-            let mut results = Arc::new((SearchResults::default(), String::default()));
-            if page != previous_page {
-                let (previous_results, current_results, next_results) = join!(
-                    get_results(previous_page),
-                    get_results(page),
-                    get_results(next_page)
-                );
-                let (parsed_previous_results, parsed_next_results) =
-                    (previous_results?, next_results?);
-
-                let (cache_keys, results_list) = (
-                    [
-                        parsed_previous_results.1,
-                        results.1.clone(),
-                        parsed_next_results.1,
-                    ],
-                    [
-                        parsed_previous_results.0,
-                        results.0.clone(),
-                        parsed_next_results.0,
-                    ],
-                );
+            let results =
+                page_results(&config, &cache, &client, query, page, &search_settings).await?;
 
-                results = Arc::new(current_results?);
-
-                tokio::spawn(async move { cache.cache_results(&results_list, &cache_keys).await });
-            } else {
-                let (current_results, next_results) =
-                    join!(get_results(page), get_results(page + 1));
+            if let Some(response) = check_engines_answered(&req, &config, &results) {
+                return Ok(response);
+            }
 
-                let parsed_next_results = next_results?;
+            if feeling_lucky {
+                if let Some(result) = results.results.first() {
+                    return Ok(HttpResponse::Found()
+                        .insert_header(("location", result.url.as_str()))
+                        .finish());
+                }
+            }
 
-                results = Arc::new(current_results?);
+            // The results are refined after they are fetched (usually from the cache) so that the
+            // upstream search engines are not queried again.
+            let mut search_results: Cow<'_, SearchResults> = match &refine {
+                Some(refine) => Cow::Owned(refine_results(&results, refine)),
+                None => Cow::Borrowed(&results),
+            };
 
-                let (cache_keys, results_list) = (
-                    [results.1.clone(), parsed_next_results.1.clone()],
-                    [results.0.clone(), parsed_next_results.0],
-                );
+            // The search terms are highlighted after the results are fetched from the cache as
+            // the highlighting depends on the preference of the user.
+            if let Some(highlighter) = search_settings
+                .highlight
+                .unwrap_or(true)
+                .then(|| Highlighter::new(query))
+                .flatten()
+            {
+                for result in search_results.to_mut().results.iter_mut() {
+                    result.description = highlighter.highlight(&result.description);
+                }
+            }
 
-                tokio::spawn(async move { cache.cache_results(&results_list, &cache_keys).await });
+            // The json response goes through the same refining and highlighting as the search page
+            // so that both show the same results.
+            if params.format.as_deref() == Some("json") {
+                let mut response = HttpResponse::Ok();
+                response.content_type(ContentType::json());
+                return Ok(search_page_response(
+                    &req,
+                    response,
+                    serde_json::to_vec(&*search_results)?,
+                ));
             }
 
-            Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+            // The search query is never leaked to the sites of the results through the
+            // `Referer` header.
+            let csrf_token = csrf_token(&req);
+            let mut response = HttpResponse::Ok();
+            response
+                .content_type(ContentType::html())
+                .insert_header((header::REFERRER_POLICY, "no-referrer"))
+                .cookie(csrf_cookie(&req, &csrf_token));
+            Ok(search_page_response(
+                &req,
+                response,
                 crate::templates::views::search::search(
-                    &config.style.colorscheme,
-                    &config.style.theme,
-                    &config.style.animation,
+                    &config,
                     query,
-                    &results.0,
+                    time_range,
+                    refine.as_deref(),
+                    &preserved_params(&params),
+                    None,
+                    &search_results,
+                    &csrf_token,
                 )
-                .0,
+                .0
+                .into_bytes(),
             ))
         }
         None => Ok(HttpResponse::TemporaryRedirect()
@@ -142,14 +839,624 @@ pub async fn search(
     }
 }
 
+/// A helper function which builds the response sending back the rendered search results along
+/// with an `ETag` derived from them. The browsers revalidate the page each time it is refreshed
+/// and a `304 Not Modified` (without the page) is sent back while the results cached for the
+/// query are unchanged.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `response` - It takes the builder of the response with its headers as an argument.
+/// * `body` - It takes the rendered search page (or json) as an argument.
+fn search_page_response(
+    req: &HttpRequest,
+    mut response: HttpResponseBuilder,
+    body: Vec<u8>,
+) -> HttpResponse {
+    let etag = EntityTag::new_strong(blake3::hash(&body).to_hex().to_string());
+    // The page depends on the settings of the user and is never stored by the shared caches.
+    response
+        .insert_header(ETag(etag.clone()))
+        .insert_header((header::CACHE_CONTROL, "private, no-cache"));
+
+    if etag_matches(req, &etag) {
+        return response.status(StatusCode::NOT_MODIFIED).finish();
+    }
+    response.body(body)
+}
+
+/// A helper function which builds the search settings from the user's cookie (or from the
+/// server's config if the cookie is missing, the fields missing from the cookie or invalid are
+/// also taken from the config) and selects the upstream search engines for the provided search
+/// category.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `cookie` - It takes the optional value of the user's settings cookie as an argument.
+/// * `category` - It takes the optional search category (from the url or a bang) as an argument.
+fn search_settings<'a>(
+    config: &'a Config,
+    cookie: Option<&str>,
+    category: Option<&str>,
+) -> server_models::Cookie<'a> {
+    let defaults = server_models::Cookie::build(
+        &config.style,
+        config
+            .default_engines(category)
+            .into_iter()
+            .map(Cow::Borrowed)
+            .collect(),
+        config.safe_search,
+    );
+    let mut search_settings = match cookie {
+        Some(cookie) => server_models::Cookie::parse(cookie, defaults),
+        None => defaults,
+    };
+
+    // The engines selected by the user for the search category (the tab of the search page) take
+    // the place of the engines selected for the general category.
+    if let Some(engines) = category
+        .and_then(|category| search_settings.category_engines.get(category))
+        .filter(|engines| !engines.is_empty())
+    {
+        let engines = engines.iter().cloned().map(Cow::Owned).collect();
+        search_settings.engines = Cow::Owned(engines);
+    }
+
+    // The engines configured for the search category are used whether or not the user has a
+    // cookie, narrowed down to the engines selected by the user.
+    if let Some(engines) = category
+        .and_then(|category| config.category_engines_for(category, &search_settings.engines))
+    {
+        search_settings.engines = Cow::Owned(engines.into_iter().map(Cow::Borrowed).collect());
+    }
+
+    lock_settings(config, &mut search_settings, category);
+    search_settings
+}
+
+/// A helper function which builds the search settings of the current request, the language, the
+/// region, the time range, the upstream search engines and the safe search level provided via the
+/// url take precedence over the ones in the user's cookie.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `cookie` - It takes the optional value of the user's settings cookie as an argument.
+/// * `params` - It takes the search parameters of the current request as an argument.
+/// * `category` - It takes the optional search category (from the url or a bang) as an argument.
+fn request_search_settings<'a>(
+    config: &'a Config,
+    cookie: Option<&str>,
+    params: &SearchParams,
+    category: Option<&str>,
+) -> server_models::Cookie<'a> {
+    let mut search_settings = search_settings(config, cookie, category);
+
+    search_settings.language = params
+        .lang
+        .as_deref()
+        .or(search_settings.language.as_deref())
+        .and_then(supported_language)
+        .map(Cow::Borrowed);
+    search_settings.region = params
+        .region
+        .as_deref()
+        .or(search_settings.region.as_deref())
+        .and_then(supported_region)
+        .map(Cow::Borrowed);
+    search_settings.time_range = params
+        .time_range
+        .as_deref()
+        .or(search_settings.time_range.as_deref())
+        .and_then(TimeRange::from_name)
+        .map(|time_range| Cow::Borrowed(time_range.name()));
+
+    if let Some(engines) = params
+        .engines
+        .as_deref()
+        .filter(|engines| !engines.trim().is_empty())
+    {
+        search_settings.engines = Cow::Owned(requested_engines(config, engines));
+    }
+
+    search_settings.safe_search_level = get_safesearch_level(
+        &Some(search_settings.safe_search_level),
+        &params.safesearch,
+        config.safe_search,
+    );
+
+    // The locked settings cannot be overridden by the search parameters either.
+    lock_settings(config, &mut search_settings, category);
+    search_settings
+}
+
+/// A helper function which resets the settings locked with the `locked_settings` option to the
+/// values of the config, so that the users cannot override them with their cookie, the search
+/// parameters or the settings page.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `search_settings` - It takes the search settings of the current request as an argument.
+/// * `category` - It takes the optional search category (from the url or a bang) as an argument.
+pub(crate) fn lock_settings<'a>(
+    config: &'a Config,
+    search_settings: &mut server_models::Cookie<'a>,
+    category: Option<&str>,
+) {
+    for setting in &config.locked_settings {
+        match setting {
+            LockedSetting::SafeSearch => search_settings.safe_search_level = config.safe_search,
+            LockedSetting::Engines => {
+                search_settings.engines = Cow::Owned(
+                    config
+                        .default_engines(category)
+                        .into_iter()
+                        .map(Cow::Borrowed)
+                        .collect(),
+                );
+                search_settings.category_engines.clear();
+            }
+            LockedSetting::Language => search_settings.language = None,
+            LockedSetting::Region => search_settings.region = None,
+            LockedSetting::TimeRange => search_settings.time_range = None,
+        }
+    }
+}
+
+/// A helper function which returns the search parameters of the current request which should be
+/// kept in the links and the forms of the search page (like the language or the engines), so that
+/// changing the time range or refining the results does not reset them.
+///
+/// # Arguments
+///
+/// * `params` - It takes the search parameters of the current request as an argument.
+fn preserved_params(params: &SearchParams) -> Vec<(&'static str, String)> {
+    [
+        ("lang", params.lang.clone()),
+        ("region", params.region.clone()),
+        ("engines", params.engines.clone()),
+        ("category", params.category.clone()),
+        ("preferences", params.preferences.clone()),
+        (
+            "safesearch",
+            params.safesearch.map(|safesearch| safesearch.to_string()),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value.filter(|value| !value.is_empty())?)))
+    .collect()
+}
+
+/// A helper function which builds the url of the route streaming the search results of the
+/// current request, so that the results streamed to the search page are the same as the ones
+/// which would have been rendered with it.
+///
+/// # Arguments
+///
+/// * `query` - It takes the normalized search query (without the bang) as an argument.
+/// * `params` - It takes the search parameters of the current request as an argument.
+/// * `time_range` - It takes the time range to which the results are restricted as an argument.
+/// * `category` - It takes the optional search category (from the url or a bang) as an argument.
+fn stream_url(
+    query: &str,
+    params: &SearchParams,
+    time_range: Option<TimeRange>,
+    category: Option<&str>,
+) -> String {
+    let stream_params: Vec<String> = [("q", query.to_owned())]
+        .into_iter()
+        .chain(params.page.map(|page| ("page", page.to_string())))
+        .chain(time_range.map(|time_range| ("time_range", time_range.name().to_owned())))
+        .chain(
+            preserved_params(params)
+                .into_iter()
+                .filter(|(name, _)| *name != "category"),
+        )
+        .chain(category.map(|category| ("category", category.to_owned())))
+        .map(|(name, value)| format!("{name}={}", encode_query(&value)))
+        .collect();
+    format!("search/stream?{}", stream_params.join("&"))
+}
+
+/// A helper function which narrows the search results down to the ones which contain all the
+/// provided search terms in their title, description or url (ignoring the case).
+///
+/// # Arguments
+///
+/// * `search_results` - It takes the search results to be refined as an argument.
+/// * `refine` - It takes the additional search terms as an argument.
+fn refine_results(search_results: &SearchResults, refine: &str) -> SearchResults {
+    let terms: Vec<String> = refine.split_whitespace().map(str::to_lowercase).collect();
+
+    let mut refined_results = search_results.clone();
+    refined_results.results.retain(|result| {
+        let text = format!("{} {} {}", result.title, result.description, result.url).to_lowercase();
+        terms.iter().all(|term| text.contains(term.as_str()))
+    });
+    refined_results
+}
+
+/// A helper function which parses the comma separated list of upstream search engines provided via
+/// the `engines` parameter. The engines which are not configured in the config file are ignored,
+/// the engines which are configured but disabled by default can still be used.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `engines` - It takes the comma separated list of the upstream search engine names as an
+///   argument.
+fn requested_engines<'a>(config: &'a Config, engines: &str) -> Vec<Cow<'a, str>> {
+    let mut requested_engines: Vec<Cow<'a, str>> = engines
+        .split(',')
+        .filter_map(|engine| {
+            config
+                .upstream_search_engines
+                .keys()
+                .find(|name| name.eq_ignore_ascii_case(engine.trim()))
+                .map(|name| Cow::Borrowed(name.as_str()))
+        })
+        .collect();
+    requested_engines.sort();
+    requested_engines.dedup();
+    requested_engines
+}
+
+/// A helper function which checks the search query and the page number against the limits
+/// provided via the `request_limits` option, so that oversized requests are never sent to the
+/// upstream search engines.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `query` - It takes the normalized search query as an argument.
+/// * `page` - It takes the optional page number provided via the url as an argument.
+///
+/// # Returns
+///
+/// It returns `None` if the request is within the limits otherwise it returns a `400 Bad Request`
+/// response containing the error page.
+fn check_request_limits(config: &Config, query: &str, page: Option<u32>) -> Option<HttpResponse> {
+    let request_limits = &config.request_limits;
+    if query.chars().count() > request_limits.max_query_length {
+        return Some(bad_request(
+            config,
+            "Search Query Too Long",
+            &format!(
+                "The search query should not be longer than {} characters.",
+                request_limits.max_query_length
+            ),
+        ));
+    }
+    if page.unwrap_or(1) > request_limits.max_page {
+        return Some(bad_request(
+            config,
+            "Page Number Too Large",
+            &format!(
+                "The page number should not be greater than {}.",
+                request_limits.max_page
+            ),
+        ));
+    }
+    None
+}
+
+/// A helper function which checks that the search category is not disabled with the
+/// `disabled_categories` option.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `category` - It takes the optional search category (from the url or a bang) as an argument.
+///
+/// # Returns
+///
+/// It returns `None` if the category can be searched otherwise it returns a `403 Forbidden`
+/// response containing the error page.
+fn check_category(config: &Config, category: Option<&str>) -> Option<HttpResponse> {
+    let category = category.filter(|category| config.is_category_disabled(category))?;
+    Some(error_response(
+        HttpResponse::Forbidden(),
+        config,
+        "Search Category Disabled",
+        &format!("The {category} search category is disabled on this instance."),
+    ))
+}
+
+/// A helper function which checks that at least one of the upstream search engines answered the
+/// search, the client is asked to retry later when all the engines failed instead of being shown
+/// an empty results page.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `config` - It takes a parsed config struct.
+/// * `results` - It takes the results of the search as an argument.
+///
+/// # Returns
+///
+/// It returns `None` if an engine answered otherwise it returns a `503 Service Unavailable`
+/// response containing the error page (or the json error body) along with a `Retry-After` header
+/// set to the time after which the first of the engines is expected to be sent requests again.
+fn check_engines_answered(
+    req: &HttpRequest,
+    config: &Config,
+    results: &SearchResults,
+) -> Option<HttpResponse> {
+    if !results.all_engines_failed() {
+        return None;
+    }
+
+    let retry_after = results
+        .unavailable_engines()
+        .into_iter()
+        .map(|engine| {
+            blocked_for(engine).unwrap_or(Duration::from_secs(
+                config.aggregator.circuit_breaker_cooldown,
+            ))
+        })
+        .min()
+        .unwrap_or_default()
+        .as_secs()
+        .max(1);
+    let mut response = error_pages::error_response(
+        req,
+        StatusCode::SERVICE_UNAVAILABLE,
+        "None of the upstream search engines could answer the search, please try again in a \
+         few moments.",
+    );
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+    Some(response)
+}
+
+/// A helper function which builds a `400 Bad Request` response containing the error page.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `title` - It takes the title of the error as an argument.
+/// * `message` - It takes the message explaining the error as an argument.
+fn bad_request(config: &Config, title: &str, message: &str) -> HttpResponse {
+    error_response(HttpResponse::BadRequest(), config, title, message)
+}
+
+/// A helper function which builds a response with the provided status containing the error page.
+///
+/// # Arguments
+///
+/// * `response` - It takes the builder of the response with its status as an argument.
+/// * `config` - It takes a parsed config struct.
+/// * `title` - It takes the title of the error as an argument.
+/// * `message` - It takes the message explaining the error as an argument.
+fn error_response(
+    mut response: HttpResponseBuilder,
+    config: &Config,
+    title: &str,
+    message: &str,
+) -> HttpResponse {
+    response.content_type(ContentType::html()).body(
+        crate::templates::views::error::error(
+            &config.style.colorscheme,
+            &config.style.theme,
+            &config.style.animation,
+            title,
+            message,
+            None,
+        )
+        .0,
+    )
+}
+
+/// A helper function which counts the search in the anonymized usage statistics (in the
+/// background) when the `usage_stats.enabled` option is set. The search categories which are not
+/// configured are counted as `general` and only the configured upstream search engines are
+/// counted, so that the usage statistics cannot be filled with arbitrary names.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `config` - It takes a parsed config struct.
+/// * `category` - It takes the optional search category (from the url or a bang) as an argument.
+/// * `engines` - It takes the upstream search engines selected for the search as an argument.
+fn record_usage(
+    req: &HttpRequest,
+    config: &Arc<Config>,
+    category: Option<&str>,
+    engines: &[Cow<'_, str>],
+) {
+    if !config.usage_stats.enabled {
+        return;
+    }
+    let Some(usage_stats) = req.app_data::<web::Data<UsageStats>>().cloned() else {
+        return;
+    };
+
+    let config = Arc::clone(config);
+    let client_ip = request_client_ip(req);
+    let category = category
+        .filter(|category| config.category_engines.contains_key(*category))
+        .unwrap_or("general")
+        .to_owned();
+    let engines: Vec<String> = engines
+        .iter()
+        .filter_map(|engine| {
+            config
+                .upstream_search_engines
+                .keys()
+                .find(|name| name.eq_ignore_ascii_case(engine))
+                .cloned()
+        })
+        .collect();
+    tokio::spawn(async move {
+        usage_stats
+            .record_search(&config, client_ip, &category, &engines)
+            .await
+    });
+}
+
+/// A helper function which adds the search to the search history of the server-side profile of
+/// the user (in the background) when the `profiles.history` option is set and the user has
+/// enabled the search history on the settings page.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `config` - It takes a parsed config struct.
+/// * `query` - It takes the normalized search query as an argument.
+/// * `search_settings` - It takes the search settings of the current request as an argument.
+fn record_history(
+    req: &HttpRequest,
+    config: &Arc<Config>,
+    query: &str,
+    search_settings: &server_models::Cookie<'_>,
+) {
+    if !(config.profiles.enabled && config.profiles.history)
+        || search_settings.search_history != Some(true)
+    {
+        return;
+    }
+    let (Some(profiles), Some(token)) = (
+        req.app_data::<web::Data<Profiles>>().cloned(),
+        req.cookie(PROFILE_COOKIE),
+    ) else {
+        return;
+    };
+
+    let config = Arc::clone(config);
+    let query = query.to_owned();
+    tokio::spawn(async move {
+        if let Err(error) = profiles.record_search(&config, token.value(), &query).await {
+            tracing::error!("The search history could not be saved: {error}");
+        }
+    });
+}
+
+/// A helper function which builds the options sent to the upstream search engines from the search
+/// settings of the current request.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `search_settings` - It takes the search settings of the current request as an argument.
+fn search_options(config: &Config, search_settings: &server_models::Cookie<'_>) -> SearchOptions {
+    SearchOptions {
+        safe_search: search_settings.safe_search_level,
+        language: search_settings.language.as_deref().map(str::to_owned),
+        region: search_settings.region.as_deref().map(str::to_owned),
+        time_range: search_settings
+            .time_range
+            .as_deref()
+            .and_then(TimeRange::from_name),
+        blocked_domains: search_settings.blocked_domains.clone(),
+        allowed_domains: search_settings.allowed_domains.clone(),
+        randomize_headers: config.randomize_headers,
+    }
+}
+
+/// Fetches the results shown on a page of the search page, which holds the number of results
+/// selected by the user (or the `results_per_page` option). The pages of the upstream search
+/// engines (each of them from the cache or from the engines) are fetched concurrently and their
+/// results are merged in order until there are enough results to fill the requested page and to
+/// know whether a next page is available.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `cache` - It takes the shared cache as an argument.
+/// * `client` - It takes the shared client through which the requests are sent to the upstream
+///   search engines as an argument.
+/// * `query` - It takes the normalized search query as an argument.
+/// * `page` - It takes the page number (starting from `0`) of the search page as an argument.
+/// * `search_settings` - It takes the search settings of the current request as an argument.
+///
+/// # Error
+///
+/// Returns an error if the results of a page of the upstream search engines could not be
+/// fetched.
+async fn page_results(
+    config: &Config,
+    cache: &web::Data<SharedCache>,
+    client: &Client,
+    query: &str,
+    page: u32,
+    search_settings: &server_models::Cookie<'_>,
+) -> Result<SearchResults, Box<dyn std::error::Error>> {
+    let results_per_page = usize::from(
+        search_settings
+            .results_per_page
+            .unwrap_or(config.results_per_page),
+    );
+    let start = page as usize * results_per_page;
+    let end = start + results_per_page;
+
+    // The errors, the statistics and the suggestions of the first page of the upstream search
+    // engines are shown with every page.
+    let mut page_results = results(config, cache, client, query, 0, search_settings)
+        .await?
+        .0;
+    if page_results.disallowed || page_results.no_engines_selected() {
+        return Ok(page_results);
+    }
+
+    let mut urls: HashSet<String> = HashSet::new();
+    let mut collected = std::mem::take(&mut page_results.results);
+    collected.retain(|result| urls.insert(result.url.clone()));
+    let mut engine_page: u32 = 1;
+    'pages: while collected.len() <= end && engine_page < config.request_limits.max_page {
+        // The pages of the upstream search engines still needed to fill the requested page are
+        // estimated from the number of results on the pages fetched so far and fetched
+        // concurrently, so that their random delays and their requests overlap.
+        let results_per_engine_page = (collected.len() / engine_page as usize).max(1);
+        let missing_pages = (end + 1 - collected.len()).div_ceil(results_per_engine_page);
+        let last_page = engine_page
+            .saturating_add(u32::try_from(missing_pages).unwrap_or(u32::MAX))
+            .min(config.request_limits.max_page);
+        let engine_pages = try_join_all((engine_page..last_page).map(|engine_page| {
+            results(config, cache, client, query, engine_page, search_settings)
+        }))
+        .await?;
+
+        for (engine_results, _) in engine_pages {
+            let previous_len = collected.len();
+            collected.extend(
+                engine_results
+                    .results
+                    .into_iter()
+                    .filter(|result| urls.insert(result.url.clone())),
+            );
+            // The upstream search engines have no more results once a page brings no new result.
+            if collected.len() == previous_len {
+                break 'pages;
+            }
+        }
+        engine_page = last_page;
+    }
+
+    let has_next_page = collected.len() > end && page + 1 < config.request_limits.max_page;
+    page_results.results = collected
+        .into_iter()
+        .skip(start)
+        .take(results_per_page)
+        .collect();
+    page_results.set_page(page + 1, has_next_page);
+    Ok(page_results)
+}
+
 /// Fetches the results for a query and page. It First checks the redis cache, if that
 /// fails it gets proper results by requesting from the upstream search engines.
 ///
 /// # Arguments
 ///
 /// * `url` - It takes the url of the current page that requested the search results for a
-/// particular search query.
+///   particular search query.
 /// * `config` - It takes a parsed config struct.
+/// * `client` - It takes the shared client through which the requests are sent to the upstream
+///   search engines as an argument.
 /// * `query` - It takes the page number as u32 value.
 /// * `req` - It takes the `HttpRequest` struct as a value.
 ///
@@ -160,6 +1467,7 @@ pub async fn search(
 async fn results(
     config: &Config,
     cache: &web::Data<SharedCache>,
+    client: &Client,
     query: &str,
     page: u32,
     search_settings: &server_models::Cookie<'_>,
@@ -167,18 +1475,13 @@ async fn results(
     // eagerly parse cookie value to evaluate safe search level
     let safe_search_level = search_settings.safe_search_level;
 
-    let cache_key = format!(
-        "http://{}:{}/search?q={}&page={}&safesearch={}&engines={}",
-        config.binding_ip,
-        config.port,
-        query,
-        page,
-        safe_search_level,
-        search_settings.engines.join(",")
-    );
+    let options = search_options(config, search_settings);
+
+    let cache_key = results_cache_key(config, query, page, search_settings, &options);
 
     // fetch the cached results json.
     let cached_results = cache.cached_results(&cache_key).await;
+    METRICS.record_cache_lookup(cached_results.is_ok());
     // check if fetched cache results was indeed fetched or it was an error and if so
     // handle the data accordingly.
     match cached_results {
@@ -187,14 +1490,13 @@ async fn results(
             if safe_search_level == 4 {
                 let mut results: SearchResults = SearchResults::default();
 
-                let flag: bool =
-                    !is_match_from_filter_list(file_path(FileType::BlockList)?, query)?;
                 // Return early when query contains disallowed words,
-                if flag {
+                if is_disallowed_query(file_path(FileType::BlockList)?, query)? {
                     results.set_disallowed();
-                    cache
-                        .cache_results(&[results.clone()], &[cache_key.clone()])
-                        .await?;
+                    cache.clone().into_inner().cache_results_in_background(
+                        vec![results.clone()],
+                        vec![cache_key.clone()],
+                    );
                     results.set_safe_search_level(safe_search_level);
                     return Ok((results, cache_key));
                 }
@@ -215,7 +1517,8 @@ async fn results(
                             .iter()
                             .filter_map(|engine| EngineHandler::new(engine).ok())
                             .collect::<Vec<EngineHandler>>(),
-                        safe_search_level,
+                        client,
+                        &options,
                     )
                     .await?
                 }
@@ -225,21 +1528,81 @@ async fn results(
                     search_results
                 }
             };
-            if results.engine_errors_info().is_empty()
+            if results.engine_errors_info.is_empty()
                 && results.results().is_empty()
                 && !results.no_engines_selected()
             {
                 results.set_filtered();
             }
-            cache
-                .cache_results(&[results.clone()], &[cache_key.clone()])
-                .await?;
+            // The empty results and the failures of the upstream search engines are not cached, so
+            // that the search is sent to the engines again once they have recovered. The results
+            // are cached in the background so that the response is not delayed by the cache.
+            if results.is_cacheable() {
+                cache
+                    .clone()
+                    .into_inner()
+                    .cache_results_in_background(vec![results.clone()], vec![cache_key.clone()]);
+            }
             results.set_safe_search_level(safe_search_level);
             Ok((results, cache_key))
         }
     }
 }
 
+/// A helper function which builds the key under which the results of a page of the upstream
+/// search engines are cached, which holds all the search settings changing the results.
+///
+/// # Arguments
+///
+/// * `config` - It takes a parsed config struct.
+/// * `query` - It takes the normalized search query as an argument.
+/// * `page` - It takes the page number (starting from `0`) of the upstream search engines as an
+///   argument.
+/// * `search_settings` - It takes the search settings of the current request as an argument.
+/// * `options` - It takes the options sent to the upstream search engines as an argument.
+fn results_cache_key(
+    config: &Config,
+    query: &str,
+    page: u32,
+    search_settings: &server_models::Cookie<'_>,
+    options: &SearchOptions,
+) -> String {
+    format!(
+        "http://{}:{}/search?q={}&page={}&safesearch={}&engines={}&lang={}&region={}&time_range={}&blocked={}&allowed={}",
+        config.binding_ip,
+        config.port,
+        query,
+        page,
+        search_settings.safe_search_level,
+        search_settings.engines.join(","),
+        options.language.as_deref().unwrap_or_default(),
+        options.region.as_deref().unwrap_or_default(),
+        options
+            .time_range
+            .map(|time_range| time_range.name())
+            .unwrap_or_default(),
+        options.blocked_domains.join(","),
+        options.allowed_domains.join(",")
+    )
+}
+
+/// A helper function which checks whether the search query is disallowed with the safe search
+/// level set to 4, that is whether it matches one of the regex based rules present in the
+/// blocklist file.
+///
+/// # Arguments
+///
+/// * `blocklist` - It takes the file path of the blocklist as an argument.
+/// * `query` - It takes the search query to be checked as an argument.
+///
+/// # Error
+///
+/// Returns a bool indicating whether the search query is disallowed on success otherwise returns
+/// a standard error type if the blocklist file could not be read.
+fn is_disallowed_query(blocklist: &str, query: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    is_match_from_filter_list(blocklist, query)
+}
+
 /// A helper function which checks whether the search query contains any keywords which should be
 /// disallowed/allowed based on the regex based rules present in the blocklist and allowlist files.
 ///
@@ -288,3 +1651,471 @@ fn get_safesearch_level(cookie_level: &Option<u8>, url_level: &Option<u8>, confi
         None => cookie_level.unwrap_or(config_level),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_category_bang_with_cookie() {
+        let config = Config {
+            category_engines: HashMap::from([("images".to_owned(), vec!["Bing".to_owned()])]),
+            ..Default::default()
+        };
+        let cookie = r#"{"theme":"simple","colorscheme":"catppuccin-mocha","engines":["DuckDuckGo","Searx"],"safe_search_level":0}"#;
+
+        assert_eq!(
+            parse_bang("!images cats", &config.bangs),
+            Some((Bang::Category("images"), "cats".to_owned()))
+        );
+
+        let engines = |category: Option<&str>| {
+            search_settings(&config, Some(cookie), category)
+                .engines
+                .iter()
+                .map(|engine| engine.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(engines(Some("images")), vec!["Bing"]);
+        assert_eq!(engines(None), vec!["DuckDuckGo", "Searx"]);
+    }
+
+    #[test]
+    fn test_locked_settings() {
+        let config = Config {
+            safe_search: 2,
+            locked_settings: vec![LockedSetting::SafeSearch, LockedSetting::Engines],
+            ..Default::default()
+        };
+        let cookie = r#"{"theme":"simple","colorscheme":"catppuccin-mocha","engines":["Bing"],"safe_search_level":0,"language":"de"}"#;
+        let params =
+            web::Query::<SearchParams>::from_query("q=rust&safesearch=0&engines=brave").unwrap();
+
+        let search_settings = request_search_settings(&config, Some(cookie), &params, None);
+        assert_eq!(search_settings.safe_search_level, 2);
+        assert_eq!(*search_settings.engines, ["DuckDuckGo"]);
+        // The settings which are not locked are still taken from the cookie.
+        assert_eq!(search_settings.language.as_deref(), Some("de"));
+    }
+
+    #[test]
+    fn test_disabled_categories() {
+        let config = Config {
+            disabled_categories: vec!["images".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            check_category(&config, Some("images")).map(|response| response.status()),
+            Some(StatusCode::FORBIDDEN)
+        );
+        assert!(check_category(&config, Some("news")).is_none());
+        assert!(check_category(&config, None).is_none());
+    }
+
+    #[test]
+    fn test_engines_answered() {
+        use crate::models::{aggregation_models::EngineErrorInfo, engine_models::EngineError};
+
+        let config = Config::default();
+        let req = actix_web::test::TestRequest::default().to_http_request();
+        assert!(check_engines_answered(&req, &config, &SearchResults::default()).is_none());
+
+        let results = SearchResults::new(
+            Vec::new(),
+            &[EngineErrorInfo::new(&EngineError::TimedOut, "bing")],
+        );
+        let response = check_engines_answered(&req, &config, &results).unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers().get(header::RETRY_AFTER).unwrap(),
+            &config.aggregator.circuit_breaker_cooldown.to_string()
+        );
+    }
+
+    #[test]
+    fn test_category_engines_from_cookie() {
+        let config = Config::default();
+        let cookie = r#"{"theme":"simple","colorscheme":"catppuccin-mocha","engines":["DuckDuckGo"],"category_engines":{"news":["Bing","Brave"]},"safe_search_level":0}"#;
+
+        let engines = |category: Option<&str>| {
+            search_settings(&config, Some(cookie), category)
+                .engines
+                .iter()
+                .map(|engine| engine.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(engines(Some("news")), vec!["Bing", "Brave"]);
+        // The categories without a selection use the engines of the general category.
+        assert_eq!(engines(Some("videos")), vec!["DuckDuckGo"]);
+        assert_eq!(engines(None), vec!["DuckDuckGo"]);
+    }
+
+    #[test]
+    fn test_preserved_params() {
+        let params = web::Query::<SearchParams>::from_query(
+            "q=rust&page=2&lang=de&region=&engines=bing,brave&preferences=e30&safesearch=0&time_range=week",
+        )
+        .unwrap();
+
+        assert_eq!(
+            preserved_params(&params),
+            vec![
+                ("lang", "de".to_owned()),
+                ("engines", "bing,brave".to_owned()),
+                ("preferences", "e30".to_owned()),
+                ("safesearch", "0".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream_url() {
+        let params = web::Query::<SearchParams>::from_query(
+            "q=!images+rust+lang&page=2&lang=de&category=news&stream=true",
+        )
+        .unwrap();
+
+        assert_eq!(
+            stream_url("rust lang", &params, Some(TimeRange::Week), Some("images")),
+            "search/stream?q=rust+lang&page=2&time_range=week&lang=de&category=images"
+        );
+    }
+
+    #[test]
+    fn test_done_event_keeps_the_results_of_the_page() {
+        let results = ["https://a.com/", "https://b.com/"]
+            .map(|url| SearchResult::new(url, url, "", &["bing"]))
+            .into();
+        let event = done_event(results, 1);
+
+        let data = std::str::from_utf8(&event)
+            .unwrap()
+            .strip_prefix("event: done\ndata: ")
+            .unwrap();
+        let data: serde_json::Value = serde_json::from_str(data.trim_end()).unwrap();
+        assert_eq!(data["ranking"].as_array().unwrap().len(), 1);
+        assert_eq!(data["ranking"][0]["url"], "https://a.com/");
+    }
+
+    #[actix_web::test]
+    async fn test_stream_without_engines() {
+        use crate::results::aggregator::upstream_client;
+        use actix_web::test::{call_service, init_service, read_body, TestRequest};
+
+        let config = Config::default();
+        let app = init_service(
+            actix_web::App::new()
+                .app_data(web::Data::new(upstream_client(&config).unwrap()))
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .service(stream),
+        )
+        .await;
+
+        let request = TestRequest::get()
+            .uri("/search/stream?q=rust&engines=unknown")
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        assert_eq!(
+            read_body(response).await,
+            "event: done\ndata: {\"ranking\":[]}\n\n"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_stream_allows_the_queries_missing_from_the_blocklist() {
+        use crate::results::aggregator::upstream_client;
+        use actix_web::test::{call_service, init_service, TestRequest};
+
+        // The blocklist file of the repository is empty, so that no query is disallowed with the
+        // safe search level set to 4.
+        let config = Config {
+            safe_search: 4,
+            ..Default::default()
+        };
+        let app = init_service(
+            actix_web::App::new()
+                .app_data(web::Data::new(upstream_client(&config).unwrap()))
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .service(stream),
+        )
+        .await;
+
+        let request = TestRequest::get()
+            .uri("/search/stream?q=rust&engines=unknown")
+            .to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.status(), actix_web::http::StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+    }
+
+    #[test]
+    fn test_is_disallowed_query() {
+        use std::io::Write;
+
+        let mut blocklist_file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(blocklist_file, "(?i)casino").unwrap();
+        let blocklist = blocklist_file.path().to_str().unwrap();
+
+        assert!(is_disallowed_query(blocklist, "online Casino bonus").unwrap());
+        assert!(!is_disallowed_query(blocklist, "rust programming").unwrap());
+        assert!(is_disallowed_query("/nonexistent/blocklist.txt", "rust").is_err());
+    }
+
+    #[test]
+    fn test_refine_results() {
+        use crate::models::aggregation_models::SearchResult;
+
+        let search_results = SearchResults::new(
+            vec![
+                SearchResult::new(
+                    "The Rust Book",
+                    "https://doc.rust-lang.org/book/",
+                    "Learn the Rust programming language",
+                    &["duckduckgo"],
+                ),
+                SearchResult::new(
+                    "Rust (fungus)",
+                    "https://en.wikipedia.org/wiki/Rust_(fungus)",
+                    "Rusts are plant pathogens",
+                    &["bing"],
+                ),
+            ],
+            &[],
+        );
+        let titles = |refine: &str| {
+            refine_results(&search_results, refine)
+                .results
+                .into_iter()
+                .map(|result| result.title)
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(titles("PROGRAMMING"), vec!["The Rust Book"]);
+        assert_eq!(titles("wikipedia plant"), vec!["Rust (fungus)"]);
+        assert_eq!(titles("rust language fungus"), Vec::<String>::new());
+        assert_eq!(titles(""), vec!["The Rust Book", "Rust (fungus)"]);
+    }
+
+    #[test]
+    fn test_requested_engines() {
+        let config = Config::default();
+
+        assert_eq!(
+            requested_engines(&config, " bing,DUCKDUCKGO,unknown,Bing"),
+            vec!["Bing", "DuckDuckGo"]
+        );
+        assert!(requested_engines(&config, "").is_empty());
+        assert!(requested_engines(&config, "google,yahoo").is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_request_limits() {
+        let config = Config::default();
+        let max_page = config.request_limits.max_page;
+
+        assert!(check_request_limits(&config, "rust", Some(max_page)).is_none());
+
+        let response = check_request_limits(&config, "rust", Some(max_page + 1)).unwrap();
+        assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("Page Number Too Large"));
+
+        let long_query = "a".repeat(config.request_limits.max_query_length + 1);
+        let response = check_request_limits(&config, &long_query, None).unwrap();
+        assert_eq!(response.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("Search Query Too Long"));
+    }
+
+    #[actix_web::test]
+    async fn test_search_page_response() {
+        let page = || b"<html>results</html>".to_vec();
+        let response = search_page_response(
+            &actix_web::test::TestRequest::default().to_http_request(),
+            HttpResponse::Ok(),
+            page(),
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).unwrap(),
+            "private, no-cache"
+        );
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+        // The page is not sent again while the cached results are unchanged.
+        let revalidate = |body: Vec<u8>| {
+            search_page_response(
+                &actix_web::test::TestRequest::default()
+                    .insert_header((header::IF_NONE_MATCH, etag.clone()))
+                    .to_http_request(),
+                HttpResponse::Ok(),
+                body,
+            )
+        };
+        let response = revalidate(page());
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(header::ETAG).unwrap(), &etag);
+        let body = actix_web::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+
+        let response = revalidate(b"<html>new results</html>".to_vec());
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers().get(header::ETAG).unwrap(), &etag);
+    }
+
+    #[cfg(all(feature = "memory-cache", not(feature = "redis-cache")))]
+    #[actix_web::test]
+    async fn test_page_results() {
+        use crate::{
+            cache::cacher::create_cache, models::aggregation_models::SearchResult,
+            results::aggregator::upstream_client,
+        };
+
+        let config = Config::default();
+        let cache = web::Data::new(SharedCache::new(create_cache(&config).await));
+        let client = upstream_client(&config).unwrap();
+        let mut search_settings = search_settings(&config, None, None);
+        search_settings.results_per_page = Some(3);
+
+        // The pages of the upstream search engines are cached so that they are never queried.
+        let options = search_options(&config, &search_settings);
+        let engine_pages = [vec!["a", "b"], vec!["b", "c", "d"], vec!["e"], vec![]];
+        for (page, urls) in engine_pages.iter().enumerate() {
+            let results = urls
+                .iter()
+                .map(|url| SearchResult::new(url, url, "", &["bing"]))
+                .collect();
+            let key = results_cache_key(&config, "rust", page as u32, &search_settings, &options);
+            cache
+                .cache_results(&[SearchResults::new(results, &[])], &[key])
+                .await
+                .unwrap();
+        }
+
+        let urls = |results: &SearchResults| {
+            results
+                .results
+                .iter()
+                .map(|result| result.url.clone())
+                .collect::<Vec<String>>()
+        };
+        let results = page_results(&config, &cache, &client, "rust", 0, &search_settings)
+            .await
+            .unwrap();
+        assert_eq!(urls(&results), ["a", "b", "c"]);
+        assert_eq!((results.page, results.has_next_page), (1, true));
+
+        let results = page_results(&config, &cache, &client, "rust", 1, &search_settings)
+            .await
+            .unwrap();
+        assert_eq!(urls(&results), ["d", "e"]);
+        assert_eq!((results.page, results.has_next_page), (2, false));
+    }
+
+    #[actix_web::test]
+    async fn test_search_form_requires_csrf_token() {
+        use crate::{
+            cache::cacher::create_cache,
+            results::aggregator::upstream_client,
+            server::csrf::{csrf_token, CSRF_COOKIE},
+        };
+        use actix_web::{
+            cookie::Cookie,
+            test::{call_service, init_service, TestRequest},
+            App,
+        };
+
+        let config = Config::default();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedCache::new(
+                    create_cache(&config).await,
+                )))
+                .app_data(web::Data::new(upstream_client(&config).unwrap()))
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .service(search_form),
+        )
+        .await;
+        let token = csrf_token(&TestRequest::default().to_http_request());
+        let request = |cookie: Option<&str>, form: &[(&str, &str)]| {
+            let mut request = TestRequest::post().uri("/search").set_form(form);
+            if let Some(cookie) = cookie {
+                request = request.cookie(Cookie::new(CSRF_COOKIE, cookie.to_owned()));
+            }
+            request.to_request()
+        };
+
+        let response = call_service(&app, request(None, &[("q", "")])).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = call_service(&app, request(Some(&token), &[("q", "")])).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // The empty search is redirected to the index page once the form is accepted.
+        let response = call_service(
+            &app,
+            request(Some(&token), &[("q", ""), ("csrf_token", &token)]),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+    }
+
+    #[actix_web::test]
+    async fn test_reverse_image_upload_requires_csrf_token_and_image() {
+        use crate::{
+            cache::cacher::create_cache,
+            results::aggregator::upstream_client,
+            server::csrf::{csrf_token, CSRF_COOKIE},
+        };
+        use actix_web::{
+            cookie::Cookie,
+            test::{call_service, init_service, TestRequest},
+            App,
+        };
+
+        let config = Config::default();
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedCache::new(
+                    create_cache(&config).await,
+                )))
+                .app_data(web::Data::new(upstream_client(&config).unwrap()))
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .service(reverse_image_upload),
+        )
+        .await;
+        let token = csrf_token(&TestRequest::default().to_http_request());
+        let request = |csrf_token: &str, image_type: &str| {
+            let body = format!(
+                "--xyz\r\nContent-Disposition: form-data; name=\"csrf_token\"\r\n\r\n{csrf_token}\r\n\
+                 --xyz\r\nContent-Disposition: form-data; name=\"image\"; filename=\"cat\"\r\n\
+                 Content-Type: {image_type}\r\n\r\ncat\r\n--xyz--\r\n"
+            );
+            TestRequest::post()
+                .uri("/search/image")
+                .insert_header((header::CONTENT_TYPE, "multipart/form-data; boundary=xyz"))
+                .cookie(Cookie::new(CSRF_COOKIE, token.clone()))
+                .set_payload(body)
+                .to_request()
+        };
+
+        let response = call_service(&app, request("", "image/png")).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = call_service(&app, request(&token, "text/plain")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
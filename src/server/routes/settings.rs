@@ -0,0 +1,1064 @@
+//! This module handles the route saving the settings of the user, which validates the submitted
+//! preferences before writing them into the `appCookie` cookie (or into the server-side profile of
+//! the user) read by the search routes.
+
+use crate::{
+    config::{parser::Config, reloader::SharedConfig},
+    models::{
+        engine_models::TimeRange,
+        locale_models::{supported_language, supported_region},
+        parser_models::SafeSearchLevel,
+        server_models::{Cookie, COOKIE_VERSION, ENGINE_CATEGORIES},
+    },
+    query::bangs::encode_query,
+    results::domain_filter::domain_pattern,
+    server::{
+        csrf::{verify_csrf_token, CSRF_FIELD},
+        error_pages::HttpError,
+        profiles::{
+            is_profile_token, new_profile_token, profile_id, Profiles, MAX_CUSTOM_CSS_SIZE,
+            PROFILE_COOKIE,
+        },
+        routes::search::lock_settings,
+        static_files::etag_matches,
+    },
+};
+use actix_web::{
+    cookie::{self, SameSite},
+    get,
+    http::{
+        header::{
+            self, ContentDisposition, ContentType, DispositionParam, DispositionType, ETag,
+            EntityTag,
+        },
+        StatusCode,
+    },
+    post, web, HttpRequest, HttpResponse,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde_json::{json, Value};
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// The name of the cookie storing the settings of the user.
+pub const SETTINGS_COOKIE: &str = "appCookie";
+
+/// The name of the search parameter carrying the settings of the user encoded in the url.
+pub const PREFERENCES_PARAM: &str = "preferences";
+
+/// The name of the file into which the settings are exported.
+const EXPORT_FILE_NAME: &str = "websurfx-settings.json";
+
+/// The number of days after which the settings cookie expires.
+const SETTINGS_COOKIE_DAYS: i64 = 365;
+
+/// Handles the route saving the settings submitted with the settings form. The submitted
+/// preferences are checked against the themes, the colorschemes, the animations and the upstream
+/// search engines of the instance before they are written into the versioned `appCookie`
+/// cookie, and the user is redirected back to the settings page. The form should carry the token
+/// of the `csrf_token` cookie (set along with the settings page) in its `csrf_token` field.
+///
+/// When the `profiles.enabled` option is set, the settings are stored in the server-side profile
+/// of the user instead if the `profile` field is checked (only the style of the website is then
+/// kept in the `appCookie` cookie), and the profile saved on another browser is restored with the
+/// `restore_code` field.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -b "csrf_token=<token>" \
+///   -d "themes=simple&colorschemes=catppuccin-mocha&engines=DuckDuckGo&csrf_token=<token>" \
+///   "http://127.0.0.1:8080/settings"
+/// ```
+#[post("/settings")]
+pub async fn save_settings(
+    req: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let field = |name: &str| {
+        form.iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    if !verify_csrf_token(&req, field(CSRF_FIELD)) {
+        return Err(HttpError::new(
+            StatusCode::FORBIDDEN,
+            "The form has expired or was sent from another site, please save the settings again.",
+        ));
+    }
+
+    let profiles = req
+        .app_data::<web::Data<Profiles>>()
+        .filter(|_| config.profiles.enabled);
+    if let Some(restore_code) = field("restore_code")
+        .map(str::trim)
+        .filter(|restore_code| !restore_code.is_empty())
+    {
+        let restore_code = restore_code.to_ascii_lowercase();
+        let settings = match profiles {
+            Some(profiles) => profiles.load(&config, &restore_code).await,
+            None => None,
+        }
+        .ok_or_else(|| {
+            HttpError::bad_request("The restore code is invalid or the profile has expired.")
+        })?;
+        let defaults = Cookie::build(&config.style, Vec::new(), config.safe_search);
+        return Ok(HttpResponse::SeeOther()
+            .insert_header((header::LOCATION, "/settings?saved=true"))
+            .cookie(preference_cookie(
+                &req,
+                SETTINGS_COOKIE,
+                style_cookie(&Cookie::parse(&settings, defaults), &restore_code),
+            ))
+            .cookie(preference_cookie(&req, PROFILE_COOKIE, restore_code))
+            .finish());
+    }
+
+    let settings = settings_cookie(&config, &form)?;
+    write_settings(
+        &req,
+        &config,
+        &settings,
+        profiles.filter(|_| field("profile") == Some("on")),
+    )
+    .await
+}
+
+/// Handles the route exporting the settings of the user as a json file, which can be imported on
+/// another browser or instance with the `POST /settings/import` route. The settings which the user
+/// has not saved are exported with the defaults of the instance.
+///
+/// # Example
+///
+/// ```bash
+/// curl -b "appCookie=<settings>" "http://127.0.0.1:8080/settings/export"
+/// ```
+#[get("/settings/export")]
+pub async fn export_settings(
+    req: HttpRequest,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let defaults = default_settings(&config);
+    let settings = match user_settings(&req, &config).await {
+        Some(settings) => Cookie::parse(&settings, defaults),
+        None => defaults,
+    };
+    let exported = serde_json::to_string_pretty(&settings).map_err(|_| {
+        HttpError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "The settings could not be exported.",
+        )
+    })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::json())
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(EXPORT_FILE_NAME.to_owned())],
+        })
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(exported))
+}
+
+/// Handles the route importing the settings exported with the `GET /settings/export` route, which
+/// are provided in the `settings` field of the settings form. The imported settings are migrated
+/// from the older versions of the format and checked like the settings submitted with the form
+/// before they are saved (in the cookie or in the server-side profile like the other settings), and
+/// the user is redirected back to the settings page.
+///
+/// # Example
+///
+/// ```bash
+/// curl -X POST -b "csrf_token=<token>" \
+///   --data-urlencode "settings@websurfx-settings.json" -d "csrf_token=<token>" \
+///   "http://127.0.0.1:8080/settings/import"
+/// ```
+#[post("/settings/import")]
+pub async fn import_settings(
+    req: HttpRequest,
+    form: web::Form<Vec<(String, String)>>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let field = |name: &str| {
+        form.iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    if !verify_csrf_token(&req, field(CSRF_FIELD)) {
+        return Err(HttpError::new(
+            StatusCode::FORBIDDEN,
+            "The form has expired or was sent from another site, please import the settings again.",
+        ));
+    }
+
+    let imported = field("settings").unwrap_or_default();
+    if !matches!(
+        serde_json::from_str::<Value>(imported),
+        Ok(Value::Object(_))
+    ) {
+        return Err(HttpError::bad_request(
+            "The imported settings are not a valid settings file.",
+        ));
+    }
+    let imported_fields = settings_form(&Cookie::parse(imported, default_settings(&config)));
+    let settings = settings_cookie(&config, &imported_fields)?;
+    let profiles = req
+        .app_data::<web::Data<Profiles>>()
+        .filter(|_| config.profiles.enabled && field("profile") == Some("on"));
+    write_settings(&req, &config, &settings, profiles).await
+}
+
+/// Handles the route serving the custom stylesheet stored in a server-side profile, which is
+/// added to the pages by the browsers of the users of the profile (its url is kept in the
+/// `appCookie` cookie). The profile is identified by its id (the hash of its token), so that the
+/// url of the stylesheet does not reveal the restore code of the profile.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/profile/<id>/style.css"
+/// ```
+#[get("/profile/{id}/style.css")]
+pub async fn custom_stylesheet(
+    req: HttpRequest,
+    id: web::Path<String>,
+    config: web::Data<SharedConfig>,
+) -> Result<HttpResponse, HttpError> {
+    let config = config.load();
+    let not_found = || HttpError::new(StatusCode::NOT_FOUND, "The stylesheet does not exist.");
+    let profiles = req
+        .app_data::<web::Data<Profiles>>()
+        .filter(|_| config.profiles.enabled)
+        .ok_or_else(not_found)?;
+    let settings = profiles
+        .load_by_id(&config, &id)
+        .await
+        .ok_or_else(not_found)?;
+    let custom_css = Cookie::parse(&settings, default_settings(&config))
+        .custom_css
+        .ok_or_else(not_found)?;
+
+    // The stylesheet is revalidated on every page so that the changes are applied at once.
+    let etag = EntityTag::new_strong(blake3::hash(custom_css.as_bytes()).to_hex().to_string());
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header(ETag(etag.clone()))
+        .insert_header((header::CACHE_CONTROL, "private, no-cache"));
+    if etag_matches(&req, &etag) {
+        return Ok(response.status(StatusCode::NOT_MODIFIED).finish());
+    }
+    Ok(response
+        .content_type("text/css; charset=utf-8")
+        .body(custom_css))
+}
+
+/// A helper function which saves the validated settings of the user in the settings cookie or in
+/// the server-side profile of the user, and redirects the user back to the settings page.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request of the user as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `settings` - It takes the validated settings of the user as an argument.
+/// * `profiles` - It takes the server-side profiles if the settings should be saved in the
+///   profile of the user as an argument.
+///
+/// # Error
+///
+/// Returns a `400 Bad Request` error if the settings are too large to be saved or a `500 Internal
+/// Server Error` error if the profile could not be saved.
+async fn write_settings(
+    req: &HttpRequest,
+    config: &Config,
+    settings: &Cookie<'_>,
+    profiles: Option<&web::Data<Profiles>>,
+) -> Result<HttpResponse, HttpError> {
+    let serialized = serde_json::to_string(settings).map_err(|_| {
+        HttpError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "The settings could not be saved.",
+        )
+    })?;
+    let mut response = HttpResponse::SeeOther();
+    response.insert_header((header::LOCATION, "/settings?saved=true"));
+
+    match profiles {
+        Some(profiles) => {
+            if serialized.len() > config.profiles.max_size {
+                return Err(HttpError::bad_request(
+                    "The settings are too large to be saved, please remove some of the domains.",
+                ));
+            }
+            let token = req
+                .cookie(PROFILE_COOKIE)
+                .map(|cookie| cookie.value().to_owned())
+                .filter(|token| is_profile_token(token))
+                .unwrap_or_else(new_profile_token);
+            profiles
+                .save(config, &token, &serialized)
+                .await
+                .map_err(|error| {
+                    tracing::error!("The profile could not be saved: {error}");
+                    HttpError::new(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "The settings could not be saved.",
+                    )
+                })?;
+            response
+                .cookie(preference_cookie(
+                    req,
+                    SETTINGS_COOKIE,
+                    style_cookie(settings, &token),
+                ))
+                .cookie(preference_cookie(req, PROFILE_COOKIE, token));
+        }
+        None => {
+            if settings.custom_css.is_some() {
+                return Err(HttpError::bad_request(
+                    "The custom stylesheet can only be saved in a server-side profile.",
+                ));
+            }
+            if settings.search_history.is_some() {
+                return Err(HttpError::bad_request(
+                    "The search history can only be kept in a server-side profile.",
+                ));
+            }
+            let value = cookie_value(&serialized);
+            if value.len() > config.request_limits.max_cookie_size {
+                return Err(HttpError::bad_request(
+                    "The settings are too large to be saved, please remove some of the domains.",
+                ));
+            }
+            response.cookie(preference_cookie(req, SETTINGS_COOKIE, value));
+            // The settings of the profile are no longer used once they are saved in the cookie.
+            if req.cookie(PROFILE_COOKIE).is_some() {
+                let mut profile_cookie = preference_cookie(req, PROFILE_COOKIE, String::new());
+                profile_cookie.make_removal();
+                response.cookie(profile_cookie);
+            }
+        }
+    }
+
+    Ok(response.finish())
+}
+
+/// A function which returns the settings of the user, which are read from the `preferences`
+/// parameter of the url when it is provided, from the server-side profile of the user when the
+/// profiles are enabled and the `profile` cookie is sent, otherwise from the `appCookie` cookie.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request of the user as an argument.
+/// * `config` - It takes the parsed config struct as an argument.
+///
+/// # Returns
+///
+/// It returns the serialized settings of the user or `None` if the user has not saved any.
+pub async fn user_settings(req: &HttpRequest, config: &Config) -> Option<String> {
+    if let Some(settings) = req
+        .query_string()
+        .split('&')
+        .find_map(|param| param.strip_prefix(PREFERENCES_PARAM)?.strip_prefix('='))
+        .and_then(decode_preferences)
+    {
+        return Some(settings);
+    }
+    if let (true, Some(profiles), Some(token)) = (
+        config.profiles.enabled,
+        req.app_data::<web::Data<Profiles>>(),
+        req.cookie(PROFILE_COOKIE),
+    ) {
+        if let Some(settings) = profiles.load(config, token.value()).await {
+            return Some(settings);
+        }
+    }
+    req.cookie(SETTINGS_COOKIE)
+        .map(|cookie| cookie.value().to_owned())
+}
+
+/// A function which encodes the settings of the user into the value of the `preferences`
+/// parameter, the settings which are not set (like the empty domain lists) are left out to keep
+/// the urls short as they are taken from the defaults of the instance when the settings are read.
+///
+/// # Arguments
+///
+/// * `settings` - It takes the serialized settings of the user as an argument.
+///
+/// # Returns
+///
+/// It returns the settings encoded with the url safe base64 alphabet (without the padding).
+pub fn encode_preferences(settings: &str) -> String {
+    let settings = match serde_json::from_str::<Value>(settings) {
+        Ok(Value::Object(mut fields)) => {
+            fields.retain(|_, field| match field {
+                Value::Null => false,
+                Value::Array(values) => !values.is_empty(),
+                _ => true,
+            });
+            Value::Object(fields).to_string()
+        }
+        _ => settings.to_owned(),
+    };
+    URL_SAFE_NO_PAD.encode(settings)
+}
+
+/// A function which decodes the settings of the user from the value of the `preferences`
+/// parameter.
+///
+/// # Arguments
+///
+/// * `preferences` - It takes the value of the `preferences` parameter as an argument.
+///
+/// # Returns
+///
+/// It returns the serialized settings or `None` if the value is not valid base64 encoded text.
+pub fn decode_preferences(preferences: &str) -> Option<String> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(preferences.trim_end_matches('='))
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// A helper function which builds a cookie storing the preferences of the user.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request of the user as an argument.
+/// * `name` - It takes the name of the cookie as an argument.
+/// * `value` - It takes the value of the cookie as an argument.
+fn preference_cookie(
+    req: &HttpRequest,
+    name: &'static str,
+    value: String,
+) -> cookie::Cookie<'static> {
+    cookie::Cookie::build(name, value)
+        .path("/")
+        .max_age(cookie::time::Duration::days(SETTINGS_COOKIE_DAYS))
+        .same_site(SameSite::Lax)
+        .secure(req.connection_info().scheme() == "https")
+        // The settings cookie is read by the script applying the style of the website.
+        .http_only(name != SETTINGS_COOKIE)
+        .finish()
+}
+
+/// A helper function which encodes the serialized settings into the value of the settings cookie.
+///
+/// # Arguments
+///
+/// * `settings` - It takes the serialized settings as an argument.
+fn cookie_value(settings: &str) -> String {
+    // The spaces are percent encoded as the `+` is not decoded from the cookies.
+    encode_query(settings).replace('+', "%20")
+}
+
+/// A helper function which builds the value of the settings cookie stored along with a profile,
+/// which only keeps the style of the website (applied on all the pages by the browser) while the
+/// other settings are read from the profile. The url of the custom stylesheet of the profile is
+/// also kept in the cookie when the user has provided one, along with the `profile` flag which
+/// shows the buttons starring the search results.
+///
+/// # Arguments
+///
+/// * `settings` - It takes the settings of the user as an argument.
+/// * `token` - It takes the token of the profile of the user as an argument.
+fn style_cookie(settings: &Cookie<'_>, token: &str) -> String {
+    cookie_value(
+        &json!({
+            "version": COOKIE_VERSION,
+            "theme": settings.theme,
+            "colorscheme": settings.colorscheme,
+            "animation": settings.animation,
+            "stylesheet": settings
+                .custom_css
+                .as_ref()
+                .map(|_| format!("profile/{}/style.css", profile_id(token))),
+            "profile": true,
+        })
+        .to_string(),
+    )
+}
+
+/// A helper function which returns the settings used when the user has not saved any.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+fn default_settings(config: &Config) -> Cookie<'_> {
+    Cookie::build(
+        &config.style,
+        config
+            .default_engines(None)
+            .into_iter()
+            .map(Cow::Borrowed)
+            .collect(),
+        config.safe_search,
+    )
+}
+
+/// A helper function which converts the settings of the user back into the fields of the settings
+/// form, so that the imported settings are checked like the submitted ones.
+///
+/// # Arguments
+///
+/// * `settings` - It takes the settings of the user as an argument.
+fn settings_form(settings: &Cookie<'_>) -> Vec<(String, String)> {
+    let optional = |value: &Option<Cow<'_, str>>| value.as_deref().unwrap_or_default().to_owned();
+    let mut fields = vec![
+        ("themes".to_owned(), settings.theme.to_string()),
+        ("colorschemes".to_owned(), settings.colorscheme.to_string()),
+        ("animations".to_owned(), optional(&settings.animation)),
+        (
+            "safe_search_levels".to_owned(),
+            settings.safe_search_level.to_string(),
+        ),
+        ("languages".to_owned(), optional(&settings.language)),
+        ("regions".to_owned(), optional(&settings.region)),
+        ("time_ranges".to_owned(), optional(&settings.time_range)),
+        (
+            "highlights".to_owned(),
+            match settings.highlight {
+                Some(false) => "off",
+                _ => "on",
+            }
+            .to_owned(),
+        ),
+        (
+            "results_per_page".to_owned(),
+            settings
+                .results_per_page
+                .map(|results_per_page| results_per_page.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "custom_css".to_owned(),
+            settings.custom_css.clone().unwrap_or_default(),
+        ),
+        (
+            "search_history".to_owned(),
+            match settings.search_history {
+                Some(true) => "on",
+                _ => "off",
+            }
+            .to_owned(),
+        ),
+        (
+            "blocked_domains".to_owned(),
+            settings.blocked_domains.join("\n"),
+        ),
+        (
+            "allowed_domains".to_owned(),
+            settings.allowed_domains.join("\n"),
+        ),
+    ];
+    fields.extend(
+        settings
+            .engines
+            .iter()
+            .map(|engine| ("engines".to_owned(), engine.to_string())),
+    );
+    fields.extend(
+        settings
+            .category_engines
+            .iter()
+            .flat_map(|(category, engines)| {
+                engines
+                    .iter()
+                    .map(move |engine| (format!("{category}_engines"), engine.clone()))
+            }),
+    );
+    fields
+}
+
+/// A helper function which validates the preferences submitted with the settings form and builds
+/// the settings cookie from them. The preferences which are not submitted are set to the defaults
+/// of the instance, and the safe search level managed by the administrator of the instance (a
+/// level above `2`) cannot be changed.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `form` - It takes the fields submitted with the settings form as an argument.
+///
+/// # Error
+///
+/// Returns a `400 Bad Request` error explaining which preference is invalid.
+fn settings_cookie<'a>(
+    config: &'a Config,
+    form: &'a [(String, String)],
+) -> Result<Cookie<'a>, HttpError> {
+    let field = |name: &str| {
+        form.iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.trim())
+    };
+    // The empty options (like the `Auto` language) are sent as an empty string.
+    let optional_field = |name: &str| field(name).filter(|value| !value.is_empty());
+    let invalid = |preference: &str, value: &str| {
+        HttpError::bad_request(format!("The {preference} `{value}` is not available."))
+    };
+
+    let style = |folder: &str, preference: &str, value: &'a str| match config
+        .styles
+        .contains(folder, value)
+    {
+        true => Ok(Cow::Borrowed(value)),
+        false => Err(invalid(preference, value)),
+    };
+    let theme = match field("themes") {
+        Some(theme) => style("themes", "theme", theme)?,
+        None => Cow::Borrowed(config.style.theme.as_str()),
+    };
+    let colorscheme = match field("colorschemes") {
+        Some(colorscheme) => style("colorschemes", "colorscheme", colorscheme)?,
+        None => Cow::Borrowed(config.style.colorscheme.as_str()),
+    };
+    let animation = match field("animations") {
+        Some("") => None,
+        Some(animation) => Some(style("animations", "animation", animation)?),
+        None => config.style.animation.as_deref().map(Cow::Borrowed),
+    };
+
+    let safe_search_level = match (config.safe_search, field("safe_search_levels")) {
+        (level, _) if level > SafeSearchLevel::Moderate.level() => level,
+        (_, Some(level)) => level
+            .parse::<u8>()
+            .ok()
+            .filter(|level| SafeSearchLevel::ALL.iter().any(|all| all.level() == *level))
+            .ok_or_else(|| invalid("safe search level", level))?,
+        (level, None) => level,
+    };
+
+    let language = optional_field("languages")
+        .map(|language| supported_language(language).ok_or_else(|| invalid("language", language)))
+        .transpose()?;
+    let region = optional_field("regions")
+        .map(|region| supported_region(region).ok_or_else(|| invalid("region", region)))
+        .transpose()?;
+    let time_range = optional_field("time_ranges")
+        .map(|time_range| {
+            TimeRange::from_name(time_range)
+                .map(|time_range| time_range.name())
+                .ok_or_else(|| invalid("time range", time_range))
+        })
+        .transpose()?;
+    let highlight = match field("highlights") {
+        Some("on") | None => None,
+        Some("off") => Some(false),
+        Some(highlight) => return Err(invalid("highlighting option", highlight)),
+    };
+    let results_per_page = optional_field("results_per_page")
+        .map(|results_per_page| {
+            results_per_page
+                .parse::<u16>()
+                .ok()
+                .filter(|value| {
+                    *value > 0 && usize::from(*value) <= config.request_limits.max_results_per_page
+                })
+                .ok_or_else(|| invalid("number of results per page", results_per_page))
+        })
+        .transpose()?;
+    let custom_css = optional_field("custom_css")
+        .map(|custom_css| match custom_css.len() > MAX_CUSTOM_CSS_SIZE {
+            true => Err(HttpError::bad_request(format!(
+                "The custom stylesheet should not be larger than {MAX_CUSTOM_CSS_SIZE} bytes."
+            ))),
+            false => Ok(custom_css.to_owned()),
+        })
+        .transpose()?;
+    let search_history = match field("search_history") {
+        Some("off") | None => None,
+        Some("on") if config.profiles.history => Some(true),
+        Some("on") => {
+            return Err(HttpError::bad_request(
+                "The search history is not enabled on this instance.",
+            ))
+        }
+        Some(search_history) => return Err(invalid("search history option", search_history)),
+    };
+
+    let domains = |name: &str| {
+        field(name)
+            .unwrap_or_default()
+            .lines()
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| match domain_pattern(pattern) {
+                Ok(_) => Ok(pattern.to_owned()),
+                Err(_) => Err(HttpError::bad_request(format!(
+                    "The domain pattern `{pattern}` is invalid."
+                ))),
+            })
+            .collect::<Result<Vec<String>, HttpError>>()
+    };
+
+    let engines = |name: &str| {
+        let mut engines: Vec<&'a str> = form
+            .iter()
+            .filter(|(field, _)| field == name)
+            .map(|(_, engine)| {
+                config
+                    .upstream_search_engines
+                    .keys()
+                    .find(|known| known.eq_ignore_ascii_case(engine.trim()))
+                    .map(String::as_str)
+                    .ok_or_else(|| invalid("search engine", engine))
+            })
+            .collect::<Result<_, _>>()?;
+        engines.sort();
+        engines.dedup();
+        Ok::<_, HttpError>(engines)
+    };
+    // The categories for which the user selected no engine use the engines of the general
+    // category.
+    let mut category_engines = BTreeMap::new();
+    for category in ENGINE_CATEGORIES {
+        let selected = engines(&format!("{category}_engines"))?;
+        if !selected.is_empty() {
+            category_engines.insert(
+                category.to_owned(),
+                selected.into_iter().map(str::to_owned).collect(),
+            );
+        }
+    }
+
+    let mut settings = Cookie {
+        version: COOKIE_VERSION,
+        theme,
+        colorscheme,
+        animation,
+        engines: Cow::Owned(engines("engines")?.into_iter().map(Cow::Borrowed).collect()),
+        category_engines,
+        safe_search_level,
+        language: language.map(Cow::Borrowed),
+        region: region.map(Cow::Borrowed),
+        time_range: time_range.map(Cow::Borrowed),
+        blocked_domains: domains("blocked_domains")?,
+        allowed_domains: domains("allowed_domains")?,
+        highlight,
+        results_per_page,
+        custom_css,
+        search_history,
+    };
+    // The settings locked by the administrator of the instance are saved with the values of the
+    // config.
+    lock_settings(config, &mut settings, None);
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+        fields
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_settings_cookie() {
+        let config = Config::default();
+        let fields = form(&[
+            ("safe_search_levels", "2"),
+            ("languages", "de"),
+            ("regions", ""),
+            ("time_ranges", "week"),
+            ("highlights", "off"),
+            ("results_per_page", "50"),
+            ("blocked_domains", "pinterest.com\n\n *.example.com \n"),
+            ("engines", "duckduckgo"),
+            ("engines", "Brave"),
+            ("engines", "DuckDuckGo"),
+            ("images_engines", "bing"),
+        ]);
+        let settings = settings_cookie(&config, &fields).unwrap();
+
+        assert_eq!(settings.version, COOKIE_VERSION);
+        assert_eq!(settings.theme, config.style.theme);
+        assert_eq!(settings.safe_search_level, 2);
+        assert_eq!(settings.language.as_deref(), Some("de"));
+        assert_eq!(settings.region, None);
+        assert_eq!(settings.time_range.as_deref(), Some("week"));
+        assert_eq!(settings.highlight, Some(false));
+        assert_eq!(settings.results_per_page, Some(50));
+        assert_eq!(settings.blocked_domains, ["pinterest.com", "*.example.com"]);
+        assert_eq!(*settings.engines, ["Brave", "DuckDuckGo"]);
+        // The categories without a selection are not kept in the cookie.
+        assert_eq!(
+            settings.category_engines,
+            BTreeMap::from([("images".to_owned(), vec!["Bing".to_owned()])])
+        );
+    }
+
+    #[test]
+    fn test_invalid_settings_are_rejected() {
+        let config = Config::default();
+        for fields in [
+            [("themes", "../../etc/passwd")],
+            [("safe_search_levels", "4")],
+            [("languages", "klingon")],
+            [("time_ranges", "decade")],
+            [("results_per_page", "0")],
+            [("results_per_page", "1000")],
+            [("engines", "Unknown")],
+            [("news_engines", "Unknown")],
+            [("allowed_domains", "/[/")],
+        ] {
+            let error = settings_cookie(&config, &form(&fields)).err().unwrap();
+            assert_eq!(error.status, StatusCode::BAD_REQUEST, "{fields:?}");
+        }
+
+        // The safe search level managed by the administrator cannot be changed.
+        let config = Config {
+            safe_search: 4,
+            ..Default::default()
+        };
+        let settings = settings_cookie(&config, &form(&[("safe_search_levels", "0")])).unwrap();
+        assert_eq!(settings.safe_search_level, 4);
+
+        // The locked settings are saved with the values of the config.
+        use crate::models::parser_models::LockedSetting;
+        let config = Config {
+            locked_settings: vec![LockedSetting::SafeSearch, LockedSetting::Language],
+            ..Default::default()
+        };
+        let settings = settings_cookie(
+            &config,
+            &form(&[("safe_search_levels", "0"), ("languages", "de")]),
+        )
+        .unwrap();
+        assert_eq!(settings.safe_search_level, config.safe_search);
+        assert_eq!(settings.language, None);
+    }
+
+    #[actix_web::test]
+    async fn test_settings_profile() {
+        use crate::server::csrf::{csrf_token, CSRF_COOKIE};
+        use actix_web::test::{call_service, init_service, TestRequest};
+
+        let mut config = Config::default();
+        config.profiles.enabled = true;
+        let profiles = web::Data::new(Profiles::new(&config).unwrap());
+        let app = init_service(
+            actix_web::App::new()
+                .app_data(web::Data::new(SharedConfig::new(config.clone())))
+                .app_data(profiles.clone())
+                .service(save_settings),
+        )
+        .await;
+        let token = csrf_token(&TestRequest::default().to_http_request());
+        let request = |form: &[(&str, &str)]| {
+            TestRequest::post()
+                .uri("/settings")
+                .cookie(cookie::Cookie::new(CSRF_COOKIE, token.clone()))
+                .set_form([form, &[(CSRF_FIELD, token.as_str())]].concat())
+                .to_request()
+        };
+        let cookie_value = |response: &actix_web::dev::ServiceResponse, name: &str| {
+            response
+                .response()
+                .cookies()
+                .find(|cookie| cookie.name() == name)
+                .map(|cookie| cookie.value().to_owned())
+        };
+
+        let response = call_service(&app, request(&[("profile", "on"), ("engines", "Bing")])).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let restore_code = cookie_value(&response, PROFILE_COOKIE).unwrap();
+        // Only the style of the website is kept in the settings cookie.
+        assert!(!cookie_value(&response, SETTINGS_COOKIE)
+            .unwrap()
+            .contains("engines"));
+
+        let settings = user_settings(
+            &TestRequest::default()
+                .app_data(profiles.clone())
+                .cookie(cookie::Cookie::new(PROFILE_COOKIE, restore_code.clone()))
+                .to_http_request(),
+            &config,
+        )
+        .await
+        .unwrap();
+        assert!(settings.contains(r#""engines":["Bing"]"#));
+
+        // The profile is restored on another browser with its restore code.
+        let response = call_service(
+            &app,
+            request(&[("restore_code", &restore_code.to_uppercase())]),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        assert_eq!(cookie_value(&response, PROFILE_COOKIE), Some(restore_code));
+
+        let response = call_service(&app, request(&[("restore_code", "unknown")])).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_preferences_param() {
+        let config = Config::default();
+        let fields = form(&[("languages", "de"), ("engines", "Brave")]);
+        let settings = settings_cookie(&config, &fields).unwrap();
+        let preferences = encode_preferences(&serde_json::to_string(&settings).unwrap());
+        assert!(!preferences.contains(['+', '/', '=']));
+        let decoded = decode_preferences(&preferences).unwrap();
+        assert!(!decoded.contains("blocked_domains"));
+
+        let req = actix_web::test::TestRequest::get()
+            .uri(&format!("/search?q=rust&{PREFERENCES_PARAM}={preferences}"))
+            .cookie(cookie::Cookie::new(SETTINGS_COOKIE, "{}"))
+            .to_http_request();
+        let settings = Cookie::parse(
+            &user_settings(&req, &config).await.unwrap(),
+            Cookie::build(&config.style, Vec::new(), config.safe_search),
+        );
+        assert_eq!(settings.language.as_deref(), Some("de"));
+        assert_eq!(*settings.engines, ["Brave"]);
+        assert!(decode_preferences("not base64!").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_custom_stylesheet() {
+        use crate::server::csrf::{csrf_token, CSRF_COOKIE};
+        use actix_web::test::{call_service, init_service, read_body, TestRequest};
+
+        let mut config = Config::default();
+        config.profiles.enabled = true;
+        let app = init_service(
+            actix_web::App::new()
+                .app_data(web::Data::new(SharedConfig::new(config.clone())))
+                .app_data(web::Data::new(Profiles::new(&config).unwrap()))
+                .service(save_settings)
+                .service(custom_stylesheet),
+        )
+        .await;
+        let token = csrf_token(&TestRequest::default().to_http_request());
+        let request = |form: &[(&str, &str)]| {
+            TestRequest::post()
+                .uri("/settings")
+                .cookie(cookie::Cookie::new(CSRF_COOKIE, token.clone()))
+                .set_form([form, &[(CSRF_FIELD, token.as_str())]].concat())
+                .to_request()
+        };
+
+        // The custom stylesheet cannot be saved in the cookie.
+        let css = "body { font-size: 2rem; }";
+        let response = call_service(&app, request(&[("custom_css", css)])).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let large_css = "a".repeat(MAX_CUSTOM_CSS_SIZE + 1);
+        let response = call_service(
+            &app,
+            request(&[("profile", "on"), ("custom_css", &large_css)]),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = call_service(&app, request(&[("profile", "on"), ("custom_css", css)])).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let cookie = |name: &str| {
+            response
+                .response()
+                .cookies()
+                .find(|cookie| cookie.name() == name)
+                .map(|cookie| cookie.value().to_owned())
+                .unwrap()
+        };
+        let stylesheet = format!("profile/{}/style.css", profile_id(&cookie(PROFILE_COOKIE)));
+        assert!(cookie(SETTINGS_COOKIE).contains(&encode_query(&stylesheet)));
+
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/{stylesheet}"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(read_body(response).await, css);
+
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/{stylesheet}"))
+                .insert_header((header::IF_NONE_MATCH, etag))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/profile/{}/style.css", profile_id("unknown")))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_settings_import_export() {
+        use crate::server::csrf::{csrf_token, CSRF_COOKIE};
+        use actix_web::test::{call_service, init_service, read_body, TestRequest};
+
+        let app = init_service(
+            actix_web::App::new()
+                .app_data(web::Data::new(SharedConfig::new(Config::default())))
+                .service(export_settings)
+                .service(import_settings),
+        )
+        .await;
+
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/settings/export")
+                .cookie(cookie::Cookie::new(
+                    SETTINGS_COOKIE,
+                    r#"{"engines":["Brave"],"language":"de"}"#,
+                ))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response
+            .headers()
+            .get(header::CONTENT_DISPOSITION)
+            .is_some());
+        let exported = String::from_utf8(read_body(response).await.to_vec()).unwrap();
+        let settings: Value = serde_json::from_str(&exported).unwrap();
+        assert_eq!(settings["version"], COOKIE_VERSION);
+        assert_eq!(settings["engines"], json!(["Brave"]));
+
+        let token = csrf_token(&TestRequest::default().to_http_request());
+        let import = |settings: &str| {
+            TestRequest::post()
+                .uri("/settings/import")
+                .cookie(cookie::Cookie::new(CSRF_COOKIE, token.clone()))
+                .set_form([("settings", settings), (CSRF_FIELD, token.as_str())])
+                .to_request()
+        };
+        let response = call_service(&app, import(&exported)).await;
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let imported = response
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == SETTINGS_COOKIE)
+            .unwrap()
+            .value()
+            .to_owned();
+        assert!(imported.contains("Brave"));
+
+        let response = call_service(&app, import(r#"{"engines":["Unknown"]}"#)).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = call_service(&app, import("not json")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
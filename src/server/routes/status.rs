@@ -0,0 +1,207 @@
+//! This module handles the engine status route, which shows the users of the instance which
+//! upstream search engines are currently degraded.
+
+use crate::{
+    config::reloader::SharedConfig,
+    engines::block_backoff::blocked_for,
+    results::{
+        engine_stats::{EngineStats, EngineStatus, ENGINE_STATS},
+        health_monitor::{engine_status, HealthCheck, HEALTH_CHECKS},
+    },
+    server::{
+        error_pages::wants_json,
+        usage_stats::{DailyUsage, UsageStats},
+    },
+};
+use actix_web::{
+    get,
+    http::header::{self, ContentType},
+    web, HttpRequest, HttpResponse,
+};
+use serde::Serialize;
+
+/// A named struct which serializes the health of an upstream search engine.
+#[derive(Serialize)]
+pub struct EngineHealth<'a> {
+    /// It stores the name of the engine.
+    pub engine: &'a str,
+    /// It stores the health of the engine.
+    pub status: EngineStatus,
+    /// It stores the statistics of the engine or `None` if the engine did not answer since the
+    /// start of the server.
+    pub stats: Option<EngineStats>,
+    /// It stores the last health check of the engine or `None` if the engine was not checked by
+    /// the health monitor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<HealthCheck>,
+    /// It stores the time (in seconds) left until the engine is sent requests again when the
+    /// engine is cooling down after it blocked the instance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked_for: Option<u64>,
+}
+
+/// A named struct which serializes the engine status page.
+#[derive(Serialize)]
+pub struct InstanceStatus<'a> {
+    /// It stores the health of the upstream search engines.
+    pub engines: Vec<EngineHealth<'a>>,
+    /// It stores the anonymized usage statistics of the instance per day, which are only shown
+    /// when the `usage_stats.public` option is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Vec<DailyUsage>>,
+}
+
+/// Handles the route of the engine status page, which lists the health (up, degraded, down,
+/// blocked or unknown), the recent latency, the recent errors and the last health check of all the
+/// upstream search engines of the instance, along with the anonymized usage statistics of the
+/// instance when the `usage_stats.public` option is set. The statistics are returned as json with
+/// the `format=json` parameter.
+///
+/// # Example
+///
+/// ```bash
+/// curl "http://127.0.0.1:8080/status?format=json"
+/// ```
+#[get("/status")]
+pub async fn status(req: HttpRequest, config: web::Data<SharedConfig>) -> HttpResponse {
+    let config = config.load();
+    let mut engines: Vec<EngineHealth<'_>> = config
+        .upstream_search_engines
+        .keys()
+        .map(|engine| {
+            let name = engine.to_lowercase();
+            let stats = ENGINE_STATS.engine(&name);
+            let health_check = HEALTH_CHECKS.engine(&name);
+            let blocked_for = blocked_for(&name);
+            EngineHealth {
+                engine: engine.as_str(),
+                status: match blocked_for {
+                    Some(_) => EngineStatus::Blocked,
+                    None => engine_status(stats.as_ref(), health_check.as_ref()),
+                },
+                stats,
+                health_check,
+                blocked_for: blocked_for.map(|remaining| remaining.as_secs()),
+            }
+        })
+        .collect();
+    engines.sort_unstable_by_key(|engine| engine.engine.to_lowercase());
+
+    let usage = match req.app_data::<web::Data<UsageStats>>() {
+        Some(usage_stats) if config.usage_stats.enabled && config.usage_stats.public => {
+            usage_stats.daily_usage(&config).await
+        }
+        _ => None,
+    };
+
+    let mut response = HttpResponse::Ok();
+    // The statistics change with every search, so they are never cached.
+    response.insert_header((header::CACHE_CONTROL, "no-store"));
+
+    if wants_json(&req) {
+        return response.json(InstanceStatus { engines, usage });
+    }
+
+    response.content_type(ContentType::html()).body(
+        crate::templates::views::status::status(
+            &config.style.colorscheme,
+            &config.style.theme,
+            &config.style.animation,
+            &engines,
+            usage.as_deref(),
+        )
+        .0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::parser::Config, models::engine_models::EngineError};
+    use actix_web::{
+        test::{call_and_read_body_json, init_service, TestRequest},
+        App,
+    };
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_status_json() {
+        let mut config = Config::default();
+        config
+            .upstream_search_engines
+            .insert("StatusTest".to_owned(), Default::default());
+        config
+            .upstream_search_engines
+            .insert("StatusTestDown".to_owned(), Default::default());
+        config
+            .upstream_search_engines
+            .insert("StatusTestBlocked".to_owned(), Default::default());
+        ENGINE_STATS.record("statustest", Duration::from_millis(100), None);
+        ENGINE_STATS.record(
+            "statustestdown",
+            Duration::from_secs(1),
+            Some(&EngineError::Blocked),
+        );
+        crate::engines::block_backoff::record_response(
+            "statustestblocked",
+            Some(&EngineError::Blocked),
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+        );
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .service(status),
+        )
+        .await;
+        let body: serde_json::Value = call_and_read_body_json(
+            &app,
+            TestRequest::get().uri("/status?format=json").to_request(),
+        )
+        .await;
+        // The usage statistics are only shown with the `usage_stats.public` option.
+        assert!(body.get("usage").is_none());
+
+        let engine_status = |name: &str| {
+            body["engines"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|engine| engine["engine"] == name)
+                .map(|engine| engine["status"].clone())
+                .unwrap()
+        };
+        assert_eq!(engine_status("StatusTest"), "up");
+        assert_eq!(engine_status("StatusTestDown"), "down");
+        assert_eq!(engine_status("StatusTestBlocked"), "blocked");
+        assert_eq!(engine_status("DuckDuckGo"), "unknown");
+        assert!(body["engines"][0].get("health_check").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_status_public_usage() {
+        let mut config = Config::default();
+        config.usage_stats.enabled = true;
+        config.usage_stats.public = true;
+        let usage_stats = UsageStats::new(&config).unwrap();
+        usage_stats
+            .record_search(&config, [192, 0, 2, 1].into(), "general", &[])
+            .await;
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(config)))
+                .app_data(web::Data::new(usage_stats))
+                .service(status),
+        )
+        .await;
+        let body: serde_json::Value = call_and_read_body_json(
+            &app,
+            TestRequest::get().uri("/status?format=json").to_request(),
+        )
+        .await;
+        assert_eq!(body["usage"][0]["searches"], 1);
+        assert_eq!(body["usage"][0]["visitors"], 1);
+    }
+}
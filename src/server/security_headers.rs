@@ -0,0 +1,180 @@
+//! This module provides the functionality to send the security headers (the content security
+//! policy, the referrer policy, the permissions policy and optionally the strict transport
+//! security) configured with the `security_headers` option along with all the responses.
+
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse},
+    http::header::{
+        HeaderMap, HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, PERMISSIONS_POLICY,
+        REFERRER_POLICY, STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS,
+    },
+    web, Error, HttpMessage,
+};
+use std::future::Future;
+
+use crate::{config::reloader::SharedConfig, models::parser_models::SecurityHeaders};
+
+/// The placeholder in the content security policy which is replaced with the nonce of the
+/// response.
+pub const NONCE_PLACEHOLDER: &str = "{nonce}";
+
+/// The nonce generated for each response, which is stored in the extensions of the request so
+/// that the inline scripts and styles of the page can carry it (`script nonce=(nonce.0)`) and be
+/// allowed by the content security policy.
+#[derive(Clone)]
+pub struct CspNonce(pub String);
+
+impl CspNonce {
+    /// A function which generates a new random nonce.
+    pub fn generate() -> Self {
+        CspNonce(
+            rand::random::<[u8; 16]>()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        )
+    }
+}
+
+/// A function which builds the security headers sent with a response from the config options.
+///
+/// # Arguments
+///
+/// * `config` - It takes the config options of the security headers as an argument.
+/// * `nonce` - It takes the nonce of the response as an argument.
+///
+/// # Returns
+///
+/// It returns the names and the values of the headers, the headers whose value is empty (or
+/// invalid) in the config are left out.
+pub fn security_headers(config: &SecurityHeaders, nonce: &CspNonce) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+
+    let mut insert = |name: HeaderName, value: &str| {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            if !value.is_empty() {
+                headers.insert(name, value);
+            }
+        }
+    };
+    insert(
+        CONTENT_SECURITY_POLICY,
+        &config
+            .content_security_policy
+            .replace(NONCE_PLACEHOLDER, &nonce.0),
+    );
+    insert(REFERRER_POLICY, &config.referrer_policy);
+    insert(PERMISSIONS_POLICY, &config.permissions_policy);
+    if let Some(max_age) = config.hsts_max_age {
+        insert(STRICT_TRANSPORT_SECURITY, &format!("max-age={max_age}"));
+    }
+
+    headers
+}
+
+/// A middleware (used with `wrap_fn`) which generates the nonce of the response and adds the
+/// security headers to all the responses, the headers already set by the route (like the
+/// `Referrer-Policy` of the search page) are kept.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be handled as an argument.
+/// * `service` - It takes the wrapped service as an argument.
+pub fn add_security_headers<S, B>(
+    req: ServiceRequest,
+    service: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let nonce = CspNonce::generate();
+    let headers = req
+        .app_data::<web::Data<SharedConfig>>()
+        .map(|config| security_headers(&config.load().security_headers, &nonce));
+    req.extensions_mut().insert(nonce);
+
+    let response = service.call(req);
+    async move {
+        let mut response = response.await?;
+        for (name, value) in headers.into_iter().flatten() {
+            if !response.headers().contains_key(&name) {
+                response.headers_mut().insert(name, value);
+            }
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parser::Config;
+    use actix_web::{
+        test::{call_service, init_service, TestRequest},
+        App, HttpRequest, HttpResponse,
+    };
+
+    #[test]
+    fn test_security_headers() {
+        let nonce = CspNonce("abc".to_owned());
+        let headers = security_headers(&SecurityHeaders::default(), &nonce);
+        assert_eq!(headers.get(X_CONTENT_TYPE_OPTIONS).unwrap(), "nosniff");
+        assert!(headers
+            .get(CONTENT_SECURITY_POLICY)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("script-src 'self' 'nonce-abc'"));
+        assert_eq!(headers.get(REFERRER_POLICY).unwrap(), "no-referrer");
+        assert!(headers.contains_key(PERMISSIONS_POLICY));
+        assert!(!headers.contains_key(STRICT_TRANSPORT_SECURITY));
+
+        let config = SecurityHeaders {
+            content_security_policy: String::new(),
+            hsts_max_age: Some(31536000),
+            ..Default::default()
+        };
+        let headers = security_headers(&config, &nonce);
+        assert!(!headers.contains_key(CONTENT_SECURITY_POLICY));
+        assert_eq!(
+            headers.get(STRICT_TRANSPORT_SECURITY).unwrap(),
+            "max-age=31536000"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_add_security_headers() {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(SharedConfig::new(Config::default())))
+                .wrap_fn(add_security_headers)
+                .route(
+                    "/",
+                    web::get().to(|req: HttpRequest| async move {
+                        // The nonce of the response is available to the routes.
+                        let nonce = req.extensions().get::<CspNonce>().unwrap().0.clone();
+                        HttpResponse::Ok()
+                            .insert_header((REFERRER_POLICY, "same-origin"))
+                            .body(nonce)
+                    }),
+                ),
+        )
+        .await;
+
+        let response = call_service(&app, TestRequest::get().uri("/").to_request()).await;
+        let csp = response
+            .headers()
+            .get(CONTENT_SECURITY_POLICY)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned();
+        assert_eq!(
+            response.headers().get(REFERRER_POLICY).unwrap(),
+            "same-origin"
+        );
+        let nonce = actix_web::test::read_body(response).await;
+        assert!(csp.contains(&format!("'nonce-{}'", String::from_utf8_lossy(&nonce))));
+    }
+}
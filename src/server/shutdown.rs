@@ -0,0 +1,42 @@
+//! This module provides the functionality to shut the server down gracefully when a `SIGTERM` or
+//! a `SIGINT` signal is received, so that the restarts of the instance (like the container
+//! restarts) do not drop the requests of the users.
+
+use actix_web::dev::ServerHandle;
+
+/// The default time (in seconds) given to the in-flight requests to complete when the server
+/// shuts down.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: u8 = 30;
+
+/// A function which waits for a `SIGTERM` or a `SIGINT` signal (or for a Ctrl-C on the systems
+/// other than unix) and stops the server gracefully, so that it stops accepting the connections
+/// and waits for the in-flight requests to complete.
+///
+/// # Arguments
+///
+/// * `server` - It takes the handle of the running server as an argument.
+pub async fn stop_on_signal(server: ServerHandle) {
+    shutdown_requested().await;
+    tracing::info!("Shutting down gracefully, waiting for the in-flight requests to complete");
+    server.stop(true).await;
+}
+
+/// A helper function which waits until the shutdown of the server is requested with a signal.
+async fn shutdown_requested() {
+    #[cfg(unix)]
+    match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            tokio::select! {
+                _ = sigterm.recv() => {},
+                _ = tokio::signal::ctrl_c() => {},
+            }
+            return;
+        }
+        Err(error) => tracing::error!("Unable to listen for the SIGTERM signal: {error}"),
+    }
+
+    if let Err(error) = tokio::signal::ctrl_c().await {
+        tracing::error!("Unable to listen for the SIGINT signal: {error}");
+        std::future::pending::<()>().await;
+    }
+}
@@ -0,0 +1,134 @@
+//! This module provides the functionality to sign the urls which are passed to the proxy and the
+//! redirect routes, so that these routes only serve the urls generated by the instance itself and
+//! cannot be abused as an open proxy or an open redirect, along with the constant time comparison
+//! of the secret tokens provided in the requests.
+
+use crate::config::parser::Config;
+use std::sync::OnceLock;
+
+/// The context used to derive the signing key from the `secret_key` option.
+const SIGNING_KEY_CONTEXT: &str = "websurfx 2024-01-01 url signing key";
+
+/// A static variable which stores the signing key generated on startup, which is used when no
+/// `secret_key` option is provided.
+static GENERATED_SIGNING_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// A helper function which returns the key with which the urls are signed. The key is derived from
+/// the `secret_key` option or generated randomly on startup if it is not provided, in which case
+/// the signed urls stop working once the server is restarted.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+fn signing_key(config: &Config) -> [u8; 32] {
+    match &config.secret_key {
+        Some(secret_key) => blake3::derive_key(SIGNING_KEY_CONTEXT, secret_key.as_bytes()),
+        None => *GENERATED_SIGNING_KEY.get_or_init(rand::random),
+    }
+}
+
+/// A function which signs the provided url for the provided purpose (like `image`), so that a
+/// signature generated for one route cannot be used with another route.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `purpose` - It takes the name of the route for which the url is signed as an argument.
+/// * `url` - It takes the url to be signed as an argument.
+///
+/// # Returns
+///
+/// It returns the signature of the url as a hex string.
+pub fn sign_url(config: &Config, purpose: &str, url: &str) -> String {
+    blake3::keyed_hash(&signing_key(config), format!("{purpose}:{url}").as_bytes())
+        .to_hex()
+        .to_string()
+}
+
+/// A function which checks whether the provided signature is a valid signature of the provided
+/// url for the provided purpose. The signatures are compared in constant time.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct as an argument.
+/// * `purpose` - It takes the name of the route for which the url was signed as an argument.
+/// * `url` - It takes the signed url as an argument.
+/// * `signature` - It takes the signature provided along with the url as an argument.
+pub fn verify_url(config: &Config, purpose: &str, url: &str, signature: &str) -> bool {
+    blake3::Hash::from_hex(signature).is_ok_and(|signature| {
+        signature == blake3::keyed_hash(&signing_key(config), format!("{purpose}:{url}").as_bytes())
+    })
+}
+
+/// A function which compares the provided tokens in constant time (with respect to the contents
+/// of the tokens) to avoid leaking the secret tokens (like the admin token, the api keys or the
+/// CSRF tokens) through timing differences.
+///
+/// # Arguments
+///
+/// * `provided_token` - It takes the token provided in the request as an argument.
+/// * `token` - It takes the secret token to which it is compared as an argument.
+pub fn tokens_match(provided_token: &str, token: &str) -> bool {
+    provided_token.len() == token.len()
+        && provided_token
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_urls() {
+        let config = Config {
+            secret_key: Some("secret".to_owned()),
+            ..Default::default()
+        };
+        let url = "https://example.com/image.png";
+        let signature = sign_url(&config, "image", url);
+
+        assert!(verify_url(&config, "image", url, &signature));
+        assert!(!verify_url(&config, "redirect", url, &signature));
+        assert!(!verify_url(
+            &config,
+            "image",
+            "https://example.com/other.png",
+            &signature
+        ));
+        assert!(!verify_url(&config, "image", url, "not a signature"));
+
+        let other_config = Config {
+            secret_key: Some("other secret".to_owned()),
+            ..Default::default()
+        };
+        assert!(!verify_url(&other_config, "image", url, &signature));
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secreT", "secret"));
+        assert!(!tokens_match("secret", "secrets"));
+        assert!(!tokens_match("", "secret"));
+    }
+
+    #[test]
+    fn test_generated_signing_key() {
+        let config = Config::default();
+        let signature = sign_url(&config, "image", "https://example.com/");
+
+        assert_eq!(
+            signature,
+            sign_url(&config, "image", "https://example.com/")
+        );
+        assert!(verify_url(
+            &config,
+            "image",
+            "https://example.com/",
+            &signature
+        ));
+    }
+}
@@ -0,0 +1,271 @@
+//! This module provides the functionality to serve the static files (the css and js files and the
+//! images) of the theme folder, along with the headers allowing the browsers to cache them and
+//! to revalidate them cheaply once they are stale. With the `embed-assets` feature, the files
+//! missing from the theme folder are served from the ones embedded into the binary.
+
+use actix_files::Files;
+use actix_web::{
+    dev::{HttpServiceFactory, Service, ServiceRequest, ServiceResponse},
+    http::{
+        header::{EntityTag, HeaderValue, IfNoneMatch, CACHE_CONTROL},
+        StatusCode,
+    },
+    web, Error, HttpMessage, HttpRequest,
+};
+use std::future::Future;
+
+#[cfg(feature = "embed-assets")]
+use crate::handler::assets::embedded_asset;
+#[cfg(feature = "embed-assets")]
+use actix_web::{
+    http::{
+        header::{ETag, CONTENT_TYPE},
+        Method,
+    },
+    HttpResponse,
+};
+
+/// The value of the `Cache-Control` header sent with the static files. The files are only cached
+/// for a day as their names do not change between the releases, after which they are revalidated
+/// with their `ETag` and `Last-Modified` headers.
+pub const STATIC_CACHE_CONTROL: HeaderValue = HeaderValue::from_static("public, max-age=86400");
+
+/// A function which builds the service serving the files of the provided folder of the theme
+/// folder (with their `ETag`, `Last-Modified` and `Content-Type` headers) under the provided
+/// path.
+///
+/// # Arguments
+///
+/// * `mount_path` - It takes the path under which the files are served (like `/static`) as an
+///   argument.
+/// * `folder` - It takes the path to the folder containing the files as an argument, or `None`
+///   if the theme folder could not be found and only the embedded files should be served.
+pub fn static_files(mount_path: &str, folder: Option<String>) -> impl HttpServiceFactory {
+    let scope = web::scope(mount_path).wrap_fn(add_cache_control);
+
+    let scope = match folder {
+        Some(folder) => {
+            let files = Files::new("", folder)
+                .use_etag(true)
+                .use_last_modified(true)
+                .show_files_listing();
+            #[cfg(feature = "embed-assets")]
+            let files = files.default_handler(web::to(embedded_file));
+            scope.service(files)
+        }
+        None => scope,
+    };
+
+    #[cfg(feature = "embed-assets")]
+    let scope = scope.default_service(web::to(embedded_file));
+    scope
+}
+
+/// Handles the requests for the files missing from the theme folder by serving the files
+/// embedded into the binary at the same path.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+#[cfg(feature = "embed-assets")]
+async fn embedded_file(req: HttpRequest) -> HttpResponse {
+    embedded_file_response(&req, req.path().trim_start_matches('/'))
+        .unwrap_or_else(|| HttpResponse::NotFound().finish())
+}
+
+/// A function which builds the response serving the embedded file at the provided path of the
+/// theme folder, along with its `Content-Type` and `ETag` headers.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `path` - It takes the path of the file relative to the theme folder as an argument.
+///
+/// # Returns
+///
+/// It returns the response, a `304 Not Modified` if the file sent earlier is still fresh, or
+/// `None` if no file is embedded at the path or the request is neither a `GET` nor a `HEAD`.
+#[cfg(feature = "embed-assets")]
+pub fn embedded_file_response(req: &HttpRequest, path: &str) -> Option<HttpResponse> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return None;
+    }
+    let asset = embedded_asset(path)?;
+
+    let etag = EntityTag::new_strong(
+        asset
+            .metadata
+            .sha256_hash()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+    );
+    if etag_matches(req, &etag) {
+        return Some(
+            HttpResponse::NotModified()
+                .insert_header(ETag(etag))
+                .finish(),
+        );
+    }
+
+    let mut response = HttpResponse::Ok();
+    response
+        .insert_header(ETag(etag))
+        .insert_header((CONTENT_TYPE, asset.metadata.mimetype()));
+    Some(if req.method() == Method::HEAD {
+        response.finish()
+    } else {
+        response.body(asset.data.into_owned())
+    })
+}
+
+/// A function which checks whether the response sent earlier to the client with the provided
+/// `ETag` is still fresh, in which case a `304 Not Modified` can be sent back instead.
+///
+/// # Arguments
+///
+/// * `req` - It takes the `HttpRequest` struct as an argument.
+/// * `etag` - It takes the `ETag` of the response as an argument.
+///
+/// # Returns
+///
+/// It returns `true` if the `If-None-Match` header of the request matches the `ETag`.
+pub fn etag_matches(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        None => false,
+    }
+}
+
+/// A middleware (used with `wrap_fn`) which adds the `Cache-Control` header to the static files
+/// sent back (or revalidated) successfully, so that the missing files are not cached.
+///
+/// # Arguments
+///
+/// * `req` - It takes the request to be handled as an argument.
+/// * `service` - It takes the wrapped service as an argument.
+pub fn add_cache_control<S, B>(
+    req: ServiceRequest,
+    service: &S,
+) -> impl Future<Output = Result<ServiceResponse<B>, Error>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+{
+    let response = service.call(req);
+    async move {
+        let mut response = response.await?;
+        if response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED {
+            response
+                .headers_mut()
+                .insert(CACHE_CONTROL, STATIC_CACHE_CONTROL);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        http::header,
+        test::{call_service, init_service, TestRequest},
+        App,
+    };
+
+    #[actix_web::test]
+    async fn test_static_files() {
+        let folder = tempfile::tempdir().unwrap();
+        std::fs::write(folder.path().join("simple.css"), "body { color: red; }").unwrap();
+        let app = init_service(App::new().service(static_files(
+            "/static",
+            Some(folder.path().to_string_lossy().into_owned()),
+        )))
+        .await;
+
+        let response = call_service(
+            &app,
+            TestRequest::get().uri("/static/simple.css").to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            STATIC_CACHE_CONTROL
+        );
+        assert!(response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("text/css"));
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+        // The browsers revalidate the stale files with their `ETag`.
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/static/simple.css")
+                .insert_header((header::IF_NONE_MATCH, etag))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            STATIC_CACHE_CONTROL
+        );
+
+        let response = call_service(
+            &app,
+            TestRequest::get().uri("/static/missing.css").to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(response.headers().get(CACHE_CONTROL).is_none());
+    }
+
+    #[cfg(feature = "embed-assets")]
+    #[actix_web::test]
+    async fn test_embedded_files() {
+        let app = init_service(App::new().service(static_files("/static", None))).await;
+
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/static/themes/simple.css")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            STATIC_CACHE_CONTROL
+        );
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/css"
+        );
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/static/themes/simple.css")
+                .insert_header((header::IF_NONE_MATCH, etag))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/static/themes/missing.css")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
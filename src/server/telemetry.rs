@@ -0,0 +1,92 @@
+//! This module provides the functionality to export the spans of the requests (along with the
+//! spans of the aggregations and of the requests sent to the upstream search engines) to an
+//! OpenTelemetry collector over OTLP, when the server is built with the `otlp` feature.
+
+use crate::models::parser_models::OtlpConfig;
+use tracing::Subscriber;
+use tracing_subscriber::{registry::LookupSpan, Layer};
+
+/// A type alias for the layer which exports the spans to the collector.
+pub type OtlpLayer<S> = Box<dyn Layer<S> + Send + Sync>;
+
+/// A function which builds the layer exporting the spans to the OTLP/HTTP endpoint provided via
+/// the `otlp.endpoint` option. The spans are exported in batches from a background task.
+///
+/// # Arguments
+///
+/// * `config` - It takes the OTLP config options as an argument.
+///
+/// # Error
+///
+/// Returns the layer or `None` if the spans should not be exported on success otherwise returns
+/// the reason why the exporter could not be built.
+#[cfg(feature = "otlp")]
+pub fn otlp_layer<S>(config: &OtlpConfig) -> Result<Option<OtlpLayer<S>>, String>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{
+        runtime,
+        trace::{self, Sampler},
+        Resource,
+    };
+
+    let Some(endpoint) = &config.endpoint else {
+        return Ok(None);
+    };
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            trace::config()
+                // The child spans follow the decision made for the span of the request, so that
+                // the traces are never cut in the middle.
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    config.sample_ratio,
+                ))))
+                .with_resource(Resource::new([KeyValue::new(
+                    "service.name",
+                    config.service_name.clone(),
+                )])),
+        )
+        .install_batch(runtime::Tokio)
+        .map_err(|error| error.to_string())?;
+
+    Ok(Some(Box::new(
+        tracing_opentelemetry::layer().with_tracer(tracer),
+    )))
+}
+
+/// A function which builds the layer exporting the spans to the collector, the spans are never
+/// exported as the server was built without the `otlp` feature.
+///
+/// # Arguments
+///
+/// * `config` - It takes the OTLP config options as an argument.
+///
+/// # Error
+///
+/// Returns an error if the `otlp.endpoint` option is provided.
+#[cfg(not(feature = "otlp"))]
+pub fn otlp_layer<S>(config: &OtlpConfig) -> Result<Option<OtlpLayer<S>>, String>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    match config.endpoint {
+        Some(_) => Err("the server should be built with the `otlp` feature".to_owned()),
+        None => Ok(None),
+    }
+}
+
+/// A function which exports the spans which are still buffered before the server exits.
+pub fn shutdown() {
+    #[cfg(feature = "otlp")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
@@ -0,0 +1,98 @@
+//! This module provides the functionality to load the certificate and the private key with which
+//! the instance is served over https, so that small instances do not need a reverse proxy only to
+//! terminate the TLS connections.
+
+use rustls::{Certificate, PrivateKey, ServerConfig};
+use rustls_pemfile::Item;
+use std::{
+    fs::File,
+    io::{self, BufReader},
+};
+
+use crate::models::parser_models::TlsConfig;
+
+/// A function which builds the rustls server config from the certificate and the private key
+/// provided with the `tls` option.
+///
+/// # Arguments
+///
+/// * `tls` - It takes the parsed `tls` config option as an argument.
+///
+/// # Error
+///
+/// Returns an io error if the certificate or the private key could not be read or do not match,
+/// otherwise it returns the server config or `None` if the instance should be served over http.
+pub fn server_config(tls: &TlsConfig) -> io::Result<Option<ServerConfig>> {
+    let (Some(certificate), Some(private_key)) = (&tls.certificate, &tls.private_key) else {
+        return Ok(None);
+    };
+
+    let certificates: Vec<Certificate> = rustls_pemfile::certs(&mut pem_reader(certificate)?)?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    if certificates.is_empty() {
+        return Err(io::Error::other(format!(
+            "no certificate was found in `{certificate}`"
+        )));
+    }
+
+    let key = rustls_pemfile::read_all(&mut pem_reader(private_key)?)?
+        .into_iter()
+        .find_map(|item| match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| io::Error::other(format!("no private key was found in `{private_key}`")))?;
+
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certificates, key)
+        .map(Some)
+        .map_err(io::Error::other)
+}
+
+/// A helper function which opens the provided PEM file for reading.
+///
+/// # Arguments
+///
+/// * `path` - It takes the path to the PEM file as an argument.
+///
+/// # Error
+///
+/// Returns an io error mentioning the path if the file could not be opened.
+fn pem_reader(path: &str) -> io::Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|error| io::Error::new(error.kind(), format!("`{path}` cannot be read: {error}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_server_config() {
+        assert!(server_config(&TlsConfig::default()).unwrap().is_none());
+
+        let missing = server_config(&TlsConfig {
+            certificate: Some("/nonexistent/fullchain.pem".to_owned()),
+            private_key: Some("/nonexistent/privkey.pem".to_owned()),
+        })
+        .unwrap_err();
+        assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+        assert!(missing.to_string().contains("/nonexistent/fullchain.pem"));
+
+        let mut empty = tempfile::NamedTempFile::new().unwrap();
+        writeln!(empty, "not a certificate").unwrap();
+        let path = empty.path().to_string_lossy().into_owned();
+        let error = server_config(&TlsConfig {
+            certificate: Some(path.clone()),
+            private_key: Some(path),
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("no certificate was found"));
+    }
+}
@@ -0,0 +1,422 @@
+//! This module provides the opt-in anonymized usage statistics of the instance, which count the
+//! searches per day along with the search categories and the upstream search engines used. The
+//! search queries are never counted and the ip addresses of the visitors are only counted through
+//! a HyperLogLog of their keyed hashes (which is renewed every day), so that the number of unique
+//! visitors can be estimated without storing who visited the instance. The statistics are kept in
+//! memory or in the redis server, so that they can be shared by all the replicas of the instance.
+
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    net::IpAddr,
+    sync::{Mutex, OnceLock, PoisonError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::config::parser::Config;
+#[cfg(feature = "redis-cache")]
+use crate::server::rate_limit::RedisStore;
+
+/// The number of bits of the hashes selecting the register of the in memory HyperLogLog, which
+/// gives 4096 registers (4 KiB per day) and a standard error of about 1.6%.
+const HLL_PRECISION: u32 = 12;
+
+/// The number of registers of the in memory HyperLogLog.
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// The number of seconds in a day.
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The context used to derive the key hashing the ip addresses of the visitors from the
+/// `secret_key` option.
+const VISITOR_KEY_CONTEXT: &str = "websurfx 2024-01-01 usage statistics visitor key";
+
+/// A static variable which stores the key hashing the ip addresses of the visitors generated on
+/// startup, which is used when no `secret_key` option is provided.
+static GENERATED_VISITOR_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// A HyperLogLog which estimates the number of distinct hashes inserted into it without storing
+/// them.
+struct HyperLogLog {
+    /// It stores the highest rank of the hashes falling into each register.
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog {
+            registers: vec![0; HLL_REGISTERS],
+        }
+    }
+}
+
+impl HyperLogLog {
+    /// A function which inserts a hash into the HyperLogLog.
+    ///
+    /// # Arguments
+    ///
+    /// * `hash` - It takes the uniformly distributed hash to be inserted as an argument.
+    fn insert(&mut self, hash: u64) {
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // The bit set after the remaining bits of the hash bounds the rank when they are all zero.
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() + 1;
+        self.registers[index] = self.registers[index].max(rank as u8);
+    }
+
+    /// A function which estimates the number of distinct hashes inserted into the HyperLogLog,
+    /// the small counts are estimated with the linear counting of the empty registers.
+    fn count(&self) -> u64 {
+        let registers = HLL_REGISTERS as f64;
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|rank| 2f64.powi(-i32::from(*rank)))
+            .sum();
+        let estimate = 0.7213 / (1.0 + 1.079 / registers) * registers * registers / sum;
+
+        let empty_registers = self.registers.iter().filter(|rank| **rank == 0).count();
+        if estimate <= 2.5 * registers && empty_registers > 0 {
+            return (registers * (registers / empty_registers as f64).ln()).round() as u64;
+        }
+        estimate.round() as u64
+    }
+}
+
+/// A named struct which stores the usage of the instance during a day in memory.
+#[derive(Default)]
+struct DayUsage {
+    /// It stores the number of the searches.
+    searches: u64,
+    /// It stores the number of the searches per search category.
+    categories: BTreeMap<String, u64>,
+    /// It stores the number of the searches sent to each upstream search engine.
+    engines: BTreeMap<String, u64>,
+    /// It stores the HyperLogLog of the hashed ip addresses of the visitors.
+    visitors: HyperLogLog,
+}
+
+/// A named struct which stores the usage statistics of the instance during a day.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DailyUsage {
+    /// It stores the day (like `2024-01-31`) in UTC.
+    pub day: String,
+    /// It stores the number of the searches.
+    pub searches: u64,
+    /// It stores the estimated number of the unique visitors.
+    pub visitors: u64,
+    /// It stores the number of the searches per search category.
+    pub categories: BTreeMap<String, u64>,
+    /// It stores the number of the searches sent to each upstream search engine.
+    pub engines: BTreeMap<String, u64>,
+}
+
+/// The store in which the usage statistics are tracked.
+enum UsageStore {
+    /// The usage statistics are tracked in the memory of the instance, keyed by the number of the
+    /// day since the unix epoch.
+    Memory(Mutex<BTreeMap<u64, DayUsage>>),
+    /// The usage statistics are tracked in the redis server, so that they are shared by all the
+    /// replicas of the instance.
+    #[cfg(feature = "redis-cache")]
+    Redis(Box<RedisStore>),
+}
+
+/// The usage statistics of the instance, which are only counted when the `usage_stats.enabled`
+/// option is set.
+pub struct UsageStats {
+    /// It stores the store in which the usage statistics are tracked.
+    store: UsageStore,
+    /// It stores the key hashing the ip addresses of the visitors.
+    visitor_key: [u8; 32],
+}
+
+impl UsageStats {
+    /// Creates the usage statistics tracked in memory or in the redis server, as selected with the
+    /// `usage_stats.redis` option.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    ///
+    /// # Error
+    ///
+    /// Returns an io error if the redis url is invalid while the usage statistics should be
+    /// tracked in the redis server.
+    pub fn new(config: &Config) -> std::io::Result<Self> {
+        #[cfg(feature = "redis-cache")]
+        let store = match config.usage_stats.redis {
+            true => UsageStore::Redis(Box::new(RedisStore::new(&config.redis_url)?)),
+            false => UsageStore::Memory(Mutex::default()),
+        };
+        #[cfg(not(feature = "redis-cache"))]
+        let store = UsageStore::Memory(Mutex::default());
+
+        // The key is derived from the secret key so that the visitors of all the replicas are
+        // hashed alike.
+        let visitor_key = match &config.secret_key {
+            Some(secret_key) => blake3::derive_key(VISITOR_KEY_CONTEXT, secret_key.as_bytes()),
+            None => *GENERATED_VISITOR_KEY.get_or_init(rand::random),
+        };
+
+        Ok(UsageStats { store, visitor_key })
+    }
+
+    /// A function which counts a search of a visitor. The search query is never provided and the
+    /// ip address of the visitor is only counted through its keyed hash, which changes every day.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    /// * `client_ip` - It takes the ip address of the visitor as an argument.
+    /// * `category` - It takes the search category (like `general`) as an argument.
+    /// * `engines` - It takes the names of the upstream search engines used for the search as an
+    ///   argument.
+    pub async fn record_search(
+        &self,
+        config: &Config,
+        client_ip: IpAddr,
+        category: &str,
+        engines: &[String],
+    ) {
+        let today = current_day();
+        let visitor =
+            blake3::keyed_hash(&self.visitor_key, format!("{today}:{client_ip}").as_bytes());
+        let retention_days = u64::from(config.usage_stats.retention_days);
+
+        match &self.store {
+            UsageStore::Memory(days) => {
+                let mut days = days.lock().unwrap_or_else(PoisonError::into_inner);
+                days.retain(|day, _| day + retention_days > today);
+                let usage = days.entry(today).or_default();
+                usage.searches += 1;
+                *usage.categories.entry(category.to_owned()).or_default() += 1;
+                for engine in engines {
+                    *usage.engines.entry(engine.clone()).or_default() += 1;
+                }
+                let mut hash = [0; 8];
+                hash.copy_from_slice(&visitor.as_bytes()[..8]);
+                usage.visitors.insert(u64::from_be_bytes(hash));
+            }
+            #[cfg(feature = "redis-cache")]
+            UsageStore::Redis(redis_store) => {
+                let prefix = redis_key_prefix(today);
+                let expiry = (retention_days * SECONDS_PER_DAY) as i64;
+                let mut pipeline = redis::pipe();
+                pipeline
+                    .atomic()
+                    .incr(format!("{prefix}:searches"), 1)
+                    .ignore()
+                    .expire(format!("{prefix}:searches"), expiry)
+                    .ignore()
+                    .hincr(format!("{prefix}:categories"), category, 1)
+                    .ignore()
+                    .expire(format!("{prefix}:categories"), expiry)
+                    .ignore()
+                    .pfadd(format!("{prefix}:visitors"), visitor.to_hex().as_str())
+                    .ignore()
+                    .expire(format!("{prefix}:visitors"), expiry)
+                    .ignore();
+                for engine in engines {
+                    pipeline
+                        .hincr(format!("{prefix}:engines"), engine, 1)
+                        .ignore();
+                }
+                pipeline
+                    .expire(format!("{prefix}:engines"), expiry)
+                    .ignore();
+
+                let recorded: redis::RedisResult<()> = async {
+                    pipeline
+                        .query_async(&mut redis_store.connection().await?)
+                        .await
+                }
+                .await;
+                if let Err(error) = recorded {
+                    tracing::error!("The usage statistics could not be counted in redis: {error}");
+                }
+            }
+        }
+    }
+
+    /// A function which returns the usage statistics of the days kept with the
+    /// `usage_stats.retention_days` option.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - It takes the parsed config struct as an argument.
+    ///
+    /// # Returns
+    ///
+    /// It returns the usage statistics of the days on which the instance was used, the most recent
+    /// day first, or `None` if they could not be read from the redis server.
+    pub async fn daily_usage(&self, config: &Config) -> Option<Vec<DailyUsage>> {
+        let today = current_day();
+        let first_day = (today + 1).saturating_sub(config.usage_stats.retention_days.into());
+
+        match &self.store {
+            UsageStore::Memory(days) => Some(
+                days.lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .range(first_day..=today)
+                    .rev()
+                    .map(|(day, usage)| DailyUsage {
+                        day: date(*day),
+                        searches: usage.searches,
+                        visitors: usage.visitors.count(),
+                        categories: usage.categories.clone(),
+                        engines: usage.engines.clone(),
+                    })
+                    .collect(),
+            ),
+            #[cfg(feature = "redis-cache")]
+            UsageStore::Redis(redis_store) => {
+                let mut pipeline = redis::pipe();
+                for day in (first_day..=today).rev() {
+                    let prefix = redis_key_prefix(day);
+                    pipeline
+                        .get(format!("{prefix}:searches"))
+                        .hgetall(format!("{prefix}:categories"))
+                        .hgetall(format!("{prefix}:engines"))
+                        .pfcount(format!("{prefix}:visitors"));
+                }
+
+                type RedisDayUsage = (
+                    Option<u64>,
+                    BTreeMap<String, u64>,
+                    BTreeMap<String, u64>,
+                    u64,
+                );
+                let days: redis::RedisResult<Vec<RedisDayUsage>> = async {
+                    pipeline
+                        .query_async(&mut redis_store.connection().await?)
+                        .await
+                }
+                .await;
+                let days = days
+                    .map_err(|error| {
+                        tracing::error!(
+                            "The usage statistics could not be read from redis: {error}"
+                        )
+                    })
+                    .ok()?;
+
+                Some(
+                    (first_day..=today)
+                        .rev()
+                        .zip(days)
+                        .filter_map(|(day, (searches, categories, engines, visitors))| {
+                            Some(DailyUsage {
+                                day: date(day),
+                                searches: searches?,
+                                visitors,
+                                categories,
+                                engines,
+                            })
+                        })
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+/// A helper function which returns the prefix of the keys storing the usage statistics of the
+/// provided day in the redis server.
+///
+/// # Arguments
+///
+/// * `day` - It takes the number of the day since the unix epoch as an argument.
+#[cfg(feature = "redis-cache")]
+fn redis_key_prefix(day: u64) -> String {
+    format!("websurfx:usage:{}", date(day))
+}
+
+/// A helper function which returns the number of the current day (in UTC) since the unix epoch.
+fn current_day() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() / SECONDS_PER_DAY)
+}
+
+/// A helper function which formats the provided day as a date (like `2024-01-31`) of the
+/// gregorian calendar.
+///
+/// # Arguments
+///
+/// * `day` - It takes the number of the day since the unix epoch as an argument.
+fn date(day: u64) -> String {
+    // The days are counted from the 1st of March of the year 0, so that the leap day is the last
+    // day of the year, in eras of 400 years (146097 days).
+    let days = day + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day_of_month:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(19_723), "2024-01-01");
+        assert_eq!(date(19_782), "2024-02-29");
+        assert_eq!(date(19_783), "2024-03-01");
+    }
+
+    #[test]
+    fn test_hyperloglog() {
+        let mut visitors = HyperLogLog::default();
+        for visitor in 0..10_000u32 {
+            let hash = blake3::hash(&visitor.to_be_bytes());
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&hash.as_bytes()[..8]);
+            // Each visitor is counted once however many searches it sends.
+            visitors.insert(u64::from_be_bytes(bytes));
+            visitors.insert(u64::from_be_bytes(bytes));
+        }
+        let count = visitors.count();
+        assert!((9_500..=10_500).contains(&count), "{count}");
+        assert_eq!(HyperLogLog::default().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_usage_stats() {
+        let config = Config::default();
+        let usage_stats = UsageStats::new(&config).unwrap();
+        let engines = ["Bing".to_owned(), "DuckDuckGo".to_owned()];
+        let first_visitor = IpAddr::from([192, 0, 2, 1]);
+        usage_stats
+            .record_search(&config, first_visitor, "general", &engines)
+            .await;
+        usage_stats
+            .record_search(&config, first_visitor, "images", &engines[..1])
+            .await;
+        usage_stats
+            .record_search(&config, IpAddr::from([192, 0, 2, 2]), "general", &[])
+            .await;
+
+        let days = usage_stats.daily_usage(&config).await.unwrap();
+        assert_eq!(
+            days,
+            [DailyUsage {
+                day: date(current_day()),
+                searches: 3,
+                visitors: 2,
+                categories: BTreeMap::from([("general".to_owned(), 2), ("images".to_owned(), 1)]),
+                engines: BTreeMap::from([("Bing".to_owned(), 2), ("DuckDuckGo".to_owned(), 1)]),
+            }]
+        );
+    }
+}
@@ -1,5 +1,5 @@
 //! This module provides other modules to handle both the view and its partials for the `websurfx`
 //! search engine frontend.
 
-mod partials;
+pub(crate) mod partials;
 pub mod views;
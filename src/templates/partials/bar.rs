@@ -2,21 +2,27 @@
 
 use maud::{html, Markup, PreEscaped};
 
+use crate::server::csrf::CSRF_FIELD;
+
 /// A functions that handles the html code for the bar for the `search_bar` partial and the
-/// home/index/main page in the search engine frontend.
+/// home/index/main page in the search engine frontend. The bar is a form sent to the `POST /search`
+/// route (when the search is not streamed by the script of the page), so that the search query
+/// does not end up in the url.
 ///
 /// # Arguments
 ///
 /// * `query` - It takes the current search query provided by user as an argument.
+/// * `csrf_token` - It takes the CSRF token submitted with the search form as an argument.
 ///
 /// # Returns
 ///
 /// It returns the compiled html code for the search bar as a result.
-pub fn bar(query: &str) -> Markup {
+pub fn bar(query: &str, csrf_token: &str) -> Markup {
     html!(
-        (PreEscaped("<div class=\"search_bar\">"))
-            input type="search" name="search-box" value=(query) placeholder="Type to search";
-            button type="submit" onclick="searchWeb()" {
+        (PreEscaped("<form class=\"search_bar\" action=\"search\" method=\"post\">"))
+            input type="search" name="q" value=(query) placeholder="Type to search";
+            input type="hidden" name=(CSRF_FIELD) value=(csrf_token);
+            button type="submit" {
                 img src="./images/magnifying_glass.svg" alt="Info icon for error box";
             }
     )
@@ -17,6 +17,7 @@ pub fn footer() -> Markup {
            }
            div{
               ul{
+                  li{a href="/status"{"Engine Status"}}
                   li{a href="https://github.com/neon-mmd/websurfx"{"Source Code"}}
                   li{a href="https://github.com/neon-mmd/websurfx/issues"{"Issues/Bugs"}}
               }
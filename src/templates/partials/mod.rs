@@ -5,4 +5,5 @@ pub mod footer;
 pub mod header;
 pub mod navbar;
 pub mod search_bar;
+pub mod search_result;
 pub mod settings_tabs;
@@ -2,19 +2,20 @@
 
 use maud::{html, Markup, PreEscaped};
 
-use crate::{models::aggregation_models::EngineErrorInfo, templates::partials::bar::bar};
-
-/// A constant holding the named safe search level options for the corresponding values 0, 1 and 2.
-const SAFE_SEARCH_LEVELS_NAME: [&str; 3] = ["None", "Low", "Moderate"];
+use crate::{
+    models::{aggregation_models::EngineErrorInfo, parser_models::SafeSearchLevel},
+    templates::partials::bar::bar,
+};
 
 /// A functions that handles the html code for the search bar for the search page.
 ///
 /// # Arguments
 ///
 /// * `engine_errors_info` - It takes the engine errors list containing errors for each upstream
-/// search engine which failed to provide results as an argument.
+///   search engine which failed to provide results as an argument.
 /// * `safe_search_level` - It takes the safe search level with values from 0-2 as an argument.
 /// * `query` - It takes the current search query provided by user as an argument.
+/// * `csrf_token` - It takes the CSRF token submitted with the search form as an argument.
 ///
 /// # Returns
 ///
@@ -23,13 +24,14 @@ pub fn search_bar(
     engine_errors_info: &[EngineErrorInfo],
     safe_search_level: u8,
     query: &str,
+    csrf_token: &str,
 ) -> Markup {
     html!(
         .search_area{
-            (bar(query))
+            (bar(query, csrf_token))
                 .error_box {
                    @if !engine_errors_info.is_empty(){
-                      button onclick="toggleErrorBox()" class="error_box_toggle_button"{
+                      button type="button" class="error_box_toggle_button"{
                          img src="./images/warning.svg" alt="Info icon for error box";
                       }
                       .dropdown_error_box{
@@ -37,13 +39,13 @@ pub fn search_bar(
                             .error_item{
                                span class="engine_name"{(errors.engine)}
                                span class="engine_name"{(errors.error)}
-                               span class="severity_color" style="background: {{{this.severity_color}}};"{}
+                               span class="severity_color"{}
                             }
                          }
                       }
                    }
                    @else {
-                      button onclick="toggleErrorBox()" class="error_box_toggle_button"{
+                      button type="button" class="error_box_toggle_button"{
                          img src="./images/info.svg" alt="Warning icon for error box";
                       }
                       .dropdown_error_box {
@@ -53,7 +55,7 @@ pub fn search_bar(
                       }
                   }
                 }
-            (PreEscaped("</div>"))
+            (PreEscaped("</form>"))
             .search_options {
                @if safe_search_level >= 3 {
                    (PreEscaped("<select name=\"safe_search_levels\" disabled>"))
@@ -61,12 +63,12 @@ pub fn search_bar(
                @else{
                    (PreEscaped("<select name=\"safe_search_levels\">"))
                }
-               @for (idx, name) in SAFE_SEARCH_LEVELS_NAME.iter().enumerate() {
-                   @if (safe_search_level as usize) == idx {
-                       option value=(idx) selected {(format!("SafeSearch: {name}"))}
+               @for level in SafeSearchLevel::ALL {
+                   @if SafeSearchLevel::from_level(safe_search_level) == level {
+                       option value=(level.level()) selected {(format!("SafeSearch: {}", level.display_name()))}
                    }
                    @else{
-                       option value=(idx) {(format!("SafeSearch: {name}"))}
+                       option value=(level.level()) {(format!("SafeSearch: {}", level.display_name()))}
                    }
                }
                (PreEscaped("</select>"))
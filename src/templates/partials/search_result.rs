@@ -0,0 +1,46 @@
+//! A module that handles the search result partial for the search page in the `websurfx`
+//! frontend.
+
+use maud::{html, Markup, PreEscaped};
+
+use crate::{
+    config::parser::Config, models::aggregation_models::SearchResult,
+    results::domain_filter::url_host, server::routes::redirect::redirect_url,
+};
+
+/// A function that handles the html code for a single search result on the search page, it is
+/// shared by the search page and the streamed search results so that both render the same markup.
+///
+/// # Arguments
+///
+/// * `config` - It takes the parsed config struct (for whether the favicons are shown and whether
+///   the links go through the anonymous redirect route) as an argument.
+/// * `result` - It takes the search result to be rendered as an argument.
+///
+/// # Returns
+///
+/// It returns the compiled html code for the search result as a result.
+pub fn search_result(config: &Config, result: &SearchResult) -> Markup {
+    let link = match config.anonymous_redirects {
+        true => redirect_url(config, &result.url),
+        false => result.url.clone(),
+    };
+    html!(
+        .result data-url=(result.url) {
+           h1{
+              @if let Some(host) = url_host(&result.url).filter(|_| config.favicon_service.is_some()) {
+                 img class="favicon" src=(format!("proxy/favicon?domain={host}")) alt="" loading="lazy";
+              }
+              a href=(link) rel="noreferrer"{(result.title)}
+           }
+           small{(result.url)}
+           p{(PreEscaped(&result.description))}
+           .upstream_engines{
+              @for name in &result.engine{
+                 span class="engine"{(name)}
+              }
+              span class="score" title="The fused score of the result"{(format!("{:.4}", result.score))}
+           }
+        }
+    )
+}
@@ -2,12 +2,26 @@
 
 use maud::{html, Markup};
 
+use crate::server::profiles::ProfileStatus;
+
 /// A functions that handles the html code for the cookies tab for the settings page for the search page.
 ///
+/// # Arguments
+///
+/// * `profile` - It takes the server-side profile of the user as an argument.
+/// * `preferences_url` - It takes the search url carrying the saved settings of the user as an
+///   argument.
+/// * `history` - It takes whether the users of the profiles can keep their search history as an
+///   argument.
+///
 /// # Returns
 ///
 /// It returns the compiled html markup code for the cookies tab.
-pub fn cookies() -> Markup {
+pub fn cookies(
+    profile: &ProfileStatus<'_>,
+    preferences_url: Option<&str>,
+    history: bool,
+) -> Markup {
     html!(
         div class="cookies tab"{
            h1{"Cookies"}
@@ -20,6 +34,65 @@ pub fn cookies() -> Markup {
                "The cookies stored are not used by us for any malicious intend or for
                tracking you in any way."
            }
+           h3{"Export and import"}
+           p class="description"{
+               "Export your settings as a json file to import them on your other browsers or on
+               another instance."
+           }
+           a class="export" href="settings/export" download{"Export the settings"}
+           input type="file" class="import_file" accept="application/json,.json";
+           textarea name="settings" rows="4" placeholder="The content of the exported settings file"{}
+           button type="submit" class="import" formaction="/settings/import"{"Import"}
+           @if let Some(preferences_url) = preferences_url {
+               h3{"Preferences url"}
+               p class="description"{
+                   "Bookmark or share this search url (followed by the search query) to search with
+                   your settings without any cookie."
+               }
+               input type="text" value=(preferences_url) readonly;
+           }
+           @if !matches!(profile, ProfileStatus::Disabled) {
+               h3{"Server-side profile"}
+               p class="description"{
+                   "Save the settings on the server instead of in the cookies, the profile can be
+                   restored on your other browsers with its restore code."
+               }
+               .profile{
+                   @if matches!(profile, ProfileStatus::Saved { .. }) {
+                       input type="checkbox" name="profile" value="on" checked;
+                   } @else {
+                       input type="checkbox" name="profile" value="on";
+                   }
+                   span{"Save the settings on the server"}
+               }
+               @if history {
+                   h3{"Search history"}
+                   p class="description"{
+                       "Keep your searches in your profile to find them again on the "
+                       a href="history"{"history page"}
+                       ", the history is never kept unless it is enabled."
+                   }
+                   .profile{
+                       input type="checkbox" name="search_history" value="on";
+                       span{"Keep the search history in the profile"}
+                   }
+               }
+               h3{"Custom stylesheet"}
+               p class="description"{
+                   "Enter the css rules which should be applied on top of the theme, they are only
+                   saved along with the settings on the server."
+               }
+               textarea name="custom_css" rows="6" placeholder="body { font-size: 1.1rem; }"{}
+               @if let ProfileStatus::Saved { restore_code, .. } = profile {
+                   h3{"Restore code"}
+                   input type="text" value=(restore_code) readonly;
+               }
+               h3{"Restore a profile"}
+               p class="description"{
+                   "Enter the restore code shown on your other browser to load its settings."
+               }
+               input type="text" name="restore_code" value="" autocomplete="off";
+           }
         }
     )
 }
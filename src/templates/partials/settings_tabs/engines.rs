@@ -4,17 +4,25 @@ use std::collections::HashMap;
 
 use maud::{html, Markup};
 
+use crate::models::server_models::ENGINE_CATEGORIES;
+
 /// A functions that handles the html code for the engines tab for the settings page for the search page.
+/// The engines of the images, videos and news categories can also be selected independently, the
+/// engines selected above are used for the categories without a selection.
 ///
 /// # Arguments
 ///
 /// * `engine_names` - It takes the key value pair list of all available engine names and there corresponding
-/// selected (enabled/disabled) value as an argument.
+///   selected (enabled/disabled) value as an argument.
+/// * `locked` - It takes whether the engines are locked by the server administrator (in which case
+///   they are shown disabled) as an argument.
 ///
 /// # Returns
 ///
 /// It returns the compiled html markup code for the engines tab.
-pub fn engines(engine_names: &HashMap<String, bool>) -> Markup {
+pub fn engines(engine_names: &HashMap<String, bool>, locked: bool) -> Markup {
+    let mut sorted_engine_names: Vec<&String> = engine_names.keys().collect();
+    sorted_engine_names.sort();
     html!(
         div class="engines tab"{
            h1{"Engines"}
@@ -22,6 +30,9 @@ pub fn engines(engine_names: &HashMap<String, bool>) -> Markup {
            p class="description"{
               "Select the search engines from the list of engines that you want results from"
            }
+           @if locked {
+              p class="admin_warning" {"⚠️  This setting is being managed by the server administrator."}
+           }
            .engine_selection{
                // Checks whether all the engines are selected or not if they are then the
                // checked `select_all` button is rendered otherwise the unchecked version
@@ -29,7 +40,7 @@ pub fn engines(engine_names: &HashMap<String, bool>) -> Markup {
                @if engine_names.values().all(|selected| *selected){
                    .toggle_btn{
                       label class="switch"{
-                         input type="checkbox" class="select_all" onchange="toggleAllSelection()" checked;
+                         input type="checkbox" class="select_all" checked disabled[locked];
                          span class="slider round"{}
                       }
                       "Select All"
@@ -38,7 +49,7 @@ pub fn engines(engine_names: &HashMap<String, bool>) -> Markup {
                @else{
                    .toggle_btn {
                       label class="switch"{
-                         input type="checkbox" class="select_all" onchange="toggleAllSelection()";
+                         input type="checkbox" class="select_all" disabled[locked];
                          span class="slider round"{}
                       }
                       "Select All"
@@ -52,7 +63,7 @@ pub fn engines(engine_names: &HashMap<String, bool>) -> Markup {
                    @if *selected {
                        .toggle_btn{
                            label class="switch"{
-                              input type="checkbox" class="engine" checked;
+                              input type="checkbox" class="engine" name="engines" value=(engine_name) checked disabled[locked];
                               span class="slider round"{}
                            }
                            (format!("{}{}",engine_name[..1].to_uppercase().to_owned(), engine_name[1..].to_owned()))
@@ -61,7 +72,26 @@ pub fn engines(engine_names: &HashMap<String, bool>) -> Markup {
                    @else {
                        .toggle_btn {
                            label class="switch"{
-                              input type="checkbox" class="engine";
+                              input type="checkbox" class="engine" name="engines" value=(engine_name) disabled[locked];
+                              span class="slider round"{}
+                           }
+                           (format!("{}{}",engine_name[..1].to_uppercase().to_owned(), engine_name[1..].to_owned()))
+                       }
+                   }
+               }
+           }
+           // The selections of the categories are filled from the saved settings by the script
+           // of the settings page.
+           @for category in ENGINE_CATEGORIES {
+               h3{(format!("{category} search engines"))}
+               p class="description"{
+                  (format!("Select the search engines used on the {category} tab, the engines selected above are used if none is selected"))
+               }
+               .engine_selection{
+                   @for engine_name in &sorted_engine_names {
+                       .toggle_btn{
+                           label class="switch"{
+                              input type="checkbox" class="category_engine" name=(format!("{category}_engines")) value=(engine_name) data-category=(category) disabled[locked];
                               span class="slider round"{}
                            }
                            (format!("{}{}",engine_name[..1].to_uppercase().to_owned(), engine_name[1..].to_owned()))
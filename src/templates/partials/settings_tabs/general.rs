@@ -1,20 +1,25 @@
 //! A module that handles the general tab for setting page view in the `websurfx` frontend.
 
+use crate::models::{
+    engine_models::TimeRange,
+    locale_models::{LANGUAGES, REGIONS},
+    parser_models::{LockedSetting, SafeSearchLevel},
+};
 use maud::{html, Markup};
 
-/// A constant holding the named safe search level options for the corresponding values 0, 1 and 2.
-const SAFE_SEARCH_LEVELS: [(u8, &str); 3] = [(0, "None"), (1, "Low"), (2, "Moderate")];
-
 /// A functions that handles the html code for the general tab for the settings page for the search page.
 ///
 /// # Arguments
 ///
 /// * `safe_search_level` - It takes the safe search level as an argument.
+/// * `locked_settings` - It takes the settings locked by the server administrator (which are
+///   shown disabled) as an argument.
 ///
 /// # Returns
 ///
 /// It returns the compiled html markup code for the general tab.
-pub fn general(safe_search_level: u8) -> Markup {
+pub fn general(safe_search_level: u8, locked_settings: &[LockedSetting]) -> Markup {
+    let locked = |setting: LockedSetting| locked_settings.contains(&setting);
     html!(
         div class="general tab active"{
            h1{"General"}
@@ -22,21 +27,88 @@ pub fn general(safe_search_level: u8) -> Markup {
            p class="description"{
                "Select a safe search level from the menu below to filter content based on the level."
            }
-           @if safe_search_level < 3 {
+           @if safe_search_level < 3 && !locked(LockedSetting::SafeSearch) {
                select name="safe_search_levels" {
                    // Sets the user selected safe_search_level name from the config file as the first option in the selection list.
-                   option value=(safe_search_level){(SAFE_SEARCH_LEVELS.iter().find(|level| level.0 == safe_search_level).unwrap().1)}
-                   @for (k,v) in SAFE_SEARCH_LEVELS.iter().filter(|level| level.0 != safe_search_level){
-                     option value=(k){(v)}
+                   option value=(safe_search_level){(SafeSearchLevel::from_level(safe_search_level).display_name())}
+                   @for level in SafeSearchLevel::ALL.iter().filter(|level| level.level() != safe_search_level){
+                     option value=(level.level()){(level.display_name())}
                    }
                }
            }
            @else {
                p class="admin_warning" {"⚠️  This setting is being managed by the server administrator."}
                select name="safe_search_levels" disabled {
-                     option value=(SAFE_SEARCH_LEVELS[2].0){(SAFE_SEARCH_LEVELS[2].1)}
+                     option value=(safe_search_level){(SafeSearchLevel::from_level(safe_search_level).display_name())}
+               }
+           }
+           h3{"Select a language"}
+           p class="description"{
+               "Select the language in which the search results should preferably be written."
+           }
+           @if locked(LockedSetting::Language) {
+               p class="admin_warning" {"⚠️  This setting is being managed by the server administrator."}
+           }
+           select name="languages" disabled[locked(LockedSetting::Language)] {
+               option value=""{"Auto"}
+               @for (code, name) in LANGUAGES {
+                   option value=(code){(name)}
+               }
+           }
+           h3{"Select a region"}
+           p class="description"{
+               "Select the country from which the local search results (like shops or news) should be provided."
+           }
+           @if locked(LockedSetting::Region) {
+               p class="admin_warning" {"⚠️  This setting is being managed by the server administrator."}
+           }
+           select name="regions" disabled[locked(LockedSetting::Region)] {
+               option value=""{"Any"}
+               @for (code, name, _) in REGIONS {
+                   option value=(code){(name)}
+               }
+           }
+           h3{"Select a time range"}
+           p class="description"{
+               "Select the time range to which the search results should be restricted by default."
+           }
+           @if locked(LockedSetting::TimeRange) {
+               p class="admin_warning" {"⚠️  This setting is being managed by the server administrator."}
+           }
+           select name="time_ranges" disabled[locked(LockedSetting::TimeRange)] {
+               option value=""{"Any time"}
+               @for time_range in TimeRange::ALL {
+                   option value=(time_range.name()){(time_range.display_name())}
                }
            }
+           h3{"Highlight the search terms"}
+           p class="description"{
+               "Select whether the search terms should be highlighted in the descriptions of the search results."
+           }
+           select name="highlights" {
+               option value="on"{"On"}
+               option value="off"{"Off"}
+           }
+           h3{"Results per page"}
+           p class="description"{
+               "Select the number of search results which should be shown on each page."
+           }
+           select name="results_per_page" {
+               option value=""{"Default"}
+               @for results_per_page in [10, 20, 50, 100] {
+                   option value=(results_per_page){(results_per_page)}
+               }
+           }
+           h3{"Blocked domains"}
+           p class="description"{
+               "Enter the domains (one per line) whose results should be hidden. `*` matches any characters and the patterns wrapped in slashes are regular expressions."
+           }
+           textarea name="blocked_domains" rows="4" placeholder="pinterest.com"{}
+           h3{"Allowed domains"}
+           p class="description"{
+               "Enter the domains (one per line) to which the results should be restricted. The results are not restricted if it is empty."
+           }
+           textarea name="allowed_domains" rows="4"{}
         }
     )
 }
@@ -1,59 +1,56 @@
 //! A module that handles the user interface tab for setting page view in the `websurfx` frontend.
 
-use crate::handler::{file_path, FileType};
+use crate::models::parser_models::{Style, StyleCatalog};
 use maud::{html, Markup};
-use std::fs::read_dir;
 
 /// A helper function that helps in building the list of all available colorscheme/theme/animation
-/// names present in the colorschemes, animations and themes folder respectively by excluding the
+/// names scanned from the colorschemes, animations and themes folder respectively by excluding the
 /// ones that have already been selected via the config file.
 ///
 /// # Arguments
 ///
-/// * `style_type` - It takes the style type of the values `theme` and `colorscheme` as an
-/// argument.
+/// * `styles` - It takes the styles scanned from the `public/static` folder as an argument.
+/// * `style_type` - It takes the style type of the values `themes`, `colorschemes` and
+///   `animations` as an argument.
 /// * `selected_style` - It takes the currently selected style value provided via the config file
-/// as an argument.
+///   as an argument.
 ///
-/// # Error
+/// # Returns
 ///
-/// Returns a list of colorscheme/theme names as a vector of tuple strings on success otherwise
-/// returns a standard error message.
+/// Returns a list of colorscheme/theme names as a vector of tuple strings.
 fn style_option_list(
+    styles: &StyleCatalog,
     style_type: &str,
     selected_style: &str,
-) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
-    let mut style_option_names: Vec<(String, String)> = Vec::new();
-    for file in read_dir(format!(
-        "{}static/{}/",
-        file_path(FileType::Theme)?,
-        style_type,
-    ))? {
-        let style_name = file?.file_name().to_str().unwrap().replace(".css", "");
-        if selected_style != style_name {
-            style_option_names.push((style_name.clone(), style_name.replace('-', " ")));
-        }
-    }
+) -> Vec<(String, String)> {
+    let mut style_option_names: Vec<(String, String)> = styles
+        .names(style_type)
+        .iter()
+        .filter(|style_name| *style_name != selected_style)
+        .map(|style_name| (style_name.clone(), style_name.replace('-', " ")))
+        .collect();
 
     if style_type == "animations" {
         style_option_names.push(("".to_owned(), "none".to_owned()))
     }
 
-    Ok(style_option_names)
+    style_option_names
 }
 
 /// A functions that handles the html code for the user interface tab for the settings page for the search page.
 ///
-/// # Error
+/// # Arguments
+///
+/// * `style` - It takes the theme, the colorscheme and the animation names selected via the
+///   config file as an argument.
+/// * `styles` - It takes the styles scanned from the `public/static` folder as an argument.
+///
+/// # Returns
 ///
-/// It returns the compiled html markup code for the user interface tab on success otherwise
-/// returns a standard error message.
-pub fn user_interface(
-    theme: &str,
-    colorscheme: &str,
-    animation: &Option<String>,
-) -> Result<Markup, Box<dyn std::error::Error>> {
-    Ok(html!(
+/// It returns the compiled html markup code for the user interface tab.
+pub fn user_interface(style: &Style, styles: &StyleCatalog) -> Markup {
+    let animation = style.animation.as_deref().unwrap_or_default();
+    html!(
         div class="user_interface tab"{
            h1{"User Interface"}
            h3{"select theme"}
@@ -62,8 +59,8 @@ pub fn user_interface(
            }
            select name="themes"{
                // Sets the user selected theme name from the config file as the first option in the selection list.
-               option value=(theme){(theme.replace('-', " "))}
-               @for (k,v) in style_option_list("themes", theme)?{
+               option value=(style.theme){(style.theme.replace('-', " "))}
+               @for (k,v) in style_option_list(styles, "themes", &style.theme){
                    option value=(k){(v)}
                }
            }
@@ -73,8 +70,8 @@ pub fn user_interface(
            }
            select name="colorschemes"{
                // Sets the user selected colorscheme name from the config file as the first option in the selection list.
-               option value=(colorscheme){(colorscheme.replace('-', " "))}
-               @for (k,v) in style_option_list("colorschemes", colorscheme)?{
+               option value=(style.colorscheme){(style.colorscheme.replace('-', " "))}
+               @for (k,v) in style_option_list(styles, "colorschemes", &style.colorscheme){
                    option value=(k){(v)}
                }
            }
@@ -84,11 +81,11 @@ pub fn user_interface(
            }
            select name="animations"{
                // Sets the user selected animation name from the config file as the first option in the selection list.
-               option value=(animation.as_ref().unwrap_or(&"".to_owned())){(animation.as_ref().unwrap_or(&"".to_owned()).replace('-'," "))}
-               @for (k,v) in style_option_list("animations", animation.as_ref().unwrap_or(&"".to_owned()))?{
+               option value=(animation){(animation.replace('-'," "))}
+               @for (k,v) in style_option_list(styles, "animations", animation){
                    option value=(k){(v)}
                }
            }
         }
-    ))
+    )
 }
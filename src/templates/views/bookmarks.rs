@@ -0,0 +1,73 @@
+//! A module that handles the view for the bookmarks page in the `websurfx` frontend.
+
+use crate::{
+    server::{csrf::CSRF_FIELD, profiles::Bookmark},
+    templates::partials::{footer::footer, header::header},
+};
+use maud::{html, Markup};
+
+/// A function that handles the html code for the bookmarks page view in the search engine
+/// frontend, which lists the search results starred by the user along with the forms removing
+/// them and the link exporting them.
+///
+/// # Arguments
+///
+/// * `colorscheme` - It takes the colorscheme name as an argument.
+/// * `theme` - It takes the theme name as an argument.
+/// * `animation` - It takes the animation name as an argument.
+/// * `csrf_token` - It takes the token embedded into the forms removing the bookmarks as an
+///   argument.
+/// * `bookmarks` - It takes the bookmarks of the user, the most recently starred result first, as
+///   an argument.
+/// * `profile` - It takes a boolean which indicates whether the user has a server-side profile as
+///   an argument.
+///
+/// # Returns
+///
+/// It returns the compiled html markup code as a result.
+pub fn bookmarks(
+    colorscheme: &str,
+    theme: &str,
+    animation: &Option<String>,
+    csrf_token: &str,
+    bookmarks: &[Bookmark],
+    profile: bool,
+) -> Markup {
+    html!(
+        (header(colorscheme, theme, animation))
+        main class="bookmarks_container"{
+         h1{"Bookmarks"}
+         @if !profile {
+          p{
+           "The starred results are kept in a server-side profile, which can be created from the cookies tab of the "
+           a href="settings"{"settings page"}
+           "."
+          }
+         }
+         @if bookmarks.is_empty() {
+          p{"No result starred yet, star the results from the search page with the \u{2606} button."}
+         } @else {
+          a class="export_bookmarks" href="bookmarks?format=json" download{"Export as json"}
+          ul class="bookmark_entries"{
+           @for bookmark in bookmarks {
+            li{
+             .bookmark_result{
+              a href=(bookmark.url) rel="noreferrer"{(bookmark.title)}
+              small{(bookmark.url)}
+              @if !bookmark.description.is_empty() {
+               p{(bookmark.description)}
+              }
+             }
+             form action="bookmarks/remove" method="post"{
+              input type="hidden" name="url" value=(bookmark.url);
+              input type="hidden" name=(CSRF_FIELD) value=(csrf_token);
+              button type="submit" title="Remove the bookmark"{"Remove"}
+             }
+            }
+           }
+          }
+         }
+        }
+        (footer())
+    )
+}
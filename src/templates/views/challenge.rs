@@ -0,0 +1,45 @@
+//! A module that handles the view for the bot challenge page in the `websurfx` frontend.
+
+use crate::templates::partials::{footer::footer, header::header};
+use maud::{html, Markup};
+
+/// A function that handles the html code for the bot challenge page view in the search engine
+/// frontend, the challenge is solved by the `challenge.js` script which then redirects back to
+/// the requested page.
+///
+/// # Arguments
+///
+/// * `colorscheme` - It takes the colorscheme name as an argument.
+/// * `theme` - It takes the theme name as an argument.
+/// * `animation` - It takes the animation name as an argument.
+/// * `challenge` - It takes the signed challenge to be solved as an argument.
+/// * `difficulty` - It takes the number of leading zero bits of the hash of the solution as an
+///   argument.
+/// * `redirect` - It takes the path of the requested page as an argument.
+///
+/// # Returns
+///
+/// It returns the compiled html markup code as a result.
+pub fn challenge(
+    colorscheme: &str,
+    theme: &str,
+    animation: &Option<String>,
+    challenge: &str,
+    difficulty: u8,
+    redirect: &str,
+) -> Markup {
+    html!(
+        (header(colorscheme, theme, animation))
+        main class="error_container challenge_container" data-challenge=(challenge) data-difficulty=(difficulty) data-redirect=(redirect){
+         .error_content{
+          h1{"One moment"}
+          h2{"Checking that you are not a bot"}
+          p{"Your browser is solving a small challenge, you will be redirected to your search once it is solved."}
+          p class="challenge_progress"{}
+          noscript{p{"Javascript is needed to solve the challenge, please enable it and reload the page."}}
+         }
+        }
+        script src="/static/challenge.js"{}
+        (footer())
+    )
+}
@@ -0,0 +1,45 @@
+//! A module that handles the view for the error page in the `websurfx` frontend.
+
+use crate::templates::partials::{footer::footer, header::header};
+use maud::{html, Markup};
+
+/// A function that handles the html code for the error page view in the search engine frontend.
+///
+/// # Arguments
+///
+/// * `colorscheme` - It takes the colorscheme name as an argument.
+/// * `theme` - It takes the theme name as an argument.
+/// * `animation` - It takes the animation name as an argument.
+/// * `title` - It takes the title of the error as an argument.
+/// * `message` - It takes the message explaining the error as an argument.
+/// * `request_id` - It takes the id of the request (which can be reported to the operators of the
+///   instance to find the request in the logs) or `None` as an argument.
+///
+/// # Returns
+///
+/// It returns the compiled html markup code as a result.
+pub fn error(
+    colorscheme: &str,
+    theme: &str,
+    animation: &Option<String>,
+    title: &str,
+    message: &str,
+    request_id: Option<&str>,
+) -> Markup {
+    html!(
+        (header(colorscheme, theme, animation))
+        main class="error_container"{
+         img src="images/robot-404.svg" alt="Image of broken robot.";
+         .error_content{
+          h1{"Aw! snap"}
+          h2{(title)}
+          p{(message)}
+          @if let Some(request_id) = request_id {
+           p class="request_id"{"Request id: "code{(request_id)}}
+          }
+          p{"Go to "{a href="/"{"search page"}}}
+         }
+        }
+        (footer())
+    )
+}
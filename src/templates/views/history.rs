@@ -0,0 +1,93 @@
+//! A module that handles the view for the search history page in the `websurfx` frontend.
+
+use crate::{
+    query::bangs::encode_query,
+    server::{csrf::CSRF_FIELD, profiles::HistoryEntry},
+    templates::partials::{footer::footer, header::header},
+};
+use actix_web::cookie::time::OffsetDateTime;
+use maud::{html, Markup};
+
+/// A function that handles the html code for the search history page view in the search engine
+/// frontend, which lists the searches kept in the server-side profile of the user along with the
+/// form filtering them and the form wiping the history.
+///
+/// # Arguments
+///
+/// * `colorscheme` - It takes the colorscheme name as an argument.
+/// * `theme` - It takes the theme name as an argument.
+/// * `animation` - It takes the animation name as an argument.
+/// * `csrf_token` - It takes the token embedded into the form clearing the history as an
+///   argument.
+/// * `entries` - It takes the searches of the history matching the filter, the most recent
+///   search first, as an argument.
+/// * `filter` - It takes the text with which the searches are filtered as an argument.
+/// * `enabled` - It takes a boolean which indicates whether the user has enabled the search
+///   history as an argument.
+///
+/// # Returns
+///
+/// It returns the compiled html markup code as a result.
+pub fn history(
+    colorscheme: &str,
+    theme: &str,
+    animation: &Option<String>,
+    csrf_token: &str,
+    entries: &[HistoryEntry],
+    filter: &str,
+    enabled: bool,
+) -> Markup {
+    html!(
+        (header(colorscheme, theme, animation))
+        main class="history_container"{
+         h1{"Search history"}
+         @if !enabled {
+          p class="history_disabled"{
+           "Your searches are not kept, the search history can be enabled from the cookies tab of the "
+           a href="settings"{"settings page"}
+           " once your settings are saved in a server-side profile."
+          }
+         }
+         form class="history_search" action="history" method="get"{
+          input type="search" name="q" value=(filter) placeholder="Search the history";
+          button type="submit"{"Search"}
+         }
+         @if entries.is_empty() {
+          p{"No search found."}
+         } @else {
+          ul class="history_entries"{
+           @for entry in entries {
+            li{
+             a href=(format!("search?q={}", encode_query(&entry.query))){(entry.query)}
+             time{(format_time(entry.at))}
+            }
+           }
+          }
+         }
+         form class="clear_history" action="history/clear" method="post"{
+          input type="hidden" name=(CSRF_FIELD) value=(csrf_token);
+          button type="submit"{"Clear the history"}
+         }
+        }
+        (footer())
+    )
+}
+
+/// A helper function which formats the time of a search for the search history page.
+///
+/// # Arguments
+///
+/// * `at` - It takes the time (in seconds since the unix epoch) of the search as an argument.
+fn format_time(at: u64) -> String {
+    match OffsetDateTime::from_unix_timestamp(at as i64) {
+        Ok(time) => format!(
+            "{}-{:02}-{:02} {:02}:{:02} UTC",
+            time.year(),
+            u8::from(time.month()),
+            time.day(),
+            time.hour(),
+            time.minute()
+        ),
+        Err(_) => String::new(),
+    }
+}
@@ -2,7 +2,12 @@
 //! `websurfx` frontend.
 
 pub mod about;
+pub mod bookmarks;
+pub mod challenge;
+pub mod error;
+pub mod history;
 pub mod index;
 pub mod not_found;
 pub mod search;
 pub mod settings;
+pub mod status;
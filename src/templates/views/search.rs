@@ -3,48 +3,126 @@
 use maud::{html, Markup, PreEscaped};
 
 use crate::{
-    models::aggregation_models::SearchResults,
-    templates::partials::{footer::footer, header::header, search_bar::search_bar},
+    config::parser::Config,
+    models::{aggregation_models::SearchResults, engine_models::TimeRange},
+    query::bangs::encode_query,
+    templates::partials::{
+        footer::footer, header::header, search_bar::search_bar, search_result::search_result,
+    },
 };
 
 /// A function that handles the html code for the search page view in the search engine frontend.
 ///
 /// # Arguments
 ///
-/// * `colorscheme` - It takes the colorscheme name as an argument.
-/// * `theme` - It takes the theme name as an argument.
+/// * `config` - It takes the parsed config struct (for the colorscheme, the theme and the
+///   animation names and whether the favicons of the results are shown) as an argument.
 /// * `query` - It takes the current search query provided by the user as an argument.
+/// * `time_range` - It takes the time range to which the results are restricted as an argument.
+/// * `refine` - It takes the additional search terms used to narrow the results as an argument.
+/// * `preserved_params` - It takes the search parameters of the current request which should be
+///   kept in the links to the other time ranges and in the refine form as an argument.
+/// * `stream_url` - It takes the optional url from which the search results are streamed to the
+///   page as the upstream search engines answer (instead of being rendered with the page) as an
+///   argument.
 /// * `search_results` - It takes the aggregated search results as an argument.
+/// * `csrf_token` - It takes the CSRF token submitted with the search form as an argument.
 ///
 /// # Returns
 ///
 /// It returns the compiled html markup code as a result.
 pub fn search(
-    colorscheme: &str,
-    theme: &str,
-    animation: &Option<String>,
+    config: &Config,
     query: &str,
+    time_range: Option<TimeRange>,
+    refine: Option<&str>,
+    preserved_params: &[(&str, String)],
+    stream_url: Option<&str>,
     search_results: &SearchResults,
+    csrf_token: &str,
 ) -> Markup {
+    let encoded_query = encode_query(query);
+    let time_range_params: String = preserved_params
+        .iter()
+        .map(|(name, value)| (*name, value.as_str()))
+        .chain(refine.map(|refine| ("refine", refine)))
+        .map(|(name, value)| format!("&{name}={}", encode_query(value)))
+        .collect();
+    let page = search_results.page.max(1);
+    let page_url = |page: u32| {
+        format!(
+            "search?q={encoded_query}&page={page}{}{time_range_params}{}",
+            time_range
+                .map(|time_range| format!("&time_range={}", time_range.name()))
+                .unwrap_or_default(),
+            if stream_url.is_some() {
+                "&stream=true"
+            } else {
+                ""
+            }
+        )
+    };
     html!(
-        (header(colorscheme, theme, animation))
+        (header(&config.style.colorscheme, &config.style.theme, &config.style.animation))
         main class="results"{
-           (search_bar(&search_results.engine_errors_info, search_results.safe_search_level, query))
-           .results_aggregated{
+           (search_bar(&search_results.engine_errors_info, search_results.safe_search_level, query, csrf_token))
+           form class="refine_results" action="search" method="get"{
+              input type="hidden" name="q" value=(query);
+              @for (name, value) in preserved_params {
+                 input type="hidden" name=(name) value=(value);
+              }
+              @if let Some(time_range) = time_range {
+                 input type="hidden" name="time_range" value=(time_range.name());
+              }
+              input type="search" name="refine" placeholder="Search within results" value=(refine.unwrap_or_default());
+           }
+           .time_range_filters{
+              @if time_range.is_none() {
+                 a class="active" href=(format!("search?q={encoded_query}&time_range={time_range_params}")){"Any time"}
+              }
+              @else {
+                 a href=(format!("search?q={encoded_query}&time_range={time_range_params}")){"Any time"}
+              }
+              @for range in TimeRange::ALL {
+                 @if time_range == Some(range) {
+                    a class="active" href=(format!("search?q={encoded_query}&time_range={}{time_range_params}", range.name())){(range.display_name())}
+                 }
+                 @else {
+                    a href=(format!("search?q={encoded_query}&time_range={}{time_range_params}", range.name())){(range.display_name())}
+                 }
+              }
+           }
+           @if !search_results.results.is_empty() {
+              .search_stats{
+                 (format!(
+                    "{} {} from {} {} in {} ms",
+                    search_results.results.len(),
+                    if search_results.results.len() == 1 { "result" } else { "results" },
+                    search_results.contributing_engines(),
+                    if search_results.contributing_engines() == 1 { "engine" } else { "engines" },
+                    search_results.response_time
+                 ))
+              }
+           }
+           .engines_unavailable hidden[search_results.unavailable_engines().is_empty()]{
+              (format!("Results from {} unavailable", search_results.unavailable_engines().join(", ")))
+           }
+           .results_aggregated data-stream=[stream_url]{
+              @if let Some(corrected_query) = &search_results.corrected_query {
+                 .corrected_query{
+                    "Did you mean "
+                    a href=(format!("search?q={}", encode_query(corrected_query))){(corrected_query)}
+                    "?"
+                 }
+              }
               @if !search_results.results.is_empty() {
                   @for result in search_results.results.iter(){
-                      .result {
-                         h1{a href=(result.url){(PreEscaped(&result.title))}}
-                         small{(result.url)}
-                         p{(PreEscaped(&result.description))}
-                         .upstream_engines{
-                            @for name in result.clone().engine{
-                               span{(name)}
-                            }
-                         }
-                      }
+                      (search_result(config, result))
                   }
               }
+              @else if stream_url.is_some() {
+                 .stream_loading{"Fetching the results from the upstream search engines..."}
+              }
               @else if search_results.disallowed{
                  .result_disallowed{
                     .description{
@@ -107,17 +185,34 @@ pub fn search(
                  }
               }
             }
+            @if !search_results.related_queries.is_empty() {
+               .related_queries{
+                  h2{"Related searches"}
+                  ul{
+                     @for related_query in &search_results.related_queries {
+                        li{a href=(format!("search?q={}", encode_query(related_query))){(related_query)}}
+                     }
+                  }
+               }
+            }
             .page_navigation {
-               button type="button" onclick="navigate_backward()"{
-                   (PreEscaped("&#8592;")) "previous"
+               @if page > 1 {
+                  a class="previous" href=(page_url(page - 1)){
+                      (PreEscaped("&#8592;")) "previous"
+                  }
+               }
+               @if search_results.has_next_page {
+                  a class="next" href=(page_url(page + 1)){"next" (PreEscaped("&#8594;"))}
                }
-               button type="button" onclick="navigate_forward()"{"next" (PreEscaped("&#8594;"))}
             }
         }
         script src="static/index.js"{}
         script src="static/search_area_options.js"{}
-        script src="static/pagination.js"{}
         script src="static/error_box.js"{}
+        script src="static/bookmarks.js"{}
+        @if stream_url.is_some() {
+           script src="static/stream.js"{}
+        }
         (footer())
     )
 }
@@ -4,6 +4,9 @@ use std::collections::HashMap;
 
 use maud::{html, Markup};
 
+use crate::config::parser::Config;
+use crate::models::parser_models::LockedSetting;
+use crate::server::{csrf::CSRF_FIELD, profiles::ProfileStatus};
 use crate::templates::partials::{
     footer::footer,
     header::header,
@@ -16,46 +19,57 @@ use crate::templates::partials::{
 ///
 /// # Arguments
 ///
-/// * `safe_search_level` - It takes the safe search level as an argument.
-/// * `colorscheme` - It takes the colorscheme name as an argument.
-/// * `theme` - It takes the theme name as an argument.
-/// * `animation` - It takes the animation name as an argument.
+/// * `csrf_token` - It takes the CSRF token submitted with the settings form as an argument.
+/// * `config` - It takes the parsed config struct (for the safe search level, the style and the
+///   available styles) as an argument.
 /// * `engine_names` - It takes a list of engine names as an argument.
+/// * `saved` - It takes whether the settings were just saved as an argument.
+/// * `profile` - It takes the server-side profile of the user as an argument.
+/// * `preferences_url` - It takes the search url carrying the saved settings of the user as an
+///   argument.
 ///
 /// # Error
 ///
 /// This function returns a compiled html markup code on success otherwise returns a standard error
 /// message.
 pub fn settings(
-    safe_search_level: u8,
-    colorscheme: &str,
-    theme: &str,
-    animation: &Option<String>,
+    csrf_token: &str,
+    config: &Config,
     engine_names: &HashMap<String, bool>,
+    saved: bool,
+    profile: &ProfileStatus<'_>,
+    preferences_url: Option<&str>,
 ) -> Result<Markup, Box<dyn std::error::Error>> {
+    // The settings of the profile are shown on the page as they are not stored in the cookie.
+    let profile_settings = match profile {
+        ProfileStatus::Saved { settings, .. } => Some(*settings),
+        _ => None,
+    };
     Ok(html!(
-        (header(colorscheme, theme, animation))
+        (header(&config.style.colorscheme, &config.style.theme, &config.style.animation))
         main class="settings"{
            h1{"Settings"}
            hr;
            .settings_container{
               .sidebar{
-                  div class="btn active" onclick="setActiveTab(this)"{"general"}
-                  .btn onclick="setActiveTab(this)"{"user interface"}
-                  .btn onclick="setActiveTab(this)"{"engines"}
-                  .btn onclick="setActiveTab(this)"{"cookies"}
+                  div class="btn active"{"general"}
+                  .btn{"user interface"}
+                  .btn{"engines"}
+                  .btn{"cookies"}
               }
-              .main_container{
-                  (general(safe_search_level))
-                  (user_interface(theme, colorscheme, animation)?)
-                  (engines(engine_names))
-                  (cookies())
-                  p class="message"{}
-                  button type="submit" onclick="setClientSettings()"{"Save"}
+              form class="main_container" method="post" action="/settings" data-settings=[profile_settings]{
+                  (general(config.safe_search, &config.locked_settings))
+                  (user_interface(&config.style, &config.styles))
+                  (engines(engine_names, config.is_locked(LockedSetting::Engines)))
+                  (cookies(profile, preferences_url, config.profiles.history))
+                  input type="hidden" name=(CSRF_FIELD) value=(csrf_token);
+                  p class="message"{
+                      @if saved {"✅ The settings have been saved successfully!!"}
+                  }
+                  button type="submit" class="save"{"Save"}
               }
            }
         }
-        script src="static/settings.js"{}
         script src="static/cookies.js"{}
         (footer())
     ))
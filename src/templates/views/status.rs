@@ -0,0 +1,124 @@
+//! A module that handles the view for the engine status page in the `websurfx` frontend.
+
+use crate::{
+    server::{routes::status::EngineHealth, usage_stats::DailyUsage},
+    templates::partials::{footer::footer, header::header},
+};
+use maud::{html, Markup};
+
+/// A function that handles the html code for the engine status page view in the search engine
+/// frontend, which shows the health of each upstream search engine computed from its recent
+/// responses, along with the anonymized usage statistics of the instance.
+///
+/// # Arguments
+///
+/// * `colorscheme` - It takes the colorscheme name as an argument.
+/// * `theme` - It takes the theme name as an argument.
+/// * `animation` - It takes the animation name as an argument.
+/// * `engines` - It takes the health of the upstream search engines along with their statistics
+///   and their last health check as an argument.
+/// * `usage` - It takes the usage statistics of the instance per day (or `None` if they should
+///   not be shown) as an argument.
+///
+/// # Returns
+///
+/// It returns the compiled html markup code as a result.
+pub fn status(
+    colorscheme: &str,
+    theme: &str,
+    animation: &Option<String>,
+    engines: &[EngineHealth<'_>],
+    usage: Option<&[DailyUsage]>,
+) -> Markup {
+    html!(
+        (header(colorscheme, theme, animation))
+        main class="status_container"{
+         h1{"Engine status"}
+         p{"The health of the upstream search engines computed from their recent responses on this instance."}
+         table class="engine_status"{
+          thead{
+           tr{
+            th{"Engine"}
+            th{"Status"}
+            th{"Success rate"}
+            th{"Latency (median / p95)"}
+            th{"Recent errors"}
+            th{"Last check"}
+           }
+          }
+          tbody{
+           @for engine in engines {
+            tr{
+             td{(engine.engine)}
+             td{
+              span class={"engine_state " (engine.status.name())}{(engine.status.name())}
+              @if let Some(blocked_for) = engine.blocked_for {
+               span class="engine_error"{"for "(blocked_for)" s"}
+              }
+             }
+             @match &engine.stats {
+              Some(stats) => {
+               td{(format!("{:.0}%", stats.success_rate * 100.0))" of "(stats.requests)}
+               td{(stats.p50_latency_ms)" ms / "(stats.p95_latency_ms)" ms"}
+               td{
+                @if stats.errors.is_empty() {"None"}
+                @for (error, count) in &stats.errors {
+                 span class="engine_error"{(error)" ("(count)")"}
+                }
+               }
+              }
+              None => {
+               td{"-"}
+               td{"-"}
+               td{"-"}
+              }
+             }
+             td{
+              @match &engine.health_check {
+               Some(check) if check.passed() => {(check.results)" results in "(check.latency_ms)" ms"}
+               Some(check) => {span class="engine_error"{(check.error.as_deref().unwrap_or_default())}}
+               None => {"-"}
+              }
+             }
+            }
+           }
+          }
+         }
+         @if let Some(usage) = usage {
+          h2{"Usage"}
+          p{"The number of searches per day on this instance, the search queries and the ip addresses of the visitors are never stored."}
+          table class="usage_stats"{
+           thead{
+            tr{
+             th{"Day"}
+             th{"Searches"}
+             th{"Visitors"}
+             th{"Categories"}
+             th{"Engines"}
+            }
+           }
+           tbody{
+            @for day in usage {
+             tr{
+              td{(day.day)}
+              td{(day.searches)}
+              td{(day.visitors)}
+              td{
+               @for (category, searches) in &day.categories {
+                span class="usage_entry"{(category)" ("(searches)")"}
+               }
+              }
+              td{
+               @for (engine, searches) in &day.engines {
+                span class="usage_entry"{(engine)" ("(searches)")"}
+               }
+              }
+             }
+            }
+           }
+          }
+         }
+        }
+        (footer())
+    )
+}
@@ -22,16 +22,84 @@ async fn test_index() {
     let client = reqwest::Client::new();
     let res = client.get(address).send().await.unwrap();
     assert_eq!(res.status(), 200);
+    let csrf_token = csrf_cookie(&res).expect("The index page should set the csrf_token cookie");
 
     let config = Config::parse(true).unwrap();
     let template = views::index::index(
         &config.style.colorscheme,
         &config.style.theme,
         &config.style.animation,
+        &csrf_token,
     )
     .0;
     assert_eq!(res.text().await.unwrap(), template);
 }
 
+// Returns the token of the `csrf_token` cookie set by the response
+fn csrf_cookie(res: &reqwest::Response) -> Option<String> {
+    res.headers()
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .filter_map(|cookie| cookie.to_str().ok())
+        .find_map(|cookie| cookie.strip_prefix("csrf_token="))
+        .and_then(|cookie| cookie.split(';').next())
+        .map(str::to_owned)
+}
+
+#[tokio::test]
+async fn test_search_form_from_index() {
+    let address = spawn_app().await;
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+
+    // A client without any cookie gets the token along with the index page.
+    let res = client.get(&address).send().await.unwrap();
+    let csrf_token = csrf_cookie(&res).expect("The index page should set the csrf_token cookie");
+    let page = res.text().await.unwrap();
+    assert!(page.contains(r#"action="search" method="post""#));
+    assert!(page.contains(&format!(r#"name="csrf_token" value="{csrf_token}""#)));
+
+    let search = |token: Option<&str>| {
+        let mut request = client
+            .post(format!("{address}search"))
+            .form(&[("q", ""), ("csrf_token", token.unwrap_or_default())]);
+        if let Some(token) = token {
+            request = request.header(reqwest::header::COOKIE, format!("csrf_token={token}"));
+        }
+        request.send()
+    };
+
+    // The form is refused without the cookie and accepted with the token of the index page (the
+    // empty search is redirected back to the index page).
+    assert_eq!(search(None).await.unwrap().status(), 403);
+    assert_eq!(search(Some(&csrf_token)).await.unwrap().status(), 307);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_index_on_unix_socket() {
+    use std::os::unix::net::UnixListener;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let directory = tempfile::tempdir().unwrap();
+    let socket_path = directory.path().join("websurfx.sock");
+    let listener = UnixListener::bind(&socket_path).expect("Failed to bind the unix socket");
+    let config = Config::parse(true).unwrap();
+    let cache = websurfx::cache::cacher::create_cache(&config).await;
+    let server = run(listener, config, cache).expect("Failed to bind address");
+    tokio::spawn(server);
+
+    let mut stream = tokio::net::UnixStream::connect(&socket_path).await.unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+}
+
 // TODO: Write tests for testing parameters for search function that if provided with something
 // other than u32 like alphabets and special characters than it should panic
@@ -1,32 +1,118 @@
 -- ### General ###
 logging = true -- an option to enable or disable logs.
 debug = false -- an option to enable or disable debug mode.
+log_level = nil -- the level of the logs (like `"warn"`) or the filter directives per module (like `"websurfx=debug,actix_web=warn"`) or `nil` to derive it from the `logging` and `debug` options (the `RUST_LOG` environment variable takes precedence).
+log_format = "text" -- the format of the logs, `"text"` for human readable lines or `"json"` for one json object per line.
+otlp = {
+	endpoint = nil, -- The OTLP/HTTP endpoint of the OpenTelemetry collector (like `"http://127.0.0.1:4318"`) to which the spans of the requests are exported or `nil` to not export them (needs the `otlp` feature).
+	service_name = "websurfx", -- The name of the service under which the spans are exported.
+	sample_ratio = 1, -- The ratio (between `0` and `1`) of the requests whose spans are exported.
+}
 threads = 10 -- the amount of threads that the app will use to run (the value should be greater than 0).
 
 -- ### Server ###
 port = "8080" -- port on which server should be launched
 binding_ip = "127.0.0.1" --ip address on the which server should be launched.
+unix_socket = nil -- the path to the unix socket (like `"/run/websurfx/websurfx.sock"`) on which the server should be launched instead of the `binding_ip` and the `port` or `nil` to use them.
 production_use = false -- whether to use production mode or not (in other words this option should be used if it is to be used to host it on the server to provide a service to a large number of users (more than one))
 -- if production_use is set to true
 -- There will be a random delay before sending the request to the search engines, this is to prevent DDoSing the upstream search engines from a large number of simultaneous requests.
 request_timeout = 30 -- timeout for the search requests sent to the upstream search engines to be fetched (value in seconds).
+shutdown_timeout = 30 -- the time given to the in-flight requests to complete when the server receives a `SIGTERM` or `SIGINT` signal (value in seconds).
+max_connections = 25000 -- the number of the connections each thread accepts at once (the value should be greater than 0).
+keep_alive = 5 -- the time during which the idle connections of the clients are kept open (value in seconds, `0` to close the connections after each request).
+client_request_timeout = 5 -- the time within which the clients should send the headers of their requests (value in seconds, `0` to never time out).
+connect_timeout = nil -- the time within which the connections to the upstream search engines should be established (value in seconds) or `nil` to only use the `request_timeout`.
+proxy = nil -- the http(s) or socks5 proxy (like `"socks5h://127.0.0.1:9050"`) through which the requests to the upstream search engines are sent or `nil` to send them directly.
+dns_over_https = nil -- the DNS-over-HTTPS server (like `"https://cloudflare-dns.com/dns-query"`) through which the domain names of the upstream search engines are resolved or `nil` to use the resolver of the host.
+user_agents = {} -- the user agents picked at random for each request to the upstream search engines (they are generated when it is empty).
+randomize_headers = false -- randomize the `Accept` and `Accept-Language` headers and the order of the headers sent to the upstream search engines.
+metrics = false -- export the metrics of the instance (the requests per route, the aggregation durations, the cache hits and the responses of the upstream search engines) in the Prometheus format on the `/metrics` route.
+secret_key = nil -- the secret from which the key signing the urls of the proxied images is derived (use `secret_key_file` to read it from a file, a random key is generated on startup when it is `nil`).
 rate_limiter = {
 	number_of_requests = 20, -- The number of request that are allowed within a provided time limit.
 	time_limit = 3, -- The time limit in which the quantity of requests that should be accepted.
 }
+search_rate_limiter = {
+	burst = 20, -- The number of searches a client can send at once before it is rate limited.
+	per_minute = 30, -- The number of searches per minute a client can send in a sustained way.
+	redis = false, -- Track the rate of the clients in the redis server, so that it is shared by the replicas of the instance (needs the `redis-cache` feature).
+}
+bot_challenge = {
+	enabled = false, -- Make the clients looking like bots (searching in bursts or without the headers sent by the browsers) solve a proof-of-work challenge before searching.
+	difficulty = 16, -- The number of leading zero bits of the hash of a solved challenge (each additional bit doubles the work of the clients).
+	burst = 10, -- The number of searches a client can send at once before it is challenged.
+	per_minute = 10, -- The number of searches per minute a client can send in a sustained way before it is challenged.
+	validity = 86400, -- The number of seconds during which a client which solved a challenge is not challenged again.
+}
+trusted_proxies = {} -- the ip addresses or networks (like `"10.0.0.0/8"`) of the reverse proxies from which the client addresses forwarded in the `Forwarded` or `X-Forwarded-For` headers are trusted for the rate limiting.
 -- Set whether the server will use an adaptive/dynamic HTTPS window size, see https://httpwg.org/specs/rfc9113.html#fc-principles
 https_adaptive_window_size = false
+request_limits = {
+	max_query_length = 512, -- The maximum number of characters allowed in the search query.
+	max_page = 100, -- The maximum page number which can be requested.
+	max_results_per_page = 100, -- The maximum number of results displayed on a single page.
+	max_query_string_length = 4096, -- The maximum length (in bytes) of the query string of a request.
+	max_cookie_size = 8192, -- The maximum size (in bytes) of the cookies sent with a request.
+	max_body_size = 65536, -- The maximum size (in bytes) of the body of a request.
+}
+admin = {
+	token = nil, -- The token which authenticates the requests to the admin api (the admin api is disabled when it is `nil`, use `token_file` to read it from a file).
+	state_file = nil, -- The file in which the changes made through the admin api are persisted (`nil` to use `$XDG_STATE_HOME/websurfx/state.json`).
+}
+api = {
+	keys = {}, -- The keys which give access to the json api under `/api/v1` (like `{ { name = "alice", key = "<secret>", burst = 10, per_minute = 60 } }`, use `key_file` to read a key from a file, the json api is disabled when it is empty).
+	redis = false, -- Track the quotas and the usage counters of the keys in the redis server, so that they are shared by the replicas of the instance (needs the `redis-cache` feature).
+}
+tls = {
+	certificate = nil, -- The PEM file containing the certificate chain with which the instance is served over https (`nil` to serve it over http).
+	private_key = nil, -- The PEM file containing the private key of the certificate.
+}
+tor = {
+	enabled = false, -- Route the requests to the upstream search engines through Tor (the engines with their own `proxy` option are not routed through Tor).
+	socks_url = "socks5h://127.0.0.1:9050", -- The url of the socks port of the Tor daemon.
+	isolate_streams = true, -- Send the concurrent searches through separate Tor circuits.
+	control_address = nil, -- The address of the control port (like `"127.0.0.1:9051"`) used to request new circuits when the engines block the requests, or `nil`.
+	control_password = nil, -- The password of the control port (use `control_password_file` to read it from a file).
+	newnym_after_blocks = 3, -- The number of consecutive blocked requests after which new circuits are requested (`0` to never request them).
+}
+privacy = {
+	no_query_logging = false, -- Replace the search queries with their hashes wherever they would be written to the logs.
+}
+usage_stats = {
+	enabled = false, -- Count the searches per day along with the search categories and the upstream search engines used (the queries and the ip addresses are never stored), shown through the admin api.
+	public = false, -- Show the usage statistics on the public engine status page.
+	redis = false, -- Track the usage statistics in the redis server, so that they are shared by the replicas of the instance (needs the `redis-cache` feature).
+	retention_days = 30, -- The number of days for which the usage statistics are kept.
+}
+profiles = {
+	enabled = false, -- Allow the users to save their settings in a server-side profile restored on other browsers with a restore code.
+	redis = false, -- Store the profiles in the redis server, so that they are shared by the replicas of the instance (needs the `redis-cache` feature).
+	expiry_days = 365, -- The number of days after which an unused profile is deleted.
+	max_size = 65536, -- The maximum size (in bytes) of the settings stored in a profile.
+	history = false, -- Allow the users of the profiles to opt into keeping their search history in their profile.
+	max_history = 100, -- The maximum number of searches kept in the search history of a profile.
+}
+security_headers = {
+	content_security_policy = "default-src 'self'; script-src 'self' 'nonce-{nonce}'; style-src 'self' 'nonce-{nonce}'; img-src 'self' data:; object-src 'none'; base-uri 'self'; form-action 'self'; frame-ancestors 'none'", -- The `Content-Security-Policy` header (`{nonce}` is replaced with the nonce generated for each response, `""` to not send it).
+	referrer_policy = "no-referrer", -- The `Referrer-Policy` header (`""` to not send it).
+	permissions_policy = "camera=(), geolocation=(), microphone=(), payment=(), usb=()", -- The `Permissions-Policy` header (`""` to not send it).
+	hsts_max_age = nil, -- The max age of the `Strict-Transport-Security` header (value in seconds, like `31536000`) or `nil` to not send it (it should only be set when the instance is served over https).
+}
 
 -- ### Search ###
 -- Filter results based on different levels. The levels provided are:
 -- {{
--- 0 - None
--- 1 - Low
--- 2 - Moderate
--- 3 - High
--- 4 - Aggressive
+-- 0 - Off ("off")
+-- 1 - Moderate ("moderate")
+-- 2 - Strict ("strict")
+-- 3 - Strict with the filter lists
+-- 4 - Strict with the filter lists and blocking of disallowed queries
 -- }}
-safe_search = 2
+safe_search = "moderate"
+results_per_page = 20 -- The default number of results shown on each page of the search page (up to `max_results_per_page`), which the users can change on the settings page.
+locked_settings = {} -- The settings which the users cannot override with their cookie, the search parameters or the settings page (`safe_search`, `engines`, `language`, `region` and `time_range` can be locked), like `{ "safe_search" }` on a family-friendly instance.
+disabled_categories = {} -- The search categories (like `images` or `videos`) which cannot be searched on the instance.
 
 -- ### Website ###
 -- The different colorschemes provided are:
@@ -55,11 +141,26 @@ theme = "simple" -- the theme name which should be used for the website
 -- simple-frosted-glow
 -- }}
 animation = "simple-frosted-glow" -- the animation name which should be used with the theme or `nil` if you don't want any animations.
+favicon_service = "https://icons.duckduckgo.com/ip3/{domain}.ico" -- the service from which the favicons of the results are fetched through the instance (`{domain}` is replaced with the domain) or `nil` to hide the favicons.
+anonymous_redirects = false -- route the links of the results through the `/r` redirect route, so that the sites of the results never learn the search query from the `Referer` header.
+onion_address = nil -- the url of the Tor onion service of the instance (like `"http://<address>.onion"`) advertised to the Tor Browser with the `Onion-Location` header or `nil` if there is none.
 
 -- ### Caching ###
-redis_url = "redis://127.0.0.1:8082" -- redis connection url address on which the client should connect on.
+redis_url = "redis://127.0.0.1:8082" -- redis connection url address on which the client should connect on (use `redis_url_file` to read it from a file).
 cache_expiry_time = 600 -- This option takes the expiry time of the search results (value in seconds and the value should be greater than or equal to 60 seconds).
 -- ### Search Engines ###
+-- Each engine can either be enabled/disabled with a boolean or configured with a table of engine
+-- specific options, for example:
+-- Bing = {
+--     enabled = true, -- whether the engine is enabled by default.
+--     weight = 1.0, -- the weight of the results from the engine when ranking the results.
+--     timeout = 10, -- the request timeout for the engine (overrides `request_timeout`).
+--     connect_timeout = 3, -- the connect timeout for the engine (overrides `connect_timeout`).
+--     requests_per_second = 2.0, -- the rate of the requests sent to the engine (the requests exceeding it are queued for up to a second or the engine is skipped).
+--     region = "us", -- the default region of the results as a country code.
+--     api_key = nil, -- the key of the official search api of the engine, only supported by Brave (use `api_key_file` to read it from a file).
+--     proxy = "http://127.0.0.1:8118", -- the http(s) or socks5 proxy through which the requests to the engine are sent (overrides `proxy`).
+-- },
 upstream_search_engines = {
     DuckDuckGo = true,
     Searx = false,
@@ -69,3 +170,73 @@ upstream_search_engines = {
     Mojeek = false,
     Bing = false,
 } -- select the upstream search engines from which the results should be fetched.
+rank_constant = 60 -- the rank constant of the reciprocal rank fusion used to rank the results (the higher it is the less the top results of every engine are favoured).
+aggregation_deadline = nil -- the time budget of the aggregation (value in milliseconds) after which the results of the upstream search engines which have answered so far are returned or `nil` to wait for all the engines.
+circuit_breaker_threshold = 5 -- the number of the consecutive failures (or timeouts) of an upstream search engine after which the engine is skipped for the cool-down period (`0` disables the circuit breakers).
+circuit_breaker_cooldown = 30 -- the time (value in seconds) for which a failing upstream search engine is skipped before a probe request is sent to it.
+engine_retries = 1 -- the number of the times a request to an upstream search engine is retried after a transient error (a `429` or a `5xx` response or a failed connection), within the aggregation deadline (`0` disables the retries).
+retry_backoff = 200 -- the time (value in milliseconds) to wait before the first retry, which doubles with every retry.
+retry_jitter = 100 -- the largest random time (value in milliseconds) added to the wait before a retry.
+block_cooldown = 60 -- the time (value in seconds) for which an upstream search engine which blocked the instance (with a `403` or a `429` response, a CAPTCHA or a block page) is skipped, which doubles every time the engine blocks the instance again (`0` disables the backoff).
+max_block_cooldown = 3600 -- the longest time (value in seconds) for which a blocked upstream search engine is skipped.
+max_engines = nil -- the number of the upstream search engines to which each search is sent, picked from the selected engines based on their recent success rate and latency (`nil` to send each search to all the selected engines).
+exploration_rate = 0.1 -- the probability (between 0 and 1) with which an engine which is not among the best performing ones is picked, so that the statistics of all the engines stay fresh.
+health_monitor = {
+	enabled = false, -- Periodically send a canary query to each enabled upstream search engine, so that the engines which stopped answering (or whose markup changed) are shown on the engine status page before the users notice.
+	interval = 600, -- The interval (value in seconds, at least 60) at which the canary queries are sent.
+	query = "wikipedia", -- The canary query, which should be answered with results by every engine.
+}
+reverse_image_engines = { "TinEye", "Yandex", "BingVisual" } -- the upstream search engines to which the reverse image searches (`/search/image?url=`) are sent (`TinEye`, `Yandex` and `BingVisual` are supported).
+engine_scripts = {} -- the paths of the lua scripts defining additional upstream search engines (see the docs).
+engine_definitions = nil -- the directory of the TOML and JSON files defining additional upstream search engines (see the docs).
+-- The factors by which the scores of the results from the matching domains are multiplied
+-- to boost (greater than 1) or to penalize (less than 1) them. For example: `["*.org"] = 1.2`.
+domain_weights = {
+    -- ["*.org"] = 1.2,
+    -- ["paywalled-news.com"] = 0.5,
+}
+
+-- The upstream search engines which should be used by default for each search category
+-- (general/images/videos/news/...), the engines enabled above are used for the categories which
+-- are not listed here.
+category_engines = {
+    -- images = { "Bing", "DuckDuckGo" },
+}
+
+-- ### Bangs ###
+-- Custom bangs which redirect the user to the provided url when the search query contains
+-- `!<bang>`, `{query}` in the url is replaced with the rest of the search query. These take
+-- precedence over the built-in bangs.
+bangs = {
+    -- w = "https://en.wikipedia.org/w/index.php?search={query}",
+}
+
+-- ### Domain Filters ###
+-- The patterns of the domains whose results should be dropped and to which the results should be
+-- restricted (if any). `*` matches any characters and the subdomains of the domains are matched
+-- too, the patterns wrapped in slashes (like `/^spam[0-9]+\.net$/`) are regexes.
+blocked_domains = {} -- for example: `{ "pinterest.com", "*.content-farm.*" }`.
+allowed_domains = {} -- the results are not restricted if it is empty.
+-- The urls of the remote blocklists in the uBlacklist format whose matching results should be
+-- dropped, they are fetched in the background and refreshed periodically.
+blocklist_subscriptions = {} -- for example: `{ "https://example.com/uBlacklist.txt" }`.
+blocklist_refresh_interval = 86400 -- the interval after which the blocklists are refreshed (value in seconds).
+
+-- ### Url Rewrites ###
+-- The hosts of the sites along with the urls of the privacy respecting frontends to which the
+-- links of the search results pointing to these sites (or to their subdomains) are rewritten.
+url_rewrites = {
+    -- ["youtube.com"] = "https://yewtu.be",
+    -- ["reddit.com"] = "https://safereddit.com",
+    -- ["twitter.com"] = "https://nitter.net",
+    -- ["medium.com"] = "https://scribe.rip",
+}
+-- The AMP urls of the results are rewritten to the canonical urls of the pages, these are
+-- resolved with `HEAD` requests when it is enabled and guessed otherwise.
+resolve_amp_urls = false
+
+-- ### Result Filters ###
+-- The built-in filters run over the results in the provided order (`trackers`, `domains`,
+-- `blocklists` and `rewrites` are supported), the filters left out are not run. The safe
+-- search is always enforced before them.
+result_filters = { "trackers", "domains", "blocklists", "rewrites" }